@@ -0,0 +1,88 @@
+//! Allocations and timings of parameter calls with their names interned
+//!
+//! Only the DLL backend passes names to MEDAQLib, so the calls go to a stand-in
+//! compiled from `benches/medaqlib/fake.c` and loaded like MEDAQLib. The
+//! allocations of all threads per call are printed before the timings, for names
+//! the crate keeps converted and for names converted on every call once it keeps
+//! no more.
+
+#[cfg(all(unix, not(feature = "link")))]
+mod common;
+
+#[cfg(all(unix, not(feature = "link")))]
+mod fake {
+    use std::{hint::black_box, path::PathBuf};
+
+    use criterion::Criterion;
+    use medaqlib::{Interface, Sensor, SensorBuilder, ME_SENSOR};
+
+    /// Names the crate keeps converted at most
+    const MAX_NAMES: usize = 1024;
+
+    /// Build the stand-in for MEDAQLib as a shared library
+    fn library() -> PathBuf {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let library = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("libMEDAQLib-fake.so");
+        let target = env!("MEDAQLIB_TARGET");
+        let compiler = cc::Build::new()
+            .target(target)
+            .host(target)
+            .opt_level(2)
+            .cargo_metadata(false)
+            .get_compiler();
+        let status = compiler
+            .to_command()
+            .args(["-shared", "-fPIC", "-o"])
+            .arg(&library)
+            .arg(root.join("benches/medaqlib/fake.c"))
+            .status()
+            .unwrap();
+        assert!(status.success(), "compiling benches/medaqlib/fake.c failed");
+        library
+    }
+
+    /// A parameter, a double answer and a command named `name`
+    fn calls(sensor: &Sensor, name: &str) {
+        sensor.set_parameter_int(name, 1).unwrap();
+        black_box(sensor.get_parameter_double(name).unwrap());
+        sensor.execute_command(name).unwrap();
+    }
+
+    pub fn parameters(c: &mut Criterion) {
+        medaqlib::set_library_path(library());
+        let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1420)
+            .with_interface(Interface::TcpIp)
+            .with_ip_address("10.10.10.10")
+            .connect()
+            .expect("the stand-in connects");
+        let mut group = c.benchmark_group("parameters");
+
+        calls(&sensor, "SP_Measrate");
+        eprintln!(
+            "interned: {:.2} allocations per call",
+            super::common::allocations(1000, || calls(&sensor, "SP_Measrate")) / 3.
+        );
+        group.bench_function("interned", |b| b.iter(|| calls(&sensor, "SP_Measrate")));
+
+        for counter in 0..MAX_NAMES {
+            sensor
+                .set_parameter_int(&format!("SP_Answer{counter}"), 1)
+                .unwrap();
+        }
+        eprintln!(
+            "converted: {:.2} allocations per call",
+            super::common::allocations(1000, || calls(&sensor, "SP_LaserPower")) / 3.
+        );
+        group.bench_function("converted", |b| b.iter(|| calls(&sensor, "SP_LaserPower")));
+        group.finish();
+    }
+}
+
+#[cfg(all(unix, not(feature = "link")))]
+criterion::criterion_group!(benches, fake::parameters);
+#[cfg(all(unix, not(feature = "link")))]
+criterion::criterion_main!(benches);
+
+/// The stand-in is loaded at runtime and built as a shared library for unix
+#[cfg(not(all(unix, not(feature = "link"))))]
+fn main() {}
@@ -0,0 +1,105 @@
+/* Stand-in for MEDAQLib that answers like a sensor with one channel without
+ * touching any hardware, built as a shared library by benches/intern.rs */
+
+#include <stdint.h>
+#include <string.h>
+
+#define ERR_NOERROR 0
+#define ERR_NOT_FOUND -25
+
+static int answer(const char *text, char *value, uint32_t max_len) {
+    if (max_len == 0 || strlen(text) >= max_len) {
+        return ERR_NOT_FOUND;
+    }
+    strcpy(value, text);
+    return ERR_NOERROR;
+}
+
+int GetDLLVersion(char *version, uint32_t max_len) {
+    return answer("5.5.0.400", version, max_len);
+}
+
+uint32_t CreateSensorInstance(int sensor_type) {
+    (void)sensor_type;
+    return 1;
+}
+
+int ReleaseSensorInstance(uint32_t handle) {
+    (void)handle;
+    return ERR_NOERROR;
+}
+
+int OpenSensor(uint32_t handle) {
+    (void)handle;
+    return ERR_NOERROR;
+}
+
+int CloseSensor(uint32_t handle) {
+    (void)handle;
+    return ERR_NOERROR;
+}
+
+int SetParameterString(uint32_t handle, const char *name, const char *value) {
+    (void)handle, (void)name, (void)value;
+    return ERR_NOERROR;
+}
+
+int SetParameterInt(uint32_t handle, const char *name, int32_t value) {
+    (void)handle, (void)name, (void)value;
+    return ERR_NOERROR;
+}
+
+int SetParameterDouble(uint32_t handle, const char *name, double value) {
+    (void)handle, (void)name, (void)value;
+    return ERR_NOERROR;
+}
+
+int GetParameterString(uint32_t handle, const char *name, char *value, uint32_t *max_len) {
+    (void)handle;
+    if (strcmp(name, "IA_Scaled_Name1") == 0) {
+        return answer("Distance", value, *max_len);
+    }
+    if (strcmp(name, "IA_Scaled_Unit1") == 0) {
+        return answer("mm", value, *max_len);
+    }
+    return ERR_NOT_FOUND;
+}
+
+int GetParameterInt(uint32_t handle, const char *name, int32_t *value) {
+    (void)handle, (void)name;
+    *value = 0;
+    return ERR_NOERROR;
+}
+
+int GetParameterDouble(uint32_t handle, const char *name, double *value) {
+    (void)handle;
+    if (strncmp(name, "IA_", 3) == 0) {
+        return ERR_NOT_FOUND;
+    }
+    *value = 0.5;
+    return ERR_NOERROR;
+}
+
+int ExecSCmd(uint32_t handle, const char *command) {
+    (void)handle, (void)command;
+    return ERR_NOERROR;
+}
+
+int GetError(uint32_t handle, char *text, uint32_t max_len) {
+    (void)handle;
+    return answer("", text, max_len);
+}
+
+int DataAvail(uint32_t handle, int32_t *avail) {
+    (void)handle;
+    *avail = 0;
+    return ERR_NOERROR;
+}
+
+int TransferData(uint32_t handle, int32_t *raw, double *scaled, int32_t max_values, int32_t *read) {
+    (void)handle, (void)raw, (void)scaled, (void)max_values;
+    if (read) {
+        *read = 0;
+    }
+    return ERR_NOERROR;
+}
@@ -0,0 +1,105 @@
+//! Steady-state allocations and round trips of the acquisition thread with and
+//! without a [`DataPool`]
+//!
+//! Every capture goes through [`MockBackend`] without its call log. The
+//! allocations of all threads per round trip are printed before the timings.
+
+mod common;
+
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use medaqlib::{
+    AcquisitionConfig, AcquisitionHandle, Data, DataPool, Interface, MockBackend, PooledData,
+    SensorBuilder, ME_SENSOR,
+};
+
+const CHANNELS: usize = 4;
+const FRAMES: usize = 100;
+
+fn connect() -> (MockBackend, medaqlib::Sensor) {
+    let channels = (0..CHANNELS).map(|channel| format!("ch{channel}"));
+    let mock = MockBackend::new()
+        .with_channels(channels)
+        .without_call_log();
+    let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+        .with_interface(Interface::TcpIp)
+        .with_ip_address("10.10.10.10")
+        .with_backend(mock.clone())
+        .connect()
+        .expect("mock connects");
+    (mock, sensor)
+}
+
+fn config() -> AcquisitionConfig {
+    AcquisitionConfig {
+        poll_interval: Duration::ZERO,
+        ..Default::default()
+    }
+}
+
+/// Captures as received from the acquisitions
+trait Capture {
+    fn data(&self) -> &Data;
+}
+
+impl Capture for Data {
+    fn data(&self) -> &Data {
+        self
+    }
+}
+
+impl Capture for PooledData {
+    fn data(&self) -> &Data {
+        self
+    }
+}
+
+/// [`FRAMES`] frames through the acquisition, dropped once received
+fn round_trip<T: Capture>(mock: &MockBackend, acquisition: &AcquisitionHandle<T>) {
+    let raw = [1; CHANNELS];
+    let scaled = [0.5; CHANNELS];
+    for _ in 0..FRAMES {
+        mock.push_frame(&raw, &scaled);
+    }
+    let mut frames = 0;
+    while frames < FRAMES {
+        let data = acquisition.receiver().recv().unwrap().unwrap();
+        frames += data.data().frame_count();
+    }
+}
+
+/// Allocations per round trip after warming up
+fn allocations<T: Capture>(mock: &MockBackend, acquisition: &AcquisitionHandle<T>) -> f64 {
+    for _ in 0..100 {
+        round_trip(mock, acquisition);
+    }
+    common::allocations(1000, || round_trip(mock, acquisition))
+}
+
+fn acquisition(c: &mut Criterion) {
+    let mut group = c.benchmark_group("acquisition");
+
+    let (mock, sensor) = connect();
+    let plain = sensor.start_acquisition(config());
+    eprintln!(
+        "plain: {:.2} allocations per round trip",
+        allocations(&mock, &plain)
+    );
+    group.bench_function("plain", |b| b.iter(|| round_trip(&mock, &plain)));
+    drop(plain);
+
+    let (mock, sensor) = connect();
+    let pool = DataPool::new(64);
+    let pooled = sensor.start_pooled_acquisition(config(), &pool);
+    eprintln!(
+        "pooled: {:.2} allocations per round trip, {:?}",
+        allocations(&mock, &pooled),
+        pool.stats()
+    );
+    group.bench_function("pooled", |b| b.iter(|| round_trip(&mock, &pooled)));
+    group.finish();
+}
+
+criterion_group!(benches, acquisition);
+criterion_main!(benches);
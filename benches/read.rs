@@ -0,0 +1,152 @@
+//! Benchmarks of the read path and of the processing of captures
+//!
+//! Every read goes through [`MockBackend`] without its call log, so the
+//! numbers reflect the work of the crate rather than of a sensor. The
+//! allocations of all threads per read are printed before the timings of
+//! `read_data`.
+
+mod common;
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use medaqlib::{CsvOptions, Data, Interface, MockBackend, Sensor, SensorBuilder, ME_SENSOR};
+
+fn channels(count: usize) -> Vec<String> {
+    (0..count).map(|channel| format!("ch{channel}")).collect()
+}
+
+/// Capture of `values` values over `channels` channels, every 50th value out of range
+fn capture(channels: usize, values: usize) -> Data {
+    let raw = (0..values as i32)
+        .map(|i| if i % 50 == 0 { -1 } else { i })
+        .collect();
+    let scaled = (0..values)
+        .map(|i| match i % 50 {
+            0 => medaqlib::SCALED_OUT_OF_RANGE,
+            _ => i as f64 * 0.01,
+        })
+        .collect();
+    Data::new(self::channels(channels), raw, scaled)
+}
+
+fn connect(channels: usize) -> (MockBackend, Sensor) {
+    let mock = MockBackend::new()
+        .with_channels(self::channels(channels))
+        .without_call_log();
+    let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+        .with_interface(Interface::TcpIp)
+        .with_ip_address("10.10.10.10")
+        .with_backend(mock.clone())
+        .connect()
+        .expect("mock connects");
+    (mock, sensor)
+}
+
+/// Allocations per read of 25 frames over `channels` channels, next to those of
+/// copying the channel names for every capture
+fn allocations(channels: usize) {
+    const READS: usize = 1000;
+    let (mock, mut sensor) = connect(channels);
+    sensor.set_max_read(25);
+    let data = capture(channels, channels * 25);
+    for _ in 0..READS {
+        mock.push_data(&data);
+    }
+    let read = common::allocations(READS, || drop(sensor.read_data().unwrap()));
+    let names = common::allocations(READS, || drop(black_box(data.channels().to_vec())));
+    eprintln!(
+        "read_data {channels}ch: {read:.2} allocations per read, copying the names would add {names:.2}"
+    );
+}
+
+fn read_data(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_data");
+    for channels in [1, 4, 16] {
+        allocations(channels);
+        for values in [100, 10_000] {
+            let (mock, sensor) = connect(channels);
+            let data = capture(channels, values);
+            group.throughput(Throughput::Elements(values as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{channels}ch"), values),
+                &data,
+                |b, data| {
+                    b.iter_batched(
+                        || mock.push_data(data),
+                        |_| sensor.read_data().unwrap(),
+                        BatchSize::SmallInput,
+                    )
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn read_into(c: &mut Criterion) {
+    let mut group = c.benchmark_group("read_into");
+    let (mock, sensor) = connect(4);
+    let data = capture(4, 10_000);
+    group.throughput(Throughput::Elements(10_000));
+    group.bench_function("reuse", |b| {
+        let mut buffer = Data::new(vec![], vec![], vec![]);
+        b.iter_batched(
+            || mock.push_data(&data),
+            |_| sensor.read_into(&mut buffer).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("fresh", |b| {
+        b.iter_batched(
+            || mock.push_data(&data),
+            |_| {
+                let mut buffer = Data::new(vec![], vec![], vec![]);
+                sensor.read_into(&mut buffer).unwrap();
+                buffer
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+fn statistics(c: &mut Criterion) {
+    let mut group = c.benchmark_group("statistics");
+    let data = capture(4, 1_000_000);
+    group.throughput(Throughput::Elements(1_000_000));
+    group.bench_function("get_mean_scaled", |b| {
+        b.iter(|| black_box(&data).get_mean_scaled().len())
+    });
+    group.bench_function("get_mean_raw", |b| {
+        b.iter(|| black_box(&data).get_mean_raw().len())
+    });
+    group.bench_function("summary", |b| b.iter(|| black_box(&data).summary()));
+    group.finish();
+}
+
+fn serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialization");
+    let data = capture(4, 100_000);
+    group.throughput(Throughput::Elements(100_000));
+    group.bench_function("csv", |b| {
+        let mut csv = Vec::new();
+        b.iter(|| {
+            csv.clear();
+            data.write_csv(&mut csv, &CsvOptions::default()).unwrap();
+            csv.len()
+        })
+    });
+    group.bench_function("binary", |b| {
+        let mut binary = Vec::new();
+        b.iter(|| {
+            binary.clear();
+            data.write_binary(&mut binary).unwrap();
+            binary.len()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, read_data, read_into, statistics, serialization);
+criterion_main!(benches);
@@ -1,7 +1,120 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Directory of `MEDAQLib.h`, the crate root if unset
+const INCLUDE_DIR: &str = "MEDAQLIB_INCLUDE_DIR";
+/// Directory of `MEDAQLib.lib` for the `link` feature, the crate root if unset,
+/// and of the library the loader searches at runtime
+const LIB_DIR: &str = "MEDAQLIB_LIB_DIR";
+
 fn main() {
-    // Tell cargo to link the DLL
-    // println!("cargo:rustc-link-lib=dylib=MEDAQLib");
-    
-    // Specify where to find the DLL
-    println!("cargo:rustc-link-search=native=.");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed={INCLUDE_DIR}");
+    println!("cargo:rerun-if-env-changed={LIB_DIR}");
+    // for compiling C code in tests
+    println!(
+        "cargo:rustc-env=MEDAQLIB_TARGET={}",
+        env::var("TARGET").unwrap()
+    );
+
+    // without features neither the header nor the library is needed to build, e.g.
+    // on docs.rs, MEDAQLib is loaded at runtime
+    // searched first by the loader, e.g. for `cargo run` without copying the library
+    if let Some(dir) = env::var_os(LIB_DIR) {
+        let dir = env::current_dir().unwrap().join(dir);
+        println!("cargo:rustc-env=MEDAQLIB_BUILD_LIB_DIR={}", dir.display());
+    }
+    if env::var_os("CARGO_FEATURE_LINK").is_some() {
+        link_search();
+    }
+    #[cfg(feature = "bindgen")]
+    generate_bindings();
+    #[cfg(feature = "capi")]
+    generate_header();
+}
+
+/// Where the `link` feature finds the import library of MEDAQLib
+fn link_search() {
+    let lib_dir = env::var_os(LIB_DIR)
+        .map(PathBuf::from)
+        .or_else(|| find("MEDAQLib.lib"))
+        .unwrap_or_else(|| PathBuf::from("."));
+    println!("cargo:rustc-link-search=native={}", lib_dir.display());
+}
+
+/// Directory containing the file, the crate root first and then the Micro-Epsilon
+/// installation directories on Windows
+fn find(file: &str) -> Option<PathBuf> {
+    let mut candidates = vec![PathBuf::from(".")];
+    if cfg!(windows) {
+        for program_files in ["ProgramFiles", "ProgramFiles(x86)"] {
+            if let Some(dir) = env::var_os(program_files) {
+                candidates.push(Path::new(&dir).join("MICRO-EPSILON").join("MEDAQLib"));
+            }
+        }
+    }
+    candidates.into_iter().find(|dir| dir.join(file).is_file())
+}
+
+/// Regenerate the bindings with the options of `cargo make generate-bindings`,
+/// the checked-in `src/bindings.rs` is used without the `bindgen` feature
+#[cfg(feature = "bindgen")]
+fn generate_bindings() {
+    let header = match env::var_os(INCLUDE_DIR) {
+        Some(dir) => Path::new(&dir).join("MEDAQLib.h"),
+        None => find("MEDAQLib.h").unwrap_or_default().join("MEDAQLib.h"),
+    };
+    if !header.is_file() {
+        panic!(
+            "MEDAQLib.h not found at {}: set {INCLUDE_DIR} to the directory containing \
+             the header of MEDAQLib, or build without the bindgen feature to use the \
+             checked-in bindings",
+            header.display()
+        );
+    }
+    println!("cargo:rerun-if-changed={}", header.display());
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    bindgen::Builder::default()
+        .header(header.to_string_lossy())
+        .allowlist_function(".*")
+        .layout_tests(false)
+        .default_enum_style(bindgen::EnumVariation::Rust {
+            non_exhaustive: false,
+        })
+        .dynamic_library_name("MEDAQLib")
+        .clang_args(["-x", "c++"])
+        .generate()
+        .expect("could not generate bindings of MEDAQLib.h")
+        .write_to_file(out_dir.join("bindings.rs"))
+        .expect("could not write bindings");
+}
+
+/// Generate the C header of the `capi` feature, `tests/capi.rs` compares it with
+/// the checked-in `include/medaqlib.h`
+#[cfg(feature = "capi")]
+fn generate_header() {
+    println!("cargo:rerun-if-changed=src/capi.rs");
+
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        include_guard: Some("MEDAQLIB_H".into()),
+        cpp_compat: true,
+        autogen_warning: Some("/* Generated by cbindgen from src/capi.rs, do not edit */".into()),
+        enumeration: cbindgen::EnumConfig {
+            rename_variants: cbindgen::RenameRule::ScreamingSnakeCase,
+            prefix_with_name: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src("src/capi.rs")
+        .generate()
+        .expect("could not generate the C header")
+        .write_to_file(out_dir.join("medaqlib.h"));
 }
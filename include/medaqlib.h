@@ -0,0 +1,119 @@
+#ifndef MEDAQLIB_H
+#define MEDAQLIB_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of a call of the C API
+ */
+enum MedaqStatus
+#if defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+  : int32_t
+#endif // defined(__cplusplus) || __STDC_VERSION__ >= 202311L
+ {
+  MEDAQ_STATUS_OK = 0,
+  /**
+   * The call failed, see `medaq_last_error_message`
+   */
+  MEDAQ_STATUS_ERROR = -1,
+  /**
+   * A pointer was null, a string not valid UTF-8 or a buffer too small for a
+   * frame
+   */
+  MEDAQ_STATUS_INVALID_ARGUMENT = -2,
+  /**
+   * The call panicked, see `medaq_last_error_message`
+   */
+  MEDAQ_STATUS_PANIC = -3,
+};
+#ifndef __cplusplus
+#if __STDC_VERSION__ >= 202311L
+typedef enum MedaqStatus MedaqStatus;
+#else
+typedef int32_t MedaqStatus;
+#endif // __STDC_VERSION__ >= 202311L
+#endif // __cplusplus
+
+/**
+ * Connected sensor, values read but not yet returned by `medaq_read` stay buffered
+ */
+typedef struct MedaqSensor MedaqSensor;
+
+/**
+ * Sensor to connect to with `medaq_connect`
+ */
+typedef struct MedaqConfig {
+  /**
+   * Sensor type, e.g. `"SENSOR_IFD2421"` or `"IFD2421"`
+   */
+  const char *sensor_type;
+  /**
+   * Interface, e.g. `"TCP/IP"` or `"Simulated"`
+   */
+  const char *interface;
+  /**
+   * IP address of the sensor, null for the simulator
+   */
+  const char *ip_address;
+} MedaqConfig;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Message of the last failed call on this thread, empty if none failed
+ *
+ * The string stays valid until the next call on the same thread.
+ */
+const char *medaq_last_error_message(void);
+
+/**
+ * Connect to a sensor, null if connecting fails
+ *
+ * # Safety
+ * `config` is null or points to a config whose strings are null or nul terminated.
+ */
+struct MedaqSensor *medaq_connect(const struct MedaqConfig *config);
+
+/**
+ * Number of channels of the sensor, `0` for null
+ *
+ * # Safety
+ * `sensor` is null or returned by `medaq_connect` and not closed.
+ */
+uintptr_t medaq_channel_count(const struct MedaqSensor *sensor);
+
+/**
+ * Read whole frames of scaled values into `out_frames`, out of range values are
+ * NaN
+ *
+ * `*out_len` is the capacity of `out_frames` in values before the call, at least
+ * `medaq_channel_count` values, and the number of values written after it. Frames
+ * that do not fit are returned by the next calls, which read from the sensor
+ * again once fewer values than their capacity are left.
+ *
+ * # Safety
+ * `sensor` is null or returned by `medaq_connect` and not closed, `out_frames`
+ * holds `*out_len` values.
+ */
+MedaqStatus medaq_read(struct MedaqSensor *sensor, double *out_frames, uintptr_t *out_len);
+
+/**
+ * Disconnect and free the sensor, does nothing for null
+ *
+ * # Safety
+ * `sensor` is null or returned by `medaq_connect` and not closed.
+ */
+void medaq_close(struct MedaqSensor *sensor);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MEDAQLIB_H */
@@ -0,0 +1,905 @@
+use std::{
+    cell::Cell,
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    batch::Batched,
+    gap::{GapDeliver, GapMonitor},
+    priority,
+    ring::{FrameRing, RingDeliver},
+    stats::Stats,
+    sync::{self, Shared, StopFlag},
+    AcquisitionEvent, AcquisitionStats, BatchConfig, Data, DataPool, GapConfig, MedaqError, Pacer,
+    PacerConfig, RingBufferConfig, Sensor, StopToken, ThreadPriority,
+};
+
+/// Configuration of a background acquisition, see [`Sensor::start_acquisition`]
+#[derive(Debug, Clone)]
+pub struct AcquisitionConfig {
+    /// Pause between reads when no data was available or a read failed
+    pub poll_interval: Duration,
+    /// Number of results buffered in the channel. The thread waits for the
+    /// receiver when the channel is full.
+    pub channel_capacity: usize,
+    /// End the acquisition after the first read error instead of retrying
+    pub stop_on_error: bool,
+    /// Keep only the newest frames for [`AcquisitionHandle::snapshot`] instead of
+    /// sending captures through the channel, which then only receives read errors
+    pub ring_buffer: Option<RingBufferConfig>,
+    /// End the acquisition when the token is cancelled, in addition to
+    /// [`AcquisitionHandle::stop`]
+    pub stop_token: Option<StopToken>,
+    /// Adapt the pause after reads without data to the data rate instead of
+    /// always waiting `poll_interval`
+    pub pacing: Option<PacerConfig>,
+    /// Check a counter channel for frames lost inside MEDAQLib, gaps are counted in
+    /// [`AcquisitionHandle::stats`] and reported by [`AcquisitionHandle::events`]
+    pub gap_detection: Option<GapConfig>,
+    /// Priority of the acquisition thread, requires the `thread-priority` feature.
+    /// Failing to apply it is logged as a warning.
+    pub priority: Option<ThreadPriority>,
+    /// Cores the acquisition thread may run on, requires the `thread-priority`
+    /// feature on Windows or Linux. Failing to apply it is logged as a warning.
+    pub affinity: Option<Vec<usize>>,
+    /// Name of the acquisition thread, `medaqlib-acq-<sensor handle>` if empty
+    pub name: String,
+    /// Read the frames still buffered in MEDAQLib after a stop request and deliver
+    /// them before the thread exits
+    pub drain_on_stop: bool,
+    /// Longest time spent draining, including waiting for the receiver
+    pub drain_timeout: Duration,
+    /// Deliver one combined capture per interval or number of frames instead of
+    /// every read. Batches are timestamped at their start and record their
+    /// [`Data::span`], a partial batch is delivered when the acquisition stops.
+    pub aggregate_every: Option<BatchConfig>,
+}
+
+impl Default for AcquisitionConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_millis(10),
+            channel_capacity: 64,
+            stop_on_error: false,
+            ring_buffer: None,
+            stop_token: None,
+            pacing: None,
+            gap_detection: None,
+            priority: None,
+            affinity: None,
+            name: String::new(),
+            drain_on_stop: true,
+            drain_timeout: Duration::from_secs(1),
+            aggregate_every: None,
+        }
+    }
+}
+
+/// Source of captures for the acquisition thread
+///
+/// Not bound by `'static`, so a borrowed sensor can be acquired on a scoped thread
+pub(crate) trait Acquire: Send {
+    fn acquire(&mut self) -> Result<Option<Data>, MedaqError>;
+
+    /// Read into a recycled capture, returns the number of frames read
+    fn acquire_into(&mut self, data: &mut Data) -> Result<usize, MedaqError> {
+        Ok(match self.acquire()? {
+            Some(read) => {
+                *data = read;
+                data.frame_count()
+            }
+            None => 0,
+        })
+    }
+
+    /// Name of the acquisition thread unless configured
+    fn thread_name(&self) -> String {
+        "medaqlib-acq".into()
+    }
+}
+
+impl Acquire for Sensor {
+    fn acquire(&mut self) -> Result<Option<Data>, MedaqError> {
+        self.read_data()
+    }
+
+    fn acquire_into(&mut self, data: &mut Data) -> Result<usize, MedaqError> {
+        self.read_into(data)
+    }
+
+    fn thread_name(&self) -> String {
+        format!("medaqlib-acq-{}", self.core.handle())
+    }
+}
+
+impl<S: Acquire> Acquire for &mut S {
+    fn acquire(&mut self) -> Result<Option<Data>, MedaqError> {
+        (**self).acquire()
+    }
+
+    fn acquire_into(&mut self, data: &mut Data) -> Result<usize, MedaqError> {
+        (**self).acquire_into(data)
+    }
+
+    fn thread_name(&self) -> String {
+        (**self).thread_name()
+    }
+}
+
+impl Sensor {
+    /// Move the sensor to a thread reading captures and sending them to
+    /// [`AcquisitionHandle::receiver`]
+    ///
+    /// Read errors are sent through the channel as well. The sensor is returned
+    /// by [`AcquisitionHandle::join`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use medaqlib::{AcquisitionConfig, Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// let acquisition = sensor.start_acquisition(AcquisitionConfig::default());
+    /// for data in acquisition.receiver().iter().take(100) {
+    ///     println!("{}", data.unwrap());
+    /// }
+    /// let sensor = acquisition.join();
+    /// ```
+    pub fn start_acquisition(self, config: AcquisitionConfig) -> AcquisitionHandle {
+        let stats = self.stats.clone();
+        let (acquisition, parts) = Acquisition::with_parts(self, stats, config);
+        AcquisitionHandle { acquisition, parts }
+    }
+}
+
+/// Running background acquisition, see [`Sensor::start_acquisition`]
+///
+/// Dropping the handle stops the acquisition and releases the sensor. Captures
+/// are received as `T`, [`PooledData`](crate::PooledData) for
+/// [`Sensor::start_pooled_acquisition`].
+pub struct AcquisitionHandle<T = Data> {
+    // dropped first, so a drain does not wait for a receiver nobody reads
+    pub(crate) parts: HandleParts<T>,
+    pub(crate) acquisition: Acquisition<Sensor>,
+}
+
+/// Receiving ends of an acquisition
+pub(crate) struct HandleParts<T = Data> {
+    pub(crate) receiver: Receiver<Result<T, MedaqError>>,
+    pub(crate) events: Receiver<AcquisitionEvent>,
+    pub(crate) ring: Option<Shared<FrameRing>>,
+    pub(crate) stats: Stats,
+}
+
+impl<T> HandleParts<T> {
+    pub(crate) fn snapshot(&self) -> Data {
+        match &self.ring {
+            Some(ring) => ring.lock().snapshot(),
+            None => Data::new(Vec::<String>::new(), vec![], vec![]),
+        }
+    }
+}
+
+impl<T> AcquisitionHandle<T> {
+    /// Captures and read errors in the order they occurred
+    ///
+    /// In ring buffer mode only read errors are received.
+    pub fn receiver(&self) -> &Receiver<Result<T, MedaqError>> {
+        &self.parts.receiver
+    }
+
+    /// Events such as gaps in the counter channel, see
+    /// [`AcquisitionConfig::gap_detection`]
+    ///
+    /// Events are discarded while the channel is full.
+    pub fn events(&self) -> &Receiver<AcquisitionEvent> {
+        &self.parts.events
+    }
+
+    /// Frames currently held by the ring buffer, oldest first, with the metadata of
+    /// the newest capture
+    ///
+    /// Empty if no ring buffer is configured or nothing was received yet.
+    pub fn snapshot(&self) -> Data {
+        self.parts.snapshot()
+    }
+
+    /// Number of frames discarded by the ring buffer so far
+    pub fn dropped_frames(&self) -> u64 {
+        self.parts.stats.get().frames_dropped
+    }
+
+    /// Diagnostics of the acquisition, see [`Sensor::stats`]
+    pub fn stats(&self) -> AcquisitionStats {
+        self.parts.stats.get()
+    }
+
+    /// See [`Sensor::warn_backlog_above`]
+    pub fn warn_backlog_above(
+        &self,
+        frames: usize,
+        callback: impl Fn(usize) + Send + Sync + 'static,
+    ) {
+        self.parts
+            .stats
+            .warn_backlog_above(frames, Arc::new(callback));
+    }
+
+    /// Ask the thread to stop, it finishes the current read and exits
+    ///
+    /// With [`AcquisitionConfig::drain_on_stop`] the frames still buffered in
+    /// MEDAQLib are read and delivered first, [`receiver`](Self::receiver) is
+    /// disconnected once they were all sent.
+    pub fn stop(&self) {
+        self.acquisition.stop()
+    }
+
+    /// `true` once the thread exited, e.g. after [`stop`](Self::stop)
+    pub fn is_finished(&self) -> bool {
+        self.acquisition.is_finished()
+    }
+
+    /// Stop the acquisition and get the sensor back
+    ///
+    /// Results still buffered in the channel and frames drained after the stop are
+    /// discarded, except those sent to a [`DataSink`](crate::DataSink).
+    pub fn join(self) -> Sensor {
+        let Self {
+            parts,
+            mut acquisition,
+        } = self;
+        drop(parts);
+        acquisition.join()
+    }
+}
+
+/// Acquisition thread independent of the source, so it can be tested without a sensor
+pub(crate) struct Acquisition<S: Acquire + 'static> {
+    stopped: StopFlag,
+    thread: Option<JoinHandle<S>>,
+}
+
+/// Receives the results of the acquisition thread
+pub(crate) trait Deliver: Send + 'static {
+    /// Hand over a result, `false` ends the acquisition
+    fn deliver(&mut self, result: Result<Data, MedaqError>, control: &Control) -> bool;
+
+    /// Pool the captures are read into instead of allocating each
+    fn pool(&self) -> Option<&DataPool> {
+        None
+    }
+}
+
+/// State of the acquisition thread available to a [`Deliver`]
+pub(crate) struct Control<'a> {
+    stopped: &'a StopFlag,
+    token: Option<&'a StopToken>,
+    poll_interval: Cell<Duration>,
+    /// Drain timeout if draining is enabled
+    drain: Option<Duration>,
+    /// First time the stop request was noticed while waiting
+    stop_seen: Cell<Option<Instant>>,
+}
+
+/// Longest pause between checks of a [`StopToken`], which cannot wake the thread
+const TOKEN_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+impl<'a> Control<'a> {
+    pub(crate) fn new(
+        stopped: &'a StopFlag,
+        token: Option<&'a StopToken>,
+        poll_interval: Duration,
+        drain: Option<Duration>,
+    ) -> Self {
+        Self {
+            stopped,
+            token,
+            poll_interval: Cell::new(poll_interval),
+            drain,
+            stop_seen: Cell::new(None),
+        }
+    }
+
+    /// A stop was requested
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped.is_stopped() || self.token.is_some_and(StopToken::is_cancelled)
+    }
+
+    /// A stop was requested and pending results may be given up, i.e. draining is
+    /// disabled or its timeout expired
+    pub(crate) fn should_abort(&self) -> bool {
+        if !self.is_stopped() {
+            return false;
+        }
+        let Some(timeout) = self.drain else {
+            return true;
+        };
+        let stop_seen = self.stop_seen.get().unwrap_or_else(Instant::now);
+        self.stop_seen.set(Some(stop_seen));
+        stop_seen.elapsed() >= timeout
+    }
+
+    /// Wait one poll interval or until the acquisition is stopped
+    pub(crate) fn pause(&self) {
+        let poll_interval = self.poll_interval.get();
+        if self.is_stopped() {
+            // draining, the stop request no longer wakes the thread
+            sync::park_timeout(poll_interval.min(TOKEN_CHECK_INTERVAL));
+            return;
+        }
+        let Some(token) = self.token else {
+            sync::park_timeout(poll_interval);
+            return;
+        };
+        let start = Instant::now();
+        loop {
+            let remaining = poll_interval.saturating_sub(start.elapsed());
+            if remaining.is_zero() || token.is_cancelled() || self.stopped.is_stopped() {
+                return;
+            }
+            sync::park_timeout(remaining.min(TOKEN_CHECK_INTERVAL));
+        }
+    }
+}
+
+impl Deliver for SyncSender<Result<Data, MedaqError>> {
+    fn deliver(&mut self, result: Result<Data, MedaqError>, control: &Control) -> bool {
+        send(self, result, control)
+    }
+}
+
+/// Send to the channel, waiting for the receiver without missing a stop request
+pub(crate) fn send<T>(
+    sender: &SyncSender<Result<T, MedaqError>>,
+    result: Result<T, MedaqError>,
+    control: &Control,
+) -> bool {
+    let mut pending = result;
+    loop {
+        match sender.try_send(pending) {
+            Ok(()) => return true,
+            Err(TrySendError::Full(result)) if !control.should_abort() => {
+                pending = result;
+                control.pause();
+            }
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => return false,
+        }
+    }
+}
+
+impl<S: Acquire + 'static> Acquisition<S> {
+    /// Acquisition sending its results to the returned receiver
+    pub(crate) fn with_channel(
+        source: S,
+        config: AcquisitionConfig,
+    ) -> (Self, Receiver<Result<Data, MedaqError>>) {
+        let (sender, receiver) = mpsc::sync_channel(config.channel_capacity);
+        (Self::start(source, config, sender), receiver)
+    }
+
+    /// Acquisition sending to a channel or storing in a ring buffer, see
+    /// [`AcquisitionConfig::ring_buffer`]
+    pub(crate) fn with_parts(
+        source: S,
+        stats: Stats,
+        config: AcquisitionConfig,
+    ) -> (Self, HandleParts) {
+        let (sender, receiver) = mpsc::sync_channel(config.channel_capacity);
+        let (events_sender, events) = mpsc::sync_channel(config.channel_capacity);
+        let ring = config
+            .ring_buffer
+            .map(|ring| Shared::new(FrameRing::new(ring)));
+        let parts = HandleParts {
+            receiver,
+            events,
+            ring: ring.clone(),
+            stats: stats.clone(),
+        };
+
+        let gaps = GapMonitor::new(config.gap_detection.clone(), stats.clone(), events_sender);
+        let acquisition = match ring {
+            Some(ring) => {
+                let deliver = RingDeliver {
+                    ring,
+                    stats,
+                    errors: sender,
+                };
+                Self::start_monitored(source, config, deliver, gaps)
+            }
+            None => Self::start_monitored(source, config, sender, gaps),
+        };
+        (acquisition, parts)
+    }
+
+    /// Acquisition checking captures for gaps if configured
+    pub(crate) fn start_monitored(
+        source: S,
+        config: AcquisitionConfig,
+        deliver: impl Deliver,
+        gaps: Option<GapMonitor>,
+    ) -> Self {
+        match gaps {
+            Some(monitor) => Self::start(
+                source,
+                config,
+                GapDeliver {
+                    monitor,
+                    inner: deliver,
+                },
+            ),
+            None => Self::start(source, config, deliver),
+        }
+    }
+
+    pub(crate) fn start(source: S, config: AcquisitionConfig, deliver: impl Deliver) -> Self {
+        let stopped = StopFlag::default();
+        let thread = {
+            let stopped = stopped.clone();
+            thread_builder(&source, &config)
+                .spawn(move || run(source, config, deliver, &stopped))
+                .expect("could not spawn acquisition thread")
+        };
+        Self {
+            stopped,
+            thread: Some(thread),
+        }
+    }
+
+    pub(crate) fn stop(&self) {
+        self.stopped.stop();
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_none_or(|thread| thread.is_finished())
+    }
+
+    pub(crate) fn join(&mut self) -> S {
+        self.stop();
+        let thread = self.thread.take().expect("acquisition joined once");
+        match thread.join() {
+            Ok(source) => source,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl<S: Acquire + 'static> Drop for Acquisition<S> {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stopped.stop();
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Builder of the acquisition thread with the configured name
+pub(crate) fn thread_builder(source: &impl Acquire, config: &AcquisitionConfig) -> thread::Builder {
+    let name = match config.name.is_empty() {
+        true => source.thread_name(),
+        false => config.name.clone(),
+    };
+    thread::Builder::new().name(name)
+}
+
+/// Body of the acquisition thread, returns the source once stopped
+pub(crate) fn run<S: Acquire>(
+    mut source: S,
+    config: AcquisitionConfig,
+    deliver: impl Deliver,
+    stopped: &StopFlag,
+) -> S {
+    priority::apply(config.priority, config.affinity.as_deref());
+    let control = Control::new(
+        stopped,
+        config.stop_token.as_ref(),
+        config.poll_interval,
+        config.drain_on_stop.then_some(config.drain_timeout),
+    );
+    // batches are combined into fresh captures
+    let pool = match config.aggregate_every {
+        Some(_) => None,
+        None => deliver.pool().cloned(),
+    };
+    let mut deliver = Batched::new(config.aggregate_every, deliver);
+    let mut pacer = config.pacing.map(Pacer::new);
+    while !control.is_stopped() {
+        let result = acquire(&mut source, pool.as_ref());
+        if let Some(pacer) = &mut pacer {
+            let frames = match &result {
+                Ok(Some(data)) => data.frame_count(),
+                _ => 0,
+            };
+            pacer.record_poll(frames);
+            control.poll_interval.set(pacer.interval());
+        }
+        let result = match result {
+            Ok(Some(data)) => Ok(data),
+            Ok(None) => {
+                if !deliver.idle(&control) {
+                    return source;
+                }
+                control.pause();
+                continue;
+            }
+            Err(err) => Err(err),
+        };
+        let failed = result.is_err();
+        if !deliver.deliver(result, &control) {
+            return source;
+        }
+
+        if failed {
+            if config.stop_on_error {
+                return source;
+            }
+            control.pause();
+        }
+    }
+
+    if config.drain_on_stop {
+        drain(&mut source, pool.as_ref(), &mut deliver, &control);
+    }
+    deliver.finish(&control);
+    source
+}
+
+/// Read a capture, into a recycled one of `pool` if given
+fn acquire(source: &mut impl Acquire, pool: Option<&DataPool>) -> Result<Option<Data>, MedaqError> {
+    let Some(pool) = pool else {
+        return source.acquire();
+    };
+    let mut data = pool.take();
+    match source.acquire_into(&mut data) {
+        Ok(0) => {
+            pool.put(data);
+            Ok(None)
+        }
+        Ok(_) => Ok(Some(data)),
+        Err(err) => {
+            pool.put(data);
+            Err(err)
+        }
+    }
+}
+
+/// Deliver the frames still buffered in MEDAQLib after a stop request
+fn drain(
+    source: &mut impl Acquire,
+    pool: Option<&DataPool>,
+    deliver: &mut Batched<impl Deliver>,
+    control: &Control,
+) {
+    while !control.should_abort() {
+        match acquire(source, pool) {
+            Ok(Some(data)) => {
+                if !deliver.deliver(Ok(data), control) {
+                    return;
+                }
+            }
+            Ok(None) => return,
+            Err(err) => {
+                deliver.deliver(Err(err), control);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        collections::VecDeque,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use super::{Acquire, Acquisition};
+    use crate::{
+        stats::Stats, AcquisitionConfig, AcquisitionEvent, Call, Data, DropPolicy, GapConfig,
+        Intercept, MedaqError, MockBackend, PacerConfig, RingBufferConfig, StopToken,
+        ThreadPriority, ERR_CODE, ME_SENSOR,
+    };
+
+    /// Replays the given results, then has no data
+    pub(crate) struct Script {
+        pub(crate) results: VecDeque<Result<Option<Data>, MedaqError>>,
+        pub(crate) reads: usize,
+    }
+
+    impl Script {
+        pub(crate) fn new(results: Vec<Result<Option<Data>, MedaqError>>) -> Self {
+            Self {
+                results: results.into(),
+                reads: 0,
+            }
+        }
+    }
+
+    impl Acquire for Script {
+        fn acquire(&mut self) -> Result<Option<Data>, MedaqError> {
+            self.reads += 1;
+            self.results.pop_front().unwrap_or(Ok(None))
+        }
+    }
+
+    pub(crate) fn data(value: f64) -> Data {
+        Data::new(vec!["Distance".to_string()], vec![], vec![value])
+    }
+
+    fn config() -> AcquisitionConfig {
+        AcquisitionConfig {
+            poll_interval: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_acquisition_forwards_data_and_errors_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        mock.push_frame(&[0], &[1.]);
+        let transfers = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&transfers);
+        mock.intercept(move |call| match call.call {
+            // the second transfer fails, reading continues afterwards
+            Call::TransferData if counter.fetch_add(1, Ordering::SeqCst) == 1 => {
+                Intercept::Fail(ERR_CODE::ERR_NO_ANSWER_RECEIVED)
+            }
+            _ => Intercept::Pass,
+        });
+        let (mut acquisition, receiver) =
+            Acquisition::with_channel(mock.connect(ME_SENSOR::SENSOR_IFD2421), config());
+
+        assert_eq!(receiver.recv().unwrap().unwrap().scaled_data, vec![1.]);
+        mock.push_frame(&[0], &[2.]);
+        assert!(matches!(
+            receiver.recv().unwrap(),
+            Err(MedaqError::Sensor(ERR_CODE::ERR_NO_ANSWER_RECEIVED))
+        ));
+        assert_eq!(receiver.recv().unwrap().unwrap().scaled_data, vec![2.]);
+
+        acquisition.join();
+        assert_eq!(transfers.load(Ordering::SeqCst), 3);
+        assert_eq!(mock.pending_values(), 0);
+    }
+
+    #[test]
+    fn test_acquisition_ring_buffer_test() {
+        // the error is sent after all captures are in the ring
+        let mut results: Vec<_> = (0..10).map(|i| Ok(Some(data(i as f64)))).collect();
+        results.push(Err(MedaqError::NoChannels));
+        let config = AcquisitionConfig {
+            ring_buffer: Some(RingBufferConfig {
+                capacity_frames: 4,
+                on_drop: DropPolicy::DropOldest,
+            }),
+            ..config()
+        };
+        let (mut acquisition, parts) =
+            Acquisition::with_parts(Script::new(results), Stats::default(), config);
+
+        assert!(matches!(
+            parts.receiver.recv_timeout(Duration::from_secs(5)),
+            Ok(Err(MedaqError::NoChannels))
+        ));
+        let script = acquisition.join();
+        assert!(script.results.is_empty());
+        assert_eq!(parts.snapshot().scaled_data, vec![6., 7., 8., 9.]);
+        assert_eq!(parts.stats.get().frames_dropped, 6);
+        assert!(parts.receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_acquisition_gap_detection_test() {
+        let counters = |counters: Vec<f64>| {
+            let scaled_data = counters.into_iter().flat_map(|c| [1., c]).collect();
+            let channels = vec!["Distance".to_string(), "Counter".to_string()];
+            Ok(Some(Data::new(channels, vec![], scaled_data)))
+        };
+        let script = Script::new(vec![
+            counters(vec![1., 2., 3.]),
+            counters(vec![6., 7.]),
+            Ok(Some(data(1.))),
+            counters(vec![0., 2.]),
+        ]);
+        let config = AcquisitionConfig {
+            gap_detection: Some(GapConfig {
+                counter_channel: "Counter".into(),
+                modulus: Some(8),
+            }),
+            ..config()
+        };
+        let stats = Stats::default();
+        let (mut acquisition, parts) = Acquisition::with_parts(script, stats.clone(), config);
+
+        // every capture is still delivered
+        assert_eq!(parts.receiver.iter().take(4).count(), 4);
+        acquisition.join();
+        let gaps: Vec<_> = parts
+            .events
+            .try_iter()
+            .map(
+                |AcquisitionEvent::Gap {
+                     missing, counter, ..
+                 }| (missing, counter),
+            )
+            .collect();
+        assert_eq!(gaps, vec![(2, 6), (1, 2)]);
+        assert_eq!((stats.get().gaps, stats.get().missing_frames), (2, 3));
+    }
+
+    #[test]
+    fn test_acquisition_thread_options_test() {
+        struct Name;
+        impl Acquire for Name {
+            fn acquire(&mut self) -> Result<Option<Data>, MedaqError> {
+                let name = std::thread::current().name().map(String::from);
+                Err(MedaqError::UnknownChannel {
+                    name: name.unwrap_or_default(),
+                    available: vec![],
+                })
+            }
+        }
+        let thread_name = |config: AcquisitionConfig| {
+            let (mut acquisition, receiver) = Acquisition::with_channel(Name, config);
+            let name = match receiver.recv_timeout(Duration::from_secs(5)) {
+                Ok(Err(MedaqError::UnknownChannel { name, .. })) => name,
+                result => panic!("unexpected result {result:?}"),
+            };
+            acquisition.join();
+            name
+        };
+
+        assert_eq!(thread_name(config()), "medaqlib-acq");
+        // priority and affinity failures are not fatal
+        let config = AcquisitionConfig {
+            name: "camera-sync".into(),
+            priority: Some(ThreadPriority::Value(50)),
+            affinity: Some(vec![0]),
+            ..config()
+        };
+        assert_eq!(thread_name(config), "camera-sync");
+    }
+
+    #[test]
+    fn test_acquisition_stop_on_error_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        mock.push_frame(&[0], &[1.]);
+        mock.fail_call(Call::TransferData, 1, ERR_CODE::ERR_NO_ANSWER_RECEIVED);
+        let config = AcquisitionConfig {
+            stop_on_error: true,
+            ..config()
+        };
+        let (mut acquisition, receiver) =
+            Acquisition::with_channel(mock.connect(ME_SENSOR::SENSOR_IFD2421), config);
+
+        // the channel closes as the acquisition ends after the error
+        let received: Vec<_> = receiver.iter().collect();
+        assert_eq!(received.len(), 1);
+        acquisition.join();
+        let transfers = mock
+            .calls()
+            .iter()
+            .filter(|call| call.call == Call::TransferData)
+            .count();
+        assert_eq!((transfers, mock.pending_values()), (1, 1));
+    }
+
+    #[test]
+    fn test_acquisition_stops_promptly_test() {
+        let config = AcquisitionConfig {
+            poll_interval: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let (mut acquisition, _receiver) = Acquisition::with_channel(Script::new(vec![]), config);
+        std::thread::sleep(Duration::from_millis(20));
+
+        let start = Instant::now();
+        acquisition.join();
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_acquisition_pacing_test() {
+        let config = AcquisitionConfig {
+            poll_interval: Duration::from_secs(60),
+            pacing: Some(PacerConfig {
+                max_interval: Duration::from_millis(5),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let script = Script::new(vec![Ok(None), Ok(None), Ok(Some(data(1.)))]);
+        let (mut acquisition, receiver) = Acquisition::with_channel(script, config);
+
+        // the pacer replaces the poll interval
+        let received = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(received.unwrap().scaled_data, vec![1.]);
+        assert!(acquisition.join().reads >= 3);
+    }
+
+    #[test]
+    fn test_acquisition_drains_on_stop_test() {
+        let drained = |drain_on_stop| {
+            let results = (0..20).map(|i| Ok(Some(data(i as f64)))).collect();
+            let config = AcquisitionConfig {
+                channel_capacity: 2,
+                drain_on_stop,
+                ..config()
+            };
+            let (acquisition, receiver) = Acquisition::with_channel(Script::new(results), config);
+            // the thread waits for the receiver with captures left in the script
+            std::thread::sleep(Duration::from_millis(20));
+            acquisition.stop();
+
+            // ends once the thread exits
+            let received: Vec<_> = receiver
+                .iter()
+                .map(|data| data.unwrap().scaled_data[0])
+                .collect();
+            (received, acquisition)
+        };
+
+        let (received, mut acquisition) = drained(true);
+        assert_eq!(received, (0..20).map(|i| i as f64).collect::<Vec<_>>());
+        assert!(acquisition.join().results.is_empty());
+
+        let (received, mut acquisition) = drained(false);
+        assert!(received.len() < 20);
+        assert!(!acquisition.join().results.is_empty());
+    }
+
+    #[test]
+    fn test_acquisition_stop_token_test() {
+        let token = StopToken::new();
+        let config = AcquisitionConfig {
+            poll_interval: Duration::from_secs(60),
+            stop_token: Some(token.clone()),
+            ..Default::default()
+        };
+        let (acquisition, _receiver) = Acquisition::with_channel(Script::new(vec![]), config);
+        std::thread::sleep(Duration::from_millis(20));
+
+        token.cancel();
+        let start = Instant::now();
+        while !acquisition.is_finished() {
+            assert!(start.elapsed() < Duration::from_millis(50));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_acquisition_stops_with_full_channel_test() {
+        let results = (0..10).map(|i| Ok(Some(data(i as f64)))).collect();
+        let config = AcquisitionConfig {
+            channel_capacity: 2,
+            drain_on_stop: false,
+            ..config()
+        };
+        let (mut acquisition, receiver) = Acquisition::with_channel(Script::new(results), config);
+        std::thread::sleep(Duration::from_millis(50));
+
+        // two results buffered, one waiting to be sent
+        let script = acquisition.join();
+        assert!(script.results.len() >= 7);
+        let received: Vec<_> = receiver.try_iter().collect();
+        assert!(received.len() <= 2);
+    }
+}
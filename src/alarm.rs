@@ -0,0 +1,220 @@
+use crate::{Data, MedaqError, Value};
+
+/// Condition a sample must meet to count as alarming
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    /// Value greater than the threshold
+    Above(f64),
+    /// Value less than the threshold
+    Below(f64),
+    /// Value outside of `low..=high`
+    OutsideBand { low: f64, high: f64 },
+}
+
+impl Comparison {
+    fn matches(&self, value: f64) -> bool {
+        match *self {
+            Comparison::Above(threshold) => value > threshold,
+            Comparison::Below(threshold) => value < threshold,
+            Comparison::OutsideBand { low, high } => value < low || value > high,
+        }
+    }
+
+    /// `true` if `value` is further from the allowed range than `extreme`
+    fn more_extreme(&self, value: f64, extreme: f64) -> bool {
+        match *self {
+            Comparison::Above(_) => value > extreme,
+            Comparison::Below(_) => value < extreme,
+            Comparison::OutsideBand { low, high } => {
+                let deviation = |v: f64| (low - v).max(v - high);
+                deviation(value) > deviation(extreme)
+            }
+        }
+    }
+}
+
+/// How out of range samples affect a run of alarming samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidSamples {
+    /// An out of range sample ends the run
+    #[default]
+    BreakRun,
+    /// Out of range samples are skipped, the run continues with the next valid sample
+    ContinueRun,
+}
+
+/// Alarm if a channel meets a comparison for a minimum number of consecutive frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlarmRule {
+    pub channel: String,
+    pub comparison: Comparison,
+    /// Minimum length of a run, from its first to its last alarming frame
+    pub min_frames: usize,
+    pub invalid: InvalidSamples,
+}
+
+/// Run of alarming samples found by [`Data::evaluate`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlarmEvent {
+    /// First alarming frame of the run
+    pub start_frame: usize,
+    /// Last alarming frame of the run, inclusive
+    pub end_frame: usize,
+    /// Value furthest beyond the threshold within the run
+    pub extreme: f64,
+}
+
+impl AlarmEvent {
+    /// Number of frames from start to end of the run
+    pub fn frame_count(&self) -> usize {
+        self.end_frame - self.start_frame + 1
+    }
+}
+
+impl Data {
+    /// Find runs of scaled values of a channel meeting the rule
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{AlarmRule, Comparison, Data, InvalidSamples};
+    ///
+    /// let data = Data::new(vec!["Distance".to_string()], vec![], vec![1., 5., 6., 1.]);
+    /// let rule = AlarmRule {
+    ///     channel: "Distance".into(),
+    ///     comparison: Comparison::Above(4.),
+    ///     min_frames: 2,
+    ///     invalid: InvalidSamples::BreakRun,
+    /// };
+    /// let events = data.evaluate(&rule).unwrap();
+    /// assert_eq!((events[0].start_frame, events[0].end_frame, events[0].extreme), (1, 2, 6.));
+    /// ```
+    pub fn evaluate(&self, rule: &AlarmRule) -> Result<Vec<AlarmEvent>, MedaqError> {
+        let channel = self.channel_indices(&[rule.channel.as_str()])?[0];
+        let channels = self.channel_count();
+
+        let mut events = vec![];
+        let mut run: Option<AlarmEvent> = None;
+        let samples = self.scaled_data.iter().skip(channel).step_by(channels);
+        for (frame, &sample) in samples.enumerate() {
+            match Value::from_sample(sample) {
+                Value::Valid(value) if rule.comparison.matches(value) => match &mut run {
+                    Some(run) => {
+                        run.end_frame = frame;
+                        if rule.comparison.more_extreme(value, run.extreme) {
+                            run.extreme = value;
+                        }
+                    }
+                    None => {
+                        run = Some(AlarmEvent {
+                            start_frame: frame,
+                            end_frame: frame,
+                            extreme: value,
+                        })
+                    }
+                },
+                Value::OutOfRange if rule.invalid == InvalidSamples::ContinueRun => (),
+                _ => events.extend(run.take()),
+            }
+        }
+        events.extend(run);
+
+        events.retain(|event| event.frame_count() >= rule.min_frames);
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        AlarmEvent, AlarmRule, Comparison, Data, DataBuilder, InvalidSamples, MedaqError,
+        SCALED_OUT_OF_RANGE as OOR,
+    };
+
+    fn data(values: Vec<f64>) -> Data {
+        DataBuilder::channels(["Other", "Distance"])
+            .frames(values.len(), |frame| [0., values[frame]])
+            .build()
+    }
+
+    fn rule(comparison: Comparison, min_frames: usize, invalid: InvalidSamples) -> AlarmRule {
+        AlarmRule {
+            channel: "Distance".into(),
+            comparison,
+            min_frames,
+            invalid,
+        }
+    }
+
+    fn event(start_frame: usize, end_frame: usize, extreme: f64) -> AlarmEvent {
+        AlarmEvent {
+            start_frame,
+            end_frame,
+            extreme,
+        }
+    }
+
+    #[test]
+    fn test_evaluate_above_test() {
+        let data = data(vec![5., 1., 6., 7., 5., 1., 9.]);
+        let events = data
+            .evaluate(&rule(Comparison::Above(4.), 1, InvalidSamples::BreakRun))
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![event(0, 0, 5.), event(2, 4, 7.), event(6, 6, 9.)]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_min_frames_spanning_boundaries_test() {
+        let data = data(vec![5., 6., 1., 6., 1., 7., 8.]);
+        let events = data
+            .evaluate(&rule(Comparison::Above(4.), 2, InvalidSamples::BreakRun))
+            .unwrap();
+        assert_eq!(events, vec![event(0, 1, 6.), event(5, 6, 8.)]);
+    }
+
+    #[test]
+    fn test_evaluate_below_test() {
+        let data = data(vec![1., 0., 0.5, 3.]);
+        let events = data
+            .evaluate(&rule(Comparison::Below(2.), 1, InvalidSamples::BreakRun))
+            .unwrap();
+        assert_eq!(events, vec![event(0, 2, 0.)]);
+    }
+
+    #[test]
+    fn test_evaluate_outside_band_test() {
+        let data = data(vec![1., 5., 12., 4., 3.]);
+        let comparison = Comparison::OutsideBand { low: 2., high: 4.5 };
+        let events = data
+            .evaluate(&rule(comparison, 1, InvalidSamples::BreakRun))
+            .unwrap();
+        assert_eq!(events, vec![event(0, 2, 12.)]);
+    }
+
+    #[test]
+    fn test_evaluate_invalid_samples_test() {
+        let data = data(vec![5., OOR, 6., OOR, 1.]);
+
+        let events = data
+            .evaluate(&rule(Comparison::Above(4.), 2, InvalidSamples::BreakRun))
+            .unwrap();
+        assert_eq!(events, vec![]);
+
+        let events = data
+            .evaluate(&rule(Comparison::Above(4.), 2, InvalidSamples::ContinueRun))
+            .unwrap();
+        assert_eq!(events, vec![event(0, 2, 6.)]);
+    }
+
+    #[test]
+    fn test_evaluate_unknown_channel_test() {
+        let mut rule = rule(Comparison::Above(4.), 1, InvalidSamples::BreakRun);
+        rule.channel = "Intensity".into();
+        assert!(matches!(
+            data(vec![]).evaluate(&rule),
+            Err(MedaqError::UnknownChannel { .. })
+        ));
+    }
+}
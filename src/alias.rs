@@ -0,0 +1,168 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{ChannelInfo, Data, MedaqError, Sensor};
+
+impl Data {
+    /// Replace channel names by their aliases
+    ///
+    /// Keys of `aliases` are current channel names, channels without an entry keep
+    /// their name. Afterwards channels are only known by their alias, lookups by
+    /// name (e.g. [`Data::select`]) and exports use the alias.
+    ///
+    /// Fails with [`MedaqError::UnknownChannel`] if a key is not a channel of the
+    /// capture and with [`MedaqError::DuplicateChannel`] if two channels would end
+    /// up with the same name. The capture is unchanged on error.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashMap;
+    /// use medaqlib::Data;
+    ///
+    /// let mut data = Data::new(vec!["Distance 1 [scaled]".to_string()], vec![], vec![1.5]);
+    /// let aliases = HashMap::from([("Distance 1 [scaled]".to_string(), "dist_mm".to_string())]);
+    /// data.rename_channels(&aliases).unwrap();
+    /// assert_eq!(data.channels(), ["dist_mm"]);
+    /// ```
+    pub fn rename_channels(&mut self, aliases: &HashMap<String, String>) -> Result<(), MedaqError> {
+        let channels = rename(self.channels(), aliases)?;
+        self.set_channels(channels);
+        self.channel_info = self.channel_info().map(|info| rename_info(info, aliases));
+        Ok(())
+    }
+}
+
+impl Sensor {
+    /// Replace the reported channel names by their aliases in every [`Data`] read
+    /// from now on
+    ///
+    /// Keys are the current channel names of the sensor, see [`Sensor::parameters`],
+    /// and the same rules as for [`Data::rename_channels`] apply.
+    pub fn set_channel_aliases(
+        &mut self,
+        aliases: HashMap<String, String>,
+    ) -> Result<(), MedaqError> {
+        self.parameters = rename(&self.parameters, &aliases)?;
+        self.channel_info = rename_info(&self.channel_info, &aliases);
+        Ok(())
+    }
+}
+
+/// Aliased channel names, checked for unknown keys and duplicates
+fn rename(
+    channels: &[String],
+    aliases: &HashMap<String, String>,
+) -> Result<Arc<[String]>, MedaqError> {
+    if let Some(name) = aliases.keys().find(|name| !channels.contains(name)) {
+        return Err(MedaqError::UnknownChannel {
+            name: name.clone(),
+            available: channels.to_vec(),
+        });
+    }
+
+    let renamed: Vec<String> = channels.iter().map(|name| alias(name, aliases)).collect();
+    for (index, name) in renamed.iter().enumerate() {
+        if renamed[..index].contains(name) {
+            return Err(MedaqError::DuplicateChannel(name.clone()));
+        }
+    }
+    Ok(renamed.into())
+}
+
+fn rename_info(
+    channel_info: &[ChannelInfo],
+    aliases: &HashMap<String, String>,
+) -> Arc<[ChannelInfo]> {
+    channel_info
+        .iter()
+        .map(|info| ChannelInfo {
+            name: alias(&info.name, aliases),
+            ..info.clone()
+        })
+        .collect()
+}
+
+fn alias(name: &String, aliases: &HashMap<String, String>) -> String {
+    aliases.get(name).unwrap_or(name).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{ChannelInfo, CsvOptions, Data, MedaqError};
+
+    fn data() -> Data {
+        Data::new(
+            vec!["Distance1".to_string(), "Intensity1".to_string()],
+            vec![],
+            vec![1.5, 20., 2.5, 30.],
+        )
+        .with_channel_info(vec![
+            ChannelInfo::new("Distance1").with_unit("mm"),
+            ChannelInfo::new("Intensity1"),
+        ])
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, alias)| (name.to_string(), alias.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rename_channels_test() {
+        let mut data = data();
+        data.rename_channels(&aliases(&[("Distance1", "dist")]))
+            .unwrap();
+
+        assert_eq!(data.channels(), ["dist", "Intensity1"]);
+        assert_eq!(data.channel_info().unwrap()[0].label(), "dist [mm]");
+        assert_eq!(data.select(&["dist"]).unwrap().scaled_data, vec![1.5, 2.5]);
+        assert!(data.select(&["Distance1"]).is_err());
+
+        let mut csv = vec![];
+        data.write_csv(&mut csv, &CsvOptions::default()).unwrap();
+        assert!(String::from_utf8(csv)
+            .unwrap()
+            .starts_with("dist [mm],Intensity1\n"));
+    }
+
+    #[test]
+    fn test_rename_channels_does_not_affect_clones_test() {
+        let original = data();
+        let mut renamed = original.clone();
+        renamed
+            .rename_channels(&aliases(&[("Intensity1", "int")]))
+            .unwrap();
+        assert_eq!(original.channels(), ["Distance1", "Intensity1"]);
+        assert_eq!(renamed.channels(), ["Distance1", "int"]);
+    }
+
+    #[test]
+    fn test_rename_channels_unknown_channel_test() {
+        let mut data = data();
+        let err = data
+            .rename_channels(&aliases(&[("Distance1", "dist"), ("Distance2", "dist2")]))
+            .unwrap_err();
+        assert!(matches!(err, MedaqError::UnknownChannel { name, .. } if name == "Distance2"));
+        assert_eq!(data.channels(), ["Distance1", "Intensity1"]);
+    }
+
+    #[test]
+    fn test_rename_channels_duplicate_test() {
+        let mut data = data();
+        let err = data
+            .rename_channels(&aliases(&[("Distance1", "Intensity1")]))
+            .unwrap_err();
+        assert!(matches!(err, MedaqError::DuplicateChannel(name) if name == "Intensity1"));
+
+        // swapping names is fine
+        data.rename_channels(&aliases(&[
+            ("Distance1", "Intensity1"),
+            ("Intensity1", "Distance1"),
+        ]))
+        .unwrap();
+        assert_eq!(data.channels(), ["Intensity1", "Distance1"]);
+    }
+}
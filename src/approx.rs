@@ -0,0 +1,118 @@
+use crate::{ChannelValue, Data, Value};
+
+impl Data {
+    /// `true` if both captures have the same channels and all values are within
+    /// `epsilon` of each other
+    ///
+    /// Out of range samples only equal out of range samples. Timestamps and channel
+    /// metadata are not compared.
+    pub fn approx_eq(&self, other: &Data, epsilon: f64) -> bool {
+        self.channels() == other.channels()
+            && slices_approx_eq(&self.raw_data, &other.raw_data, epsilon)
+            && slices_approx_eq(&self.scaled_data, &other.scaled_data, epsilon)
+    }
+}
+
+impl<T: Copy + Into<f64>> ChannelValue<'_, T> {
+    /// `true` if channel and unit are equal and the values are within `epsilon`
+    /// of each other
+    ///
+    /// Out of range values only equal out of range values.
+    pub fn approx_eq(&self, other: &ChannelValue<'_, T>, epsilon: f64) -> bool {
+        self.channel == other.channel
+            && self.unit == other.unit
+            && self.value.approx_eq(&other.value, epsilon)
+    }
+}
+
+impl<T: Copy + Into<f64>> Value<T> {
+    fn approx_eq(&self, other: &Value<T>, epsilon: f64) -> bool {
+        match (self, other) {
+            (Value::Valid(left), Value::Valid(right)) => {
+                ((*left).into() - (*right).into()).abs() <= epsilon
+            }
+            (Value::OutOfRange, Value::OutOfRange) => true,
+            _ => false,
+        }
+    }
+}
+
+fn slices_approx_eq<T: Copy + Into<f64>>(left: &[T], right: &[T], epsilon: f64) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .all(|(&l, &r)| Value::from_sample(l).approx_eq(&Value::from_sample(r), epsilon))
+}
+
+/// Assert that two [`Data`] or [`ChannelValue`] are equal within an epsilon
+///
+/// Available with the `test-util` feature, see [`Data::approx_eq`] and
+/// [`ChannelValue::approx_eq`].
+///
+/// # Example
+/// ```
+/// use medaqlib::{assert_data_approx_eq, Data};
+///
+/// let channels = vec!["Distance".to_string()];
+/// let data = Data::new(channels.clone(), vec![], vec![0.1 + 0.2]);
+/// assert_data_approx_eq!(data, Data::new(channels, vec![], vec![0.3]), 1e-9);
+/// ```
+#[cfg(any(test, feature = "test-util"))]
+#[macro_export]
+macro_rules! assert_data_approx_eq {
+    ($left:expr, $right:expr, $epsilon:expr $(,)?) => {
+        match (&$left, &$right, $epsilon) {
+            (left, right, epsilon) => {
+                if !left.approx_eq(right, epsilon) {
+                    panic!(
+                        "assertion `left ≈ right` failed (epsilon: {})\n  left: {:?}\n right: {:?}",
+                        epsilon, left, right
+                    )
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChannelValue, Data, Value, SCALED_OUT_OF_RANGE};
+
+    fn data(scaled_data: Vec<f64>) -> Data {
+        Data::new(vec!["1".to_string()], vec![], scaled_data)
+    }
+
+    #[test]
+    fn test_data_approx_eq_test() {
+        let data = data(vec![0.1 + 0.2, SCALED_OUT_OF_RANGE]);
+        assert!(data.approx_eq(&self::data(vec![0.3, SCALED_OUT_OF_RANGE]), 1e-9));
+        assert!(!data.approx_eq(&self::data(vec![0.3, SCALED_OUT_OF_RANGE]), 0.));
+        assert!(!data.approx_eq(&self::data(vec![0.3, 0.]), 1e-9));
+        assert!(!data.approx_eq(&self::data(vec![0.3]), 1e-9));
+
+        let other = Data::new(vec!["2".to_string()], vec![], data.scaled_data.clone());
+        assert!(!data.approx_eq(&other, 1e-9));
+    }
+
+    #[test]
+    fn test_channel_value_approx_eq_test() {
+        let value = |value, unit| ChannelValue {
+            channel: "1",
+            value,
+            unit,
+        };
+        assert!(value(Value::Valid(1.), None).approx_eq(&value(Value::Valid(1.05), None), 0.1));
+        assert!(!value(Value::Valid(1.), None).approx_eq(&value(Value::Valid(1.2), None), 0.1));
+        let mm = value(Value::Valid(1.), Some("mm"));
+        assert!(!mm.approx_eq(&value(Value::Valid(1.), None), 0.1));
+        assert!(value(Value::OutOfRange, None).approx_eq(&value(Value::OutOfRange, None), 0.));
+        assert!(!value(Value::OutOfRange, None).approx_eq(&value(Value::Valid(1.), None), 0.1));
+    }
+
+    #[test]
+    #[should_panic(expected = "left ≈ right")]
+    fn test_assert_data_approx_eq_panics_test() {
+        crate::assert_data_approx_eq!(data(vec![1.]), data(vec![2.]), 0.5);
+    }
+}
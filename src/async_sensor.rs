@@ -0,0 +1,185 @@
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use crate::{Data, MedaqError, Sensor};
+
+/// Sensor for async code, running every call on the blocking thread pool of tokio
+///
+/// The sensor is owned behind a mutex, so overlapping calls, e.g. from
+/// concurrently polled futures or cloned handles, are executed one after the
+/// other instead of interleaving on the same sensor.
+///
+/// # Cancellation
+///
+/// Dropping a future of this type does not cancel the call into MEDAQLib: the
+/// blocking task owns the sensor lock and the read buffers until the call
+/// returns, its result is then discarded. Later calls wait for it to finish.
+/// To end a blocking read early, call [`Sensor::read_data_cancellable`] through
+/// [`run`](Self::run) and cancel its [`StopToken`](crate::StopToken).
+///
+/// # Example
+/// ```no_run
+/// # async fn example() {
+/// use std::time::Duration;
+/// use medaqlib::{AsyncSensor, Interface, SensorBuilder, ME_SENSOR};
+///
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::TcpIp)
+///     .with_ip_address("10.10.10.10")
+///     .connect()
+///     .unwrap();
+///
+/// let sensor = AsyncSensor::new(sensor);
+/// if let Some(data) = sensor.read_data_blocking_async(Duration::from_secs(1)).await.unwrap() {
+///     println!("{data}");
+/// }
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AsyncSensor {
+    shared: Shared<Sensor>,
+}
+
+impl AsyncSensor {
+    pub fn new(sensor: Sensor) -> Self {
+        Self {
+            shared: Shared::new(sensor),
+        }
+    }
+
+    /// See [`Sensor::read_data`]
+    pub async fn read_data_async(&self) -> Result<Option<Data>, MedaqError> {
+        self.run(|sensor| sensor.read_data()).await
+    }
+
+    /// See [`Sensor::read_data_blocking`]
+    pub async fn read_data_blocking_async(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<Data>, MedaqError> {
+        self.run(move |sensor| sensor.read_data_blocking(timeout))
+            .await
+    }
+
+    /// Run `f` with exclusive access to the sensor on the blocking thread pool
+    pub async fn run<T, F>(&self, f: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Sensor) -> T + Send + 'static,
+    {
+        self.shared.run(f).await
+    }
+
+    /// Get the sensor back, fails while clones of this handle or unfinished
+    /// calls exist
+    pub fn into_inner(self) -> Result<Sensor, Self> {
+        self.shared.into_inner().map_err(|shared| Self { shared })
+    }
+}
+
+impl From<Sensor> for AsyncSensor {
+    fn from(sensor: Sensor) -> Self {
+        Self::new(sensor)
+    }
+}
+
+/// Value used exclusively by one blocking task at a time
+pub(crate) struct Shared<S> {
+    inner: Arc<Mutex<S>>,
+}
+
+impl<S> Clone for Shared<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<S: Send + 'static> Shared<S> {
+    pub(crate) fn new(value: S) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(value)),
+        }
+    }
+
+    pub(crate) async fn run<T, F>(&self, f: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut S) -> T + Send + 'static,
+    {
+        let inner = Arc::clone(&self.inner);
+        let task = tokio::task::spawn_blocking(move || {
+            // a panicking call leaves the sensor itself in a usable state
+            let mut value = inner.lock().unwrap_or_else(PoisonError::into_inner);
+            f(&mut value)
+        });
+        match task.await {
+            Ok(result) => result,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> Result<S, Self> {
+        Arc::try_unwrap(self.inner)
+            .map(|inner| inner.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map_err(|inner| Self { inner })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::Shared;
+
+    /// Fails if two calls overlap
+    #[derive(Default)]
+    struct Exclusive {
+        busy: Arc<AtomicBool>,
+        calls: usize,
+    }
+
+    impl Exclusive {
+        fn call(&mut self) {
+            assert!(!self.busy.swap(true, Ordering::SeqCst), "overlapping calls");
+            std::thread::sleep(Duration::from_millis(5));
+            self.calls += 1;
+            self.busy.store(false, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_shared_serializes_calls_test() {
+        let shared = Shared::new(Exclusive::default());
+        let calls: Vec<_> = (0..8)
+            .map(|_| {
+                let shared = shared.clone();
+                tokio::spawn(async move { shared.run(|value| value.call()).await })
+            })
+            .collect();
+        for call in calls {
+            call.await.unwrap();
+        }
+        assert_eq!(shared.run(|value| value.calls).await, 8);
+    }
+
+    #[tokio::test]
+    async fn test_shared_cancelled_call_completes_test() {
+        let shared = Shared::new(Exclusive::default());
+        let call = shared.run(|value| value.call());
+        // cancel the call once it started on the blocking pool
+        let _ = tokio::time::timeout(Duration::from_millis(1), call).await;
+
+        assert_eq!(shared.run(|value| value.calls).await, 1);
+        assert!(shared.into_inner().is_ok());
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    ffi::{c_char, CString},
+    fmt::Debug,
+    ptr,
+    sync::Arc,
+};
+
+use crate::{
+    intern,
+    library::{self, Library},
+    DllVersion, MedaqError, ERR_CODE, ME_SENSOR,
+};
+
+/// Size of the buffer for strings returned by `GetParameterString`
+const PARAMETER_STRING_LEN: usize = 1024;
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+/// The MEDAQLib functions used by this crate, see [`SensorBuilder::with_backend`](crate::SensorBuilder::with_backend)
+///
+/// Implemented by the DLL, which is used unless another backend is selected, and
+/// by [`MockBackend`](crate::MockBackend). The trait is sealed.
+pub trait Backend: sealed::Sealed + Debug + Send + Sync {
+    fn create_instance(&self, sensor_type: ME_SENSOR) -> u32;
+
+    fn release_instance(&self, handle: u32) -> Result<(), MedaqError>;
+
+    fn open_sensor(&self, handle: u32) -> Result<(), MedaqError>;
+
+    fn close_sensor(&self, handle: u32) -> Result<(), MedaqError>;
+
+    fn set_parameter_string(&self, handle: u32, name: &str, value: &str) -> Result<(), MedaqError>;
+
+    fn set_parameter_int(&self, handle: u32, name: &str, value: i32) -> Result<(), MedaqError>;
+
+    fn get_parameter_string(&self, handle: u32, name: &str) -> Result<String, MedaqError>;
+
+    fn get_parameter_double(&self, handle: u32, name: &str) -> Result<f64, MedaqError>;
+
+    /// Optional, fails with [`MedaqError::UnsupportedByDll`] if MEDAQLib does not
+    /// export `SetParameterDouble`
+    fn set_parameter_double(&self, handle: u32, name: &str, value: f64) -> Result<(), MedaqError>;
+
+    /// Optional, fails with [`MedaqError::UnsupportedByDll`] if MEDAQLib does not
+    /// export `GetParameterInt`
+    fn get_parameter_int(&self, handle: u32, name: &str) -> Result<i32, MedaqError>;
+
+    fn exec_scmd(&self, handle: u32, command: &str) -> Result<(), MedaqError>;
+
+    /// Text of the last error of the instance, empty if there was none
+    fn get_error(&self, handle: u32) -> String;
+
+    /// Version of the loaded MEDAQLib, `None` for backends without one
+    fn dll_version(&self) -> Option<DllVersion> {
+        None
+    }
+
+    /// Number of values, not frames, ready to be transferred
+    fn data_avail(&self, handle: u32) -> Result<i32, MedaqError>;
+
+    /// Transfer at most `max_values` values into the given buffers, returns the
+    /// number of values written
+    ///
+    /// Both buffers hold at least `max_values` values.
+    fn transfer_data(
+        &self,
+        handle: u32,
+        raw: Option<&mut [i32]>,
+        scaled: Option<&mut [f64]>,
+        max_values: usize,
+    ) -> Result<usize, MedaqError>;
+}
+
+/// Functions missing in older versions of MEDAQLib, which sensors work without
+pub(crate) const OPTIONAL_FUNCTIONS: [&str; 2] = ["SetParameterDouble", "GetParameterInt"];
+
+/// Backend of sensors not configured otherwise, fails if MEDAQLib cannot be loaded
+pub(crate) fn default_backend() -> Result<Arc<dyn Backend>, MedaqError> {
+    Ok(Arc::new(Dll(library::get()?)))
+}
+
+/// The functions of MEDAQLib called by the DLL backend, loaded at runtime or
+/// linked with the `link` feature
+///
+/// The signatures are those of the generated bindings.
+pub(crate) struct Functions {
+    pub(crate) create_sensor_instance: unsafe extern "C" fn(ME_SENSOR) -> u32,
+    pub(crate) release_sensor_instance: unsafe extern "C" fn(u32) -> ERR_CODE,
+    pub(crate) open_sensor: unsafe extern "C" fn(u32) -> ERR_CODE,
+    pub(crate) close_sensor: unsafe extern "C" fn(u32) -> ERR_CODE,
+    pub(crate) set_parameter_string:
+        unsafe extern "C" fn(u32, *const c_char, *const c_char) -> ERR_CODE,
+    pub(crate) set_parameter_int: unsafe extern "C" fn(u32, *const c_char, i32) -> ERR_CODE,
+    pub(crate) get_parameter_string:
+        unsafe extern "C" fn(u32, *const c_char, *mut c_char, *mut u32) -> ERR_CODE,
+    pub(crate) get_parameter_double: unsafe extern "C" fn(u32, *const c_char, *mut f64) -> ERR_CODE,
+    pub(crate) exec_scmd: unsafe extern "C" fn(u32, *const c_char) -> ERR_CODE,
+    pub(crate) get_error: unsafe extern "C" fn(u32, *mut c_char, u32) -> ERR_CODE,
+    pub(crate) data_avail: unsafe extern "C" fn(u32, *mut i32) -> ERR_CODE,
+    pub(crate) transfer_data:
+        unsafe extern "C" fn(u32, *mut i32, *mut f64, i32, *mut i32) -> ERR_CODE,
+    // optional, see `OPTIONAL_FUNCTIONS`
+    pub(crate) set_parameter_double:
+        Option<unsafe extern "C" fn(u32, *const c_char, f64) -> ERR_CODE>,
+    pub(crate) get_parameter_int:
+        Option<unsafe extern "C" fn(u32, *const c_char, *mut i32) -> ERR_CODE>,
+}
+
+/// MEDAQLib loaded on first use, see [`set_library_path`](crate::set_library_path)
+///
+/// Keeps the library loaded until the last sensor using it is dropped, see
+/// [`shutdown`](crate::shutdown).
+struct Dll(Arc<Library>);
+
+impl Dll {
+    fn functions(&self) -> &Functions {
+        self.0
+            .functions
+            .as_ref()
+            .expect("library::get checks that no function is missing")
+    }
+}
+
+impl Debug for Dll {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dll").finish_non_exhaustive()
+    }
+}
+
+impl sealed::Sealed for Dll {}
+
+/// A parameter value, names are passed as [`intern::name`], fails with
+/// [`MedaqError::NulInString`] for a value containing a nul byte
+fn cstring(value: &str) -> Result<CString, MedaqError> {
+    Ok(CString::new(value)?)
+}
+
+/// The optional function, [`MedaqError::UnsupportedByDll`] if MEDAQLib does not
+/// export it
+fn optional<F>(function: Option<F>, name: &'static str) -> Result<F, MedaqError> {
+    function.ok_or(MedaqError::UnsupportedByDll { function: name })
+}
+
+/// Text MEDAQLib wrote into `buffer`, up to the first nul or the whole buffer if it
+/// is not terminated
+pub(crate) fn terminated(buffer: &[u8]) -> &[u8] {
+    let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    &buffer[..len]
+}
+
+/// Value of a buffer filled by `GetParameterString`, invalid UTF-8 is an error
+fn parameter_string(buffer: &[u8]) -> Result<String, MedaqError> {
+    let value = CString::new(terminated(buffer)).expect("buffer is cut at first nul");
+    Ok(value.into_string()?)
+}
+
+impl Backend for Dll {
+    fn create_instance(&self, sensor_type: ME_SENSOR) -> u32 {
+        unsafe { (self.functions().create_sensor_instance)(sensor_type) }
+    }
+
+    fn release_instance(&self, handle: u32) -> Result<(), MedaqError> {
+        unsafe { (self.functions().release_sensor_instance)(handle).into() }
+    }
+
+    fn open_sensor(&self, handle: u32) -> Result<(), MedaqError> {
+        unsafe { (self.functions().open_sensor)(handle).into() }
+    }
+
+    fn close_sensor(&self, handle: u32) -> Result<(), MedaqError> {
+        unsafe { (self.functions().close_sensor)(handle).into() }
+    }
+
+    fn set_parameter_string(&self, handle: u32, name: &str, value: &str) -> Result<(), MedaqError> {
+        let (name, value) = (intern::name(name)?, cstring(value)?);
+        unsafe {
+            (self.functions().set_parameter_string)(handle, name.as_ptr(), value.as_ptr()).into()
+        }
+    }
+
+    fn set_parameter_int(&self, handle: u32, name: &str, value: i32) -> Result<(), MedaqError> {
+        let name = intern::name(name)?;
+        unsafe { (self.functions().set_parameter_int)(handle, name.as_ptr(), value).into() }
+    }
+
+    fn get_parameter_string(&self, handle: u32, name: &str) -> Result<String, MedaqError> {
+        let name = intern::name(name)?;
+        let mut buffer = vec![0u8; PARAMETER_STRING_LEN];
+        let mut max_len = buffer.len() as u32;
+
+        unsafe {
+            (self.functions().get_parameter_string)(
+                handle,
+                name.as_ptr(),
+                buffer.as_mut_ptr() as *mut c_char,
+                &mut max_len,
+            )
+            .to_result()?;
+        }
+
+        parameter_string(&buffer)
+    }
+
+    fn get_parameter_double(&self, handle: u32, name: &str) -> Result<f64, MedaqError> {
+        let name = intern::name(name)?;
+        let mut value = 0.;
+        unsafe {
+            (self.functions().get_parameter_double)(handle, name.as_ptr(), &mut value)
+                .to_result()?;
+        }
+        Ok(value)
+    }
+
+    fn set_parameter_double(&self, handle: u32, name: &str, value: f64) -> Result<(), MedaqError> {
+        let set_parameter_double =
+            optional(self.functions().set_parameter_double, "SetParameterDouble")?;
+        let name = intern::name(name)?;
+        unsafe { set_parameter_double(handle, name.as_ptr(), value).into() }
+    }
+
+    fn get_parameter_int(&self, handle: u32, name: &str) -> Result<i32, MedaqError> {
+        let get_parameter_int = optional(self.functions().get_parameter_int, "GetParameterInt")?;
+        let name = intern::name(name)?;
+        let mut value = 0;
+        unsafe {
+            get_parameter_int(handle, name.as_ptr(), &mut value).to_result()?;
+        }
+        Ok(value)
+    }
+
+    fn exec_scmd(&self, handle: u32, command: &str) -> Result<(), MedaqError> {
+        let command = intern::name(command)?;
+        unsafe { (self.functions().exec_scmd)(handle, command.as_ptr()).into() }
+    }
+
+    fn get_error(&self, handle: u32) -> String {
+        let mut buffer = vec![0u8; PARAMETER_STRING_LEN];
+        let result = unsafe {
+            (self.functions().get_error)(
+                handle,
+                buffer.as_mut_ptr() as *mut c_char,
+                buffer.len() as u32,
+            )
+        };
+        if result.to_result().is_err() {
+            return String::new();
+        }
+        String::from_utf8_lossy(terminated(&buffer)).into_owned()
+    }
+
+    fn dll_version(&self) -> Option<DllVersion> {
+        self.0.version
+    }
+
+    fn data_avail(&self, handle: u32) -> Result<i32, MedaqError> {
+        let mut avail = 0;
+        unsafe {
+            (self.functions().data_avail)(handle, &mut avail).to_result()?;
+        }
+        Ok(avail)
+    }
+
+    fn transfer_data(
+        &self,
+        handle: u32,
+        raw: Option<&mut [i32]>,
+        scaled: Option<&mut [f64]>,
+        max_values: usize,
+    ) -> Result<usize, MedaqError> {
+        assert!(raw.as_ref().is_none_or(|raw| raw.len() >= max_values));
+        assert!(scaled
+            .as_ref()
+            .is_none_or(|scaled| scaled.len() >= max_values));
+        let raw_ptr = raw.map_or(ptr::null_mut(), |raw| raw.as_mut_ptr());
+        let scaled_ptr = scaled.map_or(ptr::null_mut(), |scaled| scaled.as_mut_ptr());
+        let mut read = 0;
+
+        unsafe {
+            // both buffers hold at least `max_values` values
+            (self.functions().transfer_data)(
+                handle,
+                raw_ptr,
+                scaled_ptr,
+                max_values as i32,
+                &mut read,
+            )
+            .to_result()?;
+        }
+        Ok(read.max(0) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cstring, parameter_string, terminated, PARAMETER_STRING_LEN};
+    use crate::MedaqError;
+
+    #[test]
+    fn test_parameter_string_test() {
+        assert_eq!(parameter_string(&[0; 8]).unwrap(), "");
+        assert_eq!(parameter_string(&[]).unwrap(), "");
+        assert_eq!(
+            parameter_string(b"Distance\0garbage\0").unwrap(),
+            "Distance"
+        );
+        assert_eq!(
+            parameter_string("Dist\u{1}\t\r\n\u{7f}µm\0".as_bytes()).unwrap(),
+            "Dist\u{1}\t\r\n\u{7f}µm"
+        );
+        assert!(matches!(
+            parameter_string(b"Dist\xff\0"),
+            Err(MedaqError::InvalidString(_))
+        ));
+        // a cut multi byte character
+        assert!(matches!(
+            parameter_string(&"µ".as_bytes()[..1]),
+            Err(MedaqError::InvalidString(_))
+        ));
+    }
+
+    #[test]
+    fn test_cstring_nul_test() {
+        assert_eq!(cstring("TCP/IP").unwrap().as_bytes(), b"TCP/IP");
+        assert!(matches!(
+            cstring("a\0b"),
+            Err(MedaqError::NulInString(err)) if err.nul_position() == 1
+        ));
+    }
+
+    #[test]
+    fn test_parameter_string_unterminated_test() {
+        let buffer = vec![b'a'; PARAMETER_STRING_LEN];
+        assert_eq!(
+            parameter_string(&buffer).unwrap().len(),
+            PARAMETER_STRING_LEN
+        );
+        assert_eq!(terminated(&buffer).len(), PARAMETER_STRING_LEN);
+    }
+
+    #[test]
+    fn test_parameter_string_all_short_buffers_test() {
+        // every buffer of up to two bytes decodes or fails, always like from_utf8
+        for first in 0..=u8::MAX {
+            for buffer in (0..=u8::MAX).map(|second| [first, second]) {
+                let text = terminated(&buffer);
+                match parameter_string(&buffer) {
+                    Ok(value) => assert_eq!(value.as_bytes(), text),
+                    Err(err) => {
+                        assert!(std::str::from_utf8(text).is_err(), "{buffer:?}: {err}");
+                    }
+                }
+                assert_eq!(parameter_string(&buffer[..1]).is_ok(), first < 0x80);
+            }
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{
+    acquisition::{Control, Deliver},
+    Data, MedaqError,
+};
+
+/// Combine successive captures before delivering them, see
+/// [`AcquisitionConfig::aggregate_every`](crate::AcquisitionConfig::aggregate_every)
+///
+/// A batch is emitted once its interval elapsed or it holds at least `frames`
+/// frames, whichever comes first. Captures are never split, so a batch may exceed
+/// `frames`. Without either limit every capture is emitted on its own.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+///
+/// use medaqlib::{AcquisitionConfig, BatchConfig};
+///
+/// let config = AcquisitionConfig {
+///     aggregate_every: Some(BatchConfig {
+///         interval: Some(Duration::from_millis(100)),
+///         frames: Some(10_000),
+///     }),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BatchConfig {
+    pub interval: Option<Duration>,
+    pub frames: Option<usize>,
+}
+
+impl Data {
+    /// Append the frames of `other`, keeping the metadata and timestamp of `self`
+    ///
+    /// Raw or scaled values are discarded unless both captures hold them for
+    /// every frame.
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::Data;
+    ///
+    /// let mut data = Data::new(vec!["Distance".to_string()], vec![1], vec![0.1]);
+    /// data.append(&Data::new(vec!["Distance".to_string()], vec![2], vec![0.2]))
+    ///     .unwrap();
+    /// assert_eq!(data.raw_data, vec![1, 2]);
+    /// assert_eq!(data.scaled_data, vec![0.1, 0.2]);
+    /// ```
+    pub fn append(&mut self, other: &Data) -> Result<(), MedaqError> {
+        if self.channels() != other.channels() {
+            return Err(MedaqError::ChannelMismatch {
+                expected: self.channels().to_vec(),
+                found: other.channels().to_vec(),
+            });
+        }
+        let values = |data: &Data| data.frame_count() * data.channel_count();
+        let (own, appended) = (values(self), values(other));
+
+        match self.raw_data.len() == own && other.raw_data.len() == appended {
+            true => self.raw_data.extend_from_slice(&other.raw_data),
+            false => self.raw_data.clear(),
+        }
+        match self.scaled_data.len() == own && other.scaled_data.len() == appended {
+            true => self.scaled_data.extend_from_slice(&other.scaled_data),
+            false => self.scaled_data.clear(),
+        }
+        if self.channel_info.is_none() {
+            self.channel_info = other.channel_info.clone();
+        }
+        Ok(())
+    }
+}
+
+/// Captures collected since the start of the current batch
+#[derive(Debug)]
+struct Batch {
+    data: Data,
+    started: Instant,
+}
+
+/// Collects captures into batches in front of a [`Deliver`], or passes them on
+/// unchanged if batching is disabled
+pub(crate) struct Batched<D> {
+    config: Option<BatchConfig>,
+    batch: Option<Batch>,
+    inner: D,
+}
+
+impl<D: Deliver> Batched<D> {
+    pub(crate) fn new(config: Option<BatchConfig>, inner: D) -> Self {
+        Self {
+            config,
+            batch: None,
+            inner,
+        }
+    }
+
+    /// Hand over a result, `false` ends the acquisition
+    ///
+    /// Read errors are delivered right away, after the batch collected so far.
+    pub(crate) fn deliver(&mut self, result: Result<Data, MedaqError>, control: &Control) -> bool {
+        if self.config.is_none() {
+            return self.inner.deliver(result, control);
+        }
+        let data = match result {
+            Ok(data) => data,
+            Err(err) => return self.finish(control) && self.inner.deliver(Err(err), control),
+        };
+
+        let appended = match &mut self.batch {
+            Some(batch) => batch.data.append(&data).is_ok(),
+            None => false,
+        };
+        if !appended {
+            // the channels changed, the batch ends early
+            if !self.finish(control) {
+                return false;
+            }
+            let timestamp = data.timestamp().unwrap_or_else(SystemTime::now);
+            self.batch = Some(Batch {
+                data: data.with_timestamp(timestamp),
+                started: Instant::now(),
+            });
+        }
+        self.idle(control)
+    }
+
+    /// Deliver the batch if it is complete, called while there is no new data
+    pub(crate) fn idle(&mut self, control: &Control) -> bool {
+        let (Some(config), Some(batch)) = (&self.config, &self.batch) else {
+            return true;
+        };
+        let full = config
+            .frames
+            .is_none_or(|frames| batch.data.frame_count() >= frames);
+        let elapsed = config
+            .interval
+            .is_none_or(|interval| batch.started.elapsed() >= interval);
+        let due = match (config.interval, config.frames) {
+            (Some(_), Some(_)) => full || elapsed,
+            _ => full && elapsed,
+        };
+        match due {
+            true => self.finish(control),
+            false => true,
+        }
+    }
+
+    /// Deliver the batch collected so far, even if incomplete
+    pub(crate) fn finish(&mut self, control: &Control) -> bool {
+        match self.batch.take() {
+            Some(Batch { data, started }) => self
+                .inner
+                .deliver(Ok(data.with_span(started.elapsed())), control),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::{
+        acquisition::{
+            tests::{data, Script},
+            Acquisition,
+        },
+        AcquisitionConfig, BatchConfig, ChannelInfo, Data, MedaqError,
+    };
+
+    fn frames(values: std::ops::Range<i32>) -> Data {
+        let raw_data: Vec<_> = values.collect();
+        let scaled_data = raw_data.iter().map(|&v| v as f64).collect();
+        Data::new(vec!["Distance".to_string()], raw_data, scaled_data)
+    }
+
+    fn config(aggregate_every: BatchConfig) -> AcquisitionConfig {
+        AcquisitionConfig {
+            poll_interval: Duration::from_millis(1),
+            aggregate_every: Some(aggregate_every),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_append_test() {
+        let mut data = frames(0..2).with_timestamp(UNIX_EPOCH);
+        data.append(&frames(2..3).with_channel_info(vec![ChannelInfo::new("Distance")]))
+            .unwrap();
+        assert_eq!(data.raw_data, vec![0, 1, 2]);
+        assert_eq!(data.scaled_data, vec![0., 1., 2.]);
+        assert_eq!(data.timestamp(), Some(UNIX_EPOCH));
+        assert!(data.channel_info().is_some());
+
+        // the second capture has no raw values
+        data.append(&self::data(3.)).unwrap();
+        assert!(data.raw_data.is_empty());
+        assert_eq!(data.scaled_data, vec![0., 1., 2., 3.]);
+
+        let other = Data::new(vec!["Intensity".to_string()], vec![], vec![1.]);
+        assert!(matches!(
+            data.append(&other),
+            Err(MedaqError::ChannelMismatch { .. })
+        ));
+        assert_eq!(data.frame_count(), 4);
+    }
+
+    #[test]
+    fn test_aggregate_every_frames_test() {
+        let results = (0..10)
+            .map(|i| Ok(Some(frames(2 * i..2 * i + 2))))
+            .collect();
+        let config = config(BatchConfig {
+            interval: None,
+            frames: Some(5),
+        });
+        let (mut acquisition, receiver) = Acquisition::with_channel(Script::new(results), config);
+
+        let mut batches: Vec<_> = receiver.iter().take(3).map(Result::unwrap).collect();
+        acquisition.stop();
+        // the partial batch is delivered on stop
+        batches.extend(receiver.iter().map(Result::unwrap));
+        acquisition.join();
+
+        let sizes: Vec<_> = batches.iter().map(Data::frame_count).collect();
+        assert_eq!(sizes, vec![6, 6, 6, 2]);
+        let raw: Vec<_> = batches.iter().flat_map(|b| b.raw_data.clone()).collect();
+        assert_eq!(raw, (0..20).collect::<Vec<_>>());
+        assert!(batches.iter().all(|batch| batch.span().is_some()));
+    }
+
+    #[test]
+    fn test_aggregate_every_interval_test() {
+        let at = |second| UNIX_EPOCH + Duration::from_secs(second);
+        let script = Script::new(vec![
+            Ok(Some(frames(0..1).with_timestamp(at(10)))),
+            Ok(Some(frames(1..3).with_timestamp(at(11)))),
+        ]);
+        let config = config(BatchConfig {
+            interval: Some(Duration::from_millis(20)),
+            frames: Some(100),
+        });
+        let (mut acquisition, receiver) = Acquisition::with_channel(script, config);
+
+        // delivered without further captures once the interval elapsed
+        let batch = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(batch.raw_data, vec![0, 1, 2]);
+        assert_eq!(batch.timestamp(), Some(at(10)));
+        assert!(batch.span().unwrap() >= Duration::from_millis(20));
+        acquisition.join();
+    }
+
+    #[test]
+    fn test_aggregate_every_errors_and_channel_changes_test() {
+        let other = |value| Data::new(vec!["Intensity".to_string()], vec![], vec![value]);
+        let script = Script::new(vec![
+            Ok(Some(data(1.))),
+            Ok(Some(data(2.))),
+            Err(MedaqError::NoChannels),
+            Ok(Some(data(3.))),
+            Ok(Some(other(4.))),
+            Ok(Some(other(5.))),
+        ]);
+        let config = config(BatchConfig {
+            interval: Some(Duration::from_secs(60)),
+            frames: None,
+        });
+        let (mut acquisition, receiver) = Acquisition::with_channel(script, config);
+
+        let mut received: Vec<_> = receiver.iter().take(3).collect();
+        acquisition.stop();
+        received.extend(receiver.iter());
+        acquisition.join();
+
+        assert_eq!(received.len(), 4);
+        assert_eq!(received[0].as_ref().unwrap().scaled_data, vec![1., 2.]);
+        assert!(matches!(received[1], Err(MedaqError::NoChannels)));
+        assert_eq!(received[2].as_ref().unwrap().scaled_data, vec![3.]);
+        let last = received[3].as_ref().unwrap();
+        assert_eq!(
+            (last.channels(), &last.scaled_data[..]),
+            (&["Intensity".to_string()][..], &[4., 5.][..])
+        );
+    }
+}
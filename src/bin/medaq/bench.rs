@@ -0,0 +1,185 @@
+//! `medaq bench`, how fast the sensor can be drained
+
+use std::{
+    fmt::Display,
+    io::{self, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use medaqlib::{Data, Sensor};
+
+use crate::{parse_duration, stop_token, Connection, Failure, Format};
+
+#[derive(Args)]
+pub(crate) struct Bench {
+    /// How long to read, e.g. 10s
+    #[arg(long, value_parser = parse_duration, default_value = "10s")]
+    duration: Duration,
+    /// Most values transferred per read, rounded down to whole frames
+    #[arg(long, default_value_t = 65536)]
+    chunk: usize,
+    /// Print a JSON object instead of text, like --format json
+    #[arg(long)]
+    json: bool,
+}
+
+pub(crate) fn bench(connection: &Connection, bench: &Bench, format: Format) -> Result<(), Failure> {
+    let sensor = connection.connect()?;
+    let report = run(&sensor, bench)?;
+    sensor.close();
+    match format == Format::Text && !bench.json {
+        true => writeln!(io::stdout(), "{report}")?,
+        false => writeln!(io::stdout(), "{}", report.json())?,
+    }
+    Ok(())
+}
+
+/// Read through the capped path until the duration passed or Ctrl-C
+fn run(sensor: &Sensor, bench: &Bench) -> Result<Report, Failure> {
+    let channels = sensor.parameters().len().max(1);
+    let values = (bench.chunk / channels).max(1) * channels;
+    let (mut raw, mut scaled) = (vec![0; values], vec![0.; values]);
+    // only used to classify the values
+    let mut frames = Data::new(sensor.parameters().to_vec(), vec![], vec![]);
+    let mut latencies = vec![];
+    let mut invalid_samples = 0;
+
+    let token = stop_token(Some(bench.duration));
+    let start = Instant::now();
+    while !token.is_cancelled() {
+        let before = Instant::now();
+        let read = sensor
+            .read_into_slices(Some(&mut raw), Some(&mut scaled))
+            .map_err(Failure::Read)?;
+        if read == 0 {
+            thread::yield_now();
+            continue;
+        }
+        latencies.push(before.elapsed());
+        frames.scaled_data.clear();
+        frames
+            .scaled_data
+            .extend_from_slice(&scaled[..read * channels]);
+        invalid_samples += frames.summary().invalid_samples;
+    }
+
+    let stats = sensor.stats();
+    Ok(Report::new(
+        start.elapsed(),
+        stats.frames_read,
+        channels,
+        latencies,
+        stats.peak_backlog,
+        invalid_samples,
+    ))
+}
+
+/// Results of a benchmark
+#[derive(Debug, PartialEq)]
+struct Report {
+    elapsed: Duration,
+    frames: u64,
+    frames_per_second: f64,
+    values_per_second: f64,
+    /// Mean duration of the reads that returned frames
+    mean_latency: Duration,
+    /// 99th percentile of the same reads
+    p99_latency: Duration,
+    /// Largest backlog seen, in frames
+    peak_backlog: usize,
+    invalid_samples: usize,
+}
+
+impl Report {
+    fn new(
+        elapsed: Duration,
+        frames: u64,
+        channels: usize,
+        mut latencies: Vec<Duration>,
+        peak_backlog: usize,
+        invalid_samples: usize,
+    ) -> Self {
+        let seconds = elapsed.as_secs_f64().max(f64::MIN_POSITIVE);
+        latencies.sort_unstable();
+        let mean_latency = match latencies.len() {
+            0 => Duration::ZERO,
+            reads => latencies.iter().sum::<Duration>() / reads as u32,
+        };
+        // nearest rank
+        let p99_index = (latencies.len() * 99).div_ceil(100).saturating_sub(1);
+        Self {
+            elapsed,
+            frames,
+            frames_per_second: frames as f64 / seconds,
+            values_per_second: (frames * channels as u64) as f64 / seconds,
+            mean_latency,
+            p99_latency: latencies.get(p99_index).copied().unwrap_or_default(),
+            peak_backlog,
+            invalid_samples,
+        }
+    }
+
+    fn json(&self) -> String {
+        format!(
+            "{{\"seconds\":{:.3},\"frames\":{},\"frames_per_second\":{:.1},\
+             \"values_per_second\":{:.1},\"mean_latency_us\":{},\"p99_latency_us\":{},\
+             \"peak_backlog\":{},\"invalid_samples\":{}}}",
+            self.elapsed.as_secs_f64(),
+            self.frames,
+            self.frames_per_second,
+            self.values_per_second,
+            self.mean_latency.as_micros(),
+            self.p99_latency.as_micros(),
+            self.peak_backlog,
+            self.invalid_samples
+        )
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} frames in {:.3} s",
+            self.frames,
+            self.elapsed.as_secs_f64()
+        )?;
+        writeln!(f, "frames/s:        {:.1}", self.frames_per_second)?;
+        writeln!(f, "values/s:        {:.1}", self.values_per_second)?;
+        writeln!(f, "mean latency:    {:?}", self.mean_latency)?;
+        writeln!(f, "p99 latency:     {:?}", self.p99_latency)?;
+        writeln!(f, "peak backlog:    {} frames", self.peak_backlog)?;
+        write!(f, "invalid samples: {}", self.invalid_samples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Report;
+
+    #[test]
+    fn test_report_test() {
+        let latencies = (1..=200).rev().map(Duration::from_micros).collect();
+        let report = Report::new(Duration::from_secs(2), 1000, 2, latencies, 7, 3);
+        assert_eq!(report.frames_per_second, 500.);
+        assert_eq!(report.values_per_second, 1000.);
+        assert_eq!(report.mean_latency, Duration::from_nanos(100_500));
+        assert_eq!(report.p99_latency, Duration::from_micros(198));
+        assert_eq!(
+            report.json(),
+            "{\"seconds\":2.000,\"frames\":1000,\"frames_per_second\":500.0,\
+             \"values_per_second\":1000.0,\"mean_latency_us\":100,\"p99_latency_us\":198,\
+             \"peak_backlog\":7,\"invalid_samples\":3}"
+        );
+
+        let empty = Report::new(Duration::from_secs(1), 0, 1, vec![], 0, 0);
+        assert_eq!(
+            (empty.mean_latency, empty.p99_latency),
+            (Duration::ZERO, Duration::ZERO)
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! `medaq.toml`, named profiles of the connection options, e.g.
+//!
+//! ```toml
+//! [profiles.cell3]
+//! sensor = "IFD2421"
+//! interface = "tcp"
+//! ip = "10.0.3.5"
+//! port = 10001
+//! log = true
+//!
+//! # several sensors for `record-multi`, options not given are those of the profile
+//! [profiles.thickness]
+//! sensor = "IFD2421"
+//! interface = "tcp"
+//!
+//! [[profiles.thickness.sensors]]
+//! name = "top"
+//! ip = "10.0.4.5"
+//!
+//! [[profiles.thickness.sensors]]
+//! name = "bottom"
+//! ip = "10.0.4.6"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::Subcommand;
+use medaqlib::{Interface, ME_SENSOR};
+use toml::{Table, Value};
+
+use crate::{
+    params::{object, quoted},
+    Connection, Failure, Format,
+};
+
+/// Searched in the working directory, then in `medaq` of the user config directory
+const FILE_NAME: &str = "medaq.toml";
+
+#[derive(Subcommand)]
+pub(crate) enum Config {
+    /// Check every profile of the file, or the one given with --profile, without
+    /// connecting
+    Check,
+}
+
+/// Connection options of a profile or the command line, unset ones fall back to the
+/// next source
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Profile {
+    pub(crate) sensor: Option<ME_SENSOR>,
+    pub(crate) interface: Option<Interface>,
+    pub(crate) ip: Option<String>,
+    pub(crate) port: Option<u16>,
+    pub(crate) log: Option<bool>,
+    /// Named sensors of the profile, empty for a single sensor
+    pub(crate) sensors: Vec<(String, Profile)>,
+}
+
+impl Profile {
+    fn parse(table: &Table) -> Result<Self, String> {
+        let mut profile = Profile::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "sensor" => profile.sensor = Some(parsed(key, value)?),
+                "interface" => profile.interface = Some(parsed(key, value)?),
+                "ip" => profile.ip = Some(text(key, value)?.into()),
+                "port" => {
+                    let port = value.as_integer().and_then(|port| port.try_into().ok());
+                    profile.port = Some(port.ok_or_else(|| format!("{key} is no port"))?);
+                }
+                "log" => {
+                    let log = value.as_bool();
+                    profile.log = Some(log.ok_or_else(|| format!("{key} is no boolean"))?);
+                }
+                "sensors" => profile.sensors = members(value)?,
+                _ => return Err(format!("unknown option {key}")),
+            }
+        }
+        Ok(profile)
+    }
+
+    /// Options of `self`, those missing taken from `fallback`
+    pub(crate) fn or(&self, fallback: &Profile) -> Profile {
+        Profile {
+            sensor: self.sensor.or(fallback.sensor),
+            interface: self.interface.or(fallback.interface),
+            ip: self.ip.clone().or_else(|| fallback.ip.clone()),
+            port: self.port.or(fallback.port),
+            log: self.log.or(fallback.log),
+            sensors: match self.sensors.is_empty() {
+                true => fallback.sensors.clone(),
+                false => self.sensors.clone(),
+            },
+        }
+    }
+
+    /// Options of every sensor of the profile: `flags` over those of the sensor over
+    /// those of the profile
+    pub(crate) fn members(&self, flags: &Profile) -> Vec<(String, Profile)> {
+        let shared = Profile {
+            sensors: vec![],
+            ..self.clone()
+        };
+        self.sensors
+            .iter()
+            .map(|(name, sensor)| (name.clone(), flags.or(&sensor.or(&shared))))
+            .collect()
+    }
+}
+
+/// `[[profiles.NAME.sensors]]`, each with a unique name and the options of a profile
+fn members(value: &Value) -> Result<Vec<(String, Profile)>, String> {
+    let tables = value.as_array().ok_or("sensors is no array of tables")?;
+    let mut members: Vec<(String, Profile)> = vec![];
+    for table in tables {
+        let mut table = table
+            .as_table()
+            .ok_or("sensors is no array of tables")?
+            .clone();
+        let name = match table.remove("name") {
+            Some(Value::String(name)) => name,
+            _ => return Err("every sensor needs a name".into()),
+        };
+        if members.iter().any(|(other, _)| *other == name) {
+            return Err(format!("sensor {name} is listed twice"));
+        }
+        if table.contains_key("sensors") {
+            return Err(format!("sensor {name} has sensors"));
+        }
+        let profile = Profile::parse(&table).map_err(|err| format!("sensor {name}: {err}"))?;
+        members.push((name, profile));
+    }
+    Ok(members)
+}
+
+fn text<'a>(key: &str, value: &'a Value) -> Result<&'a str, String> {
+    value.as_str().ok_or_else(|| format!("{key} is no string"))
+}
+
+fn parsed<T>(key: &str, value: &Value) -> Result<T, String>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    text(key, value)?
+        .parse()
+        .map_err(|err| format!("invalid {key}: {err}"))
+}
+
+/// Profiles by name read from a file
+pub(crate) struct File {
+    pub(crate) path: PathBuf,
+    pub(crate) profiles: BTreeMap<String, Profile>,
+}
+
+impl File {
+    /// Read `path`, or the first file found in the default locations without
+    pub(crate) fn load(path: Option<&Path>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => default_paths()
+                .into_iter()
+                .find(|path| path.is_file())
+                .ok_or_else(|| format!("no {FILE_NAME} found, pass its path with --config"))?,
+        };
+        let text = fs::read_to_string(&path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?;
+        let profiles = parse(&text).map_err(|err| format!("{}: {err}", path.display()))?;
+        Ok(File { path, profiles })
+    }
+
+    pub(crate) fn profile(&self, name: &str) -> Result<&Profile, String> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| format!("{} has no profile {name}", self.path.display()))
+    }
+}
+
+fn parse(text: &str) -> Result<BTreeMap<String, Profile>, String> {
+    let table: Table = text
+        .parse()
+        .map_err(|err: toml::de::Error| err.to_string())?;
+    let mut profiles = BTreeMap::new();
+    for (key, value) in table {
+        if key != "profiles" {
+            return Err(format!("unknown table {key}, profiles are [profiles.NAME]"));
+        }
+        let Value::Table(tables) = value else {
+            return Err("profiles is no table".into());
+        };
+        for (name, table) in tables {
+            let Value::Table(table) = table else {
+                return Err(format!("profile {name} is no table"));
+            };
+            let profile = Profile::parse(&table).map_err(|err| format!("profile {name}: {err}"))?;
+            profiles.insert(name, profile);
+        }
+    }
+    Ok(profiles)
+}
+
+fn default_paths() -> Vec<PathBuf> {
+    let config_dir = user_config_dir().map(|dir| dir.join("medaq").join(FILE_NAME));
+    [Some(PathBuf::from(FILE_NAME)), config_dir]
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// `%APPDATA%` on Windows, `$XDG_CONFIG_HOME` or `~/.config` elsewhere
+fn user_config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return env::var_os("APPDATA").map(PathBuf::from);
+    }
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+pub(crate) fn config(
+    connection: &Connection,
+    config: &Config,
+    format: Format,
+) -> Result<(), Failure> {
+    let Config::Check = config;
+    let file = File::load(connection.config.as_deref()).map_err(Failure::Config)?;
+    let profiles: Vec<(&str, &Profile)> = match &connection.profile {
+        Some(name) => vec![(name, file.profile(name).map_err(Failure::Config)?)],
+        None => file
+            .profiles
+            .iter()
+            .map(|(name, profile)| (name.as_str(), profile))
+            .collect(),
+    };
+    // what is given on the command line is checked as well, and every sensor of a
+    // profile listing several
+    let flags = connection.flags();
+    let results: Vec<_> = profiles
+        .into_iter()
+        .flat_map(|(name, profile)| match profile.sensors.is_empty() {
+            true => vec![(name.to_string(), flags.or(profile))],
+            false => profile
+                .members(&flags)
+                .into_iter()
+                .map(|(sensor, options)| (format!("{name}.{sensor}"), options))
+                .collect(),
+        })
+        .map(|(name, options)| {
+            let result = crate::builder(&options)
+                .and_then(|builder| builder.validate().map_err(|err| err.to_string()));
+            (name, result)
+        })
+        .collect();
+
+    if format == Format::Text {
+        for (name, result) in &results {
+            match result {
+                Ok(()) => writeln!(io::stdout(), "{name}: ok")?,
+                Err(err) => writeln!(io::stdout(), "{name}: {err}")?,
+            }
+        }
+    } else {
+        let entries = results.iter().map(|(name, result)| {
+            let value = result
+                .as_ref()
+                .err()
+                .map_or("null".into(), |err| quoted(err));
+            (name.clone(), value)
+        });
+        io::stdout().write_all(object(entries, format.compact()).as_bytes())?;
+    }
+    match results.iter().filter(|(_, result)| result.is_err()).count() {
+        0 => Ok(()),
+        invalid => Err(Failure::Config(format!(
+            "{invalid} of {} profiles in {} are invalid",
+            results.len(),
+            file.path.display()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use medaqlib::{Interface, ME_SENSOR};
+
+    use super::parse;
+    use crate::Cli;
+
+    const FILE: &str = r#"
+        [profiles.cell3]
+        sensor = "IFD2421"
+        interface = "tcp"
+        ip = "10.0.3.5"
+        port = 10001
+
+        [profiles.bench]
+        interface = "simulated"
+        log = true
+
+        [profiles.thickness]
+        sensor = "IFD2421"
+        interface = "tcp"
+        port = 10001
+
+        [[profiles.thickness.sensors]]
+        name = "top"
+        ip = "10.0.4.5"
+
+        [[profiles.thickness.sensors]]
+        name = "bottom"
+        ip = "10.0.4.6"
+        port = 10002
+    "#;
+
+    #[test]
+    fn test_parse_test() {
+        let profiles = parse(FILE).unwrap();
+        assert_eq!(
+            profiles.keys().collect::<Vec<_>>(),
+            ["bench", "cell3", "thickness"]
+        );
+        let cell3 = &profiles["cell3"];
+        assert_eq!(cell3.sensor, Some(ME_SENSOR::SENSOR_IFD2421));
+        assert!(matches!(cell3.interface, Some(Interface::TcpIp)));
+        assert_eq!(cell3.ip.as_deref(), Some("10.0.3.5"));
+        assert_eq!((cell3.port, cell3.log), (Some(10001), None));
+        assert_eq!(profiles["bench"].log, Some(true));
+
+        let err = |text| parse(text).err().unwrap();
+        assert_eq!(
+            err("[profiles.a]\nsensor = \"IFD9999\""),
+            "profile a: invalid sensor: unknown sensor type \"IFD9999\""
+        );
+        assert_eq!(
+            err("[profiles.a]\nport = 70000"),
+            "profile a: port is no port"
+        );
+        assert_eq!(
+            err("[profiles.a]\nspeed = 1"),
+            "profile a: unknown option speed"
+        );
+        assert!(err("[sensors.a]").starts_with("unknown table sensors"));
+        assert_eq!(
+            err("[[profiles.a.sensors]]\nip = \"10.0.0.1\""),
+            "profile a: every sensor needs a name"
+        );
+        assert_eq!(
+            err("[[profiles.a.sensors]]\nname = \"b\"\n[[profiles.a.sensors]]\nname = \"b\""),
+            "profile a: sensor b is listed twice"
+        );
+        assert!(err("[profiles.a").contains("TOML parse error"));
+    }
+
+    #[test]
+    fn test_precedence_test() {
+        let profiles = parse(FILE).unwrap();
+        let cli = Cli::try_parse_from(["medaq", "--ip", "10.0.0.7", "--profile", "cell3", "read"])
+            .unwrap();
+        let merged = cli.connection.flags().or(&profiles["cell3"]);
+        // the flag overrides the profile, which sets what is not given as a flag
+        assert_eq!(merged.ip.as_deref(), Some("10.0.0.7"));
+        assert_eq!(merged.port, Some(10001));
+        assert!(matches!(merged.interface, Some(Interface::TcpIp)));
+        assert_eq!(merged.sensor, Some(ME_SENSOR::SENSOR_IFD2421));
+
+        let cli = Cli::try_parse_from(["medaq", "--interface", "IF2008", "--log", "read"]).unwrap();
+        let merged = cli.connection.flags().or(&profiles["bench"]);
+        assert!(matches!(merged.interface, Some(Interface::If2008)));
+        assert_eq!(merged.log, Some(true));
+
+        // without either the defaults of `builder` apply
+        let cli = Cli::try_parse_from(["medaq", "read"]).unwrap();
+        let merged = cli.connection.flags().or(&Default::default());
+        assert!(merged.interface.is_none() && merged.log.is_none());
+        assert!(crate::builder(&merged).is_err());
+        let with_sensor = super::Profile {
+            sensor: Some(ME_SENSOR::SENSOR_IFD2421),
+            ..merged
+        };
+        assert!(matches!(
+            crate::builder(&with_sensor).map(|builder| builder.validate()),
+            Ok(Err(medaqlib::MedaqError::MissingIpAddress))
+        ));
+    }
+
+    #[test]
+    fn test_members_test() {
+        let profiles = parse(FILE).unwrap();
+        let flags = Cli::try_parse_from(["medaq", "--port", "9999", "read"])
+            .unwrap()
+            .connection
+            .flags();
+        let members = profiles["thickness"].members(&flags);
+        let names: Vec<_> = members.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["top", "bottom"]);
+        let (top, bottom) = (&members[0].1, &members[1].1);
+        assert_eq!(top.ip.as_deref(), Some("10.0.4.5"));
+        assert_eq!(top.sensor, Some(ME_SENSOR::SENSOR_IFD2421));
+        assert_eq!(bottom.ip.as_deref(), Some("10.0.4.6"));
+        // the flag over the sensor over the profile
+        assert_eq!((top.port, bottom.port), (Some(9999), Some(9999)));
+        let members = profiles["thickness"].members(&Default::default());
+        assert_eq!(
+            (members[0].1.port, members[1].1.port),
+            (Some(10001), Some(10002))
+        );
+        assert!(profiles["cell3"].members(&flags).is_empty());
+    }
+}
@@ -0,0 +1,339 @@
+//! `medaq convert`, binary recordings streamed into another format
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, ValueEnum};
+use medaqlib::{BinaryReader, BinaryRecorder, ChannelSummary, CsvOptions, CsvWriter, Data, Value};
+#[cfg(feature = "serde")]
+use medaqlib::{JsonlOptions, JsonlRecorder};
+
+use crate::{Failure, Format};
+
+#[derive(Args)]
+pub(crate) struct Convert {
+    /// Binary recording to read
+    input: PathBuf,
+    /// Format to write
+    #[arg(long, value_enum)]
+    to: Target,
+    /// File to write
+    #[arg(long)]
+    out: PathBuf,
+    /// Replace the output if it exists
+    #[arg(long)]
+    force: bool,
+    /// Keep only every Nth frame
+    #[arg(long, value_name = "N", default_value = "1")]
+    downsample: NonZeroUsize,
+    /// Separator between the columns of csv
+    #[arg(long, default_value_t = ',')]
+    delimiter: char,
+    /// Write 1,5 instead of 1.5 to csv
+    #[arg(long)]
+    decimal_comma: bool,
+    /// Text written to csv for out of range values
+    #[arg(long, default_value = "")]
+    out_of_range: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Target {
+    /// Scaled values, see `medaq record`
+    Csv,
+    /// A JSON object per frame
+    #[cfg(feature = "serde")]
+    Jsonl,
+    /// Binary recording, e.g. to downsample
+    Bin,
+}
+
+pub(crate) fn convert(convert: &Convert, format: Format) -> Result<(), Failure> {
+    let input = File::open(&convert.input).map_err(Failure::Input)?;
+    let mut writer = Writer::new(convert, create(&convert.out, convert.force)?);
+    let mut downsample = Downsample::new(convert.downsample);
+    let mut totals = Totals::default();
+
+    for data in BinaryReader::new(BufReader::new(input)) {
+        let data = downsample.apply(data.map_err(Failure::Input)?);
+        totals.add(&data).map_err(Failure::Input)?;
+        writer.write(&data)?;
+    }
+    writer.finish()?;
+
+    let channels = totals.summaries();
+    let mut out = io::stdout().lock();
+    match format {
+        Format::Text => {
+            writeln!(
+                out,
+                "{} frames of {} captures written to {}",
+                totals.frames,
+                totals.captures,
+                convert.out.display()
+            )?;
+            for channel in channels {
+                writeln!(out, "{channel}")?;
+            }
+        }
+        #[cfg(feature = "serde")]
+        Format::Json | Format::Jsonl => {
+            #[derive(serde::Serialize)]
+            struct Report {
+                frames: u64,
+                captures: u64,
+                channels: Vec<ChannelSummary>,
+            }
+
+            let report = Report {
+                frames: totals.frames,
+                captures: totals.captures,
+                channels,
+            };
+            let json = match format.compact() {
+                true => serde_json::to_string(&report),
+                false => serde_json::to_string_pretty(&report),
+            };
+            writeln!(out, "{}", json.expect("reports serialize"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Create `path`, failing if it exists unless `force`
+fn create(path: &Path, force: bool) -> io::Result<File> {
+    if force {
+        return File::create(path);
+    }
+    File::create_new(path).map_err(|err| match err.kind() {
+        io::ErrorKind::AlreadyExists => io::Error::new(
+            err.kind(),
+            format!("{} exists, replace it with --force", path.display()),
+        ),
+        _ => err,
+    })
+}
+
+/// Exporter of the target format
+enum Writer {
+    Csv(CsvWriter<BufWriter<File>>),
+    #[cfg(feature = "serde")]
+    Jsonl(JsonlRecorder<BufWriter<File>>),
+    Bin(BinaryRecorder<BufWriter<File>>),
+}
+
+impl Writer {
+    fn new(convert: &Convert, file: File) -> Self {
+        let file = BufWriter::new(file);
+        match convert.to {
+            Target::Csv => Writer::Csv(CsvWriter::new(
+                file,
+                CsvOptions {
+                    delimiter: convert.delimiter,
+                    decimal_comma: convert.decimal_comma,
+                    out_of_range: convert.out_of_range.clone(),
+                    ..CsvOptions::default()
+                },
+            )),
+            #[cfg(feature = "serde")]
+            Target::Jsonl => Writer::Jsonl(JsonlRecorder::new(file, JsonlOptions::default())),
+            Target::Bin => Writer::Bin(BinaryRecorder::new(file)),
+        }
+    }
+
+    fn write(&mut self, data: &Data) -> io::Result<()> {
+        match self {
+            Writer::Csv(csv) => csv.write(data),
+            #[cfg(feature = "serde")]
+            Writer::Jsonl(jsonl) => jsonl.record(data),
+            Writer::Bin(bin) => bin.record(data),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Writer::Csv(csv) => csv.into_inner().map(drop),
+            #[cfg(feature = "serde")]
+            Writer::Jsonl(jsonl) => jsonl.into_inner().map(drop),
+            Writer::Bin(bin) => bin.into_inner().map(drop),
+        }
+    }
+}
+
+/// Keeps every nth frame, counted over all captures
+struct Downsample {
+    factor: usize,
+    /// Frames to drop at the start of the next capture
+    skip: usize,
+}
+
+impl Downsample {
+    fn new(factor: NonZeroUsize) -> Self {
+        Self {
+            factor: factor.get(),
+            skip: 0,
+        }
+    }
+
+    fn apply(&mut self, mut data: Data) -> Data {
+        let (channels, frames) = (data.channel_count(), data.frame_count());
+        if self.factor == 1 || channels == 0 {
+            return data;
+        }
+        let kept: Vec<usize> = (self.skip..frames).step_by(self.factor).collect();
+        self.skip = self.skip + kept.len() * self.factor - frames;
+
+        data.scaled_data = frames_at(&data.scaled_data, channels, &kept);
+        if data.raw_data.len() == frames * channels {
+            data.raw_data = frames_at(&data.raw_data, channels, &kept);
+        }
+        data
+    }
+}
+
+/// Values of the frames at `indices`
+fn frames_at<T: Copy>(values: &[T], channels: usize, indices: &[usize]) -> Vec<T> {
+    indices
+        .iter()
+        .flat_map(|&frame| &values[frame * channels..(frame + 1) * channels])
+        .copied()
+        .collect()
+}
+
+/// Statistics of the whole recording, the captures are only seen once
+#[derive(Default)]
+struct Totals {
+    captures: u64,
+    frames: u64,
+    channels: Vec<String>,
+    units: Vec<Option<String>>,
+    per_channel: Vec<Channel>,
+}
+
+#[derive(Clone, Default)]
+struct Channel {
+    valid: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Totals {
+    fn add(&mut self, data: &Data) -> io::Result<()> {
+        if self.captures == 0 {
+            self.channels = data.channels().to_vec();
+            self.units = (0..data.channel_count())
+                .map(|index| data.unit(index).map(String::from))
+                .collect();
+            self.per_channel = vec![Channel::default(); data.channel_count()];
+        } else if data.channels() != self.channels {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("the channels change in capture {}", self.captures + 1),
+            ));
+        }
+        self.captures += 1;
+        self.frames += data.frame_count() as u64;
+        for frame in data.iter_values() {
+            for (channel, value) in self.per_channel.iter_mut().zip(frame) {
+                if let Value::Valid(value) = value.value {
+                    channel.valid += 1;
+                    channel.sum += value;
+                    channel.min = Some(channel.min.map_or(value, |min| min.min(value)));
+                    channel.max = Some(channel.max.map_or(value, |max| max.max(value)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn summaries(&self) -> Vec<ChannelSummary> {
+        self.per_channel
+            .iter()
+            .zip(self.channels.iter().zip(&self.units))
+            .map(|(totals, (channel, unit))| ChannelSummary {
+                channel: channel.clone(),
+                unit: unit.clone(),
+                mean: (totals.valid > 0).then(|| totals.sum / totals.valid as f64),
+                min: totals.min,
+                max: totals.max,
+                valid_ratio: match self.frames {
+                    0 => 0.,
+                    frames => totals.valid as f64 / frames as f64,
+                },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use medaqlib::{ChannelInfo, Data, SCALED_OUT_OF_RANGE as OOR};
+
+    use super::{Downsample, Totals};
+
+    fn capture(first: usize, frames: usize) -> Data {
+        let channels = vec!["Distance".to_string(), "Intensity".to_string()];
+        let scaled = (first..first + frames)
+            .flat_map(|frame| [frame as f64, frame as f64 * 10.])
+            .collect();
+        let raw = (first..first + frames)
+            .flat_map(|frame| [frame as i32, frame as i32 * 10])
+            .collect();
+        Data::new(channels, raw, scaled)
+    }
+
+    #[test]
+    fn test_downsample_test() {
+        let mut downsample = Downsample::new(NonZeroUsize::new(3).unwrap());
+        let kept: Vec<_> = [capture(0, 4), capture(4, 1), capture(5, 5)]
+            .into_iter()
+            .map(|data| downsample.apply(data))
+            .collect();
+        assert_eq!(kept[0].scaled_data, [0., 0., 3., 30.]);
+        assert_eq!(kept[0].raw_data, [0, 0, 3, 30]);
+        assert!(kept[1].is_empty());
+        assert_eq!(kept[2].scaled_data, [6., 60., 9., 90.]);
+
+        let mut unchanged = Downsample::new(NonZeroUsize::MIN);
+        assert_eq!(
+            unchanged.apply(capture(0, 2)).scaled_data,
+            capture(0, 2).scaled_data
+        );
+    }
+
+    #[test]
+    fn test_totals_test() {
+        let mut totals = Totals::default();
+        let data = Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            vec![1., OOR, 3., 20.],
+        )
+        .with_channel_info(vec![
+            ChannelInfo::new("Distance").with_unit("mm"),
+            ChannelInfo::new("Intensity"),
+        ]);
+        totals.add(&data).unwrap();
+        totals.add(&data).unwrap();
+        let summaries = totals.summaries();
+        assert_eq!(
+            summaries[0].to_string(),
+            "Distance [mm] mean=2.000 min=1.000 max=3.000 valid=100.0%"
+        );
+        assert_eq!(
+            summaries[1].to_string(),
+            "Intensity mean=20.000 min=20.000 max=20.000 valid=50.0%"
+        );
+        assert_eq!((totals.captures, totals.frames), (2, 4));
+        assert!(totals
+            .add(&capture(0, 1).select(&["Distance"]).unwrap())
+            .is_err());
+    }
+}
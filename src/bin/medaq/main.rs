@@ -0,0 +1,475 @@
+//! Command line interface to sensors, see `medaq --help`
+//!
+//! Exits with `0` on success, `1` on other errors, `2` on invalid arguments, `3` if
+//! connecting fails, `4` if reading fails after connecting and `130` after Ctrl-C.
+//!
+//! Ctrl-C stops the acquisition, the frames still buffered are written and the
+//! files finished before the sensor is closed. A second Ctrl-C exits at once.
+//!
+//! Only the output selected with `--format` is printed on stdout, diagnostics go to
+//! stderr.
+
+use std::{
+    fmt::Display,
+    io::{self, Write},
+    path::PathBuf,
+    process::{self, ExitCode},
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+use clap::{error::ErrorKind, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+#[cfg(feature = "serde")]
+use medaqlib::JsonlOptions;
+use medaqlib::{Data, Interface, MedaqError, Sensor, SensorBuilder, StopToken, ME_SENSOR};
+
+mod bench;
+mod config;
+mod convert;
+mod params;
+#[cfg(feature = "mqtt")]
+mod publish;
+mod record;
+mod record_multi;
+mod serve;
+#[cfg(feature = "ws")]
+mod serve_ws;
+mod watch;
+
+const EXIT_ERROR: u8 = 1;
+const EXIT_CONNECT: u8 = 3;
+const EXIT_READ: u8 = 4;
+/// 128 + SIGINT, as shells report it
+const EXIT_INTERRUPTED: u8 = 130;
+
+/// Set by the first Ctrl-C
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Poll interval while no data is available
+const POLL: Duration = Duration::from_millis(1);
+
+/// Read Micro-Epsilon sensors through MEDAQLib
+#[derive(Parser)]
+#[command(name = "medaq", version, after_help = EXAMPLES)]
+struct Cli {
+    #[command(flatten)]
+    connection: Connection,
+    /// What is printed on stdout
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+    #[command(subcommand)]
+    command: Command,
+}
+
+const EXAMPLES: &str = "Examples:
+  medaq --sensor SENSOR_IFD2421 --interface tcp --ip 10.0.0.5 read --frames 100
+  medaq --interface simulated read --duration 2s
+  medaq --interface simulated --format jsonl read --frames 10
+  medaq record --out capture.csv --duration 30s --sensor IFD2421 --ip 10.0.0.5
+  medaq --profile thickness record-multi --out capture/ --duration 1m
+  medaq watch --refresh 200ms --sensor IFD2421 --ip 10.0.0.5
+  medaq params dump --json --name SP_Offset --sensor IFD2421 --ip 10.0.0.5
+  medaq params set SP_Offset=0.5 --sensor IFD2421 --ip 10.0.0.5
+  medaq bench --duration 10s --sensor IFD2421 --ip 10.0.0.5
+  medaq --profile cell3 publish --broker broker.plant.local --interval 5s
+  medaq serve --listen 0.0.0.0:5000 --decimation 10 --sensor IFD2421 --ip 10.0.0.5
+  medaq --interface simulated serve-ws --listen 127.0.0.1:8080
+  medaq --profile cell3 watch
+  medaq convert capture.bin --to csv --out capture.csv --downsample 10
+  medaq config check --config fleet/medaq.toml";
+
+/// Sensor to connect to, accepted before or after the subcommand
+///
+/// Options given here override those of the profile.
+#[derive(Args)]
+struct Connection {
+    /// Sensor type, e.g. SENSOR_IFD2421 or IFD2421, required unless simulated
+    #[arg(long, global = true)]
+    sensor: Option<ME_SENSOR>,
+    /// Interface, e.g. tcp, TCP/IP, IF2008 or simulated, TCP/IP by default
+    #[arg(long, global = true)]
+    interface: Option<Interface>,
+    /// IP address of the sensor
+    #[arg(long, global = true)]
+    ip: Option<String>,
+    /// TCP port of the sensor, defaults to the port of the sensor type
+    #[arg(long, global = true)]
+    port: Option<u16>,
+    /// Let MEDAQLib write a log file
+    #[arg(long, global = true)]
+    log: bool,
+    /// Profile of the config file to take the options of the sensor from
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Config file with the profiles, by default medaq.toml in the working directory
+    /// or in medaq of the user config directory
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+}
+
+/// Output on stdout
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    /// Text for humans
+    Text,
+    /// A JSON document, a JSON object per frame or refresh for `read` and `watch`,
+    /// errors as `{"error":{"code":3,"message":"..."}}`
+    #[cfg(feature = "serde")]
+    Json,
+    /// Like json, but every document on a single line
+    #[cfg(feature = "serde")]
+    Jsonl,
+}
+
+impl Format {
+    /// Whether JSON documents are printed on a single line
+    fn compact(self) -> bool {
+        #[cfg(feature = "serde")]
+        if self == Format::Jsonl {
+            return true;
+        }
+        false
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the scaled values, a header with the channel names and one frame per
+    /// line, until Ctrl-C or a limit
+    Read(Limit),
+    /// Write the scaled values to a CSV file while they arrive, until Ctrl-C or the
+    /// duration expired
+    Record(record::Record),
+    /// Record every sensor of a profile to its own CSV file and the means of all
+    /// sensors per window to combined.csv, reconnecting sensors that fail, until
+    /// Ctrl-C or the duration expired
+    RecordMulti(record_multi::RecordMulti),
+    /// Show the latest value and statistics of the newest frames per channel, refreshed
+    /// until Ctrl-C, as plain lines unless printing to a terminal
+    Watch(watch::Watch),
+    /// Dump or set parameters of the sensor instance
+    #[command(subcommand)]
+    Params(params::Params),
+    /// Read as fast as possible and report the throughput, the latency of the reads,
+    /// the largest backlog and the number of invalid samples
+    Bench(bench::Bench),
+    /// Forward the frames to every client connected over TCP, a line per frame,
+    /// until Ctrl-C or the duration expired
+    Serve(serve::Serve),
+    /// Stream the frames to browsers over WebSocket, a JSON object per frame after
+    /// the channel metadata, until Ctrl-C or the duration expired
+    #[cfg(feature = "ws")]
+    ServeWs(serve_ws::ServeWs),
+    /// Publish a message per channel to an MQTT broker, a summary per interval or
+    /// every frame, until Ctrl-C or the duration expired
+    #[cfg(feature = "mqtt")]
+    Publish(publish::Publish),
+    /// Convert a binary recording to another format, printing statistics of every
+    /// channel afterwards
+    Convert(convert::Convert),
+    /// Work with the config file
+    #[command(subcommand)]
+    Config(config::Config),
+}
+
+/// When to stop reading, both limits may be combined
+#[derive(Args)]
+struct Limit {
+    /// Stop after this time, e.g. 500ms, 30s or 2m
+    #[arg(long, value_parser = parse_duration)]
+    duration: Option<Duration>,
+    /// Stop after this many frames
+    #[arg(long)]
+    frames: Option<u64>,
+}
+
+enum Failure {
+    Connect(MedaqError),
+    Read(MedaqError),
+    Output(io::Error),
+    /// A parameter was rejected, with the error text of the sensor
+    Parameter(String),
+    /// The config file is missing or invalid
+    Config(String),
+    /// A file to convert could not be read
+    Input(io::Error),
+    /// Binding the address to serve on failed
+    Listen(String, io::Error),
+}
+
+impl Failure {
+    fn code(&self) -> u8 {
+        match self {
+            Failure::Connect(_) => EXIT_CONNECT,
+            Failure::Read(_) => EXIT_READ,
+            Failure::Output(_)
+            | Failure::Parameter(_)
+            | Failure::Config(_)
+            | Failure::Input(_)
+            | Failure::Listen(..) => EXIT_ERROR,
+        }
+    }
+
+    /// The last line on stdout when printing JSON, with the exit code
+    #[cfg(feature = "serde")]
+    fn json(&self) -> String {
+        serde_json::json!({"error": {"code": self.code(), "message": self.to_string()}}).to_string()
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Failure::Connect(err) => write!(f, "could not connect: {err}"),
+            Failure::Read(err) => write!(f, "could not read: {err}"),
+            Failure::Output(err) => write!(f, "could not write output: {err}"),
+            Failure::Input(err) => write!(f, "could not read input: {err}"),
+            Failure::Listen(address, err) => write!(f, "could not listen on {address}: {err}"),
+            Failure::Parameter(message) | Failure::Config(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(err: io::Error) -> Self {
+        Failure::Output(err)
+    }
+}
+
+impl Connection {
+    fn connect(&self) -> Result<Sensor, Failure> {
+        let builder = builder(&self.resolve()?).unwrap_or_else(|message| {
+            Cli::command()
+                .error(ErrorKind::MissingRequiredArgument, message)
+                .exit()
+        });
+        builder.connect().map_err(Failure::Connect)
+    }
+
+    /// The options given on the command line
+    fn flags(&self) -> config::Profile {
+        config::Profile {
+            sensor: self.sensor,
+            interface: self.interface,
+            ip: self.ip.clone(),
+            port: self.port,
+            log: self.log.then_some(true),
+            sensors: vec![],
+        }
+    }
+
+    /// The options given on the command line, the missing ones taken from the profile
+    fn resolve(&self) -> Result<config::Profile, Failure> {
+        let Some(name) = &self.profile else {
+            return Ok(self.flags());
+        };
+        let file = config::File::load(self.config.as_deref()).map_err(Failure::Config)?;
+        let profile = file.profile(name).map_err(Failure::Config)?;
+        Ok(self.flags().or(profile))
+    }
+}
+
+/// Builder of the options, with the defaults for those not set
+fn builder(options: &config::Profile) -> Result<SensorBuilder, String> {
+    let interface = options.interface.unwrap_or(Interface::TcpIp);
+    let sensor_type = match (options.sensor, interface) {
+        (Some(sensor_type), _) => sensor_type,
+        (None, Interface::Simulated) => ME_SENSOR::NO_SENSOR,
+        (None, _) => return Err("--sensor is required unless the interface is simulated".into()),
+    };
+    let mut builder = SensorBuilder::new(sensor_type).with_interface(interface);
+    if let Some(ip) = &options.ip {
+        builder = builder.with_ip_address(ip);
+    }
+    if let Some(port) = options.port {
+        builder = builder.with_port(port);
+    }
+    if options.log == Some(true) {
+        builder = builder.enable_logging();
+    }
+    Ok(builder)
+}
+
+/// Token cancelled by Ctrl-C or once `duration` passed, only called once per run
+fn stop_token(duration: Option<Duration>) -> StopToken {
+    let token = StopToken::new();
+    let interrupt = token.clone();
+    ctrlc::set_handler(move || {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            eprintln!("medaq: interrupted again, exiting without finishing");
+            process::exit(EXIT_INTERRUPTED.into());
+        }
+        eprintln!("medaq: interrupted, finishing, press Ctrl-C again to exit at once");
+        interrupt.cancel();
+    })
+    .expect("could not set Ctrl-C handler");
+    if let Some(duration) = duration {
+        let expired = token.clone();
+        thread::spawn(move || {
+            thread::sleep(duration);
+            expired.cancel();
+        });
+    }
+    token
+}
+
+fn read(connection: &Connection, limit: &Limit, format: Format) -> Result<(), Failure> {
+    let sensor = connection.connect()?;
+    let token = stop_token(limit.duration);
+    let mut out = io::stdout().lock();
+    if format == Format::Text {
+        writeln!(out, "{}", sensor.parameters().join("\t"))?;
+    }
+
+    let mut remaining = limit.frames.unwrap_or(u64::MAX);
+    for data in sensor.iter_data(POLL).with_stop_token(token) {
+        let mut data = data.map_err(Failure::Read)?;
+        let frames = data
+            .frame_count()
+            .min(remaining.try_into().unwrap_or(usize::MAX));
+        let values = frames * data.channel_count();
+        data.raw_data.truncate(values);
+        data.scaled_data.truncate(values);
+        write_frames(&mut out, &data, format)?;
+        out.flush()?;
+        remaining -= frames as u64;
+        if remaining == 0 {
+            break;
+        }
+    }
+    sensor.close();
+    Ok(())
+}
+
+/// A line per frame, the tab separated values or a JSON object
+fn write_frames(out: &mut impl Write, data: &Data, format: Format) -> io::Result<()> {
+    match format {
+        Format::Text => {
+            for frame in data.iter_values() {
+                let values: Vec<_> = frame.map(|value| value.value.to_string()).collect();
+                writeln!(out, "{}", values.join("\t"))?;
+            }
+            Ok(())
+        }
+        #[cfg(feature = "serde")]
+        Format::Json | Format::Jsonl => data.write_jsonl(out, &JsonlOptions::default()),
+    }
+}
+
+/// Duration of a number and a unit of `ms`, `s`, `m` or `h`, e.g. `1.5s`
+fn parse_duration(text: &str) -> Result<Duration, String> {
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .ok_or_else(|| format!("{text:?} has no unit of ms, s, m or h"))?;
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("{number:?} is not a number"))?;
+    let seconds = match unit {
+        "ms" => number / 1000.,
+        "s" => number,
+        "m" => number * 60.,
+        "h" => number * 3600.,
+        _ => return Err(format!("unknown unit {unit:?}, expected ms, s, m or h")),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        Command::Read(limit) => read(&cli.connection, limit, cli.format),
+        Command::Record(options) => record::record(&cli.connection, options),
+        Command::RecordMulti(options) => {
+            record_multi::record_multi(&cli.connection, options, cli.format)
+        }
+        Command::Watch(options) => watch::watch(&cli.connection, options, cli.format),
+        Command::Params(params) => params::params(&cli.connection, params, cli.format),
+        Command::Bench(options) => bench::bench(&cli.connection, options, cli.format),
+        Command::Serve(options) => serve::serve(&cli.connection, options, cli.format),
+        #[cfg(feature = "ws")]
+        Command::ServeWs(options) => serve_ws::serve_ws(&cli.connection, options),
+        #[cfg(feature = "mqtt")]
+        Command::Publish(options) => publish::publish(&cli.connection, options, cli.format),
+        Command::Convert(options) => convert::convert(options, cli.format),
+        Command::Config(config) => config::config(&cli.connection, config, cli.format),
+    };
+    match result {
+        Ok(()) if INTERRUPTED.load(Ordering::SeqCst) => EXIT_INTERRUPTED.into(),
+        Ok(()) => ExitCode::SUCCESS,
+        // e.g. piped into `head`
+        Err(Failure::Output(err)) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(failure) => {
+            eprintln!("medaq: {failure}");
+            #[cfg(feature = "serde")]
+            if cli.format != Format::Text {
+                let _ = writeln!(io::stdout(), "{}", failure.json());
+            }
+            failure.code().into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use clap::CommandFactory;
+    use medaqlib::{Data, SCALED_OUT_OF_RANGE};
+
+    use super::{parse_duration, write_frames, Cli, Format};
+
+    fn data() -> Data {
+        let channels = vec!["Distance".to_string(), "Intensity".to_string()];
+        Data::new(channels, vec![], vec![1.5, 10., 2., SCALED_OUT_OF_RANGE])
+    }
+
+    #[test]
+    fn test_cli_test() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_duration_test() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("3d").is_err());
+    }
+
+    #[test]
+    fn test_write_frames_text_test() {
+        let mut out = vec![];
+        write_frames(&mut out, &data(), Format::Text).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1.5\t10\n2\tOutOfRange\n");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_write_frames_jsonl_test() {
+        let mut out = vec![];
+        write_frames(&mut out, &data(), Format::Jsonl).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"Distance\":1.5,\"Intensity\":10.0}\n{\"Distance\":2.0,\"Intensity\":null}\n"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_failure_json_test() {
+        use medaqlib::{MedaqError, ERR_CODE};
+
+        use super::Failure;
+
+        let failure = Failure::Connect(MedaqError::Sensor(ERR_CODE::ERR_NOT_OPEN));
+        assert_eq!(
+            failure.json(),
+            "{\"error\":{\"code\":3,\"message\":\"could not connect: sensor error: ERR_NOT_OPEN\"}}"
+        );
+    }
+}
@@ -0,0 +1,330 @@
+//! `medaq params`, reading and writing parameters of the sensor instance
+
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use clap::{Args, Subcommand, ValueEnum};
+use medaqlib::{MedaqError, Sensor};
+
+use crate::{Connection, Failure, Format};
+
+/// Parameters of the interface dumped besides those of the channels
+const INTERFACE_PARAMETERS: [&str; 3] = ["IP_Interface", "IP_RemoteAddr", "IP_Port"];
+
+/// Parameters dumped for each transmitted channel, followed by its number
+const CHANNEL_PARAMETERS: [&str; 6] = [
+    "IA_Scaled_Name",
+    "IA_Scaled_Unit",
+    "IA_Raw_RangeMin",
+    "IA_Raw_RangeMax",
+    "IA_Scaled_RangeMin",
+    "IA_Scaled_RangeMax",
+];
+
+#[derive(Subcommand)]
+pub(crate) enum Params {
+    /// Print the parameters of the interface and the channels, and those given with
+    /// --name
+    Dump(Dump),
+    /// Set parameters, e.g. `SP_Offset=0.5`, in the given order
+    Set(Set),
+}
+
+#[derive(Args)]
+pub(crate) struct Dump {
+    /// Dump this parameter as well, may be repeated
+    #[arg(long = "name", value_name = "NAME")]
+    names: Vec<String>,
+    /// Print a JSON object, parameters that could not be read are null, like
+    /// --format json
+    #[arg(long, conflicts_with = "toml")]
+    json: bool,
+    /// Print TOML, parameters that could not be read are comments
+    #[arg(long)]
+    toml: bool,
+}
+
+#[derive(Args)]
+pub(crate) struct Set {
+    /// Assignments of NAME=VALUE
+    #[arg(required = true, value_parser = parse_assignment, value_name = "NAME=VALUE")]
+    assignments: Vec<(String, String)>,
+    /// Type of every value instead of inferring it, int if it parses as one, float if
+    /// it parses as a number, string otherwise
+    #[arg(long = "type", value_enum)]
+    kind: Option<Kind>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Kind {
+    Int,
+    Float,
+    String,
+}
+
+/// Typed value of an assignment
+#[derive(Debug, PartialEq)]
+enum Parameter {
+    Int(i32),
+    Float(f64),
+    String(String),
+}
+
+impl Parameter {
+    fn new(value: &str, kind: Option<Kind>) -> Result<Self, String> {
+        let int = || value.parse().map(Parameter::Int);
+        let float = || value.parse().map(Parameter::Float);
+        match kind {
+            None => Ok(int()
+                .or_else(|_| float())
+                .unwrap_or_else(|_| Parameter::String(value.into()))),
+            Some(Kind::Int) => int().map_err(|err| format!("{value:?} is no int: {err}")),
+            Some(Kind::Float) => float().map_err(|err| format!("{value:?} is no float: {err}")),
+            Some(Kind::String) => Ok(Parameter::String(value.into())),
+        }
+    }
+
+    fn set(&self, sensor: &Sensor, name: &str) -> Result<(), MedaqError> {
+        match self {
+            Parameter::Int(value) => sensor.set_parameter_int(name, *value),
+            Parameter::Float(value) => sensor.set_parameter_double(name, *value),
+            Parameter::String(value) => sensor.set_parameter_string(name, value),
+        }
+    }
+
+    /// JSON literal of the value, `null` if it is not finite
+    fn json(&self) -> String {
+        match self {
+            Parameter::Int(value) => value.to_string(),
+            Parameter::Float(value) if value.is_finite() => format!("{value:?}"),
+            Parameter::Float(_) => "null".into(),
+            Parameter::String(value) => quoted(value),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Parameter::Int(_) => "int",
+            Parameter::Float(_) => "float",
+            Parameter::String(_) => "string",
+        }
+    }
+}
+
+fn parse_assignment(text: &str) -> Result<(String, String), String> {
+    match text.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.into(), value.into())),
+        _ => Err(format!("{text:?} is not NAME=VALUE")),
+    }
+}
+
+pub(crate) fn params(
+    connection: &Connection,
+    params: &Params,
+    format: Format,
+) -> Result<(), Failure> {
+    let sensor = connection.connect()?;
+    match params {
+        Params::Dump(dump) => {
+            let values = read(&sensor, &dump.names);
+            let output = match (format, dump.toml) {
+                (_, true) => toml(&values),
+                (Format::Text, false) if !dump.json => plain(&values),
+                _ => json(&values, format.compact()),
+            };
+            io::stdout().write_all(output.as_bytes())?;
+            Ok(())
+        }
+        Params::Set(set) => {
+            // printed once all were accepted
+            let mut accepted = vec![];
+            for (name, value) in &set.assignments {
+                let parameter = Parameter::new(value, set.kind).map_err(|detail| {
+                    Failure::Parameter(format!("could not set {name}: {detail}"))
+                })?;
+                parameter.set(&sensor, name).map_err(|err| {
+                    let detail = sensor.last_error().map(|text| format!(": {text}"));
+                    Failure::Parameter(format!(
+                        "could not set {name}: {err}{}",
+                        detail.unwrap_or_default()
+                    ))
+                })?;
+                if format == Format::Text {
+                    writeln!(io::stdout(), "{name} = {value} ({})", parameter.kind())?;
+                } else {
+                    accepted.push((name.clone(), parameter.json()));
+                }
+            }
+            if format != Format::Text {
+                io::stdout().write_all(object(accepted, format.compact()).as_bytes())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Values of the curated and the extra parameters in that order, the error if one
+/// could not be read
+fn read(sensor: &Sensor, extra: &[String]) -> Vec<(String, Result<String, MedaqError>)> {
+    let channels = (1..=sensor.parameters().len())
+        .flat_map(|channel| CHANNEL_PARAMETERS.map(|parameter| format!("{parameter}{channel}")));
+    INTERFACE_PARAMETERS
+        .map(String::from)
+        .into_iter()
+        .chain(channels)
+        .chain(extra.iter().cloned())
+        .map(|name| {
+            let value = sensor.get_parameter_string(&name);
+            (name, value)
+        })
+        .collect()
+}
+
+type Values = [(String, Result<String, MedaqError>)];
+
+fn plain(values: &Values) -> String {
+    let mut output = String::new();
+    for (name, value) in values {
+        let _ = match value {
+            Ok(value) => writeln!(output, "{name} = {value}"),
+            Err(err) => writeln!(output, "{name}: {err}"),
+        };
+    }
+    output
+}
+
+fn json(values: &Values, compact: bool) -> String {
+    let entries = values.iter().map(|(name, value)| {
+        let value = value.as_ref().map_or("null".into(), |value| quoted(value));
+        (name.clone(), value)
+    });
+    object(entries, compact)
+}
+
+/// JSON object of names and their JSON literals, a line per entry unless `compact`
+pub(crate) fn object(entries: impl IntoIterator<Item = (String, String)>, compact: bool) -> String {
+    let entries: Vec<_> = entries
+        .into_iter()
+        .map(|(name, value)| match compact {
+            true => format!("{}:{value}", quoted(&name)),
+            false => format!("  {}: {value}", quoted(&name)),
+        })
+        .collect();
+    match compact {
+        true => format!("{{{}}}\n", entries.join(",")),
+        false => format!("{{\n{}\n}}\n", entries.join(",\n")),
+    }
+}
+
+fn toml(values: &Values) -> String {
+    let mut output = String::new();
+    for (name, value) in values {
+        let _ = match value {
+            Ok(value) => writeln!(output, "{} = {}", quoted(name), quoted(value)),
+            Err(err) => writeln!(output, "# {name}: {err}"),
+        };
+    }
+    output
+}
+
+/// String literal valid in JSON and as a TOML basic string
+pub(crate) fn quoted(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use medaqlib::{MedaqError, ERR_CODE};
+
+    use super::{json, object, parse_assignment, plain, quoted, toml, Kind, Parameter};
+
+    #[test]
+    fn test_parameter_test() {
+        assert_eq!(Parameter::new("1", None), Ok(Parameter::Int(1)));
+        assert_eq!(Parameter::new("-0.5", None), Ok(Parameter::Float(-0.5)));
+        assert_eq!(
+            Parameter::new("1e3x", None),
+            Ok(Parameter::String("1e3x".into()))
+        );
+        assert_eq!(
+            Parameter::new("1", Some(Kind::Float)),
+            Ok(Parameter::Float(1.))
+        );
+        assert_eq!(
+            Parameter::new("1", Some(Kind::String)),
+            Ok(Parameter::String("1".into()))
+        );
+        assert!(Parameter::new("1.5", Some(Kind::Int)).is_err());
+
+        let accepted = [
+            Parameter::Int(1),
+            Parameter::Float(0.5),
+            Parameter::Float(f64::INFINITY),
+            Parameter::String("a\"b".into()),
+        ];
+        let accepted = accepted
+            .iter()
+            .enumerate()
+            .map(|(index, parameter)| (format!("SP_{index}"), parameter.json()));
+        assert_eq!(
+            object(accepted, true),
+            "{\"SP_0\":1,\"SP_1\":0.5,\"SP_2\":null,\"SP_3\":\"a\\\"b\"}\n"
+        );
+
+        assert_eq!(
+            parse_assignment("SP_Name=a=b"),
+            Ok(("SP_Name".into(), "a=b".into()))
+        );
+        assert_eq!(
+            parse_assignment("SP_Name="),
+            Ok(("SP_Name".into(), "".into()))
+        );
+        assert!(parse_assignment("SP_Name").is_err());
+        assert!(parse_assignment("=1").is_err());
+    }
+
+    #[test]
+    fn test_dump_formats_test() {
+        let values = [
+            ("IA_Scaled_Unit1".to_string(), Ok("µm".to_string())),
+            (
+                "IP_Port".to_string(),
+                Err(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER)),
+            ),
+        ];
+        assert_eq!(
+            plain(&values),
+            "IA_Scaled_Unit1 = µm\nIP_Port: sensor error: ERR_WRONG_PARAMETER\n"
+        );
+        assert_eq!(
+            json(&values, false),
+            "{\n  \"IA_Scaled_Unit1\": \"µm\",\n  \"IP_Port\": null\n}\n"
+        );
+        assert_eq!(
+            json(&values, true),
+            "{\"IA_Scaled_Unit1\":\"µm\",\"IP_Port\":null}\n"
+        );
+        assert_eq!(
+            toml(&values),
+            "\"IA_Scaled_Unit1\" = \"µm\"\n# IP_Port: sensor error: ERR_WRONG_PARAMETER\n"
+        );
+        assert_eq!(quoted("a\"\\\n\u{1}"), "\"a\\\"\\\\\\n\\u0001\"");
+    }
+}
@@ -0,0 +1,148 @@
+//! `medaq publish`, the frames of a sensor published to an MQTT broker
+
+use std::{
+    io::{self, Write},
+    num::NonZeroUsize,
+    thread,
+    time::Duration,
+};
+
+use clap::{Args, ValueEnum};
+use medaqlib::{AcquisitionConfig, MqttConfig, MqttPayload, MqttPublisher, MqttQos};
+
+use crate::{params::object, parse_duration, stop_token, Connection, Failure, Format};
+
+/// Interval the broker connection and the stop request are checked
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Args)]
+pub(crate) struct Publish {
+    /// Broker as HOST or HOST:PORT
+    #[arg(long, default_value = "localhost:1883")]
+    broker: String,
+    /// Topic of the messages, {sensor_serial} and {channel} are replaced
+    #[arg(long, default_value = "plant/{sensor_serial}/{channel}")]
+    topic: String,
+    /// Replaces {sensor_serial}, the name of the profile by default
+    #[arg(long)]
+    serial: Option<String>,
+    /// Client id sent to the broker
+    #[arg(long, default_value = "medaq")]
+    client_id: String,
+    /// Quality of service
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=2), default_value_t = 0)]
+    qos: u8,
+    /// What is published per channel
+    #[arg(long, value_enum, default_value_t = Payload::Summary)]
+    payload: Payload,
+    /// Time a summary covers, e.g. 1s
+    #[arg(long, value_parser = parse_duration, default_value = "1s")]
+    interval: Duration,
+    /// Publish only every Nth frame with --payload frames
+    #[arg(long, value_name = "N", default_value = "1")]
+    decimation: NonZeroUsize,
+    /// Stop after this time, e.g. 8h, publishes until Ctrl-C without
+    #[arg(long, value_parser = parse_duration)]
+    duration: Option<Duration>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Payload {
+    /// Mean, minimum, maximum and share of valid values per interval
+    Summary,
+    /// The timestamp and value of every frame
+    Frames,
+}
+
+impl Publish {
+    fn config(&self, serial: String) -> Result<MqttConfig, Failure> {
+        let (host, port) = match self.broker.rsplit_once(':') {
+            Some((host, port)) => {
+                let port = port.parse().map_err(|_| {
+                    Failure::Config(format!("{:?} is not a valid broker port", port))
+                })?;
+                (host.to_string(), port)
+            }
+            None => (self.broker.clone(), MqttConfig::default().port),
+        };
+        Ok(MqttConfig {
+            host,
+            port,
+            client_id: self.client_id.clone(),
+            topic: self.topic.clone(),
+            sensor_serial: serial,
+            qos: match self.qos {
+                0 => MqttQos::AtMostOnce,
+                1 => MqttQos::AtLeastOnce,
+                _ => MqttQos::ExactlyOnce,
+            },
+            payload: match self.payload {
+                Payload::Summary => MqttPayload::Summary {
+                    interval: self.interval,
+                },
+                Payload::Frames => MqttPayload::Frames {
+                    decimation: self.decimation.get(),
+                },
+            },
+            ..MqttConfig::default()
+        })
+    }
+}
+
+pub(crate) fn publish(
+    connection: &Connection,
+    publish: &Publish,
+    format: Format,
+) -> Result<(), Failure> {
+    let Some(serial) = publish.serial.clone().or(connection.profile.clone()) else {
+        return Err(Failure::Config(
+            "publish needs --serial or a --profile naming the sensor".into(),
+        ));
+    };
+    let config = publish.config(serial)?;
+    let sensor = connection.connect()?;
+    let publisher = MqttPublisher::connect(config);
+    let monitor = publisher.monitor();
+
+    let token = stop_token(publish.duration);
+    let acquisition = sensor.start_acquisition_with_sink(
+        AcquisitionConfig {
+            stop_token: Some(token.clone()),
+            ..AcquisitionConfig::default()
+        },
+        publisher,
+    );
+    // report changes of the broker connection, the sensor keeps being read meanwhile
+    let mut connected = false;
+    while !token.is_cancelled() && !acquisition.is_finished() {
+        thread::sleep(CHECK_INTERVAL);
+        let stats = monitor.stats();
+        if stats.connected != connected {
+            connected = stats.connected;
+            match (connected, &stats.last_error) {
+                (true, _) => eprintln!("connected to {}", publish.broker),
+                (false, Some(err)) => eprintln!("lost {}: {err}", publish.broker),
+                (false, None) => eprintln!("lost {}", publish.broker),
+            }
+        }
+    }
+    acquisition.join().close();
+
+    let stats = monitor.stats();
+    let mut out = io::stdout().lock();
+    if format == Format::Text {
+        writeln!(
+            out,
+            "{} messages published, {} dropped, {} connections to the broker",
+            stats.published, stats.dropped, stats.connections
+        )?;
+    } else {
+        let entries = [
+            ("published".to_string(), stats.published.to_string()),
+            ("dropped".to_string(), stats.dropped.to_string()),
+            ("connections".to_string(), stats.connections.to_string()),
+        ];
+        out.write_all(object(entries, format.compact()).as_bytes())?;
+    }
+    Ok(())
+}
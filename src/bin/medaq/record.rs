@@ -0,0 +1,162 @@
+//! `medaq record`, captures written to a CSV file while they arrive
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, IsTerminal, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use medaqlib::{AcquisitionConfig, CsvOptions, CsvWriter, Sensor, StopToken};
+
+use crate::{parse_duration, stop_token, Connection, Failure};
+
+/// Shortest time between updates of the counter
+const COUNTER_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Args)]
+pub(crate) struct Record {
+    /// CSV file to create, an existing file is replaced
+    #[arg(long)]
+    out: PathBuf,
+    /// Stop after this time, e.g. 30s, records until Ctrl-C without
+    #[arg(long, value_parser = parse_duration)]
+    duration: Option<Duration>,
+    /// Separator between columns, use `;` together with --decimal-comma
+    #[arg(long, default_value_t = ',')]
+    delimiter: char,
+    /// Write 1,5 instead of 1.5
+    #[arg(long)]
+    decimal_comma: bool,
+    /// Text written for out of range values
+    #[arg(long, default_value = "")]
+    out_of_range: String,
+}
+
+impl Record {
+    fn options(&self) -> CsvOptions {
+        CsvOptions {
+            delimiter: self.delimiter,
+            decimal_comma: self.decimal_comma,
+            out_of_range: self.out_of_range.clone(),
+            ..CsvOptions::default()
+        }
+    }
+}
+
+pub(crate) fn record(connection: &Connection, record: &Record) -> Result<(), Failure> {
+    let sensor = connection.connect()?;
+    let file = BufWriter::new(File::create(&record.out)?);
+    let csv = CsvWriter::new(file, record.options());
+    let (sensor, result) = capture(sensor, csv, stop_token(record.duration));
+    sensor.close();
+    result.map(drop)
+}
+
+/// Write the captures until `token` is cancelled, then the frames still buffered
+/// in MEDAQLib, and finish the file
+///
+/// The sensor is given back even if writing failed, so it can be closed.
+fn capture<W: Write>(
+    sensor: Sensor,
+    mut csv: CsvWriter<W>,
+    token: StopToken,
+) -> (Sensor, Result<W, Failure>) {
+    let acquisition = sensor.start_acquisition(AcquisitionConfig {
+        stop_token: Some(token),
+        stop_on_error: true,
+        ..AcquisitionConfig::default()
+    });
+    let mut counter = Counter::new();
+    let mut result = Ok(());
+    for data in acquisition.receiver() {
+        let written = match data {
+            Ok(data) => csv
+                .write(&data)
+                .and_then(|()| csv.flush())
+                .and_then(|()| counter.add(data.frame_count())),
+            Err(err) => {
+                result = Err(Failure::Read(err));
+                continue;
+            }
+        };
+        if let Err(err) = written {
+            result = Err(Failure::Output(err));
+            break;
+        }
+    }
+    let sensor = acquisition.join();
+    let result = result.and_then(|()| {
+        let file = csv.into_inner()?;
+        counter.finish()?;
+        Ok(file)
+    });
+    (sensor, result)
+}
+
+/// Number of frames written, updated in place while stderr is a terminal
+struct Counter {
+    frames: usize,
+    live: bool,
+    updated: Instant,
+}
+
+impl Counter {
+    fn new() -> Self {
+        Self {
+            frames: 0,
+            live: io::stderr().is_terminal(),
+            updated: Instant::now(),
+        }
+    }
+
+    fn add(&mut self, frames: usize) -> io::Result<()> {
+        self.frames += frames;
+        if self.live && self.updated.elapsed() >= COUNTER_INTERVAL {
+            self.updated = Instant::now();
+            write!(io::stderr(), "\r{} frames written", self.frames)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        let start = if self.live { "\r" } else { "" };
+        writeln!(io::stderr(), "{start}{} frames written", self.frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use medaqlib::{CsvOptions, CsvWriter, Interface, SensorBuilder, StopToken, ME_SENSOR};
+
+    use super::capture;
+
+    #[test]
+    fn test_capture_stopped_test() {
+        let sensor = SensorBuilder::new(ME_SENSOR::NO_SENSOR)
+            .with_interface(Interface::Simulated)
+            .connect()
+            .unwrap();
+        let token = StopToken::new();
+        let interrupt = token.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            interrupt.cancel();
+        });
+
+        let csv = CsvWriter::new(vec![], CsvOptions::default());
+        let (sensor, result) = capture(sensor, csv, token);
+        let Ok(bytes) = result else {
+            panic!("capture failed")
+        };
+        let text = String::from_utf8(bytes).unwrap();
+        let (header, frames) = text.split_once('\n').unwrap();
+        assert_eq!(header.split(',').count(), sensor.parameters().len());
+        assert!(frames.lines().count() > 0);
+        assert!(text.ends_with('\n'));
+        sensor.close();
+    }
+}
@@ -0,0 +1,413 @@
+//! `medaq record-multi`, every sensor of a profile recorded together through a
+//! [`SensorGroup`]
+
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::RecvTimeoutError,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use clap::Args;
+use medaqlib::{CsvOptions, CsvWriter, Data, GroupConfig, Sensor, SensorGroup, StopToken};
+
+use crate::{
+    builder, config::Profile, params::object, parse_duration, stop_token, Connection, Failure,
+    Format,
+};
+
+/// Time between status lines
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest wait for a window before checking for Ctrl-C
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// File with a row per window, next to those of the sensors
+const COMBINED: &str = "combined.csv";
+
+#[derive(Args)]
+pub(crate) struct RecordMulti {
+    /// Directory to write NAME.csv of every sensor and combined.csv to, created if
+    /// missing, existing files are replaced
+    #[arg(long)]
+    out: PathBuf,
+    /// Stop after this time, e.g. 30s, records until Ctrl-C without
+    #[arg(long, value_parser = parse_duration)]
+    duration: Option<Duration>,
+    /// Width of the windows of combined.csv
+    #[arg(long, value_parser = parse_duration, default_value = "100ms")]
+    window: Duration,
+    /// Reconnect a sensor once its reads failed and it delivered nothing for this
+    /// long, also the time between attempts
+    #[arg(long, value_parser = parse_duration, default_value = "2s")]
+    reconnect_after: Duration,
+}
+
+pub(crate) fn record_multi(
+    connection: &Connection,
+    record: &RecordMulti,
+    format: Format,
+) -> Result<(), Failure> {
+    let members = connection.resolve()?.members(&connection.flags());
+    if members.is_empty() {
+        return Err(Failure::Config(
+            "record-multi needs a --profile listing [[profiles.NAME.sensors]]".into(),
+        ));
+    }
+    fs::create_dir_all(&record.out)?;
+    let mut slots = members
+        .into_iter()
+        .map(|(name, options)| Slot::connect(name, options, &record.out))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut combined = BufWriter::new(File::create(record.out.join(COMBINED))?);
+    writeln!(combined, "{}", combined_header(&slots))?;
+
+    let token = stop_token(record.duration);
+    let mut status = Instant::now();
+    while !token.is_cancelled() {
+        match slots.iter().any(|slot| slot.sensor.is_some()) {
+            true => record_group(&mut slots, record, &token, &mut combined, &mut status)?,
+            false => sleep(record.reconnect_after, &token),
+        }
+        if token.is_cancelled() {
+            break;
+        }
+        let now = Instant::now();
+        for slot in &mut slots {
+            if slot.health.failing(now, record.reconnect_after) {
+                slot.reconnect(now);
+            }
+        }
+    }
+
+    combined.flush()?;
+    let mut out = io::stdout().lock();
+    if format == Format::Text {
+        for slot in &slots {
+            writeln!(out, "{}", slot.summary())?;
+        }
+    } else {
+        let entries = slots.iter().map(|slot| (slot.name.clone(), slot.json()));
+        out.write_all(object(entries, format.compact()).as_bytes())?;
+    }
+    for slot in slots {
+        slot.csv.into_inner()?;
+        if let Some(sensor) = slot.sensor {
+            sensor.close();
+        }
+    }
+    Ok(())
+}
+
+/// Record the connected sensors as one group until Ctrl-C, or until a sensor has
+/// to be reconnected, which needs a new group
+fn record_group(
+    slots: &mut [Slot],
+    record: &RecordMulti,
+    token: &StopToken,
+    combined: &mut impl Write,
+    status: &mut Instant,
+) -> Result<(), Failure> {
+    let connected: Vec<usize> = (0..slots.len())
+        .filter(|&index| slots[index].sensor.is_some())
+        .collect();
+    let group: SensorGroup = connected
+        .iter()
+        .filter_map(|&index| slots[index].sensor.take())
+        .collect();
+    let acquisition = group.start_acquisition(GroupConfig {
+        window: record.window,
+        ..GroupConfig::default()
+    });
+    for slot in slots.iter_mut() {
+        slot.read_errors = 0;
+    }
+
+    let mut result = Ok(());
+    while !token.is_cancelled() {
+        let capture = match acquisition.receiver().recv_timeout(RECEIVE_TIMEOUT) {
+            Ok(capture) => capture,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let stats = acquisition.stats();
+        let now = Instant::now();
+        let mut row = seconds_since_epoch(capture.window.0);
+        let written = slots.iter_mut().enumerate().try_for_each(|(index, slot)| {
+            let member = connected.iter().position(|&connected| connected == index);
+            let data = member.and_then(|member| capture.per_sensor[member].as_ref());
+            let read_errors = member.map_or(0, |member| stats.read_errors[member]);
+            slot.record(data, read_errors, now)?;
+            slot.combined_values(data, &mut row);
+            Ok::<_, io::Error>(())
+        });
+        if let Err(err) = written.and_then(|()| writeln!(combined, "{row}")) {
+            result = Err(Failure::Output(err));
+            break;
+        }
+        if status.elapsed() >= STATUS_INTERVAL {
+            *status = Instant::now();
+            let statuses: Vec<_> = slots.iter().map(Slot::status).collect();
+            eprintln!("{}", statuses.join(" | "));
+        }
+        if slots
+            .iter()
+            .any(|slot| slot.health.failing(now, record.reconnect_after))
+        {
+            break;
+        }
+    }
+
+    let sensors = acquisition.join().into_sensors();
+    for (index, sensor) in connected.into_iter().zip(sensors) {
+        slots[index].sensor = Some(sensor);
+    }
+    result
+}
+
+/// Sleep for `duration`, waking up early once `token` is cancelled
+fn sleep(duration: Duration, token: &StopToken) {
+    let start = Instant::now();
+    while !token.is_cancelled() && start.elapsed() < duration {
+        thread::sleep(RECEIVE_TIMEOUT);
+    }
+}
+
+/// A sensor of the profile and what was recorded of it
+struct Slot {
+    name: String,
+    options: Profile,
+    /// `None` while disconnected, or while it is part of a group
+    sensor: Option<Sensor>,
+    /// `name channel [unit]` of every channel, the columns of the combined file
+    columns: Vec<String>,
+    csv: CsvWriter<BufWriter<File>>,
+    health: Health,
+    /// Failed reads in the current group
+    read_errors: u64,
+    /// Reconnects tried since the sensor last delivered frames
+    attempts: u64,
+    /// `false` once a reconnect failed, until one succeeds
+    connected: bool,
+    frames: u64,
+    /// Windows without frames of the sensor
+    gaps: u64,
+    reconnects: u64,
+}
+
+impl Slot {
+    fn connect(name: String, options: Profile, dir: &Path) -> Result<Self, Failure> {
+        let sensor = connect(&name, &options)?;
+        let columns = sensor
+            .channel_info()
+            .iter()
+            .map(|info| format!("{name} {}", info.label()))
+            .collect();
+        let file = BufWriter::new(File::create(dir.join(format!("{name}.csv")))?);
+        Ok(Self {
+            csv: CsvWriter::new(file, CsvOptions::default()),
+            name,
+            options,
+            sensor: Some(sensor),
+            columns,
+            health: Health::new(Instant::now()),
+            read_errors: 0,
+            attempts: 0,
+            connected: true,
+            frames: 0,
+            gaps: 0,
+            reconnects: 0,
+        })
+    }
+
+    /// Account for a window, `data` is `None` if the sensor delivered nothing
+    fn record(&mut self, data: Option<&Data>, read_errors: u64, now: Instant) -> io::Result<()> {
+        let failed = read_errors > self.read_errors;
+        self.read_errors = read_errors;
+        self.health.update(now, data.is_some(), failed);
+        match data {
+            Some(data) => {
+                self.attempts = 0;
+                self.frames += data.frame_count() as u64;
+                self.csv.write(data)?;
+                self.csv.flush()
+            }
+            None => {
+                self.gaps += 1;
+                Ok(())
+            }
+        }
+    }
+
+    /// Mean of every channel in the window appended to a row of the combined file,
+    /// empty columns without frames
+    fn combined_values(&self, data: Option<&Data>, row: &mut String) {
+        let means: Vec<_> = data.map_or(vec![], |data| {
+            let summary = data.summary();
+            summary
+                .channels
+                .into_iter()
+                .map(|channel| channel.mean)
+                .collect()
+        });
+        for index in 0..self.columns.len() {
+            row.push(',');
+            if let Some(mean) = means.get(index).copied().flatten() {
+                let _ = write!(row, "{mean}");
+            }
+        }
+    }
+
+    /// Release the sensor and connect again with the options of the profile
+    fn reconnect(&mut self, now: Instant) {
+        self.sensor = None;
+        self.attempts += 1;
+        eprintln!(
+            "medaq: {} failed, reconnecting, attempt {}",
+            self.name, self.attempts
+        );
+        match connect(&self.name, &self.options) {
+            Ok(sensor) => {
+                eprintln!("medaq: {} reconnected", self.name);
+                self.sensor = Some(sensor);
+                self.connected = true;
+                self.reconnects += 1;
+                self.health = Health::new(now);
+            }
+            Err(failure) => {
+                eprintln!("medaq: {}: {failure}", self.name);
+                self.connected = false;
+                self.health = Health::failed(now);
+            }
+        }
+    }
+
+    /// Part of the status line, e.g. `top: 1000 frames, 2 gaps`
+    fn status(&self) -> String {
+        let state = match self.connected {
+            true => "",
+            false => ", disconnected",
+        };
+        format!(
+            "{}: {} frames, {} gaps{state}",
+            self.name, self.frames, self.gaps
+        )
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{}: {} frames, {} gaps, {} reconnects",
+            self.name, self.frames, self.gaps, self.reconnects
+        )
+    }
+
+    fn json(&self) -> String {
+        format!(
+            "{{\"frames\":{},\"gaps\":{},\"reconnects\":{}}}",
+            self.frames, self.gaps, self.reconnects
+        )
+    }
+}
+
+fn connect(name: &str, options: &Profile) -> Result<Sensor, Failure> {
+    let builder =
+        builder(options).map_err(|message| Failure::Config(format!("{name}: {message}")))?;
+    builder.connect().map_err(Failure::Connect)
+}
+
+/// `time` of the window start followed by the columns of every sensor
+fn combined_header(slots: &[Slot]) -> String {
+    let columns: Vec<_> = slots
+        .iter()
+        .flat_map(|slot| &slot.columns)
+        .cloned()
+        .collect();
+    format!("time,{}", columns.join(","))
+}
+
+/// Seconds since the UNIX epoch with millisecond resolution
+fn seconds_since_epoch(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("{:.3}", since.as_secs_f64())
+}
+
+/// Whether a sensor stopped working and needs a reconnect
+#[derive(Debug, PartialEq)]
+struct Health {
+    /// The sensor last delivered frames, or was connected
+    since: Instant,
+    /// Reads failed since then
+    failed: bool,
+}
+
+impl Health {
+    fn new(now: Instant) -> Self {
+        Self {
+            since: now,
+            failed: false,
+        }
+    }
+
+    /// A reconnect failed at `now`
+    fn failed(now: Instant) -> Self {
+        Self {
+            since: now,
+            failed: true,
+        }
+    }
+
+    fn update(&mut self, now: Instant, delivered: bool, read_failed: bool) {
+        match delivered {
+            true => *self = Health::new(now),
+            false => self.failed |= read_failed,
+        }
+    }
+
+    /// Reads failed and nothing was delivered for `after`
+    fn failing(&self, now: Instant, after: Duration) -> bool {
+        self.failed && now.duration_since(self.since) >= after
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    use super::{seconds_since_epoch, Health};
+
+    #[test]
+    fn test_health_test() {
+        let start = Instant::now();
+        let after = Duration::from_secs(2);
+        let at = |seconds| start + Duration::from_secs(seconds);
+
+        let mut health = Health::new(start);
+        // a stall without errors, e.g. a slow sensor, is no failure
+        health.update(at(5), false, false);
+        assert!(!health.failing(at(5), after));
+
+        health.update(at(6), true, false);
+        health.update(at(7), false, true);
+        assert!(!health.failing(at(7), after));
+        health.update(at(8), false, false);
+        assert!(health.failing(at(8), after));
+
+        // frames again
+        health.update(at(9), true, false);
+        assert_eq!(health, Health::new(at(9)));
+
+        let health = Health::failed(at(10));
+        assert!(!health.failing(at(11), after));
+        assert!(health.failing(at(12), after));
+    }
+
+    #[test]
+    fn test_seconds_since_epoch_test() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+        assert_eq!(seconds_since_epoch(time), "1700000000.250");
+        assert_eq!(seconds_since_epoch(SystemTime::UNIX_EPOCH), "0.000");
+    }
+}
@@ -0,0 +1,104 @@
+//! `medaq serve`, the frames forwarded to TCP clients, e.g. a PLC
+
+use std::{
+    io::{self, Write},
+    num::NonZeroUsize,
+    thread,
+    time::Duration,
+};
+
+use clap::{Args, ValueEnum};
+use medaqlib::{AcquisitionConfig, LineFormat, RestreamConfig, TcpRestreamer};
+
+use crate::{params::object, parse_duration, stop_token, Connection, Failure, Format};
+
+/// Interval the clients and the stop request are checked
+const CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Args)]
+pub(crate) struct Serve {
+    /// Address to listen on, port 0 picks a free port
+    #[arg(long, default_value = "0.0.0.0:5000")]
+    listen: String,
+    /// Line sent per frame
+    #[arg(long, value_enum, default_value_t = Lines::KeyValue)]
+    lines: Lines,
+    /// Send only every Nth frame
+    #[arg(long, value_name = "N", default_value = "1")]
+    decimation: NonZeroUsize,
+    /// Lines queued per client, the oldest are dropped for slower clients
+    #[arg(long, default_value_t = 1024)]
+    queue: usize,
+    /// Stop after this time, e.g. 8h, serves until Ctrl-C without
+    #[arg(long, value_parser = parse_duration)]
+    duration: Option<Duration>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Lines {
+    /// `Distance=1.23;Intensity=OutOfRange`
+    KeyValue,
+    /// A JSON object per frame
+    #[cfg(feature = "serde")]
+    Jsonl,
+}
+
+impl Serve {
+    fn config(&self) -> RestreamConfig {
+        RestreamConfig {
+            format: match self.lines {
+                Lines::KeyValue => LineFormat::KeyValue,
+                #[cfg(feature = "serde")]
+                Lines::Jsonl => LineFormat::Jsonl(medaqlib::JsonlOptions::default()),
+            },
+            decimation: self.decimation.get(),
+            client_queue: self.queue.max(1),
+        }
+    }
+}
+
+pub(crate) fn serve(connection: &Connection, serve: &Serve, format: Format) -> Result<(), Failure> {
+    let sensor = connection.connect()?;
+    let restreamer = TcpRestreamer::bind(serve.listen.as_str(), serve.config())
+        .map_err(|err| Failure::Listen(serve.listen.clone(), err))?;
+    eprintln!("serving on {}", restreamer.local_addr());
+
+    let token = stop_token(serve.duration);
+    let acquisition = sensor.start_acquisition_with_sink(
+        AcquisitionConfig {
+            stop_token: Some(token.clone()),
+            ..AcquisitionConfig::default()
+        },
+        restreamer.clone(),
+    );
+    let mut clients = 0;
+    while !token.is_cancelled() && !acquisition.is_finished() {
+        thread::sleep(CHECK_INTERVAL);
+        if restreamer.clients() != clients {
+            clients = restreamer.clients();
+            eprintln!("{clients} clients connected");
+        }
+    }
+    let stats = acquisition.stats();
+    acquisition.join().close();
+
+    let mut out = io::stdout().lock();
+    if format == Format::Text {
+        writeln!(
+            out,
+            "{} frames read, {} lines dropped for slow clients",
+            stats.frames_read,
+            restreamer.dropped_lines()
+        )?;
+    } else {
+        let entries = [
+            ("frames".to_string(), stats.frames_read.to_string()),
+            (
+                "dropped_lines".to_string(),
+                restreamer.dropped_lines().to_string(),
+            ),
+        ];
+        out.write_all(object(entries, format.compact()).as_bytes())?;
+    }
+    Ok(())
+}
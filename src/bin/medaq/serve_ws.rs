@@ -0,0 +1,57 @@
+//! `medaq serve-ws`, the frames streamed to browsers over WebSocket
+
+use std::{thread, time::Duration};
+
+use clap::Args;
+use medaqlib::{AcquisitionConfig, WebsocketConfig, WebsocketStreamer};
+
+use crate::{parse_duration, stop_token, Connection, Failure};
+
+/// Interval the clients and the stop request are checked
+const CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Args)]
+pub(crate) struct ServeWs {
+    /// Address to listen on, port 0 picks a free port
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+    /// Messages buffered per browser, newer frames are dropped while it is full
+    #[arg(long, default_value_t = 256)]
+    queue: usize,
+    /// Stop after this time, e.g. 1h, serves until Ctrl-C without
+    #[arg(long, value_parser = parse_duration)]
+    duration: Option<Duration>,
+}
+
+pub(crate) fn serve_ws(connection: &Connection, serve: &ServeWs) -> Result<(), Failure> {
+    let sensor = connection.connect()?;
+    let token = stop_token(serve.duration);
+    let acquisition = sensor.start_acquisition(AcquisitionConfig {
+        stop_token: Some(token.clone()),
+        ..AcquisitionConfig::default()
+    });
+    let config = WebsocketConfig {
+        client_queue: serve.queue.max(1),
+        ..WebsocketConfig::default()
+    };
+    let streamer = match WebsocketStreamer::start(serve.listen.as_str(), acquisition, config) {
+        Ok(streamer) => streamer,
+        Err(err) => return Err(Failure::Listen(serve.listen.clone(), err)),
+    };
+    eprintln!("serving on ws://{}", streamer.local_addr());
+
+    let mut clients = 0;
+    while !token.is_cancelled() {
+        thread::sleep(CHECK_INTERVAL);
+        if streamer.clients() != clients {
+            clients = streamer.clients();
+            eprintln!("{clients} browsers connected");
+        }
+    }
+    let dropped = streamer.dropped();
+    streamer.stop().join().close();
+    if dropped > 0 {
+        eprintln!("{dropped} frames dropped for slow browsers");
+    }
+    Ok(())
+}
@@ -0,0 +1,306 @@
+//! `medaq watch`, statistics of the newest frames refreshed in place
+
+use std::{
+    fmt::Write as _,
+    io::{self, IsTerminal, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use medaqlib::{
+    AcquisitionConfig, ChannelSummary, Data, DropPolicy, RingBufferConfig, StopToken, Value,
+};
+
+use crate::{parse_duration, stop_token, Connection, Failure, Format};
+
+/// Longest sleep between checks for Ctrl-C
+const TOKEN_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Clear the terminal and move the cursor to the top left corner
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+#[derive(Args)]
+pub(crate) struct Watch {
+    /// Time between refreshes, e.g. 200ms or 1s
+    #[arg(long, value_parser = parse_duration, default_value = "500ms")]
+    refresh: Duration,
+    /// Number of the newest frames the statistics are calculated over
+    #[arg(long, default_value_t = 1000)]
+    window: usize,
+}
+
+pub(crate) fn watch(connection: &Connection, watch: &Watch, format: Format) -> Result<(), Failure> {
+    let sensor = connection.connect()?;
+    let token = stop_token(None);
+    let acquisition = sensor.start_acquisition(AcquisitionConfig {
+        ring_buffer: Some(RingBufferConfig {
+            capacity_frames: watch.window.max(1),
+            on_drop: DropPolicy::DropOldest,
+        }),
+        stop_token: Some(token.clone()),
+        ..AcquisitionConfig::default()
+    });
+    // plain lines when redirected, e.g. into a log file
+    let terminal = io::stdout().is_terminal();
+    let mut out = io::stdout().lock();
+    let mut rate = Rate::new();
+
+    while !acquisition.is_finished() {
+        sleep(watch.refresh, &token);
+        if token.is_cancelled() {
+            break;
+        }
+        // only read errors are sent in ring buffer mode, they are counted in the stats
+        acquisition.receiver().try_iter().for_each(drop);
+        let stats = acquisition.stats();
+        let view = View {
+            rate: rate.update(stats.frames_read),
+            window: acquisition.snapshot(),
+            read_errors: stats.read_errors,
+            last_error: stats.last_error,
+        };
+        match (format, terminal) {
+            (Format::Text, true) => write!(out, "{CLEAR}{}", view.table())?,
+            (Format::Text, false) => writeln!(out, "{}", view.line())?,
+            #[cfg(feature = "serde")]
+            (Format::Json | Format::Jsonl, _) => writeln!(out, "{}", view.json())?,
+        }
+        out.flush()?;
+    }
+    acquisition.join().close();
+    Ok(())
+}
+
+/// Sleep for `duration`, waking up early once `token` is cancelled
+fn sleep(duration: Duration, token: &StopToken) {
+    let start = Instant::now();
+    while !token.is_cancelled() {
+        let left = duration.saturating_sub(start.elapsed());
+        if left.is_zero() {
+            break;
+        }
+        thread::sleep(left.min(TOKEN_CHECK_INTERVAL));
+    }
+}
+
+/// Frames per second between successive updates
+struct Rate {
+    frames: u64,
+    at: Instant,
+}
+
+impl Rate {
+    fn new() -> Self {
+        Self {
+            frames: 0,
+            at: Instant::now(),
+        }
+    }
+
+    fn update(&mut self, frames: u64) -> f64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.at).as_secs_f64();
+        let rate = match elapsed {
+            0. => 0.,
+            elapsed => frames.saturating_sub(self.frames) as f64 / elapsed,
+        };
+        (self.frames, self.at) = (frames, now);
+        rate
+    }
+}
+
+/// What is shown after each refresh
+struct View {
+    /// Frames per second since the last refresh
+    rate: f64,
+    /// The newest frames
+    window: Data,
+    read_errors: u64,
+    last_error: Option<String>,
+}
+
+impl View {
+    /// Summary and latest value of every channel
+    fn channels(&self) -> Vec<(ChannelSummary, Option<Value<f64>>)> {
+        let latest: Vec<_> = match self.window.iter_values().next_back() {
+            Some(frame) => frame.map(|value| Some(value.value)).collect(),
+            None => vec![],
+        };
+        self.window
+            .summary()
+            .channels
+            .into_iter()
+            .enumerate()
+            .map(|(index, summary)| (summary, latest.get(index).copied().flatten()))
+            .collect()
+    }
+
+    /// A row per channel below a header, for a terminal cleared before
+    fn table(&self) -> String {
+        let mut table = format!(
+            "{:.1} frames/s, statistics of the last {} frames, Ctrl-C to exit\n\n",
+            self.rate,
+            self.window.frame_count()
+        );
+        let _ = writeln!(
+            table,
+            "{:<20} {:>14} {:>12} {:>12} {:>12} {:>8}",
+            "Channel", "Latest", "Min", "Max", "Mean", "Invalid"
+        );
+        for (summary, latest) in self.channels() {
+            let unit = summary.unit.as_deref().unwrap_or("");
+            let _ = writeln!(
+                table,
+                "{:<20} {:>14} {:>12} {:>12} {:>12} {:>7.1}%",
+                summary.channel,
+                format!("{} {unit}", number(latest.and_then(Value::into_raw))),
+                number(summary.min),
+                number(summary.max),
+                number(summary.mean),
+                (1. - summary.valid_ratio) * 100.
+            );
+        }
+        if let Some(last_error) = &self.last_error {
+            let _ = writeln!(
+                table,
+                "\n{} read errors, last: {last_error}",
+                self.read_errors
+            );
+        }
+        table
+    }
+
+    /// Everything on a single line, e.g.
+    /// `rate=1000.0/s | Distance [mm] latest=1.500 mean=1.500 min=1.000 max=2.000 valid=75.0%`
+    fn line(&self) -> String {
+        let mut line = format!("rate={:.1}/s", self.rate);
+        for (summary, latest) in self.channels() {
+            let label = match &summary.unit {
+                Some(unit) => format!("{} [{unit}]", summary.channel),
+                None => summary.channel.clone(),
+            };
+            let _ = write!(
+                line,
+                " | {label} latest={} mean={} min={} max={} valid={:.1}%",
+                number(latest.and_then(Value::into_raw)),
+                number(summary.mean),
+                number(summary.min),
+                number(summary.max),
+                summary.valid_ratio * 100.
+            );
+        }
+        if self.read_errors > 0 {
+            let _ = write!(line, " | read_errors={}", self.read_errors);
+        }
+        line
+    }
+
+    /// Everything as a JSON object on a single line, e.g.
+    /// `{"rate":1000.0,"frames":3,"channels":[{"channel":"Distance","unit":"mm",...}],...}`
+    #[cfg(feature = "serde")]
+    fn json(&self) -> String {
+        #[derive(serde::Serialize)]
+        struct Channel {
+            #[serde(flatten)]
+            summary: ChannelSummary,
+            latest: Option<f64>,
+        }
+
+        #[derive(serde::Serialize)]
+        struct Refresh<'a> {
+            rate: f64,
+            frames: usize,
+            channels: Vec<Channel>,
+            read_errors: u64,
+            last_error: Option<&'a str>,
+        }
+
+        let channels = self
+            .channels()
+            .into_iter()
+            .map(|(summary, latest)| Channel {
+                summary,
+                latest: latest.and_then(Value::into_raw),
+            })
+            .collect();
+        let refresh = Refresh {
+            rate: self.rate,
+            frames: self.window.frame_count(),
+            channels,
+            read_errors: self.read_errors,
+            last_error: self.last_error.as_deref(),
+        };
+        serde_json::to_string(&refresh).expect("refreshes serialize")
+    }
+}
+
+/// Value with three decimals, `-` if missing
+fn number(value: Option<f64>) -> String {
+    value.map_or("-".into(), |value| format!("{value:.3}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use medaqlib::{ChannelInfo, Data, SCALED_OUT_OF_RANGE as OOR};
+
+    use super::View;
+
+    fn view() -> View {
+        let channels = vec!["Distance".to_string(), "Intensity".to_string()];
+        let window = Data::new(channels, vec![], vec![1., 10., 2., OOR, 1.5, 30.])
+            .with_channel_info(vec![
+                ChannelInfo::new("Distance").with_unit("mm"),
+                ChannelInfo::new("Intensity"),
+            ]);
+        View {
+            rate: 1000.,
+            window,
+            read_errors: 0,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn test_watch_line_test() {
+        assert_eq!(
+            view().line(),
+            "rate=1000.0/s \
+             | Distance [mm] latest=1.500 mean=1.500 min=1.000 max=2.000 valid=100.0% \
+             | Intensity latest=30.000 mean=20.000 min=10.000 max=30.000 valid=66.7%"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_watch_json_test() {
+        assert_eq!(
+            view().json(),
+            "{\"rate\":1000.0,\"frames\":3,\"channels\":[\
+             {\"channel\":\"Distance\",\"unit\":\"mm\",\"mean\":1.5,\"min\":1.0,\"max\":2.0,\
+             \"valid_ratio\":1.0,\"latest\":1.5},\
+             {\"channel\":\"Intensity\",\"unit\":null,\"mean\":20.0,\"min\":10.0,\"max\":30.0,\
+             \"valid_ratio\":0.6666666666666666,\"latest\":30.0}],\
+             \"read_errors\":0,\"last_error\":null}"
+        );
+    }
+
+    #[test]
+    fn test_watch_table_test() {
+        let view = View {
+            read_errors: 2,
+            last_error: Some("sensor error: ERR_NOT_OPEN".into()),
+            ..view()
+        };
+        let table = view.table();
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(
+            lines[0],
+            "1000.0 frames/s, statistics of the last 3 frames, Ctrl-C to exit"
+        );
+        assert!(lines[2].starts_with("Channel "));
+        assert!(lines[3].starts_with("Distance ") && lines[3].contains("1.500 mm"));
+        assert!(lines[4].ends_with("33.3%"), "{}", lines[4]);
+        assert_eq!(lines[6], "2 read errors, last: sensor error: ERR_NOT_OPEN");
+    }
+}
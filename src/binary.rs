@@ -0,0 +1,391 @@
+//! Compact binary format for recording captures.
+//!
+//! Every capture is written as a self-contained record, so a file may hold
+//! any number of captures back to back:
+//!
+//! | field          | encoding                                          |
+//! |----------------|---------------------------------------------------|
+//! | magic          | `b"MEDQ"`                                         |
+//! | version        | `u16`                                             |
+//! | flags          | `u8`, raw present / scaled present / units present |
+//! | channel count  | `u16`                                             |
+//! | channel names  | `u16` length + UTF-8 bytes per channel             |
+//! | units          | `u16` length + UTF-8 bytes per channel, if present |
+//! | frame count    | `u64`                                             |
+//! | raw samples    | `i32` per value, if present                       |
+//! | scaled samples | `f64` per value, if present                       |
+//!
+//! All numbers are little endian.
+
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    sync::Arc,
+};
+
+use crate::{ChannelInfo, Data};
+
+const MAGIC: &[u8; 4] = b"MEDQ";
+/// Current version of the binary format
+pub const BINARY_FORMAT_VERSION: u16 = 1;
+
+const FLAG_RAW: u8 = 0b001;
+const FLAG_SCALED: u8 = 0b010;
+const FLAG_UNITS: u8 = 0b100;
+
+impl Data {
+    /// Write the capture in the crate's binary format
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let channels = self.channels();
+        let channel_count = u16::try_from(channels.len())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "too many channels"))?;
+        let frames = self.binary_frame_count()?;
+
+        let mut flags = 0;
+        if !self.raw_data.is_empty() {
+            flags |= FLAG_RAW;
+        }
+        if !self.scaled_data.is_empty() {
+            flags |= FLAG_SCALED;
+        }
+        if self.channel_info().is_some() {
+            flags |= FLAG_UNITS;
+        }
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&BINARY_FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&[flags])?;
+        writer.write_all(&channel_count.to_le_bytes())?;
+        for channel in channels {
+            write_str(&mut writer, channel)?;
+        }
+        if let Some(info) = self.channel_info() {
+            for info in info {
+                write_str(&mut writer, info.unit.as_deref().unwrap_or_default())?;
+            }
+        }
+        writer.write_all(&(frames as u64).to_le_bytes())?;
+
+        let mut buffer = Vec::with_capacity(self.raw_data.len() * 4 + self.scaled_data.len() * 8);
+        for value in &self.raw_data {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in &self.scaled_data {
+            buffer.extend_from_slice(&value.to_le_bytes());
+        }
+        writer.write_all(&buffer)
+    }
+
+    /// Read a capture written by [`Data::write_binary`]
+    ///
+    /// Unknown format versions are rejected with [`ErrorKind::InvalidData`].
+    pub fn read_binary<R: Read>(mut reader: R) -> io::Result<Data> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        read_record(&mut reader, magic, None)
+    }
+
+    fn binary_frame_count(&self) -> io::Result<usize> {
+        self.validate()
+            .map_err(|err| io::Error::new(ErrorKind::InvalidInput, err))?;
+        Ok(self.frame_count())
+    }
+}
+
+/// Appends captures to a binary recording
+pub struct BinaryRecorder<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> BinaryRecorder<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Append the capture to the recording
+    pub fn record(&mut self, data: &Data) -> io::Result<()> {
+        data.write_binary(&mut self.writer)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Iterates over the captures of a binary recording
+///
+/// Captures with the same channels share the channel names, just like
+/// captures read from a [`Sensor`](crate::Sensor).
+pub struct BinaryReader<R: Read> {
+    reader: R,
+    channels: Option<Arc<[String]>>,
+}
+
+impl<R: Read> BinaryReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            channels: None,
+        }
+    }
+
+    /// Read the next capture, `Ok(None)` at the end of the recording
+    pub fn read_next(&mut self) -> io::Result<Option<Data>> {
+        let mut magic = [0; 4];
+        let read = read_fill(&mut self.reader, &mut magic)?;
+        match read {
+            0 => return Ok(None),
+            4 => (),
+            _ => return Err(ErrorKind::UnexpectedEof.into()),
+        }
+        let data = read_record(&mut self.reader, magic, self.channels.as_ref())?;
+        self.channels = Some(data.channels_arc());
+        Ok(Some(data))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for BinaryReader<R> {
+    type Item = io::Result<Data>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_next().transpose()
+    }
+}
+
+fn read_record<R: Read>(
+    reader: &mut R,
+    magic: [u8; 4],
+    previous_channels: Option<&Arc<[String]>>,
+) -> io::Result<Data> {
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "not a binary recording",
+        ));
+    }
+    let version = u16::from_le_bytes(read_array(reader)?);
+    if version != BINARY_FORMAT_VERSION {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("unsupported binary format version {version}"),
+        ));
+    }
+    let [flags] = read_array(reader)?;
+    let channel_count = u16::from_le_bytes(read_array(reader)?) as usize;
+
+    let names = (0..channel_count)
+        .map(|_| read_str(reader))
+        .collect::<io::Result<Vec<_>>>()?;
+    let units = if flags & FLAG_UNITS != 0 {
+        let units = (0..channel_count)
+            .map(|_| read_str(reader))
+            .collect::<io::Result<Vec<_>>>()?;
+        Some(units)
+    } else {
+        None
+    };
+
+    let frames = u64::from_le_bytes(read_array(reader)?);
+    let values = usize::try_from(frames)
+        .ok()
+        .and_then(|frames| frames.checked_mul(channel_count))
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "frame count too large"))?;
+
+    let raw_data = if flags & FLAG_RAW != 0 {
+        read_values(reader, values, i32::from_le_bytes)?
+    } else {
+        vec![]
+    };
+    let scaled_data = if flags & FLAG_SCALED != 0 {
+        read_values(reader, values, f64::from_le_bytes)?
+    } else {
+        vec![]
+    };
+
+    let channels: Arc<[String]> = match previous_channels {
+        Some(previous) if previous[..] == names[..] => Arc::clone(previous),
+        _ => names.into(),
+    };
+    let data = Data::new(Arc::clone(&channels), raw_data, scaled_data);
+    Ok(match units {
+        Some(units) => {
+            let info: Vec<_> = channels
+                .iter()
+                .zip(units)
+                .map(|(name, unit)| ChannelInfo {
+                    name: name.clone(),
+                    unit: Some(unit).filter(|unit| !unit.is_empty()),
+                    ..Default::default()
+                })
+                .collect();
+            data.with_channel_info(info)
+        }
+        None => data,
+    })
+}
+
+fn write_str<W: Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    let len = u16::try_from(s.len())
+        .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "channel name too long"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(s.as_bytes())
+}
+
+fn read_str<R: Read>(reader: &mut R) -> io::Result<String> {
+    let len = u16::from_le_bytes(read_array(reader)?) as usize;
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_values<R: Read, T, const N: usize>(
+    reader: &mut R,
+    values: usize,
+    from_le_bytes: fn([u8; N]) -> T,
+) -> io::Result<Vec<T>> {
+    let len = values
+        .checked_mul(N)
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "frame count too large"))?;
+    let mut bytes = vec![];
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes
+        .chunks_exact(N)
+        .map(|chunk| from_le_bytes(chunk.try_into().expect("chunk has N bytes")))
+        .collect())
+}
+
+/// Like `read_exact` but returns the number of bytes read when hitting the end
+fn read_fill<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == ErrorKind::Interrupted => (),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use crate::{BinaryReader, BinaryRecorder, ChannelInfo, Data};
+
+    fn data() -> Data {
+        Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![1, 2, -3, 4],
+            vec![1.5, 2., -1.7976931348623157e308, 4.],
+        )
+    }
+
+    fn assert_same(left: &Data, right: &Data) {
+        assert_eq!(left.channels(), right.channels());
+        assert_eq!(left.channel_info(), right.channel_info());
+        assert_eq!(left.raw_data, right.raw_data);
+        assert_eq!(left.scaled_data, right.scaled_data);
+    }
+
+    #[test]
+    fn test_binary_round_trip_test() {
+        let data = data().with_channel_info(vec![
+            ChannelInfo::new("Distance").with_unit("mm"),
+            ChannelInfo::new("Intensity"),
+        ]);
+        let mut bytes = vec![];
+        data.write_binary(&mut bytes).unwrap();
+
+        let read = Data::read_binary(&bytes[..]).unwrap();
+        assert_same(&data, &read);
+    }
+
+    #[test]
+    fn test_binary_round_trip_scaled_only_test() {
+        let data = Data::new(vec!["Distance".to_string()], vec![], vec![1., 2., 3.]);
+        let mut bytes = vec![];
+        data.write_binary(&mut bytes).unwrap();
+
+        let read = Data::read_binary(&bytes[..]).unwrap();
+        assert_same(&data, &read);
+    }
+
+    #[test]
+    fn test_binary_recorder_multiple_captures_test() {
+        let mut recorder = BinaryRecorder::new(vec![]);
+        recorder.record(&data()).unwrap();
+        recorder.record(&data()).unwrap();
+        let bytes = recorder.into_inner().unwrap();
+
+        let captures: Vec<_> = BinaryReader::new(&bytes[..])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(captures.len(), 2);
+        assert_same(&captures[0], &data());
+        assert_same(&captures[1], &data());
+        assert!(std::ptr::eq(captures[0].channels(), captures[1].channels()));
+    }
+
+    #[test]
+    fn test_binary_rejects_unknown_version_test() {
+        let mut bytes = vec![];
+        data().write_binary(&mut bytes).unwrap();
+        bytes[4..6].copy_from_slice(&2u16.to_le_bytes());
+
+        let err = Data::read_binary(&bytes[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("version 2"));
+    }
+
+    #[test]
+    fn test_binary_rejects_bad_magic_test() {
+        let err = Data::read_binary(&b"CSV,1,2,3,4,5,6"[..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_binary_reader_truncated_test() {
+        let mut bytes = vec![];
+        data().write_binary(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 3);
+
+        let mut reader = BinaryReader::new(&bytes[..]);
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_binary_rejects_partial_frames_test() {
+        let data = Data::new(vec!["1".to_string(), "2".to_string()], vec![], vec![1.]);
+        let err = data.write_binary(vec![]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    fmt::{Debug, Display},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    acquisition::{Acquire, Acquisition, Control, Deliver},
+    AcquisitionConfig, Data, MedaqError, Sensor,
+};
+
+/// Failure reported to [`CallbackConfig::on_error`]
+#[derive(Debug)]
+pub enum CallbackError {
+    /// Reading from the sensor failed
+    Read(MedaqError),
+    /// The data callback panicked with the given message, the acquisition continues
+    Panic(String),
+}
+
+impl Display for CallbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallbackError::Read(err) => write!(f, "read failed: {err}"),
+            CallbackError::Panic(message) => write!(f, "data callback panicked: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CallbackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CallbackError::Read(err) => Some(err),
+            CallbackError::Panic(_) => None,
+        }
+    }
+}
+
+/// Hook receiving read errors and panics of the data callback
+pub type ErrorHook = Arc<dyn Fn(CallbackError) + Send + Sync>;
+
+/// Configuration of [`Sensor::on_data`]
+#[derive(Clone, Default)]
+pub struct CallbackConfig {
+    pub acquisition: AcquisitionConfig,
+    /// Discard captures arriving while the callback is still running instead of
+    /// waiting for it
+    pub drop_when_busy: bool,
+    /// Called on the acquisition thread for every error, errors are logged as
+    /// warnings through the `log` facade if not set
+    pub on_error: Option<ErrorHook>,
+}
+
+impl Debug for CallbackConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackConfig")
+            .field("acquisition", &self.acquisition)
+            .field("drop_when_busy", &self.drop_when_busy)
+            .field("on_error", &self.on_error.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
+
+impl Sensor {
+    /// Move the sensor to a background acquisition calling `callback` for every capture
+    ///
+    /// The callback runs on the acquisition thread, so the sensor is not read while
+    /// it runs: a slow callback makes the backlog in the sensor buffer grow but no
+    /// data is lost. With [`CallbackConfig::drop_when_busy`] the callback runs on a
+    /// thread of its own instead and captures arriving while it is busy are
+    /// dropped, see [`CallbackHandle::dropped`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use medaqlib::{CallbackConfig, Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// let handle = sensor.on_data(|data| println!("{data}"), CallbackConfig::default());
+    /// std::thread::sleep(std::time::Duration::from_secs(10));
+    /// let sensor = handle.join();
+    /// ```
+    pub fn on_data(
+        self,
+        callback: impl FnMut(Data) + Send + 'static,
+        config: CallbackConfig,
+    ) -> CallbackHandle {
+        let (acquisition, dropped) = start(self, callback, config);
+        CallbackHandle {
+            acquisition,
+            dropped,
+        }
+    }
+}
+
+/// Background acquisition calling a callback, see [`Sensor::on_data`]
+///
+/// Dropping the handle stops the acquisition, waits for a running callback and
+/// releases the sensor.
+pub struct CallbackHandle {
+    acquisition: Acquisition<Sensor>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl CallbackHandle {
+    /// Ask the thread to stop after the current read or callback
+    pub fn stop(&self) {
+        self.acquisition.stop()
+    }
+
+    /// Number of captures dropped because the callback was busy
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stop the acquisition and get the sensor back
+    pub fn join(mut self) -> Sensor {
+        self.acquisition.join()
+    }
+}
+
+fn start<S: Acquire + 'static>(
+    source: S,
+    callback: impl FnMut(Data) + Send + 'static,
+    config: CallbackConfig,
+) -> (Acquisition<S>, Arc<AtomicU64>) {
+    let on_error = config
+        .on_error
+        .unwrap_or_else(|| Arc::new(|err| log::warn!("acquisition error: {err}")));
+    let dropped = Arc::new(AtomicU64::new(0));
+    let deliver = match config.drop_when_busy {
+        false => Callback::Direct(callback_with_hook(callback, Arc::clone(&on_error))),
+        true => Callback::Worker(Worker::spawn(
+            callback_with_hook(callback, Arc::clone(&on_error)),
+            Arc::clone(&dropped),
+        )),
+    };
+    let deliver = CallbackDeliver { deliver, on_error };
+    (
+        Acquisition::start(source, config.acquisition, deliver),
+        dropped,
+    )
+}
+
+/// Call the data callback, reporting panics to the hook
+fn callback_with_hook(
+    mut callback: impl FnMut(Data) + Send + 'static,
+    on_error: ErrorHook,
+) -> Box<dyn FnMut(Data) + Send> {
+    Box::new(move |data| {
+        if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| callback(data))) {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".into());
+            on_error(CallbackError::Panic(message));
+        }
+    })
+}
+
+struct CallbackDeliver {
+    deliver: Callback,
+    on_error: ErrorHook,
+}
+
+enum Callback {
+    Direct(Box<dyn FnMut(Data) + Send>),
+    Worker(Worker),
+}
+
+impl Deliver for CallbackDeliver {
+    fn deliver(&mut self, result: Result<Data, MedaqError>, _: &Control) -> bool {
+        match (result, &mut self.deliver) {
+            (Ok(data), Callback::Direct(callback)) => callback(data),
+            (Ok(data), Callback::Worker(worker)) => worker.offer(data),
+            (Err(err), _) => (self.on_error)(CallbackError::Read(err)),
+        }
+        true
+    }
+}
+
+/// Thread running the callback, accepting captures only while idle
+struct Worker {
+    sender: Option<SyncSender<Data>>,
+    thread: Option<JoinHandle<()>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Worker {
+    fn spawn(mut callback: Box<dyn FnMut(Data) + Send>, dropped: Arc<AtomicU64>) -> Self {
+        // without capacity a send only succeeds while the worker waits for data
+        let (sender, receiver) = mpsc::sync_channel::<Data>(0);
+        let thread = thread::Builder::new()
+            .name("medaqlib-callback".into())
+            .spawn(move || receiver.iter().for_each(&mut callback))
+            .expect("could not spawn callback thread");
+        Self {
+            sender: Some(sender),
+            thread: Some(thread),
+            dropped,
+        }
+    }
+
+    fn offer(&mut self, data: Data) {
+        let sender = self.sender.as_ref().expect("sender lives until drop");
+        if let Err(TrySendError::Full(_)) = sender.try_send(data) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{mpsc, Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use super::start;
+    use crate::{
+        acquisition::tests::{data, Script},
+        AcquisitionConfig, CallbackConfig, CallbackError, MedaqError,
+    };
+
+    fn config() -> CallbackConfig {
+        CallbackConfig {
+            acquisition: AcquisitionConfig {
+                poll_interval: Duration::from_millis(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_on_data_calls_callback_for_every_capture_test() {
+        let results = (0..20).map(|i| Ok(Some(data(i as f64)))).collect();
+        let (sender, receiver) = mpsc::channel();
+        let callback = move |data: crate::Data| {
+            std::thread::sleep(Duration::from_millis(1));
+            sender.send(data.scaled_data[0]).unwrap();
+        };
+        let (mut acquisition, dropped) = start(Script::new(results), callback, config());
+
+        let values: Vec<f64> = receiver.iter().take(20).collect();
+        assert_eq!(values, (0..20).map(|i| i as f64).collect::<Vec<_>>());
+        assert_eq!(dropped.load(std::sync::atomic::Ordering::Relaxed), 0);
+        acquisition.join();
+    }
+
+    #[test]
+    fn test_on_data_reports_errors_and_panics_test() {
+        let errors = Arc::new(Mutex::new(vec![]));
+        let config = CallbackConfig {
+            on_error: Some({
+                let errors = Arc::clone(&errors);
+                Arc::new(move |err: CallbackError| errors.lock().unwrap().push(err.to_string()))
+            }),
+            ..config()
+        };
+        let script = Script::new(vec![
+            Ok(Some(data(1.))),
+            Err(MedaqError::NoChannels),
+            Ok(Some(data(2.))),
+        ]);
+        let (sender, receiver) = mpsc::channel();
+        let callback = move |data: crate::Data| {
+            if data.scaled_data[0] == 1. {
+                panic!("bad value");
+            }
+            sender.send(data.scaled_data[0]).unwrap();
+        };
+        let (mut acquisition, _) = start(script, callback, config);
+
+        // the acquisition survives the panic
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(2.));
+        acquisition.join();
+        assert_eq!(
+            *errors.lock().unwrap(),
+            vec![
+                "data callback panicked: bad value",
+                "read failed: sensor does not transmit any channels"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_on_data_drop_when_busy_test() {
+        let results = (0..50).map(|i| Ok(Some(data(i as f64)))).collect();
+        let calls = Arc::new(Mutex::new(0));
+        let callback = {
+            let calls = Arc::clone(&calls);
+            move |_| {
+                std::thread::sleep(Duration::from_millis(20));
+                *calls.lock().unwrap() += 1;
+            }
+        };
+        let config = CallbackConfig {
+            drop_when_busy: true,
+            ..config()
+        };
+        let script = Script::new(results);
+        let (mut acquisition, dropped) = start(script, callback, config);
+
+        let total = || *calls.lock().unwrap() + dropped.load(std::sync::atomic::Ordering::Relaxed);
+        wait_until(|| total() == 50);
+        acquisition.join();
+        assert!(dropped.load(std::sync::atomic::Ordering::Relaxed) > 0);
+        assert!(*calls.lock().unwrap() > 0);
+    }
+}
@@ -0,0 +1,149 @@
+use std::ops::Range;
+
+use crate::{measuring_range::millimetres, ChannelInfo, Sensor, ME_SENSOR};
+
+/// What the connected sensor offers, see [`Sensor::capabilities`]
+///
+/// Known sensor types are described by a table of their data sheets, the typed
+/// settings of [`sensors`](crate::sensors) refuse what a sensor lacks with
+/// [`MedaqError::Unsupported`](crate::MedaqError::Unsupported) before calling
+/// MEDAQLib. Other sensor types are assumed to support everything, with as many
+/// channels as transmitted when connecting.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Laser power, or switching the laser off
+    pub supports_laser_control: bool,
+    /// Triggered measurements
+    pub supports_trigger: bool,
+    /// Thickness of transparent or double sided targets
+    pub supports_thickness: bool,
+    /// Analog output mirroring a measurement, see
+    /// [`Sensor::configure_analog_output`]
+    pub supports_analog_output: bool,
+    /// Digital limit switch outputs, see [`Sensor::configure_limit_switch`], any
+    /// number for types not in the table
+    pub limit_switches: usize,
+    /// Measurement channels of the device
+    pub max_channels: usize,
+    /// Measuring rate presets in Hz, empty if any rate is passed on to the sensor
+    pub supported_measuring_rates: Vec<f64>,
+    /// Scaled range of the first distance channel in mm as reported when
+    /// connecting, `None` without a range or with an unknown unit, see
+    /// [`Sensor::set_measuring_range`]
+    pub full_scale: Option<Range<f64>>,
+}
+
+/// Rate presets of the ILD1420 in Hz
+const ILD1420_RATES: [f64; 5] = [250., 500., 1000., 2000., 4000.];
+/// Rate presets of the ILD1900 in Hz
+const ILD1900_RATES: [f64; 7] = [250., 500., 1000., 2000., 4000., 8000., 10000.];
+
+/// Laser control, thickness, analog output, limit switches, channels and rate
+/// presets of a sensor type
+type Row = (bool, bool, bool, usize, usize, &'static [f64]);
+
+impl Capabilities {
+    /// The capabilities of the sensor type transmitting `channel_info`, as many
+    /// channels as transmitted for types not in the table
+    pub(crate) fn of(sensor_type: ME_SENSOR, channel_info: &[ChannelInfo]) -> Self {
+        let transmitted = channel_info.len();
+        let (laser, thickness, analog, switches, channels, rates): Row = match sensor_type {
+            ME_SENSOR::SENSOR_ILD1420 => (true, false, true, 1, 1, &ILD1420_RATES),
+            ME_SENSOR::SENSOR_ILD1900 => (true, false, true, 2, 1, &ILD1900_RATES),
+            ME_SENSOR::SENSOR_ILD1220
+            | ME_SENSOR::SENSOR_ILD1320
+            | ME_SENSOR::SENSOR_ILD1750
+            | ME_SENSOR::SENSOR_ILD2300
+            | ME_SENSOR::SENSOR_ILD5500 => (true, false, true, 1, 1, &[]),
+            ME_SENSOR::SENSOR_IFD2451
+            | ME_SENSOR::SENSOR_IFD2461
+            | ME_SENSOR::SENSOR_IFD2465
+            | ME_SENSOR::SENSOR_IFD2471 => (false, true, true, 2, 2, &[]),
+            ME_SENSOR::SENSOR_DT6120 => (false, false, true, 0, 1, &[]),
+            ME_SENSOR::CONTROLLER_DT6200 => (false, false, false, 0, 8, &[]),
+            ME_SENSOR::SENSOR_ODC2500
+            | ME_SENSOR::SENSOR_ODC2520
+            | ME_SENSOR::SENSOR_ODC2600
+            | ME_SENSOR::SENSOR_ODC2700 => (false, false, true, 2, 1, &[]),
+            ME_SENSOR::THICKNESS_SENSOR => (true, true, false, 2, 2, &[]),
+            _ => (true, true, true, usize::MAX, transmitted, &[]),
+        };
+        Self {
+            supports_laser_control: laser,
+            supports_trigger: true,
+            supports_thickness: thickness,
+            supports_analog_output: analog,
+            limit_switches: switches,
+            max_channels: channels,
+            supported_measuring_rates: rates.to_vec(),
+            full_scale: channel_info.iter().find_map(|info| {
+                let factor = millimetres(info.unit.as_deref())?;
+                let range = info.range.as_ref()?;
+                Some(range.start * factor..range.end * factor)
+            }),
+        }
+    }
+
+    /// Whether the sensor offers the rate, any rate without known presets
+    pub fn supports_measuring_rate(&self, hz: f64) -> bool {
+        self.supported_measuring_rates.is_empty() || self.supported_measuring_rates.contains(&hz)
+    }
+}
+
+impl Sensor {
+    /// What the sensor offers, found when connecting
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Interface, MockBackend, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::CONTROLLER_DT6200)
+    ///     .with_interface(Interface::Simulated)
+    ///     .with_backend(MockBackend::new().with_channels(["Channel 1"]))
+    ///     .connect()
+    ///     .unwrap();
+    /// assert_eq!(sensor.capabilities().max_channels, 8);
+    /// assert!(!sensor.capabilities().supports_laser_control);
+    /// ```
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Capabilities;
+    use crate::{ChannelInfo, MockBackend, ME_SENSOR};
+
+    #[test]
+    fn test_capabilities_test() {
+        let channels = [
+            ChannelInfo::new("Intensity").with_unit("%"),
+            ChannelInfo::new("Distance")
+                .with_unit("µm")
+                .with_range(-500.0..500.),
+        ];
+        let ild = Capabilities::of(ME_SENSOR::SENSOR_ILD1420, &channels);
+        assert!(ild.supports_laser_control && !ild.supports_thickness);
+        assert_eq!(ild.max_channels, 1);
+        assert!(ild.supports_measuring_rate(4000.));
+        assert!(!ild.supports_measuring_rate(8000.));
+        assert!(ild.supports_analog_output);
+        assert_eq!(ild.limit_switches, 1);
+        assert_eq!(ild.full_scale, Some(-0.5..0.5));
+        let dt6200 = Capabilities::of(ME_SENSOR::CONTROLLER_DT6200, &channels[..1]);
+        assert!(!dt6200.supports_analog_output);
+        assert_eq!(dt6200.max_channels, 8);
+        assert_eq!(dt6200.full_scale, None);
+
+        // unknown types are not restricted
+        let mock = MockBackend::new().with_channels(["Distance", "Intensity", "Status"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_LLT27xx);
+        let capabilities = sensor.capabilities();
+        assert_eq!(capabilities.max_channels, 3);
+        assert!(capabilities.supports_trigger && capabilities.supports_laser_control);
+        assert!(capabilities.supports_measuring_rate(123.));
+    }
+}
@@ -0,0 +1,281 @@
+//! C API of the `capi` feature, see `include/medaqlib.h`
+//!
+//! Build the shared library with
+//! `cargo rustc --release --lib --features capi --crate-type cdylib`. Every function
+//! catches panics and reports them as [`MedaqStatus::Panic`].
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use crate::{Interface, MedaqError, Sensor, SensorBuilder, Value, ME_SENSOR};
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::default();
+}
+
+/// Result of a call of the C API
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MedaqStatus {
+    Ok = 0,
+    /// The call failed, see `medaq_last_error_message`
+    Error = -1,
+    /// A pointer was null, a string not valid UTF-8 or a buffer too small for a
+    /// frame
+    InvalidArgument = -2,
+    /// The call panicked, see `medaq_last_error_message`
+    Panic = -3,
+}
+
+/// Sensor to connect to with `medaq_connect`
+#[repr(C)]
+pub struct MedaqConfig {
+    /// Sensor type, e.g. `"SENSOR_IFD2421"` or `"IFD2421"`
+    pub sensor_type: *const c_char,
+    /// Interface, e.g. `"TCP/IP"` or `"Simulated"`
+    pub interface: *const c_char,
+    /// IP address of the sensor, null for the simulator
+    pub ip_address: *const c_char,
+}
+
+/// Connected sensor, values read but not yet returned by `medaq_read` stay buffered
+pub struct MedaqSensor {
+    sensor: Sensor,
+    /// Scaled values of whole frames, out of range values are NaN
+    pending: VecDeque<f64>,
+}
+
+/// Message of the last failed call on this thread, empty if none failed
+///
+/// The string stays valid until the next call on the same thread.
+#[no_mangle]
+pub extern "C" fn medaq_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ptr())
+}
+
+/// Connect to a sensor, null if connecting fails
+///
+/// # Safety
+/// `config` is null or points to a config whose strings are null or nul terminated.
+#[no_mangle]
+pub unsafe extern "C" fn medaq_connect(config: *const MedaqConfig) -> *mut MedaqSensor {
+    let mut sensor = ptr::null_mut();
+    guard(|| {
+        let config = unsafe { config.as_ref() }.ok_or(Failure::InvalidArgument)?;
+        let sensor_type: ME_SENSOR = unsafe { string(config.sensor_type) }?
+            .ok_or(Failure::InvalidArgument)?
+            .parse()?;
+        let interface: Interface = unsafe { string(config.interface) }?
+            .ok_or(Failure::InvalidArgument)?
+            .parse()?;
+        let mut builder = SensorBuilder::new(sensor_type).with_interface(interface);
+        if let Some(ip_address) = unsafe { string(config.ip_address) }? {
+            builder = builder.with_ip_address(ip_address);
+        }
+        sensor = Box::into_raw(Box::new(MedaqSensor {
+            sensor: builder.connect()?,
+            pending: VecDeque::new(),
+        }));
+        Ok(())
+    });
+    sensor
+}
+
+/// Number of channels of the sensor, `0` for null
+///
+/// # Safety
+/// `sensor` is null or returned by `medaq_connect` and not closed.
+#[no_mangle]
+pub unsafe extern "C" fn medaq_channel_count(sensor: *const MedaqSensor) -> usize {
+    unsafe { sensor.as_ref() }.map_or(0, |sensor| sensor.sensor.parameters().len())
+}
+
+/// Read whole frames of scaled values into `out_frames`, out of range values are
+/// NaN
+///
+/// `*out_len` is the capacity of `out_frames` in values before the call, at least
+/// `medaq_channel_count` values, and the number of values written after it. Frames
+/// that do not fit are returned by the next calls, which read from the sensor
+/// again once fewer values than their capacity are left.
+///
+/// # Safety
+/// `sensor` is null or returned by `medaq_connect` and not closed, `out_frames`
+/// holds `*out_len` values.
+#[no_mangle]
+pub unsafe extern "C" fn medaq_read(
+    sensor: *mut MedaqSensor,
+    out_frames: *mut f64,
+    out_len: *mut usize,
+) -> MedaqStatus {
+    guard(|| {
+        let sensor = unsafe { sensor.as_mut() }.ok_or(Failure::InvalidArgument)?;
+        let out_len = unsafe { out_len.as_mut() }.ok_or(Failure::InvalidArgument)?;
+        let channels = sensor.sensor.parameters().len().max(1);
+        if out_frames.is_null() || *out_len < channels {
+            return Err(Failure::InvalidArgument);
+        }
+        let capacity = std::mem::take(out_len);
+
+        // the values buffered stay below the capacity and one read
+        if sensor.pending.len() < capacity {
+            if let Some(data) = sensor.sensor.read_data()? {
+                let values =
+                    data.scaled_data
+                        .iter()
+                        .map(|&value| match Value::from_sample(value) {
+                            Value::Valid(value) => value,
+                            Value::OutOfRange => f64::NAN,
+                        });
+                sensor.pending.extend(values);
+            }
+        }
+        let len = sensor.pending.len().min(capacity) / channels * channels;
+        for (index, value) in sensor.pending.drain(..len).enumerate() {
+            unsafe { out_frames.add(index).write(value) };
+        }
+        *out_len = len;
+        Ok(())
+    })
+}
+
+/// Disconnect and free the sensor, does nothing for null
+///
+/// # Safety
+/// `sensor` is null or returned by `medaq_connect` and not closed.
+#[no_mangle]
+pub unsafe extern "C" fn medaq_close(sensor: *mut MedaqSensor) {
+    if !sensor.is_null() {
+        guard(|| {
+            drop(unsafe { Box::from_raw(sensor) });
+            Ok(())
+        });
+    }
+}
+
+enum Failure {
+    InvalidArgument,
+    Medaq(MedaqError),
+}
+
+impl From<MedaqError> for Failure {
+    fn from(err: MedaqError) -> Self {
+        Failure::Medaq(err)
+    }
+}
+
+/// Run `call` catching panics and store the message of a failure
+fn guard(call: impl FnOnce() -> Result<(), Failure>) -> MedaqStatus {
+    let (status, message) = match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => (MedaqStatus::Ok, String::new()),
+        Ok(Err(Failure::InvalidArgument)) => {
+            (MedaqStatus::InvalidArgument, "invalid argument".into())
+        }
+        Ok(Err(Failure::Medaq(err))) => (MedaqStatus::Error, err.to_string()),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            (MedaqStatus::Panic, format!("panic: {message}"))
+        }
+    };
+    let message = CString::new(message.replace('\0', " ")).expect("nul bytes are replaced");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = message);
+    status
+}
+
+/// The nul terminated string, `None` for null
+unsafe fn string<'a>(value: *const c_char) -> Result<Option<&'a str>, Failure> {
+    if value.is_null() {
+        return Ok(None);
+    }
+    let value = unsafe { CStr::from_ptr(value) };
+    value
+        .to_str()
+        .map(Some)
+        .map_err(|_| Failure::InvalidArgument)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CStr, ptr, time::Duration};
+
+    use super::{
+        medaq_channel_count, medaq_close, medaq_connect, medaq_last_error_message, medaq_read,
+        MedaqConfig, MedaqStatus,
+    };
+
+    fn last_error() -> String {
+        let message = unsafe { CStr::from_ptr(medaq_last_error_message()) };
+        message.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_capi_read_test() {
+        let config = MedaqConfig {
+            sensor_type: c"IFD2421".as_ptr(),
+            interface: c"Simulated".as_ptr(),
+            ip_address: ptr::null(),
+        };
+        let sensor = unsafe { medaq_connect(&config) };
+        assert!(!sensor.is_null(), "{}", last_error());
+        assert_eq!(unsafe { medaq_channel_count(sensor) }, 1);
+
+        std::thread::sleep(Duration::from_millis(20));
+        let mut frames = [0.; 4];
+        let mut len = frames.len();
+        let status = unsafe { medaq_read(sensor, frames.as_mut_ptr(), &mut len) };
+        assert_eq!(status, MedaqStatus::Ok);
+        assert_eq!(len, 4);
+        assert_eq!(last_error(), "");
+
+        // the rest stays buffered for the next call, which reads no more
+        let pending = unsafe { &(*sensor).pending }.len();
+        assert!(pending >= 4, "{pending} values buffered");
+        let mut len = frames.len();
+        unsafe { medaq_read(sensor, frames.as_mut_ptr(), &mut len) };
+        assert_eq!(len, 4);
+        assert_eq!(unsafe { &(*sensor).pending }.len(), pending - 4);
+
+        // not even a frame fits
+        let mut len = 0;
+        let status = unsafe { medaq_read(sensor, frames.as_mut_ptr(), &mut len) };
+        assert_eq!(status, MedaqStatus::InvalidArgument);
+        let mut len = frames.len();
+        let status = unsafe { medaq_read(sensor, ptr::null_mut(), &mut len) };
+        assert_eq!(status, MedaqStatus::InvalidArgument);
+        unsafe { medaq_close(sensor) };
+    }
+
+    #[test]
+    fn test_capi_errors_test() {
+        let config = MedaqConfig {
+            sensor_type: c"IFD2421".as_ptr(),
+            interface: c"USB".as_ptr(),
+            ip_address: ptr::null(),
+        };
+        assert!(unsafe { medaq_connect(&config) }.is_null());
+        assert_eq!(last_error(), "unknown interface \"USB\"");
+        assert!(unsafe { medaq_connect(ptr::null()) }.is_null());
+        assert_eq!(last_error(), "invalid argument");
+
+        let mut len = 0;
+        let status = unsafe { medaq_read(ptr::null_mut(), ptr::null_mut(), &mut len) };
+        assert_eq!(status, MedaqStatus::InvalidArgument);
+        assert_eq!(unsafe { medaq_channel_count(ptr::null()) }, 0);
+        unsafe { medaq_close(ptr::null_mut()) };
+    }
+
+    #[test]
+    fn test_capi_catches_panics_test() {
+        let status = super::guard(|| panic!("broken"));
+        assert_eq!(status, MedaqStatus::Panic);
+        assert_eq!(last_error(), "panic: broken");
+    }
+}
@@ -0,0 +1,92 @@
+use std::{fmt::Display, ops::Range};
+
+/// Metadata of a channel transmitted by the sensor
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChannelInfo {
+    /// Name of the channel as reported by the sensor
+    pub name: String,
+    /// Unit of the scaled values, if reported by the sensor
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub unit: Option<String>,
+    /// Factor from raw counts to scaled values, if reported by the sensor
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub scale: Option<f64>,
+    /// Scaled value of a raw count of zero, if reported by the sensor
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub offset: Option<f64>,
+    /// Scaled values of the lowest and highest raw count, if reported by the sensor
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub range: Option<Range<f64>>,
+}
+
+impl ChannelInfo {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the unit of the scaled values
+    pub fn with_unit(self, unit: impl Into<String>) -> Self {
+        let unit = Some(unit.into());
+        Self { unit, ..self }
+    }
+
+    /// Set the linear scaling from raw counts to scaled values
+    pub fn with_scaling(self, scale: f64, offset: f64) -> Self {
+        Self {
+            scale: Some(scale),
+            offset: Some(offset),
+            ..self
+        }
+    }
+
+    /// Set the scaled values of the lowest and highest raw count
+    pub fn with_range(self, range: Range<f64>) -> Self {
+        let range = Some(range);
+        Self { range, ..self }
+    }
+
+    /// Scaled value of a raw count, `None` if the sensor did not report a scaling
+    pub fn scale_raw(&self, raw: i32) -> Option<f64> {
+        self.scale
+            .map(|scale| raw as f64 * scale + self.offset.unwrap_or_default())
+    }
+
+    /// Name including the unit, e.g. `Distance [mm]`
+    pub fn label(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for ChannelInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.unit {
+            Some(unit) => write!(f, "{} [{unit}]", self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ChannelInfo;
+
+    #[test]
+    fn test_label_test() {
+        assert_eq!(ChannelInfo::new("Distance").label(), "Distance");
+        assert_eq!(
+            ChannelInfo::new("Distance").with_unit("mm").label(),
+            "Distance [mm]"
+        );
+    }
+
+    #[test]
+    fn test_scale_raw_test() {
+        assert_eq!(ChannelInfo::new("Distance").scale_raw(10), None);
+        let info = ChannelInfo::new("Distance").with_scaling(0.5, -1.);
+        assert_eq!(info.scale_raw(10), Some(4.));
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+};
+
+use crate::{ChannelInfo, Data, Value, SCALED_OUT_OF_RANGE};
+
+/// Options for writing [`Data`] as CSV
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Separator between columns. Use `;` together with `decimal_comma`, writing
+    /// fails with [`io::ErrorKind::InvalidInput`] for `,`
+    pub delimiter: char,
+    /// Write `1,5` instead of `1.5`
+    pub decimal_comma: bool,
+    /// Text written for out of range values
+    pub out_of_range: String,
+    /// Write a header line with the channel names (and units when known)
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            decimal_comma: false,
+            out_of_range: String::new(),
+            header: true,
+        }
+    }
+}
+
+/// Writes captures as CSV, one line per frame of scaled values.
+///
+/// The header is written before the first capture, so successive captures
+/// of a read loop can be appended to the same file. Names containing the
+/// delimiter or a quote are quoted.
+pub struct CsvWriter<W: Write> {
+    writer: W,
+    options: CsvOptions,
+    header_written: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(writer: W, options: CsvOptions) -> Self {
+        let header_written = !options.header;
+        Self {
+            writer,
+            options,
+            header_written,
+        }
+    }
+
+    /// Append all frames of the capture
+    pub fn write(&mut self, data: &Data) -> io::Result<()> {
+        if self.options.decimal_comma && self.options.delimiter == ',' {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "decimal commas need a delimiter other than ','",
+            ));
+        }
+        let delimiter = self.options.delimiter.to_string();
+
+        if !self.header_written {
+            let header: Vec<_> = match data.channel_info() {
+                Some(info) => info.iter().map(|info| info.label()).collect(),
+                None => data.channels().to_vec(),
+            };
+            let header: Vec<_> = header
+                .iter()
+                .map(|field| quote(field, self.options.delimiter))
+                .collect();
+            writeln!(self.writer, "{}", header.join(&delimiter))?;
+            self.header_written = true;
+        }
+
+        let channels = data.channels().len();
+        if channels == 0 {
+            return Ok(());
+        }
+
+        for frame in data.scaled_data.chunks_exact(channels) {
+            let line: Vec<_> = frame
+                .iter()
+                .map(|&value| self.format_value(Value::from_sample(value)))
+                .collect();
+            writeln!(self.writer, "{}", line.join(&delimiter))?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    fn format_value(&self, value: Value<f64>) -> String {
+        match value {
+            Value::Valid(value) if self.options.decimal_comma => {
+                value.to_string().replace('.', ",")
+            }
+            Value::Valid(value) => value.to_string(),
+            Value::OutOfRange => self.options.out_of_range.clone(),
+        }
+    }
+}
+
+impl Data {
+    /// Write the scaled values as CSV
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{CsvOptions, Data};
+    ///
+    /// let data = Data::new(vec!["Distance".to_string()], vec![], vec![1.5, 2.5]);
+    /// let mut csv = vec![];
+    /// data.write_csv(&mut csv, &CsvOptions::default()).unwrap();
+    /// assert_eq!(String::from_utf8(csv).unwrap(), "Distance\n1.5\n2.5\n");
+    /// ```
+    pub fn write_csv<W: Write>(&self, writer: W, options: &CsvOptions) -> io::Result<()> {
+        let mut writer = CsvWriter::new(writer, options.clone());
+        writer.write(self)?;
+        writer.flush()
+    }
+}
+
+/// Header field quoted if it contains the delimiter, a quote or a line break
+fn quote(field: &str, delimiter: char) -> Cow<'_, str> {
+    match field.contains([delimiter, '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")).into(),
+        false => field.into(),
+    }
+}
+
+/// Fields of a header line written by [`quote`]
+fn header_fields(header: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = header.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("starts with a field");
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields
+}
+
+/// Parse scaled values written by a [`CsvWriter`] with a header line
+///
+/// The delimiter is `;` if the header contains one outside of quotes, `,`
+/// otherwise, values with a
+/// decimal comma are accepted either way. Fields that are no number, e.g. the
+/// configured `out_of_range` text, are read as out of range.
+pub(crate) fn read_csv(text: &str) -> io::Result<Data> {
+    let mut lines = text.lines().filter(|line| !line.is_empty());
+    let header = lines
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing csv header"))?;
+    // every other part is quoted
+    let delimiter = match header.split('"').step_by(2).any(|part| part.contains(';')) {
+        true => ';',
+        false => ',',
+    };
+
+    let info: Vec<_> = header_fields(header, delimiter)
+        .iter()
+        .map(
+            |label| match label.strip_suffix(']').and_then(|l| l.split_once(" [")) {
+                Some((name, unit)) => ChannelInfo::new(name).with_unit(unit),
+                None => ChannelInfo::new(label),
+            },
+        )
+        .collect();
+    let channels = info.len();
+
+    let mut scaled_data = vec![];
+    for line in lines {
+        let mut fields: Vec<_> = line.split(delimiter).collect();
+        // a single column with decimal commas looks like two columns
+        if fields.len() != channels && channels == 1 {
+            fields = vec![line];
+        }
+        if fields.len() != channels {
+            let message = format!("expected {channels} values in csv line {line:?}");
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        scaled_data.extend(fields.iter().map(|field| {
+            field
+                .replace(',', ".")
+                .parse()
+                .unwrap_or(SCALED_OUT_OF_RANGE)
+        }));
+    }
+
+    let names: Vec<_> = info.iter().map(|info| info.name.clone()).collect();
+    let data = Data::new(names, vec![], scaled_data);
+    Ok(match info.iter().any(|info| info.unit.is_some()) {
+        true => data.with_channel_info(info),
+        false => data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{ChannelInfo, CsvOptions, CsvWriter, Data};
+
+    fn data() -> Data {
+        Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            vec![1.5, 20., -1.7976931348623157e308, 30.],
+        )
+    }
+
+    fn to_csv(data: &Data, options: &CsvOptions) -> String {
+        let mut csv = vec![];
+        data.write_csv(&mut csv, options).unwrap();
+        String::from_utf8(csv).unwrap()
+    }
+
+    #[test]
+    fn test_write_csv_without_units_test() {
+        let csv = to_csv(&data(), &CsvOptions::default());
+        assert_eq!(csv, "Distance,Intensity\n1.5,20\n,30\n");
+    }
+
+    #[test]
+    fn test_write_csv_with_units_test() {
+        let data = data().with_channel_info(vec![
+            ChannelInfo::new("Distance").with_unit("mm"),
+            ChannelInfo::new("Intensity"),
+        ]);
+        let csv = to_csv(&data, &CsvOptions::default());
+        assert_eq!(csv, "Distance [mm],Intensity\n1.5,20\n,30\n");
+    }
+
+    #[test]
+    fn test_write_csv_options_test() {
+        let options = CsvOptions {
+            delimiter: ';',
+            decimal_comma: true,
+            out_of_range: "OutOfRange".into(),
+            header: false,
+        };
+        let csv = to_csv(&data(), &options);
+        assert_eq!(csv, "1,5;20\nOutOfRange;30\n");
+    }
+
+    #[test]
+    fn test_write_csv_decimal_comma_needs_delimiter_test() {
+        let options = CsvOptions {
+            decimal_comma: true,
+            ..CsvOptions::default()
+        };
+        let mut csv = vec![];
+        let err = data().write_csv(&mut csv, &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(csv.is_empty());
+    }
+
+    #[test]
+    fn test_write_csv_quotes_header_test() {
+        let data = Data::new(
+            vec!["Distance, top".to_string(), "Edge \"1\"".to_string()],
+            vec![],
+            vec![1.5, 20.],
+        );
+        let csv = to_csv(&data, &CsvOptions::default());
+        assert_eq!(csv, "\"Distance, top\",\"Edge \"\"1\"\"\"\n1.5,20\n");
+        let read = super::read_csv(&csv).unwrap();
+        assert_eq!(read.channels(), ["Distance, top", "Edge \"1\""]);
+        assert_eq!(read.scaled_data, data.scaled_data);
+
+        let options = CsvOptions {
+            delimiter: ';',
+            decimal_comma: true,
+            ..CsvOptions::default()
+        };
+        let csv = to_csv(&data, &options);
+        assert_eq!(csv, "Distance, top;\"Edge \"\"1\"\"\"\n1,5;20\n");
+        let read = super::read_csv(&csv).unwrap();
+        assert_eq!(read.channels(), ["Distance, top", "Edge \"1\""]);
+    }
+
+    #[test]
+    fn test_csv_writer_writes_header_once_test() {
+        let mut writer = CsvWriter::new(vec![], CsvOptions::default());
+        writer.write(&data()).unwrap();
+        writer.write(&data()).unwrap();
+        let csv = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(csv, "Distance,Intensity\n1.5,20\n,30\n1.5,20\n,30\n");
+    }
+
+    #[test]
+    fn test_read_csv_test() {
+        let data = data().with_channel_info(vec![
+            ChannelInfo::new("Distance").with_unit("mm"),
+            ChannelInfo::new("Intensity"),
+        ]);
+        let options = CsvOptions {
+            delimiter: ';',
+            decimal_comma: true,
+            out_of_range: "OutOfRange".into(),
+            header: true,
+        };
+        let read = super::read_csv(&to_csv(&data, &options)).unwrap();
+        assert_eq!(read.channels(), ["Distance", "Intensity"]);
+        assert_eq!(read.unit(0), Some("mm"));
+        assert_eq!(read.scaled_data, data.scaled_data);
+
+        let read = super::read_csv("Distance\n1,5\n\n2\n").unwrap();
+        assert_eq!(read.scaled_data, vec![1.5, 2.]);
+        assert!(read.channel_info().is_none());
+        assert!(super::read_csv("a,b\n1\n").is_err());
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs::File,
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{AcquisitionStats, Capabilities, ChannelInfo, MedaqError, Sensor};
+
+/// Parameters of the connection in a [`DiagnosticReport`]
+const PARAMETERS: [&str; 4] = [
+    "IP_Interface",
+    "IP_RemoteAddr",
+    "IP_Port",
+    "IP_EnableLogging",
+];
+
+/// Answers of `Get_Info` in a [`DiagnosticReport`]
+const SENSOR_INFO: [&str; 4] = [
+    "SA_SensorName",
+    "SA_SerialNumber",
+    "SA_ArticleNumber",
+    "SA_SoftwareVersion",
+];
+
+/// Bytes kept of the end of every file of a support bundle
+const FILE_LIMIT: u64 = 1 << 20;
+
+/// A failed call of a sensor, see [`Sensor::recent_errors`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ErrorRecord {
+    pub time: SystemTime,
+    /// The parameter set, the command executed or `read`
+    pub call: String,
+    pub message: String,
+}
+
+/// A query of [`Sensor::diagnostic_report`] that failed, the report lacks its
+/// value
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct QueryFailure {
+    /// The parameter read or the command executed
+    pub query: String,
+    pub error: String,
+}
+
+/// Everything known about a sensor for a support request, see
+/// [`Sensor::diagnostic_report`]
+///
+/// Displayed as readable text, with the `serde` feature it serializes as well.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct DiagnosticReport {
+    /// Version of this crate
+    pub crate_version: String,
+    /// Version of the loaded MEDAQLib, `None` for other backends or if it does not
+    /// report one
+    pub dll_version: Option<String>,
+    pub sensor_type: String,
+    /// Answers of `Get_Info`, e.g. `SA_SerialNumber`
+    pub sensor_info: BTreeMap<String, String>,
+    pub capabilities: Capabilities,
+    /// Parameters of the connection, e.g. `IP_RemoteAddr`
+    pub parameters: BTreeMap<String, String>,
+    pub channels: Vec<ChannelInfo>,
+    pub stats: AcquisitionStats,
+    /// Oldest first, see [`Sensor::recent_errors`]
+    pub recent_errors: Vec<ErrorRecord>,
+    /// Text of the last error MEDAQLib reported, see [`Sensor::last_error`]
+    pub last_error: Option<String>,
+    /// File of [`SensorBuilder::with_trace`](crate::SensorBuilder::with_trace)
+    pub trace_path: Option<PathBuf>,
+    /// Queries whose values are missing
+    pub failures: Vec<QueryFailure>,
+}
+
+impl Sensor {
+    /// Gather the versions, sensor info, capabilities, connection parameters,
+    /// channels, statistics and errors of the sensor
+    ///
+    /// Every query is made even if others fail, failures leave their value out of
+    /// the report and are listed in [`DiagnosticReport::failures`].
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Interface, MockBackend, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1420)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .with_backend(MockBackend::new().with_channels(["Distance"]))
+    ///     .connect()
+    ///     .unwrap();
+    /// let report = sensor.diagnostic_report();
+    /// assert_eq!(report.parameters["IP_RemoteAddr"], "10.10.10.10");
+    /// println!("{report}");
+    /// ```
+    pub fn diagnostic_report(&self) -> DiagnosticReport {
+        let mut failures = vec![];
+        let mut noted = |query: &str, result: Result<String, MedaqError>| match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                failures.push(QueryFailure {
+                    query: query.into(),
+                    error: err.to_string(),
+                });
+                None
+            }
+        };
+
+        let _instance = self.hold().ok();
+        let mut sensor_info = BTreeMap::new();
+        let info = self.execute_command("Get_Info").map(|()| String::new());
+        if noted("Get_Info", info).is_some() {
+            for name in SENSOR_INFO {
+                if let Some(value) = noted(name, self.get_parameter_string(name)) {
+                    sensor_info.insert(name.to_string(), value);
+                }
+            }
+        }
+        let mut parameters = BTreeMap::new();
+        for name in PARAMETERS {
+            if let Some(value) = noted(name, self.get_parameter_string(name)) {
+                parameters.insert(name.to_string(), value);
+            }
+        }
+
+        DiagnosticReport {
+            crate_version: env!("CARGO_PKG_VERSION").into(),
+            dll_version: self
+                .core
+                .backend()
+                .dll_version()
+                .map(|version| version.to_string()),
+            sensor_type: format!("{:?}", self.sensor_type()),
+            sensor_info,
+            capabilities: self.capabilities().clone(),
+            parameters,
+            channels: self.channel_info().to_vec(),
+            stats: self.stats(),
+            recent_errors: self.recent_errors(),
+            last_error: self.last_error(),
+            trace_path: self.trace.clone(),
+            failures,
+        }
+    }
+
+    /// The latest failed calls setting parameters, executing commands or reading,
+    /// oldest first, including those of background acquisitions
+    ///
+    /// Reading parameters is left out, answers missing by design, e.g. the end of
+    /// the channel list, would push out the errors of interest.
+    pub fn recent_errors(&self) -> Vec<ErrorRecord> {
+        self.stats.recent_errors()
+    }
+
+    /// File of [`SensorBuilder::with_trace`](crate::SensorBuilder::with_trace), if
+    /// tracing
+    pub fn trace_path(&self) -> Option<&Path> {
+        self.trace.as_deref()
+    }
+
+    /// Write the [`diagnostic_report`](Self::diagnostic_report) to a text file at
+    /// `path`, followed by the trace file and `files`, e.g. logs of MEDAQLib
+    ///
+    /// Only the last MiB of each file is kept. A file that cannot be read is noted
+    /// in the bundle instead of failing, errors writing `path` are returned.
+    pub fn write_support_bundle(
+        &self,
+        path: impl AsRef<Path>,
+        files: &[&Path],
+    ) -> io::Result<DiagnosticReport> {
+        let report = self.diagnostic_report();
+        let mut bundle = BufWriter::new(File::create(path)?);
+        write!(bundle, "{report}")?;
+        for file in report
+            .trace_path
+            .as_deref()
+            .into_iter()
+            .chain(files.iter().copied())
+        {
+            writeln!(bundle, "\n===== {} =====", file.display())?;
+            match tail(file) {
+                Ok((skipped, content)) => {
+                    if skipped > 0 {
+                        writeln!(bundle, "[{skipped} bytes before left out]")?;
+                    }
+                    bundle.write_all(&content)?;
+                }
+                Err(err) => writeln!(bundle, "[could not be read: {err}]")?,
+            }
+        }
+        bundle.flush()?;
+        Ok(report)
+    }
+}
+
+/// The last [`FILE_LIMIT`] bytes of a file and the number of bytes before
+fn tail(path: &Path) -> io::Result<(u64, Vec<u8>)> {
+    let mut file = File::open(path)?;
+    let skipped = file.metadata()?.len().saturating_sub(FILE_LIMIT);
+    file.seek(SeekFrom::Start(skipped))?;
+    let mut content = vec![];
+    file.take(FILE_LIMIT).read_to_end(&mut content)?;
+    Ok((skipped, content))
+}
+
+impl Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dll_version = self.dll_version.as_deref().unwrap_or("unknown");
+        writeln!(f, "medaqlib {}, MEDAQLib {dll_version}", self.crate_version)?;
+        writeln!(f, "sensor {}", self.sensor_type)?;
+        for (name, value) in &self.sensor_info {
+            writeln!(f, "  {name}: {value}")?;
+        }
+
+        let capabilities = &self.capabilities;
+        let offered = [
+            (capabilities.supports_laser_control, "laser control"),
+            (capabilities.supports_trigger, "trigger"),
+            (capabilities.supports_thickness, "thickness"),
+            (capabilities.supports_analog_output, "analog output"),
+        ];
+        let offered: Vec<&str> = offered
+            .into_iter()
+            .filter_map(|(supported, name)| supported.then_some(name))
+            .collect();
+        write!(
+            f,
+            "capabilities: {}, {} channels",
+            offered.join(", "),
+            capabilities.max_channels
+        )?;
+        if capabilities.limit_switches != usize::MAX {
+            write!(f, ", {} limit switches", capabilities.limit_switches)?;
+        }
+        if !capabilities.supported_measuring_rates.is_empty() {
+            let rates: Vec<String> = (capabilities.supported_measuring_rates.iter())
+                .map(|rate| rate.to_string())
+                .collect();
+            write!(f, ", rates {} Hz", rates.join(", "))?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "parameters")?;
+        for (name, value) in &self.parameters {
+            writeln!(f, "  {name}: {value}")?;
+        }
+        writeln!(f, "channels")?;
+        for (index, info) in self.channels.iter().enumerate() {
+            writeln!(f, "  {} {info}", index + 1)?;
+        }
+
+        let stats = &self.stats;
+        writeln!(
+            f,
+            "stats: {} frames read, {} dropped, {} gaps with {} frames missing, {} read errors, backlog {} (peak {})",
+            stats.frames_read,
+            stats.frames_dropped,
+            stats.gaps,
+            stats.missing_frames,
+            stats.read_errors,
+            stats.backlog,
+            stats.peak_backlog
+        )?;
+        if !self.recent_errors.is_empty() {
+            writeln!(f, "recent errors")?;
+            for error in &self.recent_errors {
+                let time = error.time.duration_since(UNIX_EPOCH);
+                let time = time.unwrap_or(Duration::ZERO).as_secs_f64();
+                writeln!(f, "  {time:.3} {}: {}", error.call, error.message)?;
+            }
+        }
+        if let Some(last_error) = &self.last_error {
+            writeln!(f, "last MEDAQLib error: {last_error}")?;
+        }
+        if let Some(trace_path) = &self.trace_path {
+            writeln!(f, "trace: {}", trace_path.display())?;
+        }
+        if !self.failures.is_empty() {
+            writeln!(f, "failed queries")?;
+            for failure in &self.failures {
+                writeln!(f, "  {}: {}", failure.query, failure.error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
+
+    use crate::{Call, Intercept, Interface, MockBackend, SensorBuilder, ERR_CODE, ME_SENSOR};
+
+    #[test]
+    fn test_diagnostic_report_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance"])
+            .with_parameter_string("SA_SerialNumber", "1234")
+            .with_parameter_string("SA_SensorName", "ILD1420-50");
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        mock.intercept(|call| match call.call {
+            Call::ExecSCmd if call.arguments[0] == "Set_Measrate" => {
+                Intercept::Fail(ERR_CODE::ERR_WRONG_PARAMETER)
+            }
+            Call::GetParameterString if call.arguments[0] == "IP_Port" => {
+                Intercept::Fail(ERR_CODE::ERR_NOT_FOUND)
+            }
+            _ => Intercept::Pass,
+        });
+        assert!(sensor.execute_command("Set_Measrate").is_err());
+
+        let report = sensor.diagnostic_report();
+        assert_eq!(report.sensor_type, "SENSOR_ILD1420");
+        assert_eq!(report.dll_version, None);
+        assert_eq!(report.sensor_info["SA_SerialNumber"], "1234");
+        assert_eq!(report.sensor_info.len(), 2);
+        assert_eq!(report.parameters["IP_Interface"], "TCP/IP");
+        assert!(!report.parameters.contains_key("IP_Port"));
+        assert_eq!(report.channels[0].name, "Distance");
+        assert_eq!(report.recent_errors.len(), 1);
+        assert_eq!(report.recent_errors[0].call, "Set_Measrate");
+        // the failed queries do not keep the others from being made
+        let failed: Vec<_> = report.failures.iter().map(|f| &*f.query).collect();
+        assert_eq!(
+            failed,
+            [
+                "SA_ArticleNumber",
+                "SA_SoftwareVersion",
+                "IP_Port",
+                "IP_EnableLogging"
+            ]
+        );
+
+        let text = report.to_string();
+        assert!(text.starts_with("medaqlib "), "{text}");
+        assert!(text.contains("\nsensor SENSOR_ILD1420\n  SA_SensorName: ILD1420-50\n"));
+        assert!(text.contains("capabilities: laser control, trigger, analog output, 1 channels"));
+        assert!(text.contains("  1 Distance"));
+        assert!(text.contains(" Set_Measrate: sensor error: ERR_WRONG_PARAMETER\n"));
+        assert!(text.contains("failed queries\n  SA_ArticleNumber: "));
+    }
+
+    #[test]
+    fn test_support_bundle_test() {
+        let dir = std::env::temp_dir().join(format!("medaqlib-bundle-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let trace = dir.join("sensor.trace");
+        let log = dir.join("medaqlib.log");
+        fs::write(&log, "log line\n").unwrap();
+
+        let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1420)
+            .with_interface(Interface::TcpIp)
+            .with_ip_address("10.10.10.10")
+            .with_backend(MockBackend::new().with_channels(["Distance"]))
+            .with_trace(&trace)
+            .connect()
+            .unwrap();
+        assert_eq!(sensor.trace_path(), Some(&*trace));
+
+        let bundle = dir.join("bundle.txt");
+        let missing = dir.join("missing.log");
+        let report = sensor
+            .write_support_bundle(&bundle, &[&log, &missing])
+            .unwrap();
+        assert_eq!(report.trace_path.as_deref(), Some(&*trace));
+
+        let text = fs::read_to_string(&bundle).unwrap();
+        assert!(text.starts_with(&report.to_string()));
+        assert!(text.contains(&format!("===== {} =====\n", trace.display())));
+        assert!(text.contains("Get_TransmittedDataInfo"));
+        assert!(text.contains(&format!("===== {} =====\nlog line\n", log.display())));
+        assert!(text.contains(&format!(
+            "===== {} =====\n[could not be read: ",
+            missing.display()
+        )));
+        drop(sensor);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
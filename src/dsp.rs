@@ -0,0 +1,228 @@
+use std::f64::consts::PI;
+
+use rustfft::{num_complex::Complex, FftPlanner};
+
+use crate::{Data, MedaqError, Value};
+
+/// Window applied to the samples before the FFT
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Window {
+    /// Rectangular window, i.e. the samples as they are
+    None,
+    #[default]
+    Hann,
+    Hamming,
+}
+
+impl Window {
+    fn coefficients(&self, len: usize) -> Vec<f64> {
+        let cosine = |a0: f64| {
+            (0..len)
+                .map(|n| match len {
+                    1 => 1.,
+                    _ => a0 - (1. - a0) * (2. * PI * n as f64 / (len - 1) as f64).cos(),
+                })
+                .collect()
+        };
+        match self {
+            Window::None => vec![1.; len],
+            Window::Hann => cosine(0.5),
+            Window::Hamming => cosine(0.54),
+        }
+    }
+}
+
+/// Single sided amplitude spectrum of a channel, see [`Data::spectrum`]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Spectrum {
+    /// Center frequency of every bin in Hz, from 0 to the Nyquist frequency
+    pub frequencies: Vec<f64>,
+    /// Amplitude of every bin in the unit of the channel, corrected for the gain
+    /// of the window
+    pub magnitudes: Vec<f64>,
+}
+
+impl Spectrum {
+    /// Frequency and magnitude of the strongest bin, ignoring the DC bin
+    pub fn peak(&self) -> Option<(f64, f64)> {
+        self.frequencies
+            .iter()
+            .zip(&self.magnitudes)
+            .skip(1)
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(&frequency, &magnitude)| (frequency, magnitude))
+    }
+}
+
+impl Data {
+    /// Amplitude spectrum of the scaled values of a channel
+    ///
+    /// Out of range samples are filled before the transform: gaps between valid
+    /// samples are interpolated linearly, leading and trailing gaps take the value
+    /// of the nearest valid sample. A channel without any valid sample is treated
+    /// as all zeros.
+    ///
+    /// The mean is removed before the window is applied, so a large offset, e.g.
+    /// the distance to the target, does not leak into the low frequency bins. The
+    /// DC bin holds the magnitude of the mean instead.
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Data, Window};
+    ///
+    /// let samples = (0..100).map(|n| (n as f64 * 0.5).sin() + 1.).collect();
+    /// let data = Data::new(vec!["Distance".to_string()], vec![], samples);
+    /// let spectrum = data.spectrum("Distance", 1000., Window::Hann).unwrap();
+    /// assert_eq!(spectrum.frequencies[1], 10.);
+    /// ```
+    pub fn spectrum(
+        &self,
+        channel: &str,
+        sample_rate_hz: f64,
+        window: Window,
+    ) -> Result<Spectrum, MedaqError> {
+        let (channel, _) = self.select(&[channel])?.interpolate_gaps(usize::MAX);
+        let mut samples = channel.scaled_data;
+        fill_edges(&mut samples);
+
+        let len = samples.len();
+        if len == 0 {
+            return Ok(Spectrum::default());
+        }
+        let mean = samples.iter().sum::<f64>() / len as f64;
+        let coefficients = window.coefficients(len);
+        let gain: f64 = coefficients.iter().sum();
+        let mut buffer: Vec<Complex<f64>> = samples
+            .iter()
+            .zip(&coefficients)
+            .map(|(sample, coefficient)| Complex::new((sample - mean) * coefficient, 0.))
+            .collect();
+        FftPlanner::new().plan_fft_forward(len).process(&mut buffer);
+
+        let bins = len / 2 + 1;
+        let frequencies = (0..bins)
+            .map(|bin| bin as f64 * sample_rate_hz / len as f64)
+            .collect();
+        let magnitudes = buffer[..bins]
+            .iter()
+            .enumerate()
+            .map(|(bin, value)| match bin {
+                0 => mean.abs(),
+                // all bins but Nyquist share their energy with the negative frequencies
+                _ if 2 * bin == len => value.norm() / gain,
+                _ => 2. * value.norm() / gain,
+            })
+            .collect();
+
+        Ok(Spectrum {
+            frequencies,
+            magnitudes,
+        })
+    }
+}
+
+/// Replace out of range samples before the first and after the last valid sample
+fn fill_edges(samples: &mut [f64]) {
+    let valid = |sample: &f64| matches!(Value::from_sample(*sample), Value::Valid(_));
+    let (Some(first), Some(last)) = (
+        samples.iter().position(valid),
+        samples.iter().rposition(valid),
+    ) else {
+        samples.fill(0.);
+        return;
+    };
+    let (first_value, last_value) = (samples[first], samples[last]);
+    samples[..first].fill(first_value);
+    samples[last + 1..].fill(last_value);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use crate::{Data, MedaqError, Window, SCALED_OUT_OF_RANGE};
+
+    /// Capture of `frequency` Hz with amplitude 2 around 10 on the second channel
+    fn sine(frequency: f64, sample_rate_hz: f64, frames: usize) -> Data {
+        let scaled_data = (0..frames)
+            .flat_map(|n| {
+                let t = n as f64 / sample_rate_hz;
+                [0., 10. + 2. * (2. * PI * frequency * t).sin()]
+            })
+            .collect();
+        Data::new(
+            vec!["Intensity".to_string(), "Distance".to_string()],
+            vec![],
+            scaled_data,
+        )
+    }
+
+    #[test]
+    fn test_spectrum_bins_test() {
+        let spectrum = sine(50., 1000., 1000)
+            .spectrum("Distance", 1000., Window::None)
+            .unwrap();
+        assert_eq!(spectrum.frequencies.len(), 501);
+        assert_eq!(spectrum.magnitudes.len(), 501);
+        assert_eq!(spectrum.frequencies[0], 0.);
+        assert_eq!(spectrum.frequencies[1], 1.);
+        assert_eq!(spectrum.frequencies[500], 500.);
+
+        let (frequency, magnitude) = spectrum.peak().unwrap();
+        assert_eq!(frequency, 50.);
+        assert!((magnitude - 2.).abs() < 1e-9);
+        assert!((spectrum.magnitudes[0] - 10.).abs() < 1e-9);
+        assert!(spectrum.magnitudes[49] < 1e-9);
+    }
+
+    #[test]
+    fn test_spectrum_windows_test() {
+        let data = sine(125., 1000., 512);
+        for window in [Window::None, Window::Hann, Window::Hamming] {
+            let spectrum = data.spectrum("Distance", 1000., window).unwrap();
+            assert_eq!(spectrum.frequencies[1], 1000. / 512.);
+            let (frequency, magnitude) = spectrum.peak().unwrap();
+            assert_eq!(frequency, 125.);
+            assert!((magnitude - 2.).abs() < 0.05, "{window:?}: {magnitude}");
+        }
+    }
+
+    #[test]
+    fn test_spectrum_fills_invalid_samples_test() {
+        let mut data = sine(50., 1000., 1000);
+        for frame in [0, 1, 400, 401, 999] {
+            data.scaled_data[2 * frame + 1] = SCALED_OUT_OF_RANGE;
+        }
+        let spectrum = data.spectrum("Distance", 1000., Window::Hann).unwrap();
+        let (frequency, magnitude) = spectrum.peak().unwrap();
+        assert_eq!(frequency, 50.);
+        assert!((magnitude - 2.).abs() < 0.05);
+        assert!((spectrum.magnitudes[0] - 10.).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_spectrum_invalid_channel_test() {
+        let data = Data::new(
+            vec!["Distance".to_string()],
+            vec![],
+            vec![SCALED_OUT_OF_RANGE; 4],
+        );
+        let spectrum = data.spectrum("Distance", 100., Window::None).unwrap();
+        assert_eq!(spectrum.magnitudes, vec![0.; 3]);
+
+        let empty = Data::new(vec!["Distance".to_string()], vec![], vec![]);
+        assert_eq!(
+            empty
+                .spectrum("Distance", 100., Window::None)
+                .unwrap()
+                .frequencies
+                .len(),
+            0
+        );
+
+        assert!(matches!(
+            data.spectrum("Intensity", 100., Window::None),
+            Err(MedaqError::UnknownChannel { .. })
+        ));
+    }
+}
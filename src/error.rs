@@ -0,0 +1,312 @@
+use std::{
+    error::Error,
+    ffi::{IntoStringError, NulError},
+    fmt::{Debug, Display},
+    io,
+    net::AddrParseError,
+    time::Duration,
+};
+
+use crate::{
+    bindings::{ERR_CODE, ME_SENSOR},
+    DllVersion,
+};
+
+/// Errors returned by this crate
+#[derive(Debug)]
+pub enum MedaqError {
+    /// MEDAQLib returned an error code
+    Sensor(ERR_CODE),
+    /// No interface was configured before connecting
+    MissingInterface,
+    /// No ip address was configured before connecting
+    MissingIpAddress,
+    /// The configured ip address could not be parsed
+    InvalidIpAddress(AddrParseError),
+    /// A string returned by MEDAQLib is not valid UTF-8
+    InvalidString(IntoStringError),
+    /// A parameter name, command or value passed to MEDAQLib contains a nul byte
+    NulInString(NulError),
+    /// The sensor does not transmit any channels
+    NoChannels,
+    /// Neither a raw nor a scaled buffer was provided for a read
+    NoBuffer,
+    /// A channel name is not part of the capture
+    UnknownChannel {
+        name: String,
+        available: Vec<String>,
+    },
+    /// Two channels would have the same name
+    DuplicateChannel(String),
+    /// A blocking call was cancelled through a [`StopToken`](crate::StopToken)
+    Cancelled,
+    /// Captures with different channels cannot be combined
+    ChannelMismatch {
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    /// No sensor type has the given name
+    UnknownSensorType(String),
+    /// No interface has the given name
+    UnknownInterface(String),
+    /// The trace file of [`SensorBuilder::with_trace`](crate::SensorBuilder::with_trace)
+    /// could not be created
+    TraceFile(io::Error),
+    /// A call differs from the next call of a [`ReplayTrace`](crate::ReplayTrace)
+    TraceMismatch { expected: String, found: String },
+    /// MEDAQLib could not be loaded, with the paths tried and their errors, see
+    /// [`library_available`](crate::library_available)
+    LibraryUnavailable(String),
+    /// The loaded MEDAQLib is older than this crate requires, `found` is `None` if it
+    /// does not report its version
+    DllTooOld {
+        found: Option<DllVersion>,
+        required: DllVersion,
+        /// Functions used by sensors that the library does not export
+        missing: Vec<&'static str>,
+    },
+    /// The loaded MEDAQLib does not export an optional function, see
+    /// [`dll_capabilities`](crate::dll_capabilities)
+    UnsupportedByDll { function: &'static str },
+    /// The sensor is not of the type a typed API is made for, see
+    /// [`sensors`](crate::sensors)
+    WrongSensorType {
+        /// The types the API supports
+        expected: &'static [ME_SENSOR],
+        found: ME_SENSOR,
+    },
+    /// A channel index beyond the channels of a controller, counted from `1`
+    ChannelOutOfRange { channel: usize, channels: usize },
+    /// A procedure of the sensor, e.g. a dark reference, was still running after
+    /// the timeout
+    CommandTimeout {
+        command: &'static str,
+        timeout: Duration,
+    },
+    /// The sensor type does not offer a setting, e.g. a measuring rate, see
+    /// [`Sensor::capabilities`](crate::Sensor::capabilities)
+    Unsupported {
+        sensor_type: ME_SENSOR,
+        setting: String,
+    },
+    /// A setting outside of what the sensor accepts, e.g. an empty scaling range
+    InvalidSetting { setting: String, reason: String },
+    /// The sensor answered a parameter with a value this crate does not know
+    InvalidAnswer { parameter: String, answer: String },
+    /// The sensor reported another value or an error text after a setting, e.g.
+    /// [`Sensor::set_sync_mode`](crate::Sensor::set_sync_mode)
+    Rejected { setting: String, reported: String },
+    /// MEDAQLib reported a number of values that cannot be read, e.g. a negative
+    /// count from `DataAvail` or more values transferred than requested
+    InvalidDataCount { function: &'static str, count: i64 },
+    /// The MEDAQLib instance was released, e.g. a [`SensorRef`](crate::SensorRef)
+    /// used after its [`Sensor`](crate::Sensor) was closed
+    Closed,
+    /// A sensor of a [`SensorGroup`](crate::SensorGroup) failed, by its position
+    GroupMember {
+        sensor: usize,
+        source: Box<MedaqError>,
+    },
+}
+
+impl Display for MedaqError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MedaqError::Sensor(err_code) => write!(f, "sensor error: {err_code}"),
+            MedaqError::MissingInterface => write!(f, "no interface provided"),
+            MedaqError::MissingIpAddress => write!(f, "no ip address provided"),
+            MedaqError::InvalidIpAddress(err) => write!(f, "invalid ip address: {err}"),
+            MedaqError::InvalidString(err) => write!(f, "invalid string from sensor: {err}"),
+            MedaqError::NulInString(err) => write!(f, "invalid string for MEDAQLib: {err}"),
+            MedaqError::NoChannels => write!(f, "sensor does not transmit any channels"),
+            MedaqError::NoBuffer => write!(f, "neither raw nor scaled buffer provided"),
+            MedaqError::UnknownChannel { name, available } => write!(
+                f,
+                "unknown channel {name:?}, available channels: {}",
+                available.join(", ")
+            ),
+            MedaqError::DuplicateChannel(name) => write!(f, "duplicate channel {name:?}"),
+            MedaqError::Cancelled => write!(f, "cancelled"),
+            MedaqError::ChannelMismatch { expected, found } => write!(
+                f,
+                "channels {} differ from {}",
+                found.join(", "),
+                expected.join(", ")
+            ),
+            MedaqError::UnknownSensorType(name) => write!(f, "unknown sensor type {name:?}"),
+            MedaqError::UnknownInterface(name) => write!(f, "unknown interface {name:?}"),
+            MedaqError::TraceFile(err) => write!(f, "could not create trace file: {err}"),
+            MedaqError::TraceMismatch { expected, found } => {
+                write!(f, "trace expected {expected} but got {found}")
+            }
+            MedaqError::LibraryUnavailable(detail) => write!(f, "{detail}"),
+            MedaqError::DllTooOld {
+                found,
+                required,
+                missing,
+            } => {
+                match found {
+                    Some(found) => write!(f, "MEDAQLib {found}")?,
+                    None => write!(f, "MEDAQLib of unknown version")?,
+                }
+                write!(f, " is older than the required {required}")?;
+                match missing.is_empty() {
+                    true => Ok(()),
+                    false => write!(f, ", missing functions: {}", missing.join(", ")),
+                }
+            }
+            MedaqError::UnsupportedByDll { function } => {
+                write!(f, "the loaded MEDAQLib does not export {function}")
+            }
+            MedaqError::WrongSensorType { expected, found } => {
+                let expected: Vec<String> = expected.iter().map(|ty| format!("{ty:?}")).collect();
+                write!(
+                    f,
+                    "expected a {} but the sensor is a {found:?}",
+                    expected.join(" or ")
+                )
+            }
+            MedaqError::ChannelOutOfRange { channel, channels } => {
+                write!(f, "channel {channel} is not within the {channels} channels")
+            }
+            MedaqError::CommandTimeout { command, timeout } => {
+                write!(f, "{command} did not finish within {timeout:?}")
+            }
+            MedaqError::Unsupported {
+                sensor_type,
+                setting,
+            } => write!(f, "{setting} is not supported by the {sensor_type:?}"),
+            MedaqError::InvalidSetting { setting, reason } => {
+                write!(f, "invalid {setting}: {reason}")
+            }
+            MedaqError::InvalidAnswer { parameter, answer } => {
+                write!(f, "unexpected answer {answer:?} for {parameter}")
+            }
+            MedaqError::Rejected { setting, reported } => {
+                write!(
+                    f,
+                    "the sensor reported {reported} after setting the {setting}"
+                )
+            }
+            MedaqError::InvalidDataCount { function, count } => {
+                write!(f, "{function} reported an invalid count of {count} values")
+            }
+            MedaqError::Closed => write!(f, "the sensor was closed"),
+            MedaqError::GroupMember { sensor, source } => write!(f, "sensor {sensor}: {source}"),
+        }
+    }
+}
+
+impl Error for MedaqError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MedaqError::Sensor(err_code) => Some(err_code),
+            MedaqError::InvalidIpAddress(err) => Some(err),
+            MedaqError::InvalidString(err) => Some(err),
+            MedaqError::NulInString(err) => Some(err),
+            MedaqError::TraceFile(err) => Some(err),
+            MedaqError::GroupMember { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Inconsistent buffers of a [`Data`](crate::Data), see [`Data::validate`](crate::Data::validate)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataLayoutError {
+    /// Values are present but no channels
+    NoChannels { values: usize },
+    /// Raw and scaled buffers are both non-empty but differ in length
+    LengthMismatch { raw: usize, scaled: usize },
+    /// A buffer does not hold whole frames
+    PartialFrame { values: usize, channels: usize },
+    /// A channel name occurs more than once
+    DuplicateChannel(String),
+}
+
+impl Display for DataLayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataLayoutError::NoChannels { values } => write!(f, "{values} values without channels"),
+            DataLayoutError::LengthMismatch { raw, scaled } => {
+                write!(f, "{raw} raw values but {scaled} scaled values")
+            }
+            DataLayoutError::PartialFrame { values, channels } => {
+                write!(
+                    f,
+                    "{values} values are no whole frames of {channels} channels"
+                )
+            }
+            DataLayoutError::DuplicateChannel(name) => write!(f, "duplicate channel {name:?}"),
+        }
+    }
+}
+
+impl Error for DataLayoutError {}
+
+/// Error of [`shutdown`](crate::shutdown)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShutdownError {
+    /// The number of sensors still using MEDAQLib, drop them first
+    SensorsAlive(usize),
+}
+
+impl Display for ShutdownError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShutdownError::SensorsAlive(1) => write!(f, "1 sensor still uses MEDAQLib"),
+            ShutdownError::SensorsAlive(count) => {
+                write!(f, "{count} sensors still use MEDAQLib")
+            }
+        }
+    }
+}
+
+impl Error for ShutdownError {}
+
+impl From<ERR_CODE> for MedaqError {
+    fn from(value: ERR_CODE) -> Self {
+        MedaqError::Sensor(value)
+    }
+}
+
+impl From<AddrParseError> for MedaqError {
+    fn from(value: AddrParseError) -> Self {
+        MedaqError::InvalidIpAddress(value)
+    }
+}
+
+impl From<IntoStringError> for MedaqError {
+    fn from(value: IntoStringError) -> Self {
+        MedaqError::InvalidString(value)
+    }
+}
+
+impl From<NulError> for MedaqError {
+    fn from(value: NulError) -> Self {
+        MedaqError::NulInString(value)
+    }
+}
+
+impl From<ERR_CODE> for Result<(), MedaqError> {
+    fn from(value: ERR_CODE) -> Self {
+        match value {
+            ERR_CODE::ERR_NOERROR => Ok(()),
+            err_code => Err(err_code.into()),
+        }
+    }
+}
+
+impl ERR_CODE {
+    pub(crate) fn to_result(self) -> Result<(), MedaqError> {
+        self.into()
+    }
+}
+
+impl Error for ERR_CODE {}
+
+impl Display for ERR_CODE {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
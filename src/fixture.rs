@@ -0,0 +1,191 @@
+use std::time::SystemTime;
+
+use crate::{ChannelInfo, Data, Value, SCALED_OUT_OF_RANGE};
+
+/// Builder of [`Data`] fixtures frame by frame instead of interleaving vectors by
+/// hand
+///
+/// Available with the `test-util` feature. Frames either all have raw values or
+/// none do.
+///
+/// # Example
+/// ```
+/// use medaqlib::{DataBuilder, Value};
+///
+/// let data = DataBuilder::channels(["Distance", "Intensity"])
+///     .frame([1.0, 2.0])
+///     .frame_invalid([Value::OutOfRange, Value::Valid(3.0)])
+///     .frames(2, |frame| [frame as f64, 10.])
+///     .build();
+/// assert_eq!(data.frame_count(), 4);
+/// assert_eq!(data.scaled_data[4..], [0., 10., 1., 10.]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DataBuilder {
+    channels: Vec<String>,
+    units: Option<Vec<Option<String>>>,
+    raw_data: Vec<i32>,
+    scaled_data: Vec<f64>,
+    /// `Some(true)` once a frame with raw values was added, `Some(false)` without
+    has_raw: Option<bool>,
+    timestamp: Option<SystemTime>,
+}
+
+impl DataBuilder {
+    /// Start a capture of the given channels
+    pub fn channels<S: Into<String>>(channels: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            channels: channels.into_iter().map(Into::into).collect(),
+            units: None,
+            raw_data: vec![],
+            scaled_data: vec![],
+            has_raw: None,
+            timestamp: None,
+        }
+    }
+
+    /// Add a frame of scaled values
+    ///
+    /// # Panics
+    /// If the number of values differs from the number of channels.
+    pub fn frame(self, scaled: impl IntoIterator<Item = f64>) -> Self {
+        self.push(None, scaled.into_iter().collect())
+    }
+
+    /// Add a frame of scaled values, [`Value::OutOfRange`] becomes
+    /// [`SCALED_OUT_OF_RANGE`]
+    ///
+    /// # Panics
+    /// If the number of values differs from the number of channels.
+    pub fn frame_invalid(self, values: impl IntoIterator<Item = Value<f64>>) -> Self {
+        let scaled = values
+            .into_iter()
+            .map(|value| value.into_raw().unwrap_or(SCALED_OUT_OF_RANGE))
+            .collect();
+        self.push(None, scaled)
+    }
+
+    /// Add a frame of raw and scaled values
+    ///
+    /// # Panics
+    /// If the number of raw or scaled values differs from the number of channels.
+    pub fn raw_frame(
+        self,
+        raw: impl IntoIterator<Item = i32>,
+        scaled: impl IntoIterator<Item = f64>,
+    ) -> Self {
+        let raw = raw.into_iter().collect();
+        self.push(Some(raw), scaled.into_iter().collect())
+    }
+
+    /// Add `count` frames of scaled values returned by `frame` for the index of the
+    /// frame, counted from `0` for each call
+    pub fn frames<I: IntoIterator<Item = f64>>(
+        self,
+        count: usize,
+        mut frame: impl FnMut(usize) -> I,
+    ) -> Self {
+        (0..count).fold(self, |builder, index| builder.frame(frame(index)))
+    }
+
+    /// Attach units in channel order, see [`Data::with_channel_info`]
+    pub fn units<S: Into<String>>(self, units: impl IntoIterator<Item = Option<S>>) -> Self {
+        let units = Some(units.into_iter().map(|unit| unit.map(Into::into)).collect());
+        Self { units, ..self }
+    }
+
+    /// Set the timestamp of the capture
+    pub fn timestamp(self, timestamp: SystemTime) -> Self {
+        let timestamp = Some(timestamp);
+        Self { timestamp, ..self }
+    }
+
+    pub fn build(self) -> Data {
+        let mut data = Data::new(self.channels.clone(), self.raw_data, self.scaled_data);
+        if let Some(units) = self.units {
+            assert_eq!(units.len(), self.channels.len(), "one unit per channel");
+            let info: Vec<_> = self
+                .channels
+                .iter()
+                .zip(units)
+                .map(|(name, unit)| match unit {
+                    Some(unit) => ChannelInfo::new(name).with_unit(unit),
+                    None => ChannelInfo::new(name),
+                })
+                .collect();
+            data = data.with_channel_info(info);
+        }
+        if let Some(timestamp) = self.timestamp {
+            data = data.with_timestamp(timestamp);
+        }
+        data
+    }
+
+    fn push(mut self, raw: Option<Vec<i32>>, scaled: Vec<f64>) -> Self {
+        let channels = self.channels.len();
+        assert_eq!(scaled.len(), channels, "one scaled value per channel");
+        let has_raw = raw.is_some();
+        assert_eq!(
+            *self.has_raw.get_or_insert(has_raw),
+            has_raw,
+            "frames with and without raw values"
+        );
+        if let Some(raw) = raw {
+            assert_eq!(raw.len(), channels, "one raw value per channel");
+            self.raw_data.extend(raw);
+        }
+        self.scaled_data.extend(scaled);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use crate::{DataBuilder, Value, SCALED_OUT_OF_RANGE as OOR};
+
+    #[test]
+    fn test_data_builder_test() {
+        let data = DataBuilder::channels(["1", "2"])
+            .raw_frame([1, 2], [0.1, 0.2])
+            .raw_frame([3, -1], [0.3, OOR])
+            .units([Some("mm"), None])
+            .timestamp(UNIX_EPOCH)
+            .build();
+        assert_eq!(data.channels(), ["1", "2"]);
+        assert_eq!(data.raw_data, vec![1, 2, 3, -1]);
+        assert_eq!(data.scaled_data, vec![0.1, 0.2, 0.3, OOR]);
+        assert_eq!(data.timestamp(), Some(UNIX_EPOCH));
+        assert_eq!((data.unit(0), data.unit(1)), (Some("mm"), None));
+        assert_eq!(data.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_data_builder_frames_test() {
+        let data = DataBuilder::channels(["1"])
+            .frame_invalid([Value::OutOfRange])
+            .frames(3, |frame| [frame as f64 * 2.])
+            .build();
+        assert_eq!(data.scaled_data, vec![OOR, 0., 2., 4.]);
+        assert!(data.raw_data.is_empty());
+
+        let empty = DataBuilder::channels(["1", "2"]).build();
+        assert!(empty.is_empty());
+        assert_eq!(empty.channel_count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "one scaled value per channel")]
+    fn test_data_builder_short_frame_test() {
+        DataBuilder::channels(["1", "2"]).frame([1.]);
+    }
+
+    #[test]
+    #[should_panic(expected = "frames with and without raw values")]
+    fn test_data_builder_mixed_raw_test() {
+        DataBuilder::channels(["1"])
+            .frame([1.])
+            .raw_frame([1], [1.]);
+    }
+}
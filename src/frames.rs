@@ -0,0 +1,289 @@
+use std::{
+    iter::{Enumerate, Zip},
+    slice::{ChunksExact, Iter},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use crate::{ChannelInfo, ChannelValue, Data, Value};
+
+impl Data {
+    /// Frames of scaled values, one slice with a value per channel for every measurement
+    pub fn frames(&self) -> ChunksExact<'_, f64> {
+        match self.channel_count() {
+            0 => self.scaled_data[..0].chunks_exact(1),
+            channels => self.scaled_data.chunks_exact(channels),
+        }
+    }
+
+    /// Scaled values of every frame paired with their channel
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::Data;
+    ///
+    /// let channels = vec!["Distance".to_string(), "Intensity".to_string()];
+    /// let data = Data::new(channels, vec![], vec![1., 10., 2., 20.]);
+    /// for frame in data.iter_values() {
+    ///     for cv in frame {
+    ///         println!("{cv}")
+    ///     }
+    /// }
+    /// ```
+    pub fn iter_values(&self) -> ValueFrames<'_> {
+        ValueFrames {
+            frames: self.frames(),
+            channels: self.channels(),
+            info: self.channel_info(),
+        }
+    }
+
+    /// Owned copy of the frame at `index`, with the timestamp of the capture
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Data, Value};
+    ///
+    /// let channels = vec!["Distance".to_string(), "Intensity".to_string()];
+    /// let data = Data::new(channels, vec![], vec![1., 10., 2., 20.]);
+    /// let frame = data.frame(1).unwrap();
+    /// assert_eq!(frame.scaled(), [2., 20.]);
+    /// assert_eq!(frame.value("Intensity"), Some(Value::Valid(20.)));
+    /// ```
+    pub fn frame(&self, index: usize) -> Option<Frame> {
+        if index >= self.frame_count() {
+            return None;
+        }
+        let channels = self.channel_count();
+        let values = |len: usize| match len {
+            0 => 0..0,
+            _ => index * channels..(index + 1) * channels,
+        };
+        Some(Frame {
+            channels: self.channels_arc(),
+            raw: self.raw_data[values(self.raw_data.len())].to_vec(),
+            scaled: self.scaled_data[values(self.scaled_data.len())].to_vec(),
+            timestamp: self.timestamp(),
+        })
+    }
+}
+
+/// Single measurement of all channels, see [`Data::frame`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Frame {
+    channels: Arc<[String]>,
+    raw: Vec<i32>,
+    scaled: Vec<f64>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    timestamp: Option<SystemTime>,
+}
+
+impl Frame {
+    /// Names of the channels, in the order of the values
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Raw values, empty if the capture had none
+    pub fn raw(&self) -> &[i32] {
+        &self.raw
+    }
+
+    /// Scaled values, empty if the capture had none
+    pub fn scaled(&self) -> &[f64] {
+        &self.scaled
+    }
+
+    /// Time the capture holding the frame was read, if known
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.timestamp
+    }
+
+    /// Scaled value of a channel, `None` if the channel is unknown or the frame has
+    /// no scaled values
+    pub fn value(&self, channel: &str) -> Option<Value<f64>> {
+        let index = self.channels.iter().position(|name| name == channel)?;
+        self.scaled.get(index).copied().map(Value::from_sample)
+    }
+}
+
+/// Iterator over the frames of a capture, see [`Data::iter_values`]
+#[derive(Debug, Clone)]
+pub struct ValueFrames<'a> {
+    frames: ChunksExact<'a, f64>,
+    channels: &'a [String],
+    info: Option<&'a [ChannelInfo]>,
+}
+
+impl<'a> ValueFrames<'a> {
+    fn frame_values(&self, frame: &'a [f64]) -> FrameValues<'a> {
+        FrameValues {
+            values: self.channels.iter().zip(frame.iter()).enumerate(),
+            info: self.info,
+        }
+    }
+}
+
+impl<'a> Iterator for ValueFrames<'a> {
+    type Item = FrameValues<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next()?;
+        Some(self.frame_values(frame))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.frames.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for ValueFrames<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next_back()?;
+        Some(self.frame_values(frame))
+    }
+}
+
+impl ExactSizeIterator for ValueFrames<'_> {}
+
+/// Values of a single frame paired with their channel
+#[derive(Debug, Clone)]
+pub struct FrameValues<'a> {
+    values: Enumerate<Zip<Iter<'a, String>, Iter<'a, f64>>>,
+    info: Option<&'a [ChannelInfo]>,
+}
+
+impl<'a> FrameValues<'a> {
+    fn channel_value(
+        &self,
+        (index, (channel, &value)): (usize, (&'a String, &f64)),
+    ) -> ChannelValue<'a, f64> {
+        ChannelValue {
+            channel,
+            value: Value::from_sample(value),
+            unit: self
+                .info
+                .and_then(|info| info.get(index))
+                .and_then(|info| info.unit.as_deref()),
+        }
+    }
+}
+
+impl<'a> Iterator for FrameValues<'a> {
+    type Item = ChannelValue<'a, f64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.values.next()?;
+        Some(self.channel_value(value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for FrameValues<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let value = self.values.next_back()?;
+        Some(self.channel_value(value))
+    }
+}
+
+impl ExactSizeIterator for FrameValues<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChannelInfo, Data, DataBuilder, Value, SCALED_OUT_OF_RANGE};
+
+    fn data() -> Data {
+        DataBuilder::channels(["1", "2"])
+            .frame([1., 2.])
+            .frame([3., SCALED_OUT_OF_RANGE])
+            .frame([5., 6.])
+            .build()
+    }
+
+    #[test]
+    fn test_frames_test() {
+        let data = data();
+        let frames: Vec<_> = data.frames().collect();
+        assert_eq!(
+            frames,
+            vec![&[1., 2.][..], &[3., SCALED_OUT_OF_RANGE], &[5., 6.]]
+        );
+    }
+
+    #[test]
+    fn test_iter_values_test() {
+        let data = data().with_channel_info(vec![
+            ChannelInfo::new("1").with_unit("mm"),
+            ChannelInfo::new("2"),
+        ]);
+        let frames = data.iter_values();
+        assert_eq!(frames.len(), 3);
+
+        let values: Vec<Vec<_>> = frames.map(|frame| frame.collect()).collect();
+        assert_eq!(values[1][0].channel, "1");
+        assert_eq!(values[1][0].value, Value::Valid(3.));
+        assert_eq!(values[1][0].unit, Some("mm"));
+        assert_eq!(values[1][1].channel, "2");
+        assert_eq!(values[1][1].value, Value::OutOfRange);
+        assert_eq!(values[1][1].unit, None);
+    }
+
+    #[test]
+    fn test_iter_values_double_ended_test() {
+        let data = data();
+        let mut frames = data.iter_values();
+        let mut last = frames.next_back().unwrap();
+        assert_eq!(last.len(), 2);
+        assert_eq!(last.next_back().unwrap().value, Value::Valid(6.));
+        assert_eq!(last.next().unwrap().value, Value::Valid(5.));
+        assert!(last.next().is_none());
+        assert_eq!(frames.len(), 2);
+
+        let reversed: Vec<_> = data
+            .iter_values()
+            .rev()
+            .map(|mut frame| frame.next().unwrap().value)
+            .collect();
+        assert_eq!(
+            reversed,
+            vec![Value::Valid(5.), Value::Valid(3.), Value::Valid(1.)]
+        );
+    }
+
+    #[test]
+    fn test_iter_values_empty_capture_test() {
+        let data = Data::new(vec!["1".to_string()], vec![], vec![]);
+        assert_eq!(data.iter_values().len(), 0);
+        assert!(data.iter_values().next().is_none());
+
+        let data = Data::new(Vec::<String>::new(), vec![], vec![]);
+        assert_eq!(data.frames().len(), 0);
+        assert!(data.iter_values().next().is_none());
+    }
+
+    #[test]
+    fn test_frame_test() {
+        let timestamp = std::time::UNIX_EPOCH;
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string()],
+            vec![1, 2, 3, 4],
+            vec![1., SCALED_OUT_OF_RANGE, 3., 4.],
+        )
+        .with_timestamp(timestamp);
+
+        let frame = data.frame(0).unwrap();
+        assert_eq!(frame.raw(), [1, 2]);
+        assert_eq!(frame.value("2"), Some(Value::OutOfRange));
+        assert_eq!(frame.value("3"), None);
+        assert_eq!(data.frame(1).unwrap().scaled(), [3., 4.]);
+        assert_eq!(data.frame(1).unwrap().timestamp(), Some(timestamp));
+        assert!(data.frame(2).is_none());
+
+        let scaled_only = Data::new(vec!["1".to_string()], vec![], vec![5.]);
+        assert!(scaled_only.frame(0).unwrap().raw().is_empty());
+    }
+}
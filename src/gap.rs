@@ -0,0 +1,271 @@
+use std::{sync::mpsc::SyncSender, time::SystemTime};
+
+use crate::{
+    acquisition::{Control, Deliver},
+    stats::Stats,
+    Data, DataPool, MedaqError, Value,
+};
+
+/// Counter channel used to detect frames lost inside MEDAQLib, see [`GapDetector`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GapConfig {
+    /// Channel holding a frame counter incremented by one per frame
+    pub counter_channel: String,
+    /// The counter wraps to zero at this value, e.g. `1 << 18`. Without a modulus a
+    /// decreasing counter is taken as a restart of the sensor.
+    pub modulus: Option<u64>,
+}
+
+/// Noteworthy occurrence of a background acquisition, see
+/// [`AcquisitionHandle::events`](crate::AcquisitionHandle::events)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcquisitionEvent {
+    /// Frames are missing before the frame with the given counter value
+    Gap {
+        missing: u64,
+        counter: u64,
+        /// Timestamp of the capture containing the frame after the gap
+        at: SystemTime,
+    },
+}
+
+/// Tracks a counter channel across captures to find missing frames
+///
+/// # Example
+/// ```
+/// use medaqlib::{AcquisitionEvent, Data, GapConfig, GapDetector};
+///
+/// let mut detector = GapDetector::new(GapConfig {
+///     counter_channel: "Counter".into(),
+///     modulus: Some(8),
+/// });
+/// let data = Data::new(vec!["Counter".to_string()], vec![], vec![6., 7., 2.]);
+/// let gaps = detector.check(&data).unwrap();
+/// assert!(matches!(gaps[..], [AcquisitionEvent::Gap { missing: 2, counter: 2, .. }]));
+/// assert_eq!(detector.missing_frames(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct GapDetector {
+    config: GapConfig,
+    last: Option<u64>,
+    frames: u64,
+    gaps: u64,
+    missing: u64,
+}
+
+impl GapDetector {
+    pub fn new(config: GapConfig) -> Self {
+        Self {
+            config,
+            last: None,
+            frames: 0,
+            gaps: 0,
+            missing: 0,
+        }
+    }
+
+    /// Check the counter values of the next capture, returns the gaps within it and
+    /// since the previous capture
+    ///
+    /// Out of range counter values are skipped. Fails if the counter channel is not
+    /// part of the capture.
+    pub fn check(&mut self, data: &Data) -> Result<Vec<AcquisitionEvent>, MedaqError> {
+        let channel = data.channel_indices(&[self.config.counter_channel.as_str()])?[0];
+        let at = data.timestamp().unwrap_or_else(SystemTime::now);
+        let mut events = vec![];
+        let counters = data
+            .scaled_data
+            .iter()
+            .skip(channel)
+            .step_by(data.channel_count());
+        for &sample in counters {
+            let Value::Valid(value) = Value::from_sample(sample) else {
+                continue;
+            };
+            self.frames += 1;
+            let counter = match self.config.modulus {
+                Some(modulus) => value.round() as u64 % modulus,
+                None => value.round() as u64,
+            };
+            if let Some(missing) = self
+                .last
+                .and_then(|last| self.missing_between(last, counter))
+            {
+                self.gaps += 1;
+                self.missing += missing;
+                events.push(AcquisitionEvent::Gap {
+                    missing,
+                    counter,
+                    at,
+                });
+            }
+            self.last = Some(counter);
+        }
+        Ok(events)
+    }
+
+    fn missing_between(&self, last: u64, counter: u64) -> Option<u64> {
+        let step = match self.config.modulus {
+            Some(modulus) => (counter + modulus - last) % modulus,
+            None => counter.checked_sub(last)?,
+        };
+        step.checked_sub(1).filter(|&missing| missing > 0)
+    }
+
+    /// Frames with a valid counter seen so far
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Number of gaps found so far
+    pub fn gaps(&self) -> u64 {
+        self.gaps
+    }
+
+    /// Total number of missing frames found so far
+    pub fn missing_frames(&self) -> u64 {
+        self.missing
+    }
+
+    /// Forget the last counter value, e.g. after reconnecting
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Gap detection of a background acquisition
+pub(crate) struct GapMonitor {
+    detector: GapDetector,
+    stats: Stats,
+    events: SyncSender<AcquisitionEvent>,
+}
+
+impl GapMonitor {
+    pub(crate) fn new(
+        config: Option<GapConfig>,
+        stats: Stats,
+        events: SyncSender<AcquisitionEvent>,
+    ) -> Option<Self> {
+        config.map(|config| Self {
+            detector: GapDetector::new(config),
+            stats,
+            events,
+        })
+    }
+}
+
+/// Checks captures for gaps before handing them on
+pub(crate) struct GapDeliver<D> {
+    pub(crate) monitor: GapMonitor,
+    pub(crate) inner: D,
+}
+
+impl<D: Deliver> Deliver for GapDeliver<D> {
+    fn deliver(&mut self, result: Result<Data, MedaqError>, control: &Control) -> bool {
+        // captures without the counter channel are not checked
+        if let Ok(events) = result
+            .as_ref()
+            .map(|data| self.monitor.detector.check(data))
+        {
+            for event in events.into_iter().flatten() {
+                let AcquisitionEvent::Gap { missing, .. } = event;
+                self.monitor.stats.record_gap(missing);
+                // events are dropped while the receiver is behind, the statistics
+                // still count them
+                let _ = self.monitor.events.try_send(event);
+            }
+        }
+        self.inner.deliver(result, control)
+    }
+
+    fn pool(&self) -> Option<&DataPool> {
+        self.inner.pool()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        AcquisitionEvent, Data, DataBuilder, GapConfig, GapDetector, MedaqError,
+        SCALED_OUT_OF_RANGE,
+    };
+
+    fn detector(modulus: Option<u64>) -> GapDetector {
+        GapDetector::new(GapConfig {
+            counter_channel: "Counter".into(),
+            modulus,
+        })
+    }
+
+    fn data(counters: &[f64]) -> Data {
+        DataBuilder::channels(["Distance", "Counter"])
+            .frames(counters.len(), |frame| [1., counters[frame]])
+            .build()
+    }
+
+    fn missing(events: Vec<AcquisitionEvent>) -> Vec<(u64, u64)> {
+        events
+            .into_iter()
+            .map(
+                |AcquisitionEvent::Gap {
+                     missing, counter, ..
+                 }| (missing, counter),
+            )
+            .collect()
+    }
+
+    #[test]
+    fn test_gap_detector_test() {
+        let mut detector = detector(None);
+        assert_eq!(
+            missing(detector.check(&data(&[1., 2., 5., 6.])).unwrap()),
+            vec![(2, 5)]
+        );
+        // gap between two captures
+        assert_eq!(
+            missing(detector.check(&data(&[9., 10.])).unwrap()),
+            vec![(2, 9)]
+        );
+        assert_eq!(detector.frames(), 6);
+        assert_eq!(detector.gaps(), 2);
+        assert_eq!(detector.missing_frames(), 4);
+    }
+
+    #[test]
+    fn test_gap_detector_wrap_test() {
+        let mut detector = detector(Some(16));
+        let events = detector.check(&data(&[14., 15., 0., 1., 15., 2.])).unwrap();
+        assert_eq!(missing(events), vec![(13, 15), (2, 2)]);
+    }
+
+    #[test]
+    fn test_gap_detector_restart_and_duplicates_test() {
+        let mut detector = detector(None);
+        let events = detector
+            .check(&data(&[100., 101., 0., 1., 1., 2.]))
+            .unwrap();
+        assert_eq!(missing(events), vec![]);
+
+        detector.reset();
+        assert_eq!(missing(detector.check(&data(&[50.])).unwrap()), vec![]);
+    }
+
+    #[test]
+    fn test_gap_detector_skips_invalid_counters_test() {
+        let mut detector = detector(None);
+        let events = detector
+            .check(&data(&[1., SCALED_OUT_OF_RANGE, 2., 4.]))
+            .unwrap();
+        assert_eq!(missing(events), vec![(1, 4)]);
+        assert_eq!(detector.frames(), 3);
+    }
+
+    #[test]
+    fn test_gap_detector_unknown_channel_test() {
+        let data = Data::new(vec!["Distance".to_string()], vec![], vec![1.]);
+        assert!(matches!(
+            detector(None).check(&data),
+            Err(MedaqError::UnknownChannel { .. })
+        ));
+    }
+}
@@ -0,0 +1,443 @@
+use std::{
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    acquisition::{Acquire, Acquisition},
+    ring::FrameRing,
+    sync::{self, Shared, StopFlag},
+    AcquisitionConfig, Data, DropPolicy, MedaqError, RingBufferConfig, Sensor,
+};
+
+/// Sensors acquired together, e.g. two opposing heads of a thickness measurement
+#[derive(Debug, Default)]
+pub struct SensorGroup {
+    sensors: Vec<Sensor>,
+}
+
+impl SensorGroup {
+    pub fn new(sensors: Vec<Sensor>) -> Self {
+        Self { sensors }
+    }
+
+    pub fn sensors(&self) -> &[Sensor] {
+        &self.sensors
+    }
+
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    pub fn into_sensors(self) -> Vec<Sensor> {
+        self.sensors
+    }
+
+    /// Acquire every sensor on its own thread and combine their captures into
+    /// [`GroupCapture`]s of consecutive wall-clock windows
+    ///
+    /// A capture belongs to the window containing its timestamp. A sensor erroring
+    /// or stalling only leaves its slot empty, its errors are counted in
+    /// [`GroupAcquisition::stats`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// use medaqlib::{GroupConfig, Interface, SensorBuilder, SensorGroup, ME_SENSOR};
+    ///
+    /// let connect = |ip: &str| {
+    ///     SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///         .with_interface(Interface::TcpIp)
+    ///         .with_ip_address(ip)
+    ///         .connect()
+    ///         .unwrap()
+    /// };
+    /// let group = SensorGroup::new(vec![connect("10.10.10.10"), connect("10.10.10.11")]);
+    ///
+    /// let acquisition = group.start_acquisition(GroupConfig::default());
+    /// for capture in acquisition.receiver().iter().take(10) {
+    ///     if let [Some(top), Some(bottom)] = &capture.per_sensor[..] {
+    ///         println!("{top} {bottom}");
+    ///     }
+    /// }
+    /// let group = acquisition.join();
+    /// ```
+    pub fn start_acquisition(self, config: GroupConfig) -> GroupAcquisition {
+        GroupAcquisition {
+            inner: GroupInner::start(self.sensors, config),
+        }
+    }
+}
+
+impl FromIterator<Sensor> for SensorGroup {
+    fn from_iter<I: IntoIterator<Item = Sensor>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+/// Configuration of [`SensorGroup::start_acquisition`]
+#[derive(Debug, Clone)]
+pub struct GroupConfig {
+    /// Configuration of the thread of every sensor
+    pub acquisition: AcquisitionConfig,
+    /// Width of the wall-clock windows captures are combined over
+    pub window: Duration,
+    /// Number of combined captures buffered in the channel. The combiner waits for
+    /// the receiver when the channel is full.
+    pub channel_capacity: usize,
+}
+
+impl Default for GroupConfig {
+    fn default() -> Self {
+        Self {
+            acquisition: AcquisitionConfig::default(),
+            window: Duration::from_millis(100),
+            channel_capacity: 16,
+        }
+    }
+}
+
+/// Captures of all sensors of a group within one window
+#[derive(Debug, Clone)]
+pub struct GroupCapture {
+    /// Frames of every sensor in group order, `None` if a sensor delivered nothing
+    pub per_sensor: Vec<Option<Data>>,
+    /// Start and end of the window, the end is exclusive
+    pub window: (SystemTime, SystemTime),
+}
+
+/// Diagnostics of a group acquisition, the vectors are in group order
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GroupStats {
+    /// Windows combined so far
+    pub windows: u64,
+    /// Windows without a capture of the sensor
+    pub missing: Vec<u64>,
+    pub read_errors: Vec<u64>,
+    /// Message of the most recent failed read of the sensor
+    pub last_errors: Vec<Option<String>>,
+}
+
+impl GroupStats {
+    fn new(sensors: usize) -> Self {
+        Self {
+            windows: 0,
+            missing: vec![0; sensors],
+            read_errors: vec![0; sensors],
+            last_errors: vec![None; sensors],
+        }
+    }
+}
+
+/// Running group acquisition, see [`SensorGroup::start_acquisition`]
+///
+/// Dropping the handle stops all threads and releases the sensors.
+pub struct GroupAcquisition {
+    inner: GroupInner<Sensor>,
+}
+
+impl GroupAcquisition {
+    /// Combined captures in window order
+    pub fn receiver(&self) -> &Receiver<GroupCapture> {
+        &self.inner.receiver
+    }
+
+    pub fn stats(&self) -> GroupStats {
+        self.inner.stats()
+    }
+
+    /// Ask all threads to stop
+    pub fn stop(&self) {
+        self.inner.stop()
+    }
+
+    /// `true` once the combiner exited, e.g. after [`stop`](Self::stop)
+    pub fn is_finished(&self) -> bool {
+        self.inner
+            .thread
+            .as_ref()
+            .is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stop the acquisition and get the sensors back
+    ///
+    /// Waits for every sensor to finish its current read.
+    pub fn join(mut self) -> SensorGroup {
+        SensorGroup::new(self.inner.join())
+    }
+}
+
+/// Group acquisition independent of the source, so it can be tested without sensors
+struct GroupInner<S: Acquire + 'static> {
+    stopped: StopFlag,
+    stats: Shared<GroupStats>,
+    receiver: Receiver<GroupCapture>,
+    thread: Option<JoinHandle<Vec<Acquisition<S>>>>,
+}
+
+impl<S: Acquire + 'static> GroupInner<S> {
+    fn start(sources: Vec<S>, config: GroupConfig) -> Self {
+        let stopped = StopFlag::default();
+        let stats = Shared::new(GroupStats::new(sources.len()));
+        let (sender, receiver) = mpsc::sync_channel(config.channel_capacity);
+
+        let sources = sources
+            .into_iter()
+            .map(|source| Acquisition::with_channel(source, config.acquisition.clone()))
+            .collect();
+        let combiner = Combiner {
+            window: config.window,
+            stopped: stopped.clone(),
+            stats: stats.clone(),
+            sender,
+        };
+        let thread = thread::spawn(move || combiner.run(sources));
+
+        Self {
+            stopped,
+            stats,
+            receiver,
+            thread: Some(thread),
+        }
+    }
+
+    fn stats(&self) -> GroupStats {
+        self.stats.lock().clone()
+    }
+
+    fn stop(&self) {
+        self.stopped.stop();
+        if let Some(thread) = &self.thread {
+            thread.thread().unpark();
+        }
+    }
+
+    fn join(&mut self) -> Vec<S> {
+        self.stop();
+        let thread = self.thread.take().expect("joined only once");
+        match thread.join() {
+            Ok(acquisitions) => acquisitions
+                .into_iter()
+                .map(|mut acquisition| acquisition.join())
+                .collect(),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl<S: Acquire + 'static> Drop for GroupInner<S> {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stop();
+            // dropping the acquisitions stops and joins them
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Combiner {
+    window: Duration,
+    stopped: StopFlag,
+    stats: Shared<GroupStats>,
+    sender: SyncSender<GroupCapture>,
+}
+
+type Source<S> = (Acquisition<S>, Receiver<Result<Data, MedaqError>>);
+
+impl Combiner {
+    fn is_stopped(&self) -> bool {
+        self.stopped.is_stopped()
+    }
+
+    fn run<S: Acquire + 'static>(self, sources: Vec<Source<S>>) -> Vec<Acquisition<S>> {
+        // captures with a timestamp past the current window
+        let mut pending: Vec<Vec<Data>> = sources.iter().map(|_| vec![]).collect();
+        let mut start = SystemTime::now();
+        let mut deadline = Instant::now() + self.window;
+
+        while !self.is_stopped() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if !remaining.is_zero() {
+                sync::park_timeout(remaining);
+                continue;
+            }
+
+            let end = start + self.window;
+            let per_sensor = sources
+                .iter()
+                .zip(&mut pending)
+                .enumerate()
+                .map(|(index, ((_, receiver), pending))| {
+                    self.collect(index, receiver, pending, end)
+                })
+                .collect();
+            let capture = GroupCapture {
+                per_sensor,
+                window: (start, end),
+            };
+            if !self.send(capture) {
+                break;
+            }
+            start = end;
+            deadline += self.window;
+        }
+        sources
+            .into_iter()
+            .map(|(acquisition, _)| acquisition)
+            .collect()
+    }
+
+    /// Frames of one sensor before `end`, older captures are part of the window too
+    fn collect(
+        &self,
+        index: usize,
+        receiver: &Receiver<Result<Data, MedaqError>>,
+        pending: &mut Vec<Data>,
+        end: SystemTime,
+    ) -> Option<Data> {
+        let mut stats = self.stats.lock();
+        for result in receiver.try_iter() {
+            match result {
+                Ok(data) => pending.push(data),
+                Err(err) => {
+                    stats.read_errors[index] += 1;
+                    stats.last_errors[index] = Some(err.to_string());
+                }
+            }
+        }
+
+        let (current, later) = std::mem::take(pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|data| data.timestamp().is_none_or(|ts| ts < end));
+        *pending = later;
+
+        let mut frames = FrameRing::new(RingBufferConfig {
+            capacity_frames: usize::MAX,
+            on_drop: DropPolicy::DropOldest,
+        });
+        for data in current.iter().filter(|data| !data.is_empty()) {
+            frames.push(data);
+        }
+        let data = frames.snapshot();
+        if index == 0 {
+            stats.windows += 1;
+        }
+        if data.is_empty() {
+            stats.missing[index] += 1;
+            return None;
+        }
+        Some(data)
+    }
+
+    /// Wait for the receiver without missing a stop request, `false` if it is gone
+    fn send(&self, capture: GroupCapture) -> bool {
+        let mut pending = capture;
+        loop {
+            match self.sender.try_send(pending) {
+                Ok(()) => return true,
+                Err(TrySendError::Full(capture)) if !self.is_stopped() => {
+                    pending = capture;
+                    sync::park_timeout(self.window);
+                }
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::GroupInner;
+    use crate::{
+        acquisition::{
+            tests::{data, Script},
+            Acquire,
+        },
+        AcquisitionConfig, Data, GroupConfig, MedaqError,
+    };
+
+    fn config() -> GroupConfig {
+        GroupConfig {
+            acquisition: AcquisitionConfig {
+                poll_interval: Duration::from_millis(1),
+                ..Default::default()
+            },
+            window: Duration::from_millis(30),
+            ..Default::default()
+        }
+    }
+
+    enum Source {
+        Script(Script),
+        /// Blocks in every read for longer than a window
+        Stalled,
+    }
+
+    impl Acquire for Source {
+        fn acquire(&mut self) -> Result<Option<Data>, MedaqError> {
+            match self {
+                Source::Script(script) => script.acquire(),
+                Source::Stalled => {
+                    std::thread::sleep(Duration::from_millis(200));
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_group_combines_windows_test() {
+        let later = SystemTime::now() + Duration::from_millis(45);
+        let sources = vec![
+            Script::new(vec![
+                Ok(Some(data(1.))),
+                Ok(Some(data(2.))),
+                Ok(Some(data(3.).with_timestamp(later))),
+            ]),
+            Script::new(vec![Err(MedaqError::NoChannels)]),
+        ];
+        let mut group = GroupInner::start(sources, config());
+
+        let first = group.receiver.recv().unwrap();
+        assert_eq!(
+            first.per_sensor[0].as_ref().unwrap().scaled_data,
+            vec![1., 2.]
+        );
+        assert!(first.per_sensor[1].is_none());
+        let second = group.receiver.recv().unwrap();
+        assert_eq!(second.per_sensor[0].as_ref().unwrap().scaled_data, vec![3.]);
+        assert_eq!(second.window.0, first.window.1);
+        assert!(second.window.0 <= later && later < second.window.1);
+
+        let stats = group.stats();
+        assert!(stats.windows >= 2);
+        assert_eq!(stats.read_errors, vec![0, 1]);
+        assert_eq!(
+            stats.last_errors[1].as_deref(),
+            Some("sensor does not transmit any channels")
+        );
+        assert!(stats.missing[1] >= 2);
+
+        let scripts = group.join();
+        assert!(scripts.iter().all(|script| script.results.is_empty()));
+    }
+
+    #[test]
+    fn test_group_stalled_sensor_test() {
+        let results = (0..50).map(|i| Ok(Some(data(i as f64)))).collect();
+        let sources = vec![Source::Script(Script::new(results)), Source::Stalled];
+        let group = GroupInner::start(sources, config());
+
+        for _ in 0..3 {
+            let capture = group.receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+            assert!(capture.per_sensor[1].is_none());
+        }
+        assert!(group.stats().missing[1] >= 3);
+    }
+}
@@ -0,0 +1,188 @@
+//! Instrumentation of the MEDAQLib calls with the `tracing` feature
+//!
+//! Every call is a debug event of the target `medaqlib::ffi` with the `function`,
+//! the sensor `handle` (`0` before `CreateSensorInstance` returned it), the
+//! `parameter` or command if any, the `duration_us` and the `err_code` or other
+//! `error` it failed with. [`SensorBuilder::connect`] and [`Sensor::read_into`],
+//! also behind [`Sensor::read_data`], are spans around their calls. Without the
+//! feature these are plain functions the compiler inlines away.
+//!
+//! [`SensorBuilder::connect`]: crate::SensorBuilder::connect
+//! [`Sensor::read_into`]: crate::Sensor::read_into
+//! [`Sensor::read_data`]: crate::Sensor::read_data
+
+use crate::ME_SENSOR;
+#[cfg(feature = "tracing")]
+use crate::{MedaqError, ERR_CODE};
+
+/// Result of a MEDAQLib call, as far as it is recorded in an event
+#[cfg(feature = "tracing")]
+pub(crate) trait Outcome {
+    fn error(&self) -> Option<&MedaqError> {
+        None
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl<T> Outcome for Result<T, MedaqError> {
+    fn error(&self) -> Option<&MedaqError> {
+        self.as_ref().err()
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Outcome for u32 {}
+
+#[cfg(feature = "tracing")]
+impl Outcome for String {}
+
+/// Make the MEDAQLib call `function` on the instance `handle`
+#[cfg(feature = "tracing")]
+pub(crate) fn call<T: Outcome>(
+    function: &'static str,
+    handle: u32,
+    parameter: Option<&str>,
+    call: impl FnOnce() -> T,
+) -> T {
+    let started = std::time::Instant::now();
+    let outcome = call();
+    let duration_us = started.elapsed().as_micros() as u64;
+    let (err_code, error): (Option<ERR_CODE>, _) = match outcome.error() {
+        Some(MedaqError::Sensor(err_code)) => (Some(*err_code), None),
+        error => (None, error),
+    };
+    tracing::debug!(
+        target: "medaqlib::ffi",
+        function,
+        handle,
+        parameter,
+        duration_us,
+        err_code = err_code.map(tracing::field::debug),
+        error = error.map(tracing::field::display),
+        "MEDAQLib call"
+    );
+    outcome
+}
+
+/// Make the MEDAQLib call `function` on the instance `handle`
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn call<T>(_: &'static str, _: u32, _: Option<&str>, call: impl FnOnce() -> T) -> T {
+    call()
+}
+
+/// Span entered until dropped, nothing without the `tracing` feature
+#[must_use]
+pub(crate) struct Entered {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+/// Span of connecting a sensor of `sensor_type`
+#[cfg(feature = "tracing")]
+pub(crate) fn connect(sensor_type: ME_SENSOR) -> Entered {
+    let _span = tracing::debug_span!("connect", sensor_type = ?sensor_type).entered();
+    Entered { _span }
+}
+
+/// Span of connecting a sensor of `sensor_type`
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn connect(_: ME_SENSOR) -> Entered {
+    Entered {}
+}
+
+/// Span of reading the data available on the instance `handle`
+#[cfg(feature = "tracing")]
+pub(crate) fn read(handle: u32) -> Entered {
+    let _span = tracing::debug_span!("read", handle).entered();
+    Entered { _span }
+}
+
+/// Span of reading the data available on the instance `handle`
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+pub(crate) fn read(_: u32) -> Entered {
+    Entered {}
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{MockBackend, ME_SENSOR};
+
+    /// Output of the fmt subscriber, one line per event with its spans
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_instrument_test() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .without_time()
+            .with_writer(move || writer.clone())
+            .finish();
+
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        tracing::subscriber::with_default(subscriber, || {
+            let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+            mock.push_frame(&[1], &[0.5]);
+            sensor.read_data().unwrap().unwrap();
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        // span and function of every event
+        let calls: Vec<_> = output
+            .lines()
+            .map(|line| {
+                let (spans, fields) = line.split_once("medaqlib::ffi: MEDAQLib call ").unwrap();
+                let function = fields.split(' ').next().unwrap();
+                let spans = spans.trim_start_matches("DEBUG ").trim_end_matches(": ");
+                (spans, function.trim_start_matches("function="))
+            })
+            .collect();
+        let connect = "connect{sensor_type=SENSOR_ILD1420}";
+        let read = "read{handle=1}";
+        assert_eq!(
+            calls,
+            [
+                (connect, "\"CreateSensorInstance\""),
+                (connect, "\"SetParameterString\""),
+                (connect, "\"SetParameterString\""),
+                (connect, "\"OpenSensor\""),
+                (connect, "\"ExecSCmd\""),
+                (connect, "\"GetParameterString\""),
+                (connect, "\"GetParameterString\""),
+                (connect, "\"GetParameterDouble\""),
+                (connect, "\"GetParameterDouble\""),
+                (connect, "\"GetParameterString\""),
+                (read, "\"DataAvail\""),
+                (read, "\"TransferData\""),
+                // dropped outside of a span
+                ("", "\"CloseSensor\""),
+                ("", "\"ReleaseSensorInstance\""),
+            ]
+        );
+
+        let opened = output.lines().find(|line| line.contains("\"OpenSensor\""));
+        assert!(opened.unwrap().contains(" handle=1 duration_us="));
+        let remote = output.lines().find(|line| line.contains("IP_RemoteAddr"));
+        assert!(remote.unwrap().contains("parameter=\"IP_RemoteAddr\""));
+        let unit = output.lines().find(|line| line.contains("IA_Scaled_Unit1"));
+        assert!(unit.unwrap().ends_with(" err_code=ERR_WRONG_PARAMETER"));
+    }
+}
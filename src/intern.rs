@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    sync::{Arc, LazyLock, Mutex, PoisonError},
+};
+
+use crate::MedaqError;
+
+/// Prefixes of the parameters MEDAQLib numbers by channel, e.g. `IA_Scaled_Name1`
+const CHANNEL_PREFIXES: [&str; 6] = [
+    "IA_Scaled_Name",
+    "IA_Scaled_Unit",
+    "IA_Scaled_RangeMin",
+    "IA_Scaled_RangeMax",
+    "IA_Raw_RangeMin",
+    "IA_Raw_RangeMax",
+];
+
+/// Channels whose parameters are interned up front
+const PREGENERATED_CHANNELS: usize = 32;
+
+/// Names kept at most, converted again on every call beyond, e.g. if a caller
+/// builds names from data
+const MAX_NAMES: usize = 1024;
+
+/// Parameter and command names converted for MEDAQLib, shared by every sensor
+static NAMES: LazyLock<Names> = LazyLock::new(|| {
+    let names = CHANNEL_PREFIXES.iter().flat_map(|prefix| {
+        (1..=PREGENERATED_CHANNELS).map(move |channel| format!("{prefix}{channel}"))
+    });
+    Names::new(MAX_NAMES, names)
+});
+
+/// The name of a parameter or command as passed to MEDAQLib, converted once,
+/// fails with [`MedaqError::NulInString`] for a name containing a nul byte
+pub(crate) fn name(name: &str) -> Result<Arc<CStr>, MedaqError> {
+    NAMES.get(name)
+}
+
+/// Converted names, at most `max` of them
+struct Names {
+    names: Mutex<HashMap<Box<str>, Arc<CStr>>>,
+    max: usize,
+}
+
+impl Names {
+    /// Names that cannot be converted are left out
+    fn new(max: usize, names: impl IntoIterator<Item = String>) -> Self {
+        let names = names.into_iter().filter_map(|name| {
+            let interned = convert(&name).ok()?;
+            Some((name.into_boxed_str(), interned))
+        });
+        Self {
+            names: Mutex::new(names.collect()),
+            max,
+        }
+    }
+
+    fn get(&self, name: &str) -> Result<Arc<CStr>, MedaqError> {
+        let mut names = self.names.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(interned) = names.get(name) {
+            return Ok(Arc::clone(interned));
+        }
+        let interned = convert(name)?;
+        if names.len() < self.max {
+            names.insert(name.into(), Arc::clone(&interned));
+        }
+        Ok(interned)
+    }
+}
+
+fn convert(name: &str) -> Result<Arc<CStr>, MedaqError> {
+    Ok(CString::new(name)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{name, Names};
+    use crate::MedaqError;
+
+    #[test]
+    fn test_intern_limit_test() {
+        let names = Names::new(2, ["IA_Scaled_Name1".to_string()]);
+        let get = |name: &str| names.get(name).unwrap();
+        assert!(Arc::ptr_eq(
+            &get("IA_Scaled_Name1"),
+            &get("IA_Scaled_Name1")
+        ));
+        // interned on first use
+        assert!(Arc::ptr_eq(&get("SP_Measrate"), &get("SP_Measrate")));
+
+        // names built at runtime beyond those kept are converted on every call
+        let parameter = format!("SA_Answer{}", 3);
+        assert_eq!(get(&parameter).to_str(), Ok(&*parameter));
+        assert!(!Arc::ptr_eq(&get(&parameter), &get(&parameter)));
+        assert!(Arc::ptr_eq(&get("SP_Measrate"), &get("SP_Measrate")));
+    }
+
+    #[test]
+    fn test_intern_nul_test() {
+        let names = Names::new(1, ["SP_\0".to_string()]);
+        assert!(matches!(names.get("a\0b"), Err(MedaqError::NulInString(_))));
+        assert!(matches!(name("a\0b"), Err(MedaqError::NulInString(_))));
+        // neither takes the place of a name
+        let get = |name: &str| names.get(name).unwrap();
+        assert!(Arc::ptr_eq(&get("SP_Measrate"), &get("SP_Measrate")));
+    }
+
+    #[test]
+    fn test_intern_names_test() {
+        let name = |name: &str| super::name(name).unwrap();
+        assert_eq!(&*name("IA_Scaled_Name1"), c"IA_Scaled_Name1");
+        assert_eq!(&*name("IA_Scaled_Name120"), c"IA_Scaled_Name120");
+        assert!(Arc::ptr_eq(
+            &name("IA_Scaled_Unit7"),
+            &name("IA_Scaled_Unit7")
+        ));
+
+        for channel in [1, 8, 33, 120] {
+            let parameter = format!("IA_Scaled_RangeMin{channel}");
+            assert_eq!(name(&parameter).to_str(), Ok(&*parameter));
+        }
+    }
+}
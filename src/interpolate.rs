@@ -0,0 +1,115 @@
+use crate::{Data, Value};
+
+impl Data {
+    /// Fill short runs of out of range scaled values by linear interpolation
+    ///
+    /// Runs of at most `max_gap` frames between two valid values are filled per
+    /// channel, longer runs stay out of range. Out of range values at the start or
+    /// end of the capture are never filled. Raw values are left untouched. Returns
+    /// the interpolated capture and the number of filled samples per channel in
+    /// channel order.
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Data, SCALED_OUT_OF_RANGE};
+    ///
+    /// let data = Data::new(vec!["Distance".to_string()], vec![], vec![1., SCALED_OUT_OF_RANGE, 3.]);
+    /// let (filled, interpolated) = data.interpolate_gaps(1);
+    /// assert_eq!(filled.scaled_data, vec![1., 2., 3.]);
+    /// assert_eq!(interpolated, vec![1]);
+    /// ```
+    pub fn interpolate_gaps(&self, max_gap: usize) -> (Data, Vec<usize>) {
+        let channels = self.channel_count();
+        let mut scaled_data = self.scaled_data.clone();
+        let mut interpolated = vec![0; channels];
+
+        for (channel, interpolated) in interpolated.iter_mut().enumerate() {
+            let frames = scaled_data.len() / channels;
+            let index = |frame: usize| frame * channels + channel;
+
+            let mut last_valid: Option<(usize, f64)> = None;
+            for frame in 0..frames {
+                let Value::Valid(value) = Value::from_sample(scaled_data[index(frame)]) else {
+                    continue;
+                };
+                if let Some((start, start_value)) = last_valid {
+                    let gap = frame - start - 1;
+                    if gap > 0 && gap <= max_gap {
+                        let step = (value - start_value) / (gap + 1) as f64;
+                        for (offset, missing) in (start + 1..frame).enumerate() {
+                            scaled_data[index(missing)] = start_value + step * (offset + 1) as f64;
+                        }
+                        *interpolated += gap;
+                    }
+                }
+                last_valid = Some((frame, value));
+            }
+        }
+
+        (
+            self.with_values(self.raw_data.clone(), scaled_data),
+            interpolated,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Data, SCALED_OUT_OF_RANGE as OOR};
+
+    fn data(scaled_data: Vec<f64>) -> Data {
+        Data::new(vec!["1".to_string(), "2".to_string()], vec![], scaled_data)
+    }
+
+    #[test]
+    fn test_interpolate_gaps_test() {
+        let data = data(vec![0., 10., OOR, 20., OOR, OOR, 6., 50.]);
+        let (filled, interpolated) = data.interpolate_gaps(2);
+        crate::assert_data_approx_eq!(
+            filled,
+            self::data(vec![0., 10., 2., 20., 4., 35., 6., 50.]),
+            1e-12
+        );
+        assert_eq!(interpolated, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_interpolate_gaps_at_boundaries_test() {
+        let data = data(vec![OOR, 1., 1., OOR, 2., 3., OOR, OOR]);
+        let (filled, interpolated) = data.interpolate_gaps(5);
+        assert_eq!(filled.scaled_data, vec![OOR, 1., 1., 2., 2., 3., OOR, OOR]);
+        assert_eq!(interpolated, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_interpolate_gaps_exceeding_max_gap_test() {
+        let data = data(vec![0., 1., OOR, 1., OOR, 1., OOR, 1., 8., 1.]);
+        let (filled, interpolated) = data.interpolate_gaps(2);
+        assert_eq!(filled.scaled_data, data.scaled_data);
+        assert_eq!(interpolated, vec![0, 0]);
+
+        let (filled, interpolated) = data.interpolate_gaps(3);
+        crate::assert_data_approx_eq!(
+            filled,
+            self::data(vec![0., 1., 2., 1., 4., 1., 6., 1., 8., 1.]),
+            1e-12
+        );
+        assert_eq!(interpolated, vec![3, 0]);
+    }
+
+    #[test]
+    fn test_interpolate_gaps_entirely_invalid_channel_test() {
+        let data = data(vec![1., OOR, OOR, OOR, 3., OOR]);
+        let (filled, interpolated) = data.interpolate_gaps(10);
+        crate::assert_data_approx_eq!(filled, self::data(vec![1., OOR, 2., OOR, 3., OOR]), 1e-12);
+        assert_eq!(interpolated, vec![1, 0]);
+    }
+
+    #[test]
+    fn test_interpolate_gaps_zero_max_gap_test() {
+        let data = data(vec![1., 1., OOR, 1., 3., 1.]);
+        let (filled, interpolated) = data.interpolate_gaps(0);
+        assert_eq!(filled.scaled_data, data.scaled_data);
+        assert_eq!(interpolated, vec![0, 0]);
+    }
+}
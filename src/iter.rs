@@ -0,0 +1,178 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{Data, MedaqError, Sensor, StopToken};
+
+/// Longest sleep between checks of the stop token
+const TOKEN_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+impl Sensor {
+    /// Iterate over captures, polling every `poll` while no data is available
+    ///
+    /// Empty polls are skipped, so the iterator only ends after a read error or
+    /// once a token given to [`DataIter::with_stop_token`] is cancelled.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use medaqlib::{Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// for data in sensor.iter_data(Duration::from_millis(1)).take(100) {
+    ///     println!("{}", data.unwrap());
+    /// }
+    /// ```
+    pub fn iter_data(&self, poll: Duration) -> DataIter<'_> {
+        DataIter::new(poll, move || self.read_data())
+    }
+}
+
+/// Blocking iterator over captures, see [`Sensor::iter_data`]
+pub struct DataIter<'a> {
+    read: Box<dyn FnMut() -> Result<Option<Data>, MedaqError> + 'a>,
+    poll: Duration,
+    token: Option<StopToken>,
+    done: bool,
+}
+
+impl<'a> DataIter<'a> {
+    pub(crate) fn new(
+        poll: Duration,
+        read: impl FnMut() -> Result<Option<Data>, MedaqError> + 'a,
+    ) -> Self {
+        Self {
+            read: Box::new(read),
+            poll,
+            token: None,
+            done: false,
+        }
+    }
+
+    /// End the iteration soon after `token` is cancelled
+    pub fn with_stop_token(self, token: StopToken) -> Self {
+        Self {
+            token: Some(token),
+            ..self
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.token.as_ref().is_some_and(StopToken::is_cancelled)
+    }
+
+    /// Sleep one poll interval, waking up early if cancelled
+    fn pause(&self) {
+        if self.token.is_none() {
+            thread::sleep(self.poll);
+            return;
+        }
+        let start = Instant::now();
+        while !self.is_cancelled() {
+            let remaining = self.poll.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                return;
+            }
+            thread::sleep(remaining.min(TOKEN_CHECK_INTERVAL));
+        }
+    }
+}
+
+impl Iterator for DataIter<'_> {
+    type Item = Result<Data, MedaqError>;
+
+    /// Blocks until the next capture, a read error is yielded once and ends the
+    /// iteration
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done && !self.is_cancelled() {
+            match (self.read)() {
+                Ok(Some(data)) => return Some(Ok(data)),
+                Ok(None) => self.pause(),
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl std::iter::FusedIterator for DataIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::DataIter;
+    use crate::{
+        acquisition::{
+            tests::{data, Script},
+            Acquire,
+        },
+        MedaqError, StopToken,
+    };
+
+    fn iter(script: &mut Script, poll: Duration) -> DataIter<'_> {
+        DataIter::new(poll, move || script.acquire())
+    }
+
+    #[test]
+    fn test_iter_data_skips_empty_polls_test() {
+        let mut script = Script::new(vec![
+            Ok(None),
+            Ok(Some(data(1.))),
+            Ok(None),
+            Ok(None),
+            Ok(Some(data(2.))),
+            Err(MedaqError::NoChannels),
+            Ok(Some(data(3.))),
+        ]);
+        let start = Instant::now();
+        let items: Vec<_> = iter(&mut script, Duration::from_millis(5)).collect();
+        assert!(start.elapsed() >= Duration::from_millis(15));
+
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].as_ref().unwrap().scaled_data, vec![1.]);
+        assert_eq!(items[1].as_ref().unwrap().scaled_data, vec![2.]);
+        assert!(matches!(items[2], Err(MedaqError::NoChannels)));
+        assert_eq!(script.reads, 6);
+    }
+
+    #[test]
+    fn test_iter_data_take_while_test() {
+        let results = (0..10).map(|i| Ok(Some(data(i as f64)))).collect();
+        let mut script = Script::new(results);
+        let values: Vec<_> = iter(&mut script, Duration::from_millis(1))
+            .map_while(Result::ok)
+            .map(|data| data.scaled_data[0])
+            .take_while(|&value| value < 3.)
+            .collect();
+        assert_eq!(values, vec![0., 1., 2.]);
+    }
+
+    #[test]
+    fn test_iter_data_cancelled_test() {
+        let token = StopToken::new();
+        let canceller = token.clone();
+        let cancelled_at = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            canceller.cancel();
+            Instant::now()
+        });
+
+        let mut script = Script::new(vec![Ok(Some(data(1.)))]);
+        let mut iter = iter(&mut script, Duration::from_secs(60)).with_stop_token(token);
+        assert!(iter.next().unwrap().is_ok());
+        assert!(iter.next().is_none());
+        let reaction = Instant::now().duration_since(cancelled_at.join().unwrap());
+        assert!(reaction < Duration::from_millis(50), "{reaction:?}");
+        assert!(iter.next().is_none());
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    io::{self, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Data, Value};
+
+/// How out of range samples are written to JSON
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum InvalidEncoding {
+    /// Write `null`
+    #[default]
+    Null,
+    /// Write the given string, e.g. `"OutOfRange"`
+    Text(String),
+}
+
+/// Options for writing [`Data`] as JSON Lines
+#[derive(Debug, Clone, Default)]
+pub struct JsonlOptions {
+    /// Add a `"ts"` field with the capture timestamp in seconds since the UNIX epoch
+    pub timestamp: bool,
+    /// Sample rate of the sensor. If set, every frame gets its own timestamp, offset
+    /// from the capture timestamp by its position in the capture.
+    pub sample_rate_hz: Option<f64>,
+    /// Channel holding a frame counter. Together with `sample_rate_hz` the offsets
+    /// are derived from the counter instead of the position in the capture.
+    pub counter_channel: Option<String>,
+    pub invalid: InvalidEncoding,
+}
+
+impl Data {
+    /// Write one JSON object per frame, e.g. `{"ts":1700000000.5,"Distance":1.23,"Intensity":null}`
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Data, JsonlOptions};
+    ///
+    /// let data = Data::new(vec!["Distance".to_string()], vec![], vec![1.5]);
+    /// let mut jsonl = vec![];
+    /// data.write_jsonl(&mut jsonl, &JsonlOptions::default()).unwrap();
+    /// assert_eq!(String::from_utf8(jsonl).unwrap(), "{\"Distance\":1.5}\n");
+    /// ```
+    pub fn write_jsonl<W: Write>(&self, mut writer: W, options: &JsonlOptions) -> io::Result<()> {
+        let channels = self.channel_count();
+        if channels == 0 {
+            return Ok(());
+        }
+        let keys: Vec<String> = self
+            .channels()
+            .iter()
+            .map(|channel| serde_json::to_string(channel).expect("strings serialize"))
+            .collect();
+        let counter = options
+            .counter_channel
+            .as_ref()
+            .and_then(|counter| self.channels().iter().position(|c| c == counter));
+        let first_counter = counter.and_then(|index| self.scaled_data.get(index).copied());
+
+        let mut line = String::new();
+        for (index, frame) in self.scaled_data.chunks_exact(channels).enumerate() {
+            line.clear();
+            line.push('{');
+
+            if let Some(timestamp) = self.timestamp().filter(|_| options.timestamp) {
+                let offset = match (options.sample_rate_hz, counter, first_counter) {
+                    (Some(rate), Some(counter), Some(first)) => (frame[counter] - first) / rate,
+                    (Some(rate), _, _) => index as f64 / rate,
+                    (None, _, _) => 0.,
+                };
+                line.push_str("\"ts\":");
+                line.push_str(&format_number(seconds_since_epoch(timestamp) + offset));
+                line.push(',');
+            }
+
+            for (key, &value) in keys.iter().zip(frame) {
+                line.push_str(key);
+                line.push(':');
+                match (Value::from_sample(value), &options.invalid) {
+                    (Value::Valid(value), _) => line.push_str(&format_number(value)),
+                    (Value::OutOfRange, InvalidEncoding::Null) => line.push_str("null"),
+                    (Value::OutOfRange, InvalidEncoding::Text(text)) => {
+                        line.push_str(&serde_json::to_string(text).expect("strings serialize"))
+                    }
+                }
+                line.push(',');
+            }
+
+            line.pop();
+            line.push_str("}\n");
+            writer.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn seconds_since_epoch(timestamp: SystemTime) -> f64 {
+    timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs_f64()
+}
+
+fn format_number(value: f64) -> String {
+    serde_json::to_string(&value).expect("numbers serialize")
+}
+
+/// Writes successive captures as JSON Lines, flushing after every capture
+pub struct JsonlRecorder<W: Write> {
+    writer: W,
+    options: JsonlOptions,
+}
+
+impl<W: Write> JsonlRecorder<W> {
+    pub fn new(writer: W, options: JsonlOptions) -> Self {
+        Self { writer, options }
+    }
+
+    /// Append all frames of the capture and flush
+    pub fn record(&mut self, data: &Data) -> io::Result<()> {
+        data.write_jsonl(&mut self.writer, &self.options)?;
+        self.writer.flush()
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::{Data, InvalidEncoding, JsonlOptions, JsonlRecorder, SCALED_OUT_OF_RANGE};
+
+    fn data() -> Data {
+        Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            vec![1.23, SCALED_OUT_OF_RANGE, 1.5, 80.],
+        )
+        .with_timestamp(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+    }
+
+    fn to_jsonl(data: &Data, options: &JsonlOptions) -> String {
+        let mut jsonl = vec![];
+        data.write_jsonl(&mut jsonl, options).unwrap();
+        String::from_utf8(jsonl).unwrap()
+    }
+
+    #[test]
+    fn test_write_jsonl_test() {
+        let jsonl = to_jsonl(&data(), &JsonlOptions::default());
+        assert_eq!(
+            jsonl,
+            "{\"Distance\":1.23,\"Intensity\":null}\n{\"Distance\":1.5,\"Intensity\":80.0}\n"
+        );
+    }
+
+    #[test]
+    fn test_write_jsonl_timestamp_test() {
+        let options = JsonlOptions {
+            timestamp: true,
+            ..Default::default()
+        };
+        let jsonl = to_jsonl(&data(), &options);
+        assert_eq!(
+            jsonl,
+            "{\"ts\":1700000000.0,\"Distance\":1.23,\"Intensity\":null}\n\
+             {\"ts\":1700000000.0,\"Distance\":1.5,\"Intensity\":80.0}\n"
+        );
+    }
+
+    #[test]
+    fn test_write_jsonl_sample_rate_test() {
+        let options = JsonlOptions {
+            timestamp: true,
+            sample_rate_hz: Some(4.),
+            invalid: InvalidEncoding::Text("OutOfRange".into()),
+            ..Default::default()
+        };
+        let jsonl = to_jsonl(&data(), &options);
+        assert_eq!(
+            jsonl,
+            "{\"ts\":1700000000.0,\"Distance\":1.23,\"Intensity\":\"OutOfRange\"}\n\
+             {\"ts\":1700000000.25,\"Distance\":1.5,\"Intensity\":80.0}\n"
+        );
+    }
+
+    #[test]
+    fn test_write_jsonl_counter_channel_test() {
+        let data = Data::new(
+            vec!["Counter".to_string(), "Distance".to_string()],
+            vec![],
+            vec![10., 1., 11., 2., 14., 3.],
+        )
+        .with_timestamp(UNIX_EPOCH + Duration::from_secs(100));
+        let options = JsonlOptions {
+            timestamp: true,
+            sample_rate_hz: Some(2.),
+            counter_channel: Some("Counter".into()),
+            ..Default::default()
+        };
+        let lines: Vec<_> = to_jsonl(&data, &options)
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["ts"].as_f64())
+            .collect();
+        assert_eq!(lines, vec![Some(100.), Some(100.5), Some(102.)]);
+    }
+
+    #[test]
+    fn test_jsonl_recorder_test() {
+        let mut recorder = JsonlRecorder::new(vec![], JsonlOptions::default());
+        recorder.record(&data()).unwrap();
+        recorder.record(&data()).unwrap();
+        let jsonl = String::from_utf8(recorder.into_inner().unwrap()).unwrap();
+        assert_eq!(jsonl.lines().count(), 4);
+    }
+}
@@ -1,31 +1,215 @@
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
 use std::{
-    error::Error,
-    ffi::CString,
+    cell::{Cell, RefCell},
     fmt::{Debug, Display},
-    iter,
+    marker::PhantomData,
     net::Ipv4Addr,
-    sync::LazyLock,
-    vec,
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
 };
 
-#[allow(dead_code, non_camel_case_types, non_snake_case)]
-// mod bindings {
-//     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
-// }
+mod acquisition;
+mod alarm;
+mod alias;
+mod approx;
+#[cfg(feature = "async")]
+mod async_sensor;
+mod backend;
+mod batch;
+mod binary;
+#[cfg(feature = "bindgen")]
+#[allow(
+    dead_code,
+    non_camel_case_types,
+    non_snake_case,
+    clippy::too_many_arguments,
+    clippy::missing_safety_doc
+)]
+mod bindings {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+// MEDAQLib.h declares the functions WINAPI, which is stdcall on 32 bit Windows
+// but the checked-in bindings were generated for 64 bit with the C ABI
+#[cfg(all(windows, target_arch = "x86", not(feature = "bindgen"), not(docsrs)))]
+compile_error!("the checked-in bindings do not fit 32 bit Windows, enable the `bindgen` feature");
+#[cfg(not(feature = "bindgen"))]
+#[allow(
+    dead_code,
+    non_camel_case_types,
+    non_snake_case,
+    clippy::too_many_arguments,
+    clippy::missing_safety_doc
+)]
 mod bindings;
-
-pub use bindings::ME_SENSOR;
-use bindings::*;
-
-const MEDAQLIB_DLL: &str = "MEDAQLib.dll";
-static MEDAQLIB: LazyLock<MEDAQLib> =
-    LazyLock::new(|| unsafe { MEDAQLib::new(MEDAQLIB_DLL).expect("could not find dll") });
+mod callback;
+mod capabilities;
+#[cfg(feature = "capi")]
+mod capi;
+mod channel;
+mod csv;
+mod diagnostics;
+#[cfg(feature = "dsp")]
+mod dsp;
+mod error;
+#[cfg(any(test, feature = "test-util"))]
+mod fixture;
+/// The bindings generated from `MEDAQLib.h`, for what the safe layer does not wrap
+///
+/// Available with the `ffi` feature. The items change with MEDAQLib and bindgen
+/// versions and are exempt from semver, only `ffi::ME_SENSOR` and `ffi::ERR_CODE`
+/// are the same types as [`ME_SENSOR`] and [`ERR_CODE`] in every version.
+///
+/// Loading the library with [`MEDAQLib::new`](ffi::MEDAQLib::new) bypasses
+/// [`set_library_path`] and the library used by sensors.
+#[cfg(feature = "ffi")]
+pub mod ffi {
+    pub use crate::bindings::*;
+}
+mod frames;
+mod gap;
+mod group;
+mod instrument;
+mod intern;
+mod interpolate;
+mod iter;
+#[cfg(feature = "serde")]
+mod jsonl;
+mod library;
+#[cfg(feature = "link")]
+mod linked;
+#[cfg(not(feature = "link"))]
+mod loader;
+mod measuring_range;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod mock;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod names;
+mod outlier;
+mod outputs;
+mod pacer;
+mod pool;
+mod preset;
+mod priority;
+#[cfg(feature = "python")]
+mod python;
+mod recorder;
+mod replay;
+mod restream;
+mod ring;
+mod scaling;
+mod scoped;
+mod select;
+mod sensor_ref;
+pub mod sensors;
+mod sim;
+mod sink;
+mod stats;
+mod stop;
+#[cfg(feature = "async")]
+mod stream;
+mod summary;
+mod sync;
+mod sync_mode;
+mod trace;
+mod watch;
+#[cfg(feature = "ws")]
+mod websocket;
+mod window;
+
+pub use acquisition::{AcquisitionConfig, AcquisitionHandle};
+pub use alarm::{AlarmEvent, AlarmRule, Comparison, InvalidSamples};
+#[cfg(feature = "async")]
+pub use async_sensor::AsyncSensor;
+pub use backend::Backend;
+pub use batch::BatchConfig;
+pub use binary::{BinaryReader, BinaryRecorder, BINARY_FORMAT_VERSION};
+pub use bindings::{ERR_CODE, ME_SENSOR};
+pub use callback::{CallbackConfig, CallbackError, CallbackHandle, ErrorHook};
+pub use capabilities::Capabilities;
+#[cfg(feature = "capi")]
+pub use capi::{MedaqConfig, MedaqSensor, MedaqStatus};
+pub use channel::ChannelInfo;
+pub use csv::{CsvOptions, CsvWriter};
+pub use diagnostics::{DiagnosticReport, ErrorRecord, QueryFailure};
+#[cfg(feature = "dsp")]
+pub use dsp::{Spectrum, Window};
+pub use error::{DataLayoutError, MedaqError, ShutdownError};
+#[cfg(any(test, feature = "test-util"))]
+pub use fixture::DataBuilder;
+pub use frames::{Frame, FrameValues, ValueFrames};
+pub use gap::{AcquisitionEvent, GapConfig, GapDetector};
+pub use group::{GroupAcquisition, GroupCapture, GroupConfig, GroupStats, SensorGroup};
+pub use iter::DataIter;
+#[cfg(feature = "serde")]
+pub use jsonl::{InvalidEncoding, JsonlOptions, JsonlRecorder};
+pub use library::{
+    dll_capabilities, dll_version, library_available, shutdown, DllCapabilities, DllVersion,
+};
+#[cfg(feature = "link")]
+pub use linked::{init_with_path, set_library_path, RuntimeLoading};
+#[cfg(not(feature = "link"))]
+pub use loader::{init_with_path, set_library_path};
+#[cfg(feature = "metrics")]
+pub use metrics::PrometheusExporter;
+pub use mock::{Call, Intercept, MockBackend, RecordedCall};
+#[cfg(feature = "mqtt")]
+pub use mqtt::{
+    MqttClient, MqttConfig, MqttPayload, MqttPublisher, MqttQos, PublishMonitor, PublishStats,
+    RumqttClient,
+};
+pub use outlier::OutlierPolicy;
+pub use outputs::{AnalogOutConfig, AnalogRange, AnalogScaling, LimitConfig, LimitPolarity};
+pub use pacer::{Pacer, PacerConfig};
+pub use pool::{DataPool, PoolStats, PooledData};
+#[cfg(feature = "serde")]
+pub use preset::PresetStore;
+pub use preset::{ApplyReport, ParameterValue, Preset, PresetItem};
+pub use priority::ThreadPriority;
+pub use recorder::{read_index, IndexEntry, RecordFormat, Recorder, RecorderConfig, SyncPolicy};
+pub use replay::{ReplayBackend, ReplaySpeed};
+pub use restream::{LineFormat, RestreamConfig, TcpRestreamer};
+pub use ring::{DropPolicy, RingBufferConfig};
+pub use scaling::ScalingMismatch;
+pub use select::DataView;
+use sensor_ref::SensorCore;
+pub use sensor_ref::SensorRef;
+pub use sim::{
+    InvalidPattern, SensorFamily, SentinelProfile, SimConfig, SimulatedSensor, Waveform,
+};
+pub use sink::{sink_fn, DataSink, FnSink, SinkClosed};
+pub use stats::AcquisitionStats;
+use stats::Stats;
+pub use stop::StopToken;
+#[cfg(feature = "async")]
+pub use stream::{Backpressure, BufferedFrames, DataStream, FrameStream};
+pub use summary::{CaptureSummary, ChannelSummary};
+pub use sync_mode::SyncMode;
+pub use trace::{ReplayTrace, TraceBackend};
+pub use watch::Watch;
+#[cfg(feature = "ws")]
+pub use websocket::{WebsocketConfig, WebsocketStreamer};
+pub use window::{FrameWindow, FrameWindows, WindowEdges};
+
+/// Scaled value transmitted by the sensor for out of range samples
+pub const SCALED_OUT_OF_RANGE: f64 = f64::MIN;
+
+/// Longest pause between polls of [`Sensor::read_data_blocking`] and
+/// [`Sensor::read_data_cancellable`] unless set by [`Sensor::set_pacing`]
+const BLOCKING_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Frames a read allocates for at most unless set by [`Sensor::set_max_read`]
+const DEFAULT_MAX_READ: usize = 1 << 16;
 
 /// Builder for creating new Sensor instance and connect to it
 ///
 /// # Example
-/// ```
-/// use medaq_lib::{Interface, SensorBuilder, ME_SENSOR};
+/// ```no_run
+/// use medaqlib::{Interface, SensorBuilder, ME_SENSOR};
 ///
 /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
 ///     .with_interface(Interface::TcpIp)
@@ -33,25 +217,79 @@ static MEDAQLIB: LazyLock<MEDAQLib> =
 ///     .enable_logging()
 ///     .connect()
 ///     .unwrap();
+/// ```
 ///
-
-#[derive(Default)]
+/// Without a sensor or MEDAQLib, connect to the simulator, see [`SimConfig`]
+/// ```
+/// use std::time::Duration;
+/// use medaqlib::{Interface, SensorBuilder, ME_SENSOR};
+///
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::Simulated)
+///     .connect()?;
+/// let data = sensor.read_data_blocking(Duration::from_secs(1))?;
+/// assert_eq!(data.unwrap().channels(), ["Distance"]);
+/// # Ok::<(), medaqlib::MedaqError>(())
+/// ```
+///
+/// Its [`Debug`] output shows the configuration but not the backend.
 pub struct SensorBuilder {
-    sensor_handle: u32,
+    sensor_type: ME_SENSOR,
     interface: Option<Interface>,
     ip_address: Option<String>,
+    port: Option<u16>,
     logging: bool,
+    backend: Option<Arc<dyn Backend>>,
+    trace: Option<PathBuf>,
+}
+
+impl Debug for SensorBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SensorBuilder")
+            .field("sensor_type", &self.sensor_type)
+            .field("interface", &self.interface)
+            .field("ip_address", &self.ip_address)
+            .field("port", &self.port)
+            .field("logging", &self.logging)
+            .field("custom_backend", &self.backend.is_some())
+            .field("trace", &self.trace)
+            .finish()
+    }
+}
+
+impl Default for SensorBuilder {
+    fn default() -> Self {
+        Self::new(ME_SENSOR::NO_SENSOR)
+    }
 }
 
 impl SensorBuilder {
     pub fn new(sensor_type: ME_SENSOR) -> Self {
-        let sensor_handle = unsafe { MEDAQLIB.CreateSensorInstance(sensor_type) };
         Self {
-            sensor_handle,
-            ..Default::default()
+            sensor_type,
+            interface: None,
+            ip_address: None,
+            port: None,
+            logging: false,
+            backend: None,
+            trace: None,
         }
     }
 
+    /// Talk to `backend` instead of `MEDAQLib.dll`, e.g. a [`MockBackend`] to
+    /// develop without a sensor
+    pub fn with_backend(self, backend: impl Backend + 'static) -> Self {
+        let backend: Option<Arc<dyn Backend>> = Some(Arc::new(backend));
+        Self { backend, ..self }
+    }
+
+    /// Write every MEDAQLib call of the sensor to a trace file, which
+    /// [`ReplayTrace`] replays, e.g. to reproduce a bug report
+    pub fn with_trace(self, path: impl Into<PathBuf>) -> Self {
+        let trace = Some(path.into());
+        Self { trace, ..self }
+    }
+
     /// Select the interface to be used
     pub fn with_interface(self, interface: Interface) -> Self {
         let interface = Some(interface);
@@ -63,6 +301,13 @@ impl SensorBuilder {
         Self { ip_address, ..self }
     }
 
+    /// TCP port of the sensor, otherwise MEDAQLib uses the default port of the sensor
+    /// type
+    pub fn with_port(self, port: u16) -> Self {
+        let port = Some(port);
+        Self { port, ..self }
+    }
+
     /// enable Logfile writing
     pub fn enable_logging(self) -> Self {
         Self {
@@ -71,153 +316,328 @@ impl SensorBuilder {
         }
     }
 
-    pub fn connect(self) -> Result<Sensor, Box<dyn Error>> {
-        let interface = self.interface.ok_or("no interface provided")?;
-        self.set_interface(&interface)?;
-
-        let ip_address = self
-            .ip_address
-            .as_ref()
-            .ok_or("no ip address provided")?
-            .parse()?;
-        self.set_ip_address(&ip_address)?;
+    /// Check the options without loading MEDAQLib or touching the sensor, e.g. for a
+    /// dry run. [`connect`](Self::connect) fails with the same error.
+    ///
+    /// ```
+    /// use medaqlib::{Interface, MedaqError, SensorBuilder, ME_SENSOR};
+    ///
+    /// let builder = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421).with_interface(Interface::TcpIp);
+    /// assert!(matches!(builder.validate(), Err(MedaqError::MissingIpAddress)));
+    /// assert!(builder.with_ip_address("10.0.0.5").validate().is_ok());
+    /// ```
+    pub fn validate(&self) -> Result<(), MedaqError> {
+        self.remote().map(drop)
+    }
 
-        if self.logging {
-            self.set_enable_logging()?;
-        }
+    /// Interface and address to connect to
+    fn remote(&self) -> Result<(Interface, Option<Ipv4Addr>), MedaqError> {
+        let interface = self.interface.ok_or(MedaqError::MissingInterface)?;
+        // the simulator has no address
+        let ip_address = match (interface, &self.ip_address) {
+            (Interface::Simulated, None) => None,
+            (_, ip_address) => Some(
+                ip_address
+                    .as_ref()
+                    .ok_or(MedaqError::MissingIpAddress)?
+                    .parse()?,
+            ),
+        };
+        Ok((interface, ip_address))
+    }
 
-        self.open_sensor()?;
+    pub fn connect(self) -> Result<Sensor, MedaqError> {
+        let _span = instrument::connect(self.sensor_type);
+        let (interface, ip_address) = self.remote()?;
 
+        let backend = match (self.backend, interface) {
+            (Some(backend), _) => backend,
+            (None, Interface::Simulated) => Arc::new(SimulatedSensor::new(SimConfig::default())),
+            (None, _) => backend::default_backend()?,
+        };
+        let backend = match &self.trace {
+            Some(path) => {
+                Arc::new(TraceBackend::to_file(path, backend).map_err(MedaqError::TraceFile)?)
+            }
+            None => backend,
+        };
+        let sensor_handle = instrument::call("CreateSensorInstance", 0, None, || {
+            backend.create_instance(self.sensor_type)
+        });
+        // released by the sensor, also if connecting fails
         let mut sensor = Sensor {
-            sensor_handle: self.sensor_handle,
-            parameters: vec![],
+            core: Arc::new(SensorCore::new(backend, sensor_handle)),
+            sensor_type: self.sensor_type,
+            capabilities: Capabilities::of(self.sensor_type, &[]),
+            parameters: Arc::new([]),
+            channel_info: Arc::new([]),
+            interface,
+            ip_address,
+            stats: Stats::default(),
+            trace: self.trace,
+            pacer: RefCell::new(pacer::blocking_pacer()),
+            max_read: DEFAULT_MAX_READ,
+            _not_sync: PhantomData,
         };
 
-        sensor.get_parameters()?;
+        sensor.set_parameter_string("IP_Interface", &interface.to_string())?;
+        if let Some(ip_address) = ip_address {
+            sensor.set_parameter_string("IP_RemoteAddr", &ip_address.to_string())?;
+        }
+        if let Some(port) = self.port {
+            sensor.set_parameter_int("IP_Port", port.into())?;
+        }
+        if self.logging {
+            sensor.set_parameter_int("IP_EnableLogging", true as i32)?;
+        }
+        sensor.core.lock()?.open_sensor()?;
+
+        sensor.refresh_channels()?;
+        sensor.capabilities = Capabilities::of(sensor.sensor_type, &sensor.channel_info);
 
         Ok(sensor)
     }
+}
 
-    fn set_interface(&self, interface: &Interface) -> Result<(), Box<dyn Error>> {
-        let param_name = CString::new("IP_Interface").expect("error creating cstring");
-        let param_value = CString::new(interface.to_string()).expect("error creating cstring");
-        self.set_parameter_string(param_name, param_value)
+/// Connection to a sensor, see [`SensorBuilder`]
+///
+/// # Threads
+///
+/// A `Sensor` is [`Send`], it may be created on one thread and used or dropped on
+/// another. The handle only identifies the instance within MEDAQLib, which keeps no
+/// per thread state, and the library functions are shared by all threads.
+///
+/// It is not [`Sync`]: most operations are a sequence of calls on the same
+/// instance, e.g. setting parameters and executing a command, which must not be
+/// interleaved with calls from another thread. Different sensors can be used
+/// concurrently from different threads, share a single sensor between threads
+/// behind a [`Mutex`](std::sync::Mutex). For status queries and settings while
+/// another thread reads, e.g. an acquisition, hand out a [`SensorRef`] with
+/// [`clone_ref`](Sensor::clone_ref) instead.
+///
+/// ```compile_fail
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<medaqlib::Sensor>();
+/// ```
+///
+/// Its [`Display`] is a one line summary, e.g. `IFD2421 @ 10.0.0.5, 3 channels`,
+/// its [`Debug`] output adds the MEDAQLib handle and whether the instance is
+/// still open.
+pub struct Sensor {
+    /// Shared with the [`SensorRef`]s
+    core: Arc<SensorCore>,
+    sensor_type: ME_SENSOR,
+    capabilities: Capabilities,
+    /// Channel names shared with every [`Data`] read from this sensor
+    parameters: Arc<[String]>,
+    channel_info: Arc<[ChannelInfo]>,
+    interface: Interface,
+    /// `None` for the simulator
+    ip_address: Option<Ipv4Addr>,
+    stats: Stats,
+    /// File of [`SensorBuilder::with_trace`]
+    trace: Option<PathBuf>,
+    /// Pacing of the blocking reads
+    pacer: RefCell<Pacer>,
+    /// Frames [`read_into`](Self::read_into) allocates for at most
+    max_read: usize,
+    /// Calls on one instance must not interleave, see the type documentation
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<Sensor>();
+};
+
+impl Debug for Sensor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sensor")
+            .field("sensor_type", &self.sensor_type)
+            .field("handle", &self.core.handle())
+            .field("interface", &self.interface)
+            .field("ip_address", &self.ip_address)
+            .field("channels", &self.parameters)
+            .field("open", &self.core.is_open())
+            .finish_non_exhaustive()
     }
+}
 
-    fn set_ip_address(&self, ip_address: &Ipv4Addr) -> Result<(), Box<dyn Error>> {
-        let param_name = CString::new("IP_RemoteAddr").expect("error creating cstring");
-        let param_value = CString::new(ip_address.to_string()).expect("error creating cstring");
-        self.set_parameter_string(param_name, param_value)
+impl Display for Sensor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} @ ", names::short_name(self.sensor_type))?;
+        match self.ip_address {
+            Some(ip_address) => write!(f, "{ip_address}")?,
+            None => write!(f, "{}", self.interface)?,
+        }
+        match self.parameters.len() {
+            1 => write!(f, ", 1 channel"),
+            channels => write!(f, ", {channels} channels"),
+        }
     }
+}
+
+impl Sensor {
+    /// Ask the sensor again for the transmitted channels with
+    /// `Get_TransmittedDataInfo`, after a command changed them, e.g. activating
+    /// channels
+    ///
+    /// Done when connecting. The names reported by the sensor replace those of
+    /// [`set_channel_aliases`](Self::set_channel_aliases).
+    pub fn refresh_channels(&mut self) -> Result<(), MedaqError> {
+        let instance = self.hold()?;
+        instance.exec_scmd("Get_TransmittedDataInfo")?;
+
+        let mut parameters = vec![];
+        let mut channel_info = vec![];
+        for counter in 1.. {
+            let name = match self.get_parameter_string(&format!("IA_Scaled_Name{counter}")) {
+                Ok(name) if !name.is_empty() => name,
+                Ok(_) | Err(MedaqError::Sensor(_)) => break,
+                Err(err) => return Err(err),
+            };
+            let unit = self
+                .get_parameter_string(&format!("IA_Scaled_Unit{counter}"))
+                .ok()
+                .filter(|unit| !unit.is_empty());
+
+            let (range, scaling) = self.get_scaling(counter);
+
+            channel_info.push(ChannelInfo {
+                name: name.clone(),
+                unit,
+                scale: scaling.map(|(scale, _)| scale),
+                offset: scaling.map(|(_, offset)| offset),
+                range,
+            });
+            parameters.push(name);
+        }
 
-    fn set_enable_logging(&self) -> Result<(), Box<dyn Error>> {
-        let param_name = CString::new("IP_EnableLogging").expect("error creating cstring");
-        let param_value = true;
-        self.set_parameter_int(param_name, param_value)
+        self.parameters = parameters.into();
+        self.channel_info = channel_info.into();
+        Ok(())
     }
 
-    fn open_sensor(&self) -> Result<(), Box<dyn Error>> {
-        unsafe { MEDAQLIB.OpenSensor(self.sensor_handle).into() }
+    /// Scaled range of a channel and the linear scaling derived from it and the
+    /// raw range
+    fn get_scaling(&self, counter: usize) -> (Option<Range<f64>>, Option<(f64, f64)>) {
+        let range = |name: &str| self.get_parameter_double(&format!("{name}{counter}")).ok();
+        let (Some(scaled_min), Some(scaled_max)) =
+            (range("IA_Scaled_RangeMin"), range("IA_Scaled_RangeMax"))
+        else {
+            return (None, None);
+        };
+        let scaling = (|| {
+            let raw_min = range("IA_Raw_RangeMin")?;
+            let raw_max = range("IA_Raw_RangeMax")?;
+            if raw_max == raw_min {
+                return None;
+            }
+            let scale = (scaled_max - scaled_min) / (raw_max - raw_min);
+            Some((scale, scaled_min - raw_min * scale))
+        })();
+        (Some(scaled_min..scaled_max), scaling)
     }
 
-    fn set_parameter_string(
-        &self,
-        param_name: CString,
-        param_value: CString,
-    ) -> Result<(), Box<dyn Error>> {
-        let param_name = param_name.as_ptr();
-        let param_value = param_value.as_ptr();
-
-        unsafe {
-            MEDAQLIB
-                .SetParameterString(self.sensor_handle, param_name, param_value)
-                .into()
-        }
+    /// Set a string parameter of the sensor instance, e.g. `IP_RemoteAddr` or the
+    /// `SP_` parameter of the next command
+    pub fn set_parameter_string(&self, param_name: &str, value: &str) -> Result<(), MedaqError> {
+        let result = (self.core.lock())
+            .and_then(|instance| instance.set_parameter_string(param_name, value));
+        self.stats.record_call(param_name, &result);
+        result
     }
 
-    fn set_parameter_int(
-        &self,
-        param_name: CString,
-        param_value: bool,
-    ) -> Result<(), Box<dyn Error>> {
-        let param_name = param_name.as_ptr();
-        let param_value = param_value as i32;
-
-        unsafe {
-            MEDAQLIB
-                .SetParameterInt(self.sensor_handle, param_name, param_value)
-                .into()
-        }
+    /// Set an int parameter of the sensor instance
+    pub fn set_parameter_int(&self, param_name: &str, value: i32) -> Result<(), MedaqError> {
+        let result =
+            (self.core.lock()).and_then(|instance| instance.set_parameter_int(param_name, value));
+        self.stats.record_call(param_name, &result);
+        result
     }
-}
 
-#[derive(Debug)]
-pub struct Sensor {
-    sensor_handle: u32,
-    parameters: Vec<String>,
-}
+    /// Set a double parameter of the sensor instance, fails with
+    /// [`MedaqError::UnsupportedByDll`] if MEDAQLib does not export
+    /// `SetParameterDouble`, see [`dll_capabilities`]
+    pub fn set_parameter_double(&self, param_name: &str, value: f64) -> Result<(), MedaqError> {
+        let result = (self.core.lock())
+            .and_then(|instance| instance.set_parameter_double(param_name, value));
+        self.stats.record_call(param_name, &result);
+        result
+    }
 
-impl Sensor {
-    fn get_parameters(&mut self) -> Result<(), Box<dyn Error>> {
-        let sensor_command =
-            CString::new("Get_TransmittedDataInfo").expect("could not create cstring");
-        let mut counter = 0;
-
-        let param_names_repeater = iter::repeat_with(|| {
-            counter += 1;
-            CString::new(format!("IA_Scaled_Name{counter}")).expect("could not create cstring")
-        });
+    /// Get an int parameter, fails with [`MedaqError::UnsupportedByDll`] if MEDAQLib
+    /// does not export `GetParameterInt`, see [`dll_capabilities`]
+    pub fn get_parameter_int(&self, param_name: &str) -> Result<i32, MedaqError> {
+        self.core.lock()?.get_parameter_int(param_name)
+    }
 
-        let sensor_command = sensor_command.as_ptr();
-        unsafe {
-            MEDAQLIB
-                .ExecSCmd(self.sensor_handle, sensor_command)
-                .to_result()?;
-        }
+    /// Get a double parameter, e.g. `IA_Scaled_RangeMax1` or an `SA_` answer of the
+    /// last command
+    pub fn get_parameter_double(&self, param_name: &str) -> Result<f64, MedaqError> {
+        self.core.lock()?.get_parameter_double(param_name)
+    }
 
-        for param_name in param_names_repeater {
-            let param_name = param_name.as_ptr();
-            let (c_string, mut max_len) = {
-                let s = "                                                                    ";
-                let return_value = CString::new(s).expect("could not create cstring");
-                let max_len = s.len() as u32;
-                (return_value, max_len)
-            };
-            let return_value_ptr = c_string.into_raw();
-            let max_len = &mut max_len as *mut u32;
-            let return_value = unsafe {
-                if MEDAQLIB
-                    .GetParameterString(self.sensor_handle, param_name, return_value_ptr, max_len)
-                    .to_result()
-                    .is_ok()
-                {
-                    CString::from_raw(return_value_ptr)
-                } else {
-                    CString::new("").expect("could not create cstring")
-                }
-            }
-            .into_string()?;
+    /// Get a string parameter, e.g. `IA_Scaled_Name1` or an `SA_` answer of the last
+    /// command
+    pub fn get_parameter_string(&self, param_name: &str) -> Result<String, MedaqError> {
+        self.core.lock()?.get_parameter_string(param_name)
+    }
 
-            if return_value.is_empty() {
-                break;
-            }
-            self.parameters.push(return_value);
-        }
+    /// Execute a sensor command, e.g. `Set_Measrate`, with the `SP_` parameters set
+    /// before, the answers are read as `SA_` parameters afterwards
+    pub fn execute_command(&self, command: &str) -> Result<(), MedaqError> {
+        let result = (self.core.lock()).and_then(|instance| instance.exec_scmd(command));
+        self.stats.record_call(command, &result);
+        result
+    }
 
-        Ok(())
+    /// Sensor type the instance was created for, see [`SensorBuilder::new`]
+    pub fn sensor_type(&self) -> ME_SENSOR {
+        self.sensor_type
+    }
+
+    /// Description of the last error MEDAQLib reported for this sensor, `None` if
+    /// there was none
+    pub fn last_error(&self) -> Option<String> {
+        let text = self.core.lock().ok()?.get_error();
+        (!text.is_empty()).then_some(text)
     }
 
     pub fn parameters(&self) -> &[String] {
         &self.parameters
     }
 
-    fn data_available(&self) -> Result<i32, Box<dyn Error>> {
-        let mut avail = 0;
-        unsafe {
-            let avail = &mut avail as *mut i32;
-            MEDAQLIB.DataAvail(self.sensor_handle, avail).to_result()?;
-        };
-        Ok(avail)
+    /// Metadata (name, unit and scaling) of the transmitted channels
+    pub fn channel_info(&self) -> &[ChannelInfo] {
+        &self.channel_info
+    }
+
+    /// Number of values waiting in MEDAQLib, fails with
+    /// [`MedaqError::InvalidDataCount`] if `DataAvail` reports a negative count
+    fn data_available(&self) -> Result<usize, MedaqError> {
+        let available = self.core.lock()?.data_avail()?;
+        usize::try_from(available).map_err(|_| MedaqError::InvalidDataCount {
+            function: "DataAvail",
+            count: available.into(),
+        })
+    }
+
+    /// Frames [`read_into`](Self::read_into) and [`read_data`](Self::read_data)
+    /// read at most, see [`set_max_read`](Self::set_max_read)
+    pub fn max_read(&self) -> usize {
+        self.max_read
+    }
+
+    /// Read at most `frames` frames per [`read_into`](Self::read_into) and
+    /// [`read_data`](Self::read_data), `65536` by default
+    ///
+    /// The buffers are allocated for the frames available, so the cap bounds the
+    /// memory of a read also if MEDAQLib reports an absurd count. Frames beyond the
+    /// cap stay for the next read. [`read_into_slices`](Self::read_into_slices) is
+    /// capped by the given slices instead. A cap of `0` is taken as `1`.
+    pub fn set_max_read(&mut self, frames: usize) {
+        self.max_read = frames.max(1);
     }
 
     /// Read data from sensor.
@@ -225,9 +645,9 @@ impl Sensor {
     /// If no data available yet it will return `Ok(None)` otherwise `Ok(Data)`
     ///
     /// # Example
-    /// ```
+    /// ```no_run
     /// use std::time::Duration;
-    /// use medaq_lib::{Interface, SensorBuilder, ME_SENSOR};
+    /// use medaqlib::{Interface, SensorBuilder, ME_SENSOR};
     ///
     /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
     ///     .with_interface(Interface::TcpIp)
@@ -244,51 +664,143 @@ impl Sensor {
     ///     std::thread::sleep(Duration::from_millis(500));
     /// }
     /// ```
-    pub fn read_data(&self) -> Result<Option<Data>, Box<dyn Error>> {
-        let max_values = self.data_available()?;
-        if max_values == 0 {
-            return Ok(None);
+    pub fn read_data(&self) -> Result<Option<Data>, MedaqError> {
+        let mut data = Data::new(Arc::clone(&self.parameters), vec![], vec![])
+            .with_channel_info(Arc::clone(&self.channel_info));
+        match self.read_into(&mut data)? {
+            0 => Ok(None),
+            _ => Ok(Some(data)),
         }
+    }
 
-        let mut raw_data: Vec<i32> = Vec::with_capacity(max_values as usize);
-        let mut scaled_data: Vec<f64> = Vec::with_capacity(max_values as usize);
-
-        let mut read = 0;
-
-        unsafe {
-            // Ensure the vectors have allocated space by setting their length
-            raw_data.set_len(max_values as usize);
-            scaled_data.set_len(max_values as usize);
-
-            MEDAQLIB
-                .TransferData(
-                    self.sensor_handle,
-                    raw_data.as_mut_ptr(),
-                    scaled_data.as_mut_ptr(),
-                    max_values,
-                    &mut read,
-                )
-                .to_result()?;
-
-            // Adjust the lengths to the actual number of values read
-            raw_data.set_len(read as usize);
-            scaled_data.set_len(read as usize);
+    /// Read data from sensor, waiting up to `timeout` for data to arrive.
+    ///
+    /// Returns `Ok(None)` if no data arrived within the timeout. The pause between
+    /// polls adapts to the data rate, see [`Sensor::set_pacing`].
+    pub fn read_data_blocking(&self, timeout: Duration) -> Result<Option<Data>, MedaqError> {
+        let mut pacer = self.pacer.borrow_mut();
+        stop::poll(timeout, &StopToken::new(), &mut pacer, || self.read_data())
+    }
+
+    /// Read data from sensor into an existing [`Data`], reusing its buffers.
+    ///
+    /// Returns the number of frames read, `0` if no data is available yet.
+    pub fn read_into(&self, data: &mut Data) -> Result<usize, MedaqError> {
+        let _span = instrument::read(self.core.handle());
+        let _instance = self.hold()?;
+        let available = self.data_available()?;
+        let channels = self.parameters.len();
+        let cap = self.max_read.saturating_mul(channels);
+        let values = frames_to_transfer(available, channels, Some(cap))? * channels;
+
+        data.set_channels(Arc::clone(&self.parameters));
+        data.channel_info = Some(Arc::clone(&self.channel_info));
+        data.raw_data.resize(values, 0);
+        data.scaled_data.resize(values, 0.);
+
+        let result = self.transfer_with(
+            available,
+            Some(&mut data.raw_data),
+            Some(&mut data.scaled_data),
+        );
+        self.stats.record_read(&result);
+        let frames = result?;
+
+        let values = frames * self.parameters.len();
+        data.raw_data.truncate(values);
+        data.scaled_data.truncate(values);
+        data.timestamp = Some(SystemTime::now());
+        debug_assert_eq!(data.validate(), Ok(()));
+        Ok(frames)
+    }
+
+    /// Read data from sensor directly into caller-provided buffers without allocating.
+    ///
+    /// At most `min(available, slice length)` values are transferred, rounded down
+    /// to whole frames, so the length of the given slices caps the transfer. If both
+    /// slices are given the shorter one is used. Returns the number of frames written.
+    ///
+    /// Passing `None` for both buffers returns [`MedaqError::NoBuffer`].
+    pub fn read_into_slices(
+        &self,
+        raw: Option<&mut [i32]>,
+        scaled: Option<&mut [f64]>,
+    ) -> Result<usize, MedaqError> {
+        let result = (|| {
+            if raw.is_none() && scaled.is_none() {
+                return Err(MedaqError::NoBuffer);
+            }
+            let _instance = self.hold()?;
+            let available = self.data_available()?;
+            self.transfer_with(available, raw, scaled)
+        })();
+        self.stats.record_read(&result);
+        result
+    }
+
+    /// Transfer at most `available` values, as reported by the `DataAvail` of the
+    /// caller holding the instance
+    fn transfer_with(
+        &self,
+        available: usize,
+        raw: Option<&mut [i32]>,
+        scaled: Option<&mut [f64]>,
+    ) -> Result<usize, MedaqError> {
+        let capacity = match (&raw, &scaled) {
+            (None, None) => return Err(MedaqError::NoBuffer),
+            (Some(raw), Some(scaled)) => raw.len().min(scaled.len()),
+            (Some(raw), None) => raw.len(),
+            (None, Some(scaled)) => scaled.len(),
         };
-        Ok(Some(Data {
-            channels: self.parameters.clone(),
-            raw_data,
-            scaled_data,
-        }))
+
+        let channels = self.parameters.len();
+        self.stats.record_backlog(available / channels.max(1));
+        let frames = frames_to_transfer(available, channels, Some(capacity))?;
+        if frames == 0 {
+            return Ok(0);
+        }
+
+        // at most `available` values, so this does not overflow
+        let requested = frames * channels;
+        let read = (self.core.lock()?).transfer_data(raw, scaled, requested)?;
+        if read > requested {
+            return Err(MedaqError::InvalidDataCount {
+                function: "TransferData",
+                count: i64::try_from(read).unwrap_or(i64::MAX),
+            });
+        }
+        Ok(read / channels)
+    }
+}
+
+/// Number of whole frames to transfer for `available` values, capped by `capacity` values
+fn frames_to_transfer(
+    available: usize,
+    channels: usize,
+    capacity: Option<usize>,
+) -> Result<usize, MedaqError> {
+    if channels == 0 {
+        return Err(MedaqError::NoChannels);
+    }
+    let values = capacity.map_or(available, |capacity| available.min(capacity));
+    Ok(values / channels)
+}
+
+impl Sensor {
+    /// Close the connection and release the instance in MEDAQLib
+    ///
+    /// The same happens when the sensor is dropped, closing it explicitly marks the
+    /// end of a session, e.g. after a recording was finished.
+    ///
+    /// Calls of its [`SensorRef`]s fail with [`MedaqError::Closed`] afterwards.
+    pub fn close(self) {
+        self.core.release();
     }
 }
 
 impl Drop for Sensor {
     fn drop(&mut self) {
-        println!("release sensor...");
-        unsafe {
-            MEDAQLIB.CloseSensor(self.sensor_handle);
-            MEDAQLIB.ReleaseSensorInstance(self.sensor_handle);
-        }
+        self.core.release();
     }
 }
 
@@ -300,6 +812,9 @@ pub enum Interface {
     If2008Eth,
     TcpIp,
     WinUSB,
+    /// No sensor, data is generated by a [`SimulatedSensor`] unless another backend
+    /// is selected. An ip address is not required.
+    Simulated,
 }
 
 impl Display for Interface {
@@ -311,73 +826,248 @@ impl Display for Interface {
             Interface::If2008Eth => "IF2008_ETH",
             Interface::TcpIp => "TCP/IP",
             Interface::WinUSB => "WinUSB",
+            Interface::Simulated => "SIMULATED",
         };
 
         write!(f, "{s}")
     }
 }
 
-impl From<ERR_CODE> for Result<(), Box<dyn Error>> {
-    fn from(value: ERR_CODE) -> Self {
-        match value {
-            ERR_CODE::ERR_NOERROR => Ok(()),
-            err_code => Err(Box::new(err_code)),
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Data {
+    #[deprecated(note = "use `Data::channels()` instead, the field will become private")]
+    pub channels: Arc<[String]>,
+    pub raw_data: Vec<i32>,
+    pub scaled_data: Vec<f64>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    channel_info: Option<Arc<[ChannelInfo]>>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    timestamp: Option<SystemTime>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    span: Option<Duration>,
+}
+
+impl Data {
+    /// Create new Data from channel names and interleaved raw and scaled values
+    ///
+    /// Channel names are reference counted, so passing the same `Arc` for
+    /// every capture does not copy the names.
+    #[allow(deprecated)]
+    pub fn new(
+        channels: impl Into<Arc<[String]>>,
+        raw_data: Vec<i32>,
+        scaled_data: Vec<f64>,
+    ) -> Self {
+        Self {
+            channels: channels.into(),
+            raw_data,
+            scaled_data,
+            channel_info: None,
+            timestamp: None,
+            span: None,
         }
     }
-}
 
-impl ERR_CODE {
-    fn to_result(self) -> Result<(), Box<dyn Error>> {
-        self.into()
+    /// Set the time the capture was read from the sensor
+    pub fn with_timestamp(self, timestamp: SystemTime) -> Self {
+        let timestamp = Some(timestamp);
+        Self { timestamp, ..self }
     }
-}
 
-impl Error for ERR_CODE {}
+    /// Time the capture was read from the sensor, if known
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.timestamp
+    }
 
-impl Display for ERR_CODE {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+    /// Set the time covered by the capture, starting at its timestamp
+    pub fn with_span(self, span: Duration) -> Self {
+        let span = Some(span);
+        Self { span, ..self }
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct Data {
-    pub channels: Vec<String>,
-    pub raw_data: Vec<i32>,
-    pub scaled_data: Vec<f64>,
-}
+    /// Time covered by the capture, set for batches of
+    /// [`AcquisitionConfig::aggregate_every`]
+    pub fn span(&self) -> Option<Duration> {
+        self.span
+    }
 
-impl Data {
-    /// Get raw values of very first measurement
+    /// New capture with the same channels, metadata and timestamp but other values
+    pub(crate) fn with_values(&self, raw_data: Vec<i32>, scaled_data: Vec<f64>) -> Data {
+        Data {
+            raw_data,
+            scaled_data,
+            ..self.clone_metadata()
+        }
+    }
+
+    #[allow(deprecated)]
+    fn clone_metadata(&self) -> Data {
+        Data {
+            channels: Arc::clone(&self.channels),
+            raw_data: vec![],
+            scaled_data: vec![],
+            channel_info: self.channel_info.clone(),
+            timestamp: self.timestamp,
+            span: self.span,
+        }
+    }
+
+    /// Attach channel metadata, e.g. to display units along with the values
+    pub fn with_channel_info(self, channel_info: impl Into<Arc<[ChannelInfo]>>) -> Self {
+        let channel_info = Some(channel_info.into());
+        Self {
+            channel_info,
+            ..self
+        }
+    }
+
+    /// Metadata of the channels, if known
+    pub fn channel_info(&self) -> Option<&[ChannelInfo]> {
+        self.channel_info.as_deref()
+    }
+
+    /// Unit of the scaled values of the channel at `index`, if known
+    pub fn unit(&self, index: usize) -> Option<&str> {
+        self.channel_info()?.get(index)?.unit.as_deref()
+    }
+
+    /// Names of the channels contained in each measurement
+    #[allow(deprecated)]
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Shared channel names, cloning only the reference count
+    #[allow(deprecated)]
+    pub(crate) fn channels_arc(&self) -> Arc<[String]> {
+        Arc::clone(&self.channels)
+    }
+
+    #[allow(deprecated)]
+    pub(crate) fn set_channels(&mut self, channels: Arc<[String]>) {
+        self.channels = channels;
+    }
+
+    /// Number of channels per frame
+    pub fn channel_count(&self) -> usize {
+        self.channels().len()
+    }
+
+    /// Number of frames, i.e. measurements of all channels, in the capture
+    pub fn frame_count(&self) -> usize {
+        match self.channel_count() {
+            0 => 0,
+            channels => self.raw_data.len().max(self.scaled_data.len()) / channels,
+        }
+    }
+
+    /// `true` if the capture holds no frames
+    pub fn is_empty(&self) -> bool {
+        self.frame_count() == 0
+    }
+
+    /// Check that the buffers hold whole frames of equal length and channel names are unique
+    pub fn validate(&self) -> Result<(), DataLayoutError> {
+        let channels = self.channel_count();
+        let (raw, scaled) = (self.raw_data.len(), self.scaled_data.len());
+
+        if raw != 0 && scaled != 0 && raw != scaled {
+            return Err(DataLayoutError::LengthMismatch { raw, scaled });
+        }
+        let values = raw.max(scaled);
+        if channels == 0 && values != 0 {
+            return Err(DataLayoutError::NoChannels { values });
+        }
+        if channels != 0 && !values.is_multiple_of(channels) {
+            return Err(DataLayoutError::PartialFrame { values, channels });
+        }
+        for (index, name) in self.channels().iter().enumerate() {
+            if self.channels()[..index].contains(name) {
+                return Err(DataLayoutError::DuplicateChannel(name.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get raw values of very first measurement, out of range if there is none
     pub fn get_first_raw(&self) -> Vec<ChannelValue<'_, i32>> {
-        self.raw_data.get_first(&self.channels)
+        self.raw_data.get_first(self.channels())
     }
 
     /// Calculates mean of raw values for all channels
     pub fn get_mean_raw(&self) -> Vec<ChannelValue<'_, f64>> {
-        self.raw_data.means(&self.channels)
+        self.raw_data.means(self.channels())
     }
 
-    /// Get scaled values of very first measurement
+    /// Get scaled values of very first measurement, out of range if there is none
     pub fn get_first_scaled(&self) -> Vec<ChannelValue<'_, f64>> {
-        self.scaled_data.get_first(&self.channels)
+        self.with_units(self.scaled_data.get_first(self.channels()))
     }
 
     /// Calculates mean of scaled values for all channels
     pub fn get_mean_scaled(&self) -> Vec<ChannelValue<'_, f64>> {
-        self.scaled_data.means(&self.channels)
+        self.with_units(self.scaled_data.means(self.channels()))
+    }
+
+    /// Attach the units of the scaled values
+    fn with_units<'a, T>(
+        &'a self,
+        mut values: Vec<ChannelValue<'a, T>>,
+    ) -> Vec<ChannelValue<'a, T>> {
+        for (index, value) in values.iter_mut().enumerate() {
+            value.unit = self.unit(index);
+        }
+        values
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ChannelValue<'a, T> {
     pub channel: &'a str,
     pub value: Value<T>,
+    /// Unit of the value, only known for scaled values with channel metadata
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub unit: Option<&'a str>,
+}
+
+impl<T> ChannelValue<'_, T> {
+    /// Copy channel name and unit, e.g. to keep the value beyond the capture
+    pub fn into_owned(self) -> ChannelValueOwned<T> {
+        ChannelValueOwned {
+            channel: self.channel.to_string(),
+            value: self.value,
+            unit: self.unit.map(str::to_string),
+        }
+    }
+}
+
+/// [`ChannelValue`] owning channel name and unit
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChannelValueOwned<T> {
+    pub channel: String,
+    pub value: Value<T>,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub unit: Option<String>,
+}
+
+impl<T: Display> Display for ChannelValueOwned<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.value, &self.unit) {
+            (Value::Valid(_), Some(unit)) => write!(f, "{}: {} {unit}", self.channel, self.value),
+            _ => write!(f, "{}: {}", self.channel, self.value),
+        }
+    }
 }
 
 impl<T: Display> Display for ChannelValue<'_, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}", self.channel, self.value)
+        match (&self.value, self.unit) {
+            (Value::Valid(_), Some(unit)) => write!(f, "{}: {} {unit}", self.channel, self.value),
+            _ => write!(f, "{}: {}", self.channel, self.value),
+        }
     }
 }
 
@@ -393,6 +1083,7 @@ impl Display for Data {
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Value<T> {
     Valid(T),
     OutOfRange,
@@ -414,6 +1105,16 @@ impl<T: Default> Default for Value<T> {
     }
 }
 
+impl<T: Copy + Into<f64>> Value<T> {
+    /// Classify a sample as transmitted by the sensor, negative values are out of range
+    pub(crate) fn from_sample(value: T) -> Self {
+        match value {
+            v if v.into() < 0. => Value::OutOfRange,
+            v => Value::Valid(v),
+        }
+    }
+}
+
 impl<T> Value<T> {
     pub fn into_raw(self) -> Option<T> {
         match self {
@@ -458,22 +1159,27 @@ where
                 } else {
                     Value::Valid(value)
                 };
-                ChannelValue { channel, value }
+                ChannelValue {
+                    channel,
+                    value,
+                    unit: None,
+                }
             })
             .collect()
     }
 
     fn get_first(&'a self, channels: &'a [String]) -> Vec<ChannelValue<'a, T>> {
-        let values = &self[0..channels.len()];
-        values
+        // without a frame every channel is out of range
+        let values = self.get(0..channels.len()).unwrap_or_default();
+        channels
             .iter()
-            .zip(channels)
-            .map(|(&value, channel)| {
-                let value = match value {
-                    v if v.into() < 0. => Value::OutOfRange,
-                    v => Value::Valid(v),
-                };
-                ChannelValue { channel, value }
+            .enumerate()
+            .map(|(index, channel)| ChannelValue {
+                channel,
+                value: values
+                    .get(index)
+                    .map_or(Value::OutOfRange, |&value| Value::from_sample(value)),
+                unit: None,
             })
             .collect()
     }
@@ -481,30 +1187,50 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::{ChannelValue, Data};
+    use std::sync::{
+        atomic::{AtomicI32, Ordering},
+        Arc,
+    };
+
+    use crate::{
+        frames_to_transfer, Call, ChannelInfo, ChannelValue, Data, DataLayoutError, Intercept,
+        Interface, MedaqError, MockBackend, SensorBuilder, ERR_CODE, ME_SENSOR,
+    };
+
+    const EPSILON: f64 = 1e-12;
+
+    fn assert_values_approx_eq(left: &[ChannelValue<'_, f64>], right: &[ChannelValue<'_, f64>]) {
+        assert_eq!(left.len(), right.len());
+        for (left, right) in left.iter().zip(right) {
+            crate::assert_data_approx_eq!(left, right, EPSILON);
+        }
+    }
 
     #[test]
     fn test_get_first_raw_test() {
-        let data = Data {
-            channels: vec!["1".to_string(), "2".to_string(), "3".to_string()],
-            raw_data: vec![1, 2, 3, 4, 5, 6, 2, 3, 4],
-            scaled_data: vec![],
-        };
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            vec![1, 2, 3, 4, 5, 6, 2, 3, 4],
+            vec![],
+        );
         let means = data.get_first_raw();
         assert_eq!(
             means,
             vec![
                 ChannelValue {
                     channel: "1",
-                    value: crate::Value::Valid(1)
+                    value: crate::Value::Valid(1),
+                    unit: None
                 },
                 ChannelValue {
                     channel: "2",
-                    value: crate::Value::Valid(2)
+                    value: crate::Value::Valid(2),
+                    unit: None
                 },
                 ChannelValue {
                     channel: "3",
-                    value: crate::Value::Valid(3)
+                    value: crate::Value::Valid(3),
+                    unit: None
                 }
             ]
         )
@@ -512,64 +1238,70 @@ mod tests {
 
     #[test]
     fn test_get_mean_raw_test() {
-        let data = Data {
-            channels: vec!["1".to_string(), "2".to_string(), "3".to_string()],
-            raw_data: vec![1, 2, 3, 4, 5, 6, 2, 3, 4],
-            scaled_data: vec![],
-        };
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            vec![1, 2, 3, 4, 5, 6, 2, 3, 4],
+            vec![],
+        );
         let means = data.get_mean_raw();
-        assert_eq!(
-            means,
-            vec![
+        assert_values_approx_eq(
+            &means,
+            &[
                 ChannelValue {
                     channel: "1",
-                    value: crate::Value::Valid(7. / 3.)
+                    value: crate::Value::Valid(7. / 3.),
+                    unit: None,
                 },
                 ChannelValue {
                     channel: "2",
-                    value: crate::Value::Valid(10. / 3.)
+                    value: crate::Value::Valid(10. / 3.),
+                    unit: None,
                 },
                 ChannelValue {
                     channel: "3",
-                    value: crate::Value::Valid(13. / 3.)
-                }
-            ]
-        )
+                    value: crate::Value::Valid(13. / 3.),
+                    unit: None,
+                },
+            ],
+        );
     }
 
     #[test]
     fn test_get_mean_scaled_test() {
-        let data = Data {
-            channels: vec!["1".to_string(), "2".to_string(), "3".to_string()],
-            raw_data: vec![],
-            scaled_data: vec![1., 2., 3., 4., 5., 6., 2., 3., 4.],
-        };
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            vec![],
+            vec![1., 2., 3., 4., 5., 6., 2., 3., 4.],
+        );
         let means = data.get_mean_scaled();
-        assert_eq!(
-            means,
-            vec![
+        assert_values_approx_eq(
+            &means,
+            &[
                 ChannelValue {
                     channel: "1",
-                    value: crate::Value::Valid(7. / 3.)
+                    value: crate::Value::Valid(7. / 3.),
+                    unit: None,
                 },
                 ChannelValue {
                     channel: "2",
-                    value: crate::Value::Valid(10. / 3.)
+                    value: crate::Value::Valid(10. / 3.),
+                    unit: None,
                 },
                 ChannelValue {
                     channel: "3",
-                    value: crate::Value::Valid(13. / 3.)
-                }
-            ]
-        )
+                    value: crate::Value::Valid(13. / 3.),
+                    unit: None,
+                },
+            ],
+        );
     }
 
     #[test]
     fn test_get_mean_scaled_all_out_of_range_test() {
-        let data = Data {
-            channels: vec!["1".to_string(), "2".to_string(), "3".to_string()],
-            raw_data: vec![],
-            scaled_data: vec![
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            vec![],
+            vec![
                 -1.7976931348623157e308,
                 2.,
                 3.,
@@ -580,62 +1312,405 @@ mod tests {
                 3.,
                 4.,
             ],
-        };
+        );
         let means = data.get_mean_scaled();
-        assert_eq!(
-            means,
-            vec![
+        assert_values_approx_eq(
+            &means,
+            &[
                 ChannelValue {
                     channel: "1",
-                    value: crate::Value::OutOfRange
+                    value: crate::Value::OutOfRange,
+                    unit: None,
                 },
                 ChannelValue {
                     channel: "2",
-                    value: crate::Value::Valid(10. / 3.)
+                    value: crate::Value::Valid(10. / 3.),
+                    unit: None,
                 },
                 ChannelValue {
                     channel: "3",
-                    value: crate::Value::Valid(13. / 3.)
-                }
-            ]
-        )
+                    value: crate::Value::Valid(13. / 3.),
+                    unit: None,
+                },
+            ],
+        );
     }
 
     #[test]
     fn test_get_mean_scaled_some_out_of_range_test() {
-        let data = Data {
-            channels: vec!["1".to_string(), "2".to_string(), "3".to_string()],
-            raw_data: vec![],
-            scaled_data: vec![-1.7976931348623157e308, 2., 3., 1., 5., 6., 1., 3., 4.],
-        };
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            vec![],
+            vec![-1.7976931348623157e308, 2., 3., 1., 5., 6., 1., 3., 4.],
+        );
         let means = data.get_mean_scaled();
-        assert_eq!(
-            means,
-            vec![
+        assert_values_approx_eq(
+            &means,
+            &[
                 ChannelValue {
                     channel: "1",
-                    value: crate::Value::Valid(1.)
+                    value: crate::Value::Valid(1.),
+                    unit: None,
                 },
                 ChannelValue {
                     channel: "2",
-                    value: crate::Value::Valid(10. / 3.)
+                    value: crate::Value::Valid(10. / 3.),
+                    unit: None,
                 },
                 ChannelValue {
                     channel: "3",
-                    value: crate::Value::Valid(13. / 3.)
-                }
-            ]
+                    value: crate::Value::Valid(13. / 3.),
+                    unit: None,
+                },
+            ],
+        );
+    }
+
+    #[test]
+    fn test_clone_shares_channels_test() {
+        let channels: Arc<[String]> = vec!["1".to_string(), "2".to_string()].into();
+        let first = Data::new(Arc::clone(&channels), vec![1, 2], vec![1., 2.]);
+        let second = first.clone();
+
+        assert_eq!(first.channels(), ["1", "2"]);
+        assert!(std::ptr::eq(first.channels(), second.channels()));
+        assert!(std::ptr::eq(first.channels(), &channels[..]));
+    }
+
+    #[test]
+    fn test_frames_to_transfer_test() {
+        assert_eq!(frames_to_transfer(0, 3, None).unwrap(), 0);
+        assert_eq!(frames_to_transfer(2, 3, None).unwrap(), 0);
+        assert_eq!(frames_to_transfer(10, 3, None).unwrap(), 3);
+        assert_eq!(frames_to_transfer(10, 3, Some(7)).unwrap(), 2);
+        assert_eq!(frames_to_transfer(10, 3, Some(2)).unwrap(), 0);
+        assert!(matches!(
+            frames_to_transfer(10, 0, None),
+            Err(MedaqError::NoChannels)
+        ));
+    }
+
+    #[test]
+    fn test_data_available_counts_test() {
+        let available = Arc::new(AtomicI32::new(0));
+        let reported = Arc::clone(&available);
+        let mock = MockBackend::new().with_channels(["X", "Y", "Z"]);
+        mock.intercept(move |call| match call.call {
+            Call::DataAvail => Intercept::Available(reported.load(Ordering::Relaxed)),
+            _ => Intercept::Pass,
+        });
+        let mut sensor = mock.connect(ME_SENSOR::SENSOR_IFD2421);
+        let transferred = || {
+            let calls = mock.calls();
+            let transfers = calls.iter().filter(|call| call.call == Call::TransferData);
+            transfers
+                .map(|call| call.arguments[0].clone())
+                .collect::<Vec<_>>()
+        };
+        for frame in 0..4 {
+            mock.push_frame(&[frame; 3], &[frame as f64; 3]);
+        }
+
+        assert!(sensor.read_data().unwrap().is_none());
+        available.store(-1, Ordering::Relaxed);
+        let err = sensor.read_data().unwrap_err();
+        assert!(matches!(
+            err,
+            MedaqError::InvalidDataCount {
+                function: "DataAvail",
+                count: -1
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "DataAvail reported an invalid count of -1 values"
+        );
+        assert!(sensor.record_backlog_sample().is_err());
+
+        // whole frames of a count that is no multiple of the channels
+        available.store(7, Ordering::Relaxed);
+        let data = sensor.read_data().unwrap().unwrap();
+        assert_eq!(data.frame_count(), 2);
+        assert_eq!(transferred(), ["6"]);
+
+        // a huge count allocates for the cap only
+        available.store(i32::MAX, Ordering::Relaxed);
+        sensor.set_max_read(1);
+        let mut data = Data::new(Vec::<String>::new(), vec![], vec![]);
+        assert_eq!(sensor.read_into(&mut data).unwrap(), 1);
+        assert_eq!(data.raw_data, [2, 2, 2]);
+        assert!(data.raw_data.capacity() < 1024);
+        sensor.set_max_read(1024);
+        assert_eq!(sensor.read_into(&mut data).unwrap(), 1);
+        assert!(data.raw_data.capacity() <= 3 * 1024);
+        assert_eq!(transferred(), ["6", "3", "3072"]);
+        assert_eq!(sensor.stats().peak_backlog, i32::MAX as usize / 3);
+    }
+
+    #[test]
+    fn test_read_queries_data_avail_once_test() {
+        let mock = MockBackend::new().with_channels(["X", "Y"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_IFD2421);
+        let data_avail = || {
+            let calls = mock.calls();
+            calls
+                .iter()
+                .filter(|call| call.call == Call::DataAvail)
+                .count()
+        };
+        mock.push_frame(&[1, 2], &[0.1, 0.2]);
+
+        let mut data = Data::new(Vec::<String>::new(), vec![], vec![]);
+        assert_eq!(sensor.read_into(&mut data).unwrap(), 1);
+        assert_eq!(data_avail(), 1);
+        assert!(sensor.read_data().unwrap().is_none());
+        assert_eq!(data_avail(), 2);
+
+        mock.push_frame(&[3, 4], &[0.3, 0.4]);
+        let mut raw = [0; 2];
+        assert_eq!(sensor.read_into_slices(Some(&mut raw), None).unwrap(), 1);
+        assert_eq!(raw, [3, 4]);
+        assert_eq!(data_avail(), 3);
+        assert_eq!(sensor.stats().frames_read, 2);
+    }
+
+    #[test]
+    fn test_display_data_with_units_test() {
+        let data = Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            vec![1.5, -1.7976931348623157e308],
+        )
+        .with_channel_info(vec![
+            ChannelInfo::new("Distance").with_unit("mm"),
+            ChannelInfo::new("Intensity"),
+        ]);
+
+        assert_eq!(data.get_first_scaled()[0].unit, Some("mm"));
+        assert_eq!(data.to_string(), "Distance: 1.5 mm Intensity: OutOfRange");
+    }
+
+    #[test]
+    fn test_display_data_without_units_test() {
+        let data = Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            vec![1.5, 2.],
+        );
+
+        assert_eq!(data.get_first_scaled()[0].unit, None);
+        assert_eq!(data.to_string(), "Distance: 1.5 Intensity: 2");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_channel_value_units_test() {
+        let data = Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            vec![1.5, 2.],
         )
+        .with_channel_info(vec![
+            ChannelInfo::new("Distance").with_unit("mm"),
+            ChannelInfo::new("Intensity"),
+        ]);
+
+        let json = serde_json::to_string(&data.get_first_scaled()).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"channel":"Distance","value":{"Valid":1.5},"unit":"mm"},{"channel":"Intensity","value":{"Valid":2.0}}]"#
+        );
+    }
+
+    #[test]
+    fn test_frame_count_test() {
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string()],
+            vec![1, 2, 3, 4],
+            vec![1., 2., 3., 4.],
+        );
+        assert_eq!(data.channel_count(), 2);
+        assert_eq!(data.frame_count(), 2);
+        assert!(!data.is_empty());
+
+        let raw_only = Data::new(vec!["1".to_string()], vec![1, 2, 3], vec![]);
+        assert_eq!(raw_only.frame_count(), 3);
+
+        let scaled_only = Data::new(vec!["1".to_string()], vec![], vec![1.]);
+        assert_eq!(scaled_only.frame_count(), 1);
+    }
+
+    #[test]
+    fn test_frame_count_empty_test() {
+        let data = Data::new(vec!["1".to_string()], vec![], vec![]);
+        assert_eq!(data.frame_count(), 0);
+        assert!(data.is_empty());
+
+        let data = Data::new(Vec::<String>::new(), vec![], vec![]);
+        assert_eq!(data.channel_count(), 0);
+        assert_eq!(data.frame_count(), 0);
+        assert!(data.is_empty());
+        assert_eq!(data.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_test() {
+        let channels = || vec!["1".to_string(), "2".to_string()];
+        assert_eq!(
+            Data::new(channels(), vec![1, 2], vec![1., 2.]).validate(),
+            Ok(())
+        );
+        assert_eq!(
+            Data::new(channels(), vec![], vec![1., 2.]).validate(),
+            Ok(())
+        );
+        assert_eq!(Data::new(channels(), vec![1, 2], vec![]).validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_length_mismatch_test() {
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string()],
+            vec![1, 2, 3, 4],
+            vec![1., 2.],
+        );
+        assert_eq!(
+            data.validate(),
+            Err(DataLayoutError::LengthMismatch { raw: 4, scaled: 2 })
+        );
+    }
+
+    #[test]
+    fn test_validate_partial_frame_test() {
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string()],
+            vec![],
+            vec![1., 2., 3.],
+        );
+        assert_eq!(
+            data.validate(),
+            Err(DataLayoutError::PartialFrame {
+                values: 3,
+                channels: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_no_channels_test() {
+        let data = Data::new(Vec::<String>::new(), vec![1], vec![]);
+        assert_eq!(
+            data.validate(),
+            Err(DataLayoutError::NoChannels { values: 1 })
+        );
+        assert_eq!(data.frame_count(), 0);
+    }
+
+    #[test]
+    fn test_validate_duplicate_channel_test() {
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string(), "1".to_string()],
+            vec![],
+            vec![],
+        );
+        assert_eq!(
+            data.validate(),
+            Err(DataLayoutError::DuplicateChannel("1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_builder_validate_test() {
+        let builder = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421);
+        assert!(matches!(
+            builder.validate(),
+            Err(MedaqError::MissingInterface)
+        ));
+        let builder = builder.with_interface(Interface::Simulated);
+        assert!(builder.validate().is_ok());
+        assert!(matches!(
+            builder.with_ip_address("10.0.0").validate(),
+            Err(MedaqError::InvalidIpAddress(_))
+        ));
+    }
+
+    /// Messages logged on the calling thread, for tests running in parallel
+    fn logged() -> Vec<String> {
+        struct Capture;
+        impl log::Log for Capture {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+            fn log(&self, record: &log::Record) {
+                LOGGED.with_borrow_mut(|logged| logged.push(record.args().to_string()));
+            }
+            fn flush(&self) {}
+        }
+        thread_local! {
+            static LOGGED: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(vec![]) };
+        }
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&Capture).unwrap();
+            log::set_max_level(log::LevelFilter::Debug);
+        });
+        LOGGED.with_borrow_mut(std::mem::take)
+    }
+
+    #[test]
+    fn test_sensor_format_test() {
+        let builder = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+            .with_interface(Interface::TcpIp)
+            .with_ip_address("10.0.0.5")
+            .with_backend(MockBackend::new().with_channels(["Distance", "Intensity", "Peak"]));
+        assert_eq!(
+            format!("{builder:?}"),
+            "SensorBuilder { sensor_type: SENSOR_IFD2421, interface: Some(TcpIp), \
+             ip_address: Some(\"10.0.0.5\"), port: None, logging: false, custom_backend: true, \
+             trace: None }"
+        );
+
+        let sensor = builder.connect().unwrap();
+        assert_eq!(sensor.to_string(), "IFD2421 @ 10.0.0.5, 3 channels");
+        assert_eq!(
+            format!("{sensor:?}"),
+            "Sensor { sensor_type: SENSOR_IFD2421, handle: 1, interface: TcpIp, \
+             ip_address: Some(10.0.0.5), channels: [\"Distance\", \"Intensity\", \"Peak\"], \
+             open: true, .. }"
+        );
+
+        let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1420)
+            .with_interface(Interface::Simulated)
+            .connect()
+            .unwrap();
+        assert_eq!(sensor.to_string(), "ILD1420 @ SIMULATED, 1 channel");
+    }
+
+    #[test]
+    fn test_release_logged_once_test() {
+        logged();
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        mock.fail_call(Call::CloseSensor, 1, ERR_CODE::ERR_NOT_OPEN);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        sensor.close();
+        assert_eq!(
+            logged(),
+            ["released sensor 1: CloseSensor Err(Sensor(ERR_NOT_OPEN)), ReleaseSensorInstance Ok(())"]
+        );
+        let released = (mock.calls().iter())
+            .filter(|call| call.call == Call::ReleaseInstance)
+            .count();
+        assert_eq!(released, 1);
     }
 
     #[test]
     #[ignore = "manual test"]
     fn test_display_data() {
-        let data = Data {
-            channels: vec!["1".to_string(), "2".to_string(), "3".to_string()],
-            raw_data: vec![],
-            scaled_data: vec![1., 2., 3., 4., 5., 6., 2., 3., 4.],
-        };
+        let data = Data::new(
+            vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            vec![],
+            vec![1., 2., 3., 4., 5., 6., 2., 3., 4.],
+        );
         println!("{data}");
     }
 }
@@ -0,0 +1,485 @@
+use std::{
+    ffi::c_char,
+    fmt::Display,
+    hint, ptr,
+    sync::{
+        atomic::{AtomicPtr, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+#[cfg(feature = "link")]
+use crate::linked as source;
+use crate::{
+    backend::{self, Functions},
+    MedaqError, ShutdownError, ERR_CODE,
+};
+#[cfg(not(feature = "link"))]
+use crate::{bindings::MEDAQLib, loader as source};
+
+/// Oldest MEDAQLib this crate is used with
+const MINIMUM_VERSION: DllVersion = DllVersion::new(5, 0, 0, 0);
+
+/// Loaded on first use, a failed load is retried by the next use, unloaded by
+/// [`shutdown`]
+static MEDAQLIB: Retry<Library> = Retry::new();
+
+/// Signature of `GetDLLVersion`
+pub(crate) type GetDllVersion = unsafe extern "C" fn(*mut c_char, u32) -> ERR_CODE;
+
+/// MEDAQLib loaded at runtime or linked, and what was found out about it
+///
+/// Every sensor using the library holds a reference, it is unloaded once the last
+/// one is dropped after [`shutdown`].
+pub(crate) struct Library {
+    /// The functions of the DLL backend, or the names of those the library does not
+    /// export
+    pub(crate) functions: Result<Functions, Vec<&'static str>>,
+    pub(crate) version: Option<DllVersion>,
+    /// Keeps the library loaded at runtime
+    #[cfg(not(feature = "link"))]
+    pub(crate) _medaqlib: MEDAQLib,
+}
+
+/// Value set by the first initialization that succeeds, unlike a `LazyLock` a
+/// failed initialization is retried and the value can be reset
+///
+/// Getting the value once set takes no lock, the value is published as a pointer
+/// that is unpublished before a reset drops it. Attempts to initialize or reset it
+/// are serialized. Holders of the returned reference use it without any lock.
+pub(crate) struct Retry<T> {
+    value: Mutex<Option<Arc<T>>>,
+    /// The value held by `value`, null while it is unset or being reset
+    published: AtomicPtr<T>,
+    /// Gets reading `published`, a reset waits for them before dropping the value
+    readers: AtomicUsize,
+}
+
+impl<T> Retry<T> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            value: Mutex::new(None),
+            published: AtomicPtr::new(ptr::null_mut()),
+            readers: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn get_or_try_init<E>(
+        &self,
+        init: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Arc<T>, E> {
+        if let Some(value) = self.published() {
+            return Ok(value);
+        }
+        let mut value = self.value.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(value) = &*value {
+            return Ok(value.clone());
+        }
+        let value = value.insert(Arc::new(init()?));
+        self.published
+            .store(Arc::as_ptr(value).cast_mut(), Ordering::SeqCst);
+        Ok(value.clone())
+    }
+
+    /// The published value, without locking
+    fn published(&self) -> Option<Arc<T>> {
+        self.readers.fetch_add(1, Ordering::SeqCst);
+        let value = self.published.load(Ordering::SeqCst);
+        let value = (!value.is_null()).then(|| {
+            // the pointer is that of the `Arc` in `value`, which a reset only drops
+            // after unpublishing it and waiting until no get reads it anymore
+            unsafe {
+                Arc::increment_strong_count(value);
+                Arc::from_raw(value)
+            }
+        });
+        self.readers.fetch_sub(1, Ordering::SeqCst);
+        value
+    }
+
+    /// Drop the value unless references returned before are still alive, their
+    /// number otherwise, the next use initializes it again
+    pub(crate) fn reset(&self) -> Result<(), usize> {
+        let mut value = self.value.lock().unwrap_or_else(PoisonError::into_inner);
+        // gets from now on wait for the lock, those that read the pointer before
+        // have counted their reference once finished
+        self.published.store(ptr::null_mut(), Ordering::SeqCst);
+        while self.readers.load(Ordering::SeqCst) != 0 {
+            hint::spin_loop();
+        }
+        match value.as_ref().map(|kept| (kept, Arc::strong_count(kept))) {
+            Some((kept, count)) if count > 1 => {
+                self.published
+                    .store(Arc::as_ptr(kept).cast_mut(), Ordering::SeqCst);
+                Err(count - 1)
+            }
+            // dropped while holding the lock, a concurrent use loads it again after
+            _ => {
+                *value = None;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Version of MEDAQLib reported by `GetDLLVersion`, see [`dll_version`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DllVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    pub build: u32,
+}
+
+impl DllVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32, build: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            build,
+        }
+    }
+
+    /// First dotted version in the text, e.g. `5.5.0.400` of `MEDAQLib 5.5.0.400`,
+    /// missing parts are `0`
+    fn parse(text: &str) -> Option<Self> {
+        let start = text.find(|c: char| c.is_ascii_digit())?;
+        let version = text[start..]
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()?;
+        let mut parts = version.split('.').map(|part| part.parse().ok());
+        let mut next = || parts.next().flatten().unwrap_or(0);
+        Some(Self::new(next(), next(), next(), next()))
+    }
+}
+
+impl Display for DllVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            major,
+            minor,
+            patch,
+            build,
+        } = self;
+        write!(f, "{major}.{minor}.{patch}.{build}")
+    }
+}
+
+/// Optional functions of MEDAQLib and whether the loaded library exports them, see
+/// [`dll_capabilities`]
+///
+/// Older versions of MEDAQLib lack some of them, sensors work without but the
+/// methods using them fail with [`MedaqError::UnsupportedByDll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DllCapabilities {
+    /// `SetParameterDouble` of [`Sensor::set_parameter_double`](crate::Sensor::set_parameter_double)
+    pub set_parameter_double: bool,
+    /// `GetParameterInt` of [`Sensor::get_parameter_int`](crate::Sensor::get_parameter_int)
+    pub get_parameter_int: bool,
+}
+
+impl DllCapabilities {
+    fn of(functions: &Functions) -> Self {
+        Self {
+            set_parameter_double: functions.set_parameter_double.is_some(),
+            get_parameter_int: functions.get_parameter_int.is_some(),
+        }
+    }
+
+    /// Names of the optional functions the library does not export
+    pub fn unsupported(&self) -> Vec<&'static str> {
+        let [set_parameter_double, get_parameter_int] = backend::OPTIONAL_FUNCTIONS;
+        [
+            (set_parameter_double, self.set_parameter_double),
+            (get_parameter_int, self.get_parameter_int),
+        ]
+        .into_iter()
+        .filter(|&(_, supported)| !supported)
+        .map(|(function, _)| function)
+        .collect()
+    }
+}
+
+/// Whether MEDAQLib can be loaded, see
+/// [`set_library_path`](crate::set_library_path)
+///
+/// Loads the library if it is not loaded yet, after a failure the next call tries
+/// again. Without it connecting fails with [`MedaqError::LibraryUnavailable`], only
+/// [`Interface::Simulated`](crate::Interface::Simulated) and other backends of
+/// [`SensorBuilder::with_backend`](crate::SensorBuilder::with_backend) work.
+pub fn library_available() -> bool {
+    loaded().is_ok()
+}
+
+/// Version of the loaded MEDAQLib, `None` if it does not report one
+pub fn dll_version() -> Result<Option<DllVersion>, MedaqError> {
+    Ok(loaded()?.version)
+}
+
+/// Unload MEDAQLib, e.g. to replace the file while the application keeps running
+///
+/// Fails with [`ShutdownError::SensorsAlive`] while any sensor using the library is
+/// not dropped yet, the library stays loaded then. Does nothing if it is not loaded,
+/// the next sensor connected loads it again. With the `link` feature the library
+/// stays linked.
+///
+/// # Example
+/// ```no_run
+/// # fn install_update() {}
+/// medaqlib::shutdown().expect("every sensor is dropped");
+/// install_update();
+/// assert!(medaqlib::library_available());
+/// ```
+pub fn shutdown() -> Result<(), ShutdownError> {
+    MEDAQLIB.reset().map_err(ShutdownError::SensorsAlive)
+}
+
+/// Which optional functions the loaded MEDAQLib exports
+///
+/// # Example
+/// ```no_run
+/// let capabilities = medaqlib::dll_capabilities().unwrap();
+/// if !capabilities.get_parameter_int {
+///     println!("unsupported: {:?}", capabilities.unsupported());
+/// }
+/// ```
+pub fn dll_capabilities() -> Result<DllCapabilities, MedaqError> {
+    let library = get()?;
+    let functions = library.functions.as_ref();
+    Ok(DllCapabilities::of(
+        functions.expect("check fails while functions are missing"),
+    ))
+}
+
+/// MEDAQLib, loaded on first use, if it is recent enough and exports every
+/// function sensors use
+pub(crate) fn get() -> Result<Arc<Library>, MedaqError> {
+    let library = loaded()?;
+    let missing = library
+        .functions
+        .as_ref()
+        .err()
+        .map_or(&[][..], Vec::as_slice);
+    check(library.version, missing)?;
+    Ok(library)
+}
+
+/// The library, loading it unless it was loaded before
+pub(crate) fn loaded() -> Result<Arc<Library>, MedaqError> {
+    MEDAQLIB
+        .get_or_try_init(source::load)
+        .map_err(|err| MedaqError::LibraryUnavailable(err.to_string()))
+}
+
+/// Fails if the version is older than [`MINIMUM_VERSION`], unknown, or functions
+/// used by sensors are missing
+fn check(version: Option<DllVersion>, missing: &[&'static str]) -> Result<(), MedaqError> {
+    match version {
+        Some(version) if version >= MINIMUM_VERSION && missing.is_empty() => Ok(()),
+        found => Err(MedaqError::DllTooOld {
+            found,
+            required: MINIMUM_VERSION,
+            missing: missing.to_vec(),
+        }),
+    }
+}
+
+/// Query the version through `GetDLLVersion`
+pub(crate) fn version(get_dll_version: GetDllVersion) -> Option<DllVersion> {
+    let mut buffer = [0u8; 64];
+    unsafe { get_dll_version(buffer.as_mut_ptr() as *mut c_char, buffer.len() as u32) }
+        .to_result()
+        .ok()?;
+    DllVersion::parse(&String::from_utf8_lossy(backend::terminated(&buffer)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Barrier,
+        },
+        thread,
+    };
+
+    use super::{check, library_available, DllCapabilities, Retry, MINIMUM_VERSION};
+    use crate::{DllVersion, Interface, MedaqError, SensorBuilder, ME_SENSOR};
+
+    #[test]
+    fn test_dll_version_test() {
+        let parse = DllVersion::parse;
+        assert_eq!(parse("5.5.0.400"), Some(DllVersion::new(5, 5, 0, 400)));
+        assert_eq!(
+            parse("MEDAQLib 5.5.0.400 (x64)"),
+            Some(DllVersion::new(5, 5, 0, 400))
+        );
+        assert_eq!(parse("V4.12"), Some(DllVersion::new(4, 12, 0, 0)));
+        assert_eq!(parse("5..1"), Some(DllVersion::new(5, 0, 1, 0)));
+        assert_eq!(parse("no version"), None);
+        assert_eq!(parse(""), None);
+
+        assert!(DllVersion::new(5, 5, 0, 400) > DllVersion::new(5, 4, 9, 999));
+        assert!(DllVersion::new(4, 9, 0, 0) < MINIMUM_VERSION);
+        assert_eq!(DllVersion::new(5, 5, 0, 400).to_string(), "5.5.0.400");
+    }
+
+    #[test]
+    fn test_dll_too_old_test() {
+        assert!(check(Some(MINIMUM_VERSION), &[]).is_ok());
+        assert!(check(Some(DllVersion::new(5, 5, 0, 400)), &[]).is_ok());
+
+        let err = check(Some(DllVersion::new(4, 2, 0, 0)), &["TransferData"]).unwrap_err();
+        assert!(matches!(
+            &err,
+            MedaqError::DllTooOld { found: Some(found), required, missing }
+                if *found == DllVersion::new(4, 2, 0, 0)
+                    && *required == MINIMUM_VERSION
+                    && *missing == ["TransferData"]
+        ));
+        assert_eq!(
+            err.to_string(),
+            "MEDAQLib 4.2.0.0 is older than the required 5.0.0.0, missing functions: TransferData"
+        );
+
+        let err = check(None, &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "MEDAQLib of unknown version is older than the required 5.0.0.0"
+        );
+        // every function is needed, whatever version is reported
+        assert!(check(Some(MINIMUM_VERSION), &["ExecSCmd"]).is_err());
+    }
+
+    #[test]
+    fn test_library_unavailable_test() {
+        if library_available() {
+            return;
+        }
+        assert!(matches!(
+            crate::dll_capabilities(),
+            Err(MedaqError::LibraryUnavailable(_))
+        ));
+        let connect = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+            .with_interface(Interface::TcpIp)
+            .with_ip_address("127.0.0.1")
+            .connect();
+        assert!(matches!(
+            connect.err(),
+            Some(MedaqError::LibraryUnavailable(detail)) if detail.starts_with("could not load MEDAQLib")
+        ));
+        // the simulator does not need the library
+        let simulated = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+            .with_interface(Interface::Simulated)
+            .connect();
+        assert!(simulated.is_ok());
+    }
+
+    #[test]
+    fn test_dll_capabilities_test() {
+        let old = DllCapabilities {
+            set_parameter_double: false,
+            get_parameter_int: true,
+        };
+        assert_eq!(old.unsupported(), ["SetParameterDouble"]);
+        let current = DllCapabilities {
+            set_parameter_double: true,
+            ..old
+        };
+        assert!(current.unsupported().is_empty());
+    }
+
+    #[test]
+    fn test_retry_test() {
+        let retry = Retry::new();
+        assert_eq!(retry.get_or_try_init(|| Err("locked")), Err("locked"));
+        assert_eq!(
+            retry.get_or_try_init(|| Ok::<_, &str>(1)).as_deref(),
+            Ok(&1)
+        );
+        // a value once set stays, without initializing again
+        assert_eq!(
+            retry.get_or_try_init(|| Ok::<_, &str>(2)).as_deref(),
+            Ok(&1)
+        );
+        assert_eq!(retry.get_or_try_init(|| Err("unused")).as_deref(), Ok(&1));
+    }
+
+    #[test]
+    fn test_retry_reset_test() {
+        let retry = Retry::new();
+        assert_eq!(retry.reset(), Ok(()));
+        let first = retry.get_or_try_init(|| Ok::<_, ()>(1)).unwrap();
+        let second = retry.get_or_try_init(|| Ok::<_, ()>(2)).unwrap();
+        assert_eq!(retry.reset(), Err(2));
+        drop(second);
+        assert_eq!(retry.reset(), Err(1));
+        // the value stays until the last reference is gone
+        assert_eq!(retry.get_or_try_init(|| Ok::<_, ()>(3)).as_deref(), Ok(&1));
+        drop(first);
+        assert_eq!(retry.reset(), Ok(()));
+        assert_eq!(retry.get_or_try_init(|| Ok::<_, ()>(4)).as_deref(), Ok(&4));
+    }
+
+    #[test]
+    fn test_shutdown_unloaded_test() {
+        if library_available() {
+            return;
+        }
+        // nothing is loaded, sensors of other backends do not hold the library
+        let _simulated = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+            .with_interface(Interface::Simulated)
+            .connect()
+            .unwrap();
+        assert_eq!(crate::shutdown(), Ok(()));
+    }
+
+    #[test]
+    fn test_retry_initializes_once_test() {
+        let retry = Retry::new();
+        let barrier = Barrier::new(4);
+        let attempts = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    barrier.wait();
+                    let value = retry.get_or_try_init(|| {
+                        attempts.fetch_add(1, Ordering::SeqCst);
+                        Ok::<_, ()>(7)
+                    });
+                    assert_eq!(value.as_deref(), Ok(&7));
+                });
+            }
+        });
+        assert_eq!(attempts.into_inner(), 1);
+    }
+
+    #[test]
+    fn test_retry_reset_while_getting_test() {
+        let retry = Retry::new();
+        let inits = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..3 {
+                scope.spawn(|| {
+                    for _ in 0..10_000 {
+                        let value = retry.get_or_try_init(|| {
+                            Ok::<_, ()>(vec![inits.fetch_add(1, Ordering::SeqCst)])
+                        });
+                        // a reset never drops a value a get returned
+                        assert_eq!(value.unwrap().len(), 1);
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for _ in 0..10_000 {
+                    let _ = retry.reset();
+                }
+            });
+        });
+        // no reference is left once the gets are done
+        assert_eq!(retry.reset(), Ok(()));
+        assert!(inits.into_inner() >= 1);
+    }
+}
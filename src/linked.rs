@@ -0,0 +1,103 @@
+use std::{convert::Infallible, ffi::c_char};
+
+use crate::{
+    backend::Functions,
+    library::{self, Library},
+    ERR_CODE, ME_SENSOR,
+};
+
+/// Never fails, the loader of the operating system fails at start instead
+pub(crate) type LoadError = Infallible;
+
+// The signatures are those of the generated bindings, `Functions` only accepts
+// functions of the same types.
+#[link(name = "MEDAQLib")]
+#[allow(non_snake_case)]
+extern "C" {
+    fn CreateSensorInstance(sensorType: ME_SENSOR) -> u32;
+    fn ReleaseSensorInstance(instanceHandle: u32) -> ERR_CODE;
+    fn OpenSensor(instanceHandle: u32) -> ERR_CODE;
+    fn CloseSensor(instanceHandle: u32) -> ERR_CODE;
+    fn SetParameterString(
+        instanceHandle: u32,
+        paramName: *const c_char,
+        paramValue: *const c_char,
+    ) -> ERR_CODE;
+    fn SetParameterInt(instanceHandle: u32, paramName: *const c_char, paramValue: i32) -> ERR_CODE;
+    fn GetParameterString(
+        instanceHandle: u32,
+        paramName: *const c_char,
+        paramValue: *mut c_char,
+        maxLen: *mut u32,
+    ) -> ERR_CODE;
+    fn GetParameterDouble(
+        instanceHandle: u32,
+        paramName: *const c_char,
+        paramValue: *mut f64,
+    ) -> ERR_CODE;
+    fn ExecSCmd(instanceHandle: u32, sensorCommand: *const c_char) -> ERR_CODE;
+    fn GetError(instanceHandle: u32, errText: *mut c_char, maxLen: u32) -> ERR_CODE;
+    fn DataAvail(instanceHandle: u32, avail: *mut i32) -> ERR_CODE;
+    fn TransferData(
+        instanceHandle: u32,
+        rawData: *mut i32,
+        scaledData: *mut f64,
+        maxValues: i32,
+        read: *mut i32,
+    ) -> ERR_CODE;
+    fn GetDLLVersion(versionStr: *mut c_char, maxLen: u32) -> ERR_CODE;
+    // optional when loading at runtime, the import library has to export them
+    fn SetParameterDouble(
+        instanceHandle: u32,
+        paramName: *const c_char,
+        paramValue: f64,
+    ) -> ERR_CODE;
+    fn GetParameterInt(
+        instanceHandle: u32,
+        paramName: *const c_char,
+        paramValue: *mut i32,
+    ) -> ERR_CODE;
+}
+
+/// MEDAQLib linked at build time
+pub(crate) fn load() -> Result<Library, LoadError> {
+    let functions = Functions {
+        create_sensor_instance: CreateSensorInstance,
+        release_sensor_instance: ReleaseSensorInstance,
+        open_sensor: OpenSensor,
+        close_sensor: CloseSensor,
+        set_parameter_string: SetParameterString,
+        set_parameter_int: SetParameterInt,
+        get_parameter_string: GetParameterString,
+        get_parameter_double: GetParameterDouble,
+        exec_scmd: ExecSCmd,
+        get_error: GetError,
+        data_avail: DataAvail,
+        transfer_data: TransferData,
+        set_parameter_double: Some(SetParameterDouble),
+        get_parameter_int: Some(GetParameterInt),
+    };
+    Ok(Library {
+        functions: Ok(functions),
+        version: library::version(GetDLLVersion),
+    })
+}
+
+/// Bound of [`set_library_path`] and [`init_with_path`] that no type fulfills with the `link` feature
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "loading MEDAQLib from a path is not available with the `link` feature",
+    label = "MEDAQLib is linked at build time",
+    note = "set MEDAQLIB_LIB_DIR when building, or disable the `link` feature to load MEDAQLib at runtime"
+)]
+pub trait RuntimeLoading {}
+
+/// Calling it does not compile with the `link` feature
+#[doc(hidden)]
+pub fn set_library_path<P: RuntimeLoading>(_: P) {}
+
+/// Calling it does not compile with the `link` feature
+#[doc(hidden)]
+pub fn init_with_path<P: RuntimeLoading>(_: P) -> Result<(), crate::MedaqError> {
+    Ok(())
+}
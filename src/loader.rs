@@ -0,0 +1,462 @@
+use std::{
+    env,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{
+    backend::Functions,
+    bindings::MEDAQLib,
+    library::{self, Library},
+    MedaqError,
+};
+
+/// File name of MEDAQLib
+#[cfg(windows)]
+const LIBRARY_NAME: &str = "MEDAQLib.dll";
+/// File name of MEDAQLib
+#[cfg(not(windows))]
+const LIBRARY_NAME: &str = "libMEDAQLib.so";
+/// Subdirectory of the installation with the library for the architecture of the
+/// program
+const ARCH_DIR: &str = if cfg!(target_pointer_width = "64") {
+    "x64"
+} else {
+    "x86"
+};
+
+/// Directory the library is searched in first, also read at build time
+const LIB_DIR: &str = "MEDAQLIB_LIB_DIR";
+/// `MEDAQLIB_LIB_DIR` when the crate was built, made absolute
+const BUILD_LIB_DIR: Option<&str> = option_env!("MEDAQLIB_BUILD_LIB_DIR");
+
+static LIBRARY_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Load MEDAQLib from `path` instead of searching for it
+///
+/// The library is loaded once per process when the first sensor connects through
+/// it, see [`init_with_path`] to load it right away. A call after the library was
+/// loaded has no effect, after a failed load the next use loads from the new path.
+/// Without a path MEDAQLib, `MEDAQLib.dll` on
+/// Windows and `libMEDAQLib.so` elsewhere, is searched in order
+/// 1. in the directory of the `MEDAQLIB_LIB_DIR` environment variable
+/// 2. in the directory `MEDAQLIB_LIB_DIR` named when the crate was built, e.g. for
+///    `cargo run`
+/// 3. next to the executable
+/// 4. by its name in the search path of the system
+/// 5. in the installation directories of Micro-Epsilon on Windows, for the
+///    architecture of the program
+///
+/// # Example
+/// ```no_run
+/// medaqlib::set_library_path(r"C:\MEDAQLib\x64\MEDAQLib.dll");
+/// ```
+pub fn set_library_path(path: impl Into<PathBuf>) {
+    *LIBRARY_PATH.lock().unwrap_or_else(PoisonError::into_inner) = Some(path.into());
+}
+
+/// Load MEDAQLib from `path` now, see [`set_library_path`]
+///
+/// Unlike the load on first use, a failure is returned, e.g. while an installer
+/// still holds the file. Calling it again retries, once it succeeded every sensor
+/// uses the library and later calls return `Ok` without loading again, until
+/// [`shutdown`](crate::shutdown).
+///
+/// # Example
+/// ```no_run
+/// use std::{thread, time::Duration};
+///
+/// while let Err(err) = medaqlib::init_with_path(r"C:\MEDAQLib\x64\MEDAQLib.dll") {
+///     eprintln!("{err}, retrying");
+///     thread::sleep(Duration::from_secs(1));
+/// }
+/// ```
+pub fn init_with_path(path: impl Into<PathBuf>) -> Result<(), MedaqError> {
+    set_library_path(path);
+    library::loaded().map(|_| ())
+}
+
+/// Load MEDAQLib from the first candidate that loads
+pub(crate) fn load() -> Result<Library, LoadError> {
+    let path = LIBRARY_PATH
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+    load_from(candidates(path, search_dirs())).map(library)
+}
+
+/// What is known about the loaded library
+fn library(medaqlib: MEDAQLib) -> Library {
+    let version = medaqlib
+        .GetDLLVersion
+        .as_ref()
+        .ok()
+        .and_then(|&get_dll_version| library::version(get_dll_version));
+    Library {
+        functions: functions(&medaqlib),
+        version,
+        _medaqlib: medaqlib,
+    }
+}
+
+/// The functions of the DLL backend, the names of those the library does not export
+/// if any but the optional ones is missing
+fn functions(medaqlib: &MEDAQLib) -> Result<Functions, Vec<&'static str>> {
+    macro_rules! functions {
+        (
+            $($field:ident: $symbol:ident),* $(,)?;
+            optional: $($optional:ident: $optional_symbol:ident),* $(,)?
+        ) => {
+            match ($(&medaqlib.$symbol,)*) {
+                ($(Ok($field),)*) => Ok(Functions {
+                    $($field: *$field,)*
+                    $($optional: medaqlib.$optional_symbol.as_ref().ok().copied(),)*
+                }),
+                _ => Err([$((stringify!($symbol), medaqlib.$symbol.is_ok())),*]
+                    .into_iter()
+                    .filter(|&(_, exported)| !exported)
+                    .map(|(name, _)| name)
+                    .collect()),
+            }
+        };
+    }
+    functions!(
+        create_sensor_instance: CreateSensorInstance,
+        release_sensor_instance: ReleaseSensorInstance,
+        open_sensor: OpenSensor,
+        close_sensor: CloseSensor,
+        set_parameter_string: SetParameterString,
+        set_parameter_int: SetParameterInt,
+        get_parameter_string: GetParameterString,
+        get_parameter_double: GetParameterDouble,
+        exec_scmd: ExecSCmd,
+        get_error: GetError,
+        data_avail: DataAvail,
+        transfer_data: TransferData;
+        optional:
+        set_parameter_double: SetParameterDouble,
+        get_parameter_int: GetParameterInt,
+    )
+}
+
+/// Directories searched before the search path of the system, see
+/// [`set_library_path`]
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = env::var_os(LIB_DIR)
+        .map(PathBuf::from)
+        .into_iter()
+        .collect();
+    dirs.extend(BUILD_LIB_DIR.map(PathBuf::from));
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+    dirs.extend(exe_dir);
+    dirs
+}
+
+/// Paths to load MEDAQLib from in order, only `path` if it is set
+fn candidates(path: Option<PathBuf>, dirs: Vec<PathBuf>) -> Vec<PathBuf> {
+    if let Some(path) = path {
+        return vec![path];
+    }
+    let mut candidates: Vec<_> = dirs.iter().map(|dir| dir.join(LIBRARY_NAME)).collect();
+    candidates.push(PathBuf::from(LIBRARY_NAME));
+    if cfg!(windows) {
+        for program_files in ["ProgramFiles", "ProgramFiles(x86)"] {
+            if let Some(dir) = env::var_os(program_files) {
+                let install = Path::new(&dir).join("MICRO-EPSILON").join("MEDAQLib");
+                candidates.push(install.join(ARCH_DIR).join(LIBRARY_NAME));
+                candidates.push(install.join(LIBRARY_NAME));
+            }
+        }
+    }
+    candidates
+}
+
+fn load_from(candidates: Vec<PathBuf>) -> Result<MEDAQLib, LoadError> {
+    let mut attempts = vec![];
+    for path in candidates {
+        match unsafe { MEDAQLib::new(&path) } {
+            Ok(library) => return Ok(library),
+            Err(err) => {
+                let bits = fs::read(&path).ok().and_then(|file| library_bits(&file));
+                attempts.push(Attempt {
+                    path,
+                    error: err.to_string(),
+                    bits: bits.filter(|&bits| bits != usize::BITS),
+                });
+            }
+        }
+    }
+    Err(LoadError { attempts })
+}
+
+/// Bits of the architecture a library was built for, from the class of an ELF
+/// header or the machine field of a PE header
+fn library_bits(file: &[u8]) -> Option<u32> {
+    if file.starts_with(b"\x7fELF") {
+        return match file.get(4)? {
+            1 => Some(32),
+            2 => Some(64),
+            _ => None,
+        };
+    }
+    let offset = u32::from_le_bytes(file.get(0x3c..0x40)?.try_into().ok()?) as usize;
+    if file.get(offset..offset + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let machine = u16::from_le_bytes(file.get(offset + 4..offset + 6)?.try_into().ok()?);
+    match machine {
+        0x014c => Some(32),
+        0x8664 | 0xaa64 => Some(64),
+        _ => None,
+    }
+}
+
+/// MEDAQLib could not be loaded from any candidate
+#[derive(Debug)]
+pub(crate) struct LoadError {
+    attempts: Vec<Attempt>,
+}
+
+#[derive(Debug)]
+struct Attempt {
+    path: PathBuf,
+    error: String,
+    /// Bits of the library if they differ from the program
+    bits: Option<u32>,
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "could not load MEDAQLib")?;
+        for attempt in &self.attempts {
+            write!(f, "\n  {}: {}", attempt.path.display(), attempt.error)?;
+            if let Some(bits) = attempt.bits {
+                write!(
+                    f,
+                    " (the library is {bits} bit but the program is {} bit)",
+                    usize::BITS
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::{candidates, library_bits, load_from, search_dirs, BUILD_LIB_DIR, LIBRARY_NAME};
+
+    /// Start of a Windows library whose PE header names the machine
+    fn pe_file(machine: u16) -> Vec<u8> {
+        let mut file = vec![0; 0x80];
+        file[..2].copy_from_slice(b"MZ");
+        file[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        file[0x40..0x44].copy_from_slice(b"PE\0\0");
+        file[0x44..0x46].copy_from_slice(&machine.to_le_bytes());
+        file
+    }
+
+    #[test]
+    fn test_candidates_test() {
+        let path = PathBuf::from("vendor/MEDAQLib.dll");
+        let dirs = vec![PathBuf::from("lib")];
+        assert_eq!(candidates(Some(path.clone()), dirs.clone()), [path]);
+
+        let searched = candidates(None, dirs);
+        let name = if cfg!(windows) {
+            "MEDAQLib.dll"
+        } else {
+            "libMEDAQLib.so"
+        };
+        assert_eq!(LIBRARY_NAME, name);
+        assert_eq!(searched[0], PathBuf::from("lib").join(LIBRARY_NAME));
+        assert_eq!(searched[1], PathBuf::from(LIBRARY_NAME));
+        assert!(searched.iter().all(|path| path.ends_with(LIBRARY_NAME)));
+        if cfg!(not(windows)) {
+            assert_eq!(searched.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_search_dirs_test() {
+        let dirs = search_dirs();
+        let exe = env::current_exe().unwrap();
+        assert_eq!(dirs.last().map(PathBuf::as_path), exe.parent());
+        if let Some(dir) = BUILD_LIB_DIR {
+            assert!(dirs.contains(&PathBuf::from(dir)));
+        }
+    }
+
+    #[test]
+    fn test_load_from_search_dir_test() {
+        // a dummy library in a search directory is tried before the system search path
+        let dir = env::temp_dir().join(format!("medaqlib-search-dir-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dummy = dir.join(LIBRARY_NAME);
+        fs::write(&dummy, b"not a library").unwrap();
+
+        let err = load_from(candidates(None, vec![dir.clone()]))
+            .err()
+            .unwrap()
+            .to_string();
+        let lines: Vec<_> = err.lines().collect();
+        assert!(
+            lines[1].starts_with(&format!("  {}: ", dummy.display())),
+            "{err}"
+        );
+        assert!(
+            lines[2].starts_with(&format!("  {LIBRARY_NAME}: ")),
+            "{err}"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_library_bits_test() {
+        assert_eq!(library_bits(&pe_file(0x014c)), Some(32));
+        assert_eq!(library_bits(&pe_file(0x8664)), Some(64));
+        assert_eq!(library_bits(&pe_file(0x0200)), None);
+        assert_eq!(library_bits(b"MZ"), None);
+        assert_eq!(library_bits(&pe_file(0x8664)[..0x44]), None);
+
+        let mut file = pe_file(0x8664);
+        file[0x3c..0x40].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(library_bits(&file), None);
+
+        assert_eq!(library_bits(b"\x7fELF\x01\x01"), Some(32));
+        assert_eq!(library_bits(b"\x7fELF\x02\x01"), Some(64));
+        assert_eq!(library_bits(b"\x7fELF"), None);
+    }
+
+    #[test]
+    fn test_library_bits_of_test_binary_test() {
+        // the running test binary is built for the architecture of the program
+        let file = fs::read(env::current_exe().unwrap()).unwrap();
+        assert_eq!(library_bits(&file), Some(usize::BITS));
+    }
+
+    #[test]
+    fn test_load_error_lists_attempts_test() {
+        let dir = env::temp_dir().join(format!("medaqlib-loader-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let other_bits = if usize::BITS == 64 { 0x014c } else { 0x8664 };
+        let wrong = dir.join("wrong.dll");
+        fs::write(&wrong, pe_file(other_bits)).unwrap();
+        let missing = dir.join("missing.dll");
+
+        let err = load_from(vec![missing.clone(), wrong.clone()])
+            .err()
+            .unwrap()
+            .to_string();
+        let lines: Vec<_> = err.lines().collect();
+        assert_eq!(lines[0], "could not load MEDAQLib");
+        assert!(lines[1].starts_with(&format!("  {}: ", missing.display())));
+        assert!(!lines[1].contains("bit"), "{err}");
+        assert!(lines[2].starts_with(&format!("  {}: ", wrong.display())));
+        let bits = if usize::BITS == 64 { 32 } else { 64 };
+        assert!(
+            lines[2].ends_with(&format!(
+                " (the library is {bits} bit but the program is {} bit)",
+                usize::BITS
+            )),
+            "{err}"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Compile a library exporting only `GetDLLVersion` to `path`
+    #[cfg(unix)]
+    fn compile_dummy(path: &std::path::Path) {
+        let source = path.with_extension("c");
+        fs::write(
+            &source,
+            "#include <string.h>\n\
+             int GetDLLVersion(char *version, unsigned max_len) {\n\
+             strncpy(version, \"5.5.0.400\", max_len);\n\
+             return 0;\n\
+             }\n",
+        )
+        .unwrap();
+        let target = env!("MEDAQLIB_TARGET");
+        let status = cc::Build::new()
+            .target(target)
+            .host(target)
+            .opt_level(0)
+            .cargo_metadata(false)
+            .get_compiler()
+            .to_command()
+            .args(["-shared", "-fPIC", "-o"])
+            .arg(path)
+            .arg(&source)
+            .status()
+            .unwrap();
+        assert!(status.success(), "compiling the dummy library failed");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_after_failure_test() {
+        use super::library;
+        use crate::{library::Retry, DllVersion};
+
+        let dir = env::temp_dir().join(format!("medaqlib-retry-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LIBRARY_NAME);
+        let retry = Retry::new();
+        let load = || load_from(candidates(Some(path.clone()), vec![])).map(library);
+
+        // not installed yet, then only partly written
+        assert!(retry.get_or_try_init(load).is_err());
+        fs::write(&path, b"\x7fELF").unwrap();
+        assert!(retry.get_or_try_init(load).is_err());
+
+        fs::remove_file(&path).unwrap();
+        compile_dummy(&path);
+        let loaded = retry.get_or_try_init(load).unwrap();
+        assert_eq!(loaded.version, Some(DllVersion::new(5, 5, 0, 400)));
+        assert_eq!(loaded.functions.as_ref().err().map(Vec::len), Some(12));
+
+        // the loaded library stays, also if the file is gone
+        fs::remove_dir_all(&dir).unwrap();
+        let again = retry.get_or_try_init(load).unwrap();
+        assert!(std::sync::Arc::ptr_eq(&loaded, &again));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_unload_test() {
+        use super::library;
+        use crate::library::Retry;
+
+        let dir = env::temp_dir().join(format!("medaqlib-unload-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(LIBRARY_NAME);
+        compile_dummy(&path);
+        let mapped = || {
+            let maps = fs::read_to_string("/proc/self/maps").unwrap();
+            maps.contains(path.to_str().unwrap())
+        };
+        let retry = Retry::new();
+        let load = || load_from(candidates(Some(path.clone()), vec![])).map(library);
+
+        let loaded = retry.get_or_try_init(load).unwrap();
+        assert!(mapped());
+        // a sensor still holds the library
+        assert_eq!(retry.reset(), Err(1));
+        assert!(mapped());
+        drop(loaded);
+        assert_eq!(retry.reset(), Ok(()));
+        assert!(!mapped());
+
+        // loaded again on the next use
+        let _loaded = retry.get_or_try_init(load).unwrap();
+        assert!(mapped());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
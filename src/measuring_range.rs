@@ -0,0 +1,175 @@
+use std::ops::Range;
+
+use crate::{MedaqError, Sensor};
+
+/// Factor from `unit` to mm, `None` for units that are no length, distances
+/// without a unit are in mm
+pub(crate) fn millimetres(unit: Option<&str>) -> Option<f64> {
+    match unit.map(str::trim) {
+        None | Some("mm") => Some(1.),
+        Some("µm" | "μm" | "um") => Some(1e-3),
+        Some("nm") => Some(1e-6),
+        Some("m") => Some(1e3),
+        Some(_) => None,
+    }
+}
+
+impl Sensor {
+    /// Limit the measuring range to `start` to `end` in mm with
+    /// `Set_MeasuringRangeLimits`, e.g. to ignore reflections beyond the target
+    ///
+    /// Fails with [`MedaqError::InvalidSetting`] before calling MEDAQLib if the
+    /// range is empty or beyond the full scale of the sensor, see
+    /// [`Capabilities::full_scale`](crate::Capabilities::full_scale), and with
+    /// [`MedaqError::Rejected`] with the error text of the sensor if it refuses
+    /// the range. The scaling of the channels is read again afterwards, see
+    /// [`refresh_channels`](Self::refresh_channels).
+    ///
+    /// # Example
+    /// ```no_run
+    /// use medaqlib::{Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let mut sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1900)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    /// sensor.set_measuring_range(2., 8.).unwrap();
+    /// assert_eq!(sensor.measuring_range().unwrap(), 2.0..8.);
+    /// ```
+    pub fn set_measuring_range(&mut self, start: f64, end: f64) -> Result<(), MedaqError> {
+        let setting = format!("measuring range {start} mm to {end} mm");
+        if !(start.is_finite() && end.is_finite() && start < end) {
+            return Err(MedaqError::InvalidSetting {
+                setting: "measuring range".into(),
+                reason: format!("{start} mm to {end} mm is no range"),
+            });
+        }
+        if let Some(full_scale) = &self.capabilities().full_scale {
+            if start < full_scale.start || end > full_scale.end {
+                return Err(MedaqError::InvalidSetting {
+                    setting: "measuring range".into(),
+                    reason: format!(
+                        "{start} mm to {end} mm is beyond the full scale of {} mm to {} mm",
+                        full_scale.start, full_scale.end
+                    ),
+                });
+            }
+        }
+
+        let _instance = self.hold()?;
+        self.set_parameter_double("SP_MeasuringRangeMin", start)?;
+        self.set_parameter_double("SP_MeasuringRangeMax", end)?;
+        match self.execute_command("Set_MeasuringRangeLimits") {
+            Err(MedaqError::Sensor(err_code)) => {
+                return Err(match self.last_error() {
+                    Some(text) => MedaqError::Rejected {
+                        setting,
+                        reported: format!("{text:?}"),
+                    },
+                    None => MedaqError::Sensor(err_code),
+                })
+            }
+            result => result?,
+        }
+        // the scaled minimum and maximum of the channels follow the range
+        self.refresh_channels()
+    }
+
+    /// The measuring range in mm as reported by `Get_MeasuringRangeLimits`, see
+    /// [`set_measuring_range`](Self::set_measuring_range)
+    pub fn measuring_range(&self) -> Result<Range<f64>, MedaqError> {
+        let _instance = self.hold()?;
+        self.execute_command("Get_MeasuringRangeLimits")?;
+        Ok(self.answer("SA_MeasuringRangeMin")?..self.answer("SA_MeasuringRangeMax")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::millimetres;
+    use crate::{Call, Intercept, MedaqError, MockBackend, Sensor, ERR_CODE, ME_SENSOR};
+
+    /// Sensor with a full scale of 0 mm to 50 mm, reported in µm
+    fn connect(mock: &MockBackend) -> Sensor {
+        let mock = mock
+            .clone()
+            .with_channels(["Distance"])
+            .with_parameter_string("IA_Scaled_Unit1", "µm")
+            .with_parameter_double("IA_Raw_RangeMin1", 0.)
+            .with_parameter_double("IA_Raw_RangeMax1", 1000.)
+            .with_parameter_double("IA_Scaled_RangeMin1", 0.)
+            .with_parameter_double("IA_Scaled_RangeMax1", 50000.);
+        mock.connect(ME_SENSOR::SENSOR_ILD1900)
+    }
+
+    #[test]
+    fn test_millimetres_test() {
+        assert_eq!(millimetres(None), Some(1.));
+        assert_eq!(millimetres(Some("µm")), Some(1e-3));
+        assert_eq!(millimetres(Some("m")), Some(1e3));
+        assert_eq!(millimetres(Some("%")), None);
+    }
+
+    #[test]
+    fn test_measuring_range_test() {
+        let mock = MockBackend::new()
+            .with_parameter_string("SA_MeasuringRangeMin", "10")
+            .with_parameter_string("SA_MeasuringRangeMax", "40");
+        let mut sensor = connect(&mock);
+        assert_eq!(sensor.capabilities().full_scale, Some(0.0..50.));
+
+        // the sensor scales the narrowed range
+        let mock = mock.with_parameter_double("IA_Scaled_RangeMax1", 40000.);
+        sensor.set_measuring_range(10., 40.).unwrap();
+        let calls = mock.calls();
+        let settings: Vec<_> = (calls.iter())
+            .filter(|call| matches!(call.call, Call::SetParameterDouble | Call::ExecSCmd))
+            .map(|call| call.arguments.join("="))
+            .collect();
+        assert_eq!(
+            settings[1..],
+            [
+                "SP_MeasuringRangeMin=10",
+                "SP_MeasuringRangeMax=40",
+                "Set_MeasuringRangeLimits",
+                "Get_TransmittedDataInfo"
+            ]
+        );
+        assert_eq!(sensor.channel_info()[0].range, Some(0.0..40000.));
+        assert_eq!(sensor.capabilities().full_scale, Some(0.0..50.));
+        assert_eq!(sensor.measuring_range().unwrap(), 10.0..40.);
+    }
+
+    #[test]
+    fn test_measuring_range_rejected_test() {
+        let mock = MockBackend::new();
+        let mut sensor = connect(&mock);
+        let connected = mock.calls().len();
+
+        let err = sensor.set_measuring_range(10., 60.).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid measuring range: 10 mm to 60 mm is beyond the full scale of 0 mm to 50 mm"
+        );
+        assert!(matches!(
+            sensor.set_measuring_range(40., 10.),
+            Err(MedaqError::InvalidSetting { .. })
+        ));
+        assert_eq!(mock.calls().len(), connected);
+
+        mock.intercept(|call| match call.call {
+            Call::ExecSCmd if call.arguments[0] == "Set_MeasuringRangeLimits" => {
+                Intercept::Fail(ERR_CODE::ERR_SENSOR_ANSWER_ERROR)
+            }
+            Call::GetError => Intercept::String("Range limits exceed the sensor".into()),
+            _ => Intercept::Pass,
+        });
+        let err = sensor.set_measuring_range(0.5, 49.5).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the sensor reported \"Range limits exceed the sensor\" after setting the \
+             measuring range 0.5 mm to 49.5 mm"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    sync::{Shared, StopFlag},
+    Value, Watch,
+};
+
+/// Interval the server checks for new requests and the stop request
+const ACCEPT_POLL: Duration = Duration::from_millis(10);
+/// Time a client gets to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Name, type and help of the metrics, in the order they are rendered
+const FAMILIES: [(&str, &str, &str); 7] = [
+    ("latest_value", "gauge", "Last scaled value per channel"),
+    (
+        "frames_received_total",
+        "counter",
+        "Frames read from the sensor",
+    ),
+    ("read_errors_total", "counter", "Reads that failed"),
+    (
+        "invalid_sample_ratio",
+        "gauge",
+        "Share of out of range samples",
+    ),
+    ("backlog_frames", "gauge", "Frames waiting in the driver"),
+    (
+        "reconnects_total",
+        "counter",
+        "Times the sensor was registered again",
+    ),
+    ("seconds_since_last_frame", "gauge", "Age of the last frame"),
+];
+
+/// Sensor registered with a [`PrometheusExporter`]
+struct Entry {
+    watch: Watch,
+    reconnects: u64,
+    /// Frames of the watches replaced so far, keeps the total counting up
+    frames_before: u64,
+}
+
+type Sensors = Shared<BTreeMap<String, Entry>>;
+
+/// Serves the health of watched sensors as Prometheus metrics on `/metrics`
+///
+/// Every sensor is registered as a [`Watch`] under a name, which becomes the
+/// `sensor` label. The metrics are taken from [`Watch::stats`], [`Watch::latest`]
+/// and friends when the endpoint is scraped:
+///
+/// - `medaq_latest_value{sensor,channel,unit}`, `NaN` out of range
+/// - `medaq_frames_received_total{sensor}`
+/// - `medaq_read_errors_total{sensor}`
+/// - `medaq_invalid_sample_ratio{sensor}`
+/// - `medaq_backlog_frames{sensor}`
+/// - `medaq_reconnects_total{sensor}`, counting registrations replacing a watch
+/// - `medaq_seconds_since_last_frame{sensor}`, missing before the first frame
+///
+/// # Example
+/// ```no_run
+/// use medaqlib::{AcquisitionConfig, Interface, PrometheusExporter, SensorBuilder, ME_SENSOR};
+///
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::TcpIp)
+///     .with_ip_address("10.10.10.10")
+///     .connect()
+///     .unwrap();
+///
+/// let exporter = PrometheusExporter::bind("0.0.0.0:9184").unwrap();
+/// exporter.register("thickness", sensor.watch_latest(AcquisitionConfig::default()));
+/// std::thread::sleep(std::time::Duration::from_secs(3600));
+/// let sensor = exporter.unregister("thickness").unwrap().join();
+/// ```
+pub struct PrometheusExporter {
+    sensors: Sensors,
+    local_addr: SocketAddr,
+    stop: StopFlag,
+    server: Option<JoinHandle<()>>,
+}
+
+impl PrometheusExporter {
+    /// Serve on `addr`, e.g. `0.0.0.0:9184` or `127.0.0.1:0` for any free port
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let sensors = Sensors::default();
+        let stop = StopFlag::default();
+        let server = {
+            let (sensors, stop) = (sensors.clone(), stop.clone());
+            thread::Builder::new()
+                .name("medaq-metrics".into())
+                .spawn(move || serve(&listener, &sensors, &stop))?
+        };
+        Ok(Self {
+            sensors,
+            local_addr,
+            stop,
+            server: Some(server),
+        })
+    }
+
+    /// Address of the endpoint, with the port chosen for port `0`
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Export the sensor behind `watch` as `name`
+    ///
+    /// Registering a watch under a name already in use counts as a reconnect of that
+    /// sensor, the replaced watch is returned.
+    pub fn register(&self, name: &str, watch: Watch) -> Option<Watch> {
+        let mut sensors = self.sensors.lock();
+        match sensors.remove(name) {
+            Some(replaced) => {
+                let entry = Entry {
+                    watch,
+                    reconnects: replaced.reconnects + 1,
+                    frames_before: replaced.frames_before + replaced.watch.stats().frames_read,
+                };
+                sensors.insert(name.to_string(), entry);
+                Some(replaced.watch)
+            }
+            None => {
+                let entry = Entry {
+                    watch,
+                    reconnects: 0,
+                    frames_before: 0,
+                };
+                sensors.insert(name.to_string(), entry);
+                None
+            }
+        }
+    }
+
+    /// Stop exporting `name`, returns its watch
+    pub fn unregister(&self, name: &str) -> Option<Watch> {
+        self.sensors.lock().remove(name).map(|entry| entry.watch)
+    }
+
+    /// The metrics in the Prometheus text format, as served on `/metrics`
+    pub fn render(&self) -> String {
+        render(&self.sensors)
+    }
+}
+
+impl Drop for PrometheusExporter {
+    fn drop(&mut self) {
+        self.stop.stop();
+        if let Some(server) = self.server.take() {
+            let _ = server.join();
+        }
+    }
+}
+
+fn serve(listener: &TcpListener, sensors: &Sensors, stop: &StopFlag) {
+    while !stop.is_stopped() {
+        match listener.accept() {
+            // a failing client only ends its own request
+            Ok((stream, _)) => drop(respond(stream, sensors)),
+            Err(_) => thread::sleep(ACCEPT_POLL),
+        }
+    }
+}
+
+fn respond(mut stream: TcpStream, sensors: &Sensors) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < 8192 {
+        match stream.read(&mut buffer)? {
+            0 => break,
+            read => request.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut line = request.lines().next().unwrap_or_default().split(' ');
+    let (status, body) = match (line.next(), line.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render(sensors)),
+        (Some("GET"), _) => ("404 Not Found", "only /metrics is served\n".into()),
+        _ => ("405 Method Not Allowed", "only GET is supported\n".into()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+fn render(sensors: &Sensors) -> String {
+    let sensors = sensors.lock();
+    let mut samples: [String; 7] = Default::default();
+    for (name, entry) in sensors.iter() {
+        let sensor = format!("sensor=\"{}\"", escape(name));
+        let stats = entry.watch.stats();
+        let mut sample = |family: usize, labels: &str, value: f64| {
+            let _ = writeln!(
+                samples[family],
+                "medaq_{}{{{labels}}} {}",
+                FAMILIES[family].0,
+                number(value)
+            );
+        };
+        if let Some((_, values)) = entry.watch.latest() {
+            for value in values {
+                let mut labels = format!("{sensor},channel=\"{}\"", escape(&value.channel));
+                if let Some(unit) = &value.unit {
+                    let _ = write!(labels, ",unit=\"{}\"", escape(unit));
+                }
+                let value = match value.value {
+                    Value::Valid(value) => value,
+                    Value::OutOfRange => f64::NAN,
+                };
+                sample(0, &labels, value);
+            }
+        }
+        sample(1, &sensor, (entry.frames_before + stats.frames_read) as f64);
+        sample(2, &sensor, stats.read_errors as f64);
+        sample(3, &sensor, entry.watch.invalid_ratio());
+        sample(4, &sensor, stats.backlog as f64);
+        sample(5, &sensor, entry.reconnects as f64);
+        if let Some(age) = entry.watch.latest_age() {
+            sample(6, &sensor, age.as_secs_f64());
+        }
+    }
+
+    let mut text = String::new();
+    for ((name, kind, help), samples) in FAMILIES.iter().zip(samples) {
+        let _ = writeln!(text, "# HELP medaq_{name} {help}");
+        let _ = writeln!(text, "# TYPE medaq_{name} {kind}");
+        text.push_str(&samples);
+    }
+    text
+}
+
+/// Label value with backslashes, quotes and line feeds escaped
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn number(value: f64) -> String {
+    match value {
+        f64::INFINITY => "+Inf".into(),
+        f64::NEG_INFINITY => "-Inf".into(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, number};
+
+    #[test]
+    fn test_metrics_format_test() {
+        assert_eq!(escape("a \"b\"\\c\nd"), "a \\\"b\\\"\\\\c\\nd");
+        assert_eq!(number(1.5), "1.5");
+        assert_eq!(number(f64::NAN), "NaN");
+        assert_eq!(number(f64::INFINITY), "+Inf");
+    }
+}
@@ -0,0 +1,1052 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Debug},
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use crate::{backend::Backend, Data, MedaqError, ERR_CODE, ME_SENSOR};
+
+/// Scriptable [`Backend`] for development and tests without a sensor or
+/// `MEDAQLib.dll`
+///
+/// Parameters and channel names are preloaded, frames are queued with
+/// [`push_frame`](Self::push_frame) and handed out by `TransferData` in order.
+/// Clones share their state, so a clone kept after connecting can queue frames and
+/// inspect what the crate did.
+///
+/// Every call is logged, see [`calls`](Self::calls), and can be made to fail with
+/// [`fail_call`](Self::fail_call) or [`intercept`](Self::intercept). Calls can be
+/// slowed down like a real interface with [`with_latency`](Self::with_latency),
+/// [`with_jitter`](Self::with_jitter) and [`stall_call`](Self::stall_call).
+///
+/// # Example
+/// ```
+/// use medaqlib::{Interface, MockBackend, SensorBuilder, ME_SENSOR};
+///
+/// let mock = MockBackend::new().with_channels(["Distance", "Intensity"]);
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::TcpIp)
+///     .with_ip_address("10.10.10.10")
+///     .with_backend(mock.clone())
+///     .connect()
+///     .unwrap();
+///
+/// mock.push_frame(&[100, 20], &[1.5, 80.]);
+/// let data = sensor.read_data().unwrap().unwrap();
+/// assert_eq!(data.scaled_data, vec![1.5, 80.]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    state: Arc<Mutex<MockState>>,
+    interceptors: Arc<Mutex<Vec<Interceptor>>>,
+}
+
+/// MEDAQLib function called on a [`MockBackend`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Call {
+    CreateInstance,
+    ReleaseInstance,
+    OpenSensor,
+    CloseSensor,
+    SetParameterString,
+    SetParameterInt,
+    GetParameterString,
+    GetParameterDouble,
+    /// Optional, see [`MockBackend::without_function`]
+    SetParameterDouble,
+    /// Optional, see [`MockBackend::without_function`]
+    GetParameterInt,
+    ExecSCmd,
+    GetError,
+    DataAvail,
+    TransferData,
+}
+
+/// Entry of the call log of a [`MockBackend`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedCall {
+    pub call: Call,
+    /// Parameter name and value, command or maximum number of values, formatted
+    pub arguments: Vec<String>,
+}
+
+/// Answer of an interceptor, see [`MockBackend::intercept`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Intercept {
+    /// Handle the call as usual
+    Pass,
+    /// Fail the call with the error code, calls without result are still made
+    Fail(ERR_CODE),
+    /// Return the string from `GetParameterString` or the error text from
+    /// `GetError`, other calls are handled as usual
+    String(String),
+    /// Report the number of values from `DataAvail` whatever is queued, e.g. a
+    /// negative or huge count, other calls are handled as usual
+    Available(i32),
+}
+
+struct Interceptor(Box<dyn FnMut(&RecordedCall) -> Intercept + Send>);
+
+impl Debug for Interceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Interceptor")
+    }
+}
+
+#[derive(Debug)]
+struct MockState {
+    handles: u32,
+    parameters: HashMap<String, Parameter>,
+    raw: VecDeque<i32>,
+    scaled: VecDeque<f64>,
+    open: bool,
+    commands: Vec<String>,
+    calls: Vec<RecordedCall>,
+    counts: HashMap<Call, usize>,
+    failures: Vec<(Call, usize, ERR_CODE)>,
+    call_log: bool,
+    latency: HashMap<Call, Duration>,
+    jitter: HashMap<Call, Range<Duration>>,
+    stalls: Vec<(Call, usize, Duration)>,
+    /// Optional functions answered like a MEDAQLib that does not export them
+    unsupported: HashSet<Call>,
+    /// State of the generator of the jitter
+    rng: u64,
+}
+
+impl MockState {
+    /// Time the `nth` call of a kind takes
+    fn delay(&mut self, call: Call, nth: usize) -> Duration {
+        let mut delay = self.latency.get(&call).copied().unwrap_or_default();
+        if let Some(jitter) = self.jitter.get(&call).cloned() {
+            let span = jitter.end.saturating_sub(jitter.start).as_nanos() as u64;
+            let offset = match span {
+                0 => 0,
+                span => self.next_random() % span,
+            };
+            delay += jitter.start + Duration::from_nanos(offset);
+        }
+        let stalls = self
+            .stalls
+            .iter()
+            .filter(|(kind, n, _)| *kind == call && *n == nth);
+        delay + stalls.map(|&(_, _, stall)| stall).sum::<Duration>()
+    }
+
+    /// splitmix64, any seed gives a good sequence
+    fn next_random(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        Self {
+            handles: 0,
+            parameters: HashMap::new(),
+            raw: VecDeque::new(),
+            scaled: VecDeque::new(),
+            open: false,
+            commands: vec![],
+            calls: vec![],
+            counts: HashMap::new(),
+            failures: vec![],
+            call_log: true,
+            latency: HashMap::new(),
+            jitter: HashMap::new(),
+            stalls: vec![],
+            unsupported: HashSet::new(),
+            rng: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Parameter {
+    Int(i32),
+    Double(f64),
+    String(String),
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sensor of `sensor_type` connected over TCP/IP to `10.10.10.10` through a
+    /// clone of the mock, available with the `test-util` feature
+    ///
+    /// # Panics
+    /// If connecting fails, e.g. through a call failed with
+    /// [`fail_call`](Self::fail_call).
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn connect(&self, sensor_type: ME_SENSOR) -> crate::Sensor {
+        crate::SensorBuilder::new(sensor_type)
+            .with_interface(crate::Interface::TcpIp)
+            .with_ip_address("10.10.10.10")
+            .with_backend(self.clone())
+            .connect()
+            .expect("connecting through the mock")
+    }
+
+    /// Names of the transmitted channels, reported as `IA_Scaled_Name1`, ...
+    pub fn with_channels<S: Into<String>>(self, channels: impl IntoIterator<Item = S>) -> Self {
+        let mut mock = self;
+        for (index, channel) in channels.into_iter().enumerate() {
+            let name = format!("IA_Scaled_Name{}", index + 1);
+            mock = mock.with_parameter_string(name, channel);
+        }
+        mock
+    }
+
+    /// Preload a string parameter
+    pub fn with_parameter_string(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set(name.into(), Parameter::String(value.into()));
+        self
+    }
+
+    /// Preload a double parameter, e.g. `IA_Scaled_RangeMax1`
+    pub fn with_parameter_double(self, name: impl Into<String>, value: f64) -> Self {
+        self.set(name.into(), Parameter::Double(value));
+        self
+    }
+
+    /// Preload an int parameter
+    pub fn with_parameter_int(self, name: impl Into<String>, value: i32) -> Self {
+        self.set(name.into(), Parameter::Int(value));
+        self
+    }
+
+    /// Queue the raw and scaled values of one frame
+    ///
+    /// # Panics
+    /// If both slices differ in length.
+    pub fn push_frame(&self, raw: &[i32], scaled: &[f64]) {
+        assert_eq!(raw.len(), scaled.len(), "raw and scaled values per frame");
+        let mut state = self.lock();
+        state.raw.extend(raw);
+        state.scaled.extend(scaled);
+    }
+
+    /// Queue all frames of a capture, missing raw values are transferred as `0`
+    pub fn push_data(&self, data: &Data) {
+        let values = data.frame_count() * data.channel_count();
+        let mut state = self.lock();
+        let raw = data.raw_data.iter().copied().chain(std::iter::repeat(0));
+        state.raw.extend(raw.take(values));
+        let scaled = data.scaled_data.iter().copied();
+        state
+            .scaled
+            .extend(scaled.chain(std::iter::repeat(0.)).take(values));
+    }
+
+    /// Number of queued values not transferred yet
+    pub fn pending_values(&self) -> usize {
+        self.lock().scaled.len()
+    }
+
+    /// Current value of a string parameter, e.g. the configured `IP_Interface`
+    pub fn parameter_string(&self, name: &str) -> Option<String> {
+        match self.lock().parameters.get(name) {
+            Some(Parameter::String(value)) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Current value of an int parameter
+    pub fn parameter_int(&self, name: &str) -> Option<i32> {
+        match self.lock().parameters.get(name) {
+            Some(Parameter::Int(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// `true` between `OpenSensor` and `CloseSensor`
+    pub fn is_open(&self) -> bool {
+        self.lock().open
+    }
+
+    /// Sensor commands executed so far, in order
+    pub fn commands(&self) -> Vec<String> {
+        self.lock().commands.clone()
+    }
+
+    /// Fail the `nth` call of a kind with the error code, counted from `1` since
+    /// creating the mock
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Call, MockBackend, ERR_CODE};
+    ///
+    /// // OpenSensor fails twice, then succeeds
+    /// let mock = MockBackend::new();
+    /// mock.fail_call(Call::OpenSensor, 1, ERR_CODE::ERR_CANNOT_OPEN);
+    /// mock.fail_call(Call::OpenSensor, 2, ERR_CODE::ERR_CANNOT_OPEN);
+    /// ```
+    pub fn fail_call(&self, call: Call, nth: usize, err_code: ERR_CODE) {
+        self.lock().failures.push((call, nth, err_code));
+    }
+
+    /// Decide on every call after it is logged, after the failures of
+    /// [`fail_call`](Self::fail_call). The first interceptor not answering
+    /// [`Intercept::Pass`] wins.
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Call, Intercept, MockBackend};
+    ///
+    /// // truncated channel names
+    /// let mock = MockBackend::new().with_channels(["Distance"]);
+    /// mock.intercept(|call| match call.call {
+    ///     Call::GetParameterString => Intercept::String("Dist".into()),
+    ///     _ => Intercept::Pass,
+    /// });
+    /// ```
+    pub fn intercept(&self, interceptor: impl FnMut(&RecordedCall) -> Intercept + Send + 'static) {
+        self.interceptors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Interceptor(Box::new(interceptor)));
+    }
+
+    /// Delay every call of a kind by `delay`
+    pub fn with_latency(self, call: Call, delay: Duration) -> Self {
+        self.lock().latency.insert(call, delay);
+        self
+    }
+
+    /// Delay every call of a kind by a random time within `jitter`, in addition to
+    /// [`with_latency`](Self::with_latency)
+    ///
+    /// The delays are drawn from a generator seeded by [`with_seed`](Self::with_seed),
+    /// so a test sees the same delays on every run.
+    pub fn with_jitter(self, call: Call, jitter: Range<Duration>) -> Self {
+        self.lock().jitter.insert(call, jitter);
+        self
+    }
+
+    /// Seed of the generator of [`with_jitter`](Self::with_jitter), `0` by default
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.lock().rng = seed;
+        self
+    }
+
+    /// Delay the `nth` call of a kind by `duration`, counted like
+    /// [`fail_call`](Self::fail_call)
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use medaqlib::{Call, MockBackend};
+    ///
+    /// // the second read of the backlog hangs for half a second
+    /// let mock = MockBackend::new()
+    ///     .with_latency(Call::TransferData, Duration::from_millis(1));
+    /// mock.stall_call(Call::DataAvail, 2, Duration::from_millis(500));
+    /// ```
+    pub fn stall_call(&self, call: Call, nth: usize, duration: Duration) {
+        self.lock().stalls.push((call, nth, duration));
+    }
+
+    /// Answer an optional function like an older MEDAQLib that does not export it,
+    /// with [`MedaqError::UnsupportedByDll`] and without logging the call
+    ///
+    /// # Panics
+    /// If the call is not [`Call::SetParameterDouble`] or [`Call::GetParameterInt`].
+    pub fn without_function(self, call: Call) -> Self {
+        assert!(
+            optional_function(call).is_some(),
+            "{call:?} is not an optional function"
+        );
+        self.lock().unsupported.insert(call);
+        self
+    }
+
+    /// Stop logging calls, e.g. for benchmarks reading for a long time
+    pub fn without_call_log(self) -> Self {
+        self.lock().call_log = false;
+        self
+    }
+
+    /// Calls made so far, in order
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.lock().calls.clone()
+    }
+
+    /// Wait for the latency of the call, then log it and ask the failures and
+    /// interceptors how to answer it
+    fn record(&self, call: Call, arguments: impl FnOnce() -> Vec<String>) -> Intercept {
+        let (nth, delay) = {
+            let mut state = self.lock();
+            let count = state.counts.entry(call).or_default();
+            *count += 1;
+            let nth = *count;
+            (nth, state.delay(call, nth))
+        };
+        // without holding a lock, so clones stay usable during a stall
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+
+        let mut interceptors = self
+            .interceptors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut state = self.lock();
+        let failure = state
+            .failures
+            .iter()
+            .find(|(kind, n, _)| *kind == call && *n == nth)
+            .map(|&(_, _, err_code)| err_code);
+        if !state.call_log && interceptors.is_empty() {
+            return failure.map_or(Intercept::Pass, Intercept::Fail);
+        }
+
+        let call = RecordedCall {
+            call,
+            arguments: arguments(),
+        };
+        if state.call_log {
+            state.calls.push(call.clone());
+        }
+        drop(state);
+        if let Some(err_code) = failure {
+            return Intercept::Fail(err_code);
+        }
+        interceptors
+            .iter_mut()
+            .map(|Interceptor(interceptor)| interceptor(&call))
+            .find(|intercept| *intercept != Intercept::Pass)
+            .unwrap_or(Intercept::Pass)
+    }
+
+    /// [`record`](Self::record) for calls returning a result
+    fn check(
+        &self,
+        call: Call,
+        arguments: impl FnOnce() -> Vec<String>,
+    ) -> Result<Option<String>, MedaqError> {
+        match self.record(call, arguments) {
+            Intercept::Pass | Intercept::Available(_) => Ok(None),
+            Intercept::Fail(err_code) => Err(MedaqError::Sensor(err_code)),
+            Intercept::String(value) => Ok(Some(value)),
+        }
+    }
+
+    /// Fails if the optional function was removed by
+    /// [`without_function`](Self::without_function)
+    fn supported(&self, call: Call) -> Result<(), MedaqError> {
+        match (
+            self.lock().unsupported.contains(&call),
+            optional_function(call),
+        ) {
+            (true, Some(function)) => Err(MedaqError::UnsupportedByDll { function }),
+            _ => Ok(()),
+        }
+    }
+
+    fn set(&self, name: String, value: Parameter) {
+        self.lock().parameters.insert(name, value);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn parameter(&self, name: &str) -> Result<Parameter, MedaqError> {
+        self.lock()
+            .parameters
+            .get(name)
+            .cloned()
+            .ok_or(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER))
+    }
+}
+
+/// Name of the optional function of MEDAQLib
+fn optional_function(call: Call) -> Option<&'static str> {
+    match call {
+        Call::SetParameterDouble => Some("SetParameterDouble"),
+        Call::GetParameterInt => Some("GetParameterInt"),
+        _ => None,
+    }
+}
+
+impl crate::backend::sealed::Sealed for MockBackend {}
+
+impl Backend for MockBackend {
+    fn create_instance(&self, sensor_type: ME_SENSOR) -> u32 {
+        // a failed CreateSensorInstance returns no handle
+        if let Intercept::Fail(_) =
+            self.record(Call::CreateInstance, || vec![format!("{sensor_type:?}")])
+        {
+            return 0;
+        }
+        let mut state = self.lock();
+        state.handles += 1;
+        state.handles
+    }
+
+    fn release_instance(&self, handle: u32) -> Result<(), MedaqError> {
+        self.check(Call::ReleaseInstance, || vec![handle.to_string()])?;
+        Ok(())
+    }
+
+    fn open_sensor(&self, _: u32) -> Result<(), MedaqError> {
+        self.check(Call::OpenSensor, Vec::new)?;
+        let mut state = self.lock();
+        if state.open {
+            return Err(MedaqError::Sensor(ERR_CODE::ERR_ALREADY_OPEN));
+        }
+        state.open = true;
+        Ok(())
+    }
+
+    fn close_sensor(&self, _: u32) -> Result<(), MedaqError> {
+        let result = self.check(Call::CloseSensor, Vec::new);
+        self.lock().open = false;
+        result.map(|_| ())
+    }
+
+    fn set_parameter_string(&self, _: u32, name: &str, value: &str) -> Result<(), MedaqError> {
+        self.check(Call::SetParameterString, || vec![name.into(), value.into()])?;
+        self.set(name.into(), Parameter::String(value.into()));
+        Ok(())
+    }
+
+    fn set_parameter_int(&self, _: u32, name: &str, value: i32) -> Result<(), MedaqError> {
+        self.check(Call::SetParameterInt, || {
+            vec![name.into(), value.to_string()]
+        })?;
+        self.set(name.into(), Parameter::Int(value));
+        Ok(())
+    }
+
+    fn get_parameter_string(&self, _: u32, name: &str) -> Result<String, MedaqError> {
+        if let Some(value) = self.check(Call::GetParameterString, || vec![name.into()])? {
+            return Ok(value);
+        }
+        match self.parameter(name)? {
+            Parameter::String(value) => Ok(value),
+            Parameter::Int(value) => Ok(value.to_string()),
+            Parameter::Double(value) => Ok(value.to_string()),
+        }
+    }
+
+    fn get_parameter_double(&self, _: u32, name: &str) -> Result<f64, MedaqError> {
+        self.check(Call::GetParameterDouble, || vec![name.into()])?;
+        match self.parameter(name)? {
+            Parameter::Double(value) => Ok(value),
+            Parameter::Int(value) => Ok(value.into()),
+            Parameter::String(_) => Err(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER)),
+        }
+    }
+
+    fn set_parameter_double(&self, _: u32, name: &str, value: f64) -> Result<(), MedaqError> {
+        self.supported(Call::SetParameterDouble)?;
+        self.check(Call::SetParameterDouble, || {
+            vec![name.into(), value.to_string()]
+        })?;
+        self.set(name.into(), Parameter::Double(value));
+        Ok(())
+    }
+
+    fn get_parameter_int(&self, _: u32, name: &str) -> Result<i32, MedaqError> {
+        self.supported(Call::GetParameterInt)?;
+        self.check(Call::GetParameterInt, || vec![name.into()])?;
+        match self.parameter(name)? {
+            Parameter::Int(value) => Ok(value),
+            _ => Err(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER)),
+        }
+    }
+
+    fn exec_scmd(&self, _: u32, command: &str) -> Result<(), MedaqError> {
+        self.check(Call::ExecSCmd, || vec![command.into()])?;
+        let mut state = self.lock();
+        if !state.open {
+            return Err(MedaqError::Sensor(ERR_CODE::ERR_NOT_OPEN));
+        }
+        state.commands.push(command.into());
+        Ok(())
+    }
+
+    fn get_error(&self, _: u32) -> String {
+        match self.record(Call::GetError, Vec::new) {
+            Intercept::String(text) => text,
+            _ => String::new(),
+        }
+    }
+
+    fn data_avail(&self, _: u32) -> Result<i32, MedaqError> {
+        let reported = match self.record(Call::DataAvail, Vec::new) {
+            Intercept::Fail(err_code) => return Err(MedaqError::Sensor(err_code)),
+            Intercept::Available(values) => Some(values),
+            _ => None,
+        };
+        let state = self.lock();
+        if !state.open {
+            return Err(MedaqError::Sensor(ERR_CODE::ERR_NOT_OPEN));
+        }
+        Ok(reported.unwrap_or(state.scaled.len().min(i32::MAX as usize) as i32))
+    }
+
+    fn transfer_data(
+        &self,
+        _: u32,
+        raw: Option<&mut [i32]>,
+        scaled: Option<&mut [f64]>,
+        max_values: usize,
+    ) -> Result<usize, MedaqError> {
+        self.check(Call::TransferData, || vec![max_values.to_string()])?;
+        let mut state = self.lock();
+        if !state.open {
+            return Err(MedaqError::Sensor(ERR_CODE::ERR_NOT_OPEN));
+        }
+        let state = &mut *state;
+        let values = max_values.min(state.scaled.len());
+        // drained values are removed even if no buffer was given
+        let raw_values = state.raw.drain(..values);
+        let scaled_values = state.scaled.drain(..values);
+        if let Some(raw) = raw {
+            raw.iter_mut()
+                .zip(raw_values)
+                .for_each(|(raw, value)| *raw = value);
+        }
+        if let Some(scaled) = scaled {
+            scaled
+                .iter_mut()
+                .zip(scaled_values)
+                .for_each(|(scaled, value)| *scaled = value);
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use super::{Call, Intercept, MockBackend, RecordedCall};
+    use crate::{Interface, MedaqError, PacerConfig, Sensor, SensorBuilder, ERR_CODE, ME_SENSOR};
+
+    fn connect(mock: &MockBackend) -> Result<Sensor, MedaqError> {
+        SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+            .with_interface(Interface::TcpIp)
+            .with_ip_address("10.10.10.10")
+            .with_port(10001)
+            .enable_logging()
+            .with_backend(mock.clone())
+            .connect()
+    }
+
+    #[test]
+    fn test_mock_connect_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance", "Intensity"])
+            .with_parameter_string("IA_Scaled_Unit1", "mm")
+            .with_parameter_double("IA_Raw_RangeMin1", 0.)
+            .with_parameter_double("IA_Raw_RangeMax1", 1000.)
+            .with_parameter_double("IA_Scaled_RangeMin1", 0.)
+            .with_parameter_double("IA_Scaled_RangeMax1", 10.);
+        let sensor = connect(&mock).unwrap();
+
+        assert_eq!(sensor.parameters(), ["Distance", "Intensity"]);
+        let info = sensor.channel_info();
+        assert_eq!(info[0].unit.as_deref(), Some("mm"));
+        assert_eq!((info[0].scale, info[0].offset), (Some(0.01), Some(0.)));
+        assert_eq!((info[1].unit.as_deref(), info[1].scale), (None, None));
+
+        assert_eq!(mock.parameter_string("IP_Interface").unwrap(), "TCP/IP");
+        assert_eq!(
+            mock.parameter_string("IP_RemoteAddr").unwrap(),
+            "10.10.10.10"
+        );
+        assert_eq!(mock.parameter_int("IP_Port"), Some(10001));
+        assert_eq!(mock.parameter_int("IP_EnableLogging"), Some(1));
+        assert_eq!(mock.commands(), ["Get_TransmittedDataInfo"]);
+
+        assert!(mock.is_open());
+        drop(sensor);
+        assert!(!mock.is_open());
+    }
+
+    #[test]
+    fn test_mock_close_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        connect(&mock).unwrap().close();
+        assert!(!mock.is_open());
+        let released = mock
+            .calls()
+            .iter()
+            .filter(|call| call.call == Call::ReleaseInstance)
+            .count();
+        assert_eq!(released, 1);
+    }
+
+    #[test]
+    fn test_mock_read_data_test() {
+        let mock = MockBackend::new().with_channels(["Distance", "Intensity"]);
+        let sensor = connect(&mock).unwrap();
+        assert!(sensor.read_data().unwrap().is_none());
+
+        mock.push_frame(&[1, 10], &[0.1, 1.]);
+        mock.push_frame(&[2, 20], &[0.2, 2.]);
+        let data = sensor.read_data().unwrap().unwrap();
+        assert_eq!(data.raw_data, vec![1, 10, 2, 20]);
+        assert_eq!(data.scaled_data, vec![0.1, 1., 0.2, 2.]);
+        assert!(data.timestamp().is_some());
+
+        // the buffer holds one and a half frames, only whole frames are read
+        mock.push_frame(&[3, 30], &[0.3, 3.]);
+        mock.push_frame(&[4, 40], &[0.4, 4.]);
+        let mut scaled = [0.; 3];
+        assert_eq!(sensor.read_into_slices(None, Some(&mut scaled)).unwrap(), 1);
+        assert_eq!(scaled, [0.3, 3., 0.]);
+        assert_eq!(mock.pending_values(), 2);
+
+        let blocking = sensor.read_data_blocking(Duration::from_millis(10));
+        assert_eq!(blocking.unwrap().unwrap().raw_data, vec![4, 40]);
+        assert_eq!(sensor.stats().frames_read, 4);
+    }
+
+    #[test]
+    fn test_mock_without_channels_test() {
+        let mock = MockBackend::new();
+        let sensor = connect(&mock).unwrap();
+        assert!(sensor.parameters().is_empty());
+        assert!(matches!(sensor.read_data(), Err(MedaqError::NoChannels)));
+    }
+
+    #[test]
+    fn test_mock_fail_call_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        mock.fail_call(Call::OpenSensor, 1, ERR_CODE::ERR_CANNOT_OPEN);
+        mock.fail_call(Call::OpenSensor, 2, ERR_CODE::ERR_CANNOT_OPEN);
+        mock.fail_call(Call::TransferData, 2, ERR_CODE::ERR_NO_ANSWER_RECEIVED);
+
+        for _ in 0..2 {
+            assert!(matches!(
+                connect(&mock),
+                Err(MedaqError::Sensor(ERR_CODE::ERR_CANNOT_OPEN))
+            ));
+        }
+        let sensor = connect(&mock).unwrap();
+        mock.push_frame(&[0], &[1.]);
+        assert_eq!(sensor.read_data().unwrap().unwrap().scaled_data, vec![1.]);
+        mock.push_frame(&[0], &[2.]);
+        mock.push_frame(&[0], &[3.]);
+        // the failed transfer keeps the values queued
+        assert!(matches!(
+            sensor.read_data(),
+            Err(MedaqError::Sensor(ERR_CODE::ERR_NO_ANSWER_RECEIVED))
+        ));
+        assert_eq!(mock.pending_values(), 2);
+        assert_eq!(
+            sensor.read_data().unwrap().unwrap().scaled_data,
+            vec![2., 3.]
+        );
+
+        let opens = mock
+            .calls()
+            .iter()
+            .filter(|call| call.call == Call::OpenSensor)
+            .count();
+        assert_eq!(opens, 3);
+        let transfers: Vec<_> = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.call == Call::TransferData)
+            .collect();
+        assert_eq!(transfers[1].arguments, ["2"]);
+    }
+
+    #[test]
+    fn test_mock_call_log_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        drop(connect(&mock).unwrap());
+
+        let calls = mock.calls();
+        let call = |call, arguments: &[&str]| RecordedCall {
+            call,
+            arguments: arguments
+                .iter()
+                .map(|argument| argument.to_string())
+                .collect(),
+        };
+        assert_eq!(calls[0], call(Call::CreateInstance, &["SENSOR_IFD2421"]));
+        assert_eq!(
+            calls[1],
+            call(Call::SetParameterString, &["IP_Interface", "TCP/IP"])
+        );
+        assert!(calls.contains(&call(Call::OpenSensor, &[])));
+        assert!(calls.contains(&call(Call::ExecSCmd, &["Get_TransmittedDataInfo"])));
+        assert!(calls.contains(&call(Call::GetParameterString, &["IA_Scaled_Name1"])));
+        assert_eq!(
+            calls[calls.len() - 2..],
+            [
+                call(Call::CloseSensor, &[]),
+                call(Call::ReleaseInstance, &["1"])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mock_intercept_test() {
+        let mock = MockBackend::new().with_channels(["Distance", "Intensity"]);
+        mock.intercept(|call| match call.arguments.first().map(String::as_str) {
+            Some("IA_Scaled_Name1") => Intercept::String("Dist".into()),
+            _ => Intercept::Pass,
+        });
+        let sensor = connect(&mock).unwrap();
+        assert_eq!(sensor.parameters(), ["Dist", "Intensity"]);
+
+        mock.intercept(|call| match call.call {
+            Call::DataAvail => Intercept::Fail(ERR_CODE::ERR_READING_SENSOR_DATA),
+            _ => Intercept::Pass,
+        });
+        assert!(matches!(
+            sensor.read_data(),
+            Err(MedaqError::Sensor(ERR_CODE::ERR_READING_SENSOR_DATA))
+        ));
+    }
+
+    fn data_avail_calls(mock: &MockBackend) -> usize {
+        let calls = mock.calls();
+        calls
+            .iter()
+            .filter(|call| call.call == Call::DataAvail)
+            .count()
+    }
+
+    #[test]
+    fn test_mock_delay_test() {
+        let delays = |seed| {
+            let mock = MockBackend::new()
+                .with_latency(Call::DataAvail, Duration::from_millis(2))
+                .with_jitter(
+                    Call::DataAvail,
+                    Duration::from_millis(1)..Duration::from_millis(3),
+                )
+                .with_seed(seed);
+            mock.stall_call(Call::DataAvail, 3, Duration::from_secs(1));
+            let mut state = mock.lock();
+            let delays: Vec<_> = (1..=20)
+                .map(|nth| state.delay(Call::DataAvail, nth))
+                .collect();
+            assert_eq!(state.delay(Call::TransferData, 3), Duration::ZERO);
+            delays
+        };
+
+        let first = delays(7);
+        assert_eq!(first, delays(7));
+        assert_ne!(first, delays(8));
+        for (nth, delay) in (1..).zip(&first) {
+            let delay = match nth {
+                3 => *delay - Duration::from_secs(1),
+                _ => *delay,
+            };
+            assert!(delay >= Duration::from_millis(3) && delay < Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_mock_blocking_read_deadline_during_call_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = connect(&mock).unwrap();
+        assert!(sensor.read_data().unwrap().is_none());
+        let per_read = data_avail_calls(&mock);
+
+        // the deadline passes during the first poll, no second poll is made
+        mock.stall_call(Call::DataAvail, per_read + 1, Duration::from_millis(40));
+        let start = Instant::now();
+        let blocking = sensor.read_data_blocking(Duration::from_millis(20));
+        assert!(blocking.unwrap().is_none());
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert_eq!(data_avail_calls(&mock), 2 * per_read);
+
+        // data arriving while the stalled call is in flight is still returned
+        let stalled = 2 * per_read + 1;
+        mock.stall_call(Call::DataAvail, stalled, Duration::from_millis(40));
+        let feeder = mock.clone();
+        let seen = AtomicUsize::new(2 * per_read);
+        mock.intercept(move |call| {
+            if call.call == Call::DataAvail && seen.fetch_add(1, Ordering::SeqCst) + 1 == stalled {
+                feeder.push_frame(&[1], &[1.]);
+            }
+            Intercept::Pass
+        });
+        let blocking = sensor.read_data_blocking(Duration::from_millis(20));
+        assert_eq!(blocking.unwrap().unwrap().scaled_data, vec![1.]);
+    }
+
+    #[test]
+    fn test_mock_pacer_follows_rate_change_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance"])
+            .with_latency(Call::DataAvail, Duration::from_millis(2))
+            .with_jitter(Call::DataAvail, Duration::ZERO..Duration::from_millis(1))
+            .with_seed(3);
+        let mut sensor = connect(&mock).unwrap();
+        sensor.set_pacing(PacerConfig {
+            frames_per_read: 10,
+            max_latency: None,
+            ..Default::default()
+        });
+
+        // every poll of the backlog finds new frames
+        let frames = Arc::new(AtomicUsize::new(1));
+        let feeder = mock.clone();
+        let per_poll = Arc::clone(&frames);
+        mock.intercept(move |call| {
+            if call.call == Call::DataAvail {
+                for _ in 0..per_poll.load(Ordering::SeqCst) {
+                    feeder.push_frame(&[0], &[0.]);
+                }
+            }
+            Intercept::Pass
+        });
+        let settle = || {
+            for _ in 0..30 {
+                sensor.read_data_blocking(Duration::from_secs(1)).unwrap();
+            }
+            sensor.pacer()
+        };
+
+        let slow = settle();
+        frames.store(20, Ordering::SeqCst);
+        let fast = settle();
+        let (slow_rate, fast_rate) = (slow.observed_rate().unwrap(), fast.observed_rate().unwrap());
+        assert!(fast_rate > 5. * slow_rate, "{slow_rate} -> {fast_rate}");
+        assert!(fast.interval() * 5 < slow.interval());
+    }
+
+    #[test]
+    fn test_mock_connect_waits_for_stalled_open_test() {
+        // there is no connect timeout, connecting takes as long as OpenSensor
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        mock.stall_call(Call::OpenSensor, 1, Duration::from_millis(50));
+        let start = Instant::now();
+        let sensor = connect(&mock).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(sensor.parameters(), ["Distance"]);
+
+        // a clone stays usable while a call is stalled
+        mock.stall_call(Call::DataAvail, 1, Duration::from_millis(200));
+        let reader = std::thread::spawn(move || sensor.read_data().map(|data| data.is_none()));
+        std::thread::sleep(Duration::from_millis(20));
+        let start = Instant::now();
+        assert!(mock.is_open());
+        mock.push_frame(&[0], &[1.]);
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert!(!reader.join().unwrap().unwrap());
+    }
+
+    #[test]
+    fn test_mock_hostile_parameter_strings_test() {
+        let long = "x".repeat(1023);
+        let mock = MockBackend::new()
+            .with_channels(["Dist\u{1}\t\r\nance", "", "Hidden"])
+            .with_parameter_string("IA_Scaled_Name2", long.clone())
+            .with_parameter_string("IA_Scaled_Unit1", "\u{7f}")
+            .with_parameter_string("IA_Scaled_Unit2", "mm");
+        // the empty third name ends the channels, later names are ignored
+        mock.intercept(|call| match call.arguments.first().map(String::as_str) {
+            Some("IA_Scaled_Name3") => Intercept::String(String::new()),
+            Some("IA_Scaled_Unit1") => Intercept::Fail(ERR_CODE::ERR_WRONG_PARAMETER),
+            _ => Intercept::Pass,
+        });
+        let sensor = connect(&mock).unwrap();
+
+        assert_eq!(sensor.parameters(), ["Dist\u{1}\t\r\nance", long.as_str()]);
+        let units: Vec<_> = sensor
+            .channel_info()
+            .iter()
+            .map(|info| info.unit.as_deref())
+            .collect();
+        // a failed unit query leaves the units of the other channels in place
+        assert_eq!(units, [None, Some("mm")]);
+
+        mock.push_frame(&[1, 2], &[1., 2.]);
+        let data = sensor.read_data().unwrap().unwrap();
+        assert_eq!(data.channels(), sensor.parameters());
+        assert_eq!(data.unit(1), Some("mm"));
+        assert!(data.to_string().contains(&long));
+    }
+
+    #[test]
+    fn test_mock_failing_channel_name_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance", "Intensity", "Counter"])
+            .with_parameter_string("IA_Scaled_Unit1", "mm")
+            .with_parameter_string("IA_Scaled_Unit2", "%");
+        mock.fail_call(Call::GetParameterString, 2, ERR_CODE::ERR_WRONG_PARAMETER);
+        let sensor = connect(&mock).unwrap();
+        // the Unit1 query is the second, so the first channel loses only its unit
+        assert_eq!(sensor.parameters(), ["Distance", "Intensity", "Counter"]);
+        let info = sensor.channel_info();
+        assert_eq!(
+            (info[0].unit.as_deref(), info[1].unit.as_deref()),
+            (None, Some("%"))
+        );
+
+        let mock = MockBackend::new().with_channels(["Distance", "Intensity"]);
+        mock.fail_call(
+            Call::GetParameterString,
+            3,
+            ERR_CODE::ERR_NO_ANSWER_RECEIVED,
+        );
+        let sensor = connect(&mock).unwrap();
+        // a failed name query ends the channels
+        assert_eq!(sensor.parameters(), ["Distance"]);
+    }
+
+    #[test]
+    fn test_mock_optional_functions_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance"])
+            .with_parameter_int("SA_Count", 3);
+        let sensor = connect(&mock).unwrap();
+        sensor.set_parameter_double("SP_Offset", 1.5).unwrap();
+        assert_eq!(sensor.get_parameter_double("SP_Offset").unwrap(), 1.5);
+        assert_eq!(sensor.get_parameter_int("SA_Count").unwrap(), 3);
+
+        // an older MEDAQLib without them still connects
+        let old = MockBackend::new()
+            .with_channels(["Distance"])
+            .without_function(Call::SetParameterDouble)
+            .without_function(Call::GetParameterInt);
+        let sensor = connect(&old).unwrap();
+        assert!(matches!(
+            sensor.set_parameter_double("SP_Offset", 1.5),
+            Err(MedaqError::UnsupportedByDll {
+                function: "SetParameterDouble"
+            })
+        ));
+        let err = sensor.get_parameter_int("SA_Count").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the loaded MEDAQLib does not export GetParameterInt"
+        );
+        assert!(old
+            .calls()
+            .iter()
+            .all(|call| !matches!(call.call, Call::SetParameterDouble | Call::GetParameterInt)));
+    }
+
+    #[test]
+    #[should_panic(expected = "OpenSensor is not an optional function")]
+    fn test_mock_without_core_function_test() {
+        MockBackend::new().without_function(Call::OpenSensor);
+    }
+}
@@ -0,0 +1,582 @@
+use std::{
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use rumqttc::{Client, Connection, Event, MqttOptions, Packet, QoS, RecvTimeoutError};
+
+use crate::{
+    sink::SinkClosed,
+    sync::{Shared, StopFlag},
+    ChannelSummary, Data, DataSink, MedaqError, Value,
+};
+
+/// Interval the event loop checks for the stop request while waiting
+const STOP_CHECK: Duration = Duration::from_millis(100);
+
+/// Delivery guarantee of the messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MqttQos {
+    #[default]
+    AtMostOnce,
+    AtLeastOnce,
+    ExactlyOnce,
+}
+
+/// What is published per channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MqttPayload {
+    /// `{"ts":1700000000.5,"value":1.23}` for every nth frame, `value` is `null` out
+    /// of range, `ts` is missing without a capture timestamp
+    Frames { decimation: usize },
+    /// The [`ChannelSummary`] of the frames received within every interval
+    Summary { interval: Duration },
+}
+
+impl Default for MqttPayload {
+    fn default() -> Self {
+        MqttPayload::Summary {
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Broker and messages of an [`MqttPublisher`]
+#[derive(Debug, Clone)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Topic of every message, `{sensor_serial}` and `{channel}` are replaced
+    pub topic: String,
+    /// Replaces `{sensor_serial}` in the topic
+    pub sensor_serial: String,
+    pub qos: MqttQos,
+    pub payload: MqttPayload,
+    pub keep_alive: Duration,
+    /// Wait time before connecting again after the broker connection was lost
+    pub reconnect_delay: Duration,
+    /// Messages queued for the broker, further messages are dropped until there is room
+    pub queue: usize,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".into(),
+            port: 1883,
+            client_id: "medaqlib".into(),
+            topic: "plant/{sensor_serial}/{channel}".into(),
+            sensor_serial: "sensor".into(),
+            qos: MqttQos::default(),
+            payload: MqttPayload::default(),
+            keep_alive: Duration::from_secs(30),
+            reconnect_delay: Duration::from_secs(2),
+            queue: 1024,
+        }
+    }
+}
+
+/// Counters of an [`MqttPublisher`], see [`PublishMonitor::stats`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PublishStats {
+    /// Messages handed to the client
+    pub published: u64,
+    /// Messages the client did not accept, e.g. because its queue was full
+    pub dropped: u64,
+    /// Whether the broker acknowledged the current connection
+    pub connected: bool,
+    /// Number of connections acknowledged by the broker, more than one after reconnects
+    pub connections: u64,
+    /// Latest error of the client or the broker connection
+    pub last_error: Option<String>,
+}
+
+/// Reads the [`PublishStats`] of a publisher that was moved into an acquisition
+#[derive(Clone)]
+pub struct PublishMonitor(Shared<PublishStats>);
+
+impl PublishMonitor {
+    pub fn stats(&self) -> PublishStats {
+        self.0.lock().clone()
+    }
+}
+
+/// Client a [`MqttPublisher`] sends its messages through, implemented for rumqttc
+/// and by test doubles
+pub trait MqttClient: Send + 'static {
+    /// Queue a message without blocking, fails if it cannot be queued
+    fn publish(&mut self, topic: &str, qos: MqttQos, payload: Vec<u8>) -> Result<(), String>;
+}
+
+/// [`MqttClient`] of rumqttc, the event loop runs in its own thread
+///
+/// The connection to the broker is kept up independently of the sensor, lost
+/// connections are retried every [`MqttConfig::reconnect_delay`].
+pub struct RumqttClient {
+    client: Client,
+    stop: StopFlag,
+}
+
+impl RumqttClient {
+    fn start(config: &MqttConfig, stats: Shared<PublishStats>) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(config.keep_alive);
+        let (client, connection) = Client::new(options, config.queue.max(1));
+        let stop = StopFlag::default();
+        let stopped = stop.clone();
+        let delay = config.reconnect_delay;
+        thread::Builder::new()
+            .name("medaq-mqtt".into())
+            .spawn(move || run_connection(connection, &stats, &stopped, delay))
+            .expect("could not spawn the MQTT thread");
+        Self { client, stop }
+    }
+}
+
+impl MqttClient for RumqttClient {
+    fn publish(&mut self, topic: &str, qos: MqttQos, payload: Vec<u8>) -> Result<(), String> {
+        let qos = match qos {
+            MqttQos::AtMostOnce => QoS::AtMostOnce,
+            MqttQos::AtLeastOnce => QoS::AtLeastOnce,
+            MqttQos::ExactlyOnce => QoS::ExactlyOnce,
+        };
+        self.client
+            .try_publish(topic, qos, false, payload)
+            .map_err(|err| err.to_string())
+    }
+}
+
+impl Drop for RumqttClient {
+    fn drop(&mut self) {
+        // the thread ends once the disconnect was sent or the broker is unreachable
+        self.stop.stop();
+        let _ = self.client.try_disconnect();
+    }
+}
+
+/// Keep the connection going until stopped, updating the stats
+fn run_connection(
+    mut connection: Connection,
+    stats: &Shared<PublishStats>,
+    stop: &StopFlag,
+    delay: Duration,
+) {
+    while !stop.is_stopped() {
+        match connection.recv_timeout(STOP_CHECK) {
+            Ok(Ok(Event::Incoming(Packet::ConnAck(_)))) => {
+                let mut stats = stats.lock();
+                stats.connected = true;
+                stats.connections += 1;
+            }
+            Ok(Ok(Event::Outgoing(rumqttc::Outgoing::Disconnect))) => break,
+            Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Ok(Err(err)) => {
+                {
+                    let mut stats = stats.lock();
+                    stats.connected = false;
+                    stats.last_error = Some(err.to_string());
+                }
+                let since = Instant::now();
+                while !stop.is_stopped() && since.elapsed() < delay {
+                    thread::sleep(STOP_CHECK.min(delay));
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    stats.lock().connected = false;
+}
+
+/// Publishes the frames of an acquisition to an MQTT broker, a message per channel
+///
+/// Used as [`DataSink`], it never blocks the acquisition: messages the client
+/// cannot queue are counted as dropped. Read errors are not published.
+///
+/// # Example
+/// ```no_run
+/// use medaqlib::{
+///     AcquisitionConfig, Interface, MqttConfig, MqttPublisher, SensorBuilder, ME_SENSOR,
+/// };
+///
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::TcpIp)
+///     .with_ip_address("10.10.10.10")
+///     .connect()
+///     .unwrap();
+///
+/// let publisher = MqttPublisher::connect(MqttConfig {
+///     host: "broker.plant.local".into(),
+///     sensor_serial: "1234".into(),
+///     ..MqttConfig::default()
+/// });
+/// let monitor = publisher.monitor();
+/// let acquisition = sensor.start_acquisition_with_sink(AcquisitionConfig::default(), publisher);
+/// std::thread::sleep(std::time::Duration::from_secs(60));
+/// println!("{:?}", monitor.stats());
+/// let sensor = acquisition.join();
+/// ```
+pub struct MqttPublisher<C: MqttClient = RumqttClient> {
+    config: MqttConfig,
+    client: C,
+    stats: Shared<PublishStats>,
+    /// Frames to skip before the next one is published
+    skip: usize,
+    interval: Option<Interval>,
+}
+
+impl MqttPublisher {
+    /// Publish through rumqttc, connecting to the broker in the background
+    pub fn connect(config: MqttConfig) -> Self {
+        let stats = Shared::default();
+        let client = RumqttClient::start(&config, stats.clone());
+        Self::with_stats(config, client, stats)
+    }
+}
+
+impl<C: MqttClient> MqttPublisher<C> {
+    /// Publish through `client`, e.g. a test double
+    pub fn with_client(config: MqttConfig, client: C) -> Self {
+        Self::with_stats(config, client, Shared::default())
+    }
+
+    fn with_stats(config: MqttConfig, client: C, stats: Shared<PublishStats>) -> Self {
+        Self {
+            config,
+            client,
+            stats,
+            skip: 0,
+            interval: None,
+        }
+    }
+
+    /// Handle to the stats that remains usable once the publisher was moved
+    pub fn monitor(&self) -> PublishMonitor {
+        PublishMonitor(self.stats.clone())
+    }
+
+    fn topic(&self, channel: &str) -> String {
+        self.config
+            .topic
+            .replace("{sensor_serial}", &self.config.sensor_serial)
+            .replace("{channel}", channel)
+    }
+
+    fn publish(&mut self, channel: &str, payload: String) {
+        let topic = self.topic(channel);
+        let published = self
+            .client
+            .publish(&topic, self.config.qos, payload.into_bytes());
+        let mut stats = self.stats.lock();
+        match published {
+            Ok(()) => stats.published += 1,
+            Err(err) => {
+                stats.dropped += 1;
+                stats.last_error = Some(err);
+            }
+        }
+    }
+
+    fn publish_frames(&mut self, data: &Data, decimation: usize) {
+        let (channels, frames) = (data.channel_count(), data.frame_count());
+        let step = decimation.max(1);
+        let sent: Vec<usize> = (self.skip..frames).step_by(step).collect();
+        self.skip = (self.skip + sent.len() * step).saturating_sub(frames);
+
+        let ts = data
+            .timestamp()
+            .map(|timestamp| format!("\"ts\":{},", number(seconds_since_epoch(timestamp))))
+            .unwrap_or_default();
+        for frame in sent {
+            let values = &data.scaled_data[frame * channels..(frame + 1) * channels];
+            for (channel, &value) in data.channels().iter().zip(values) {
+                let value = Value::from_sample(value)
+                    .into_raw()
+                    .map_or("null".into(), number);
+                self.publish(channel, format!("{{{ts}\"value\":{value}}}"));
+            }
+        }
+    }
+
+    fn publish_summary(&mut self, data: &Data, length: Duration) {
+        let now = Instant::now();
+        let interval = self.interval.get_or_insert_with(|| Interval::new(now));
+        if interval.channels != data.channels() {
+            *interval = Interval::new(now);
+        }
+        interval.add(data);
+        if now.duration_since(interval.start) < length {
+            return;
+        }
+        let summaries = std::mem::replace(interval, Interval::new(now)).summaries();
+        for summary in summaries {
+            let payload = serde_json::to_string(&summary).expect("summaries serialize");
+            self.publish(&summary.channel, payload);
+        }
+    }
+}
+
+impl<C: MqttClient> DataSink for MqttPublisher<C> {
+    fn send(&mut self, result: Result<Data, MedaqError>) -> Result<(), SinkClosed> {
+        let Ok(data) = result else {
+            return Ok(());
+        };
+        if data.channel_count() == 0 {
+            return Ok(());
+        }
+        match self.config.payload {
+            MqttPayload::Frames { decimation } => self.publish_frames(&data, decimation),
+            MqttPayload::Summary { interval } => self.publish_summary(&data, interval),
+        }
+        Ok(())
+    }
+}
+
+/// Frames of the current summary interval
+struct Interval {
+    start: Instant,
+    channels: Vec<String>,
+    units: Vec<Option<String>>,
+    frames: u64,
+    valid: Vec<u64>,
+    sum: Vec<f64>,
+    min: Vec<Option<f64>>,
+    max: Vec<Option<f64>>,
+}
+
+impl Interval {
+    fn new(start: Instant) -> Self {
+        Self {
+            start,
+            channels: vec![],
+            units: vec![],
+            frames: 0,
+            valid: vec![],
+            sum: vec![],
+            min: vec![],
+            max: vec![],
+        }
+    }
+
+    fn add(&mut self, data: &Data) {
+        let channels = data.channel_count();
+        if self.channels.is_empty() {
+            self.channels = data.channels().to_vec();
+            self.units = (0..channels)
+                .map(|index| data.unit(index).map(String::from))
+                .collect();
+            self.valid = vec![0; channels];
+            self.sum = vec![0.; channels];
+            self.min = vec![None; channels];
+            self.max = vec![None; channels];
+        }
+        self.frames += data.frame_count() as u64;
+        for frame in data.scaled_data.chunks_exact(channels) {
+            for (index, &sample) in frame.iter().enumerate() {
+                if let Some(value) = Value::from_sample(sample).into_raw() {
+                    self.valid[index] += 1;
+                    self.sum[index] += value;
+                    self.min[index] = Some(self.min[index].map_or(value, |min| min.min(value)));
+                    self.max[index] = Some(self.max[index].map_or(value, |max| max.max(value)));
+                }
+            }
+        }
+    }
+
+    fn summaries(self) -> Vec<ChannelSummary> {
+        (0..self.channels.len())
+            .map(|index| ChannelSummary {
+                channel: self.channels[index].clone(),
+                unit: self.units[index].clone(),
+                mean: (self.valid[index] > 0).then(|| self.sum[index] / self.valid[index] as f64),
+                min: self.min[index],
+                max: self.max[index],
+                valid_ratio: match self.frames {
+                    0 => 0.,
+                    frames => self.valid[index] as f64 / frames as f64,
+                },
+            })
+            .collect()
+    }
+}
+
+fn seconds_since_epoch(timestamp: SystemTime) -> f64 {
+    timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs_f64()
+}
+
+fn number(value: f64) -> String {
+    serde_json::to_string(&value).expect("numbers serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{MqttClient, MqttQos};
+    use crate::{
+        sync::Shared, Data, DataSink, MedaqError, MqttConfig, MqttPayload, MqttPublisher,
+        SCALED_OUT_OF_RANGE,
+    };
+
+    /// Records the messages, rejects them once `capacity` were published
+    #[derive(Clone)]
+    struct Recorder {
+        messages: Shared<Vec<(String, String)>>,
+        capacity: usize,
+    }
+
+    impl MqttClient for Recorder {
+        fn publish(&mut self, topic: &str, _: MqttQos, payload: Vec<u8>) -> Result<(), String> {
+            let mut messages = self.messages.lock();
+            if messages.len() == self.capacity {
+                return Err("queue full".into());
+            }
+            messages.push((topic.into(), String::from_utf8(payload).unwrap()));
+            Ok(())
+        }
+    }
+
+    fn publisher(payload: MqttPayload, capacity: usize) -> (MqttPublisher<Recorder>, Recorder) {
+        let recorder = Recorder {
+            messages: Shared::default(),
+            capacity,
+        };
+        let config = MqttConfig {
+            sensor_serial: "1234".into(),
+            payload,
+            ..MqttConfig::default()
+        };
+        (
+            MqttPublisher::with_client(config, recorder.clone()),
+            recorder,
+        )
+    }
+
+    fn data(first: usize, frames: usize) -> Data {
+        let scaled = (first..first + frames)
+            .flat_map(|frame| [frame as f64, SCALED_OUT_OF_RANGE])
+            .collect();
+        Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            scaled,
+        )
+        .with_timestamp(UNIX_EPOCH + Duration::from_secs(100))
+    }
+
+    #[test]
+    fn test_mqtt_frames_test() {
+        let (mut publisher, recorder) = publisher(MqttPayload::Frames { decimation: 2 }, 3);
+        let monitor = publisher.monitor();
+        publisher.send(Ok(data(0, 3))).unwrap();
+        publisher.send(Err(MedaqError::NoChannels)).unwrap();
+
+        let messages = recorder.messages.lock().clone();
+        assert_eq!(
+            messages,
+            [
+                (
+                    "plant/1234/Distance".to_string(),
+                    "{\"ts\":100.0,\"value\":0.0}".to_string()
+                ),
+                (
+                    "plant/1234/Intensity".to_string(),
+                    "{\"ts\":100.0,\"value\":null}".to_string()
+                ),
+                (
+                    "plant/1234/Distance".to_string(),
+                    "{\"ts\":100.0,\"value\":2.0}".to_string()
+                ),
+            ]
+        );
+        let stats = monitor.stats();
+        assert_eq!((stats.published, stats.dropped), (3, 1));
+        assert_eq!(stats.last_error.as_deref(), Some("queue full"));
+    }
+
+    #[test]
+    fn test_mqtt_summary_test() {
+        let (mut publisher, recorder) = publisher(
+            MqttPayload::Summary {
+                interval: Duration::ZERO,
+            },
+            usize::MAX,
+        );
+        publisher.send(Ok(data(1, 2))).unwrap();
+        let messages = recorder.messages.lock().clone();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0, "plant/1234/Distance");
+        let summary: serde_json::Value = serde_json::from_str(&messages[0].1).unwrap();
+        assert_eq!(summary["mean"], 1.5);
+        assert_eq!(summary["valid_ratio"], 1.);
+        let summary: serde_json::Value = serde_json::from_str(&messages[1].1).unwrap();
+        assert!(summary["mean"].is_null());
+        assert_eq!(summary["valid_ratio"], 0.);
+    }
+
+    #[test]
+    fn test_mqtt_reconnect_test() {
+        use std::{
+            io::{Read, Write},
+            net::TcpListener,
+            thread,
+            time::Instant,
+        };
+
+        // accepts two connections, answering CONNECT with CONNACK and closing the first
+        let broker = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = broker.local_addr().unwrap().port();
+        let connections = thread::spawn(move || {
+            let mut streams = vec![];
+            for _ in 0..2 {
+                let (mut stream, _) = broker.accept().unwrap();
+                let mut connect = [0; 256];
+                let _ = stream.read(&mut connect).unwrap();
+                stream.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap();
+                streams.push(stream);
+                if streams.len() == 1 {
+                    thread::sleep(Duration::from_millis(100));
+                    streams.clear();
+                }
+            }
+            streams
+        });
+
+        let publisher = MqttPublisher::connect(MqttConfig {
+            host: "127.0.0.1".into(),
+            port,
+            reconnect_delay: Duration::from_millis(50),
+            ..MqttConfig::default()
+        });
+        let monitor = publisher.monitor();
+        let start = Instant::now();
+        while monitor.stats().connections < 2 {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "{:?}",
+                monitor.stats()
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+        let stats = monitor.stats();
+        assert!(stats.connected, "{stats:?}");
+        assert!(stats.last_error.is_some(), "{stats:?}");
+        drop(publisher);
+        drop(connections.join().unwrap());
+    }
+
+    #[test]
+    fn test_mqtt_summary_interval_test() {
+        let (mut publisher, recorder) = publisher(
+            MqttPayload::Summary {
+                interval: Duration::from_secs(3600),
+            },
+            usize::MAX,
+        );
+        publisher.send(Ok(data(1, 2))).unwrap();
+        assert!(recorder.messages.lock().is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+use std::str::FromStr;
+
+use crate::{Interface, MedaqError, ERR_CODE, ME_SENSOR};
+
+/// Every sensor type of the bindings
+const SENSOR_TYPES: [ME_SENSOR; 54] = [
+    ME_SENSOR::NO_SENSOR,
+    ME_SENSOR::SENSOR_ILR1171,
+    ME_SENSOR::SENSOR_ILR118x,
+    ME_SENSOR::SENSOR_ILR1191,
+    ME_SENSOR::SENSOR_ILR2250,
+    ME_SENSOR::SENSOR_ILR3800,
+    ME_SENSOR::SENSOR_ILD1220,
+    ME_SENSOR::SENSOR_ILD1320,
+    ME_SENSOR::SENSOR_ILD1420,
+    ME_SENSOR::SENSOR_ILD1750,
+    ME_SENSOR::SENSOR_ILD1900,
+    ME_SENSOR::SENSOR_ILD2300,
+    ME_SENSOR::SENSOR_ILD5500,
+    ME_SENSOR::SENSOR_IFD2410,
+    ME_SENSOR::SENSOR_IFD2411,
+    ME_SENSOR::SENSOR_IFD2415,
+    ME_SENSOR::SENSOR_IFD2416,
+    ME_SENSOR::SENSOR_IFD2421,
+    ME_SENSOR::SENSOR_IFD2422,
+    ME_SENSOR::SENSOR_IFD2445,
+    ME_SENSOR::SENSOR_IFD2451,
+    ME_SENSOR::SENSOR_IFD2461,
+    ME_SENSOR::SENSOR_IFD2465,
+    ME_SENSOR::SENSOR_IFD2466,
+    ME_SENSOR::SENSOR_IFD2471,
+    ME_SENSOR::SENSOR_ODC2500,
+    ME_SENSOR::SENSOR_ODC2520,
+    ME_SENSOR::SENSOR_ODC2600,
+    ME_SENSOR::SENSOR_ODC2700,
+    ME_SENSOR::SENSOR_LLT27xx,
+    ME_SENSOR::SENSOR_DT3060,
+    ME_SENSOR::SENSOR_DT3100,
+    ME_SENSOR::SENSOR_IMC5200,
+    ME_SENSOR::SENSOR_IMC5400,
+    ME_SENSOR::SENSOR_IMC5600,
+    ME_SENSOR::SENSOR_DT6120,
+    ME_SENSOR::CONTROLLER_DT6200,
+    ME_SENSOR::CONTROLLER_DT6500,
+    ME_SENSOR::CONTROLLER_KSS64xx,
+    ME_SENSOR::CONTROLLER_KSB6430,
+    ME_SENSOR::SENSOR_ON_MEBUS,
+    ME_SENSOR::PCI_CARD_IF2008,
+    ME_SENSOR::ETH_ADAPTER_IF2008,
+    ME_SENSOR::ETH_IF1032,
+    ME_SENSOR::IE_IF203x,
+    ME_SENSOR::USB_ADAPTER_IF2004,
+    ME_SENSOR::CONTROLLER_CBOX,
+    ME_SENSOR::THICKNESS_SENSOR,
+    ME_SENSOR::CONTROLLER_DPU,
+    ME_SENSOR::SENSOR_ACS7000,
+    ME_SENSOR::SENSOR_CFO,
+    ME_SENSOR::SENSOR_MFA,
+    ME_SENSOR::SENSOR_GENERIC,
+    ME_SENSOR::MULTI_SENSOR,
+];
+
+/// Every error code of the bindings, kept complete by `listed_index` in the tests
+const ERR_CODES: [ERR_CODE; 28] = [
+    ERR_CODE::ERR_NOERROR,
+    ERR_CODE::ERR_FUNCTION_NOT_SUPPORTED,
+    ERR_CODE::ERR_CANNOT_OPEN,
+    ERR_CODE::ERR_NOT_OPEN,
+    ERR_CODE::ERR_APPLYING_PARAMS,
+    ERR_CODE::ERR_SEND_CMD_TO_SENSOR,
+    ERR_CODE::ERR_CLEARING_BUFFER,
+    ERR_CODE::ERR_HW_COMMUNICATION,
+    ERR_CODE::ERR_TIMEOUT_READING_FROM_SENSOR,
+    ERR_CODE::ERR_READING_SENSOR_DATA,
+    ERR_CODE::ERR_INTERFACE_NOT_SUPPORTED,
+    ERR_CODE::ERR_ALREADY_OPEN,
+    ERR_CODE::ERR_CANNOT_CREATE_INTERFACE,
+    ERR_CODE::ERR_NO_SENSORDATA_AVAILABLE,
+    ERR_CODE::ERR_UNKNOWN_SENSOR_COMMAND,
+    ERR_CODE::ERR_UNKNOWN_SENSOR_ANSWER,
+    ERR_CODE::ERR_SENSOR_ANSWER_ERROR,
+    ERR_CODE::ERR_SENSOR_ANSWER_TOO_SHORT,
+    ERR_CODE::ERR_WRONG_PARAMETER,
+    ERR_CODE::ERR_NOMEMORY,
+    ERR_CODE::ERR_NO_ANSWER_RECEIVED,
+    ERR_CODE::ERR_SENSOR_ANSWER_DOES_NOT_MATCH_COMMAND,
+    ERR_CODE::ERR_BAUDRATE_TOO_LOW,
+    ERR_CODE::ERR_OVERFLOW,
+    ERR_CODE::ERR_INSTANCE_NOT_EXIST,
+    ERR_CODE::ERR_NOT_FOUND,
+    ERR_CODE::ERR_WARNING,
+    ERR_CODE::ERR_SENSOR_ANSWER_WARNING,
+];
+
+const INTERFACES: [Interface; 7] = [
+    Interface::RS232,
+    Interface::If2004Usb,
+    Interface::If2008,
+    Interface::If2008Eth,
+    Interface::TcpIp,
+    Interface::WinUSB,
+    Interface::Simulated,
+];
+
+/// Name of `sensor_type` without the `SENSOR_` prefix, e.g. `IFD2421`
+pub(crate) fn short_name(sensor_type: ME_SENSOR) -> String {
+    let name = format!("{sensor_type:?}");
+    match name.strip_prefix("SENSOR_") {
+        Some(short) => short.into(),
+        None => name,
+    }
+}
+
+impl FromStr for ME_SENSOR {
+    type Err = MedaqError;
+
+    /// Sensor type by its name ignoring case, e.g. `SENSOR_IFD2421` or `ifd2421`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SENSOR_TYPES
+            .into_iter()
+            .find(|sensor_type| {
+                format!("{sensor_type:?}").eq_ignore_ascii_case(s)
+                    || short_name(*sensor_type).eq_ignore_ascii_case(s)
+            })
+            .ok_or_else(|| MedaqError::UnknownSensorType(s.into()))
+    }
+}
+
+impl FromStr for Interface {
+    type Err = MedaqError;
+
+    /// Interface by its MEDAQLib or variant name ignoring case, e.g. `TCP/IP` or `TcpIp`,
+    /// or `tcp`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        INTERFACES
+            .into_iter()
+            .find(|interface| {
+                interface.to_string().eq_ignore_ascii_case(s)
+                    || format!("{interface:?}").eq_ignore_ascii_case(s)
+                    || matches!(interface, Interface::TcpIp) && s.eq_ignore_ascii_case("tcp")
+            })
+            .ok_or_else(|| MedaqError::UnknownInterface(s.into()))
+    }
+}
+
+/// Error code by its name, e.g. `ERR_NOT_OPEN`
+pub(crate) fn err_code(name: &str) -> Option<ERR_CODE> {
+    ERR_CODES
+        .into_iter()
+        .find(|err_code| format!("{err_code:?}") == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{ERR_CODES, SENSOR_TYPES};
+    use crate::{Interface, MedaqError, ERR_CODE, ME_SENSOR};
+
+    /// Position of the code in `ERR_CODES`, stops compiling when the bindings gain
+    /// a code because the match has no wildcard arm
+    fn listed_index(err_code: ERR_CODE) -> usize {
+        match err_code {
+            ERR_CODE::ERR_NOERROR => 0,
+            ERR_CODE::ERR_FUNCTION_NOT_SUPPORTED => 1,
+            ERR_CODE::ERR_CANNOT_OPEN => 2,
+            ERR_CODE::ERR_NOT_OPEN => 3,
+            ERR_CODE::ERR_APPLYING_PARAMS => 4,
+            ERR_CODE::ERR_SEND_CMD_TO_SENSOR => 5,
+            ERR_CODE::ERR_CLEARING_BUFFER => 6,
+            ERR_CODE::ERR_HW_COMMUNICATION => 7,
+            ERR_CODE::ERR_TIMEOUT_READING_FROM_SENSOR => 8,
+            ERR_CODE::ERR_READING_SENSOR_DATA => 9,
+            ERR_CODE::ERR_INTERFACE_NOT_SUPPORTED => 10,
+            ERR_CODE::ERR_ALREADY_OPEN => 11,
+            ERR_CODE::ERR_CANNOT_CREATE_INTERFACE => 12,
+            ERR_CODE::ERR_NO_SENSORDATA_AVAILABLE => 13,
+            ERR_CODE::ERR_UNKNOWN_SENSOR_COMMAND => 14,
+            ERR_CODE::ERR_UNKNOWN_SENSOR_ANSWER => 15,
+            ERR_CODE::ERR_SENSOR_ANSWER_ERROR => 16,
+            ERR_CODE::ERR_SENSOR_ANSWER_TOO_SHORT => 17,
+            ERR_CODE::ERR_WRONG_PARAMETER => 18,
+            ERR_CODE::ERR_NOMEMORY => 19,
+            ERR_CODE::ERR_NO_ANSWER_RECEIVED => 20,
+            ERR_CODE::ERR_SENSOR_ANSWER_DOES_NOT_MATCH_COMMAND => 21,
+            ERR_CODE::ERR_BAUDRATE_TOO_LOW => 22,
+            ERR_CODE::ERR_OVERFLOW => 23,
+            ERR_CODE::ERR_INSTANCE_NOT_EXIST => 24,
+            ERR_CODE::ERR_NOT_FOUND => 25,
+            ERR_CODE::ERR_WARNING => 26,
+            ERR_CODE::ERR_SENSOR_ANSWER_WARNING => 27,
+        }
+    }
+
+    #[test]
+    fn test_sensor_type_from_str_test() {
+        assert_eq!(
+            "SENSOR_IFD2421".parse::<ME_SENSOR>().unwrap(),
+            ME_SENSOR::SENSOR_IFD2421
+        );
+        assert_eq!(
+            "ild1320".parse::<ME_SENSOR>().unwrap(),
+            ME_SENSOR::SENSOR_ILD1320
+        );
+        assert_eq!(
+            "CONTROLLER_DT6200".parse::<ME_SENSOR>().unwrap(),
+            ME_SENSOR::CONTROLLER_DT6200
+        );
+        assert!(matches!(
+            "IFD9999".parse::<ME_SENSOR>(),
+            Err(MedaqError::UnknownSensorType(name)) if name == "IFD9999"
+        ));
+        for sensor_type in SENSOR_TYPES {
+            assert_eq!(
+                format!("{sensor_type:?}").parse::<ME_SENSOR>().unwrap(),
+                sensor_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_interface_from_str_test() {
+        assert!(matches!("TCP/IP".parse(), Ok(Interface::TcpIp)));
+        assert!(matches!("tcpip".parse(), Ok(Interface::TcpIp)));
+        assert!(matches!("TCP".parse(), Ok(Interface::TcpIp)));
+        assert!(matches!("IF2004_USB".parse(), Ok(Interface::If2004Usb)));
+        assert!(matches!("simulated".parse(), Ok(Interface::Simulated)));
+        assert!(matches!(
+            "USB".parse::<Interface>(),
+            Err(MedaqError::UnknownInterface(_))
+        ));
+    }
+
+    #[test]
+    fn test_err_code_by_name_test() {
+        for err_code in ERR_CODES {
+            assert_eq!(super::err_code(&format!("{err_code:?}")), Some(err_code));
+        }
+        assert_eq!(super::err_code("ERR_SOMETHING"), None);
+    }
+
+    #[test]
+    fn test_err_codes_complete_test() {
+        for (index, err_code) in ERR_CODES.into_iter().enumerate() {
+            assert_eq!(listed_index(err_code), index, "{err_code:?} listed twice");
+            // the header numbers the codes downwards from 0
+            assert_eq!(err_code as i32, -(index as i32), "{err_code:?}");
+        }
+        let listed = format!("{ERR_CODES:?}");
+        assert_eq!(listed.matches("ERR_").count(), ERR_CODES.len());
+
+        // every code has its own message naming it
+        let messages: HashSet<_> = ERR_CODES.iter().map(ERR_CODE::to_string).collect();
+        assert_eq!(messages.len(), ERR_CODES.len());
+        for err_code in ERR_CODES {
+            let message = MedaqError::Sensor(err_code).to_string();
+            assert_eq!(message, format!("sensor error: {err_code:?}"));
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{Data, Value, SCALED_OUT_OF_RANGE};
+
+/// Rule for rejecting outliers with [`Data::filter_outliers`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutlierPolicy {
+    /// Reject values outside `(min, max)`, one range per channel in channel order.
+    /// Channels without a range are not filtered.
+    Absolute(Vec<(f64, f64)>),
+    /// Reject values deviating from the channel median by more than `threshold`
+    /// standard deviations, estimated robustly from the median absolute deviation
+    Mad { threshold: f64 },
+}
+
+/// Factor to estimate the standard deviation of normal distributed values from the MAD
+const MAD_TO_SIGMA: f64 = 1.4826;
+
+impl Data {
+    /// Mark outliers in the scaled values as out of range
+    ///
+    /// Rejected samples are not removed, so frames stay aligned. Raw values are
+    /// left untouched. Returns the filtered capture and the number of rejected
+    /// samples per channel in channel order.
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Data, OutlierPolicy, Value};
+    ///
+    /// let data = Data::new(vec!["Distance".to_string()], vec![], vec![1.0, 1.5, 50.0, 0.5]);
+    /// let (filtered, rejected) = data.filter_outliers(&OutlierPolicy::Mad { threshold: 3.0 });
+    /// assert_eq!(rejected, vec![1]);
+    /// assert_eq!(filtered.get_mean_scaled()[0].value, Value::Valid(1.0));
+    /// ```
+    pub fn filter_outliers(&self, policy: &OutlierPolicy) -> (Data, Vec<usize>) {
+        let channels = self.channels().len();
+        let mut scaled_data = self.scaled_data.clone();
+        let mut rejected = vec![0; channels];
+
+        for (channel, rejected) in rejected.iter_mut().enumerate() {
+            let Some((min, max)) = accepted_range(&self.scaled_data, channels, channel, policy)
+            else {
+                continue;
+            };
+            for value in scaled_data.iter_mut().skip(channel).step_by(channels) {
+                if let Value::Valid(v) = Value::from_sample(*value) {
+                    if v < min || v > max {
+                        *value = SCALED_OUT_OF_RANGE;
+                        *rejected += 1;
+                    }
+                }
+            }
+        }
+
+        (
+            self.with_values(self.raw_data.clone(), scaled_data),
+            rejected,
+        )
+    }
+}
+
+/// Range of accepted values for the channel, `None` if the channel is not filtered
+fn accepted_range(
+    values: &[f64],
+    channels: usize,
+    channel: usize,
+    policy: &OutlierPolicy,
+) -> Option<(f64, f64)> {
+    match policy {
+        OutlierPolicy::Absolute(ranges) => ranges.get(channel).copied(),
+        OutlierPolicy::Mad { threshold } => {
+            let mut valid: Vec<f64> = values
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .filter_map(|&v| Value::from_sample(v).into_raw())
+                .collect();
+            let center = median(&mut valid)?;
+            let mut deviations: Vec<f64> = valid.iter().map(|v| (v - center).abs()).collect();
+            let sigma = median(&mut deviations)? * MAD_TO_SIGMA;
+            let tolerance = threshold * sigma;
+            Some((center - tolerance, center + tolerance))
+        }
+    }
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / 2.)
+    } else {
+        Some(values[mid])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Data, DataBuilder, OutlierPolicy, SCALED_OUT_OF_RANGE};
+
+    fn data() -> Data {
+        DataBuilder::channels(["1", "2"])
+            .raw_frame([1, 2], [10., 1.])
+            .raw_frame([3, 4], [10.2, 1.1])
+            .raw_frame([5, 6], [95., 0.9])
+            .raw_frame([7, 8], [9.9, SCALED_OUT_OF_RANGE])
+            .raw_frame([9, 10], [10.1, 1.])
+            .build()
+    }
+
+    #[test]
+    fn test_filter_outliers_absolute_test() {
+        let data = data();
+        let (filtered, rejected) =
+            data.filter_outliers(&OutlierPolicy::Absolute(vec![(0., 20.), (0.95, 2.)]));
+
+        assert_eq!(rejected, vec![1, 1]);
+        assert_eq!(
+            filtered.scaled_data,
+            vec![
+                10.,
+                1.,
+                10.2,
+                1.1,
+                SCALED_OUT_OF_RANGE,
+                SCALED_OUT_OF_RANGE,
+                9.9,
+                SCALED_OUT_OF_RANGE,
+                10.1,
+                1.,
+            ]
+        );
+        assert_eq!(filtered.raw_data, data.raw_data);
+    }
+
+    #[test]
+    fn test_filter_outliers_absolute_missing_range_test() {
+        let (filtered, rejected) =
+            data().filter_outliers(&OutlierPolicy::Absolute(vec![(0., 20.)]));
+        assert_eq!(rejected, vec![1, 0]);
+        assert_eq!(filtered.scaled_data[5], 0.9);
+    }
+
+    #[test]
+    fn test_filter_outliers_mad_test() {
+        let (filtered, rejected) = data().filter_outliers(&OutlierPolicy::Mad { threshold: 3. });
+
+        assert_eq!(rejected, vec![1, 0]);
+        assert_eq!(filtered.scaled_data[4], SCALED_OUT_OF_RANGE);
+        assert_eq!(filtered.scaled_data.len(), data().scaled_data.len());
+    }
+
+    #[test]
+    fn test_filter_outliers_mad_all_invalid_channel_test() {
+        let data = Data::new(
+            vec!["1".to_string()],
+            vec![],
+            vec![SCALED_OUT_OF_RANGE, SCALED_OUT_OF_RANGE],
+        );
+        let (filtered, rejected) = data.filter_outliers(&OutlierPolicy::Mad { threshold: 3. });
+        assert_eq!(rejected, vec![0]);
+        assert_eq!(filtered.scaled_data, data.scaled_data);
+    }
+}
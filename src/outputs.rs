@@ -0,0 +1,450 @@
+use crate::{MedaqError, Sensor};
+
+/// Signal of an analog output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnalogRange {
+    /// 4 mA to 20 mA
+    Current4To20mA,
+    /// 0 V to 5 V
+    Voltage0To5V,
+    /// 0 V to 10 V
+    Voltage0To10V,
+    /// -5 V to 5 V
+    VoltagePlusMinus5V,
+    /// -10 V to 10 V
+    VoltagePlusMinus10V,
+}
+
+/// Measurements mapped to the signal range of an analog output
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnalogScaling {
+    /// The measuring range of the sensor
+    Standard,
+    /// `start` at the beginning and `end` at the end of the signal range, in the
+    /// unit of the channel, `end` may be below `start` to invert the output
+    TwoPoint { start: f64, end: f64 },
+}
+
+/// Analog output, see [`Sensor::configure_analog_output`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalogOutConfig {
+    pub range: AnalogRange,
+    /// The channel mirrored, counted from `1`
+    pub channel: usize,
+    pub scaling: AnalogScaling,
+}
+
+/// Level of an active limit switch output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitPolarity {
+    /// High while the limit is exceeded
+    ActiveHigh,
+    /// Low while the limit is exceeded, e.g. for wire break detection
+    ActiveLow,
+}
+
+/// Digital limit switch output, see [`Sensor::configure_limit_switch`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LimitConfig {
+    /// Switching point, in the unit of the measurement
+    pub threshold: f64,
+    /// Distance below `threshold` the measurement has to fall back to before the
+    /// output switches back, not negative
+    pub hysteresis: f64,
+    pub polarity: LimitPolarity,
+}
+
+/// MEDAQLib values of the analog ranges, by their position
+const ANALOG_RANGES: [AnalogRange; 5] = [
+    AnalogRange::Current4To20mA,
+    AnalogRange::Voltage0To5V,
+    AnalogRange::Voltage0To10V,
+    AnalogRange::VoltagePlusMinus5V,
+    AnalogRange::VoltagePlusMinus10V,
+];
+
+impl Sensor {
+    /// Set the analog output with `Set_AnalogOutput`
+    ///
+    /// Sets `SP_AnalogOutputChannel`, `SP_AnalogOutputRange` and
+    /// `SP_AnalogScalingMode`, with `SP_AnalogScalingStart` and `SP_AnalogScalingEnd`
+    /// for a two point scaling. Fails with [`MedaqError::Unsupported`] if the sensor
+    /// has no analog output, see [`Capabilities`](crate::Capabilities), with
+    /// [`MedaqError::ChannelOutOfRange`] for a channel it does not have and with
+    /// [`MedaqError::InvalidSetting`] for a scaling range that is empty or not
+    /// finite, before calling MEDAQLib.
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{
+    ///     AnalogOutConfig, AnalogRange, AnalogScaling, Interface, MockBackend, SensorBuilder,
+    ///     ME_SENSOR,
+    /// };
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1420)
+    ///     .with_interface(Interface::Simulated)
+    ///     .with_backend(MockBackend::new().with_channels(["Distance"]))
+    ///     .connect()
+    ///     .unwrap();
+    /// sensor
+    ///     .configure_analog_output(AnalogOutConfig {
+    ///         range: AnalogRange::Current4To20mA,
+    ///         channel: 1,
+    ///         scaling: AnalogScaling::TwoPoint { start: 2., end: 8. },
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn configure_analog_output(&self, config: AnalogOutConfig) -> Result<(), MedaqError> {
+        self.check_analog_output()?;
+        self.check_output_channel(config.channel)?;
+        if let AnalogScaling::TwoPoint { start, end } = config.scaling {
+            if !start.is_finite() || !end.is_finite() || start == end {
+                return Err(MedaqError::InvalidSetting {
+                    setting: "analog scaling".into(),
+                    reason: format!("{start} to {end} is no range"),
+                });
+            }
+        }
+
+        // every range is listed
+        let range = ANALOG_RANGES
+            .iter()
+            .position(|range| *range == config.range)
+            .unwrap_or_default();
+        // checked against the channels
+        let _instance = self.hold()?;
+        self.set_parameter_int("SP_AnalogOutputChannel", config.channel as i32)?;
+        self.set_parameter_int("SP_AnalogOutputRange", range as i32)?;
+        match config.scaling {
+            AnalogScaling::Standard => self.set_parameter_int("SP_AnalogScalingMode", 0)?,
+            AnalogScaling::TwoPoint { start, end } => {
+                self.set_parameter_int("SP_AnalogScalingMode", 1)?;
+                self.set_parameter_double("SP_AnalogScalingStart", start)?;
+                self.set_parameter_double("SP_AnalogScalingEnd", end)?;
+            }
+        }
+        self.execute_command("Set_AnalogOutput")
+    }
+
+    /// The analog output as reported by `Get_AnalogOutput`, see
+    /// [`configure_analog_output`](Self::configure_analog_output)
+    pub fn analog_output(&self) -> Result<AnalogOutConfig, MedaqError> {
+        self.check_analog_output()?;
+        let _instance = self.hold()?;
+        self.execute_command("Get_AnalogOutput")?;
+        let channel = self.answer("SA_AnalogOutputChannel")?;
+        let range: usize = self.answer("SA_AnalogOutputRange")?;
+        let range = *ANALOG_RANGES
+            .get(range)
+            .ok_or_else(|| invalid_answer("SA_AnalogOutputRange", range))?;
+        let scaling = match self.answer("SA_AnalogScalingMode")? {
+            0 => AnalogScaling::Standard,
+            1 => AnalogScaling::TwoPoint {
+                start: self.answer("SA_AnalogScalingStart")?,
+                end: self.answer("SA_AnalogScalingEnd")?,
+            },
+            mode => return Err(invalid_answer("SA_AnalogScalingMode", mode)),
+        };
+        Ok(AnalogOutConfig {
+            range,
+            channel,
+            scaling,
+        })
+    }
+
+    /// Set limit switch output `switch`, counted from `1`, with `Set_LimitSwitch`
+    ///
+    /// Sets `SP_LimitSwitch`, `SP_LimitThreshold`, `SP_LimitHysteresis` and
+    /// `SP_LimitPolarity`. Fails with [`MedaqError::Unsupported`] beyond the limit
+    /// switches of the sensor, see [`Capabilities`](crate::Capabilities), and with
+    /// [`MedaqError::InvalidSetting`] for a threshold that is not finite or a
+    /// negative hysteresis, before calling MEDAQLib.
+    pub fn configure_limit_switch(
+        &self,
+        switch: usize,
+        config: LimitConfig,
+    ) -> Result<(), MedaqError> {
+        self.check_limit_switch(switch)?;
+        let invalid = |setting: &str, reason: String| MedaqError::InvalidSetting {
+            setting: setting.into(),
+            reason,
+        };
+        if !config.threshold.is_finite() {
+            let reason = format!("{} is not finite", config.threshold);
+            return Err(invalid("limit threshold", reason));
+        }
+        if !(config.hysteresis >= 0. && config.hysteresis.is_finite()) {
+            let reason = format!("{} is not a finite distance", config.hysteresis);
+            return Err(invalid("limit hysteresis", reason));
+        }
+
+        let polarity = match config.polarity {
+            LimitPolarity::ActiveHigh => 0,
+            LimitPolarity::ActiveLow => 1,
+        };
+        let _instance = self.hold()?;
+        self.set_parameter_int("SP_LimitSwitch", switch as i32)?;
+        self.set_parameter_double("SP_LimitThreshold", config.threshold)?;
+        self.set_parameter_double("SP_LimitHysteresis", config.hysteresis)?;
+        self.set_parameter_int("SP_LimitPolarity", polarity)?;
+        self.execute_command("Set_LimitSwitch")
+    }
+
+    /// Limit switch output `switch` as reported by `Get_LimitSwitch`, see
+    /// [`configure_limit_switch`](Self::configure_limit_switch)
+    pub fn limit_switch(&self, switch: usize) -> Result<LimitConfig, MedaqError> {
+        self.check_limit_switch(switch)?;
+        let _instance = self.hold()?;
+        self.set_parameter_int("SP_LimitSwitch", switch as i32)?;
+        self.execute_command("Get_LimitSwitch")?;
+        let polarity = match self.answer("SA_LimitPolarity")? {
+            0 => LimitPolarity::ActiveHigh,
+            1 => LimitPolarity::ActiveLow,
+            polarity => return Err(invalid_answer("SA_LimitPolarity", polarity)),
+        };
+        Ok(LimitConfig {
+            threshold: self.answer("SA_LimitThreshold")?,
+            hysteresis: self.answer("SA_LimitHysteresis")?,
+            polarity,
+        })
+    }
+
+    fn check_analog_output(&self) -> Result<(), MedaqError> {
+        match self.capabilities().supports_analog_output {
+            true => Ok(()),
+            false => Err(MedaqError::Unsupported {
+                sensor_type: self.sensor_type(),
+                setting: "analog output".into(),
+            }),
+        }
+    }
+
+    fn check_output_channel(&self, channel: usize) -> Result<(), MedaqError> {
+        let channels = self.capabilities().max_channels;
+        match (1..=channels).contains(&channel) {
+            true => Ok(()),
+            false => Err(MedaqError::ChannelOutOfRange { channel, channels }),
+        }
+    }
+
+    fn check_limit_switch(&self, switch: usize) -> Result<(), MedaqError> {
+        match (1..=self.capabilities().limit_switches).contains(&switch) {
+            true => Ok(()),
+            false => Err(MedaqError::Unsupported {
+                sensor_type: self.sensor_type(),
+                setting: format!("limit switch {switch}"),
+            }),
+        }
+    }
+
+    /// An `SA_` parameter of the last command, read as a string as
+    /// `GetParameterInt` and `GetParameterDouble` are optional
+    pub(crate) fn answer<T: std::str::FromStr>(&self, parameter: &str) -> Result<T, MedaqError> {
+        let answer = self.get_parameter_string(parameter)?;
+        answer
+            .trim()
+            .parse()
+            .map_err(|_| invalid_answer(parameter, answer))
+    }
+}
+
+fn invalid_answer(parameter: &str, answer: impl ToString) -> MedaqError {
+    MedaqError::InvalidAnswer {
+        parameter: parameter.into(),
+        answer: answer.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnalogOutConfig, AnalogRange, AnalogScaling, LimitConfig, LimitPolarity};
+    use crate::{Call, MedaqError, MockBackend, ME_SENSOR};
+
+    /// Parameters set and commands executed after connecting
+    fn settings(mock: &MockBackend, connected: usize) -> Vec<String> {
+        let settings = [
+            Call::SetParameterInt,
+            Call::SetParameterDouble,
+            Call::ExecSCmd,
+        ];
+        mock.calls()[connected..]
+            .iter()
+            .filter(|call| settings.contains(&call.call))
+            .map(|call| call.arguments.join("="))
+            .collect()
+    }
+
+    #[test]
+    fn test_analog_output_test() {
+        let mock = MockBackend::new().with_channels(["Distance1", "Distance2"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_IFD2465);
+        let connected = mock.calls().len();
+        sensor
+            .configure_analog_output(AnalogOutConfig {
+                range: AnalogRange::Voltage0To10V,
+                channel: 2,
+                scaling: AnalogScaling::Standard,
+            })
+            .unwrap();
+        let scaled = mock.calls().len();
+        sensor
+            .configure_analog_output(AnalogOutConfig {
+                range: AnalogRange::Current4To20mA,
+                channel: 1,
+                scaling: AnalogScaling::TwoPoint {
+                    start: 2.5,
+                    end: -1.5,
+                },
+            })
+            .unwrap();
+        assert_eq!(
+            settings(&mock, connected)[..4],
+            [
+                "SP_AnalogOutputChannel=2",
+                "SP_AnalogOutputRange=2",
+                "SP_AnalogScalingMode=0",
+                "Set_AnalogOutput",
+            ]
+        );
+        assert_eq!(
+            settings(&mock, scaled),
+            [
+                "SP_AnalogOutputChannel=1",
+                "SP_AnalogOutputRange=0",
+                "SP_AnalogScalingMode=1",
+                "SP_AnalogScalingStart=2.5",
+                "SP_AnalogScalingEnd=-1.5",
+                "Set_AnalogOutput",
+            ]
+        );
+
+        // the values set are those answered by the mock
+        let mock = mock
+            .with_parameter_string("SA_AnalogOutputChannel", "1")
+            .with_parameter_string("SA_AnalogOutputRange", "0")
+            .with_parameter_string("SA_AnalogScalingMode", "1")
+            .with_parameter_double("SA_AnalogScalingStart", 2.5)
+            .with_parameter_double("SA_AnalogScalingEnd", -1.5);
+        assert_eq!(
+            sensor.analog_output().unwrap(),
+            AnalogOutConfig {
+                range: AnalogRange::Current4To20mA,
+                channel: 1,
+                scaling: AnalogScaling::TwoPoint {
+                    start: 2.5,
+                    end: -1.5
+                },
+            }
+        );
+        assert_eq!(mock.commands().last().unwrap(), "Get_AnalogOutput");
+
+        let mock = mock.with_parameter_string("SA_AnalogOutputRange", "7");
+        assert!(matches!(
+            sensor.analog_output(),
+            Err(MedaqError::InvalidAnswer { answer, .. }) if answer == "7"
+        ));
+        let commands = mock.commands();
+        let reads = commands
+            .iter()
+            .filter(|command| *command == "Get_AnalogOutput");
+        assert_eq!(reads.count(), 2);
+    }
+
+    #[test]
+    fn test_analog_output_validation_test() {
+        let mock = MockBackend::new().with_channels(["Distance1", "Distance2"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_IFD2465);
+        let config = |channel, scaling| AnalogOutConfig {
+            range: AnalogRange::VoltagePlusMinus5V,
+            channel,
+            scaling,
+        };
+        assert!(matches!(
+            sensor.configure_analog_output(config(3, AnalogScaling::Standard)),
+            Err(MedaqError::ChannelOutOfRange {
+                channel: 3,
+                channels: 2
+            })
+        ));
+        for (start, end) in [(1., 1.), (0., f64::NAN), (f64::INFINITY, 1.)] {
+            let scaling = AnalogScaling::TwoPoint { start, end };
+            assert!(matches!(
+                sensor.configure_analog_output(config(1, scaling)),
+                Err(MedaqError::InvalidSetting { .. })
+            ));
+        }
+
+        let mock = MockBackend::new().with_channels(["Channel 1"]);
+        let sensor = mock.connect(ME_SENSOR::CONTROLLER_DT6200);
+        let err = sensor
+            .configure_analog_output(config(1, AnalogScaling::Standard))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "analog output is not supported by the CONTROLLER_DT6200"
+        );
+        assert!(sensor.analog_output().is_err());
+        assert_eq!(mock.commands(), ["Get_TransmittedDataInfo"]);
+    }
+
+    #[test]
+    fn test_limit_switch_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1900);
+        let connected = mock.calls().len();
+        let config = LimitConfig {
+            threshold: 12.5,
+            hysteresis: 0.25,
+            polarity: LimitPolarity::ActiveLow,
+        };
+        sensor.configure_limit_switch(2, config).unwrap();
+        assert_eq!(
+            settings(&mock, connected),
+            [
+                "SP_LimitSwitch=2",
+                "SP_LimitThreshold=12.5",
+                "SP_LimitHysteresis=0.25",
+                "SP_LimitPolarity=1",
+                "Set_LimitSwitch",
+            ]
+        );
+
+        let mock = mock
+            .with_parameter_double("SA_LimitThreshold", 12.5)
+            .with_parameter_double("SA_LimitHysteresis", 0.25)
+            .with_parameter_int("SA_LimitPolarity", 1);
+        let read = mock.calls().len();
+        assert_eq!(sensor.limit_switch(2).unwrap(), config);
+        assert_eq!(
+            settings(&mock, read),
+            ["SP_LimitSwitch=2", "Get_LimitSwitch"]
+        );
+
+        for switch in [0, 3] {
+            assert!(matches!(
+                sensor.configure_limit_switch(switch, config),
+                Err(MedaqError::Unsupported { .. })
+            ));
+        }
+        for (threshold, hysteresis) in [(f64::NAN, 0.), (1., -0.5), (1., f64::INFINITY)] {
+            let config = LimitConfig {
+                threshold,
+                hysteresis,
+                ..config
+            };
+            assert!(matches!(
+                sensor.configure_limit_switch(1, config),
+                Err(MedaqError::InvalidSetting { .. })
+            ));
+        }
+        assert_eq!(mock.commands().last().unwrap(), "Get_LimitSwitch");
+
+        // no limit switches at all
+        let mock = MockBackend::new().with_channels(["Channel 1"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_DT6120);
+        assert!(matches!(
+            sensor.limit_switch(1),
+            Err(MedaqError::Unsupported { .. })
+        ));
+    }
+}
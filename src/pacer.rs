@@ -0,0 +1,238 @@
+use std::time::{Duration, Instant};
+
+use crate::{Sensor, BLOCKING_POLL_INTERVAL};
+
+/// Weight of the newest rate sample in the smoothed rate
+const SMOOTHING: f64 = 0.3;
+
+/// Goals and bounds of a [`Pacer`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacerConfig {
+    /// Number of frames a read should pick up at the observed rate
+    pub frames_per_read: usize,
+    /// Longest time frames may wait in the driver, caps the interval
+    pub max_latency: Option<Duration>,
+    /// Shortest interval between polls
+    pub min_interval: Duration,
+    /// Longest interval between polls, used while no frames arrive
+    pub max_interval: Duration,
+}
+
+impl Default for PacerConfig {
+    fn default() -> Self {
+        Self {
+            frames_per_read: 100,
+            max_latency: Some(Duration::from_millis(10)),
+            min_interval: Duration::from_micros(100),
+            max_interval: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Adapts the pause between polls to the data rate of a sensor
+///
+/// The pacer measures the frames arriving per poll and chooses the interval that
+/// picks up [`PacerConfig::frames_per_read`] frames per read, capped by
+/// [`PacerConfig::max_latency`] and clamped to the configured bounds. It starts
+/// at the shortest allowed interval.
+///
+/// # Example
+/// ```no_run
+/// use medaqlib::{Interface, Pacer, PacerConfig, SensorBuilder, ME_SENSOR};
+///
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::TcpIp)
+///     .with_ip_address("10.10.10.10")
+///     .connect()
+///     .unwrap();
+///
+/// let mut pacer = Pacer::new(PacerConfig::default());
+/// loop {
+///     let data = sensor.read_data().unwrap();
+///     pacer.record_poll(data.as_ref().map_or(0, |data| data.frame_count()));
+///     if let Some(data) = data {
+///         println!("{data}");
+///     }
+///     pacer.sleep();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    config: PacerConfig,
+    interval: Duration,
+    /// Smoothed frames per second
+    rate: Option<f64>,
+    last_poll: Option<Instant>,
+}
+
+impl Pacer {
+    pub fn new(config: PacerConfig) -> Self {
+        let mut pacer = Self {
+            config,
+            interval: Duration::ZERO,
+            rate: None,
+            last_poll: None,
+        };
+        pacer.interval = pacer.target_interval();
+        pacer
+    }
+
+    pub fn config(&self) -> &PacerConfig {
+        &self.config
+    }
+
+    /// Current pause between polls
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Smoothed data rate in frames per second, `None` before the second poll
+    pub fn observed_rate(&self) -> Option<f64> {
+        self.rate
+    }
+
+    /// Record the frames returned by a poll made now
+    pub fn record_poll(&mut self, frames: usize) {
+        let now = Instant::now();
+        if let Some(last_poll) = self.last_poll.replace(now) {
+            self.record(frames, now.duration_since(last_poll));
+        }
+    }
+
+    /// Record `frames` arriving within `elapsed` since the previous poll
+    pub fn record(&mut self, frames: usize, elapsed: Duration) {
+        if elapsed.is_zero() {
+            return;
+        }
+        let sample = frames as f64 / elapsed.as_secs_f64();
+        self.rate = Some(match self.rate {
+            Some(rate) => rate + SMOOTHING * (sample - rate),
+            None => sample,
+        });
+        self.interval = self.target_interval();
+    }
+
+    /// Sleep for the current interval
+    pub fn sleep(&self) {
+        std::thread::sleep(self.interval);
+    }
+
+    fn target_interval(&self) -> Duration {
+        let config = &self.config;
+        let interval = match self.rate {
+            None => config.min_interval,
+            Some(rate) if rate <= 0. => config.max_interval,
+            Some(rate) => {
+                let seconds =
+                    (config.frames_per_read as f64 / rate).min(config.max_interval.as_secs_f64());
+                Duration::from_secs_f64(seconds)
+            }
+        };
+        let interval = config
+            .max_latency
+            .map_or(interval, |latency| interval.min(latency));
+        interval.clamp(
+            config.min_interval,
+            config.max_interval.max(config.min_interval),
+        )
+    }
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Self::new(PacerConfig::default())
+    }
+}
+
+impl Sensor {
+    /// Set the pacing of [`read_data_blocking`](Self::read_data_blocking) and
+    /// [`read_data_cancellable`](Self::read_data_cancellable)
+    ///
+    /// By default polls are at most 1 ms apart.
+    pub fn set_pacing(&mut self, config: PacerConfig) {
+        *self.pacer.get_mut() = Pacer::new(config);
+    }
+
+    /// State of the pacer of the blocking reads, for diagnostics
+    pub fn pacer(&self) -> Pacer {
+        self.pacer.borrow().clone()
+    }
+}
+
+/// Pacer of the blocking reads of a new sensor
+pub(crate) fn blocking_pacer() -> Pacer {
+    Pacer::new(PacerConfig {
+        max_latency: Some(BLOCKING_POLL_INTERVAL),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Pacer, PacerConfig};
+
+    /// Polls a source producing `rate` frames per second until the pacer settles
+    fn settle(pacer: &mut Pacer, rate: f64) {
+        for _ in 0..50 {
+            let elapsed = pacer.interval();
+            pacer.record((rate * elapsed.as_secs_f64()).round() as usize, elapsed);
+        }
+    }
+
+    fn assert_near(interval: Duration, expected: Duration) {
+        let error = interval.abs_diff(expected);
+        assert!(error < expected / 20, "{interval:?} not near {expected:?}");
+    }
+
+    #[test]
+    fn test_pacer_adapts_to_rate_test() {
+        let mut pacer = Pacer::new(PacerConfig {
+            frames_per_read: 10,
+            max_latency: None,
+            ..Default::default()
+        });
+        assert_eq!(pacer.interval(), Duration::from_micros(100));
+        assert_eq!(pacer.observed_rate(), None);
+
+        settle(&mut pacer, 1000.);
+        assert_near(pacer.interval(), Duration::from_millis(10));
+        assert!((pacer.observed_rate().unwrap() - 1000.).abs() < 50.);
+
+        settle(&mut pacer, 10_000.);
+        assert_near(pacer.interval(), Duration::from_millis(1));
+
+        // no data, back off to the ceiling
+        settle(&mut pacer, 0.);
+        assert_eq!(pacer.interval(), Duration::from_millis(100));
+
+        // faster than the floor allows
+        settle(&mut pacer, 1e9);
+        assert_eq!(pacer.interval(), Duration::from_micros(100));
+    }
+
+    #[test]
+    fn test_pacer_max_latency_test() {
+        let mut pacer = Pacer::new(PacerConfig {
+            frames_per_read: 100,
+            max_latency: Some(Duration::from_millis(5)),
+            ..Default::default()
+        });
+        settle(&mut pacer, 1000.);
+        assert_eq!(pacer.interval(), Duration::from_millis(5));
+        settle(&mut pacer, 0.);
+        assert_eq!(pacer.interval(), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_pacer_ignores_zero_elapsed_test() {
+        let mut pacer = Pacer::default();
+        pacer.record(10, Duration::ZERO);
+        assert_eq!(pacer.observed_rate(), None);
+        pacer.record_poll(0);
+        std::thread::sleep(Duration::from_millis(1));
+        pacer.record_poll(0);
+        assert_eq!(pacer.observed_rate(), Some(0.));
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    fmt::Display,
+    ops::{Deref, DerefMut},
+    sync::mpsc::{self, SyncSender},
+};
+
+use crate::{
+    acquisition::{self, Acquisition, AcquisitionHandle, Control, Deliver, HandleParts},
+    gap::GapMonitor,
+    sync::Shared,
+    AcquisitionConfig, Data, MedaqError, Sensor,
+};
+
+/// Recycled captures, so an acquisition handing out owned captures does not
+/// allocate once warmed up, see [`Sensor::start_pooled_acquisition`]
+///
+/// Holds at most `capacity` idle captures. Captures returned to a full pool are
+/// dropped and a consumer keeping its captures only makes the pool allocate
+/// again, so the pool never grows beyond `capacity`. Clones share the pool.
+#[derive(Debug, Clone)]
+pub struct DataPool(Shared<PoolState>);
+
+#[derive(Debug)]
+struct PoolState {
+    idle: Vec<Data>,
+    capacity: usize,
+    stats: PoolStats,
+}
+
+/// Use of a [`DataPool`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PoolStats {
+    /// Captures taken from the pool
+    pub hits: u64,
+    /// Captures allocated because the pool was empty
+    pub misses: u64,
+    /// Captures returned for reuse
+    pub returned: u64,
+    /// Captures dropped because the pool was full
+    pub discarded: u64,
+}
+
+impl DataPool {
+    /// Pool holding at most `capacity` idle captures
+    pub fn new(capacity: usize) -> Self {
+        Self(Shared::new(PoolState {
+            idle: Vec::with_capacity(capacity),
+            capacity,
+            stats: PoolStats::default(),
+        }))
+    }
+
+    /// An idle capture, or a new one if there is none, returned to the pool when
+    /// dropped
+    ///
+    /// The capture keeps the channels and values of its last use until it is read
+    /// into, e.g. with [`Sensor::read_into`].
+    pub fn checkout(&self) -> PooledData {
+        PooledData {
+            data: Some(self.take()),
+            pool: self.clone(),
+        }
+    }
+
+    /// Number of idle captures
+    pub fn idle(&self) -> usize {
+        self.0.lock().idle.len()
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        self.0.lock().stats
+    }
+
+    pub(crate) fn take(&self) -> Data {
+        let mut state = self.0.lock();
+        match state.idle.pop() {
+            Some(data) => {
+                state.stats.hits += 1;
+                data
+            }
+            None => {
+                state.stats.misses += 1;
+                drop(state);
+                Data::new(Vec::<String>::new(), vec![], vec![])
+            }
+        }
+    }
+
+    pub(crate) fn put(&self, mut data: Data) {
+        let mut state = self.0.lock();
+        if state.idle.len() >= state.capacity {
+            state.stats.discarded += 1;
+            // freed outside of the lock
+            drop(state);
+            return;
+        }
+        data.span = None;
+        state.stats.returned += 1;
+        state.idle.push(data);
+    }
+}
+
+/// Capture borrowed from a [`DataPool`], returned to it when dropped
+#[derive(Debug)]
+pub struct PooledData {
+    /// Taken when dropped
+    data: Option<Data>,
+    pool: DataPool,
+}
+
+impl PooledData {
+    /// Keep the capture instead of returning it to the pool
+    pub fn into_inner(mut self) -> Data {
+        self.data.take().expect("taken when dropped")
+    }
+}
+
+impl Deref for PooledData {
+    type Target = Data;
+
+    fn deref(&self) -> &Data {
+        self.data.as_ref().expect("taken when dropped")
+    }
+}
+
+impl DerefMut for PooledData {
+    fn deref_mut(&mut self) -> &mut Data {
+        self.data.as_mut().expect("taken when dropped")
+    }
+}
+
+impl Display for PooledData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&**self, f)
+    }
+}
+
+impl Drop for PooledData {
+    fn drop(&mut self) {
+        if let Some(data) = self.data.take() {
+            self.pool.put(data);
+        }
+    }
+}
+
+/// Reads into captures of the pool and sends them to the channel
+struct PoolDeliver {
+    sender: SyncSender<Result<PooledData, MedaqError>>,
+    pool: DataPool,
+}
+
+impl Deliver for PoolDeliver {
+    fn deliver(&mut self, result: Result<Data, MedaqError>, control: &Control) -> bool {
+        let result = result.map(|data| PooledData {
+            data: Some(data),
+            pool: self.pool.clone(),
+        });
+        acquisition::send(&self.sender, result, control)
+    }
+
+    fn pool(&self) -> Option<&DataPool> {
+        Some(&self.pool)
+    }
+}
+
+impl Sensor {
+    /// Like [`start_acquisition`](Self::start_acquisition), but reads into
+    /// captures of `pool` that return to it when the receiver drops them
+    ///
+    /// Once the pool holds as many captures as are in flight, reading and handing
+    /// over a capture does not allocate. [`AcquisitionConfig::ring_buffer`] is
+    /// ignored and batches of [`AcquisitionConfig::aggregate_every`] are
+    /// allocated as usual.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use medaqlib::{AcquisitionConfig, DataPool, Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// let pool = DataPool::new(16);
+    /// let acquisition = sensor.start_pooled_acquisition(AcquisitionConfig::default(), &pool);
+    /// for data in acquisition.receiver().iter().take(100) {
+    ///     // back to the pool at the end of the iteration
+    ///     println!("{}", data.unwrap());
+    /// }
+    /// println!("{:?}", pool.stats());
+    /// ```
+    pub fn start_pooled_acquisition(
+        self,
+        config: AcquisitionConfig,
+        pool: &DataPool,
+    ) -> AcquisitionHandle<PooledData> {
+        let stats = self.stats.clone();
+        let (sender, receiver) = mpsc::sync_channel(config.channel_capacity);
+        let (events_sender, events) = mpsc::sync_channel(config.channel_capacity);
+        let gaps = GapMonitor::new(config.gap_detection.clone(), stats.clone(), events_sender);
+        let parts = HandleParts {
+            receiver,
+            events,
+            ring: None,
+            stats,
+        };
+        let deliver = PoolDeliver {
+            sender,
+            pool: pool.clone(),
+        };
+        let acquisition = Acquisition::start_monitored(self, config, deliver, gaps);
+        AcquisitionHandle { acquisition, parts }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{DataPool, PoolStats};
+    use crate::{AcquisitionConfig, Data, MockBackend, ME_SENSOR};
+
+    #[test]
+    fn test_pool_test() {
+        let pool = DataPool::new(1);
+        let mut first = pool.checkout();
+        *first = Data::new(vec!["Distance".to_string()], vec![1], vec![0.5]);
+        let second = pool.checkout();
+        drop(first);
+        // the pool is full
+        drop(second);
+        assert_eq!(pool.idle(), 1);
+
+        let reused = pool.checkout();
+        assert_eq!(reused.scaled_data, [0.5]);
+        let kept = reused.into_inner();
+        assert_eq!(kept.raw_data, [1]);
+        assert_eq!(pool.idle(), 0);
+        assert_eq!(
+            pool.stats(),
+            PoolStats {
+                hits: 1,
+                misses: 2,
+                returned: 1,
+                discarded: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_pooled_acquisition_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_IFD2421);
+        let pool = DataPool::new(4);
+        let config = AcquisitionConfig {
+            poll_interval: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let acquisition = sensor.start_pooled_acquisition(config, &pool);
+
+        for frame in 0..5 {
+            mock.push_frame(&[frame], &[frame as f64]);
+            let data = acquisition.receiver().recv().unwrap().unwrap();
+            assert_eq!(data.raw_data, [frame]);
+            assert_eq!(data.channels().len(), 1);
+        }
+        acquisition.join();
+
+        // one capture in flight and one polled at most
+        let stats = pool.stats();
+        assert!(stats.misses <= 2, "{stats:?}");
+        assert!(stats.hits >= 3, "{stats:?}");
+        assert_eq!(stats.discarded, 0);
+    }
+}
@@ -0,0 +1,440 @@
+//! Presets, setups of a sensor applied as a whole, e.g. per part program
+//!
+//! With the `serde` feature a [`Preset`] serializes as a list of items, in TOML
+//!
+//! ```toml
+//! [[items]]
+//! parameter = "SP_Measrate"
+//! value = 2.0
+//!
+//! [[items]]
+//! command = "Set_Measrate"
+//! ```
+//!
+//! and a [`PresetStore`] keeps them by name as such files in a directory.
+
+#[cfg(feature = "serde")]
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use crate::{MedaqError, Sensor};
+
+/// Value of a parameter of a [`PresetItem`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum ParameterValue {
+    Int(i32),
+    Double(f64),
+    String(String),
+}
+
+impl ParameterValue {
+    /// Int if the text parses as one, double if it parses as a number, the text
+    /// otherwise
+    fn infer(text: String) -> Self {
+        if let Ok(value) = text.parse() {
+            ParameterValue::Int(value)
+        } else if let Ok(value) = text.parse() {
+            ParameterValue::Double(value)
+        } else {
+            ParameterValue::String(text)
+        }
+    }
+}
+
+/// Step of a [`Preset`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum PresetItem {
+    /// Set a parameter, e.g. the `SP_` parameter of the next command
+    Parameter {
+        parameter: String,
+        value: ParameterValue,
+    },
+    /// Execute a sensor command, see [`Sensor::execute_command`]
+    Command { command: String },
+}
+
+/// Parameters and commands applied in order, see [`Sensor::apply_preset`]
+///
+/// # Example
+/// ```
+/// use medaqlib::{Interface, MockBackend, Preset, SensorBuilder, ME_SENSOR};
+///
+/// let preset = Preset::new()
+///     .with_double("SP_Measrate", 2.)
+///     .with_command("Set_Measrate")
+///     .with_int("SP_LaserPower", 1)
+///     .with_command("Set_LaserPower");
+///
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1420)
+///     .with_interface(Interface::Simulated)
+///     .with_backend(MockBackend::new().with_channels(["Distance"]))
+///     .connect()
+///     .unwrap();
+/// let report = sensor.apply_preset(&preset).unwrap();
+/// assert!(report.is_success());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Preset {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub items: Vec<PresetItem>,
+}
+
+impl Preset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an int parameter
+    pub fn with_int(self, parameter: impl Into<String>, value: i32) -> Self {
+        self.with_parameter(parameter, ParameterValue::Int(value))
+    }
+
+    /// Append a double parameter
+    pub fn with_double(self, parameter: impl Into<String>, value: f64) -> Self {
+        self.with_parameter(parameter, ParameterValue::Double(value))
+    }
+
+    /// Append a string parameter
+    pub fn with_string(self, parameter: impl Into<String>, value: impl Into<String>) -> Self {
+        self.with_parameter(parameter, ParameterValue::String(value.into()))
+    }
+
+    /// Append a sensor command
+    pub fn with_command(mut self, command: impl Into<String>) -> Self {
+        self.items.push(PresetItem::Command {
+            command: command.into(),
+        });
+        self
+    }
+
+    fn with_parameter(mut self, parameter: impl Into<String>, value: ParameterValue) -> Self {
+        self.items.push(PresetItem::Parameter {
+            parameter: parameter.into(),
+            value,
+        });
+        self
+    }
+}
+
+/// Outcome of every item of [`Sensor::apply_preset`], in the order of the preset
+#[derive(Debug)]
+pub struct ApplyReport {
+    pub results: Vec<(PresetItem, Result<(), MedaqError>)>,
+}
+
+impl ApplyReport {
+    /// Whether every item was applied
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|(_, result)| result.is_ok())
+    }
+
+    /// Number of the items applied
+    pub fn applied(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.is_ok())
+            .count()
+    }
+
+    /// The items the sensor refused with their errors
+    pub fn failures(&self) -> impl Iterator<Item = (&PresetItem, &MedaqError)> {
+        self.results
+            .iter()
+            .filter_map(|(item, result)| Some((item, result.as_ref().err()?)))
+    }
+}
+
+impl Sensor {
+    /// Set the parameters and execute the commands of the preset in order
+    ///
+    /// An item refused by the sensor, with [`MedaqError::Sensor`] or
+    /// [`MedaqError::UnsupportedByDll`], is reported and the next item applied
+    /// nevertheless. Other errors stop applying the preset and are returned.
+    pub fn apply_preset(&self, preset: &Preset) -> Result<ApplyReport, MedaqError> {
+        let _instance = self.hold()?;
+        apply(preset, |item| match item {
+            PresetItem::Parameter { parameter, value } => match value {
+                ParameterValue::Int(value) => self.set_parameter_int(parameter, *value),
+                ParameterValue::Double(value) => self.set_parameter_double(parameter, *value),
+                ParameterValue::String(value) => self.set_parameter_string(parameter, value),
+            },
+            PresetItem::Command { command } => self.execute_command(command),
+        })
+    }
+
+    /// A preset of the current values of the parameters, in the given order
+    ///
+    /// Values are read as strings and kept as int if they parse as one, as double
+    /// if they parse as a number and as string otherwise. Parameters of the sensor
+    /// only take effect with their command, append those with
+    /// [`Preset::with_command`].
+    pub fn capture_preset(&self, names: &[&str]) -> Result<Preset, MedaqError> {
+        let _instance = self.hold()?;
+        let mut preset = Preset::new();
+        for name in names {
+            let value = ParameterValue::infer(self.get_parameter_string(name)?);
+            preset = preset.with_parameter(*name, value);
+        }
+        Ok(preset)
+    }
+}
+
+/// Apply the items in order with `apply_item`, see [`Sensor::apply_preset`]
+pub(crate) fn apply(
+    preset: &Preset,
+    mut apply_item: impl FnMut(&PresetItem) -> Result<(), MedaqError>,
+) -> Result<ApplyReport, MedaqError> {
+    let mut results = Vec::with_capacity(preset.items.len());
+    for item in &preset.items {
+        let result = apply_item(item);
+        match result {
+            Ok(()) | Err(MedaqError::Sensor(_) | MedaqError::UnsupportedByDll { .. }) => {
+                results.push((item.clone(), result))
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(ApplyReport { results })
+}
+
+/// Presets kept as `NAME.toml` files in a directory
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone)]
+pub struct PresetStore {
+    dir: PathBuf,
+}
+
+#[cfg(feature = "serde")]
+impl PresetStore {
+    /// Presets in `dir`, which is created by the first [`save`](Self::save)
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Names of the stored presets, sorted, none if the directory does not exist
+    pub fn list(&self) -> io::Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err),
+        };
+        let mut names = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "toml")
+            {
+                if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Read a preset, fails with [`ErrorKind::NotFound`] if there is none of the
+    /// name and with [`ErrorKind::InvalidData`] if it is no valid preset
+    pub fn load(&self, name: &str) -> io::Result<Preset> {
+        let text = fs::read_to_string(self.path(name)?)?;
+        toml::from_str(&text).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+    }
+
+    /// Write a preset, replacing one of the same name
+    pub fn save(&self, name: &str, preset: &Preset) -> io::Result<()> {
+        let path = self.path(name)?;
+        let text =
+            toml::to_string(preset).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+        fs::create_dir_all(&self.dir)?;
+        fs::write(path, text)
+    }
+
+    /// File of a preset, names must not leave the directory
+    fn path(&self, name: &str) -> io::Result<PathBuf> {
+        let invalid = name.is_empty()
+            || name.starts_with('.')
+            || name.contains(['/', '\\', ':'])
+            || name.chars().any(char::is_control);
+        match invalid {
+            true => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid preset name {name:?}"),
+            )),
+            false => Ok(self.dir.join(format!("{name}.toml"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParameterValue, Preset, PresetItem};
+    use crate::{Call, Intercept, MedaqError, MockBackend, ERR_CODE, ME_SENSOR};
+
+    fn preset() -> Preset {
+        Preset::new()
+            .with_double("SP_Measrate", 2.)
+            .with_command("Set_Measrate")
+            .with_string("SP_TriggerMode", "Edge")
+            .with_command("Set_TriggerMode")
+            .with_int("SP_PeakSelection", 3)
+            .with_command("Set_PeakSelection")
+    }
+
+    #[test]
+    fn test_preset_apply_order_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        let connected = mock.calls().len();
+        let report = sensor.apply_preset(&preset()).unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.applied(), 6);
+
+        let settings = [
+            Call::SetParameterInt,
+            Call::SetParameterDouble,
+            Call::SetParameterString,
+            Call::ExecSCmd,
+        ];
+        let calls: Vec<String> = mock.calls()[connected..]
+            .iter()
+            .filter(|call| settings.contains(&call.call))
+            .map(|call| call.arguments.join("="))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                "SP_Measrate=2",
+                "Set_Measrate",
+                "SP_TriggerMode=Edge",
+                "Set_TriggerMode",
+                "SP_PeakSelection=3",
+                "Set_PeakSelection",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_preset_partial_failure_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        mock.intercept(|call| match call.call {
+            Call::ExecSCmd if call.arguments[0] == "Set_TriggerMode" => {
+                Intercept::Fail(ERR_CODE::ERR_WRONG_PARAMETER)
+            }
+            _ => Intercept::Pass,
+        });
+
+        let report = sensor.apply_preset(&preset()).unwrap();
+        assert!(!report.is_success());
+        assert_eq!(report.applied(), 5);
+        let failures: Vec<_> = report.failures().collect();
+        assert!(matches!(
+            failures[..],
+            [(
+                PresetItem::Command { command },
+                MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER)
+            )] if command == "Set_TriggerMode"
+        ));
+        // the items after the failure are applied
+        let commands: Vec<_> = mock
+            .calls()
+            .into_iter()
+            .filter(|call| call.call == Call::ExecSCmd)
+            .map(|call| call.arguments.join("="))
+            .skip(1)
+            .collect();
+        assert_eq!(
+            commands,
+            ["Set_Measrate", "Set_TriggerMode", "Set_PeakSelection"]
+        );
+    }
+
+    #[test]
+    fn test_preset_capture_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance"])
+            .with_parameter_double("SA_Measrate", 2.5)
+            .with_parameter_int("SA_PeakSelection", 3)
+            .with_parameter_string("SA_TriggerMode", "Edge");
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        let preset = sensor
+            .capture_preset(&["SA_PeakSelection", "SA_Measrate", "SA_TriggerMode"])
+            .unwrap();
+        assert_eq!(
+            preset.items,
+            [
+                PresetItem::Parameter {
+                    parameter: "SA_PeakSelection".into(),
+                    value: ParameterValue::Int(3),
+                },
+                PresetItem::Parameter {
+                    parameter: "SA_Measrate".into(),
+                    value: ParameterValue::Double(2.5),
+                },
+                PresetItem::Parameter {
+                    parameter: "SA_TriggerMode".into(),
+                    value: ParameterValue::String("Edge".into()),
+                },
+            ]
+        );
+        assert!(matches!(
+            sensor.capture_preset(&["SA_Unknown"]),
+            Err(MedaqError::Sensor(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_preset_store_test() {
+        use std::{fs, io::ErrorKind, process};
+
+        use super::PresetStore;
+
+        let dir = std::env::temp_dir().join(format!("medaqlib-presets-{}", process::id()));
+        let store = PresetStore::new(&dir);
+        assert!(store.list().unwrap().is_empty());
+
+        store.save("part-b", &preset()).unwrap();
+        store.save("part-a", &Preset::new()).unwrap();
+        assert_eq!(store.list().unwrap(), ["part-a", "part-b"]);
+        assert_eq!(store.load("part-b").unwrap(), preset());
+        assert_eq!(store.load("part-a").unwrap(), Preset::new());
+
+        let text = fs::read_to_string(dir.join("part-b.toml")).unwrap();
+        assert!(text.starts_with("[[items]]\nparameter = \"SP_Measrate\"\nvalue = 2.0\n"));
+
+        assert_eq!(
+            store.load("part-c").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            store.save("../part", &preset()).unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+        fs::write(dir.join("broken.toml"), "items = 1").unwrap();
+        assert_eq!(
+            store.load("broken").unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
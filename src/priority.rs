@@ -0,0 +1,117 @@
+/// Scheduling priority of an acquisition thread, see
+/// [`AcquisitionConfig::priority`](crate::AcquisitionConfig::priority)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadPriority {
+    Min,
+    /// Platform independent priority from 0 to 99
+    Value(u8),
+    Max,
+}
+
+/// Apply priority and affinity to the current thread, failures are logged as
+/// warnings since the acquisition works without them
+pub(crate) fn apply(priority: Option<ThreadPriority>, affinity: Option<&[usize]>) {
+    if let Some(priority) = priority {
+        if let Err(err) = set_priority(priority) {
+            log::warn!("could not set the priority of the acquisition thread: {err}");
+        }
+    }
+    if let Some(cores) = affinity {
+        if let Err(err) = set_affinity(cores) {
+            log::warn!("could not set the affinity of the acquisition thread: {err}");
+        }
+    }
+}
+
+#[cfg(feature = "thread-priority")]
+fn set_priority(priority: ThreadPriority) -> Result<(), String> {
+    use thread_priority::{ThreadPriority as Native, ThreadPriorityValue};
+
+    let priority = match priority {
+        ThreadPriority::Min => Native::Min,
+        ThreadPriority::Value(value) => Native::Crossplatform(
+            ThreadPriorityValue::try_from(value).map_err(|err| err.to_string())?,
+        ),
+        ThreadPriority::Max => Native::Max,
+    };
+    thread_priority::set_current_thread_priority(priority).map_err(|err| format!("{err:?}"))
+}
+
+#[cfg(not(feature = "thread-priority"))]
+fn set_priority(_: ThreadPriority) -> Result<(), String> {
+    Err("the `thread-priority` feature is disabled".into())
+}
+
+/// Bit mask of the given cores
+#[cfg_attr(not(all(feature = "thread-priority", windows)), allow(dead_code))]
+fn core_mask(cores: &[usize]) -> Result<usize, String> {
+    cores
+        .iter()
+        .try_fold(0, |mask, &core| match core < usize::BITS as usize {
+            true => Ok(mask | 1 << core),
+            false => Err(format!("core {core} out of range")),
+        })
+}
+
+#[cfg(all(feature = "thread-priority", windows))]
+fn set_affinity(cores: &[usize]) -> Result<(), String> {
+    use windows_sys::Win32::System::Threading::{GetCurrentThread, SetThreadAffinityMask};
+
+    let mask = core_mask(cores)?;
+    // returns the previous mask, zero on failure
+    match unsafe { SetThreadAffinityMask(GetCurrentThread(), mask) } {
+        0 => Err(std::io::Error::last_os_error().to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(all(feature = "thread-priority", target_os = "linux"))]
+fn set_affinity(cores: &[usize]) -> Result<(), String> {
+    unsafe {
+        let mut set = std::mem::zeroed::<libc::cpu_set_t>();
+        libc::CPU_ZERO(&mut set);
+        for &core in cores {
+            if core >= libc::CPU_SETSIZE as usize {
+                return Err(format!("core {core} out of range"));
+            }
+            libc::CPU_SET(core, &mut set);
+        }
+        match libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) {
+            0 => Ok(()),
+            _ => Err(std::io::Error::last_os_error().to_string()),
+        }
+    }
+}
+
+#[cfg(not(all(feature = "thread-priority", any(windows, target_os = "linux"))))]
+fn set_affinity(_: &[usize]) -> Result<(), String> {
+    match cfg!(feature = "thread-priority") {
+        true => Err("not supported on this platform".into()),
+        false => Err("the `thread-priority` feature is disabled".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::core_mask;
+
+    #[test]
+    fn test_core_mask_test() {
+        assert_eq!(core_mask(&[]), Ok(0));
+        assert_eq!(core_mask(&[0, 2, 3]), Ok(0b1101));
+        assert!(core_mask(&[usize::BITS as usize]).is_err());
+    }
+
+    #[cfg(all(feature = "thread-priority", target_os = "linux"))]
+    #[test]
+    fn test_set_affinity_test() {
+        let result = std::thread::spawn(|| super::set_affinity(&[0]))
+            .join()
+            .unwrap();
+        assert_eq!(result, Ok(()));
+        assert!(std::thread::spawn(|| super::set_affinity(&[1 << 20]))
+            .join()
+            .unwrap()
+            .is_err());
+    }
+}
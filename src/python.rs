@@ -0,0 +1,315 @@
+//! Python bindings of the `python` feature
+//!
+//! Build the extension module with
+//! `PYO3_BUILD_EXTENSION_MODULE=1 cargo rustc --release --lib --features python --crate-type cdylib`
+//! and rename `libmedaqlib.so` to `medaqlib.so` (`medaqlib.dll` to `medaqlib.pyd` on
+//! Windows). Every call into MEDAQLib releases the GIL.
+//!
+//! ```python
+//! import medaqlib
+//!
+//! builder = medaqlib.SensorBuilder(sensor_type="IFD2421", interface="TCP/IP", ip_address="10.10.10.10")
+//! with builder.connect() as sensor:
+//!     data = sensor.read_data(timeout=1.0)
+//! ```
+
+use std::{
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use pyo3::{
+    create_exception,
+    exceptions::PyException,
+    prelude::*,
+    types::{PyDict, PyType},
+};
+
+use crate::{Data, Interface, Sensor, SensorBuilder, Value, ME_SENSOR};
+
+create_exception!(
+    medaqlib,
+    MedaqError,
+    PyException,
+    "Error of the sensor or of its configuration"
+);
+create_exception!(
+    medaqlib,
+    SensorError,
+    MedaqError,
+    "MEDAQLib returned an error code"
+);
+create_exception!(
+    medaqlib,
+    LibraryUnavailable,
+    MedaqError,
+    "MEDAQLib could not be loaded or is too old"
+);
+
+impl From<crate::MedaqError> for PyErr {
+    fn from(err: crate::MedaqError) -> Self {
+        let message = err.to_string();
+        match err {
+            crate::MedaqError::Sensor(_) => SensorError::new_err(message),
+            crate::MedaqError::LibraryUnavailable(_) | crate::MedaqError::DllTooOld { .. } => {
+                LibraryUnavailable::new_err(message)
+            }
+            _ => MedaqError::new_err(message),
+        }
+    }
+}
+
+/// Configuration of a sensor, `SensorBuilder(sensor_type, interface, ip_address=None, logging=False)`
+#[pyclass(name = "SensorBuilder", module = "medaqlib")]
+struct PySensorBuilder {
+    sensor_type: ME_SENSOR,
+    interface: Interface,
+    ip_address: Option<String>,
+    logging: bool,
+}
+
+#[pymethods]
+impl PySensorBuilder {
+    #[new]
+    #[pyo3(signature = (sensor_type, interface, ip_address = None, logging = false))]
+    fn new(
+        sensor_type: &str,
+        interface: &str,
+        ip_address: Option<String>,
+        logging: bool,
+    ) -> PyResult<Self> {
+        Ok(Self {
+            sensor_type: sensor_type.parse()?,
+            interface: interface.parse()?,
+            ip_address,
+            logging,
+        })
+    }
+
+    /// Connect to the sensor, raises a `MedaqError` if connecting fails
+    fn connect(&self, py: Python<'_>) -> PyResult<PySensor> {
+        let mut builder = SensorBuilder::new(self.sensor_type).with_interface(self.interface);
+        if let Some(ip_address) = &self.ip_address {
+            builder = builder.with_ip_address(ip_address.clone());
+        }
+        if self.logging {
+            builder = builder.enable_logging();
+        }
+        let sensor = py.detach(|| builder.connect())?;
+        Ok(PySensor {
+            sensor: Mutex::new(Some(sensor)),
+        })
+    }
+}
+
+/// Connected sensor, closed by `close()` or at the end of a `with` block
+#[pyclass(name = "Sensor", module = "medaqlib")]
+struct PySensor {
+    /// `None` once closed, the mutex makes the sensor usable from Python threads
+    sensor: Mutex<Option<Sensor>>,
+}
+
+#[derive(FromPyObject)]
+enum ParameterValue {
+    Int(i32),
+    Double(f64),
+    String(String),
+}
+
+impl PySensor {
+    /// Run `call` on the open sensor without holding the GIL
+    fn with_sensor<T: Send>(
+        &self,
+        py: Python<'_>,
+        call: impl FnOnce(&Sensor) -> Result<T, crate::MedaqError> + Send,
+    ) -> PyResult<T> {
+        py.detach(|| {
+            let sensor = self.sensor.lock().unwrap_or_else(PoisonError::into_inner);
+            let sensor = sensor
+                .as_ref()
+                .ok_or_else(|| MedaqError::new_err("sensor is closed"))?;
+            Ok(call(sensor)?)
+        })
+    }
+}
+
+#[pymethods]
+impl PySensor {
+    /// Scaled values per channel name, out of range values are NaN, `None` if no
+    /// data arrived within `timeout` seconds (without waiting if not given)
+    #[pyo3(signature = (timeout = None))]
+    fn read_data<'py>(
+        &self,
+        py: Python<'py>,
+        timeout: Option<f64>,
+    ) -> PyResult<Option<Bound<'py, PyDict>>> {
+        let timeout = timeout
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .map_err(|err| pyo3::exceptions::PyValueError::new_err(err.to_string()))?;
+        let data = self.with_sensor(py, |sensor| match timeout {
+            Some(timeout) => sensor.read_data_blocking(timeout),
+            None => sensor.read_data(),
+        })?;
+        data.map(|data| channels(py, &data)).transpose()
+    }
+
+    /// Names of the transmitted channels
+    fn parameters(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        self.with_sensor(py, |sensor| Ok(sensor.parameters().to_vec()))
+    }
+
+    /// Set an int, float or string parameter of the sensor instance
+    fn set_parameter(&self, py: Python<'_>, name: &str, value: ParameterValue) -> PyResult<()> {
+        self.with_sensor(py, |sensor| match &value {
+            ParameterValue::Int(value) => sensor.set_parameter_int(name, *value),
+            ParameterValue::Double(value) => sensor.set_parameter_double(name, *value),
+            ParameterValue::String(value) => sensor.set_parameter_string(name, value),
+        })
+    }
+
+    /// Disconnect, later calls raise a `MedaqError`
+    fn close(&self, py: Python<'_>) {
+        py.detach(|| {
+            let sensor = self
+                .sensor
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            drop(sensor);
+        });
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __exit__(
+        &self,
+        py: Python<'_>,
+        _exc_type: Option<&Bound<'_, PyType>>,
+        _exc_value: Option<&Bound<'_, PyAny>>,
+        _traceback: Option<&Bound<'_, PyAny>>,
+    ) -> bool {
+        self.close(py);
+        false
+    }
+}
+
+/// Lists of the scaled values keyed by channel name
+fn channels<'py>(py: Python<'py>, data: &Data) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    let count = data.channel_count().max(1);
+    for (channel, name) in data.channels().iter().enumerate() {
+        let values: Vec<f64> = data
+            .scaled_data
+            .iter()
+            .skip(channel)
+            .step_by(count)
+            .map(|&value| match Value::from_sample(value) {
+                Value::Valid(value) => value,
+                Value::OutOfRange => f64::NAN,
+            })
+            .collect();
+        dict.set_item(name, values)?;
+    }
+    Ok(dict)
+}
+
+#[pymodule]
+fn medaqlib(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add_class::<PySensorBuilder>()?;
+    m.add_class::<PySensor>()?;
+    m.add("MedaqError", py.get_type::<MedaqError>())?;
+    m.add("SensorError", py.get_type::<SensorError>())?;
+    m.add("LibraryUnavailable", py.get_type::<LibraryUnavailable>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Mutex,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use pyo3::{prelude::*, types::PyDict};
+
+    use super::PySensor;
+    use crate::{Interface, MockBackend, SensorBuilder, ME_SENSOR};
+
+    fn run(script: &std::ffi::CStr) -> PyResult<()> {
+        Python::initialize();
+        Python::attach(|py| {
+            let globals = PyDict::new(py);
+            globals.set_item("medaqlib", pyo3::wrap_pymodule!(super::medaqlib)(py))?;
+            py.run(script, Some(&globals), None)
+        })
+    }
+
+    #[test]
+    fn test_python_sensor_test() {
+        run(c"
+builder = medaqlib.SensorBuilder(sensor_type='IFD2421', interface='Simulated')
+with builder.connect() as sensor:
+    assert sensor.parameters() == ['Distance']
+    sensor.set_parameter('SP_Name', 'value')
+    sensor.set_parameter('SP_Count', 1)
+    sensor.set_parameter('SP_Offset', 0.5)
+    data = sensor.read_data(timeout=1.0)
+    assert list(data) == ['Distance'], data
+    assert len(data['Distance']) > 0
+try:
+    sensor.read_data()
+    raise AssertionError('read from a closed sensor')
+except medaqlib.MedaqError as err:
+    assert str(err) == 'sensor is closed'
+")
+        .unwrap();
+    }
+
+    #[test]
+    fn test_python_errors_test() {
+        run(c"
+try:
+    medaqlib.SensorBuilder('IFD2421', 'USB')
+    raise AssertionError('unknown interface accepted')
+except medaqlib.MedaqError as err:
+    assert str(err) == 'unknown interface \"USB\"'
+try:
+    medaqlib.SensorBuilder('IFD2421', 'TCP/IP').connect()
+    raise AssertionError('connected without an ip address')
+except medaqlib.MedaqError as err:
+    assert str(err) == 'no ip address provided'
+assert issubclass(medaqlib.SensorError, medaqlib.MedaqError)
+assert issubclass(medaqlib.LibraryUnavailable, medaqlib.MedaqError)
+")
+        .unwrap();
+    }
+
+    #[test]
+    fn test_python_releases_gil_test() {
+        Python::initialize();
+        let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+            .with_backend(MockBackend::new().with_channels(["Distance"]))
+            .with_interface(Interface::TcpIp)
+            .with_ip_address("127.0.0.1")
+            .connect()
+            .unwrap();
+        let sensor = PySensor {
+            sensor: Mutex::new(Some(sensor)),
+        };
+        thread::scope(|scope| {
+            let reading = scope
+                .spawn(|| Python::attach(|py| sensor.read_data(py, Some(0.5)).unwrap().is_none()));
+            thread::sleep(Duration::from_millis(50));
+            // the interpreter is usable while the other thread waits for data
+            let start = Instant::now();
+            Python::attach(|_| ());
+            assert!(start.elapsed() < Duration::from_millis(250));
+            assert!(reading.join().unwrap());
+        });
+    }
+}
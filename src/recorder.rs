@@ -0,0 +1,502 @@
+//! Recording sessions writing successive captures to rotating files.
+//!
+//! Every file `<prefix>-<sequence>.<csv|bin>` gets an index `<file>.idx` next
+//! to it with one line `<start offset>,<frames>,<end offset>` per capture. The index is
+//! written after the capture is flushed, so everything up to its last entry
+//! is complete even if the recording was interrupted, see [`read_index`].
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{BinaryRecorder, CsvOptions, CsvWriter, Data};
+
+/// File format of a [`Recorder`]
+#[derive(Debug, Clone)]
+pub enum RecordFormat {
+    /// CSV file with a header per file, see [`CsvWriter`]
+    Csv(CsvOptions),
+    /// Binary recording, see [`BinaryRecorder`]
+    Binary,
+}
+
+impl RecordFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            RecordFormat::Csv(_) => "csv",
+            RecordFormat::Binary => "bin",
+        }
+    }
+}
+
+/// When recorded files are synced to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncPolicy {
+    /// Leave it to the operating system
+    Never,
+    /// Sync when a file is finished by rotation or [`Recorder::close`]
+    #[default]
+    OnRotate,
+    /// Sync after every capture
+    EveryRecord,
+}
+
+/// Configuration of a [`Recorder`]
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    pub format: RecordFormat,
+    /// Start of every file name
+    pub prefix: String,
+    /// Start a new file once the current one reached this size
+    pub max_file_bytes: Option<u64>,
+    /// Start a new file once the current one spans this duration, measured by the
+    /// capture timestamps (or the time of recording for captures without one)
+    pub max_file_duration: Option<Duration>,
+    pub sync: SyncPolicy,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            format: RecordFormat::Binary,
+            prefix: "capture".into(),
+            max_file_bytes: None,
+            max_file_duration: None,
+            sync: SyncPolicy::default(),
+        }
+    }
+}
+
+/// Capture within a recorded file, see [`read_index`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// Position of the first byte of the capture in the file
+    pub offset: u64,
+    pub frames: u64,
+}
+
+/// Read the index written next to a recorded file
+///
+/// Returns the captures and the number of bytes of the file that hold complete
+/// captures. A trailing line without newline, e.g. after a crash, is ignored.
+pub fn read_index(file: impl AsRef<Path>) -> io::Result<(Vec<IndexEntry>, u64)> {
+    let index = fs::read_to_string(index_path(file.as_ref()))?;
+    let invalid = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid index line {line:?}"),
+        )
+    };
+
+    let mut entries = vec![];
+    let mut complete = 0;
+    for line in index.split_inclusive('\n') {
+        let Some(line) = line.strip_suffix('\n') else {
+            break;
+        };
+        let (offset, end) = line.split_once(',').ok_or_else(|| invalid(line))?;
+        let (frames, end) = end.split_once(',').ok_or_else(|| invalid(line))?;
+        let parse = |value: &str| value.parse::<u64>().map_err(|_| invalid(line));
+        entries.push(IndexEntry {
+            offset: parse(offset)?,
+            frames: parse(frames)?,
+        });
+        complete = parse(end)?;
+    }
+    Ok((entries, complete))
+}
+
+fn index_path(file: &Path) -> PathBuf {
+    let mut path = file.as_os_str().to_owned();
+    path.push(".idx");
+    path.into()
+}
+
+/// Streams captures of a read loop to files in a directory, starting a new file
+/// whenever the configured size or duration is exceeded
+///
+/// Files are only rotated between captures, a capture is never split across files.
+///
+/// # Example
+/// ```no_run
+/// use medaqlib::{Recorder, RecorderConfig, Interface, SensorBuilder, ME_SENSOR};
+///
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::TcpIp)
+///     .with_ip_address("10.10.10.10")
+///     .connect()
+///     .unwrap();
+///
+/// let config = RecorderConfig {
+///     max_file_bytes: Some(64 * 1024 * 1024),
+///     ..Default::default()
+/// };
+/// let mut recorder = Recorder::new("recordings", config).unwrap();
+/// for _ in 0..1000 {
+///     if let Some(data) = sensor.read_data().unwrap() {
+///         recorder.record(&data).unwrap();
+///     }
+/// }
+/// let files = recorder.close().unwrap();
+/// ```
+pub struct Recorder {
+    dir: PathBuf,
+    config: RecorderConfig,
+    current: Option<RecordFile>,
+    files: Vec<PathBuf>,
+    sequence: usize,
+}
+
+impl Recorder {
+    /// Record into `dir`, creating it if needed. Existing files are never overwritten.
+    pub fn new(dir: impl Into<PathBuf>, config: RecorderConfig) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            config,
+            current: None,
+            files: vec![],
+            sequence: 0,
+        })
+    }
+
+    /// Append the capture to the current file, rotating before if necessary
+    pub fn record(&mut self, data: &Data) -> io::Result<()> {
+        let now = data.timestamp().unwrap_or_else(SystemTime::now);
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|file| self.rotate(file, now))
+        {
+            self.finish()?;
+        }
+        if self.current.is_none() {
+            self.current = Some(self.open(now)?);
+        }
+        let sync = self.config.sync;
+        let file = self.current.as_mut().expect("file was opened");
+        file.record(data, sync)
+    }
+
+    /// Files written so far, including the current one
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Finish the current file and return all written files
+    pub fn close(mut self) -> io::Result<Vec<PathBuf>> {
+        self.finish()?;
+        Ok(std::mem::take(&mut self.files))
+    }
+
+    fn rotate(&self, file: &RecordFile, now: SystemTime) -> bool {
+        let too_large = self
+            .config
+            .max_file_bytes
+            .is_some_and(|max| file.bytes() >= max);
+        let too_long = self.config.max_file_duration.is_some_and(|max| {
+            now.duration_since(file.started)
+                .is_ok_and(|duration| duration >= max)
+        });
+        too_large || too_long
+    }
+
+    fn open(&mut self, now: SystemTime) -> io::Result<RecordFile> {
+        let extension = self.config.format.extension();
+        loop {
+            let name = format!("{}-{:04}.{extension}", self.config.prefix, self.sequence);
+            let path = self.dir.join(name);
+            self.sequence += 1;
+
+            let file = match File::create_new(&path) {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            };
+            let index = File::create(index_path(&path))?;
+            let writer = CountingWriter {
+                writer: BufWriter::new(file),
+                written: 0,
+            };
+            let sink = match &self.config.format {
+                RecordFormat::Csv(options) => Sink::Csv(CsvWriter::new(writer, options.clone())),
+                RecordFormat::Binary => Sink::Binary(BinaryRecorder::new(writer)),
+            };
+            self.files.push(path);
+            return Ok(RecordFile {
+                sink,
+                index,
+                started: now,
+            });
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.current.take() {
+            Some(file) => file.finish(self.config.sync),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+struct RecordFile {
+    sink: Sink,
+    index: File,
+    started: SystemTime,
+}
+
+enum Sink {
+    Csv(CsvWriter<CountingWriter>),
+    Binary(BinaryRecorder<CountingWriter>),
+}
+
+impl RecordFile {
+    fn bytes(&self) -> u64 {
+        self.writer().written
+    }
+
+    fn writer(&self) -> &CountingWriter {
+        match &self.sink {
+            Sink::Csv(csv) => csv.get_ref(),
+            Sink::Binary(binary) => binary.get_ref(),
+        }
+    }
+
+    fn record(&mut self, data: &Data, sync: SyncPolicy) -> io::Result<()> {
+        let offset = self.bytes();
+        match &mut self.sink {
+            Sink::Csv(csv) => {
+                csv.write(data)?;
+                csv.flush()?;
+            }
+            Sink::Binary(binary) => {
+                binary.record(data)?;
+                binary.flush()?;
+            }
+        }
+        if sync == SyncPolicy::EveryRecord {
+            self.writer().writer.get_ref().sync_data()?;
+        }
+        let frames = data.frame_count();
+        writeln!(self.index, "{offset},{frames},{}", self.bytes())
+    }
+
+    fn finish(self, sync: SyncPolicy) -> io::Result<()> {
+        let writer = match self.sink {
+            Sink::Csv(csv) => csv.into_inner()?,
+            Sink::Binary(binary) => binary.into_inner()?,
+        };
+        let file = writer.writer.into_inner().map_err(|err| err.into_error())?;
+        if sync != SyncPolicy::Never {
+            file.sync_all()?;
+            self.index.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps track of the size of the current file
+struct CountingWriter {
+    writer: BufWriter<File>,
+    written: u64,
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File, OpenOptions},
+        io::{Read, Write},
+        path::PathBuf,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use crate::{
+        read_index, BinaryReader, CsvOptions, Data, IndexEntry, RecordFormat, Recorder,
+        RecorderConfig, SyncPolicy,
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("medaqlib-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn capture(second: u64) -> Data {
+        Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![1, 2, 3, 4],
+            vec![second as f64, 2., 3., 4.],
+        )
+        .with_timestamp(UNIX_EPOCH + Duration::from_secs(second))
+    }
+
+    fn read_binary(path: &PathBuf) -> Vec<Data> {
+        BinaryReader::new(File::open(path).unwrap())
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn first_values(captures: &[Data]) -> Vec<f64> {
+        captures.iter().map(|data| data.scaled_data[0]).collect()
+    }
+
+    #[test]
+    fn test_recorder_size_rotation_test() {
+        let dir = temp_dir("size-rotation");
+        let mut record_size = vec![];
+        capture(0).write_binary(&mut record_size).unwrap();
+
+        let config = RecorderConfig {
+            max_file_bytes: Some(2 * record_size.len() as u64),
+            ..Default::default()
+        };
+        let mut recorder = Recorder::new(&dir, config).unwrap();
+        for second in 0..5 {
+            recorder.record(&capture(second)).unwrap();
+        }
+        let files = recorder.close().unwrap();
+
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0], dir.join("capture-0000.bin"));
+        let captures: Vec<_> = files
+            .iter()
+            .map(|file| first_values(&read_binary(file)))
+            .collect();
+        assert_eq!(captures, vec![vec![0., 1.], vec![2., 3.], vec![4.]]);
+
+        let (index, complete) = read_index(&files[0]).unwrap();
+        assert_eq!(
+            index,
+            vec![
+                IndexEntry {
+                    offset: 0,
+                    frames: 2
+                },
+                IndexEntry {
+                    offset: record_size.len() as u64,
+                    frames: 2
+                }
+            ]
+        );
+        assert_eq!(complete, fs::metadata(&files[0]).unwrap().len());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_recorder_duration_rotation_test() {
+        let dir = temp_dir("duration-rotation");
+        let config = RecorderConfig {
+            max_file_duration: Some(Duration::from_secs(3)),
+            sync: SyncPolicy::EveryRecord,
+            ..Default::default()
+        };
+        let mut recorder = Recorder::new(&dir, config).unwrap();
+        for second in [0, 1, 2, 3, 4, 10] {
+            recorder.record(&capture(second)).unwrap();
+        }
+        assert_eq!(recorder.files().len(), 3);
+        let files = recorder.close().unwrap();
+
+        let captures: Vec<_> = files
+            .iter()
+            .map(|file| first_values(&read_binary(file)))
+            .collect();
+        assert_eq!(captures, vec![vec![0., 1., 2.], vec![3., 4.], vec![10.]]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_recorder_csv_rotation_test() {
+        let dir = temp_dir("csv-rotation");
+        let config = RecorderConfig {
+            format: RecordFormat::Csv(CsvOptions::default()),
+            prefix: "distance".into(),
+            max_file_bytes: Some(1),
+            ..Default::default()
+        };
+        let mut recorder = Recorder::new(&dir, config).unwrap();
+        recorder.record(&capture(1)).unwrap();
+        recorder.record(&capture(2)).unwrap();
+        let files = recorder.close().unwrap();
+
+        assert_eq!(
+            files,
+            vec![dir.join("distance-0000.csv"), dir.join("distance-0001.csv")]
+        );
+        assert_eq!(
+            fs::read_to_string(&files[1]).unwrap(),
+            "Distance,Intensity\n2,2\n3,4\n"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_recorder_interrupted_file_test() {
+        let dir = temp_dir("interrupted");
+        let mut recorder = Recorder::new(&dir, RecorderConfig::default()).unwrap();
+        recorder.record(&capture(1)).unwrap();
+        recorder.record(&capture(2)).unwrap();
+        let files = recorder.close().unwrap();
+
+        // a capture and its index line only partially written
+        let mut file = OpenOptions::new().append(true).open(&files[0]).unwrap();
+        file.write_all(b"MEDQ\x01").unwrap();
+        let mut index = OpenOptions::new()
+            .append(true)
+            .open(dir.join("capture-0000.bin.idx"))
+            .unwrap();
+        index.write_all(b"123,").unwrap();
+
+        let (entries, complete) = read_index(&files[0]).unwrap();
+        assert_eq!(entries.len(), 2);
+        let reader = File::open(&files[0]).unwrap().take(complete);
+        let captures: Vec<_> = BinaryReader::new(reader).collect::<Result<_, _>>().unwrap();
+        assert_eq!(first_values(&captures), vec![1., 2.]);
+        assert!(read_binary_err(&files[0]));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn read_binary_err(path: &PathBuf) -> bool {
+        BinaryReader::new(File::open(path).unwrap()).any(|data| data.is_err())
+    }
+
+    #[test]
+    fn test_recorder_keeps_existing_files_test() {
+        let dir = temp_dir("existing");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("capture-0000.bin"), "keep").unwrap();
+
+        let mut recorder = Recorder::new(&dir, RecorderConfig::default()).unwrap();
+        recorder.record(&capture(1)).unwrap();
+        let files = recorder.close().unwrap();
+
+        assert_eq!(files, vec![dir.join("capture-0001.bin")]);
+        assert_eq!(
+            fs::read_to_string(dir.join("capture-0000.bin")).unwrap(),
+            "keep"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,372 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::Instant,
+};
+
+use crate::{
+    backend::{self, Backend},
+    csv, sim, BinaryReader, Data, MedaqError, Value, ERR_CODE, ME_SENSOR,
+};
+
+/// Pace at which a [`ReplayBackend`] makes recorded frames available
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Every remaining frame is available at once
+    AsFastAsPossible,
+    /// Frames become available at the original sample rate, counted from opening.
+    /// Recordings hold no timestamps per frame, so the rate has to be given.
+    Realtime { sample_rate_hz: f64 },
+}
+
+/// Fake sensor replaying a recording written with [`BinaryRecorder`](crate::BinaryRecorder)
+/// or [`CsvWriter`](crate::CsvWriter)
+///
+/// Channel names, units and out of range samples are reproduced as recorded.
+/// Missing raw values are replayed as `0`, or `-1` for out of range samples, missing
+/// scaled values as the raw values. Once every frame has been read, reads fail with
+/// [`ERR_READING_SENSOR_DATA`](ERR_CODE::ERR_READING_SENSOR_DATA) as if the
+/// connection was closed, unless the backend is [`looping`](Self::looping).
+///
+/// # Example
+/// ```no_run
+/// use medaqlib::{Interface, ReplayBackend, ReplaySpeed, SensorBuilder, ME_SENSOR};
+///
+/// let replay = ReplayBackend::from_file("capture.medq", ReplaySpeed::AsFastAsPossible)?;
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1320)
+///     .with_interface(Interface::Simulated)
+///     .with_backend(replay)
+///     .connect();
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct ReplayBackend {
+    channels: Vec<String>,
+    units: Option<Vec<Option<String>>>,
+    raw: Vec<i32>,
+    scaled: Vec<f64>,
+    speed: ReplaySpeed,
+    looping: bool,
+    state: Mutex<ReplayState>,
+}
+
+#[derive(Debug, Default)]
+struct ReplayState {
+    opened: Option<Instant>,
+    delivered: u64,
+}
+
+impl ReplayBackend {
+    /// Read a recording, binary if it starts with the magic of the binary format,
+    /// CSV with a header line otherwise
+    pub fn from_file(path: impl AsRef<Path>, speed: ReplaySpeed) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(b"MEDQ") {
+            Self::from_captures(BinaryReader::new(bytes.as_slice()), speed)
+        } else {
+            let text = String::from_utf8(bytes)
+                .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+            Self::from_captures([csv::read_csv(&text)], speed)
+        }
+    }
+
+    /// Replay the frames of successive captures, which must all have the same channels
+    pub fn from_captures(
+        captures: impl IntoIterator<Item = io::Result<Data>>,
+        speed: ReplaySpeed,
+    ) -> io::Result<Self> {
+        let mut recording: Option<Data> = None;
+        for data in captures {
+            let data = data?;
+            match &mut recording {
+                Some(recording) => recording
+                    .append(&data)
+                    .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?,
+                None => recording = Some(data),
+            }
+        }
+        let recording =
+            recording.ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "empty recording"))?;
+
+        let scaled = match recording.scaled_data.is_empty() {
+            true => recording.raw_data.iter().map(|&raw| raw as f64).collect(),
+            false => recording.scaled_data.clone(),
+        };
+        let raw = match recording.raw_data.is_empty() {
+            true => scaled
+                .iter()
+                .map(|&value| match Value::from_sample(value) {
+                    Value::Valid(_) => 0,
+                    Value::OutOfRange => -1,
+                })
+                .collect(),
+            false => recording.raw_data.clone(),
+        };
+        let units = recording
+            .channel_info()
+            .map(|info| info.iter().map(|info| info.unit.clone()).collect());
+
+        Ok(Self {
+            channels: recording.channels().to_vec(),
+            units,
+            raw,
+            scaled,
+            speed,
+            looping: false,
+            state: Mutex::default(),
+        })
+    }
+
+    /// Start over at the first frame after the last one instead of failing
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// Number of frames in the recording
+    pub fn frame_count(&self) -> usize {
+        self.scaled.len() / self.channels.len().max(1)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ReplayState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Frames available but not transferred, an error once the recording is exhausted
+    fn available_frames(&self, state: &ReplayState) -> Result<u64, MedaqError> {
+        let opened = state
+            .opened
+            .ok_or(MedaqError::Sensor(ERR_CODE::ERR_NOT_OPEN))?;
+        let total = self.frame_count() as u64;
+        // a looping recording offers the rest of the current pass
+        let remaining = match self.looping && total > 0 {
+            true => total - state.delivered % total,
+            false => total - state.delivered,
+        };
+        if remaining == 0 {
+            return Err(MedaqError::Sensor(ERR_CODE::ERR_READING_SENSOR_DATA));
+        }
+        Ok(match self.speed {
+            ReplaySpeed::AsFastAsPossible => remaining,
+            ReplaySpeed::Realtime { sample_rate_hz } => {
+                let due = (opened.elapsed().as_secs_f64() * sample_rate_hz) as u64;
+                due.saturating_sub(state.delivered).min(remaining)
+            }
+        })
+    }
+}
+
+impl backend::sealed::Sealed for ReplayBackend {}
+
+impl Backend for ReplayBackend {
+    fn create_instance(&self, _: ME_SENSOR) -> u32 {
+        1
+    }
+
+    fn release_instance(&self, _: u32) -> Result<(), MedaqError> {
+        Ok(())
+    }
+
+    fn open_sensor(&self, _: u32) -> Result<(), MedaqError> {
+        let mut state = self.lock();
+        if state.opened.is_some() {
+            return Err(MedaqError::Sensor(ERR_CODE::ERR_ALREADY_OPEN));
+        }
+        *state = ReplayState {
+            opened: Some(Instant::now()),
+            delivered: 0,
+        };
+        Ok(())
+    }
+
+    fn close_sensor(&self, _: u32) -> Result<(), MedaqError> {
+        self.lock().opened = None;
+        Ok(())
+    }
+
+    fn set_parameter_string(&self, _: u32, _: &str, _: &str) -> Result<(), MedaqError> {
+        Ok(())
+    }
+
+    fn set_parameter_int(&self, _: u32, _: &str, _: i32) -> Result<(), MedaqError> {
+        Ok(())
+    }
+
+    fn get_parameter_string(&self, _: u32, name: &str) -> Result<String, MedaqError> {
+        let parameter = sim::split_index(name).and_then(|(prefix, index)| {
+            let channel = index.checked_sub(1)?;
+            match prefix {
+                "IA_Scaled_Name" => self.channels.get(channel).cloned(),
+                "IA_Scaled_Unit" => self.units.as_ref()?.get(channel)?.clone(),
+                _ => None,
+            }
+        });
+        parameter.ok_or(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER))
+    }
+
+    fn get_parameter_double(&self, _: u32, _: &str) -> Result<f64, MedaqError> {
+        Err(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER))
+    }
+
+    fn set_parameter_double(&self, _: u32, _: &str, _: f64) -> Result<(), MedaqError> {
+        Ok(())
+    }
+
+    fn get_parameter_int(&self, _: u32, _: &str) -> Result<i32, MedaqError> {
+        Err(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER))
+    }
+
+    fn exec_scmd(&self, _: u32, _: &str) -> Result<(), MedaqError> {
+        match self.lock().opened {
+            Some(_) => Ok(()),
+            None => Err(MedaqError::Sensor(ERR_CODE::ERR_NOT_OPEN)),
+        }
+    }
+
+    fn get_error(&self, _: u32) -> String {
+        String::new()
+    }
+
+    fn data_avail(&self, _: u32) -> Result<i32, MedaqError> {
+        let frames = self.available_frames(&self.lock())?;
+        let values = frames.saturating_mul(self.channels.len() as u64);
+        Ok(values.min(i32::MAX as u64) as i32)
+    }
+
+    fn transfer_data(
+        &self,
+        _: u32,
+        mut raw: Option<&mut [i32]>,
+        mut scaled: Option<&mut [f64]>,
+        max_values: usize,
+    ) -> Result<usize, MedaqError> {
+        let mut state = self.lock();
+        let channels = self.channels.len();
+        if channels == 0 {
+            return Ok(0);
+        }
+        let available = self.available_frames(&state)?;
+        let frames = available.min((max_values / channels) as u64) as usize;
+        let total = self.frame_count() as u64;
+
+        for offset in 0..frames {
+            let frame = ((state.delivered + offset as u64) % total) as usize;
+            let source = frame * channels..(frame + 1) * channels;
+            let target = offset * channels..(offset + 1) * channels;
+            if let Some(scaled) = scaled.as_deref_mut() {
+                scaled[target.clone()].copy_from_slice(&self.scaled[source.clone()]);
+            }
+            if let Some(raw) = raw.as_deref_mut() {
+                raw[target].copy_from_slice(&self.raw[source]);
+            }
+        }
+        state.delivered += frames as u64;
+        Ok(frames * channels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{process, thread, time::Duration};
+
+    use super::{ReplayBackend, ReplaySpeed};
+    use crate::{
+        BinaryRecorder, ChannelInfo, CsvOptions, Data, Interface, MedaqError, Sensor,
+        SensorBuilder, ERR_CODE, ME_SENSOR, SCALED_OUT_OF_RANGE as OOR,
+    };
+
+    fn data() -> Data {
+        Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![1, 2, -1, 4, 5, 6],
+            vec![1.5, 2., OOR, 4., 5., 6.],
+        )
+        .with_channel_info(vec![
+            ChannelInfo::new("Distance").with_unit("mm"),
+            ChannelInfo::new("Intensity"),
+        ])
+    }
+
+    fn connect(replay: ReplayBackend) -> Sensor {
+        SensorBuilder::new(ME_SENSOR::SENSOR_ILD1320)
+            .with_interface(Interface::Simulated)
+            .with_backend(replay)
+            .connect()
+            .unwrap()
+    }
+
+    fn replay(speed: ReplaySpeed) -> ReplayBackend {
+        ReplayBackend::from_captures([Ok(data())], speed).unwrap()
+    }
+
+    #[test]
+    fn test_replay_binary_file_test() {
+        let path = std::env::temp_dir().join(format!("medaqlib-replay-{}.medq", process::id()));
+        let mut recorder = BinaryRecorder::new(std::fs::File::create(&path).unwrap());
+        recorder.record(&data()).unwrap();
+        recorder.record(&data()).unwrap();
+        drop(recorder);
+
+        let replay = ReplayBackend::from_file(&path, ReplaySpeed::AsFastAsPossible).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(replay.frame_count(), 6);
+
+        let sensor = connect(replay);
+        let read = sensor.read_data().unwrap().unwrap();
+        assert_eq!(read.channels(), ["Distance", "Intensity"]);
+        assert_eq!(read.unit(0), Some("mm"));
+        assert_eq!(read.raw_data, [data().raw_data, data().raw_data].concat());
+        assert_eq!(
+            read.scaled_data,
+            [data().scaled_data, data().scaled_data].concat()
+        );
+        assert!(matches!(
+            sensor.read_data(),
+            Err(MedaqError::Sensor(ERR_CODE::ERR_READING_SENSOR_DATA))
+        ));
+    }
+
+    #[test]
+    fn test_replay_csv_file_test() {
+        let path = std::env::temp_dir().join(format!("medaqlib-replay-{}.csv", process::id()));
+        let options = CsvOptions {
+            delimiter: ';',
+            decimal_comma: true,
+            out_of_range: "OutOfRange".into(),
+            header: true,
+        };
+        data()
+            .write_csv(std::fs::File::create(&path).unwrap(), &options)
+            .unwrap();
+
+        let replay = ReplayBackend::from_file(&path, ReplaySpeed::AsFastAsPossible).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let read = connect(replay).read_data().unwrap().unwrap();
+        assert_eq!(read.unit(0), Some("mm"));
+        assert_eq!(read.scaled_data, data().scaled_data);
+        assert_eq!(read.raw_data, vec![0, 0, -1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_replay_looping_test() {
+        let sensor = connect(replay(ReplaySpeed::AsFastAsPossible).looping(true));
+        for _ in 0..3 {
+            let read = sensor.read_data().unwrap().unwrap();
+            assert_eq!(read.scaled_data, data().scaled_data);
+        }
+    }
+
+    #[test]
+    fn test_replay_realtime_test() {
+        let sensor = connect(replay(ReplaySpeed::Realtime {
+            sample_rate_hz: 10.,
+        }));
+        assert!(sensor.read_data().unwrap().is_none());
+
+        thread::sleep(Duration::from_millis(350));
+        let read = sensor.read_data().unwrap().unwrap();
+        assert_eq!(read.scaled_data, data().scaled_data);
+    }
+}
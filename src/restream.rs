@@ -0,0 +1,426 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufWriter, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+#[cfg(feature = "serde")]
+use crate::JsonlOptions;
+use crate::{sink::SinkClosed, Data, DataSink, MedaqError, Value};
+
+/// Interval the listener checks for new clients and the stop request
+const ACCEPT_POLL: Duration = Duration::from_millis(10);
+
+/// Lines sent to the clients of a [`TcpRestreamer`]
+#[derive(Debug, Clone, Default)]
+pub enum LineFormat {
+    /// `Distance=1.23;Intensity=OutOfRange`, a line per frame
+    #[default]
+    KeyValue,
+    /// A JSON object per frame, see [`Data::write_jsonl`]
+    #[cfg(feature = "serde")]
+    Jsonl(JsonlOptions),
+}
+
+/// Options of a [`TcpRestreamer`]
+#[derive(Debug, Clone)]
+pub struct RestreamConfig {
+    pub format: LineFormat,
+    /// Send every nth frame, counted over the captures, `0` is the same as `1`
+    pub decimation: usize,
+    /// Lines queued per client, the oldest are dropped once a client falls behind
+    pub client_queue: usize,
+}
+
+impl Default for RestreamConfig {
+    fn default() -> Self {
+        Self {
+            format: LineFormat::default(),
+            decimation: 1,
+            client_queue: 1024,
+        }
+    }
+}
+
+/// Forwards the frames of an acquisition to every client connected over TCP
+///
+/// Used as [`DataSink`], each clone feeds the same clients. Every client has its
+/// own writer thread and bounded queue, so a slow or stalled client only loses
+/// lines and never blocks the acquisition. Read errors are not forwarded. The
+/// listener and the connections are closed once the last clone is dropped.
+///
+/// # Example
+/// ```no_run
+/// use medaqlib::{
+///     AcquisitionConfig, Interface, RestreamConfig, SensorBuilder, TcpRestreamer, ME_SENSOR,
+/// };
+///
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::TcpIp)
+///     .with_ip_address("10.10.10.10")
+///     .connect()
+///     .unwrap();
+///
+/// let restreamer = TcpRestreamer::bind("0.0.0.0:5000", RestreamConfig::default()).unwrap();
+/// let acquisition =
+///     sensor.start_acquisition_with_sink(AcquisitionConfig::default(), restreamer.clone());
+/// std::thread::sleep(std::time::Duration::from_secs(60));
+/// println!("{} lines dropped", restreamer.dropped_lines());
+/// let sensor = acquisition.join();
+/// ```
+#[derive(Clone)]
+pub struct TcpRestreamer {
+    inner: Arc<Inner>,
+    /// Frames to skip before the next one is sent
+    skip: usize,
+}
+
+struct Inner {
+    config: RestreamConfig,
+    local_addr: SocketAddr,
+    clients: Arc<Mutex<Vec<Client>>>,
+    dropped: Arc<AtomicU64>,
+    stop: Arc<AtomicBool>,
+    listener: Option<JoinHandle<()>>,
+}
+
+impl TcpRestreamer {
+    /// Listen on `addr`, e.g. `0.0.0.0:5000` or `127.0.0.1:0` for any free port
+    pub fn bind(addr: impl ToSocketAddrs, config: RestreamConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let dropped = Arc::new(AtomicU64::new(0));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let accept = {
+            let clients = Arc::clone(&clients);
+            let stop = Arc::clone(&stop);
+            let capacity = config.client_queue.max(1);
+            thread::Builder::new()
+                .name("medaq-restream".into())
+                .spawn(move || accept(listener, &clients, &stop, capacity))?
+        };
+        Ok(Self {
+            inner: Arc::new(Inner {
+                config,
+                local_addr,
+                clients,
+                dropped,
+                stop,
+                listener: Some(accept),
+            }),
+            skip: 0,
+        })
+    }
+
+    /// Address the clients connect to, with the port chosen for port `0`
+    pub fn local_addr(&self) -> SocketAddr {
+        self.inner.local_addr
+    }
+
+    /// Number of clients currently connected
+    pub fn clients(&self) -> usize {
+        let mut clients = lock(&self.inner.clients);
+        clients.retain(|client| !client.queue.is_closed());
+        clients.len()
+    }
+
+    /// Lines discarded over all clients because their queue was full
+    pub fn dropped_lines(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Lines of the frames to send, decimation applied
+    fn lines(&mut self, data: &Data) -> Vec<Arc<str>> {
+        let frames = data.frame_count();
+        let step = self.inner.config.decimation.max(1);
+        let sent: Vec<usize> = (self.skip..frames).step_by(step).collect();
+        self.skip = (self.skip + sent.len() * step).saturating_sub(frames);
+
+        match &self.inner.config.format {
+            LineFormat::KeyValue => sent
+                .into_iter()
+                .map(|index| Arc::from(key_value(data, index)))
+                .collect(),
+            // written as a whole, so timestamps are offset by the position in the capture
+            #[cfg(feature = "serde")]
+            LineFormat::Jsonl(options) => {
+                let mut jsonl = vec![];
+                data.write_jsonl(&mut jsonl, options)
+                    .expect("writing to a Vec never fails");
+                let jsonl = String::from_utf8(jsonl).expect("JSON is UTF-8");
+                let lines: Vec<&str> = jsonl.split_inclusive('\n').collect();
+                sent.into_iter()
+                    .map(|index| Arc::from(lines[index]))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl DataSink for TcpRestreamer {
+    fn send(&mut self, result: Result<Data, MedaqError>) -> Result<(), SinkClosed> {
+        let Ok(data) = result else {
+            return Ok(());
+        };
+        if data.channel_count() == 0 {
+            return Ok(());
+        }
+        let lines = self.lines(&data);
+        let mut clients = lock(&self.inner.clients);
+        clients.retain(|client| !client.queue.is_closed());
+        for client in clients.iter() {
+            for line in &lines {
+                if client.queue.push(Arc::clone(line)) {
+                    self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(listener) = self.listener.take() {
+            let _ = listener.join();
+        }
+        // shut down first, a stalled client would block its writer forever
+        for client in lock(&self.clients).drain(..) {
+            client.queue.close();
+            let _ = client.stream.shutdown(Shutdown::Both);
+            let _ = client.writer.join();
+        }
+    }
+}
+
+/// `name=value;...` of the frame at `index`
+fn key_value(data: &Data, index: usize) -> String {
+    let channels = data.channel_count();
+    let values = &data.scaled_data[index * channels..(index + 1) * channels];
+    let mut line = String::new();
+    for (channel, &value) in data.channels().iter().zip(values) {
+        if !line.is_empty() {
+            line.push(';');
+        }
+        line.push_str(channel);
+        line.push('=');
+        line.push_str(&Value::from_sample(value).to_string());
+    }
+    line.push('\n');
+    line
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn accept(listener: TcpListener, clients: &Mutex<Vec<Client>>, stop: &AtomicBool, capacity: usize) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Ok(client) = Client::start(stream, capacity) {
+                    lock(clients).push(client);
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+            // e.g. the client reset the connection before it was accepted
+            Err(_) => thread::sleep(ACCEPT_POLL),
+        }
+    }
+}
+
+/// Connection to a client with the lines waiting to be sent
+struct Client {
+    queue: Arc<LineQueue>,
+    stream: TcpStream,
+    writer: JoinHandle<()>,
+}
+
+impl Client {
+    fn start(stream: TcpStream, capacity: usize) -> io::Result<Self> {
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        let queue = Arc::new(LineQueue::new(capacity));
+        let lines = Arc::clone(&queue);
+        let shutdown = stream.try_clone()?;
+        let writer = thread::Builder::new()
+            .name("medaq-restream-client".into())
+            .spawn(move || {
+                let mut stream = BufWriter::new(stream);
+                while let Some(batch) = lines.pop_all() {
+                    let written = batch
+                        .iter()
+                        .try_for_each(|line| stream.write_all(line.as_bytes()))
+                        .and_then(|()| stream.flush());
+                    if written.is_err() {
+                        break;
+                    }
+                }
+                lines.close();
+            })?;
+        Ok(Self {
+            queue,
+            stream: shutdown,
+            writer,
+        })
+    }
+}
+
+/// Bounded queue dropping the oldest line when full
+struct LineQueue {
+    state: Mutex<QueueState>,
+    ready: Condvar,
+    capacity: usize,
+}
+
+#[derive(Default)]
+struct QueueState {
+    lines: VecDeque<Arc<str>>,
+    closed: bool,
+}
+
+impl LineQueue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::default(),
+            ready: Condvar::new(),
+            capacity,
+        }
+    }
+
+    /// Queue `line`, returns `true` if the oldest line was dropped to make room
+    fn push(&self, line: Arc<str>) -> bool {
+        let mut state = lock(&self.state);
+        let dropped = state.lines.len() >= self.capacity;
+        if dropped {
+            state.lines.pop_front();
+        }
+        state.lines.push_back(line);
+        self.ready.notify_one();
+        dropped
+    }
+
+    /// Wait for lines and take all of them, `None` once closed
+    fn pop_all(&self) -> Option<Vec<Arc<str>>> {
+        let mut state = lock(&self.state);
+        while state.lines.is_empty() && !state.closed {
+            state = self
+                .ready
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        match state.closed {
+            true => None,
+            false => Some(state.lines.drain(..).collect()),
+        }
+    }
+
+    fn close(&self) {
+        lock(&self.state).closed = true;
+        self.ready.notify_one();
+    }
+
+    fn is_closed(&self) -> bool {
+        lock(&self.state).closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpStream,
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::LineQueue;
+    use crate::{Data, DataSink, RestreamConfig, TcpRestreamer, SCALED_OUT_OF_RANGE};
+
+    fn data(first: usize, frames: usize) -> Data {
+        let scaled = (first..first + frames)
+            .flat_map(|frame| [frame as f64, SCALED_OUT_OF_RANGE])
+            .collect();
+        Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            scaled,
+        )
+    }
+
+    fn wait_for_client(restreamer: &TcpRestreamer) {
+        let start = Instant::now();
+        while restreamer.clients() == 0 {
+            assert!(start.elapsed() < Duration::from_secs(5), "no client");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_restream_key_value_test() {
+        let config = RestreamConfig {
+            decimation: 2,
+            ..RestreamConfig::default()
+        };
+        let mut restreamer = TcpRestreamer::bind("127.0.0.1:0", config).unwrap();
+        let client = TcpStream::connect(restreamer.local_addr()).unwrap();
+        wait_for_client(&restreamer);
+
+        restreamer.send(Ok(data(0, 3))).unwrap();
+        restreamer.send(Err(crate::MedaqError::NoChannels)).unwrap();
+        restreamer.send(Ok(data(3, 2))).unwrap();
+        let lines: Vec<_> = BufReader::new(client)
+            .lines()
+            .take(3)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "Distance=0;Intensity=OutOfRange",
+                "Distance=2;Intensity=OutOfRange",
+                "Distance=4;Intensity=OutOfRange",
+            ]
+        );
+        assert_eq!(restreamer.dropped_lines(), 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_restream_jsonl_test() {
+        let config = RestreamConfig {
+            format: super::LineFormat::Jsonl(crate::JsonlOptions::default()),
+            ..RestreamConfig::default()
+        };
+        let mut restreamer = TcpRestreamer::bind("127.0.0.1:0", config).unwrap();
+        let client = TcpStream::connect(restreamer.local_addr()).unwrap();
+        wait_for_client(&restreamer);
+
+        restreamer.send(Ok(data(1, 1))).unwrap();
+        let line = BufReader::new(client).lines().next().unwrap().unwrap();
+        assert_eq!(line, "{\"Distance\":1.0,\"Intensity\":null}");
+    }
+
+    #[test]
+    fn test_line_queue_drops_oldest_test() {
+        let queue = LineQueue::new(2);
+        assert!(!queue.push(Arc::from("a")));
+        assert!(!queue.push(Arc::from("b")));
+        assert!(queue.push(Arc::from("c")));
+        assert_eq!(queue.pop_all().unwrap(), [Arc::from("b"), Arc::from("c")]);
+        queue.close();
+        assert!(queue.pop_all().is_none());
+        assert!(queue.is_closed());
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::VecDeque,
+    sync::{mpsc::SyncSender, Arc},
+    time::SystemTime,
+};
+
+use crate::{
+    acquisition::{Control, Deliver},
+    stats::Stats,
+    sync::Shared,
+    ChannelInfo, Data, MedaqError,
+};
+
+/// Which frames are discarded when the ring buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DropPolicy {
+    /// Make room for new frames, the ring always holds the newest frames
+    #[default]
+    DropOldest,
+    /// Keep the frames in the ring and discard new ones until it is cleared
+    DropNewest,
+}
+
+/// Keep only a fixed number of frames instead of queueing every capture, see
+/// [`AcquisitionConfig::ring_buffer`](crate::AcquisitionConfig::ring_buffer)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingBufferConfig {
+    pub capacity_frames: usize,
+    pub on_drop: DropPolicy,
+}
+
+/// Frames of successive captures with a fixed capacity
+#[derive(Debug)]
+pub(crate) struct FrameRing {
+    config: RingBufferConfig,
+    channels: Option<Arc<[String]>>,
+    channel_info: Option<Arc<[ChannelInfo]>>,
+    timestamp: Option<SystemTime>,
+    raw_data: VecDeque<i32>,
+    scaled_data: VecDeque<f64>,
+    /// `false` once a capture without raw values was pushed
+    has_raw: bool,
+}
+
+impl FrameRing {
+    pub(crate) fn new(config: RingBufferConfig) -> Self {
+        Self {
+            config,
+            channels: None,
+            channel_info: None,
+            timestamp: None,
+            raw_data: VecDeque::new(),
+            scaled_data: VecDeque::new(),
+            has_raw: true,
+        }
+    }
+
+    /// Append the frames of the capture, returns the number of dropped frames
+    ///
+    /// A capture with different channels replaces the contents of the ring.
+    pub(crate) fn push(&mut self, data: &Data) -> usize {
+        let channels = data.channel_count();
+        if channels == 0 {
+            return 0;
+        }
+        if self.channels.as_deref() != Some(data.channels()) {
+            *self = Self::new(self.config);
+            self.channels = Some(data.channels_arc());
+        }
+        self.channel_info = data.channel_info().map(Arc::from);
+        self.timestamp = data.timestamp();
+
+        let frames = data.frame_count();
+        let has_raw = data.raw_data.len() == frames * channels;
+        if !has_raw {
+            self.has_raw = false;
+            self.raw_data.clear();
+        }
+
+        let capacity = self.config.capacity_frames;
+        let stored = self.scaled_data.len() / channels;
+        let scaled_frames = data.scaled_data.len() / channels;
+        let accepted = match self.config.on_drop {
+            DropPolicy::DropOldest => scaled_frames.min(capacity),
+            DropPolicy::DropNewest => scaled_frames.min(capacity - stored),
+        };
+        let skip = match self.config.on_drop {
+            DropPolicy::DropOldest => scaled_frames - accepted,
+            DropPolicy::DropNewest => 0,
+        };
+
+        let values = skip * channels..(skip + accepted) * channels;
+        self.scaled_data.extend(&data.scaled_data[values.clone()]);
+        if self.has_raw {
+            self.raw_data.extend(&data.raw_data[values]);
+        }
+
+        let overflow = (self.scaled_data.len() / channels).saturating_sub(capacity);
+        self.scaled_data.drain(..overflow * channels);
+        if self.has_raw {
+            self.raw_data.drain(..overflow * channels);
+        }
+        scaled_frames - accepted + overflow
+    }
+
+    /// Contents of the ring as a capture with the metadata of the newest capture
+    pub(crate) fn snapshot(&self) -> Data {
+        let channels = self.channels.clone().unwrap_or_else(|| Arc::new([]));
+        let raw_data = match self.has_raw {
+            true => self.raw_data.iter().copied().collect(),
+            false => vec![],
+        };
+        let mut data = Data::new(
+            channels,
+            raw_data,
+            self.scaled_data.iter().copied().collect(),
+        );
+        if let Some(info) = &self.channel_info {
+            data = data.with_channel_info(Arc::clone(info));
+        }
+        if let Some(timestamp) = self.timestamp {
+            data = data.with_timestamp(timestamp);
+        }
+        data
+    }
+}
+
+/// Stores captures in the ring, read errors still go to the channel
+pub(crate) struct RingDeliver {
+    pub(crate) ring: Shared<FrameRing>,
+    pub(crate) stats: Stats,
+    pub(crate) errors: SyncSender<Result<Data, MedaqError>>,
+}
+
+impl Deliver for RingDeliver {
+    fn deliver(&mut self, result: Result<Data, MedaqError>, control: &Control) -> bool {
+        match result {
+            Ok(data) => {
+                let mut ring = self.ring.lock();
+                let dropped = ring.push(&data);
+                self.stats.record_dropped(dropped);
+                true
+            }
+            Err(err) => self.errors.deliver(Err(err), control),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DropPolicy, FrameRing, RingBufferConfig};
+    use crate::Data;
+
+    fn capture(channels: &[&str], first: i32, frames: i32) -> Data {
+        let count = channels.len() as i32;
+        let raw_data: Vec<i32> = (first * count..(first + frames) * count).collect();
+        let scaled_data = raw_data.iter().map(|&raw| raw as f64).collect();
+        let channels: Vec<String> = channels.iter().map(|c| c.to_string()).collect();
+        Data::new(channels, raw_data, scaled_data)
+    }
+
+    fn ring(capacity_frames: usize, on_drop: DropPolicy) -> FrameRing {
+        FrameRing::new(RingBufferConfig {
+            capacity_frames,
+            on_drop,
+        })
+    }
+
+    #[test]
+    fn test_ring_drop_oldest_test() {
+        let mut ring = ring(3, DropPolicy::DropOldest);
+        assert_eq!(ring.push(&capture(&["1", "2"], 0, 2)), 0);
+        assert_eq!(ring.push(&capture(&["1", "2"], 2, 2)), 1);
+        assert_eq!(ring.snapshot().raw_data, vec![2, 3, 4, 5, 6, 7]);
+
+        // a capture larger than the ring keeps its newest frames
+        assert_eq!(ring.push(&capture(&["1", "2"], 4, 5)), 5);
+        let snapshot = ring.snapshot();
+        assert_eq!(snapshot.frame_count(), 3);
+        assert_eq!(snapshot.raw_data, vec![12, 13, 14, 15, 16, 17]);
+        assert_eq!(snapshot.scaled_data, vec![12., 13., 14., 15., 16., 17.]);
+        assert_eq!(snapshot.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_ring_drop_newest_test() {
+        let mut ring = ring(3, DropPolicy::DropNewest);
+        assert_eq!(ring.push(&capture(&["1"], 0, 2)), 0);
+        assert_eq!(ring.push(&capture(&["1"], 2, 2)), 1);
+        assert_eq!(ring.push(&capture(&["1"], 4, 2)), 2);
+        assert_eq!(ring.snapshot().raw_data, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_ring_channel_change_test() {
+        let mut ring = ring(4, DropPolicy::DropOldest);
+        ring.push(&capture(&["1"], 0, 2));
+        ring.push(&capture(&["1", "2"], 0, 1));
+        let snapshot = ring.snapshot();
+        assert_eq!(snapshot.channels(), ["1", "2"]);
+        assert_eq!(snapshot.raw_data, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_ring_without_raw_values_test() {
+        let mut ring = ring(4, DropPolicy::DropOldest);
+        ring.push(&capture(&["1"], 0, 2));
+        ring.push(&Data::new(vec!["1".to_string()], vec![], vec![5.]));
+        let snapshot = ring.snapshot();
+        assert!(snapshot.raw_data.is_empty());
+        assert_eq!(snapshot.scaled_data, vec![0., 1., 5.]);
+    }
+
+    #[test]
+    fn test_ring_empty_snapshot_test() {
+        let snapshot = ring(4, DropPolicy::DropOldest).snapshot();
+        assert!(snapshot.is_empty());
+        assert_eq!(snapshot.channel_count(), 0);
+    }
+}
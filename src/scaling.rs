@@ -0,0 +1,145 @@
+use std::fmt::Display;
+
+use crate::{Data, Value};
+
+/// Sample whose scaled value does not match its scaled raw count, see [`Data::verify_scaling`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScalingMismatch {
+    pub channel: String,
+    pub frame: usize,
+    pub raw: i32,
+    pub scaled: f64,
+    /// Scaled value computed from the raw count
+    pub expected: f64,
+}
+
+impl Display for ScalingMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "channel {:?} frame {}: raw {} scales to {} but scaled value is {}",
+            self.channel, self.frame, self.raw, self.expected, self.scaled
+        )
+    }
+}
+
+impl Data {
+    /// Check that `raw * scale + offset` is within `tolerance` of the scaled value
+    /// for every frame where both values are valid
+    ///
+    /// Channels without a reported scaling, and captures without raw or scaled
+    /// values, are skipped.
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{ChannelInfo, Data};
+    ///
+    /// let data = Data::new(vec!["Distance".to_string()], vec![10, 20], vec![1., 2.5])
+    ///     .with_channel_info(vec![ChannelInfo::new("Distance").with_scaling(0.1, 0.)]);
+    /// let mismatches = data.verify_scaling(1e-9).unwrap_err();
+    /// assert_eq!(mismatches[0].frame, 1);
+    /// ```
+    pub fn verify_scaling(&self, tolerance: f64) -> Result<(), Vec<ScalingMismatch>> {
+        let channels = self.channel_count();
+        let Some(info) = self.channel_info() else {
+            return Ok(());
+        };
+        if channels == 0 || self.raw_data.len() != self.scaled_data.len() {
+            return Ok(());
+        }
+
+        let mut mismatches = vec![];
+        let samples = self.raw_data.iter().zip(&self.scaled_data).enumerate();
+        for (index, (&raw, &scaled)) in samples {
+            // channels missing from the metadata have no scaling to check
+            let Some(info) = info.get(index % channels) else {
+                continue;
+            };
+            let (Value::Valid(raw), Value::Valid(scaled)) =
+                (Value::from_sample(raw), Value::from_sample(scaled))
+            else {
+                continue;
+            };
+            let Some(expected) = info.scale_raw(raw) else {
+                continue;
+            };
+            if (expected - scaled).abs() > tolerance {
+                mismatches.push(ScalingMismatch {
+                    channel: self.channels()[index % channels].clone(),
+                    frame: index / channels,
+                    raw,
+                    scaled,
+                    expected,
+                });
+            }
+        }
+
+        match mismatches.is_empty() {
+            true => Ok(()),
+            false => Err(mismatches),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChannelInfo, Data, ScalingMismatch, SCALED_OUT_OF_RANGE};
+
+    fn data(raw_data: Vec<i32>, scaled_data: Vec<f64>) -> Data {
+        Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            raw_data,
+            scaled_data,
+        )
+        .with_channel_info(vec![
+            ChannelInfo::new("Distance").with_scaling(0.01, 2.),
+            ChannelInfo::new("Intensity"),
+        ])
+    }
+
+    #[test]
+    fn test_verify_scaling_test() {
+        let data = data(vec![100, 7, 250, 8], vec![3., 1000., 4.5, 0.]);
+        assert_eq!(data.verify_scaling(1e-9), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_scaling_mismatch_test() {
+        let data = data(vec![100, 7, 250, 8, 0, 9], vec![3., 1., 4.6, 2., 2., 3.]);
+        assert_eq!(
+            data.verify_scaling(1e-3),
+            Err(vec![ScalingMismatch {
+                channel: "Distance".to_string(),
+                frame: 1,
+                raw: 250,
+                scaled: 4.6,
+                expected: 4.5,
+            }])
+        );
+        assert_eq!(data.verify_scaling(0.2), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_scaling_skips_out_of_range_test() {
+        let data = data(vec![-1, 7, 250, 8], vec![3., 1., SCALED_OUT_OF_RANGE, 0.]);
+        assert_eq!(data.verify_scaling(1e-9), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_scaling_without_scaling_test() {
+        let data = Data::new(vec!["Distance".to_string()], vec![1, 2], vec![5., 6.]);
+        assert_eq!(data.verify_scaling(1e-9), Ok(()));
+
+        let data = data.with_channel_info(vec![ChannelInfo::new("Distance")]);
+        assert_eq!(data.verify_scaling(1e-9), Ok(()));
+
+        // metadata of fewer channels than the capture has
+        let data = Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![1, 2],
+            vec![5., 6.],
+        )
+        .with_channel_info(vec![ChannelInfo::new("Distance").with_scaling(1., 4.)]);
+        assert_eq!(data.verify_scaling(1e-9), Ok(()));
+    }
+}
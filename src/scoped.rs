@@ -0,0 +1,144 @@
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::{
+    acquisition::{self, Acquire},
+    sync::StopFlag,
+    AcquisitionConfig, Data, MedaqError, Sensor,
+};
+
+impl Sensor {
+    /// Acquire in the background while `consume` runs, the acquisition thread is
+    /// joined before this returns
+    ///
+    /// Unlike [`Sensor::start_acquisition`] the consumer may borrow from the stack.
+    /// The acquisition stops when `consume` returns or panics, results not received
+    /// by then are discarded. To receive the frames drained on stop, cancel
+    /// [`AcquisitionConfig::stop_token`] inside `consume` and keep receiving until
+    /// the receiver is disconnected.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use medaqlib::{AcquisitionConfig, Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let mut sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// let mut captures = vec![];
+    /// sensor.with_acquisition(AcquisitionConfig::default(), |receiver| {
+    ///     captures.extend(receiver.iter().take(100).filter_map(Result::ok));
+    /// });
+    /// println!("{} captures", captures.len());
+    /// ```
+    pub fn with_acquisition<R>(
+        &mut self,
+        config: AcquisitionConfig,
+        consume: impl FnOnce(&Receiver<Result<Data, MedaqError>>) -> R,
+    ) -> R {
+        scoped(self, config, consume)
+    }
+}
+
+pub(crate) fn scoped<S: Acquire, R>(
+    source: S,
+    config: AcquisitionConfig,
+    consume: impl FnOnce(&Receiver<Result<Data, MedaqError>>) -> R,
+) -> R {
+    let stopped = StopFlag::default();
+    let (sender, receiver) = mpsc::sync_channel(config.channel_capacity);
+
+    thread::scope(|scope| {
+        let thread = acquisition::thread_builder(&source, &config)
+            .spawn_scoped(scope, || acquisition::run(source, config, sender, &stopped))
+            .expect("could not spawn acquisition thread");
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| consume(&receiver)));
+
+        stopped.stop();
+        thread.thread().unpark();
+        // a drain must not wait for a receiver nobody reads
+        drop(receiver);
+        if let Err(panic) = thread.join() {
+            panic::resume_unwind(panic);
+        }
+        result.unwrap_or_else(|panic| panic::resume_unwind(panic))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        time::Duration,
+    };
+
+    use super::scoped;
+    use crate::{
+        acquisition::tests::{data, Script},
+        AcquisitionConfig, StopToken,
+    };
+
+    fn config() -> AcquisitionConfig {
+        AcquisitionConfig {
+            poll_interval: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_scoped_borrows_stack_test() {
+        let mut script = Script::new((0..5).map(|i| Ok(Some(data(i as f64)))).collect());
+        let mut values = vec![];
+        let count = scoped(&mut script, config(), |receiver| {
+            for data in receiver.iter().take(3) {
+                values.push(data.unwrap().scaled_data[0]);
+            }
+            values.len()
+        });
+        assert_eq!(count, 3);
+        assert_eq!(values, vec![0., 1., 2.]);
+        // the thread is gone, the script is borrowed no longer
+        assert!(script.reads >= 3);
+    }
+
+    #[test]
+    fn test_scoped_drain_with_stop_token_test() {
+        let token = StopToken::new();
+        let config = AcquisitionConfig {
+            channel_capacity: 1,
+            stop_token: Some(token.clone()),
+            ..config()
+        };
+        let mut script = Script::new((0..10).map(|i| Ok(Some(data(i as f64)))).collect());
+        let received = scoped(&mut script, config, |receiver| {
+            receiver.recv().unwrap().unwrap();
+            token.cancel();
+            1 + receiver.iter().count()
+        });
+        assert_eq!(received, 10);
+        assert!(script.results.is_empty());
+    }
+
+    #[test]
+    fn test_scoped_consumer_panic_joins_thread_test() {
+        let mut script = Script::new(vec![Ok(Some(data(1.)))]);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            scoped(&mut script, config(), |receiver| {
+                receiver.recv().unwrap().unwrap();
+                panic!("consumer failed");
+            })
+        }));
+        let message = result.unwrap_err();
+        assert_eq!(message.downcast_ref::<&str>(), Some(&"consumer failed"));
+
+        let reads = script.reads;
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(script.reads, reads);
+    }
+}
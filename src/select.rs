@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use crate::{ChannelInfo, ChannelValue, Data, MedaqError};
+
+impl Data {
+    /// New capture holding only the given channels, in the requested order
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::Data;
+    ///
+    /// let channels = vec!["Distance".to_string(), "Intensity".to_string()];
+    /// let data = Data::new(channels, vec![], vec![1., 10., 2., 20.]);
+    /// let intensity = data.select(&["Intensity"]).unwrap();
+    /// assert_eq!(intensity.scaled_data, vec![10., 20.]);
+    /// ```
+    pub fn select(&self, channels: &[&str]) -> Result<Data, MedaqError> {
+        let indices = self.channel_indices(channels)?;
+        let count = self.channels().len();
+
+        let names: Arc<[String]> = indices
+            .iter()
+            .map(|&index| self.channels()[index].clone())
+            .collect();
+        let raw_data = restride(&self.raw_data, count, &indices);
+        let scaled_data = restride(&self.scaled_data, count, &indices);
+
+        let mut data = Data::new(names, raw_data, scaled_data);
+        data.timestamp = self.timestamp;
+        Ok(match self.channel_info() {
+            Some(info) => {
+                // channels missing from the metadata keep just their name
+                let info: Arc<[ChannelInfo]> = indices
+                    .iter()
+                    .map(|&index| {
+                        (info.get(index).cloned())
+                            .unwrap_or_else(|| ChannelInfo::new(self.channels()[index].as_str()))
+                    })
+                    .collect();
+                data.with_channel_info(info)
+            }
+            None => data,
+        })
+    }
+
+    /// Borrowing view of the given channels, in the requested order
+    pub fn view(&self, channels: &[&str]) -> Result<DataView<'_>, MedaqError> {
+        let indices = self.channel_indices(channels)?;
+        Ok(DataView {
+            data: self,
+            indices,
+        })
+    }
+
+    pub(crate) fn channel_indices(&self, channels: &[&str]) -> Result<Vec<usize>, MedaqError> {
+        channels
+            .iter()
+            .map(|&name| {
+                self.channels()
+                    .iter()
+                    .position(|channel| channel == name)
+                    .ok_or_else(|| MedaqError::UnknownChannel {
+                        name: name.to_string(),
+                        available: self.channels().to_vec(),
+                    })
+            })
+            .collect()
+    }
+}
+
+fn restride<T: Copy>(values: &[T], channels: usize, indices: &[usize]) -> Vec<T> {
+    if channels == 0 {
+        return vec![];
+    }
+    values
+        .chunks_exact(channels)
+        .flat_map(|frame| indices.iter().map(|&index| frame[index]))
+        .collect()
+}
+
+/// Subset of the channels of a capture, see [`Data::view`]
+#[derive(Debug, Clone)]
+pub struct DataView<'a> {
+    data: &'a Data,
+    indices: Vec<usize>,
+}
+
+impl<'a> DataView<'a> {
+    /// Names of the channels in the view
+    pub fn channels(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.indices
+            .iter()
+            .map(|&index| self.data.channels()[index].as_str())
+    }
+
+    /// Get raw values of very first measurement
+    pub fn get_first_raw(&self) -> Vec<ChannelValue<'a, i32>> {
+        self.pick(self.data.get_first_raw())
+    }
+
+    /// Calculates mean of raw values for the channels in the view
+    pub fn get_mean_raw(&self) -> Vec<ChannelValue<'a, f64>> {
+        self.pick(self.data.get_mean_raw())
+    }
+
+    /// Get scaled values of very first measurement
+    pub fn get_first_scaled(&self) -> Vec<ChannelValue<'a, f64>> {
+        self.pick(self.data.get_first_scaled())
+    }
+
+    /// Calculates mean of scaled values for the channels in the view
+    pub fn get_mean_scaled(&self) -> Vec<ChannelValue<'a, f64>> {
+        self.pick(self.data.get_mean_scaled())
+    }
+
+    fn pick<T: Clone>(&self, values: Vec<ChannelValue<'a, T>>) -> Vec<ChannelValue<'a, T>> {
+        self.indices
+            .iter()
+            .map(|&index| values[index].clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChannelInfo, Data, MedaqError};
+
+    fn data() -> Data {
+        Data::new(
+            vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            vec![1, 2, 3, 4, 5, 6],
+            vec![1., 2., 3., 4., 5., 6.],
+        )
+    }
+
+    #[test]
+    fn test_select_test() {
+        let data = data().with_channel_info(vec![
+            ChannelInfo::new("1"),
+            ChannelInfo::new("2"),
+            ChannelInfo::new("3").with_unit("mm"),
+        ]);
+        let selected = data.select(&["3", "1"]).unwrap();
+
+        assert_eq!(selected.channels(), ["3", "1"]);
+        assert_eq!(selected.raw_data, vec![3, 1, 6, 4]);
+        assert_eq!(selected.scaled_data, vec![3., 1., 6., 4.]);
+        assert_eq!(selected.unit(0), Some("mm"));
+    }
+
+    #[test]
+    fn test_select_short_channel_info_test() {
+        let data = data().with_channel_info(vec![ChannelInfo::new("1").with_unit("mm")]);
+        let selected = data.select(&["2", "1"]).unwrap();
+        let info = selected.channel_info().unwrap();
+        assert_eq!(info[0].name, "2");
+        assert_eq!(selected.unit(0), None);
+        assert_eq!(selected.unit(1), Some("mm"));
+    }
+
+    #[test]
+    fn test_select_unknown_channel_test() {
+        let err = data().select(&["1", "Distance"]).unwrap_err();
+        match err {
+            MedaqError::UnknownChannel { name, available } => {
+                assert_eq!(name, "Distance");
+                assert_eq!(available, vec!["1", "2", "3"]);
+            }
+            err => panic!("unexpected error {err}"),
+        }
+        assert!(data().view(&["Distance"]).is_err());
+    }
+
+    #[test]
+    fn test_view_test() {
+        let data = data();
+        let view = data.view(&["2", "3"]).unwrap();
+        assert_eq!(view.channels().collect::<Vec<_>>(), vec!["2", "3"]);
+        assert_eq!(
+            view.get_mean_scaled(),
+            data.select(&["2", "3"]).unwrap().get_mean_scaled()
+        );
+    }
+
+    /// Pseudo random capture from a linear congruential generator
+    fn random_data(seed: u64, channels: usize, frames: usize) -> Data {
+        let mut state = seed;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as f64 / (1u64 << 31) as f64
+        };
+        let scaled_data = (0..channels * frames)
+            .map(|_| match next() {
+                v if v < 0.1 => crate::SCALED_OUT_OF_RANGE,
+                v => v * 100.,
+            })
+            .collect();
+        let names: Vec<_> = (0..channels).map(|c| c.to_string()).collect();
+        Data::new(names, vec![], scaled_data)
+    }
+
+    #[test]
+    fn test_select_then_mean_equals_mean_then_pick_test() {
+        for seed in 0..50 {
+            let channels = 1 + seed as usize % 6;
+            let frames = 1 + seed as usize * 7 % 40;
+            let data = random_data(seed, channels, frames);
+
+            let names: Vec<String> = (0..channels)
+                .rev()
+                .step_by(2)
+                .map(|c| c.to_string())
+                .collect();
+            let names: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+
+            let selected = data.select(&names).unwrap();
+            let all_means = data.get_mean_scaled();
+            for (mean, name) in selected.get_mean_scaled().iter().zip(&names) {
+                let expected = all_means.iter().find(|mean| mean.channel == *name).unwrap();
+                assert_eq!(mean, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,502 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    thread::{self, ThreadId},
+};
+
+use crate::{
+    backend::Backend,
+    instrument,
+    preset::{self, ApplyReport, ParameterValue, Preset, PresetItem},
+    stats::Stats,
+    MedaqError, Sensor, ME_SENSOR,
+};
+
+/// The MEDAQLib instance of a sensor, shared by the [`Sensor`] and its
+/// [`SensorRef`]s
+pub(crate) struct SensorCore {
+    backend: Arc<dyn Backend>,
+    handle: u32,
+    calls: Mutex<Calls>,
+    /// Signalled when no thread holds the instance any more
+    idle: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Calls {
+    /// Thread holding the instance and the number of its [`Instance`]s
+    holder: Option<(ThreadId, usize)>,
+    released: bool,
+}
+
+/// Calls on the instance, the calls of other threads wait until it is dropped
+///
+/// The thread holding one can lock the instance again, so a sequence of calls,
+/// e.g. a parameter and its command, holds it across the calls of the
+/// [`Sensor`] methods it is made of.
+pub(crate) struct Instance {
+    core: Arc<SensorCore>,
+}
+
+impl SensorCore {
+    pub(crate) fn new(backend: Arc<dyn Backend>, handle: u32) -> Self {
+        Self {
+            backend,
+            handle,
+            calls: Mutex::new(Calls::default()),
+            idle: Condvar::new(),
+        }
+    }
+
+    pub(crate) fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// The backend, for calls not made on the instance
+    pub(crate) fn backend(&self) -> &dyn Backend {
+        &*self.backend
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        !self.calls().released
+    }
+
+    /// Wait until no other thread holds the instance, fails with
+    /// [`MedaqError::Closed`] once it was released
+    pub(crate) fn lock(self: &Arc<Self>) -> Result<Instance, MedaqError> {
+        let current = thread::current().id();
+        let mut calls = self.calls();
+        loop {
+            if calls.released {
+                return Err(MedaqError::Closed);
+            }
+            match &mut calls.holder {
+                None => calls.holder = Some((current, 1)),
+                Some((holder, held)) if *holder == current => *held += 1,
+                Some(_) => {
+                    calls = (self.idle.wait(calls)).unwrap_or_else(PoisonError::into_inner);
+                    continue;
+                }
+            }
+            return Ok(Instance {
+                core: Arc::clone(self),
+            });
+        }
+    }
+
+    fn calls(&self) -> MutexGuard<'_, Calls> {
+        self.calls.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// `CloseSensor` and `ReleaseSensorInstance`, once, logged at debug level with
+    /// their results
+    ///
+    /// Waits for the calls of other threads, which fail with [`MedaqError::Closed`]
+    /// afterwards.
+    pub(crate) fn release(self: &Arc<Self>) {
+        let Ok(_instance) = self.lock() else {
+            return;
+        };
+        self.calls().released = true;
+        let handle = self.handle;
+        let closed = instrument::call("CloseSensor", handle, None, || {
+            self.backend.close_sensor(handle)
+        });
+        let released = instrument::call("ReleaseSensorInstance", handle, None, || {
+            self.backend.release_instance(handle)
+        });
+        log::debug!(
+            "released sensor {handle}: CloseSensor {closed:?}, ReleaseSensorInstance {released:?}"
+        );
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let mut calls = self.core.calls();
+        if let Some((_, held)) = &mut calls.holder {
+            *held -= 1;
+            if *held == 0 {
+                calls.holder = None;
+                drop(calls);
+                self.core.idle.notify_all();
+            }
+        }
+    }
+}
+
+impl Debug for SensorCore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SensorCore")
+            .field("handle", &self.handle)
+            .field("open", &self.is_open())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Instance {
+    pub(crate) fn open_sensor(&self) -> Result<(), MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("OpenSensor", *handle, None, || backend.open_sensor(*handle))
+    }
+
+    pub(crate) fn exec_scmd(&self, command: &str) -> Result<(), MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("ExecSCmd", *handle, Some(command), || {
+            backend.exec_scmd(*handle, command)
+        })
+    }
+
+    pub(crate) fn set_parameter_string(&self, name: &str, value: &str) -> Result<(), MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("SetParameterString", *handle, Some(name), || {
+            backend.set_parameter_string(*handle, name, value)
+        })
+    }
+
+    pub(crate) fn set_parameter_int(&self, name: &str, value: i32) -> Result<(), MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("SetParameterInt", *handle, Some(name), || {
+            backend.set_parameter_int(*handle, name, value)
+        })
+    }
+
+    pub(crate) fn set_parameter_double(&self, name: &str, value: f64) -> Result<(), MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("SetParameterDouble", *handle, Some(name), || {
+            backend.set_parameter_double(*handle, name, value)
+        })
+    }
+
+    pub(crate) fn get_parameter_int(&self, name: &str) -> Result<i32, MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("GetParameterInt", *handle, Some(name), || {
+            backend.get_parameter_int(*handle, name)
+        })
+    }
+
+    pub(crate) fn get_parameter_double(&self, name: &str) -> Result<f64, MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("GetParameterDouble", *handle, Some(name), || {
+            backend.get_parameter_double(*handle, name)
+        })
+    }
+
+    pub(crate) fn get_parameter_string(&self, name: &str) -> Result<String, MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("GetParameterString", *handle, Some(name), || {
+            backend.get_parameter_string(*handle, name)
+        })
+    }
+
+    pub(crate) fn get_error(&self) -> String {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("GetError", *handle, None, || backend.get_error(*handle))
+    }
+
+    pub(crate) fn data_avail(&self) -> Result<i32, MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("DataAvail", *handle, None, || backend.data_avail(*handle))
+    }
+
+    pub(crate) fn transfer_data(
+        &self,
+        raw: Option<&mut [i32]>,
+        scaled: Option<&mut [f64]>,
+        max_values: usize,
+    ) -> Result<usize, MedaqError> {
+        let SensorCore {
+            backend, handle, ..
+        } = &*self.core;
+        instrument::call("TransferData", *handle, None, || {
+            backend.transfer_data(*handle, raw, scaled, max_values)
+        })
+    }
+}
+
+/// Shared handle to a [`Sensor`] for status queries and settings from other
+/// threads, see [`Sensor::clone_ref`]
+///
+/// The calls of all handles and of the sensor are made one at a time, a call waits
+/// while another thread calls into the same instance or runs a sequence of calls
+/// on it, e.g. a read of an acquisition or a setter of [`crate::sensors`]. Reading
+/// data stays with the [`Sensor`], and so does the instance: closing or dropping
+/// the sensor releases it, the calls of the handles fail with
+/// [`MedaqError::Closed`] from then on. Dropping a handle releases nothing.
+#[derive(Debug, Clone)]
+pub struct SensorRef {
+    core: Arc<SensorCore>,
+    sensor_type: ME_SENSOR,
+    stats: Stats,
+}
+
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SensorRef>();
+};
+
+impl SensorRef {
+    /// See [`Sensor::sensor_type`]
+    pub fn sensor_type(&self) -> ME_SENSOR {
+        self.sensor_type
+    }
+
+    /// Whether the [`Sensor`] is still open
+    pub fn is_open(&self) -> bool {
+        self.core.is_open()
+    }
+
+    /// See [`Sensor::get_parameter_int`]
+    pub fn get_parameter_int(&self, param_name: &str) -> Result<i32, MedaqError> {
+        self.core.lock()?.get_parameter_int(param_name)
+    }
+
+    /// See [`Sensor::get_parameter_double`]
+    pub fn get_parameter_double(&self, param_name: &str) -> Result<f64, MedaqError> {
+        self.core.lock()?.get_parameter_double(param_name)
+    }
+
+    /// See [`Sensor::get_parameter_string`]
+    pub fn get_parameter_string(&self, param_name: &str) -> Result<String, MedaqError> {
+        self.core.lock()?.get_parameter_string(param_name)
+    }
+
+    /// See [`Sensor::last_error`], also `None` once the sensor was closed
+    pub fn last_error(&self) -> Option<String> {
+        let text = self.core.lock().ok()?.get_error();
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Apply a preset like [`Sensor::apply_preset`], without calls of other
+    /// threads in between, e.g. switching the laser off
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{Interface, MockBackend, Preset, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1420)
+    ///     .with_interface(Interface::Simulated)
+    ///     .with_backend(MockBackend::new().with_channels(["Distance"]))
+    ///     .connect()
+    ///     .unwrap();
+    /// let sensor_ref = sensor.clone_ref();
+    /// let laser_off = Preset::new()
+    ///     .with_int("SP_LaserPower", 2)
+    ///     .with_command("Set_LaserPower");
+    /// std::thread::spawn(move || sensor_ref.apply_preset(&laser_off))
+    ///     .join()
+    ///     .unwrap()
+    ///     .unwrap();
+    /// ```
+    pub fn apply_preset(&self, preset: &Preset) -> Result<ApplyReport, MedaqError> {
+        let instance = self.core.lock()?;
+        preset::apply(preset, |item| {
+            let (name, result) = match item {
+                PresetItem::Parameter { parameter, value } => (
+                    parameter,
+                    match value {
+                        ParameterValue::Int(value) => instance.set_parameter_int(parameter, *value),
+                        ParameterValue::Double(value) => {
+                            instance.set_parameter_double(parameter, *value)
+                        }
+                        ParameterValue::String(value) => {
+                            instance.set_parameter_string(parameter, value)
+                        }
+                    },
+                ),
+                PresetItem::Command { command } => (command, instance.exec_scmd(command)),
+            };
+            self.stats.record_call(name, &result);
+            result
+        })
+    }
+}
+
+impl Sensor {
+    /// Keep the calls of [`SensorRef`]s on other threads out until the instance
+    /// is dropped, for a sequence of calls such as the `SP_` parameters and their
+    /// command or a command and its `SA_` answers
+    pub(crate) fn hold(&self) -> Result<Instance, MedaqError> {
+        self.core.lock()
+    }
+
+    /// Handle to this sensor for status queries and settings from other threads,
+    /// e.g. while an acquisition reads on its own thread
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{AcquisitionConfig, Interface, MockBackend, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1420)
+    ///     .with_interface(Interface::Simulated)
+    ///     .with_backend(MockBackend::new().with_channels(["Distance"]))
+    ///     .connect()
+    ///     .unwrap();
+    /// let sensor_ref = sensor.clone_ref();
+    /// let acquisition = sensor.start_acquisition(AcquisitionConfig::default());
+    /// println!("{:?}", sensor_ref.get_parameter_string("IP_Interface"));
+    /// acquisition.join();
+    /// assert!(!sensor_ref.is_open());
+    /// ```
+    pub fn clone_ref(&self) -> SensorRef {
+        SensorRef {
+            core: Arc::clone(&self.core),
+            sensor_type: self.sensor_type,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Barrier},
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        sensors::ild::{Ild1420Ext, IldLaserPower},
+        Call, MedaqError, MockBackend, Preset, ME_SENSOR,
+    };
+
+    fn count(mock: &MockBackend, call: Call) -> usize {
+        mock.calls()
+            .iter()
+            .filter(|recorded| recorded.call == call)
+            .count()
+    }
+
+    #[test]
+    fn test_sensor_ref_serialized_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance"])
+            .with_parameter_string("SA_Info", "ILD1420-10");
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        let sensor_ref = sensor.clone_ref();
+        mock.push_frame(&[1], &[0.5]);
+        mock.stall_call(Call::TransferData, 1, Duration::from_millis(100));
+
+        // returns the sensor, so it is not closed before the query
+        let reader = thread::spawn(move || {
+            let data = sensor.read_data().unwrap();
+            (sensor, data)
+        });
+        // while the transfer stalls
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(
+            sensor_ref.get_parameter_string("SA_Info").unwrap(),
+            "ILD1420-10"
+        );
+        let (_sensor, data) = reader.join().unwrap();
+        assert_eq!(data.unwrap().raw_data, [1]);
+
+        let calls = mock.calls();
+        let position = |call| calls.iter().rposition(|recorded| recorded.call == call);
+        assert!(position(Call::TransferData) < position(Call::GetParameterString));
+    }
+
+    #[test]
+    fn test_sensor_ref_sequences_test() {
+        const REFS: usize = 3;
+        const RUNS: usize = 1000;
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        let connected = mock.calls().len();
+        let start = Arc::new(Barrier::new(REFS + 1));
+
+        // presets through the refs racing the ild setter between its parameter and
+        // its command
+        let presets: Vec<_> = (0..REFS)
+            .map(|_| {
+                let sensor_ref = sensor.clone_ref();
+                let start = Arc::clone(&start);
+                thread::spawn(move || {
+                    let laser_off = Preset::new()
+                        .with_int("SP_LaserPower", 2)
+                        .with_command("Set_LaserPower");
+                    start.wait();
+                    for _ in 0..RUNS {
+                        assert!(sensor_ref.apply_preset(&laser_off).unwrap().is_success());
+                    }
+                })
+            })
+            .collect();
+        let ild = sensor.ild1420().unwrap();
+        start.wait();
+        for _ in 0..RUNS {
+            ild.set_laser_power(IldLaserPower::Reduced).unwrap();
+        }
+        for preset in presets {
+            preset.join().unwrap();
+        }
+
+        let settings: Vec<_> = mock.calls()[connected..]
+            .iter()
+            .filter(|recorded| matches!(recorded.call, Call::SetParameterInt | Call::ExecSCmd))
+            .map(|recorded| recorded.arguments.join("="))
+            .collect();
+        assert_eq!(settings.len(), 2 * (REFS + 1) * RUNS);
+        for pair in settings.chunks(2) {
+            assert!(pair[0].starts_with("SP_LaserPower="), "{pair:?}");
+            assert_eq!(pair[1], "Set_LaserPower", "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn test_sensor_ref_released_once_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        let sensor_ref = sensor.clone_ref();
+        let laser_off = Preset::new()
+            .with_int("SP_LaserPower", 2)
+            .with_command("Set_LaserPower");
+        assert!(sensor_ref.apply_preset(&laser_off).unwrap().is_success());
+        assert_eq!(mock.commands().last().unwrap(), "Set_LaserPower");
+
+        sensor.close();
+        assert!(!mock.is_open());
+        assert!(!sensor_ref.is_open());
+        assert!(matches!(
+            sensor_ref.get_parameter_int("SP_LaserPower"),
+            Err(MedaqError::Closed)
+        ));
+        assert!(matches!(
+            sensor_ref.apply_preset(&laser_off),
+            Err(MedaqError::Closed)
+        ));
+        assert_eq!(sensor_ref.last_error(), None);
+        drop(sensor_ref);
+        assert_eq!(count(&mock, Call::CloseSensor), 1);
+        assert_eq!(count(&mock, Call::ReleaseInstance), 1);
+
+        // the other way round
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        drop(sensor.clone_ref());
+        assert!(mock.is_open());
+        drop(sensor);
+        assert_eq!(count(&mock, Call::CloseSensor), 1);
+        assert_eq!(count(&mock, Call::ReleaseInstance), 1);
+    }
+}
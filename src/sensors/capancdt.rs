@@ -0,0 +1,327 @@
+//! Capacitive controllers capaNCDT DT61xx and DT6200, e.g. the DT6222
+//!
+//! A controller has up to [`MAX_CHANNELS`] channels, the
+//! [`max_channels`](crate::Capabilities::max_channels) of the model, every active channel adds a
+//! value to each frame, in the order of the channels. Channels are counted from
+//! `1` and passed as `SP_Channel` to the channel commands.
+//!
+//! # Example
+//! ```no_run
+//! use medaqlib::{
+//!     sensors::capancdt::{CapaFilter, CapaMath, CapaNcdtExt},
+//!     Interface, SensorBuilder, ME_SENSOR,
+//! };
+//!
+//! let mut sensor = SensorBuilder::new(ME_SENSOR::CONTROLLER_DT6200)
+//!     .with_interface(Interface::TcpIp)
+//!     .with_ip_address("10.10.10.10")
+//!     .connect()
+//!     .unwrap();
+//!
+//! let mut capa = sensor.capancdt().unwrap();
+//! capa.set_active_channels(&[1, 2]).unwrap();
+//! capa.set_measuring_range(1, 0.5).unwrap();
+//! capa.set_measuring_range(2, 0.5).unwrap();
+//! // gap between both probes
+//! capa.set_math_function(1, &CapaMath::new(0.).with_factor(1, 1.).with_factor(2, 1.))
+//!     .unwrap();
+//! capa.set_filter(1, CapaFilter::Average(16)).unwrap();
+//! println!("{:?}", sensor.parameters());
+//! ```
+
+use crate::{MedaqError, Sensor, ME_SENSOR};
+
+/// Most channels of a controller
+pub const MAX_CHANNELS: usize = 8;
+
+/// The sensor types of the controllers
+const CONTROLLERS: [ME_SENSOR; 2] = [ME_SENSOR::SENSOR_DT6120, ME_SENSOR::CONTROLLER_DT6200];
+
+/// Math function of a channel, `offset + factor1 * channel1 + ...` of the
+/// measured values
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CapaMath {
+    offset: f64,
+    factors: [f64; MAX_CHANNELS],
+}
+
+impl CapaMath {
+    /// Only the offset, add the channels with [`with_factor`](Self::with_factor)
+    pub fn new(offset: f64) -> Self {
+        Self {
+            offset,
+            ..Self::default()
+        }
+    }
+
+    /// Add `factor` times channel `channel`, replacing its factor so far
+    ///
+    /// # Panics
+    /// If the channel is not within `1..=8`.
+    pub fn with_factor(mut self, channel: usize, factor: f64) -> Self {
+        assert!(
+            (1..=MAX_CHANNELS).contains(&channel),
+            "channel {channel} of a capaNCDT controller"
+        );
+        self.factors[channel - 1] = factor;
+        self
+    }
+}
+
+/// Averaging of a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CapaFilter {
+    Off,
+    /// Moving average over the number of values
+    Average(u16),
+    /// Median of the number of values
+    Median(u16),
+}
+
+/// Extension of [`Sensor`] for capaNCDT controllers
+pub trait CapaNcdtExt {
+    /// The settings, fails with [`MedaqError::WrongSensorType`] unless connected as
+    /// [`ME_SENSOR::SENSOR_DT6120`] or [`ME_SENSOR::CONTROLLER_DT6200`]
+    fn capancdt(&mut self) -> Result<CapaNcdt<'_>, MedaqError>;
+}
+
+impl CapaNcdtExt for Sensor {
+    fn capancdt(&mut self) -> Result<CapaNcdt<'_>, MedaqError> {
+        super::check_type(self, &CONTROLLERS)?;
+        Ok(CapaNcdt { sensor: self })
+    }
+}
+
+/// Typed settings of a capaNCDT controller, see [`CapaNcdtExt`]
+///
+/// Settings changing the transmitted channels or their scaling refresh the
+/// channels of the sensor, see [`Sensor::refresh_channels`].
+#[derive(Debug)]
+pub struct CapaNcdt<'a> {
+    sensor: &'a mut Sensor,
+}
+
+impl CapaNcdt<'_> {
+    /// `Set_ChannelStatus`, transmit the listed channels and no others
+    pub fn set_active_channels(&mut self, channels: &[usize]) -> Result<(), MedaqError> {
+        for &channel in channels {
+            self.check_channel(channel)?;
+        }
+        let _instance = self.sensor.hold()?;
+        for channel in 1..=self.sensor.capabilities().max_channels {
+            let active = channels.contains(&channel);
+            self.sensor
+                .set_parameter_int(&format!("SP_ChannelStatus{channel}"), active.into())?;
+        }
+        self.sensor.execute_command("Set_ChannelStatus")?;
+        self.sensor.refresh_channels()
+    }
+
+    /// `Set_MeasuringRange`, register the measuring range of the probe connected to
+    /// a channel in mm, which scales its values
+    pub fn set_measuring_range(&mut self, channel: usize, range_mm: f64) -> Result<(), MedaqError> {
+        let _instance = self.sensor.hold()?;
+        self.select(channel)?;
+        self.sensor
+            .set_parameter_double("SP_MeasuringRange", range_mm)?;
+        self.sensor.execute_command("Set_MeasuringRange")?;
+        self.sensor.refresh_channels()
+    }
+
+    /// `Set_MathFunction` of a channel
+    pub fn set_math_function(&mut self, channel: usize, math: &CapaMath) -> Result<(), MedaqError> {
+        let _instance = self.sensor.hold()?;
+        self.select(channel)?;
+        self.sensor
+            .set_parameter_double("SP_MathOffset", math.offset)?;
+        for (index, factor) in math.factors.iter().enumerate() {
+            let name = format!("SP_MathFactor{}", index + 1);
+            self.sensor.set_parameter_double(&name, *factor)?;
+        }
+        self.sensor.execute_command("Set_MathFunction")
+    }
+
+    /// `Set_Filter` of a channel
+    pub fn set_filter(&mut self, channel: usize, filter: CapaFilter) -> Result<(), MedaqError> {
+        let (mode, depth) = match filter {
+            CapaFilter::Off => (0, 1),
+            CapaFilter::Average(depth) => (1, depth),
+            CapaFilter::Median(depth) => (2, depth),
+        };
+        let _instance = self.sensor.hold()?;
+        self.select(channel)?;
+        self.sensor.set_parameter_int("SP_FilterMode", mode)?;
+        self.sensor
+            .set_parameter_int("SP_FilterDepth", depth.into())?;
+        self.sensor.execute_command("Set_Filter")
+    }
+
+    /// The channel of the next command
+    fn select(&self, channel: usize) -> Result<(), MedaqError> {
+        self.check_channel(channel)?;
+        self.sensor.set_parameter_int("SP_Channel", channel as i32)
+    }
+
+    fn check_channel(&self, channel: usize) -> Result<(), MedaqError> {
+        let channels = self.sensor.capabilities().max_channels;
+        match (1..=channels).contains(&channel) {
+            true => Ok(()),
+            false => Err(MedaqError::ChannelOutOfRange { channel, channels }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CapaFilter, CapaMath, CapaNcdtExt};
+    use crate::{Call, Intercept, MedaqError, MockBackend, Sensor, ME_SENSOR};
+
+    /// Controller transmitting all 8 channels until 4 are activated
+    fn connect(mock: &MockBackend) -> Sensor {
+        let mut activated = false;
+        mock.intercept(move |call| match call.call {
+            Call::ExecSCmd if call.arguments[0] == "Set_ChannelStatus" => {
+                activated = true;
+                Intercept::Pass
+            }
+            // the names end after the fourth channel
+            Call::GetParameterString if activated && call.arguments[0] == "IA_Scaled_Name5" => {
+                Intercept::String(String::new())
+            }
+            _ => Intercept::Pass,
+        });
+        mock.connect(ME_SENSOR::CONTROLLER_DT6200)
+    }
+
+    /// Parameters set and commands executed since `since` calls
+    fn settings(mock: &MockBackend, since: usize) -> Vec<String> {
+        let settings = [
+            Call::SetParameterInt,
+            Call::SetParameterDouble,
+            Call::ExecSCmd,
+        ];
+        mock.calls()[since..]
+            .iter()
+            .filter(|call| settings.contains(&call.call))
+            .map(|call| call.arguments.join("="))
+            .collect()
+    }
+
+    #[test]
+    fn test_capancdt_four_channels_test() {
+        let channels = (1..=8).map(|channel| format!("Channel {channel}"));
+        let mock = MockBackend::new().with_channels(channels);
+        let mut sensor = connect(&mock);
+        assert_eq!(sensor.parameters().len(), 8);
+
+        let since = mock.calls().len();
+        let mut capa = sensor.capancdt().unwrap();
+        capa.set_active_channels(&[1, 2, 3, 4]).unwrap();
+        let mut expected: Vec<String> = (1..=8)
+            .map(|channel| format!("SP_ChannelStatus{channel}={}", (channel <= 4) as i32))
+            .collect();
+        expected.extend(["Set_ChannelStatus", "Get_TransmittedDataInfo"].map(String::from));
+        assert_eq!(settings(&mock, since), expected);
+        assert_eq!(
+            sensor.parameters(),
+            ["Channel 1", "Channel 2", "Channel 3", "Channel 4"]
+        );
+
+        mock.push_frame(&[1, 2, 3, 4], &[0.1, 0.2, 0.3, 0.4]);
+        let data = sensor.read_data().unwrap().unwrap();
+        assert_eq!(data.frame_count(), 1);
+        assert_eq!(data.scaled_data, [0.1, 0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn test_capancdt_channel_settings_test() {
+        let mock = MockBackend::new().with_channels(["Channel 1", "Channel 2"]);
+        let mut sensor = connect(&mock);
+        let mut capa = sensor.capancdt().unwrap();
+
+        let since = mock.calls().len();
+        capa.set_measuring_range(2, 0.5).unwrap();
+        capa.set_filter(2, CapaFilter::Median(7)).unwrap();
+        capa.set_filter(1, CapaFilter::Off).unwrap();
+        assert_eq!(
+            settings(&mock, since),
+            [
+                "SP_Channel=2",
+                "SP_MeasuringRange=0.5",
+                "Set_MeasuringRange",
+                "Get_TransmittedDataInfo",
+                "SP_Channel=2",
+                "SP_FilterMode=2",
+                "SP_FilterDepth=7",
+                "Set_Filter",
+                "SP_Channel=1",
+                "SP_FilterMode=0",
+                "SP_FilterDepth=1",
+                "Set_Filter",
+            ]
+        );
+
+        let since = mock.calls().len();
+        let math = CapaMath::new(-0.5).with_factor(1, 1.).with_factor(2, -1.);
+        capa.set_math_function(3, &math).unwrap();
+        let mut expected = vec!["SP_Channel=3".to_string(), "SP_MathOffset=-0.5".into()];
+        expected.extend(
+            [1., -1., 0., 0., 0., 0., 0., 0.]
+                .iter()
+                .enumerate()
+                .map(|(index, factor)| format!("SP_MathFactor{}={factor}", index + 1)),
+        );
+        expected.push("Set_MathFunction".into());
+        assert_eq!(settings(&mock, since), expected);
+
+        let since = mock.calls().len();
+        for result in [
+            capa.set_active_channels(&[1, 9]),
+            capa.set_filter(0, CapaFilter::Off),
+        ] {
+            assert!(matches!(
+                result,
+                Err(MedaqError::ChannelOutOfRange { channels: 8, .. })
+            ));
+        }
+        assert_eq!(mock.calls().len(), since);
+    }
+
+    #[test]
+    fn test_capancdt_wrong_sensor_type_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let mut sensor = mock.connect(ME_SENSOR::SENSOR_IFD2421);
+        assert!(matches!(
+            sensor.capancdt(),
+            Err(MedaqError::WrongSensorType {
+                found: ME_SENSOR::SENSOR_IFD2421,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_capancdt_single_channel_test() {
+        let mock = MockBackend::new().with_channels(["Channel 1"]);
+        let mut sensor = mock.connect(ME_SENSOR::SENSOR_DT6120);
+        let mut capa = sensor.capancdt().unwrap();
+        assert!(matches!(
+            capa.set_active_channels(&[2]),
+            Err(MedaqError::ChannelOutOfRange {
+                channel: 2,
+                channels: 1
+            })
+        ));
+
+        let since = mock.calls().len();
+        capa.set_active_channels(&[1]).unwrap();
+        assert_eq!(
+            settings(&mock, since),
+            [
+                "SP_ChannelStatus1=1",
+                "Set_ChannelStatus",
+                "Get_TransmittedDataInfo"
+            ]
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Confocal chromatic controllers confocalDT IFC2451, IFC2461, IFC2465 and IFC2471
+//!
+//! A controller measures with one or more channels, each with its own exposure,
+//! material and peak settings. Channel commands get the channel, counted from `1`,
+//! as `SP_Channel` before their own parameters.
+//!
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use medaqlib::{
+//!     sensors::ifc::{ConfocalController, ConfocalPeak},
+//!     Interface, SensorBuilder, ME_SENSOR,
+//! };
+//!
+//! let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2465)
+//!     .with_interface(Interface::TcpIp)
+//!     .with_ip_address("10.10.10.10")
+//!     .connect()
+//!     .unwrap();
+//!
+//! let controller = ConfocalController::new(&sensor).unwrap();
+//! controller.dark_reference(Duration::from_secs(10)).unwrap();
+//! let channel = controller.channel(1).unwrap();
+//! channel.set_exposure_time(Duration::from_micros(500)).unwrap();
+//! channel.set_material("BK7").unwrap();
+//! channel.set_peak(ConfocalPeak::First).unwrap();
+//! ```
+
+use std::time::Duration;
+
+use crate::{MedaqError, Sensor, ME_SENSOR};
+
+/// The sensor types of the controllers
+const CONTROLLERS: [ME_SENSOR; 4] = [
+    ME_SENSOR::SENSOR_IFD2451,
+    ME_SENSOR::SENSOR_IFD2461,
+    ME_SENSOR::SENSOR_IFD2465,
+    ME_SENSOR::SENSOR_IFD2471,
+];
+
+/// Peak of the spectrum used for the distance, for transparent targets reflecting
+/// on both surfaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfocalPeak {
+    /// Highest intensity
+    Highest,
+    /// Nearest to the sensor
+    First,
+    /// Farthest from the sensor
+    Last,
+}
+
+/// Confocal controller connected as a [`Sensor`]
+#[derive(Debug, Clone, Copy)]
+pub struct ConfocalController<'a> {
+    sensor: &'a Sensor,
+    channels: usize,
+}
+
+/// Settings of one channel of a [`ConfocalController`]
+#[derive(Debug, Clone, Copy)]
+pub struct ConfocalChannel<'a> {
+    sensor: &'a Sensor,
+    channel: usize,
+}
+
+impl<'a> ConfocalController<'a> {
+    /// Check the sensor type and ask the controller for its channels with
+    /// `Get_Info`
+    ///
+    /// Fails with [`MedaqError::WrongSensorType`] unless connected as one of the
+    /// controllers. A controller not reporting `SA_ChannelCount` has one channel.
+    pub fn new(sensor: &'a Sensor) -> Result<Self, MedaqError> {
+        super::check_type(sensor, &CONTROLLERS)?;
+        let _instance = sensor.hold()?;
+        sensor.execute_command("Get_Info")?;
+        let channels = match sensor.get_parameter_int("SA_ChannelCount") {
+            Ok(channels) => channels.max(1) as usize,
+            Err(MedaqError::Sensor(_) | MedaqError::UnsupportedByDll { .. }) => 1,
+            Err(err) => return Err(err),
+        };
+        Ok(Self { sensor, channels })
+    }
+
+    /// Number of channels reported by the controller
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Channel `channel`, counted from `1`, fails with
+    /// [`MedaqError::ChannelOutOfRange`] beyond [`channels`](Self::channels)
+    pub fn channel(&self, channel: usize) -> Result<ConfocalChannel<'a>, MedaqError> {
+        match (1..=self.channels).contains(&channel) {
+            true => Ok(ConfocalChannel {
+                sensor: self.sensor,
+                channel,
+            }),
+            false => Err(MedaqError::ChannelOutOfRange {
+                channel,
+                channels: self.channels,
+            }),
+        }
+    }
+
+    /// `Set_DarkReference`, takes the dark spectrum of every channel, with the
+    /// measuring range free of targets
+    ///
+    /// Waits until `SA_DarkReferenceStatus` of `Get_DarkReferenceStatus` reports
+    /// the reference as done, fails with [`MedaqError::CommandTimeout`] after
+    /// `timeout`.
+    pub fn dark_reference(&self, timeout: Duration) -> Result<(), MedaqError> {
+        let status = ("Get_DarkReferenceStatus", "SA_DarkReferenceStatus");
+        super::run_until_done(self.sensor, "Set_DarkReference", status, timeout)
+    }
+
+    /// Names of the material table of the controller with `Get_MaterialTable`, see
+    /// [`ConfocalChannel::set_material`]
+    pub fn materials(&self) -> Result<Vec<String>, MedaqError> {
+        let _instance = self.sensor.hold()?;
+        self.sensor.execute_command("Get_MaterialTable")?;
+        let mut materials = vec![];
+        for counter in 1.. {
+            match self
+                .sensor
+                .get_parameter_string(&format!("SA_MaterialName{counter}"))
+            {
+                Ok(name) if !name.is_empty() => materials.push(name),
+                Ok(_) | Err(MedaqError::Sensor(_)) => break,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(materials)
+    }
+}
+
+impl ConfocalChannel<'_> {
+    /// The channel, counted from `1`
+    pub fn index(&self) -> usize {
+        self.channel
+    }
+
+    /// `Set_ExposureTime`, in whole microseconds
+    pub fn set_exposure_time(&self, exposure: Duration) -> Result<(), MedaqError> {
+        let micros = exposure.as_micros().try_into().unwrap_or(i32::MAX);
+        let _instance = self.sensor.hold()?;
+        self.select()?;
+        self.sensor.set_parameter_int("SP_ExposureTime", micros)?;
+        self.sensor.execute_command("Set_ExposureTime")
+    }
+
+    /// `Set_Material`, the refractive index of the target for thickness measurements
+    /// by its name in the material table, see [`ConfocalController::materials`]
+    pub fn set_material(&self, material: &str) -> Result<(), MedaqError> {
+        let _instance = self.sensor.hold()?;
+        self.select()?;
+        self.sensor.set_parameter_string("SP_Material", material)?;
+        self.sensor.execute_command("Set_Material")
+    }
+
+    /// `Set_PeakSelection`
+    pub fn set_peak(&self, peak: ConfocalPeak) -> Result<(), MedaqError> {
+        let peak = match peak {
+            ConfocalPeak::Highest => 0,
+            ConfocalPeak::First => 1,
+            ConfocalPeak::Last => 2,
+        };
+        let _instance = self.sensor.hold()?;
+        self.select()?;
+        self.sensor.set_parameter_int("SP_PeakSelection", peak)?;
+        self.sensor.execute_command("Set_PeakSelection")
+    }
+
+    /// The channel of the next command
+    fn select(&self) -> Result<(), MedaqError> {
+        // at most a few channels, always fits
+        self.sensor
+            .set_parameter_int("SP_Channel", self.channel as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ConfocalController, ConfocalPeak};
+    use crate::{Call, Intercept, MedaqError, MockBackend, ME_SENSOR};
+
+    /// Parameters set and commands executed after connecting
+    fn settings(mock: &MockBackend, connected: usize) -> Vec<String> {
+        let settings = [
+            Call::SetParameterInt,
+            Call::SetParameterString,
+            Call::ExecSCmd,
+        ];
+        mock.calls()[connected..]
+            .iter()
+            .filter(|call| settings.contains(&call.call))
+            .map(|call| call.arguments.join("="))
+            .collect()
+    }
+
+    #[test]
+    fn test_ifc_channel_settings_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance1", "Distance2"])
+            .with_parameter_int("SA_ChannelCount", 2);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_IFD2465);
+        let connected = mock.calls().len();
+        let controller = ConfocalController::new(&sensor).unwrap();
+        assert_eq!(controller.channels(), 2);
+
+        let channel = controller.channel(2).unwrap();
+        assert_eq!(channel.index(), 2);
+        channel
+            .set_exposure_time(Duration::from_micros(500))
+            .unwrap();
+        channel.set_material("BK7").unwrap();
+        controller
+            .channel(1)
+            .unwrap()
+            .set_peak(ConfocalPeak::Last)
+            .unwrap();
+        assert_eq!(
+            settings(&mock, connected),
+            [
+                "Get_Info",
+                "SP_Channel=2",
+                "SP_ExposureTime=500",
+                "Set_ExposureTime",
+                "SP_Channel=2",
+                "SP_Material=BK7",
+                "Set_Material",
+                "SP_Channel=1",
+                "SP_PeakSelection=2",
+                "Set_PeakSelection",
+            ]
+        );
+
+        for channel in [0, 3] {
+            assert!(matches!(
+                controller.channel(channel),
+                Err(MedaqError::ChannelOutOfRange { channels: 2, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_ifc_dark_reference_test() {
+        let mock = MockBackend::new().with_channels(["Distance1"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_IFD2461);
+        let controller = ConfocalController::new(&sensor).unwrap();
+        let mut answers = vec!["0", "1", "1"];
+        mock.intercept(move |call| match call.call {
+            Call::GetParameterString if call.arguments[0] == "SA_DarkReferenceStatus" => {
+                Intercept::String(answers.pop().unwrap_or("1").into())
+            }
+            _ => Intercept::Pass,
+        });
+
+        let connected = mock.commands().len();
+        controller.dark_reference(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            mock.commands()[connected..],
+            [
+                "Set_DarkReference",
+                "Get_DarkReferenceStatus",
+                "Get_DarkReferenceStatus",
+                "Get_DarkReferenceStatus",
+            ]
+        );
+
+        // busy from now on
+        let err = controller
+            .dark_reference(Duration::from_millis(30))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            MedaqError::CommandTimeout {
+                command: "Set_DarkReference",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_ifc_materials_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance1"])
+            .with_parameter_string("SA_MaterialName1", "Vacuum")
+            .with_parameter_string("SA_MaterialName2", "BK7");
+        let sensor = mock.connect(ME_SENSOR::SENSOR_IFD2451);
+        let controller = ConfocalController::new(&sensor).unwrap();
+        // no SA_ChannelCount
+        assert_eq!(controller.channels(), 1);
+        assert_eq!(controller.materials().unwrap(), ["Vacuum", "BK7"]);
+        assert_eq!(mock.commands().last().unwrap(), "Get_MaterialTable");
+    }
+
+    #[test]
+    fn test_ifc_wrong_sensor_type_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        let err = ConfocalController::new(&sensor).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("expected a SENSOR_IFD2451 or SENSOR_IFD2461"));
+        assert_eq!(mock.commands(), ["Get_TransmittedDataInfo"]);
+    }
+}
@@ -0,0 +1,311 @@
+//! Laser triangulation sensors optoNCDT ILD1420 and ILD1900
+//!
+//! # Example
+//! ```no_run
+//! use medaqlib::{
+//!     sensors::ild::{Ild1420Ext, IldPeak, IldRate},
+//!     Interface, SensorBuilder, ME_SENSOR,
+//! };
+//!
+//! let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ILD1420)
+//!     .with_interface(Interface::TcpIp)
+//!     .with_ip_address("10.10.10.10")
+//!     .connect()
+//!     .unwrap();
+//!
+//! let ild = sensor.ild1420().unwrap();
+//! ild.set_measuring_rate(IldRate::Khz2).unwrap();
+//! // the rear surface of a glass pane
+//! ild.set_peak(IldPeak::Last).unwrap();
+//! ```
+
+use std::ops::Range;
+
+use crate::{MedaqError, Sensor, ME_SENSOR};
+
+/// Measuring rate presets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IldRate {
+    Hz250,
+    Hz500,
+    Khz1,
+    Khz2,
+    Khz4,
+    /// ILD1900 only
+    Khz8,
+    /// ILD1900 only
+    Khz10,
+}
+
+impl IldRate {
+    /// Rate in kHz, as passed to `SP_Measrate`
+    pub fn khz(self) -> f64 {
+        match self {
+            IldRate::Hz250 => 0.25,
+            IldRate::Hz500 => 0.5,
+            IldRate::Khz1 => 1.,
+            IldRate::Khz2 => 2.,
+            IldRate::Khz4 => 4.,
+            IldRate::Khz8 => 8.,
+            IldRate::Khz10 => 10.,
+        }
+    }
+}
+
+/// Peak of the video signal used for the distance, for targets reflecting more
+/// than once such as glass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IldPeak {
+    /// Highest intensity
+    Highest,
+    /// Largest area
+    Widest,
+    /// Farthest from the sensor
+    Last,
+    /// Nearest to the sensor
+    First,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IldLaserPower {
+    Full,
+    Reduced,
+    Off,
+}
+
+/// What differs between the models
+#[derive(Debug)]
+struct Model {
+    sensor_types: &'static [ME_SENSOR],
+    /// The end of the region of interest is passed as its size
+    roi_size: bool,
+}
+
+const ILD1420: Model = Model {
+    sensor_types: &[ME_SENSOR::SENSOR_ILD1420],
+    roi_size: true,
+};
+
+const ILD1900: Model = Model {
+    sensor_types: &[ME_SENSOR::SENSOR_ILD1900],
+    roi_size: false,
+};
+
+/// Typed settings of an ILD sensor, see [`Ild1420Ext`] and [`Ild1900Ext`]
+#[derive(Debug, Clone, Copy)]
+pub struct Ild<'a> {
+    sensor: &'a Sensor,
+    model: &'static Model,
+}
+
+/// Settings of an ILD1420
+pub trait Ild1420Ext {
+    /// The settings, fails with [`MedaqError::WrongSensorType`] unless connected as
+    /// [`ME_SENSOR::SENSOR_ILD1420`]
+    fn ild1420(&self) -> Result<Ild<'_>, MedaqError>;
+}
+
+/// Settings of an ILD1900
+pub trait Ild1900Ext {
+    /// The settings, fails with [`MedaqError::WrongSensorType`] unless connected as
+    /// [`ME_SENSOR::SENSOR_ILD1900`]
+    fn ild1900(&self) -> Result<Ild<'_>, MedaqError>;
+}
+
+impl Ild1420Ext for Sensor {
+    fn ild1420(&self) -> Result<Ild<'_>, MedaqError> {
+        Ild::new(self, &ILD1420)
+    }
+}
+
+impl Ild1900Ext for Sensor {
+    fn ild1900(&self) -> Result<Ild<'_>, MedaqError> {
+        Ild::new(self, &ILD1900)
+    }
+}
+
+impl<'a> Ild<'a> {
+    fn new(sensor: &'a Sensor, model: &'static Model) -> Result<Self, MedaqError> {
+        super::check_type(sensor, model.sensor_types)?;
+        Ok(Self { sensor, model })
+    }
+
+    /// `Set_Measrate`, fails with [`MedaqError::Unsupported`] for a rate the
+    /// model does not offer, see [`Capabilities`](crate::Capabilities)
+    pub fn set_measuring_rate(&self, rate: IldRate) -> Result<(), MedaqError> {
+        let capabilities = self.sensor.capabilities();
+        if !capabilities.supports_measuring_rate(rate.khz() * 1000.) {
+            return Err(self.unsupported(format!("measuring rate {} kHz", rate.khz())));
+        }
+        let _instance = self.sensor.hold()?;
+        self.sensor
+            .set_parameter_double("SP_Measrate", rate.khz())?;
+        self.sensor.execute_command("Set_Measrate")
+    }
+
+    /// `Set_PeakSelection`
+    pub fn set_peak(&self, peak: IldPeak) -> Result<(), MedaqError> {
+        let peak = match peak {
+            IldPeak::Highest => 0,
+            IldPeak::Widest => 1,
+            IldPeak::Last => 2,
+            IldPeak::First => 3,
+        };
+        let _instance = self.sensor.hold()?;
+        self.sensor.set_parameter_int("SP_PeakSelection", peak)?;
+        self.sensor.execute_command("Set_PeakSelection")
+    }
+
+    /// `Set_LaserPower`
+    pub fn set_laser_power(&self, power: IldLaserPower) -> Result<(), MedaqError> {
+        if !self.sensor.capabilities().supports_laser_control {
+            return Err(self.unsupported("laser control".into()));
+        }
+        let power = match power {
+            IldLaserPower::Full => 0,
+            IldLaserPower::Reduced => 1,
+            IldLaserPower::Off => 2,
+        };
+        let _instance = self.sensor.hold()?;
+        self.sensor.set_parameter_int("SP_LaserPower", power)?;
+        self.sensor.execute_command("Set_LaserPower")
+    }
+
+    /// `Set_ROI`, the pixels of the video signal searched for the peak, fails with
+    /// [`MedaqError::Unsupported`] for an empty range
+    pub fn set_roi(&self, pixels: Range<u16>) -> Result<(), MedaqError> {
+        if pixels.is_empty() {
+            return Err(self.unsupported(format!("empty region of interest {pixels:?}")));
+        }
+        let _instance = self.sensor.hold()?;
+        self.sensor
+            .set_parameter_int("SP_ROIStart", pixels.start.into())?;
+        match self.model.roi_size {
+            true => self
+                .sensor
+                .set_parameter_int("SP_ROISize", (pixels.end - pixels.start).into())?,
+            false => self
+                .sensor
+                .set_parameter_int("SP_ROIEnd", pixels.end.into())?,
+        }
+        self.sensor.execute_command("Set_ROI")
+    }
+
+    fn unsupported(&self, setting: String) -> MedaqError {
+        MedaqError::Unsupported {
+            sensor_type: self.sensor.sensor_type(),
+            setting,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ild1420Ext, Ild1900Ext, IldLaserPower, IldPeak, IldRate};
+    use crate::{Call, MedaqError, MockBackend, ME_SENSOR};
+
+    /// Parameters set and commands executed after connecting
+    fn settings(mock: &MockBackend, connected: usize) -> Vec<String> {
+        mock.calls()[connected..]
+            .iter()
+            .filter(|call| {
+                matches!(
+                    call.call,
+                    Call::SetParameterInt | Call::SetParameterDouble | Call::ExecSCmd
+                )
+            })
+            .map(|call| call.arguments.join("="))
+            .collect()
+    }
+
+    #[test]
+    fn test_ild1420_settings_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        let connected = mock.calls().len();
+        let ild = sensor.ild1420().unwrap();
+
+        ild.set_measuring_rate(IldRate::Khz2).unwrap();
+        ild.set_peak(IldPeak::Last).unwrap();
+        ild.set_laser_power(IldLaserPower::Reduced).unwrap();
+        ild.set_roi(100..400).unwrap();
+        assert_eq!(
+            settings(&mock, connected),
+            [
+                "SP_Measrate=2",
+                "Set_Measrate",
+                "SP_PeakSelection=2",
+                "Set_PeakSelection",
+                "SP_LaserPower=1",
+                "Set_LaserPower",
+                "SP_ROIStart=100",
+                "SP_ROISize=300",
+                "Set_ROI",
+            ]
+        );
+
+        // refused before anything is sent
+        let connected = mock.calls().len();
+        let err = ild.set_measuring_rate(IldRate::Khz10).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "measuring rate 10 kHz is not supported by the SENSOR_ILD1420"
+        );
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 400..100;
+        assert!(matches!(
+            ild.set_roi(empty),
+            Err(MedaqError::Unsupported { .. })
+        ));
+        assert_eq!(mock.calls().len(), connected);
+    }
+
+    #[test]
+    fn test_ild1900_settings_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1900);
+        let connected = mock.calls().len();
+        let ild = sensor.ild1900().unwrap();
+
+        ild.set_measuring_rate(IldRate::Khz10).unwrap();
+        ild.set_measuring_rate(IldRate::Hz250).unwrap();
+        ild.set_peak(IldPeak::Highest).unwrap();
+        ild.set_laser_power(IldLaserPower::Off).unwrap();
+        ild.set_roi(100..400).unwrap();
+        assert_eq!(
+            settings(&mock, connected),
+            [
+                "SP_Measrate=10",
+                "Set_Measrate",
+                "SP_Measrate=0.25",
+                "Set_Measrate",
+                "SP_PeakSelection=0",
+                "Set_PeakSelection",
+                "SP_LaserPower=2",
+                "Set_LaserPower",
+                "SP_ROIStart=100",
+                "SP_ROIEnd=400",
+                "Set_ROI",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ild_wrong_sensor_type_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = mock.connect(ME_SENSOR::SENSOR_ILD1900);
+        assert_eq!(sensor.sensor_type(), ME_SENSOR::SENSOR_ILD1900);
+        let err = sensor.ild1420().unwrap_err();
+        assert!(matches!(
+            err,
+            MedaqError::WrongSensorType {
+                expected: &[ME_SENSOR::SENSOR_ILD1420],
+                found: ME_SENSOR::SENSOR_ILD1900,
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "expected a SENSOR_ILD1420 but the sensor is a SENSOR_ILD1900"
+        );
+    }
+}
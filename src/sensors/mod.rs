@@ -0,0 +1,61 @@
+//! Typed settings of particular sensor models, translated to the commands and
+//! parameters of MEDAQLib
+//!
+//! The methods set the `SP_` parameters of a command and execute it, like
+//! [`Sensor::set_parameter_int`] followed by [`Sensor::execute_command`]. Each
+//! module checks the [`ME_SENSOR`] of the connection before giving access to them.
+//! [`thickness`] interprets the captures of thickness systems instead.
+
+pub mod capancdt;
+pub mod ifc;
+pub mod ild;
+pub mod odc;
+pub mod thickness;
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{MedaqError, Sensor, ME_SENSOR};
+
+/// Pause between the status requests of [`run_until_done`]
+const STATUS_POLL: Duration = Duration::from_millis(10);
+
+/// Fails with [`MedaqError::WrongSensorType`] unless the sensor is one of `expected`
+fn check_type(sensor: &Sensor, expected: &'static [ME_SENSOR]) -> Result<(), MedaqError> {
+    match expected.contains(&sensor.sensor_type()) {
+        true => Ok(()),
+        false => Err(MedaqError::WrongSensorType {
+            expected,
+            found: sensor.sensor_type(),
+        }),
+    }
+}
+
+/// Start a procedure with `command`, then execute `status_command` until its
+/// answer `status` is `0`, failing with [`MedaqError::CommandTimeout`] after
+/// `timeout`
+fn run_until_done(
+    sensor: &Sensor,
+    command: &'static str,
+    (status_command, status): (&str, &str),
+    timeout: Duration,
+) -> Result<(), MedaqError> {
+    let _instance = sensor.hold()?;
+    let start = Instant::now();
+    sensor.execute_command(command)?;
+    loop {
+        sensor.execute_command(status_command)?;
+        // answered as a string, GetParameterInt is optional
+        let busy = sensor.get_parameter_string(status)?;
+        if busy.trim().parse() == Ok(0) {
+            return Ok(());
+        }
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Err(MedaqError::CommandTimeout { command, timeout });
+        }
+        thread::sleep(remaining.min(STATUS_POLL));
+    }
+}
@@ -0,0 +1,281 @@
+//! Optical micrometers optoCONTROL ODC2500, ODC2520, ODC2600 and ODC2700, which
+//! measure the edges and diameter of an object in the light curtain
+//!
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use medaqlib::{
+//!     sensors::odc::{OdcExt, OdcProgram},
+//!     Interface, SensorBuilder, ME_SENSOR,
+//! };
+//!
+//! let mut sensor = SensorBuilder::new(ME_SENSOR::SENSOR_ODC2520)
+//!     .with_interface(Interface::TcpIp)
+//!     .with_ip_address("10.10.10.10")
+//!     .connect()
+//!     .unwrap();
+//!
+//! let mut odc = sensor.odc().unwrap();
+//! odc.set_measurement_program(OdcProgram::Diameter).unwrap();
+//! odc.run_reference_cycle(Duration::from_secs(10)).unwrap();
+//! let channels = odc.channels();
+//!
+//! if let Some(data) = sensor.read_data().unwrap() {
+//!     for frame in channels.frames(&data) {
+//!         println!("{:?}", frame.diameter);
+//!     }
+//! }
+//! ```
+
+use std::time::Duration;
+
+use crate::{Data, MedaqError, Sensor, Value, ME_SENSOR};
+
+/// The sensor types of the micrometers
+const MICROMETERS: [ME_SENSOR; 4] = [
+    ME_SENSOR::SENSOR_ODC2500,
+    ME_SENSOR::SENSOR_ODC2520,
+    ME_SENSOR::SENSOR_ODC2600,
+    ME_SENSOR::SENSOR_ODC2700,
+];
+
+/// What the micrometer measures, see [`Odc::set_measurement_program`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OdcProgram {
+    /// Position of a transition from light to dark
+    EdgeLightDark,
+    /// Position of a transition from dark to light
+    EdgeDarkLight,
+    /// Both edges and the distance between them
+    Diameter,
+    /// Gap between two objects
+    Gap,
+}
+
+/// Positions of the named quantities within the frames, `None` for quantities the
+/// measurement program does not transmit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OdcChannels {
+    pub edge1: Option<usize>,
+    pub edge2: Option<usize>,
+    pub diameter: Option<usize>,
+}
+
+/// Quantities of one frame, see [`OdcChannels::frames`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OdcFrame {
+    pub edge1: Option<Value<f64>>,
+    pub edge2: Option<Value<f64>>,
+    pub diameter: Option<Value<f64>>,
+}
+
+/// Extension of [`Sensor`] for optoCONTROL micrometers
+pub trait OdcExt {
+    /// The settings, fails with [`MedaqError::WrongSensorType`] unless connected as
+    /// one of the ODC sensor types
+    fn odc(&mut self) -> Result<Odc<'_>, MedaqError>;
+}
+
+impl OdcExt for Sensor {
+    fn odc(&mut self) -> Result<Odc<'_>, MedaqError> {
+        super::check_type(self, &MICROMETERS)?;
+        Ok(Odc { sensor: self })
+    }
+}
+
+/// Typed settings of an optoCONTROL micrometer, see [`OdcExt`]
+#[derive(Debug)]
+pub struct Odc<'a> {
+    sensor: &'a mut Sensor,
+}
+
+impl Odc<'_> {
+    /// `Set_MeasurementProgram`, then refresh the transmitted channels, see
+    /// [`Sensor::refresh_channels`]
+    pub fn set_measurement_program(&mut self, program: OdcProgram) -> Result<(), MedaqError> {
+        let program = match program {
+            OdcProgram::EdgeLightDark => 0,
+            OdcProgram::EdgeDarkLight => 1,
+            OdcProgram::Diameter => 2,
+            OdcProgram::Gap => 3,
+        };
+        let _instance = self.sensor.hold()?;
+        self.sensor
+            .set_parameter_int("SP_MeasurementProgram", program)?;
+        self.sensor.execute_command("Set_MeasurementProgram")?;
+        self.sensor.refresh_channels()
+    }
+
+    /// `Set_ReferenceCycle`, calibrate the light curtain with nothing in it
+    ///
+    /// Waits until `SA_ReferenceCycleStatus` of `Get_ReferenceCycleStatus` reports
+    /// the cycle as done, fails with [`MedaqError::CommandTimeout`] after `timeout`.
+    pub fn run_reference_cycle(&self, timeout: Duration) -> Result<(), MedaqError> {
+        let status = ("Get_ReferenceCycleStatus", "SA_ReferenceCycleStatus");
+        super::run_until_done(self.sensor, "Set_ReferenceCycle", status, timeout)
+    }
+
+    /// The quantities of the transmitted channels
+    pub fn channels(&self) -> OdcChannels {
+        let names = self.sensor.channel_info().iter().map(|info| &*info.name);
+        OdcChannels::identify(names)
+    }
+}
+
+impl OdcChannels {
+    /// The quantities of the channels of a capture
+    pub fn of(data: &Data) -> Self {
+        Self::identify(data.channels().iter().map(String::as_str))
+    }
+
+    /// Channels named `Edge 1` or `Edge1`, `Edge 2` and `Diameter`, ignoring case
+    fn identify<'a>(names: impl Iterator<Item = &'a str>) -> Self {
+        let mut channels = Self::default();
+        for (index, name) in names.enumerate() {
+            let name = name.to_lowercase().replace(' ', "");
+            let quantity = if name.contains("edge1") {
+                &mut channels.edge1
+            } else if name.contains("edge2") {
+                &mut channels.edge2
+            } else if name.contains("diameter") {
+                &mut channels.diameter
+            } else {
+                continue;
+            };
+            quantity.get_or_insert(index);
+        }
+        channels
+    }
+
+    /// Quantities of every frame of a capture
+    pub fn frames<'a>(&self, data: &'a Data) -> impl Iterator<Item = OdcFrame> + 'a {
+        let channels = *self;
+        data.frames().map(move |frame| {
+            let value = |index: Option<usize>| {
+                let value = *frame.get(index?)?;
+                Some(Value::from_sample(value))
+            };
+            OdcFrame {
+                edge1: value(channels.edge1),
+                edge2: value(channels.edge2),
+                diameter: value(channels.diameter),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{OdcChannels, OdcExt, OdcProgram};
+    use crate::{
+        Call, DataBuilder, Intercept, MedaqError, MockBackend, Sensor, Value, ME_SENSOR,
+        SCALED_OUT_OF_RANGE,
+    };
+
+    /// Micrometer transmitting an edge until the diameter program is selected
+    fn connect(mock: &MockBackend) -> Sensor {
+        let mut diameter = false;
+        mock.intercept(move |call| match call.call {
+            Call::ExecSCmd if call.arguments[0] == "Set_MeasurementProgram" => {
+                diameter = true;
+                Intercept::Pass
+            }
+            Call::GetParameterString if diameter => match &*call.arguments[0] {
+                "IA_Scaled_Name1" => Intercept::String("Edge 1".into()),
+                "IA_Scaled_Name2" => Intercept::String("Edge 2".into()),
+                "IA_Scaled_Name3" => Intercept::String("Diameter".into()),
+                _ => Intercept::Pass,
+            },
+            _ => Intercept::Pass,
+        });
+        mock.connect(ME_SENSOR::SENSOR_ODC2520)
+    }
+
+    #[test]
+    fn test_odc_program_switch_test() {
+        let mock = MockBackend::new().with_channels(["Edge1"]);
+        let mut sensor = connect(&mock);
+        let mut odc = sensor.odc().unwrap();
+        assert_eq!(
+            odc.channels(),
+            OdcChannels {
+                edge1: Some(0),
+                ..OdcChannels::default()
+            }
+        );
+
+        let since = mock.calls().len();
+        odc.set_measurement_program(OdcProgram::Diameter).unwrap();
+        let calls: Vec<_> = mock.calls()[since..]
+            .iter()
+            .filter(|call| matches!(call.call, Call::SetParameterInt | Call::ExecSCmd))
+            .map(|call| call.arguments.join("="))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                "SP_MeasurementProgram=2",
+                "Set_MeasurementProgram",
+                "Get_TransmittedDataInfo",
+            ]
+        );
+        assert_eq!(
+            odc.channels(),
+            OdcChannels {
+                edge1: Some(0),
+                edge2: Some(1),
+                diameter: Some(2),
+            }
+        );
+
+        mock.push_frame(&[0, 0, 0], &[1.5, 7.5, 6.]);
+        let data = sensor.read_data().unwrap().unwrap();
+        let frame = OdcChannels::of(&data).frames(&data).next().unwrap();
+        assert_eq!(frame.edge2, Some(Value::Valid(7.5)));
+        assert_eq!(frame.diameter, Some(Value::Valid(6.)));
+    }
+
+    #[test]
+    fn test_odc_channel_remapping_test() {
+        // gap program with the quantities in another order, the gap is not named
+        let data = DataBuilder::channels(["Gap", "EDGE 2", "Intensity", "edge 1"])
+            .frame([1., 4., 90., SCALED_OUT_OF_RANGE])
+            .build();
+        let channels = OdcChannels::of(&data);
+        assert_eq!(
+            channels,
+            OdcChannels {
+                edge1: Some(3),
+                edge2: Some(1),
+                diameter: None,
+            }
+        );
+        let frame = channels.frames(&data).next().unwrap();
+        assert_eq!(frame.edge1, Some(Value::OutOfRange));
+        assert_eq!(frame.edge2, Some(Value::Valid(4.)));
+        assert_eq!(frame.diameter, None);
+    }
+
+    #[test]
+    fn test_odc_reference_cycle_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Edge1"])
+            .with_parameter_int("SA_ReferenceCycleStatus", 0);
+        let mut sensor = connect(&mock);
+        let odc = sensor.odc().unwrap();
+        odc.run_reference_cycle(Duration::from_secs(1)).unwrap();
+        assert_eq!(
+            mock.commands()[1..],
+            ["Set_ReferenceCycle", "Get_ReferenceCycleStatus"]
+        );
+
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let mut sensor = mock.connect(ME_SENSOR::SENSOR_ILD1420);
+        assert!(matches!(
+            sensor.odc(),
+            Err(MedaqError::WrongSensorType { .. })
+        ));
+    }
+}
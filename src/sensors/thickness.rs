@@ -0,0 +1,305 @@
+//! Thickness systems thicknessSENSOR and combiSENSOR, two distances measured from
+//! opposite sides and the thickness derived from them
+//!
+//! The distances are signed around the middle of the measuring range, only
+//! [`SCALED_OUT_OF_RANGE`] or a value that is not finite marks them invalid. The
+//! thickness is never negative, a negative one is invalid like for other sensors.
+//! As it is computed from both distances it is invalid as soon as one of them is.
+//!
+//! # Example
+//! ```
+//! use medaqlib::{sensors::thickness::ThicknessChannels, Data, Value};
+//!
+//! let channels = ["Distance top", "Distance bottom", "Thickness"].map(String::from);
+//! // a reference part of 5 mm is measured as 4.75 mm
+//! let reference = Data::new(channels.to_vec(), vec![], vec![-0.25, 0., 4.75]);
+//! let thickness = ThicknessChannels::of(&reference).unwrap();
+//! let master = thickness.master(&reference, 5.).unwrap();
+//!
+//! let data = Data::new(channels.to_vec(), vec![], vec![-0.5, 0., 4.5, -0.5, f64::MIN, 4.5]);
+//! let frames: Vec<_> = thickness.frames(&data).mastered(master).collect();
+//! assert_eq!(frames[0].thickness, Value::Valid(4.75));
+//! // invalid with the bottom distance
+//! assert_eq!(frames[1].thickness, Value::OutOfRange);
+//! ```
+
+use std::slice::ChunksExact;
+
+use crate::{ChannelInfo, Data, MedaqError, Value, SCALED_OUT_OF_RANGE};
+
+/// Values of one frame of a thickness system, see [`ThicknessChannels::frames`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThicknessFrame {
+    pub top: Value<f64>,
+    pub bottom: Value<f64>,
+    pub thickness: Value<f64>,
+}
+
+/// Positions of the distance and thickness channels within the frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThicknessChannels {
+    pub top: usize,
+    pub bottom: usize,
+    pub thickness: usize,
+}
+
+/// Correction of the thickness found by measuring a reference part, see
+/// [`ThicknessChannels::master`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThicknessMaster {
+    offset: f64,
+}
+
+impl ThicknessChannels {
+    /// Find the channels by their names in the channel metadata, e.g.
+    /// [`Sensor::channel_info`](crate::Sensor::channel_info)
+    ///
+    /// The thickness is the channel named `thickness`, the distances those named
+    /// `top` or `upper` and `bottom` or `lower`, ignoring case. Without such names
+    /// the first two other channels with `distance` in their name are the top and
+    /// bottom distance. Fails with [`MedaqError::UnknownChannel`] if a channel is
+    /// missing.
+    pub fn identify(channel_info: &[ChannelInfo]) -> Result<Self, MedaqError> {
+        let names: Vec<&str> = channel_info.iter().map(|info| info.name.as_str()).collect();
+        identify(&names)
+    }
+
+    /// The channels of a capture, see [`identify`](Self::identify)
+    pub fn of(data: &Data) -> Result<Self, MedaqError> {
+        let names: Vec<&str> = data.channels().iter().map(String::as_str).collect();
+        identify(&names)
+    }
+
+    /// Frames of a capture with the combined validity applied
+    pub fn frames<'a>(&self, data: &'a Data) -> ThicknessFrames<'a> {
+        ThicknessFrames {
+            frames: data.frames(),
+            channels: *self,
+            master: None,
+        }
+    }
+
+    /// Master with a capture of a reference part of known thickness: the
+    /// difference of `reference` to the mean of the valid thickness values
+    /// corrects the frames of [`ThicknessFrames::mastered`]
+    ///
+    /// `None` if the capture has no valid frame.
+    pub fn master(&self, reference: &Data, reference_thickness: f64) -> Option<ThicknessMaster> {
+        let (sum, count) = self
+            .frames(reference)
+            .filter_map(|frame| frame.thickness.into_raw())
+            .fold((0., 0), |(sum, count), value| (sum + value, count + 1));
+        (count > 0).then(|| ThicknessMaster {
+            offset: reference_thickness - sum / count as f64,
+        })
+    }
+}
+
+impl ThicknessMaster {
+    /// Added to every valid thickness
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+}
+
+fn identify(names: &[&str]) -> Result<ThicknessChannels, MedaqError> {
+    let lower: Vec<String> = names.iter().map(|name| name.to_lowercase()).collect();
+    let find = |words: &[&str], skip: &[usize]| {
+        lower.iter().enumerate().position(|(index, name)| {
+            !skip.contains(&index) && words.iter().any(|word| name.contains(word))
+        })
+    };
+    let missing = |name: &str| MedaqError::UnknownChannel {
+        name: name.into(),
+        available: names.iter().map(|name| name.to_string()).collect(),
+    };
+
+    let thickness = find(&["thickness"], &[]).ok_or_else(|| missing("thickness"))?;
+    let named = (
+        find(&["top", "upper"], &[thickness]),
+        find(&["bottom", "lower"], &[thickness]),
+    );
+    let (top, bottom) = match named {
+        (Some(top), Some(bottom)) => (top, bottom),
+        _ => {
+            let top = find(&["distance"], &[thickness]).ok_or_else(|| missing("top distance"))?;
+            let bottom =
+                find(&["distance"], &[thickness, top]).ok_or_else(|| missing("bottom distance"))?;
+            (top, bottom)
+        }
+    };
+    Ok(ThicknessChannels {
+        top,
+        bottom,
+        thickness,
+    })
+}
+
+/// A distance, which may be negative
+fn distance(value: Option<&f64>) -> Value<f64> {
+    match value {
+        Some(&value) if value != SCALED_OUT_OF_RANGE && value.is_finite() => Value::Valid(value),
+        _ => Value::OutOfRange,
+    }
+}
+
+/// Iterator over the [`ThicknessFrame`]s of a capture, see
+/// [`ThicknessChannels::frames`]
+#[derive(Debug, Clone)]
+pub struct ThicknessFrames<'a> {
+    frames: ChunksExact<'a, f64>,
+    channels: ThicknessChannels,
+    master: Option<ThicknessMaster>,
+}
+
+impl ThicknessFrames<'_> {
+    /// Correct the thickness by a master, see [`ThicknessChannels::master`]
+    pub fn mastered(self, master: ThicknessMaster) -> Self {
+        Self {
+            master: Some(master),
+            ..self
+        }
+    }
+
+    fn thickness_frame(&self, frame: &[f64]) -> ThicknessFrame {
+        let top = distance(frame.get(self.channels.top));
+        let bottom = distance(frame.get(self.channels.bottom));
+        let thickness = match (top, bottom, frame.get(self.channels.thickness)) {
+            (Value::Valid(_), Value::Valid(_), Some(&thickness)) => {
+                match Value::from_sample(thickness) {
+                    Value::Valid(thickness) if thickness.is_finite() => {
+                        let offset = self.master.map_or(0., |master| master.offset);
+                        Value::Valid(thickness + offset)
+                    }
+                    _ => Value::OutOfRange,
+                }
+            }
+            _ => Value::OutOfRange,
+        };
+        ThicknessFrame {
+            top,
+            bottom,
+            thickness,
+        }
+    }
+}
+
+impl Iterator for ThicknessFrames<'_> {
+    type Item = ThicknessFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next()?;
+        Some(self.thickness_frame(frame))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.frames.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for ThicknessFrames<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next_back()?;
+        Some(self.thickness_frame(frame))
+    }
+}
+
+impl ExactSizeIterator for ThicknessFrames<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{ThicknessChannels, ThicknessFrame};
+    use crate::{ChannelInfo, DataBuilder, MedaqError, Value, SCALED_OUT_OF_RANGE};
+
+    #[test]
+    fn test_thickness_identify_test() {
+        let info = ["Thickness", "Upper distance", "Lower distance"].map(ChannelInfo::new);
+        assert_eq!(
+            ThicknessChannels::identify(&info).unwrap(),
+            ThicknessChannels {
+                top: 1,
+                bottom: 2,
+                thickness: 0,
+            }
+        );
+
+        // combiSENSOR without top and bottom in the names
+        let data =
+            DataBuilder::channels(["Distance 1", "Intensity", "Distance 2", "THICKNESS"]).build();
+        assert_eq!(
+            ThicknessChannels::of(&data).unwrap(),
+            ThicknessChannels {
+                top: 0,
+                bottom: 2,
+                thickness: 3,
+            }
+        );
+
+        let data = DataBuilder::channels(["Distance 1", "Thickness"]).build();
+        let err = ThicknessChannels::of(&data).unwrap_err();
+        assert!(
+            matches!(err, MedaqError::UnknownChannel { name, .. } if name == "bottom distance")
+        );
+    }
+
+    #[test]
+    fn test_thickness_combined_validity_test() {
+        let data = DataBuilder::channels(["Top", "Bottom", "Thickness"])
+            .frame([-0.5, 0.25, 4.75])
+            .frame([SCALED_OUT_OF_RANGE, 0.25, 4.75])
+            .frame([-0.5, f64::NAN, 4.75])
+            .frame([-0.5, 0.25, SCALED_OUT_OF_RANGE])
+            .build();
+        let frames: Vec<_> = ThicknessChannels::of(&data)
+            .unwrap()
+            .frames(&data)
+            .collect();
+        assert_eq!(
+            frames[0],
+            ThicknessFrame {
+                top: Value::Valid(-0.5),
+                bottom: Value::Valid(0.25),
+                thickness: Value::Valid(4.75),
+            }
+        );
+        assert_eq!(frames[1].top, Value::OutOfRange);
+        assert_eq!(frames[1].thickness, Value::OutOfRange);
+        assert_eq!(frames[2].bottom, Value::OutOfRange);
+        assert_eq!(frames[2].thickness, Value::OutOfRange);
+        assert_eq!(frames[3].top, Value::Valid(-0.5));
+        assert_eq!(frames[3].thickness, Value::OutOfRange);
+    }
+
+    #[test]
+    fn test_thickness_master_test() {
+        let channels = ["Top", "Bottom", "Thickness"];
+        let reference = DataBuilder::channels(channels)
+            .frame([0., 0., 9.9])
+            .frame([0., 0., 10.1])
+            .frame([SCALED_OUT_OF_RANGE, 0., 50.])
+            .frame([0., 0., 9.7])
+            .build();
+        let thickness = ThicknessChannels::of(&reference).unwrap();
+        let master = thickness.master(&reference, 10.).unwrap();
+        assert!((master.offset() - 0.1).abs() < 1e-12);
+
+        let data = DataBuilder::channels(channels)
+            .frame([0., 0., 4.9])
+            .frame([0., SCALED_OUT_OF_RANGE, 4.9])
+            .build();
+        let frames: Vec<_> = thickness.frames(&data).mastered(master).collect();
+        let Value::Valid(mastered) = frames[0].thickness else {
+            panic!("{frames:?}");
+        };
+        assert!((mastered - 5.).abs() < 1e-12);
+        assert_eq!(frames[1].thickness, Value::OutOfRange);
+
+        let invalid = DataBuilder::channels(channels)
+            .frame([SCALED_OUT_OF_RANGE, 0., 1.])
+            .build();
+        assert!(thickness.master(&invalid, 10.).is_none());
+        assert!(thickness
+            .master(&DataBuilder::channels(channels).build(), 10.)
+            .is_none());
+    }
+}
@@ -0,0 +1,586 @@
+use std::{
+    f64::consts::TAU,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::Instant,
+};
+
+use crate::{
+    backend::{self, Backend},
+    MedaqError, ERR_CODE, ME_SENSOR, SCALED_OUT_OF_RANGE,
+};
+
+/// Largest raw value, the lower end of the measuring range is raw `0`
+const RAW_MAX: f64 = 65535.;
+/// Values buffered before the oldest are discarded, like MEDAQLib does when it is
+/// not read fast enough
+const BUFFER_VALUES: u64 = 1 << 20;
+
+/// Shape of the values of every channel, see [`SimConfig`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Waveform {
+    /// Sine across the measuring range, channels are shifted in phase
+    Sine { frequency_hz: f64 },
+    /// Triangle across the measuring range, channels are shifted in phase
+    Triangle { frequency_hz: f64 },
+    /// Uniformly distributed values within the measuring range
+    Noise,
+    /// The same value for every sample
+    Constant(f64),
+}
+
+/// Family of sensors sharing how invalid samples are transmitted, see
+/// [`SimConfig::sentinel_profile`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SensorFamily {
+    /// optoNCDT laser sensors like the ILD1420, the raw values of invalid samples
+    /// are error codes above the 18 bit measuring range
+    ///
+    /// Raw values are classified by their sign, so [`Data::get_mean_raw`] counts
+    /// these codes as valid. Use the scaled values of these sensors.
+    ///
+    /// [`Data::get_mean_raw`]: crate::Data::get_mean_raw
+    Ild1420,
+    /// confocalDT controllers like the IFD2421, the raw values of invalid samples
+    /// are negative
+    Ifd24xx,
+}
+
+/// Raw and scaled value of an invalid sample
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SentinelProfile {
+    pub raw: i32,
+    pub scaled: f64,
+}
+
+impl SensorFamily {
+    /// Values the sensors of the family transmit for an invalid sample
+    pub fn sentinels(self) -> SentinelProfile {
+        match self {
+            // "no peak" of the 18 bit raw values
+            SensorFamily::Ild1420 => SentinelProfile {
+                raw: 262_075,
+                scaled: SCALED_OUT_OF_RANGE,
+            },
+            SensorFamily::Ifd24xx => SentinelProfile {
+                raw: -1,
+                scaled: SCALED_OUT_OF_RANGE,
+            },
+        }
+    }
+}
+
+/// Deterministic arrangement of invalid samples, in addition to
+/// [`SimConfig::invalid_ratio`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidPattern {
+    /// Every channel of every `n`th frame, starting with frame `n - 1`
+    EveryNth(u64),
+    /// Every channel of `length` consecutive frames at the start of every `period`
+    /// frames
+    Burst { period: u64, length: u64 },
+    /// Every sample of the channel with the given index
+    Channel(usize),
+}
+
+impl InvalidPattern {
+    fn is_invalid(self, frame: u64, channel: usize) -> bool {
+        match self {
+            InvalidPattern::EveryNth(n) => n > 0 && frame % n == n - 1,
+            InvalidPattern::Burst { period, length } => period > 0 && frame % period < length,
+            InvalidPattern::Channel(index) => channel == index,
+        }
+    }
+}
+
+/// Configuration of a [`SimulatedSensor`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimConfig {
+    /// Names of the transmitted channels
+    pub channels: Vec<String>,
+    /// Frames generated per second of wall-clock time
+    pub sample_rate: f64,
+    pub waveform: Waveform,
+    /// Fraction of samples transmitted as out of range, between `0` and `1`
+    pub invalid_ratio: f64,
+    /// Lower end of the measuring range of the scaled values
+    pub range_min: f64,
+    /// Upper end of the measuring range of the scaled values
+    pub range_max: f64,
+    /// Seed of the noise and of the out of range samples
+    pub seed: u64,
+    /// Values transmitted for out of range samples
+    pub sentinels: SentinelProfile,
+    pub invalid_pattern: Option<InvalidPattern>,
+}
+
+impl SimConfig {
+    /// Transmit out of range samples like the sensors of the family
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::{InvalidPattern, SensorFamily, SimConfig};
+    ///
+    /// let config = SimConfig {
+    ///     invalid_pattern: Some(InvalidPattern::EveryNth(10)),
+    ///     ..Default::default()
+    /// }
+    /// .sentinel_profile(SensorFamily::Ild1420);
+    /// assert_eq!(config.sentinels, SensorFamily::Ild1420.sentinels());
+    /// ```
+    pub fn sentinel_profile(self, family: SensorFamily) -> Self {
+        let sentinels = family.sentinels();
+        Self { sentinels, ..self }
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            channels: vec!["Distance".into()],
+            sample_rate: 1000.,
+            waveform: Waveform::Sine { frequency_hz: 1. },
+            invalid_ratio: 0.,
+            range_min: 0.,
+            range_max: 10.,
+            seed: 0,
+            sentinels: SensorFamily::Ifd24xx.sentinels(),
+            invalid_pattern: None,
+        }
+    }
+}
+
+/// [`Backend`] producing deterministic waveforms at the configured sample rate
+///
+/// Frames become available as wall-clock time passes after opening the sensor, so
+/// polling and pacing behave as with a real sensor. The values of a frame only
+/// depend on its index and the configuration. Selected by [`Interface::Simulated`]
+/// with the default configuration, or by
+/// [`SensorBuilder::with_backend`](crate::SensorBuilder::with_backend).
+///
+/// [`Interface::Simulated`]: crate::Interface::Simulated
+///
+/// # Example
+/// ```
+/// use medaqlib::{Interface, SensorBuilder, SimConfig, SimulatedSensor, Waveform, ME_SENSOR};
+///
+/// let simulator = SimulatedSensor::new(SimConfig {
+///     channels: vec!["Distance".into(), "Intensity".into()],
+///     waveform: Waveform::Triangle { frequency_hz: 5. },
+///     ..Default::default()
+/// });
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::Simulated)
+///     .with_backend(simulator)
+///     .connect()
+///     .unwrap();
+/// assert_eq!(sensor.parameters(), ["Distance", "Intensity"]);
+/// ```
+#[derive(Debug)]
+pub struct SimulatedSensor {
+    config: SimConfig,
+    state: Mutex<SimState>,
+}
+
+#[derive(Debug, Default)]
+struct SimState {
+    opened: Option<Instant>,
+    /// Index of the next frame to transfer
+    next_frame: u64,
+}
+
+impl SimulatedSensor {
+    pub fn new(config: SimConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// Scaled value of a channel in the frame with the given index
+    pub(crate) fn scaled(&self, frame: u64, channel: usize) -> f64 {
+        let config = &self.config;
+        let channels = config.channels.len().max(1) as f64;
+        let patterned = config
+            .invalid_pattern
+            .is_some_and(|pattern| pattern.is_invalid(frame, channel));
+        if patterned || unit(config.seed, frame, channel, 1) < config.invalid_ratio {
+            return config.sentinels.scaled;
+        }
+
+        let time = frame as f64 / config.sample_rate;
+        let phase = |frequency_hz: f64| (time * frequency_hz + channel as f64 / channels).fract();
+        let position = match config.waveform {
+            Waveform::Sine { frequency_hz } => 0.5 + 0.5 * (TAU * phase(frequency_hz)).sin(),
+            Waveform::Triangle { frequency_hz } => 1. - (2. * phase(frequency_hz) - 1.).abs(),
+            Waveform::Noise => unit(config.seed, frame, channel, 0),
+            Waveform::Constant(value) => return value,
+        };
+        config.range_min + position * (config.range_max - config.range_min)
+    }
+
+    fn raw(&self, scaled: f64) -> i32 {
+        let (min, max) = (self.config.range_min, self.config.range_max);
+        match scaled {
+            _ if scaled == self.config.sentinels.scaled => self.config.sentinels.raw,
+            _ if max == min => 0,
+            scaled => ((scaled - min) / (max - min) * RAW_MAX)
+                .round()
+                .clamp(0., RAW_MAX) as i32,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, SimState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Frames generated since opening but not transferred, discarding the oldest
+    /// beyond the size of the buffer
+    fn available_frames(&self, state: &mut SimState) -> Result<u64, MedaqError> {
+        let opened = state
+            .opened
+            .ok_or(MedaqError::Sensor(ERR_CODE::ERR_NOT_OPEN))?;
+        let generated = (opened.elapsed().as_secs_f64() * self.config.sample_rate) as u64;
+        let capacity = BUFFER_VALUES / self.config.channels.len().max(1) as u64;
+        state.next_frame = state.next_frame.max(generated.saturating_sub(capacity));
+        Ok(generated - state.next_frame)
+    }
+
+    fn parameter(&self, name: &str) -> Option<Parameter> {
+        let (prefix, index) = split_index(name)?;
+        self.config.channels.get(index.checked_sub(1)?)?;
+        Some(match prefix {
+            "IA_Scaled_Name" => Parameter::String(self.config.channels[index - 1].clone()),
+            "IA_Scaled_Unit" => Parameter::String("mm".into()),
+            "IA_Raw_RangeMin" => Parameter::Double(0.),
+            "IA_Raw_RangeMax" => Parameter::Double(RAW_MAX),
+            "IA_Scaled_RangeMin" => Parameter::Double(self.config.range_min),
+            "IA_Scaled_RangeMax" => Parameter::Double(self.config.range_max),
+            _ => return None,
+        })
+    }
+}
+
+enum Parameter {
+    String(String),
+    Double(f64),
+}
+
+/// `("IA_Scaled_Name", 2)` for `"IA_Scaled_Name2"`
+pub(crate) fn split_index(name: &str) -> Option<(&str, usize)> {
+    let digits = name.len() - name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (prefix, index) = name.split_at(name.len() - digits);
+    Some((prefix, index.parse().ok()?))
+}
+
+/// Pseudo random number in `0..1` derived from a sample, splitmix64 finalizer
+fn unit(seed: u64, frame: u64, channel: usize, stream: u64) -> f64 {
+    let mut x = seed
+        ^ frame.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (channel as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ stream.wrapping_mul(0x1656_67B1_9E37_79F9);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^= x >> 31;
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+impl backend::sealed::Sealed for SimulatedSensor {}
+
+impl Backend for SimulatedSensor {
+    fn create_instance(&self, _: ME_SENSOR) -> u32 {
+        1
+    }
+
+    fn release_instance(&self, _: u32) -> Result<(), MedaqError> {
+        Ok(())
+    }
+
+    fn open_sensor(&self, _: u32) -> Result<(), MedaqError> {
+        let mut state = self.lock();
+        if state.opened.is_some() {
+            return Err(MedaqError::Sensor(ERR_CODE::ERR_ALREADY_OPEN));
+        }
+        *state = SimState {
+            opened: Some(Instant::now()),
+            next_frame: 0,
+        };
+        Ok(())
+    }
+
+    fn close_sensor(&self, _: u32) -> Result<(), MedaqError> {
+        self.lock().opened = None;
+        Ok(())
+    }
+
+    fn set_parameter_string(&self, _: u32, _: &str, _: &str) -> Result<(), MedaqError> {
+        Ok(())
+    }
+
+    fn set_parameter_int(&self, _: u32, _: &str, _: i32) -> Result<(), MedaqError> {
+        Ok(())
+    }
+
+    fn get_parameter_string(&self, _: u32, name: &str) -> Result<String, MedaqError> {
+        match self.parameter(name) {
+            Some(Parameter::String(value)) => Ok(value),
+            Some(Parameter::Double(value)) => Ok(value.to_string()),
+            None => Err(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER)),
+        }
+    }
+
+    fn get_parameter_double(&self, _: u32, name: &str) -> Result<f64, MedaqError> {
+        match self.parameter(name) {
+            Some(Parameter::Double(value)) => Ok(value),
+            _ => Err(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER)),
+        }
+    }
+
+    fn set_parameter_double(&self, _: u32, _: &str, _: f64) -> Result<(), MedaqError> {
+        Ok(())
+    }
+
+    fn get_parameter_int(&self, _: u32, _: &str) -> Result<i32, MedaqError> {
+        Err(MedaqError::Sensor(ERR_CODE::ERR_WRONG_PARAMETER))
+    }
+
+    fn exec_scmd(&self, _: u32, _: &str) -> Result<(), MedaqError> {
+        match self.lock().opened {
+            Some(_) => Ok(()),
+            None => Err(MedaqError::Sensor(ERR_CODE::ERR_NOT_OPEN)),
+        }
+    }
+
+    fn get_error(&self, _: u32) -> String {
+        String::new()
+    }
+
+    fn data_avail(&self, _: u32) -> Result<i32, MedaqError> {
+        let frames = self.available_frames(&mut self.lock())?;
+        let values = frames * self.config.channels.len() as u64;
+        Ok(values.min(i32::MAX as u64) as i32)
+    }
+
+    fn transfer_data(
+        &self,
+        _: u32,
+        mut raw: Option<&mut [i32]>,
+        mut scaled: Option<&mut [f64]>,
+        max_values: usize,
+    ) -> Result<usize, MedaqError> {
+        let mut state = self.lock();
+        let channels = self.config.channels.len();
+        if channels == 0 {
+            return Ok(0);
+        }
+        let available = self.available_frames(&mut state)?;
+        let frames = available.min((max_values / channels) as u64);
+
+        for offset in 0..frames as usize {
+            let frame = state.next_frame + offset as u64;
+            for channel in 0..channels {
+                let index = offset * channels + channel;
+                let value = self.scaled(frame, channel);
+                if let Some(scaled) = scaled.as_deref_mut() {
+                    scaled[index] = value;
+                }
+                if let Some(raw) = raw.as_deref_mut() {
+                    raw[index] = self.raw(value);
+                }
+            }
+        }
+        state.next_frame += frames;
+        Ok(frames as usize * channels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{InvalidPattern, SensorFamily, SimConfig, SimulatedSensor, Waveform};
+    use crate::{Data, Interface, SensorBuilder, Value, ME_SENSOR, SCALED_OUT_OF_RANGE};
+
+    fn simulator(waveform: Waveform) -> SimulatedSensor {
+        SimulatedSensor::new(SimConfig {
+            channels: vec!["1".into(), "2".into()],
+            sample_rate: 100.,
+            waveform,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_waveforms_test() {
+        let sine = simulator(Waveform::Sine { frequency_hz: 1. });
+        assert!((sine.scaled(0, 0) - 5.).abs() < 1e-9);
+        assert!((sine.scaled(25, 0) - 10.).abs() < 1e-9);
+        // the second channel is half a period ahead
+        assert!((sine.scaled(25, 1) - 0.).abs() < 1e-9);
+
+        let triangle = simulator(Waveform::Triangle { frequency_hz: 2. });
+        assert_eq!(triangle.scaled(0, 0), 0.);
+        assert!((triangle.scaled(25, 0) - 10.).abs() < 1e-9);
+        assert!((triangle.scaled(10, 0) - 4.).abs() < 1e-9);
+
+        let constant = simulator(Waveform::Constant(3.5));
+        assert_eq!(constant.scaled(7, 1), 3.5);
+        assert_eq!(constant.raw(10.), 65535);
+        assert_eq!(constant.raw(SCALED_OUT_OF_RANGE), -1);
+
+        let noise = simulator(Waveform::Noise);
+        let values: Vec<_> = (0..1000).map(|frame| noise.scaled(frame, 0)).collect();
+        assert!(values.iter().all(|value| (0. ..10.).contains(value)));
+        assert_eq!(
+            values,
+            (0..1000).map(|f| noise.scaled(f, 0)).collect::<Vec<_>>()
+        );
+        assert_ne!(noise.scaled(3, 0), noise.scaled(3, 1));
+    }
+
+    #[test]
+    fn test_invalid_ratio_test() {
+        let simulator = SimulatedSensor::new(SimConfig {
+            invalid_ratio: 0.25,
+            ..Default::default()
+        });
+        let invalid = (0..10_000)
+            .filter(|&frame| Value::from_sample(simulator.scaled(frame, 0)) == Value::OutOfRange)
+            .count();
+        assert!((2000..3000).contains(&invalid), "{invalid} invalid samples");
+    }
+
+    /// Capture of the first `frames` frames of two channels, as read from a sensor
+    fn capture(config: SimConfig, frames: u64) -> Data {
+        let simulator = SimulatedSensor::new(SimConfig {
+            channels: vec!["1".into(), "2".into()],
+            waveform: Waveform::Constant(4.),
+            ..config
+        });
+        let scaled_data: Vec<_> = (0..frames)
+            .flat_map(|frame| [simulator.scaled(frame, 0), simulator.scaled(frame, 1)])
+            .collect();
+        let raw_data = scaled_data.iter().map(|&s| simulator.raw(s)).collect();
+        Data::new(simulator.config.channels.clone(), raw_data, scaled_data)
+    }
+
+    fn invalid_frames(data: &Data, channel: usize) -> Vec<usize> {
+        (0..data.frame_count())
+            .filter(|&frame| {
+                let value = data.scaled_data[frame * data.channel_count() + channel];
+                Value::from_sample(value) == Value::OutOfRange
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_invalid_patterns_test() {
+        let pattern = |invalid_pattern| SimConfig {
+            invalid_pattern: Some(invalid_pattern),
+            ..Default::default()
+        };
+
+        let data = capture(pattern(InvalidPattern::EveryNth(3)), 10);
+        assert_eq!(invalid_frames(&data, 0), [2, 5, 8]);
+        assert_eq!(invalid_frames(&data, 1), [2, 5, 8]);
+
+        let data = capture(
+            pattern(InvalidPattern::Burst {
+                period: 6,
+                length: 2,
+            }),
+            14,
+        );
+        assert_eq!(invalid_frames(&data, 0), [0, 1, 6, 7, 12, 13]);
+
+        let data = capture(pattern(InvalidPattern::Channel(1)), 5);
+        assert!(invalid_frames(&data, 0).is_empty());
+        assert_eq!(invalid_frames(&data, 1), [0, 1, 2, 3, 4]);
+
+        // degenerate patterns leave every sample valid
+        let data = capture(pattern(InvalidPattern::EveryNth(0)), 5);
+        assert!(invalid_frames(&data, 0).is_empty());
+        let data = capture(
+            pattern(InvalidPattern::Burst {
+                period: 0,
+                length: 1,
+            }),
+            5,
+        );
+        assert!(invalid_frames(&data, 0).is_empty());
+    }
+
+    #[test]
+    fn test_sentinel_profiles_test() {
+        for family in [SensorFamily::Ild1420, SensorFamily::Ifd24xx] {
+            let sentinels = family.sentinels();
+            assert_eq!(sentinels.scaled, SCALED_OUT_OF_RANGE, "{family:?}");
+
+            let config = SimConfig {
+                invalid_pattern: Some(InvalidPattern::EveryNth(2)),
+                ..Default::default()
+            };
+            let data = capture(config.sentinel_profile(family), 4);
+            assert_eq!(
+                data.scaled_data,
+                [
+                    4.,
+                    4.,
+                    SCALED_OUT_OF_RANGE,
+                    SCALED_OUT_OF_RANGE,
+                    4.,
+                    4.,
+                    SCALED_OUT_OF_RANGE,
+                    SCALED_OUT_OF_RANGE
+                ]
+            );
+            assert_eq!(data.raw_data[2..4], [sentinels.raw; 2], "{family:?}");
+            // the means of the scaled values skip every invalid sample
+            let means = data.get_mean_scaled();
+            assert_eq!(means[0].value, Value::Valid(4.), "{family:?}");
+            assert_eq!(means[1].value, Value::Valid(4.), "{family:?}");
+        }
+
+        // raw values are classified by their sign
+        let raw = |family: SensorFamily| Value::from_sample(family.sentinels().raw);
+        assert_eq!(raw(SensorFamily::Ifd24xx), Value::OutOfRange);
+        assert_eq!(raw(SensorFamily::Ild1420), Value::Valid(262_075));
+    }
+
+    #[test]
+    fn test_always_invalid_channel_test() {
+        let config = SimConfig {
+            invalid_pattern: Some(InvalidPattern::Channel(0)),
+            ..Default::default()
+        };
+        let data = capture(config.sentinel_profile(SensorFamily::Ifd24xx), 8);
+        let means = data.get_mean_scaled();
+        assert_eq!(means[0].value, Value::OutOfRange);
+        assert_eq!(means[1].value, Value::Valid(4.));
+        assert_eq!(data.get_mean_raw()[0].value, Value::OutOfRange);
+        assert_eq!(data.get_first_scaled()[0].value, Value::OutOfRange);
+    }
+
+    #[test]
+    fn test_simulated_interface_test() {
+        let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+            .with_interface(Interface::Simulated)
+            .connect()
+            .unwrap();
+        assert_eq!(sensor.parameters(), ["Distance"]);
+        assert_eq!(sensor.channel_info()[0].unit.as_deref(), Some("mm"));
+
+        // frames become available with the default rate of 1 kHz
+        std::thread::sleep(Duration::from_millis(50));
+        let data = sensor.read_data().unwrap().unwrap();
+        assert!(data.frame_count() >= 50, "{} frames", data.frame_count());
+        let reference = SimulatedSensor::new(SimConfig::default());
+        for (frame, &value) in data.scaled_data.iter().enumerate() {
+            assert_eq!(value, reference.scaled(frame as u64, 0));
+        }
+        assert!(data.verify_scaling(1e-3).is_ok());
+    }
+}
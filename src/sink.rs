@@ -0,0 +1,217 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    sync::mpsc::{self, Sender, SyncSender},
+};
+
+use crate::{
+    acquisition::{Acquisition, AcquisitionHandle, Control, Deliver, HandleParts},
+    gap::GapMonitor,
+    AcquisitionConfig, Data, MedaqError, Sensor,
+};
+
+/// The receiving side of a [`DataSink`] is gone, the acquisition ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SinkClosed;
+
+impl Display for SinkClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "sink closed")
+    }
+}
+
+impl Error for SinkClosed {}
+
+/// Destination of the captures and read errors of a background acquisition, see
+/// [`Sensor::start_acquisition_with_sink`]
+pub trait DataSink: Send + 'static {
+    /// Hand over a result, may block until the receiver has room
+    fn send(&mut self, result: Result<Data, MedaqError>) -> Result<(), SinkClosed>;
+}
+
+impl DataSink for Sender<Result<Data, MedaqError>> {
+    fn send(&mut self, result: Result<Data, MedaqError>) -> Result<(), SinkClosed> {
+        Sender::send(self, result).map_err(|_| SinkClosed)
+    }
+}
+
+impl DataSink for SyncSender<Result<Data, MedaqError>> {
+    fn send(&mut self, result: Result<Data, MedaqError>) -> Result<(), SinkClosed> {
+        SyncSender::send(self, result).map_err(|_| SinkClosed)
+    }
+}
+
+#[cfg(feature = "crossbeam")]
+impl DataSink for crossbeam_channel::Sender<Result<Data, MedaqError>> {
+    fn send(&mut self, result: Result<Data, MedaqError>) -> Result<(), SinkClosed> {
+        crossbeam_channel::Sender::send(self, result).map_err(|_| SinkClosed)
+    }
+}
+
+/// [`DataSink`] calling a closure, see [`sink_fn`]
+pub struct FnSink<F>(F);
+
+/// Use a closure as [`DataSink`], returning `Err(SinkClosed)` ends the acquisition
+///
+/// # Example
+/// ```
+/// use medaqlib::{sink_fn, DataSink};
+///
+/// let mut captures = 0;
+/// let mut sink = sink_fn(move |result| {
+///     captures += result.is_ok() as usize;
+///     Ok(())
+/// });
+/// # let _ = sink.send(Err(medaqlib::MedaqError::NoChannels));
+/// ```
+pub fn sink_fn<F>(f: F) -> FnSink<F>
+where
+    F: FnMut(Result<Data, MedaqError>) -> Result<(), SinkClosed> + Send + 'static,
+{
+    FnSink(f)
+}
+
+impl<F> DataSink for FnSink<F>
+where
+    F: FnMut(Result<Data, MedaqError>) -> Result<(), SinkClosed> + Send + 'static,
+{
+    fn send(&mut self, result: Result<Data, MedaqError>) -> Result<(), SinkClosed> {
+        (self.0)(result)
+    }
+}
+
+/// Adapts a [`DataSink`] to the acquisition thread
+pub(crate) struct SinkDeliver<D>(pub(crate) D);
+
+impl<D: DataSink> Deliver for SinkDeliver<D> {
+    fn deliver(&mut self, result: Result<Data, MedaqError>, _: &Control) -> bool {
+        self.0.send(result).is_ok()
+    }
+}
+
+impl Sensor {
+    /// Like [`start_acquisition`](Self::start_acquisition), but sends captures and
+    /// read errors to `sink` instead of a std channel
+    ///
+    /// The acquisition ends when the sink reports [`SinkClosed`]. A sink blocking
+    /// in [`DataSink::send`] delays [`AcquisitionHandle::stop`] until it returns.
+    /// [`AcquisitionConfig::ring_buffer`] is ignored and
+    /// [`AcquisitionHandle::receiver`] never receives anything.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use medaqlib::{AcquisitionConfig, Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// let (sender, receiver) = std::sync::mpsc::channel();
+    /// let acquisition = sensor.start_acquisition_with_sink(AcquisitionConfig::default(), sender);
+    /// for data in receiver.iter().take(100) {
+    ///     println!("{}", data.unwrap());
+    /// }
+    /// let sensor = acquisition.join();
+    /// ```
+    pub fn start_acquisition_with_sink(
+        self,
+        config: AcquisitionConfig,
+        sink: impl DataSink,
+    ) -> AcquisitionHandle {
+        let stats = self.stats.clone();
+        let (_, receiver) = mpsc::sync_channel(0);
+        let (events_sender, events) = mpsc::sync_channel(config.channel_capacity);
+        let gaps = GapMonitor::new(config.gap_detection.clone(), stats.clone(), events_sender);
+        let parts = HandleParts {
+            receiver,
+            events,
+            ring: None,
+            stats,
+        };
+        let acquisition = Acquisition::start_monitored(self, config, SinkDeliver(sink), gaps);
+        AcquisitionHandle { acquisition, parts }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::SinkDeliver;
+    use crate::{
+        acquisition::{
+            tests::{data, Script},
+            Acquisition,
+        },
+        sink_fn, AcquisitionConfig, MedaqError, SinkClosed,
+    };
+
+    fn config() -> AcquisitionConfig {
+        AcquisitionConfig {
+            poll_interval: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sink_records_results_test() {
+        let recorded = Arc::new(Mutex::new(vec![]));
+        let recording = Arc::clone(&recorded);
+        let sink = sink_fn(move |result: Result<_, MedaqError>| {
+            let mut recorded = recording.lock().unwrap();
+            recorded.push(result.map(|data| data.scaled_data));
+            match recorded.len() {
+                3 => Err(SinkClosed),
+                _ => Ok(()),
+            }
+        });
+        let script = Script::new(vec![
+            Ok(Some(data(1.))),
+            Err(MedaqError::NoChannels),
+            Ok(Some(data(2.))),
+            Ok(Some(data(3.))),
+        ]);
+        let mut acquisition = Acquisition::start(script, config(), SinkDeliver(sink));
+
+        // the thread ends by itself once the sink is closed
+        while !acquisition.is_finished() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let script = acquisition.join();
+        assert_eq!(script.results.len(), 1);
+        let recorded = recorded.lock().unwrap();
+        assert_eq!(recorded[0].as_ref().unwrap(), &vec![1.]);
+        assert!(matches!(recorded[1], Err(MedaqError::NoChannels)));
+        assert_eq!(recorded[2].as_ref().unwrap(), &vec![2.]);
+    }
+
+    #[test]
+    fn test_sink_std_sender_test() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let script = Script::new(vec![Ok(Some(data(1.)))]);
+        let mut acquisition = Acquisition::start(script, config(), SinkDeliver(sender));
+        let received = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(received.unwrap().scaled_data, vec![1.]);
+        acquisition.join();
+    }
+
+    #[cfg(feature = "crossbeam")]
+    #[test]
+    fn test_sink_crossbeam_sender_test() {
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+        let script = Script::new(vec![Ok(Some(data(1.))), Ok(Some(data(2.)))]);
+        let mut acquisition = Acquisition::start(script, config(), SinkDeliver(sender));
+        let received: Vec<_> = receiver
+            .iter()
+            .take(2)
+            .map(|result| result.unwrap().scaled_data)
+            .collect();
+        assert_eq!(received, vec![vec![1.], vec![2.]]);
+        acquisition.join();
+    }
+}
@@ -0,0 +1,240 @@
+use std::{collections::VecDeque, fmt::Debug, sync::Arc, time::SystemTime};
+
+use crate::{sync::Shared, ErrorRecord, MedaqError, Sensor};
+
+/// Diagnostics of the reads of a sensor, see [`Sensor::stats`] and
+/// [`AcquisitionHandle::stats`](crate::AcquisitionHandle::stats)
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AcquisitionStats {
+    /// Frames waiting in the driver at the last sample, as reported by `DataAvail`
+    pub backlog: usize,
+    /// Largest backlog seen so far
+    pub peak_backlog: usize,
+    /// Frames transferred from the driver
+    pub frames_read: u64,
+    /// Frames discarded by the ring buffer, see
+    /// [`AcquisitionConfig::ring_buffer`](crate::AcquisitionConfig::ring_buffer)
+    pub frames_dropped: u64,
+    /// Gaps in the counter channel, see
+    /// [`AcquisitionConfig::gap_detection`](crate::AcquisitionConfig::gap_detection)
+    pub gaps: u64,
+    /// Frames missing in the counter channel
+    pub missing_frames: u64,
+    /// Reads that failed
+    pub read_errors: u64,
+    /// Message of the most recent failed read
+    pub last_error: Option<String>,
+}
+
+type BacklogHook = Arc<dyn Fn(usize) + Send + Sync>;
+
+/// Errors kept for [`Sensor::recent_errors`]
+const RECENT_ERRORS: usize = 20;
+
+#[derive(Default)]
+struct State {
+    stats: AcquisitionStats,
+    warning: Option<(usize, BacklogHook)>,
+    /// `true` while the backlog is above the warning threshold
+    warned: bool,
+    /// The latest errors, oldest first
+    errors: VecDeque<ErrorRecord>,
+}
+
+/// Statistics shared between a sensor and the handles of its acquisitions
+#[derive(Clone, Default)]
+pub(crate) struct Stats(Shared<State>);
+
+impl Debug for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl Stats {
+    pub(crate) fn get(&self) -> AcquisitionStats {
+        self.0.lock().stats.clone()
+    }
+
+    /// Record the current backlog, calling the warning hook when it rises above the
+    /// threshold
+    pub(crate) fn record_backlog(&self, frames: usize) {
+        let hook = {
+            let mut state = self.0.lock();
+            state.stats.backlog = frames;
+            state.stats.peak_backlog = state.stats.peak_backlog.max(frames);
+            let hook = match &state.warning {
+                Some((threshold, hook)) if frames > *threshold => Some(Arc::clone(hook)),
+                _ => None,
+            };
+            let rising = hook.is_some() && !state.warned;
+            state.warned = hook.is_some();
+            hook.filter(|_| rising)
+        };
+        // called without holding the lock, so the hook may read the statistics
+        if let Some(hook) = hook {
+            hook(frames);
+        }
+    }
+
+    pub(crate) fn record_read(&self, result: &Result<usize, MedaqError>) {
+        let mut state = self.0.lock();
+        match result {
+            Ok(frames) => state.stats.frames_read += *frames as u64,
+            Err(err) => {
+                state.stats.read_errors += 1;
+                state.stats.last_error = Some(err.to_string());
+                state.record_error("read", err);
+            }
+        }
+    }
+
+    /// Keep the error of a failed call for [`Sensor::recent_errors`]
+    pub(crate) fn record_call<T>(&self, call: &str, result: &Result<T, MedaqError>) {
+        if let Err(err) = result {
+            self.0.lock().record_error(call, err);
+        }
+    }
+
+    pub(crate) fn recent_errors(&self) -> Vec<ErrorRecord> {
+        self.0.lock().errors.iter().cloned().collect()
+    }
+
+    pub(crate) fn record_dropped(&self, frames: usize) {
+        self.0.lock().stats.frames_dropped += frames as u64;
+    }
+
+    pub(crate) fn record_gap(&self, missing: u64) {
+        let mut state = self.0.lock();
+        state.stats.gaps += 1;
+        state.stats.missing_frames += missing;
+    }
+
+    pub(crate) fn warn_backlog_above(&self, frames: usize, hook: BacklogHook) {
+        let mut state = self.0.lock();
+        state.warning = Some((frames, hook));
+        state.warned = false;
+    }
+}
+
+impl State {
+    fn record_error(&mut self, call: &str, err: &MedaqError) {
+        if self.errors.len() == RECENT_ERRORS {
+            self.errors.pop_front();
+        }
+        self.errors.push_back(ErrorRecord {
+            time: SystemTime::now(),
+            call: call.into(),
+            message: err.to_string(),
+        });
+    }
+}
+
+impl Sensor {
+    /// Diagnostics of all reads of this sensor, including reads of background
+    /// acquisitions
+    pub fn stats(&self) -> AcquisitionStats {
+        self.stats.get()
+    }
+
+    /// Query the driver backlog and update [`stats`](Self::stats), returns the
+    /// number of frames waiting
+    ///
+    /// Every read samples the backlog already, manual read loops can call this
+    /// between reads for a finer picture.
+    pub fn record_backlog_sample(&self) -> Result<usize, MedaqError> {
+        let available = self.data_available()?;
+        let frames = available / self.parameters.len().max(1);
+        self.stats.record_backlog(frames);
+        Ok(frames)
+    }
+
+    /// Call `callback` with the backlog in frames whenever it rises above `frames`
+    ///
+    /// The callback runs on the reading thread once per crossing, it is called
+    /// again only after the backlog fell back to at most `frames`. Replaces a
+    /// previously set callback.
+    pub fn warn_backlog_above(
+        &self,
+        frames: usize,
+        callback: impl Fn(usize) + Send + Sync + 'static,
+    ) {
+        self.stats.warn_backlog_above(frames, Arc::new(callback));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::Stats;
+    use crate::{AcquisitionStats, MedaqError};
+
+    #[test]
+    fn test_stats_record_test() {
+        let stats = Stats::default();
+        stats.record_backlog(10);
+        stats.record_backlog(3);
+        stats.record_read(&Ok(7));
+        stats.record_read(&Ok(3));
+        stats.record_read(&Err(MedaqError::NoChannels));
+        stats.record_dropped(2);
+        stats.record_gap(4);
+
+        assert_eq!(
+            stats.get(),
+            AcquisitionStats {
+                backlog: 3,
+                peak_backlog: 10,
+                frames_read: 10,
+                frames_dropped: 2,
+                gaps: 1,
+                missing_frames: 4,
+                read_errors: 1,
+                last_error: Some("sensor does not transmit any channels".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_stats_recent_errors_test() {
+        let stats = Stats::default();
+        stats.record_call("Set_Measrate", &Ok(()));
+        for counter in 0..25 {
+            stats.record_call(
+                &format!("SP_{counter}"),
+                &Err::<(), _>(MedaqError::NoBuffer),
+            );
+        }
+        stats.record_read(&Err(MedaqError::NoChannels));
+
+        let errors = stats.recent_errors();
+        assert_eq!(errors.len(), 20);
+        assert_eq!(errors[0].call, "SP_6");
+        assert_eq!(errors[19].call, "read");
+        assert_eq!(errors[19].message, "sensor does not transmit any channels");
+    }
+
+    #[test]
+    fn test_stats_backlog_warning_test() {
+        let stats = Stats::default();
+        let warnings = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&warnings);
+        stats.warn_backlog_above(
+            5,
+            Arc::new(move |frames| {
+                assert!(frames > 5);
+                counter.fetch_add(1, Ordering::Relaxed);
+            }),
+        );
+
+        for backlog in [1, 6, 8, 5, 9, 9, 2] {
+            stats.record_backlog(backlog);
+        }
+        assert_eq!(warnings.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,159 @@
+use std::time::{Duration, Instant};
+
+use crate::{sync::StopFlag, Data, MedaqError, Pacer, Sensor};
+
+/// Cooperative cancellation of blocking reads and background acquisitions
+///
+/// Clones share the same state, cancelling one cancels all of them. Cancellation
+/// is checked between polls, so a call into MEDAQLib already in progress is
+/// finished first.
+///
+/// # Example
+/// ```
+/// use medaqlib::StopToken;
+///
+/// let token = StopToken::new();
+/// let shutdown = token.clone();
+/// std::thread::spawn(move || shutdown.cancel()).join().unwrap();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StopToken {
+    cancelled: StopFlag,
+}
+
+impl StopToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel all blocking calls and acquisitions using this token or a clone
+    pub fn cancel(&self) {
+        self.cancelled.stop();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_stopped()
+    }
+}
+
+impl Sensor {
+    /// Like [`read_data_blocking`](Self::read_data_blocking), but returns
+    /// [`MedaqError::Cancelled`] soon after `token` is cancelled
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use medaqlib::{Interface, MedaqError, SensorBuilder, StopToken, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// let token = StopToken::new();
+    /// loop {
+    ///     match sensor.read_data_cancellable(Duration::from_secs(1), &token) {
+    ///         Ok(Some(data)) => println!("{data}"),
+    ///         Ok(None) => println!("no data"),
+    ///         Err(MedaqError::Cancelled) => break,
+    ///         Err(err) => panic!("{err}"),
+    ///     }
+    /// }
+    /// ```
+    pub fn read_data_cancellable(
+        &self,
+        timeout: Duration,
+        token: &StopToken,
+    ) -> Result<Option<Data>, MedaqError> {
+        poll(timeout, token, &mut self.pacer.borrow_mut(), || {
+            self.read_data()
+        })
+    }
+}
+
+/// Call `read` until it returns a capture, `timeout` elapsed or `token` is cancelled
+pub(crate) fn poll(
+    timeout: Duration,
+    token: &StopToken,
+    pacer: &mut Pacer,
+    mut read: impl FnMut() -> Result<Option<Data>, MedaqError>,
+) -> Result<Option<Data>, MedaqError> {
+    let start = Instant::now();
+    loop {
+        if token.is_cancelled() {
+            return Err(MedaqError::Cancelled);
+        }
+        let data = read()?;
+        pacer.record_poll(data.as_ref().map_or(0, Data::frame_count));
+        if data.is_some() {
+            return Ok(data);
+        }
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        std::thread::sleep(remaining.min(pacer.interval()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::poll;
+    use crate::{acquisition::tests::data, pacer::blocking_pacer, MedaqError, StopToken};
+
+    #[test]
+    fn test_poll_returns_value_test() {
+        let mut reads = 0;
+        let mut pacer = blocking_pacer();
+        let value = poll(
+            Duration::from_secs(5),
+            &StopToken::new(),
+            &mut pacer,
+            || {
+                reads += 1;
+                Ok((reads == 3).then(|| data(1.)))
+            },
+        );
+        assert_eq!(value.unwrap().unwrap().scaled_data, vec![1.]);
+        assert!(pacer.observed_rate().unwrap() > 0.);
+        assert!(pacer.interval() <= Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_poll_timeout_test() {
+        let mut pacer = blocking_pacer();
+        let value = poll(
+            Duration::from_millis(5),
+            &StopToken::new(),
+            &mut pacer,
+            || Ok(None),
+        );
+        assert!(value.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_poll_cancelled_promptly_test() {
+        let token = StopToken::new();
+        let canceller = token.clone();
+        let cancelled_at = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            canceller.cancel();
+            Instant::now()
+        });
+
+        let result = poll(
+            Duration::from_secs(60),
+            &token,
+            &mut blocking_pacer(),
+            || Ok(None),
+        );
+        let returned_at = Instant::now();
+        assert!(matches!(result, Err(MedaqError::Cancelled)));
+        let reaction = returned_at.duration_since(cancelled_at.join().unwrap());
+        assert!(reaction < Duration::from_millis(50), "{reaction:?}");
+    }
+}
@@ -0,0 +1,574 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use tokio::{
+    sync::{mpsc, Notify},
+    time::{self, MissedTickBehavior},
+};
+
+use crate::{acquisition::Acquire, Data, Frame, MedaqError, Sensor};
+
+/// Number of captures buffered between the polling task and the stream
+const STREAM_CAPACITY: usize = 16;
+
+impl Sensor {
+    /// Stream of captures read every `poll_interval`
+    ///
+    /// The sensor is moved to a task polling it with
+    /// [`spawn_blocking`](tokio::task::spawn_blocking), so this must be called
+    /// within a tokio runtime. Polls without data are skipped, read errors are
+    /// yielded and polling continues. Dropping the stream stops the polling and
+    /// releases the sensor.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() {
+    /// use std::{future::poll_fn, pin::pin, time::Duration};
+    /// use futures_core::Stream;
+    /// use medaqlib::{Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// let mut stream = pin!(sensor.into_stream(Duration::from_millis(100)));
+    /// while let Some(data) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+    ///     println!("{}", data.unwrap());
+    /// }
+    /// # }
+    /// ```
+    pub fn into_stream(self, poll_interval: Duration) -> DataStream {
+        DataStream::start(self, poll_interval)
+    }
+
+    /// Stream of single frame captures, see [`Sensor::into_stream`]
+    ///
+    /// Every capture is split into one [`Data`] per frame, all with the timestamp
+    /// of the capture.
+    pub fn frames_stream(self, poll_interval: Duration) -> FrameStream {
+        self.into_stream(poll_interval).frames()
+    }
+
+    /// Stream of owned frames buffered up to a fixed number of frames, see
+    /// [`Sensor::into_stream`]
+    ///
+    /// While the consumer is behind, the polling task either waits for room in the
+    /// buffer or discards the oldest frames, depending on `policy`. Read errors
+    /// are buffered as well but never discarded.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # async fn example() {
+    /// use std::{future::poll_fn, pin::pin, time::Duration};
+    /// use futures_core::Stream;
+    /// use medaqlib::{Backpressure, Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// let policy = Backpressure::DropOldest { capacity_frames: 10_000 };
+    /// let mut frames = pin!(sensor.frames_stream_with(Duration::from_millis(10), policy));
+    /// while let Some(frame) = poll_fn(|cx| frames.as_mut().poll_next(cx)).await {
+    ///     println!("{:?}", frame.unwrap().scaled());
+    /// }
+    /// # }
+    /// ```
+    pub fn frames_stream_with(
+        self,
+        poll_interval: Duration,
+        policy: Backpressure,
+    ) -> BufferedFrames {
+        BufferedFrames::start(self, poll_interval, policy)
+    }
+}
+
+/// What the polling task of [`Sensor::frames_stream_with`] does once the buffer
+/// holds `capacity_frames` frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Stop polling until the consumer made room, frames pile up in MEDAQLib
+    Block { capacity_frames: usize },
+    /// Keep polling and discard the oldest buffered frames
+    DropOldest { capacity_frames: usize },
+}
+
+impl Backpressure {
+    fn capacity_frames(&self) -> usize {
+        match *self {
+            Backpressure::Block { capacity_frames }
+            | Backpressure::DropOldest { capacity_frames } => capacity_frames.max(1),
+        }
+    }
+}
+
+/// Receives the results of the polling task
+trait Forward: Send {
+    /// Completes once nobody receives the results anymore
+    fn closed(&self) -> impl Future<Output = ()> + Send;
+
+    /// Hand over a result, `false` ends the polling
+    fn forward(&mut self, result: Result<Data, MedaqError>) -> impl Future<Output = bool> + Send;
+}
+
+impl Forward for mpsc::Sender<Result<Data, MedaqError>> {
+    fn closed(&self) -> impl Future<Output = ()> + Send {
+        mpsc::Sender::closed(self)
+    }
+
+    async fn forward(&mut self, result: Result<Data, MedaqError>) -> bool {
+        self.send(result).await.is_ok()
+    }
+}
+
+/// Captures of a sensor polled in the background, see [`Sensor::into_stream`]
+pub struct DataStream {
+    receiver: mpsc::Receiver<Result<Data, MedaqError>>,
+}
+
+impl DataStream {
+    pub(crate) fn start<S: Acquire + 'static>(source: S, poll_interval: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel(STREAM_CAPACITY);
+        tokio::spawn(poll(source, poll_interval, sender));
+        Self { receiver }
+    }
+
+    /// Split every capture into one [`Data`] per frame
+    pub fn frames(self) -> FrameStream {
+        FrameStream {
+            captures: self,
+            current: None,
+        }
+    }
+}
+
+impl Stream for DataStream {
+    type Item = Result<Data, MedaqError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+async fn poll<S: Acquire + 'static>(
+    mut source: S,
+    poll_interval: Duration,
+    mut sender: impl Forward,
+) {
+    let mut interval = time::interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = sender.closed() => break,
+            _ = interval.tick() => (),
+        }
+
+        let read = tokio::task::spawn_blocking(move || {
+            let result = source.acquire();
+            (source, result)
+        });
+        let result;
+        (source, result) = match read.await {
+            Ok(read) => read,
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        };
+
+        let result = match result {
+            Ok(Some(data)) => Ok(data),
+            Ok(None) => continue,
+            Err(err) => Err(err),
+        };
+        if !sender.forward(result).await {
+            break;
+        }
+    }
+    // closing the sensor calls into MEDAQLib, keep it off the async workers
+    let _ = tokio::task::spawn_blocking(move || drop(source)).await;
+}
+
+/// Single frame captures, see [`Sensor::frames_stream`]
+pub struct FrameStream {
+    captures: DataStream,
+    /// Capture being split and the next frame to yield
+    current: Option<(Data, usize)>,
+}
+
+impl Stream for FrameStream {
+    type Item = Result<Data, MedaqError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some((data, frame)) = &mut self.current {
+                if *frame < data.frame_count() {
+                    let single = single_frame(data, *frame);
+                    *frame += 1;
+                    return Poll::Ready(Some(Ok(single)));
+                }
+                self.current = None;
+            }
+
+            match Pin::new(&mut self.captures).poll_next(cx) {
+                Poll::Ready(Some(Ok(data))) => self.current = Some((data, 0)),
+                other => return other,
+            }
+        }
+    }
+}
+
+fn single_frame(data: &Data, frame: usize) -> Data {
+    let channels = data.channel_count();
+    let range = frame * channels..(frame + 1) * channels;
+    let values = |len: usize| match len {
+        0 => 0..0,
+        _ => range.clone(),
+    };
+    data.with_values(
+        data.raw_data[values(data.raw_data.len())].to_vec(),
+        data.scaled_data[values(data.scaled_data.len())].to_vec(),
+    )
+}
+
+/// Owned frames buffered between the polling task and the stream, see
+/// [`Sensor::frames_stream_with`]
+pub struct BufferedFrames {
+    queue: Arc<FrameQueue>,
+}
+
+struct FrameQueue {
+    state: Mutex<QueueState>,
+    /// Room was made in the buffer or the stream was dropped
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    items: VecDeque<Result<Frame, MedaqError>>,
+    dropped_frames: u64,
+    waker: Option<Waker>,
+    /// The stream was dropped
+    closed: bool,
+    /// The polling task ended
+    finished: bool,
+}
+
+impl FrameQueue {
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl BufferedFrames {
+    pub(crate) fn start<S: Acquire + 'static>(
+        source: S,
+        poll_interval: Duration,
+        policy: Backpressure,
+    ) -> Self {
+        let queue = Arc::new(FrameQueue {
+            state: Mutex::default(),
+            changed: Notify::new(),
+        });
+        let producer = Producer {
+            queue: Arc::clone(&queue),
+            policy,
+        };
+        tokio::spawn(poll(source, poll_interval, producer));
+        Self { queue }
+    }
+
+    /// Number of frames discarded by [`Backpressure::DropOldest`] so far
+    pub fn dropped_frames(&self) -> u64 {
+        self.queue.lock().dropped_frames
+    }
+}
+
+impl Stream for BufferedFrames {
+    type Item = Result<Frame, MedaqError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.queue.lock();
+        match state.items.pop_front() {
+            Some(item) => {
+                drop(state);
+                self.queue.changed.notify_one();
+                Poll::Ready(Some(item))
+            }
+            None if state.finished => Poll::Ready(None),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for BufferedFrames {
+    fn drop(&mut self) {
+        self.queue.lock().closed = true;
+        self.queue.changed.notify_one();
+    }
+}
+
+/// Polling end of a [`BufferedFrames`], ends the stream when dropped
+struct Producer {
+    queue: Arc<FrameQueue>,
+    policy: Backpressure,
+}
+
+impl Producer {
+    /// Buffer an item, `false` if the stream was dropped
+    async fn push(&self, item: Result<Frame, MedaqError>) -> bool {
+        let capacity = self.policy.capacity_frames();
+        loop {
+            let changed = self.queue.changed.notified();
+            {
+                let mut state = self.queue.lock();
+                if state.closed {
+                    return false;
+                }
+                let full = state.items.len() >= capacity;
+                match self.policy {
+                    Backpressure::Block { .. } if full => (),
+                    Backpressure::DropOldest { .. } if full && item.is_ok() => {
+                        if let Some(oldest) = state.items.iter().position(Result::is_ok) {
+                            state.items.remove(oldest);
+                            state.dropped_frames += 1;
+                        }
+                        state.items.push_back(item);
+                        return wake(state);
+                    }
+                    _ => {
+                        state.items.push_back(item);
+                        return wake(state);
+                    }
+                }
+            }
+            changed.await;
+        }
+    }
+}
+
+fn wake(mut state: std::sync::MutexGuard<'_, QueueState>) -> bool {
+    let waker = state.waker.take();
+    drop(state);
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+    true
+}
+
+impl Forward for Producer {
+    async fn closed(&self) {
+        loop {
+            let changed = self.queue.changed.notified();
+            if self.queue.lock().closed {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    async fn forward(&mut self, result: Result<Data, MedaqError>) -> bool {
+        let data = match result {
+            Ok(data) => data,
+            Err(err) => return self.push(Err(err)).await,
+        };
+        for index in 0..data.frame_count() {
+            let frame = data.frame(index).expect("index within the capture");
+            if !self.push(Ok(frame)).await {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Drop for Producer {
+    fn drop(&mut self) {
+        let mut state = self.queue.lock();
+        state.finished = true;
+        wake(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::poll_fn,
+        pin::Pin,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use futures_core::Stream;
+
+    use super::{BufferedFrames, DataStream};
+    use crate::{
+        acquisition::{
+            tests::{data, Script},
+            Acquire,
+        },
+        Backpressure, Data, MedaqError,
+    };
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn test_data_stream_test() {
+        let script = Script::new(vec![
+            Ok(None),
+            Ok(Some(data(1.))),
+            Err(MedaqError::NoChannels),
+            Ok(Some(data(2.))),
+        ]);
+        let mut stream = DataStream::start(script, Duration::from_millis(1));
+
+        assert_eq!(
+            next(&mut stream).await.unwrap().unwrap().scaled_data,
+            vec![1.]
+        );
+        assert!(matches!(
+            next(&mut stream).await,
+            Some(Err(MedaqError::NoChannels))
+        ));
+        assert_eq!(
+            next(&mut stream).await.unwrap().unwrap().scaled_data,
+            vec![2.]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_data_stream_drop_releases_source_test() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        struct Released(Script, std::sync::mpsc::Sender<()>);
+        impl crate::acquisition::Acquire for Released {
+            fn acquire(&mut self) -> Result<Option<Data>, MedaqError> {
+                self.0.acquire()
+            }
+        }
+        impl Drop for Released {
+            fn drop(&mut self) {
+                let _ = self.1.send(());
+            }
+        }
+
+        let source = Released(Script::new(vec![Ok(Some(data(1.)))]), sender);
+        let mut stream = DataStream::start(source, Duration::from_millis(1));
+        assert!(next(&mut stream).await.is_some());
+        drop(stream);
+
+        let released =
+            tokio::task::spawn_blocking(move || receiver.recv_timeout(Duration::from_secs(5)));
+        assert!(released.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_frames_stream_test() {
+        let capture = Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            vec![1., 10., 2., 20.],
+        );
+        let script = Script::new(vec![Ok(Some(capture)), Ok(Some(data(3.)))]);
+        let mut frames = DataStream::start(script, Duration::from_millis(1)).frames();
+
+        let mut values = vec![];
+        for _ in 0..3 {
+            let frame = next(&mut frames).await.unwrap().unwrap();
+            assert_eq!(frame.frame_count(), 1);
+            values.push(frame.scaled_data);
+        }
+        assert_eq!(values, vec![vec![1., 10.], vec![2., 20.], vec![3.]]);
+    }
+
+    /// Three captures of four frames counting up from zero, counting the reads
+    fn counted() -> (impl Acquire, Arc<AtomicUsize>) {
+        struct Counted(Script, Arc<AtomicUsize>);
+        impl Acquire for Counted {
+            fn acquire(&mut self) -> Result<Option<Data>, MedaqError> {
+                self.1.fetch_add(1, Ordering::SeqCst);
+                self.0.acquire()
+            }
+        }
+
+        let captures = (0..3)
+            .map(|capture| {
+                let values = (0..4).map(|frame| (capture * 4 + frame) as f64).collect();
+                Ok(Some(Data::new(
+                    vec!["Distance".to_string()],
+                    vec![],
+                    values,
+                )))
+            })
+            .collect();
+        let reads = Arc::new(AtomicUsize::new(0));
+        (Counted(Script::new(captures), Arc::clone(&reads)), reads)
+    }
+
+    async fn values(frames: &mut BufferedFrames, count: usize) -> Vec<f64> {
+        let mut values = vec![];
+        for _ in 0..count {
+            values.push(next(frames).await.unwrap().unwrap().scaled()[0]);
+        }
+        values
+    }
+
+    #[tokio::test]
+    async fn test_frames_stream_block_test() {
+        let (source, reads) = counted();
+        let policy = Backpressure::Block { capacity_frames: 5 };
+        let mut frames = BufferedFrames::start(source, Duration::from_millis(1), policy);
+
+        // the consumer pauses, polling stops in the second capture
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(reads.load(Ordering::SeqCst), 2);
+
+        let values = values(&mut frames, 12).await;
+        assert_eq!(values, (0..12).map(f64::from).collect::<Vec<_>>());
+        assert_eq!(frames.dropped_frames(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_frames_stream_drop_oldest_test() {
+        let (source, reads) = counted();
+        let policy = Backpressure::DropOldest { capacity_frames: 5 };
+        let mut frames = BufferedFrames::start(source, Duration::from_millis(1), policy);
+
+        // the consumer pauses, polling continues and keeps the newest frames
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(reads.load(Ordering::SeqCst) > 3);
+        assert_eq!(frames.dropped_frames(), 7);
+
+        let values = values(&mut frames, 5).await;
+        assert_eq!(values, vec![7., 8., 9., 10., 11.]);
+    }
+
+    #[tokio::test]
+    async fn test_frames_stream_keeps_errors_test() {
+        let script = Script::new(vec![Err(MedaqError::NoChannels), Ok(Some(data(1.)))]);
+        let policy = Backpressure::DropOldest { capacity_frames: 1 };
+        let mut frames = BufferedFrames::start(script, Duration::from_millis(1), policy);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(matches!(
+            next(&mut frames).await,
+            Some(Err(MedaqError::NoChannels))
+        ));
+        assert_eq!(next(&mut frames).await.unwrap().unwrap().scaled(), [1.]);
+    }
+}
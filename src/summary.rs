@@ -0,0 +1,205 @@
+use std::{
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Data, Value};
+
+/// Statistics of the scaled values of one channel, see [`CaptureSummary`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ChannelSummary {
+    pub channel: String,
+    pub unit: Option<String>,
+    /// Mean of the valid values, `None` if there are none
+    pub mean: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// Share of valid values, from 0 to 1
+    pub valid_ratio: f64,
+}
+
+/// Summary of a capture for logging, see [`Data::summary`]
+///
+/// The [`Display`] line has a stable format, e.g.
+///
+/// `frames=4 invalid=1 ts=1700000000.250 | Distance [mm] mean=1.500 min=1.000 max=2.000 valid=75.0%`
+///
+/// Values are printed with three decimals, missing values and timestamps as `-`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CaptureSummary {
+    pub frames: usize,
+    pub timestamp: Option<SystemTime>,
+    pub channels: Vec<ChannelSummary>,
+    /// Number of out of range scaled values over all channels
+    pub invalid_samples: usize,
+}
+
+impl Data {
+    /// Frame count, timestamp and per channel statistics of the scaled values
+    ///
+    /// # Example
+    /// ```
+    /// use medaqlib::Data;
+    ///
+    /// let data = Data::new(vec!["Distance".to_string()], vec![], vec![1., 2.]);
+    /// assert_eq!(
+    ///     data.summary().to_string(),
+    ///     "frames=2 invalid=0 ts=- | Distance mean=1.500 min=1.000 max=2.000 valid=100.0%"
+    /// );
+    /// ```
+    pub fn summary(&self) -> CaptureSummary {
+        let channel_count = self.channel_count();
+        let frames = match channel_count {
+            0 => 0,
+            channels => self.scaled_data.len() / channels,
+        };
+
+        let mut invalid_samples = 0;
+        let channels = self
+            .channels()
+            .iter()
+            .enumerate()
+            .map(|(index, channel)| {
+                let samples = self.scaled_data.iter().skip(index).step_by(channel_count);
+                let valid: Vec<f64> = samples
+                    .take(frames)
+                    .filter_map(|&sample| Value::from_sample(sample).into_raw())
+                    .collect();
+                invalid_samples += frames - valid.len();
+
+                ChannelSummary {
+                    channel: channel.clone(),
+                    unit: self.unit(index).map(str::to_string),
+                    mean: (!valid.is_empty())
+                        .then(|| valid.iter().sum::<f64>() / valid.len() as f64),
+                    min: valid.iter().copied().reduce(f64::min),
+                    max: valid.iter().copied().reduce(f64::max),
+                    valid_ratio: match frames {
+                        0 => 0.,
+                        frames => valid.len() as f64 / frames as f64,
+                    },
+                }
+            })
+            .collect();
+
+        CaptureSummary {
+            frames,
+            timestamp: self.timestamp(),
+            channels,
+            invalid_samples,
+        }
+    }
+}
+
+struct Optional(Option<f64>);
+
+impl Display for Optional {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(value) => write!(f, "{value:.3}"),
+            None => write!(f, "-"),
+        }
+    }
+}
+
+impl Display for ChannelSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.unit {
+            Some(unit) => write!(f, "{} [{unit}]", self.channel)?,
+            None => write!(f, "{}", self.channel)?,
+        }
+        write!(
+            f,
+            " mean={} min={} max={} valid={:.1}%",
+            Optional(self.mean),
+            Optional(self.min),
+            Optional(self.max),
+            self.valid_ratio * 100.
+        )
+    }
+}
+
+impl Display for CaptureSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let timestamp = self.timestamp.and_then(|timestamp| {
+            timestamp
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|since_epoch| since_epoch.as_secs_f64())
+        });
+        write!(
+            f,
+            "frames={} invalid={} ts={}",
+            self.frames,
+            self.invalid_samples,
+            Optional(timestamp)
+        )?;
+        for channel in &self.channels {
+            write!(f, " | {channel}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::{Data, DataBuilder, SCALED_OUT_OF_RANGE as OOR};
+
+    fn data() -> Data {
+        DataBuilder::channels(["Distance", "Intensity"])
+            .frame([1., OOR])
+            .frame([2., OOR])
+            .frame([OOR, OOR])
+            .frame([1.5, OOR])
+            .units([Some("mm"), None])
+            .timestamp(UNIX_EPOCH + Duration::from_millis(1_700_000_000_250))
+            .build()
+    }
+
+    #[test]
+    fn test_summary_test() {
+        let summary = data().summary();
+        assert_eq!(summary.frames, 4);
+        assert_eq!(summary.invalid_samples, 5);
+        assert_eq!(summary.channels[0].mean, Some(1.5));
+        assert_eq!(summary.channels[0].min, Some(1.));
+        assert_eq!(summary.channels[0].max, Some(2.));
+        assert_eq!(summary.channels[0].valid_ratio, 0.75);
+        assert_eq!(summary.channels[1].mean, None);
+        assert_eq!(summary.channels[1].valid_ratio, 0.);
+    }
+
+    #[test]
+    fn test_summary_display_snapshot_test() {
+        assert_eq!(
+            data().summary().to_string(),
+            "frames=4 invalid=5 ts=1700000000.250 \
+             | Distance [mm] mean=1.500 min=1.000 max=2.000 valid=75.0% \
+             | Intensity mean=- min=- max=- valid=0.0%"
+        );
+    }
+
+    #[test]
+    fn test_summary_empty_capture_test() {
+        let data = Data::new(vec!["Distance".to_string()], vec![], vec![]);
+        assert_eq!(
+            data.summary().to_string(),
+            "frames=0 invalid=0 ts=- | Distance mean=- min=- max=- valid=0.0%"
+        );
+        let data = Data::new(Vec::<String>::new(), vec![], vec![]);
+        assert_eq!(data.summary().to_string(), "frames=0 invalid=0 ts=-");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_summary_serialize_test() {
+        let json = serde_json::to_value(data().summary()).unwrap();
+        assert_eq!(json["frames"], 4);
+        assert_eq!(json["channels"][0]["unit"], "mm");
+        assert_eq!(json["channels"][1]["mean"], serde_json::Value::Null);
+    }
+}
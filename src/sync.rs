@@ -0,0 +1,227 @@
+//! Synchronization shared between the acquisition threads and their handles
+//!
+//! Built on loom with `--cfg loom`, so the tests below can check every
+//! interleaving. Nothing here calls into MEDAQLib. Run them with
+//! `RUSTFLAGS="--cfg loom" cargo test --release --lib sync::tests`.
+
+use std::{fmt::Debug, sync::PoisonError, time::Duration};
+
+#[cfg(loom)]
+use loom::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+};
+#[cfg(not(loom))]
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+};
+
+/// Stop request shared by clones
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StopFlag(Arc<AtomicBool>);
+
+impl StopFlag {
+    pub(crate) fn stop(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// Value behind a mutex shared by clones, a panic while holding the lock does not
+/// poison it for the other threads
+pub(crate) struct Shared<T>(Arc<Mutex<T>>);
+
+impl<T> Shared<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self(Arc::new(Mutex::new(value)))
+    }
+
+    pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T: Default> Default for Shared<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Debug> Debug for Shared<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.lock().fmt(f)
+    }
+}
+
+/// Wait for an unpark or the timeout, under loom only gives way to other threads
+pub(crate) fn park_timeout(timeout: Duration) {
+    #[cfg(loom)]
+    {
+        let _ = timeout;
+        thread::yield_now();
+    }
+    #[cfg(not(loom))]
+    thread::park_timeout(timeout);
+}
+
+#[cfg(all(test, loom))]
+mod tests {
+    use std::{sync::mpsc, time::Duration};
+
+    use loom::thread;
+
+    use super::{Shared, StopFlag};
+    use crate::{
+        acquisition::{self, tests::Script, Control, Deliver},
+        ring::{FrameRing, RingDeliver},
+        stats::Stats,
+        AcquisitionConfig, Data, DropPolicy, MedaqError, RingBufferConfig,
+    };
+
+    fn model(test: impl Fn() + Sync + Send + 'static) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(3);
+        builder.check(test);
+    }
+
+    fn capture(first: i32, frames: i32) -> Data {
+        let raw_data: Vec<i32> = (first..first + frames).collect();
+        let scaled_data = raw_data.iter().map(|&raw| raw as f64).collect();
+        Data::new(vec!["1".to_string()], raw_data, scaled_data)
+    }
+
+    fn ring(capacity_frames: usize) -> Shared<FrameRing> {
+        Shared::new(FrameRing::new(RingBufferConfig {
+            capacity_frames,
+            on_drop: DropPolicy::DropOldest,
+        }))
+    }
+
+    #[test]
+    fn test_stop_during_send_test() {
+        model(|| {
+            let stopped = StopFlag::default();
+            let (sender, receiver) = mpsc::sync_channel(1);
+            let thread = {
+                let stopped = stopped.clone();
+                thread::spawn(move || {
+                    let control = Control::new(&stopped, None, Duration::ZERO, None);
+                    let mut sender = sender;
+                    // the second result waits for the receiver until the stop
+                    let first = sender.deliver(Ok(capture(0, 1)), &control);
+                    let second = sender.deliver(Ok(capture(1, 1)), &control);
+                    (first, second)
+                })
+            };
+            stopped.stop();
+            assert_eq!(thread.join().unwrap(), (true, false));
+            assert!(receiver.try_recv().is_ok());
+        });
+    }
+
+    #[test]
+    fn test_snapshot_during_write_test() {
+        model(|| {
+            let ring = ring(4);
+            let stats = Stats::default();
+            let (errors, _receiver) = mpsc::sync_channel(1);
+            let thread = {
+                let mut deliver = RingDeliver {
+                    ring: ring.clone(),
+                    stats: stats.clone(),
+                    errors,
+                };
+                thread::spawn(move || {
+                    let stopped = StopFlag::default();
+                    let control = Control::new(&stopped, None, Duration::ZERO, None);
+                    deliver.deliver(Ok(capture(0, 2)), &control);
+                    deliver.deliver(Ok(capture(2, 3)), &control);
+                })
+            };
+
+            // every snapshot holds whole captures, never a partial push
+            let snapshot = ring.lock().snapshot();
+            assert!(
+                [vec![], vec![0, 1], vec![1, 2, 3, 4]].contains(&snapshot.raw_data),
+                "torn snapshot {:?}",
+                snapshot.raw_data
+            );
+            let dropped = stats.get().frames_dropped;
+            assert!(dropped <= 1);
+
+            thread.join().unwrap();
+            assert_eq!(ring.lock().snapshot().raw_data, vec![1, 2, 3, 4]);
+            assert_eq!(stats.get().frames_dropped, 1);
+        });
+    }
+
+    #[test]
+    fn test_stats_counters_test() {
+        model(|| {
+            let stats = Stats::default();
+            let threads: Vec<_> = (0..2)
+                .map(|_| {
+                    let stats = stats.clone();
+                    thread::spawn(move || {
+                        stats.record_read(&Ok(2));
+                        stats.record_read(&Err(MedaqError::NoChannels));
+                    })
+                })
+                .collect();
+            let seen = stats.get();
+            assert!(seen.frames_read <= 4 && seen.read_errors <= 2);
+            for thread in threads {
+                thread.join().unwrap();
+            }
+            let stats = stats.get();
+            assert_eq!((stats.frames_read, stats.read_errors), (4, 2));
+        });
+    }
+
+    #[test]
+    fn test_drop_while_running_test() {
+        model(|| {
+            let stopped = StopFlag::default();
+            let ring = ring(8);
+            let (errors, _receiver) = mpsc::sync_channel(1);
+            let deliver = RingDeliver {
+                ring: ring.clone(),
+                stats: Stats::default(),
+                errors,
+            };
+            let thread = {
+                let stopped = stopped.clone();
+                let config = AcquisitionConfig {
+                    poll_interval: Duration::ZERO,
+                    ..Default::default()
+                };
+                let source = Script::new(vec![Ok(Some(capture(0, 1))), Ok(Some(capture(1, 1)))]);
+                thread::spawn(move || acquisition::run(source, config, deliver, &stopped))
+            };
+
+            // what the drop of an acquisition does
+            stopped.stop();
+            let source = thread.join().unwrap();
+
+            // the drain after the stop delivers the rest of the script
+            assert!(source.reads >= 3);
+            assert_eq!(ring.lock().snapshot().raw_data, vec![0, 1]);
+        });
+    }
+}
@@ -0,0 +1,207 @@
+use crate::{MedaqError, Sensor, SensorGroup};
+
+/// Hardware synchronization of sensors measuring together, see
+/// [`Sensor::set_sync_mode`]
+///
+/// The sync output of the master is wired to the sync inputs of the slaves, with a
+/// common ground, e.g. the `Sync` pins of two opposing ILDs of a thickness
+/// measurement. MEDAQLib cannot check the wiring, slaves without the pulses of a
+/// master just stop measuring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyncMode {
+    /// Measuring on its own clock
+    None,
+    /// Measuring on its own clock and driving the sync output
+    Master,
+    /// Measuring on the pulses of the sync input
+    Slave,
+}
+
+impl Sensor {
+    /// Set the synchronization with `Set_SyncMode` and check it with
+    /// `Get_SyncMode`
+    ///
+    /// Fails with [`MedaqError::Rejected`] if the sensor reports another mode
+    /// afterwards.
+    pub fn set_sync_mode(&self, mode: SyncMode) -> Result<(), MedaqError> {
+        let value = match mode {
+            SyncMode::None => 0,
+            SyncMode::Master => 1,
+            SyncMode::Slave => 2,
+        };
+        let _instance = self.hold()?;
+        self.set_parameter_int("SP_SyncMode", value)?;
+        self.execute_command("Set_SyncMode")?;
+        match self.sync_mode()? {
+            reported if reported == mode => Ok(()),
+            reported => Err(MedaqError::Rejected {
+                setting: format!("sync mode {mode:?}"),
+                reported: format!("{reported:?}"),
+            }),
+        }
+    }
+
+    /// The synchronization as reported by `Get_SyncMode`
+    pub fn sync_mode(&self) -> Result<SyncMode, MedaqError> {
+        let _instance = self.hold()?;
+        self.execute_command("Get_SyncMode")?;
+        // answered as a string, GetParameterInt is optional
+        let answer = self.get_parameter_string("SA_SyncMode")?;
+        match answer.trim() {
+            "0" => Ok(SyncMode::None),
+            "1" => Ok(SyncMode::Master),
+            "2" => Ok(SyncMode::Slave),
+            _ => Err(MedaqError::InvalidAnswer {
+                parameter: "SA_SyncMode".into(),
+                answer,
+            }),
+        }
+    }
+}
+
+impl SensorGroup {
+    /// Make the sensor at `master` the [`SyncMode::Master`] and the others its
+    /// [`SyncMode::Slave`]s, wired as described at [`SyncMode`]
+    ///
+    /// The master is set first, then the slaves in group order, each checked with
+    /// [`Sensor::set_sync_mode`]. If a sensor fails, every sensor of the group is
+    /// set back to [`SyncMode::None`], as far as they accept it, and the error is
+    /// returned as [`MedaqError::GroupMember`]. Fails with
+    /// [`MedaqError::InvalidSetting`] without touching a sensor if there is no
+    /// sensor at `master`.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use medaqlib::{Interface, SensorBuilder, SensorGroup, ME_SENSOR};
+    ///
+    /// let connect = |ip: &str| {
+    ///     SensorBuilder::new(ME_SENSOR::SENSOR_ILD1900)
+    ///         .with_interface(Interface::TcpIp)
+    ///         .with_ip_address(ip)
+    ///         .connect()
+    ///         .unwrap()
+    /// };
+    /// let group = SensorGroup::new(vec![connect("10.10.10.10"), connect("10.10.10.11")]);
+    /// // the top head drives the bottom one
+    /// group.configure_sync(0).unwrap();
+    /// ```
+    pub fn configure_sync(&self, master: usize) -> Result<(), MedaqError> {
+        let sensors = self.sensors();
+        if master >= sensors.len() {
+            return Err(MedaqError::InvalidSetting {
+                setting: "sync master".into(),
+                reason: format!("no sensor {master} in a group of {}", sensors.len()),
+            });
+        }
+
+        let slaves = (0..sensors.len()).filter(|&index| index != master);
+        let roles = std::iter::once((master, SyncMode::Master))
+            .chain(slaves.map(|index| (index, SyncMode::Slave)));
+        for (index, mode) in roles {
+            if let Err(err) = sensors[index].set_sync_mode(mode) {
+                for sensor in sensors {
+                    // best effort, the error of the role is the one to report
+                    let _ = sensor.set_sync_mode(SyncMode::None);
+                }
+                return Err(MedaqError::GroupMember {
+                    sensor: index,
+                    source: Box::new(err),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyncMode;
+    use crate::{Call, Intercept, MedaqError, MockBackend, Sensor, SensorGroup, ME_SENSOR};
+
+    /// Sensor reporting the sync mode set last, except for `refused`
+    fn connect(mock: &MockBackend, refused: Option<&'static str>) -> Sensor {
+        let mut mode = String::from("0");
+        mock.intercept(move |call| match call.call {
+            Call::SetParameterInt if call.arguments[0] == "SP_SyncMode" => {
+                if Some(&*call.arguments[1]) != refused {
+                    mode = call.arguments[1].clone();
+                }
+                Intercept::Pass
+            }
+            Call::GetParameterString if call.arguments[0] == "SA_SyncMode" => {
+                Intercept::String(mode.clone())
+            }
+            _ => Intercept::Pass,
+        });
+        mock.connect(ME_SENSOR::SENSOR_ILD1900)
+    }
+
+    /// Sync modes set on the sensor, in order
+    fn sync_modes(mock: &MockBackend) -> Vec<String> {
+        mock.calls()
+            .into_iter()
+            .filter(|call| call.call == Call::SetParameterInt && call.arguments[0] == "SP_SyncMode")
+            .map(|call| call.arguments[1].clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_sync_mode_test() {
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor = connect(&mock, Some("1"));
+        sensor.set_sync_mode(SyncMode::Slave).unwrap();
+        assert_eq!(sensor.sync_mode().unwrap(), SyncMode::Slave);
+        assert_eq!(
+            mock.commands()[1..],
+            ["Set_SyncMode", "Get_SyncMode", "Get_SyncMode"]
+        );
+
+        let err = sensor.set_sync_mode(SyncMode::Master).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the sensor reported Slave after setting the sync mode Master"
+        );
+    }
+
+    #[test]
+    fn test_sync_group_test() {
+        let mocks: Vec<_> = (0..3)
+            .map(|_| MockBackend::new().with_channels(["Distance"]))
+            .collect();
+        let group: SensorGroup = mocks.iter().map(|mock| connect(mock, None)).collect();
+        group.configure_sync(1).unwrap();
+        let modes: Vec<_> = mocks.iter().map(sync_modes).collect();
+        assert_eq!(modes, [["2"], ["1"], ["2"]]);
+
+        assert!(matches!(
+            group.configure_sync(3),
+            Err(MedaqError::InvalidSetting { .. })
+        ));
+        assert_eq!(sync_modes(&mocks[0]), ["2"]);
+    }
+
+    #[test]
+    fn test_sync_group_rollback_test() {
+        let mocks: Vec<_> = (0..3)
+            .map(|_| MockBackend::new().with_channels(["Distance"]))
+            .collect();
+        let sensors = mocks
+            .iter()
+            .enumerate()
+            .map(|(index, mock)| connect(mock, (index == 2).then_some("2")));
+        let group = SensorGroup::new(sensors.collect());
+
+        let err = group.configure_sync(0).unwrap_err();
+        assert!(matches!(
+            &err,
+            MedaqError::GroupMember { sensor: 2, source }
+                if matches!(**source, MedaqError::Rejected { .. })
+        ));
+        // the slave at 1 was set before the failure, all are back to None
+        let modes: Vec<_> = mocks.iter().map(sync_modes).collect();
+        assert_eq!(modes, [["1", "0"], ["2", "0"], ["2", "0"]]);
+        for sensor in group.sensors() {
+            assert_eq!(sensor.sync_mode().unwrap(), SyncMode::None);
+        }
+    }
+}
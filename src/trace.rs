@@ -0,0 +1,760 @@
+//! Trace of the MEDAQLib calls of a sensor, see [`TraceBackend`] and [`ReplayTrace`].
+
+use std::{
+    ffi::CString,
+    fmt::{self, Debug},
+    fs::{self, File},
+    io::{self, LineWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Instant,
+};
+
+use crate::{
+    backend::{self, Backend},
+    names, DllVersion, MedaqError, ERR_CODE, ME_SENSOR,
+};
+
+const HEADER: &str = "# medaqlib trace 1";
+
+/// [`Backend`] decorator writing every call to a trace
+///
+/// A trace is a text file with a header line followed by one line per call,
+/// fields separated by tabs:
+///
+/// | field     | content                                                     |
+/// |-----------|-------------------------------------------------------------|
+/// | time      | microseconds since the trace was started                    |
+/// | function  | name of the call, e.g. `GetParameterString`                 |
+/// | arguments | parameter name and value, command, or `TransferData` sizes  |
+/// | `=`       | separates the arguments from the result                     |
+/// | result    | `ok` and returned values, `err` and the error code,         |
+/// |           | `unsupported` and a function MEDAQLib does not export       |
+///
+/// Transferred values are written comma separated, `-` for a buffer that was not
+/// given. Tabs, line breaks and backslashes in strings are escaped with a backslash.
+/// Handles are not written, a trace holds the calls of one sensor instance.
+///
+/// Write errors of the trace are logged once as a warning through the `log`
+/// facade and do not affect the sensor. See [`SensorBuilder::with_trace`](crate::SensorBuilder::with_trace)
+/// to trace the calls of a sensor to a file.
+pub struct TraceBackend {
+    inner: Arc<dyn Backend>,
+    started: Instant,
+    writer: Mutex<TraceWriter>,
+}
+
+struct TraceWriter {
+    writer: Box<dyn Write + Send>,
+    failed: bool,
+}
+
+impl Debug for TraceBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TraceBackend")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TraceBackend {
+    /// Trace the calls on `inner` to a new file
+    pub fn create(path: impl AsRef<Path>, inner: impl Backend + 'static) -> io::Result<Self> {
+        Self::to_file(path.as_ref(), Arc::new(inner))
+    }
+
+    /// Trace the calls on `inner` to a writer
+    pub fn new(writer: impl Write + Send + 'static, inner: impl Backend + 'static) -> Self {
+        Self::wrap(Box::new(writer), Arc::new(inner))
+    }
+
+    pub(crate) fn to_file(path: &Path, inner: Arc<dyn Backend>) -> io::Result<Self> {
+        let file = LineWriter::new(File::create(path)?);
+        Ok(Self::wrap(Box::new(file), inner))
+    }
+
+    fn wrap(writer: Box<dyn Write + Send>, inner: Arc<dyn Backend>) -> Self {
+        let trace = Self {
+            inner,
+            started: Instant::now(),
+            writer: Mutex::new(TraceWriter {
+                writer,
+                failed: false,
+            }),
+        };
+        trace.write_line(HEADER.into());
+        trace
+    }
+
+    fn record(&self, function: &str, arguments: &[&str], result: &[&str]) {
+        let mut fields = vec![
+            self.started.elapsed().as_micros().to_string(),
+            function.to_string(),
+        ];
+        fields.extend(arguments.iter().map(|argument| escape(argument)));
+        fields.push("=".into());
+        fields.extend(result.iter().map(|field| escape(field)));
+        self.write_line(fields.join("\t"));
+    }
+
+    fn record_result<T>(
+        &self,
+        function: &str,
+        arguments: &[&str],
+        result: &Result<T, MedaqError>,
+        value: impl FnOnce(&T) -> Vec<String>,
+    ) {
+        let fields = match result {
+            Ok(ok) => [vec!["ok".to_string()], value(ok)].concat(),
+            Err(MedaqError::Sensor(err_code)) => vec!["err".into(), format!("{err_code:?}")],
+            Err(MedaqError::InvalidString(_)) => vec!["invalid-utf8".into()],
+            Err(MedaqError::UnsupportedByDll { function }) => {
+                vec!["unsupported".into(), function.to_string()]
+            }
+            Err(err) => vec!["other".into(), err.to_string()],
+        };
+        let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+        self.record(function, arguments, &fields);
+    }
+
+    fn write_line(&self, line: String) {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        if writer.failed {
+            return;
+        }
+        if let Err(err) = writeln!(writer.writer, "{line}") {
+            log::warn!("writing the trace failed, no further calls are traced: {err}");
+            writer.failed = true;
+        }
+    }
+}
+
+impl Drop for TraceBackend {
+    fn drop(&mut self) {
+        let writer = self
+            .writer
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !writer.failed {
+            let _ = writer.writer.flush();
+        }
+    }
+}
+
+impl backend::sealed::Sealed for TraceBackend {}
+
+impl Backend for TraceBackend {
+    fn create_instance(&self, sensor_type: ME_SENSOR) -> u32 {
+        let handle = self.inner.create_instance(sensor_type);
+        let sensor_type = format!("{sensor_type:?}");
+        self.record("CreateInstance", &[&sensor_type], &[&handle.to_string()]);
+        handle
+    }
+
+    fn release_instance(&self, handle: u32) -> Result<(), MedaqError> {
+        let result = self.inner.release_instance(handle);
+        self.record_result("ReleaseInstance", &[], &result, |_| vec![]);
+        result
+    }
+
+    fn open_sensor(&self, handle: u32) -> Result<(), MedaqError> {
+        let result = self.inner.open_sensor(handle);
+        self.record_result("OpenSensor", &[], &result, |_| vec![]);
+        result
+    }
+
+    fn close_sensor(&self, handle: u32) -> Result<(), MedaqError> {
+        let result = self.inner.close_sensor(handle);
+        self.record_result("CloseSensor", &[], &result, |_| vec![]);
+        result
+    }
+
+    fn set_parameter_string(&self, handle: u32, name: &str, value: &str) -> Result<(), MedaqError> {
+        let result = self.inner.set_parameter_string(handle, name, value);
+        self.record_result("SetParameterString", &[name, value], &result, |_| vec![]);
+        result
+    }
+
+    fn set_parameter_int(&self, handle: u32, name: &str, value: i32) -> Result<(), MedaqError> {
+        let result = self.inner.set_parameter_int(handle, name, value);
+        let arguments = [name, &value.to_string()];
+        self.record_result("SetParameterInt", &arguments, &result, |_| vec![]);
+        result
+    }
+
+    fn get_parameter_string(&self, handle: u32, name: &str) -> Result<String, MedaqError> {
+        let result = self.inner.get_parameter_string(handle, name);
+        self.record_result("GetParameterString", &[name], &result, |value| {
+            vec![value.clone()]
+        });
+        result
+    }
+
+    fn get_parameter_double(&self, handle: u32, name: &str) -> Result<f64, MedaqError> {
+        let result = self.inner.get_parameter_double(handle, name);
+        self.record_result("GetParameterDouble", &[name], &result, |value| {
+            vec![format!("{value:?}")]
+        });
+        result
+    }
+
+    fn set_parameter_double(&self, handle: u32, name: &str, value: f64) -> Result<(), MedaqError> {
+        let result = self.inner.set_parameter_double(handle, name, value);
+        let arguments = [name, &format!("{value:?}")];
+        self.record_result("SetParameterDouble", &arguments, &result, |_| vec![]);
+        result
+    }
+
+    fn get_parameter_int(&self, handle: u32, name: &str) -> Result<i32, MedaqError> {
+        let result = self.inner.get_parameter_int(handle, name);
+        self.record_result("GetParameterInt", &[name], &result, |value| {
+            vec![value.to_string()]
+        });
+        result
+    }
+
+    fn exec_scmd(&self, handle: u32, command: &str) -> Result<(), MedaqError> {
+        let result = self.inner.exec_scmd(handle, command);
+        self.record_result("ExecSCmd", &[command], &result, |_| vec![]);
+        result
+    }
+
+    fn get_error(&self, handle: u32) -> String {
+        let text = self.inner.get_error(handle);
+        self.record("GetError", &[], &[&text]);
+        text
+    }
+
+    fn dll_version(&self) -> Option<DllVersion> {
+        self.inner.dll_version()
+    }
+
+    fn data_avail(&self, handle: u32) -> Result<i32, MedaqError> {
+        let result = self.inner.data_avail(handle);
+        self.record_result("DataAvail", &[], &result, |values| vec![values.to_string()]);
+        result
+    }
+
+    fn transfer_data(
+        &self,
+        handle: u32,
+        mut raw: Option<&mut [i32]>,
+        mut scaled: Option<&mut [f64]>,
+        max_values: usize,
+    ) -> Result<usize, MedaqError> {
+        let result = self.inner.transfer_data(
+            handle,
+            raw.as_deref_mut(),
+            scaled.as_deref_mut(),
+            max_values,
+        );
+        let buffers = match (&raw, &scaled) {
+            (Some(_), Some(_)) => "raw,scaled",
+            (Some(_), None) => "raw",
+            (None, Some(_)) => "scaled",
+            (None, None) => "-",
+        };
+        let arguments = [&max_values.to_string(), buffers];
+        self.record_result("TransferData", &arguments, &result, |&values| {
+            vec![
+                values.to_string(),
+                join(raw.as_deref().map(|raw| &raw[..values])),
+                join(scaled.as_deref().map(|scaled| &scaled[..values])),
+            ]
+        });
+        result
+    }
+}
+
+/// Comma separated values, `-` without a buffer
+fn join<T: Debug>(values: Option<&[T]>) -> String {
+    match values {
+        Some(values) => values
+            .iter()
+            .map(|value| format!("{value:?}"))
+            .collect::<Vec<_>>()
+            .join(","),
+        None => "-".into(),
+    }
+}
+
+fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('t') => unescaped.push('\t'),
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some(c) => unescaped.push(c),
+                None => unescaped.push('\\'),
+            },
+            (c, false) => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// Call of a trace
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    function: String,
+    arguments: Vec<String>,
+    result: Vec<String>,
+}
+
+impl Entry {
+    fn parse(line: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("trace line {line:?}"));
+        let mut fields = line.split('\t').skip(1).map(unescape);
+        let function = fields.next().ok_or_else(invalid)?;
+        let mut arguments = vec![];
+        for field in fields.by_ref() {
+            if field == "=" {
+                return Ok(Self {
+                    function,
+                    arguments,
+                    result: fields.collect(),
+                });
+            }
+            arguments.push(field);
+        }
+        Err(invalid())
+    }
+
+    /// `Ok` with the returned values or the recorded error
+    fn result(&self) -> Result<&[String], MedaqError> {
+        match self.result.first().map(String::as_str) {
+            Some("ok") => Ok(&self.result[1..]),
+            Some("err") => {
+                let err_code = self.result.get(1).and_then(|name| names::err_code(name));
+                Err(MedaqError::Sensor(
+                    err_code.unwrap_or(ERR_CODE::ERR_FUNCTION_NOT_SUPPORTED),
+                ))
+            }
+            Some("unsupported") => {
+                let name = self.result.get(1).map(String::as_str);
+                let function = backend::OPTIONAL_FUNCTIONS
+                    .into_iter()
+                    .find(|&function| Some(function) == name)
+                    .ok_or_else(|| self.mismatch("a recorded result"))?;
+                Err(MedaqError::UnsupportedByDll { function })
+            }
+            Some("invalid-utf8") => {
+                let invalid = CString::new([0xff]).expect("no nul").into_string();
+                Err(invalid.expect_err("not UTF-8").into())
+            }
+            _ => Err(self.mismatch("a recorded result")),
+        }
+    }
+
+    fn mismatch(&self, found: &str) -> MedaqError {
+        MedaqError::TraceMismatch {
+            expected: self.to_string(),
+            found: found.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.function, self.arguments.join(", "))
+    }
+}
+
+/// [`Backend`] answering with the results of a trace written by a [`TraceBackend`]
+///
+/// Calls are answered in the order of the trace, regardless of what the DLL would
+/// answer now. A call differing from the next one of the trace in its function
+/// or arguments fails with [`MedaqError::TraceMismatch`], as does every call after
+/// the end of the trace.
+///
+/// # Example
+/// ```no_run
+/// use medaqlib::{Interface, ReplayTrace, SensorBuilder, ME_SENSOR};
+///
+/// let trace = ReplayTrace::from_file("sensor.trace").unwrap();
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::TcpIp)
+///     .with_ip_address("169.254.168.150")
+///     .with_backend(trace)
+///     .connect()
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ReplayTrace {
+    entries: Mutex<std::vec::IntoIter<Entry>>,
+}
+
+impl ReplayTrace {
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Read a trace from its text
+    pub fn parse(trace: &str) -> io::Result<Self> {
+        let mut lines = trace.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a medaqlib trace",
+            ));
+        }
+        let entries = lines
+            .filter(|line| !line.is_empty())
+            .map(Entry::parse)
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Self {
+            entries: Mutex::new(entries.into_iter()),
+        })
+    }
+
+    /// Number of calls of the trace not replayed yet
+    pub fn remaining(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, std::vec::IntoIter<Entry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Next entry of the trace if it is the given call, a call not matching the
+    /// trace leaves it untouched
+    fn next(&self, function: &str, arguments: &[&str]) -> Result<Entry, MedaqError> {
+        let found = format!("{function}({})", arguments.join(", "));
+        let mut entries = self.lock();
+        let entry = entries
+            .as_slice()
+            .first()
+            .ok_or_else(|| MedaqError::TraceMismatch {
+                expected: "end of trace".into(),
+                found: found.clone(),
+            })?;
+        if entry.function != function || entry.arguments != arguments {
+            return Err(entry.mismatch(&found));
+        }
+        Ok(entries.next().expect("entry was peeked"))
+    }
+
+    /// Recorded result of a call returning a value
+    fn value<T>(
+        &self,
+        function: &str,
+        arguments: &[&str],
+        parse: impl FnOnce(&[String]) -> Option<T>,
+    ) -> Result<T, MedaqError> {
+        let entry = self.next(function, arguments)?;
+        let values = entry.result()?;
+        parse(values).ok_or_else(|| entry.mismatch("an unreadable result"))
+    }
+
+    /// Recorded result of `CloseSensor` or `ReleaseInstance`, traces of earlier
+    /// versions have none
+    fn released(&self, function: &str) -> Result<(), MedaqError> {
+        let entry = self.next(function, &[])?;
+        match entry.result.is_empty() {
+            true => Ok(()),
+            false => entry.result().map(|_| ()),
+        }
+    }
+}
+
+fn parse_values<T: std::str::FromStr>(field: &str) -> Option<Vec<T>> {
+    match field {
+        "-" => Some(vec![]),
+        "" => Some(vec![]),
+        field => field.split(',').map(|value| value.parse().ok()).collect(),
+    }
+}
+
+impl backend::sealed::Sealed for ReplayTrace {}
+
+impl Backend for ReplayTrace {
+    fn create_instance(&self, sensor_type: ME_SENSOR) -> u32 {
+        let sensor_type = format!("{sensor_type:?}");
+        self.next("CreateInstance", &[&sensor_type])
+            .ok()
+            .and_then(|entry| entry.result.first()?.parse().ok())
+            .unwrap_or(0)
+    }
+
+    fn release_instance(&self, _: u32) -> Result<(), MedaqError> {
+        self.released("ReleaseInstance")
+    }
+
+    fn open_sensor(&self, _: u32) -> Result<(), MedaqError> {
+        self.value("OpenSensor", &[], |_| Some(()))
+    }
+
+    fn close_sensor(&self, _: u32) -> Result<(), MedaqError> {
+        self.released("CloseSensor")
+    }
+
+    fn set_parameter_string(&self, _: u32, name: &str, value: &str) -> Result<(), MedaqError> {
+        self.value("SetParameterString", &[name, value], |_| Some(()))
+    }
+
+    fn set_parameter_int(&self, _: u32, name: &str, value: i32) -> Result<(), MedaqError> {
+        self.value("SetParameterInt", &[name, &value.to_string()], |_| Some(()))
+    }
+
+    fn get_parameter_string(&self, _: u32, name: &str) -> Result<String, MedaqError> {
+        self.value("GetParameterString", &[name], |values| {
+            values.first().cloned()
+        })
+    }
+
+    fn get_parameter_double(&self, _: u32, name: &str) -> Result<f64, MedaqError> {
+        self.value("GetParameterDouble", &[name], |values| {
+            values.first()?.parse().ok()
+        })
+    }
+
+    fn set_parameter_double(&self, _: u32, name: &str, value: f64) -> Result<(), MedaqError> {
+        let arguments = [name, &format!("{value:?}")];
+        self.value("SetParameterDouble", &arguments, |_| Some(()))
+    }
+
+    fn get_parameter_int(&self, _: u32, name: &str) -> Result<i32, MedaqError> {
+        self.value("GetParameterInt", &[name], |values| {
+            values.first()?.parse().ok()
+        })
+    }
+
+    fn exec_scmd(&self, _: u32, command: &str) -> Result<(), MedaqError> {
+        self.value("ExecSCmd", &[command], |_| Some(()))
+    }
+
+    fn get_error(&self, _: u32) -> String {
+        self.next("GetError", &[])
+            .ok()
+            .and_then(|entry| entry.result.first().cloned())
+            .unwrap_or_default()
+    }
+
+    fn data_avail(&self, _: u32) -> Result<i32, MedaqError> {
+        self.value("DataAvail", &[], |values| values.first()?.parse().ok())
+    }
+
+    fn transfer_data(
+        &self,
+        _: u32,
+        raw: Option<&mut [i32]>,
+        scaled: Option<&mut [f64]>,
+        max_values: usize,
+    ) -> Result<usize, MedaqError> {
+        let buffers = match (&raw, &scaled) {
+            (Some(_), Some(_)) => "raw,scaled",
+            (Some(_), None) => "raw",
+            (None, Some(_)) => "scaled",
+            (None, None) => "-",
+        };
+        let (values, raw_values, scaled_values) = self.value(
+            "TransferData",
+            &[&max_values.to_string(), buffers],
+            |values| match values {
+                [count, raw, scaled] => Some((
+                    count.parse::<usize>().ok()?,
+                    parse_values::<i32>(raw)?,
+                    parse_values::<f64>(scaled)?,
+                )),
+                _ => None,
+            },
+        )?;
+        if let Some(raw) = raw {
+            raw.iter_mut()
+                .zip(raw_values)
+                .for_each(|(raw, value)| *raw = value);
+        }
+        if let Some(scaled) = scaled {
+            scaled
+                .iter_mut()
+                .zip(scaled_values)
+                .for_each(|(scaled, value)| *scaled = value);
+        }
+        Ok(values.min(max_values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        process,
+        sync::{Arc, Mutex},
+    };
+
+    use super::{escape, unescape, ReplayTrace, TraceBackend};
+    use crate::{
+        Call, Interface, MedaqError, MockBackend, Sensor, SensorBuilder, ERR_CODE, ME_SENSOR,
+        SCALED_OUT_OF_RANGE,
+    };
+
+    /// Writer appending to a shared buffer
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn builder() -> SensorBuilder {
+        SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+            .with_interface(Interface::TcpIp)
+            .with_ip_address("10.10.10.10")
+    }
+
+    /// Session on a mock with a failed read, returns the trace
+    fn record() -> String {
+        let mock = MockBackend::new()
+            .with_channels(["Distance", "Intensity\twith tab"])
+            .with_parameter_string("IA_Scaled_Unit1", "mm");
+        let trace = Shared::default();
+        let sensor = builder()
+            .with_backend(TraceBackend::new(trace.clone(), mock.clone()))
+            .connect()
+            .unwrap();
+
+        mock.push_frame(&[1, -1], &[1.5, SCALED_OUT_OF_RANGE]);
+        assert_eq!(sensor.read_data().unwrap().unwrap().raw_data, vec![1, -1]);
+        mock.fail_call(Call::DataAvail, 2, ERR_CODE::ERR_READING_SENSOR_DATA);
+        assert!(sensor.read_data().is_err());
+        drop(sensor);
+
+        let trace = trace.0.lock().unwrap().clone();
+        String::from_utf8(trace).unwrap()
+    }
+
+    #[test]
+    fn test_trace_backend_test() {
+        let trace = record();
+        let lines: Vec<_> = trace.lines().collect();
+        assert_eq!(lines[0], "# medaqlib trace 1");
+        assert!(lines[1].ends_with("\tCreateInstance\tSENSOR_IFD2421\t=\t1"));
+        assert!(
+            trace.contains("\tGetParameterString\tIA_Scaled_Name2\t=\tok\tIntensity\\twith tab\n")
+        );
+        assert!(trace.contains(
+            "\tTransferData\t2\traw,scaled\t=\tok\t2\t1,-1\t1.5,-1.7976931348623157e308\n"
+        ));
+        assert!(trace.contains("\tDataAvail\t=\terr\tERR_READING_SENSOR_DATA\n"));
+        assert!(lines[lines.len() - 1].ends_with("\tReleaseInstance\t=\tok"));
+    }
+
+    #[test]
+    fn test_replay_trace_test() {
+        let replay = ReplayTrace::parse(&record()).unwrap();
+        let sensor = builder().with_backend(replay).connect().unwrap();
+        assert_eq!(sensor.parameters(), ["Distance", "Intensity\twith tab"]);
+        assert_eq!(sensor.channel_info()[0].unit.as_deref(), Some("mm"));
+
+        let data = sensor.read_data().unwrap().unwrap();
+        assert_eq!(data.raw_data, vec![1, -1]);
+        assert_eq!(data.scaled_data, vec![1.5, SCALED_OUT_OF_RANGE]);
+        assert!(matches!(
+            sensor.read_data(),
+            Err(MedaqError::Sensor(ERR_CODE::ERR_READING_SENSOR_DATA))
+        ));
+        // the trace ends after the failed read
+        assert!(matches!(
+            sensor.read_data(),
+            Err(MedaqError::TraceMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_replay_trace_mismatch_test() {
+        let replay = ReplayTrace::parse(&record()).unwrap();
+        let err = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+            .with_interface(Interface::TcpIp)
+            .with_ip_address("10.0.0.1")
+            .with_backend(replay)
+            .connect()
+            .unwrap_err();
+        match err {
+            MedaqError::TraceMismatch { expected, found } => {
+                assert_eq!(expected, "SetParameterString(IP_RemoteAddr, 10.10.10.10)");
+                assert_eq!(found, "SetParameterString(IP_RemoteAddr, 10.0.0.1)");
+            }
+            err => panic!("unexpected error {err}"),
+        }
+        assert!(ReplayTrace::parse("Distance\n1.5\n").is_err());
+    }
+
+    #[test]
+    fn test_with_trace_test() {
+        let path = std::env::temp_dir().join(format!("medaqlib-trace-{}.trace", process::id()));
+        let mock = MockBackend::new().with_channels(["Distance"]);
+        let sensor: Sensor = builder()
+            .with_backend(mock)
+            .with_trace(&path)
+            .connect()
+            .unwrap();
+        drop(sensor);
+
+        let replay = ReplayTrace::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let sensor = builder().with_backend(replay).connect().unwrap();
+        assert_eq!(sensor.parameters(), ["Distance"]);
+    }
+
+    #[test]
+    fn test_escape_test() {
+        for field in ["", "plain", "tab\there", "line\nbreak\r", "back\\slash\\t"] {
+            assert_eq!(unescape(&escape(field)), field);
+            assert!(!escape(field).contains(['\t', '\n']));
+        }
+    }
+
+    #[test]
+    fn test_trace_optional_functions_test() {
+        let mock = MockBackend::new()
+            .with_channels(["Distance"])
+            .with_parameter_int("SA_Count", 3)
+            .without_function(Call::SetParameterDouble);
+        let trace = Shared::default();
+        let sensor = builder()
+            .with_backend(TraceBackend::new(trace.clone(), mock))
+            .connect()
+            .unwrap();
+        assert_eq!(sensor.get_parameter_int("SA_Count").unwrap(), 3);
+        assert!(sensor.set_parameter_double("SP_Offset", 0.5).is_err());
+        drop(sensor);
+        let trace = String::from_utf8(trace.0.lock().unwrap().clone()).unwrap();
+        assert!(trace.contains("\tGetParameterInt\tSA_Count\t=\tok\t3\n"));
+        assert!(trace.contains(
+            "\tSetParameterDouble\tSP_Offset\t0.5\t=\tunsupported\tSetParameterDouble\n"
+        ));
+
+        let replay = ReplayTrace::parse(&trace).unwrap();
+        let sensor = builder().with_backend(replay).connect().unwrap();
+        assert_eq!(sensor.get_parameter_int("SA_Count").unwrap(), 3);
+        assert!(matches!(
+            sensor.set_parameter_double("SP_Offset", 0.5),
+            Err(MedaqError::UnsupportedByDll {
+                function: "SetParameterDouble"
+            })
+        ));
+    }
+}
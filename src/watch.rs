@@ -0,0 +1,256 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{
+    acquisition::{Acquire, Acquisition, Control, Deliver},
+    stats::Stats,
+    sync::Shared,
+    AcquisitionConfig, AcquisitionStats, ChannelValueOwned, Data, MedaqError, Sensor,
+};
+
+/// Last frame received by a [`Watch`]
+#[derive(Debug, Clone)]
+struct Latest {
+    timestamp: SystemTime,
+    received: Instant,
+    values: Vec<ChannelValueOwned<f64>>,
+}
+
+/// Last frame and the number of samples received by a [`Watch`]
+#[derive(Debug, Default)]
+struct Received {
+    latest: Option<Latest>,
+    samples: u64,
+    invalid_samples: u64,
+}
+
+type Slot = Shared<Received>;
+
+impl Deliver for Slot {
+    fn deliver(&mut self, result: Result<Data, MedaqError>, _: &Control) -> bool {
+        // read errors leave the last value in place, staleness reveals them
+        if let Ok(data) = result {
+            if let Some(frame) = data.iter_values().next_back() {
+                let latest = Latest {
+                    timestamp: data.timestamp().unwrap_or_else(SystemTime::now),
+                    received: Instant::now(),
+                    values: frame.map(|value| value.into_owned()).collect(),
+                };
+                let invalid = data.summary().invalid_samples;
+                let mut received = self.lock();
+                received.latest = Some(latest);
+                received.samples += data.scaled_data.len() as u64;
+                received.invalid_samples += invalid as u64;
+            }
+        }
+        true
+    }
+}
+
+impl Sensor {
+    /// Move the sensor to a background acquisition only keeping the most recent frame
+    ///
+    /// Unlike [`Sensor::start_acquisition`] nothing is queued, every capture
+    /// replaces the last frame of the previous one. Read errors are not reported,
+    /// use [`Watch::is_stale`] to detect that no new frames arrive.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use medaqlib::{AcquisitionConfig, Interface, SensorBuilder, ME_SENSOR};
+    ///
+    /// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+    ///     .with_interface(Interface::TcpIp)
+    ///     .with_ip_address("10.10.10.10")
+    ///     .connect()
+    ///     .unwrap();
+    ///
+    /// let watch = sensor.watch_latest(AcquisitionConfig::default());
+    /// loop {
+    ///     std::thread::sleep(Duration::from_millis(500));
+    ///     if watch.is_stale(Duration::from_secs(2)) {
+    ///         println!("no data");
+    ///     } else if let Some((_, values)) = watch.latest() {
+    ///         println!("{values:?}");
+    ///     }
+    /// }
+    /// ```
+    pub fn watch_latest(self, config: AcquisitionConfig) -> Watch {
+        let stats = self.stats.clone();
+        let watch = WatchInner::start(self, config);
+        Watch {
+            inner: watch,
+            stats,
+        }
+    }
+}
+
+/// Most recent frame of a background acquisition, see [`Sensor::watch_latest`]
+///
+/// Dropping the watch stops the acquisition and releases the sensor.
+pub struct Watch {
+    inner: WatchInner<Sensor>,
+    stats: Stats,
+}
+
+impl Watch {
+    /// Timestamp of the capture and values of its last frame, `None` before the
+    /// first frame arrived
+    pub fn latest(&self) -> Option<(SystemTime, Vec<ChannelValueOwned<f64>>)> {
+        self.inner.latest()
+    }
+
+    /// Time since the last frame arrived, `None` before the first frame
+    pub fn latest_age(&self) -> Option<Duration> {
+        self.inner.latest_age()
+    }
+
+    /// `true` if no frame arrived within `deadline`, including before the first frame
+    pub fn is_stale(&self, deadline: Duration) -> bool {
+        self.inner.is_stale(deadline)
+    }
+
+    /// Share of the out of range samples of all frames received, `0` before the
+    /// first frame
+    pub fn invalid_ratio(&self) -> f64 {
+        self.inner.invalid_ratio()
+    }
+
+    /// Diagnostics of the reads of the watched sensor, see [`Sensor::stats`]
+    pub fn stats(&self) -> AcquisitionStats {
+        self.stats.get()
+    }
+
+    /// Stop the acquisition and get the sensor back
+    pub fn join(mut self) -> Sensor {
+        self.inner.acquisition.join()
+    }
+}
+
+struct WatchInner<S: Acquire + 'static> {
+    acquisition: Acquisition<S>,
+    slot: Slot,
+}
+
+impl<S: Acquire + 'static> WatchInner<S> {
+    fn start(source: S, config: AcquisitionConfig) -> Self {
+        let slot = Slot::default();
+        let acquisition = Acquisition::start(source, config, slot.clone());
+        Self { acquisition, slot }
+    }
+
+    fn get(&self) -> Option<Latest> {
+        self.slot.lock().latest.clone()
+    }
+
+    fn latest(&self) -> Option<(SystemTime, Vec<ChannelValueOwned<f64>>)> {
+        self.get().map(|latest| (latest.timestamp, latest.values))
+    }
+
+    fn latest_age(&self) -> Option<Duration> {
+        let slot = self.slot.lock();
+        slot.latest.as_ref().map(|latest| latest.received.elapsed())
+    }
+
+    fn invalid_ratio(&self) -> f64 {
+        let slot = self.slot.lock();
+        match slot.samples {
+            0 => 0.,
+            samples => slot.invalid_samples as f64 / samples as f64,
+        }
+    }
+
+    fn is_stale(&self, deadline: Duration) -> bool {
+        self.latest_age().is_none_or(|age| age > deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant, UNIX_EPOCH};
+
+    use super::WatchInner;
+    use crate::{
+        acquisition::tests::Script, AcquisitionConfig, ChannelInfo, Data, MedaqError, Value,
+        SCALED_OUT_OF_RANGE,
+    };
+
+    fn config() -> AcquisitionConfig {
+        AcquisitionConfig {
+            poll_interval: Duration::from_millis(1),
+            ..Default::default()
+        }
+    }
+
+    fn capture(values: Vec<f64>, second: u64) -> Data {
+        Data::new(
+            vec!["Distance".to_string(), "Intensity".to_string()],
+            vec![],
+            values,
+        )
+        .with_channel_info(vec![
+            ChannelInfo::new("Distance").with_unit("mm"),
+            ChannelInfo::new("Intensity"),
+        ])
+        .with_timestamp(UNIX_EPOCH + Duration::from_secs(second))
+    }
+
+    fn wait_for<S: crate::acquisition::Acquire>(watch: &WatchInner<S>, second: u64) {
+        let start = Instant::now();
+        while watch.latest().map(|(ts, _)| ts) != Some(UNIX_EPOCH + Duration::from_secs(second)) {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "no frame from second {second}"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_watch_keeps_last_frame_test() {
+        let script = Script::new(vec![
+            Ok(Some(capture(vec![1., 10., 2., 20.], 1))),
+            Err(MedaqError::NoChannels),
+            Ok(Some(capture(vec![3., 30., 4., 40.], 2))),
+        ]);
+        let mut watch = WatchInner::start(script, config());
+        wait_for(&watch, 2);
+
+        let (_, values) = watch.latest().unwrap();
+        assert_eq!(values[0].channel, "Distance");
+        assert_eq!(values[0].value, Value::Valid(4.));
+        assert_eq!(values[0].unit.as_deref(), Some("mm"));
+        assert_eq!(values[1].value, Value::Valid(40.));
+        assert_eq!(values[0].to_string(), "Distance: 4 mm");
+        assert_eq!(watch.invalid_ratio(), 0.);
+
+        assert!(watch.acquisition.join().results.is_empty());
+    }
+
+    #[test]
+    fn test_watch_invalid_ratio_test() {
+        let script = Script::new(vec![
+            Ok(Some(capture(vec![1., SCALED_OUT_OF_RANGE, 2., 20.], 1))),
+            Ok(Some(capture(vec![SCALED_OUT_OF_RANGE, 30.], 2))),
+        ]);
+        let watch = WatchInner::start(script, config());
+        wait_for(&watch, 2);
+        assert_eq!(watch.invalid_ratio(), 2. / 6.);
+    }
+
+    #[test]
+    fn test_watch_staleness_test() {
+        let script = Script::new(vec![]);
+        let watch = WatchInner::start(script, config());
+        assert!(watch.latest().is_none());
+        assert!(watch.latest_age().is_none());
+        assert!(watch.is_stale(Duration::from_secs(60)));
+
+        let script = Script::new(vec![Ok(Some(capture(vec![1., 10.], 1)))]);
+        let watch = WatchInner::start(script, config());
+        wait_for(&watch, 1);
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(watch.latest_age().unwrap() >= Duration::from_millis(20));
+        assert!(watch.is_stale(Duration::from_millis(10)));
+        assert!(!watch.is_stale(Duration::from_secs(60)));
+    }
+}
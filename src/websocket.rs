@@ -0,0 +1,342 @@
+use std::{
+    io,
+    net::{SocketAddr, TcpListener as StdListener, ToSocketAddrs},
+    sync::{mpsc::RecvTimeoutError, Arc},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    runtime,
+    sync::{mpsc, watch},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{sync::Shared, AcquisitionHandle, ChannelInfo, Data, JsonlOptions};
+
+/// Interval the forwarding thread checks for the stop request
+const STOP_CHECK: Duration = Duration::from_millis(50);
+
+/// Options of a [`WebsocketStreamer`]
+#[derive(Debug, Clone)]
+pub struct WebsocketConfig {
+    /// Messages buffered per browser, newer frames are dropped while it is full
+    pub client_queue: usize,
+    /// How the frames are written, see [`Data::write_jsonl`]
+    pub jsonl: JsonlOptions,
+}
+
+impl Default for WebsocketConfig {
+    fn default() -> Self {
+        Self {
+            client_queue: 256,
+            jsonl: JsonlOptions::default(),
+        }
+    }
+}
+
+/// Browser connected to a [`WebsocketStreamer`]
+struct Client {
+    sender: mpsc::Sender<Arc<str>>,
+    /// Shortest time between frames, negotiated by the client
+    min_interval: Shared<Option<Duration>>,
+    last_sent: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Hub {
+    /// `{"metadata":{"channels":[...]}}` of the latest capture
+    metadata: Option<Arc<str>>,
+    clients: Vec<Client>,
+    dropped: u64,
+}
+
+/// Serves the frames of an acquisition to browsers over WebSocket, a JSON object
+/// per message
+///
+/// A client first receives `{"metadata":{"channels":[...]}}` with the
+/// [`ChannelInfo`](crate::ChannelInfo) of every channel, again whenever the
+/// channels change, and then the frames as written by [`Data::write_jsonl`]. By
+/// sending `{"max_rate_hz": 10}` a client limits its frames to the newest one
+/// every 100 ms, `{"max_rate_hz": null}` lifts the limit. Frames that do not fit
+/// into the queue of a slow client are dropped, see [`dropped`](Self::dropped),
+/// and read errors are not sent.
+///
+/// # Example
+/// ```no_run
+/// use medaqlib::{
+///     AcquisitionConfig, Interface, SensorBuilder, WebsocketConfig, WebsocketStreamer, ME_SENSOR,
+/// };
+///
+/// let sensor = SensorBuilder::new(ME_SENSOR::SENSOR_IFD2421)
+///     .with_interface(Interface::TcpIp)
+///     .with_ip_address("10.10.10.10")
+///     .connect()
+///     .unwrap();
+///
+/// let acquisition = sensor.start_acquisition(AcquisitionConfig::default());
+/// let streamer =
+///     WebsocketStreamer::start("0.0.0.0:8080", acquisition, WebsocketConfig::default()).unwrap();
+/// std::thread::sleep(std::time::Duration::from_secs(600));
+/// let sensor = streamer.stop().join();
+/// ```
+pub struct WebsocketStreamer {
+    local_addr: SocketAddr,
+    hub: Shared<Hub>,
+    stop: watch::Sender<bool>,
+    forwarder: JoinHandle<AcquisitionHandle>,
+    server: JoinHandle<()>,
+}
+
+impl WebsocketStreamer {
+    /// Listen on `addr`, e.g. `0.0.0.0:8080` or `127.0.0.1:0` for any free port, and
+    /// forward the captures received from `acquisition`
+    pub fn start(
+        addr: impl ToSocketAddrs,
+        acquisition: AcquisitionHandle,
+        config: WebsocketConfig,
+    ) -> io::Result<Self> {
+        let listener = StdListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let runtime = runtime::Builder::new_current_thread().enable_io().build()?;
+        let hub = Shared::<Hub>::default();
+        let (stop, stopped) = watch::channel(false);
+
+        let server = {
+            let (hub, stopped) = (hub.clone(), stopped.clone());
+            let capacity = config.client_queue.max(1);
+            thread::Builder::new()
+                .name("medaq-ws".into())
+                .spawn(move || runtime.block_on(serve(listener, hub, capacity, stopped)))?
+        };
+        let forwarder = {
+            let hub = hub.clone();
+            thread::Builder::new()
+                .name("medaq-ws-forward".into())
+                .spawn(move || forward(acquisition, &hub, &config.jsonl, &stopped))?
+        };
+        Ok(Self {
+            local_addr,
+            hub,
+            stop,
+            forwarder,
+            server,
+        })
+    }
+
+    /// Address the browsers connect to, with the port chosen for port `0`
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Number of connected browsers
+    pub fn clients(&self) -> usize {
+        let mut hub = self.hub.lock();
+        hub.clients.retain(|client| !client.sender.is_closed());
+        hub.clients.len()
+    }
+
+    /// Frames not sent because the queue of a client was full
+    pub fn dropped(&self) -> u64 {
+        self.hub.lock().dropped
+    }
+
+    /// Close the connections and give back the acquisition
+    pub fn stop(self) -> AcquisitionHandle {
+        let _ = self.stop.send(true);
+        let _ = self.server.join();
+        self.forwarder
+            .join()
+            .expect("the forwarding thread does not panic")
+    }
+}
+
+/// Turn the captures into messages for the clients until stopped
+fn forward(
+    acquisition: AcquisitionHandle,
+    hub: &Shared<Hub>,
+    options: &JsonlOptions,
+    stopped: &watch::Receiver<bool>,
+) -> AcquisitionHandle {
+    let mut channels: Vec<String> = vec![];
+    while !*stopped.borrow() {
+        let data = match acquisition.receiver().recv_timeout(STOP_CHECK) {
+            Ok(Ok(data)) => data,
+            Ok(Err(_)) | Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        if data.channel_count() == 0 {
+            continue;
+        }
+        let mut hub = hub.lock();
+        if data.channels() != channels {
+            channels = data.channels().to_vec();
+            let metadata = metadata(&data);
+            for client in &hub.clients {
+                let _ = client.sender.try_send(Arc::clone(&metadata));
+            }
+            hub.metadata = Some(metadata);
+        }
+        let mut jsonl = vec![];
+        data.write_jsonl(&mut jsonl, options)
+            .expect("writing to a Vec never fails");
+        let jsonl = String::from_utf8(jsonl).expect("JSON is UTF-8");
+        let frames: Vec<Arc<str>> = jsonl.lines().map(Arc::from).collect();
+        if frames.is_empty() {
+            continue;
+        }
+
+        let now = Instant::now();
+        let mut dropped = 0;
+        hub.clients.retain(|client| !client.sender.is_closed());
+        for client in &mut hub.clients {
+            let min_interval = *client.min_interval.lock();
+            let sent: &[Arc<str>] = match (min_interval, client.last_sent) {
+                (None, _) => &frames,
+                (Some(interval), Some(last)) if now.duration_since(last) < interval => &[],
+                // the newest frame only
+                (Some(_), _) => &frames[frames.len() - 1..],
+            };
+            for frame in sent {
+                if client.sender.try_send(Arc::clone(frame)).is_err() {
+                    dropped += 1;
+                }
+            }
+            if !sent.is_empty() {
+                client.last_sent = Some(now);
+            }
+        }
+        hub.dropped += dropped;
+    }
+    acquisition
+}
+
+fn metadata(data: &Data) -> Arc<str> {
+    let channels = match data.channel_info() {
+        Some(info) => serde_json::to_value(info),
+        None => serde_json::to_value(
+            data.channels()
+                .iter()
+                .map(ChannelInfo::new)
+                .collect::<Vec<_>>(),
+        ),
+    }
+    .expect("channel info serializes");
+    Arc::from(serde_json::json!({"metadata": {"channels": channels}}).to_string())
+}
+
+async fn serve(
+    listener: StdListener,
+    hub: Shared<Hub>,
+    capacity: usize,
+    mut stopped: watch::Receiver<bool>,
+) {
+    let Ok(listener) = TcpListener::from_std(listener) else {
+        return;
+    };
+    loop {
+        tokio::select! {
+            _ = stopped.changed() => break,
+            accepted = listener.accept() => {
+                if let Ok((stream, _)) = accepted {
+                    let client = connect(stream, hub.clone(), capacity, stopped.clone());
+                    tokio::spawn(client);
+                }
+            }
+        }
+    }
+}
+
+/// Handshake and then send the messages queued for the client
+async fn connect(
+    stream: TcpStream,
+    hub: Shared<Hub>,
+    capacity: usize,
+    mut stopped: watch::Receiver<bool>,
+) {
+    let Ok(socket) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    let (mut outgoing, mut incoming) = socket.split();
+    let (sender, mut queue) = mpsc::channel(capacity);
+    let min_interval = Shared::default();
+    {
+        let mut hub = hub.lock();
+        if let Some(metadata) = &hub.metadata {
+            let _ = sender.try_send(Arc::clone(metadata));
+        }
+        hub.clients.push(Client {
+            sender,
+            min_interval: min_interval.clone(),
+            last_sent: None,
+        });
+    }
+
+    loop {
+        tokio::select! {
+            _ = stopped.changed() => break,
+            message = queue.recv() => {
+                let Some(message) = message else { break };
+                if outgoing.send(Message::text(&*message)).await.is_err() {
+                    break;
+                }
+            }
+            request = incoming.next() => match request {
+                Some(Ok(Message::Text(text))) => {
+                    if let Some(rate) = max_rate(&text) {
+                        *min_interval.lock() = rate;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    let _ = outgoing.close().await;
+}
+
+/// Shortest interval between frames of `{"max_rate_hz": 10}`, `Some(None)` for no
+/// limit, `None` if the request is not understood
+fn max_rate(request: &str) -> Option<Option<Duration>> {
+    let request: serde_json::Value = serde_json::from_str(request).ok()?;
+    match request.get("max_rate_hz")? {
+        serde_json::Value::Null => Some(None),
+        rate => {
+            let rate = rate.as_f64().filter(|rate| *rate > 0.)?;
+            Some(Duration::try_from_secs_f64(1. / rate).ok())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{max_rate, metadata};
+    use crate::{ChannelInfo, Data};
+
+    #[test]
+    fn test_ws_max_rate_test() {
+        assert_eq!(
+            max_rate("{\"max_rate_hz\": 10}"),
+            Some(Some(Duration::from_millis(100)))
+        );
+        assert_eq!(max_rate("{\"max_rate_hz\": null}"), Some(None));
+        assert_eq!(max_rate("{\"max_rate_hz\": 0}"), None);
+        assert_eq!(max_rate("{\"other\": 1}"), None);
+        assert_eq!(max_rate("max_rate_hz"), None);
+    }
+
+    #[test]
+    fn test_ws_metadata_test() {
+        let data = Data::new(vec!["Distance".to_string()], vec![], vec![1.])
+            .with_channel_info(vec![ChannelInfo::new("Distance").with_unit("mm")]);
+        assert_eq!(
+            &*metadata(&data),
+            "{\"metadata\":{\"channels\":[{\"name\":\"Distance\",\"unit\":\"mm\"}]}}"
+        );
+    }
+}
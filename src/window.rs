@@ -0,0 +1,285 @@
+use crate::{ChannelValue, Data, Value, SCALED_OUT_OF_RANGE};
+
+/// How [`Data::windows`] treats the first frames of a capture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowEdges {
+    /// Start with shorter windows, so there is one window per frame
+    Partial,
+    /// Only yield full windows of `k` frames
+    Drop,
+}
+
+/// Window of consecutive frames of a capture
+#[derive(Debug, Clone, Copy)]
+pub struct FrameWindow<'a> {
+    data: &'a Data,
+    start: usize,
+    len: usize,
+}
+
+impl<'a> FrameWindow<'a> {
+    /// Index of the first frame in the window
+    pub fn start_frame(&self) -> usize {
+        self.start
+    }
+
+    /// Number of frames in the window
+    pub fn frame_count(&self) -> usize {
+        self.len
+    }
+
+    /// Mean of the valid scaled values per channel
+    pub fn means(&self) -> Vec<ChannelValue<'a, f64>> {
+        self.aggregate(|values| {
+            let (sum, count) = values.fold((0., 0), |(sum, count), v| (sum + v, count + 1));
+            (count > 0).then(|| sum / count as f64)
+        })
+    }
+
+    /// Minimum of the valid scaled values per channel
+    pub fn min(&self) -> Vec<ChannelValue<'a, f64>> {
+        self.aggregate(|values| values.reduce(f64::min))
+    }
+
+    /// Maximum of the valid scaled values per channel
+    pub fn max(&self) -> Vec<ChannelValue<'a, f64>> {
+        self.aggregate(|values| values.reduce(f64::max))
+    }
+
+    fn aggregate(
+        &self,
+        f: impl Fn(&mut dyn Iterator<Item = f64>) -> Option<f64>,
+    ) -> Vec<ChannelValue<'a, f64>> {
+        let channels = self.data.channels();
+        let frames = &self.data.scaled_data
+            [self.start * channels.len()..(self.start + self.len) * channels.len()];
+
+        channels
+            .iter()
+            .enumerate()
+            .map(|(index, channel)| {
+                let mut values = frames
+                    .iter()
+                    .skip(index)
+                    .step_by(channels.len())
+                    .filter_map(|&v| Value::from_sample(v).into_raw());
+                let value = f(&mut values).map_or(Value::OutOfRange, Value::Valid);
+                ChannelValue {
+                    channel,
+                    value,
+                    unit: self.data.unit(index),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Iterator over overlapping windows of frames, see [`Data::windows`]
+#[derive(Debug, Clone)]
+pub struct FrameWindows<'a> {
+    data: &'a Data,
+    k: usize,
+    edges: WindowEdges,
+    next: usize,
+    end: usize,
+}
+
+impl<'a> Iterator for FrameWindows<'a> {
+    type Item = FrameWindow<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+        let index = self.next;
+        self.next += 1;
+
+        let (start, len) = match self.edges {
+            WindowEdges::Partial => {
+                let start = (index + 1).saturating_sub(self.k);
+                (start, index + 1 - start)
+            }
+            WindowEdges::Drop => (index, self.k),
+        };
+        Some(FrameWindow {
+            data: self.data,
+            start,
+            len,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.end - self.next;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for FrameWindows<'_> {}
+
+impl Data {
+    /// Overlapping windows of `k` frames over the scaled values, advancing one frame at a time
+    ///
+    /// # Panics
+    /// Panics if `k` is 0.
+    pub fn windows(&self, k: usize, edges: WindowEdges) -> FrameWindows<'_> {
+        assert!(k != 0, "window size must be non-zero");
+        let frames = self.frames_in_buffer();
+        let end = match edges {
+            WindowEdges::Partial => frames,
+            WindowEdges::Drop => (frames + 1).saturating_sub(k),
+        };
+        FrameWindows {
+            data: self,
+            k,
+            edges,
+            next: 0,
+            end,
+        }
+    }
+
+    /// Smooth the scaled values with a moving average over the last `k` frames
+    ///
+    /// Each frame of the returned capture holds the means of one window, windows
+    /// without valid values for a channel are out of range. The raw values are
+    /// not carried over.
+    ///
+    /// # Panics
+    /// Panics if `k` is 0.
+    pub fn moving_average(&self, k: usize, edges: WindowEdges) -> Data {
+        let scaled_data = self
+            .windows(k, edges)
+            .flat_map(|window| window.means())
+            .map(|mean| mean.value.into_raw().unwrap_or(SCALED_OUT_OF_RANGE))
+            .collect();
+        self.with_values(vec![], scaled_data)
+    }
+
+    fn frames_in_buffer(&self) -> usize {
+        match self.channels().len() {
+            0 => 0,
+            channels => self.scaled_data.len() / channels,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChannelValue, Data, DataBuilder, Value, WindowEdges, SCALED_OUT_OF_RANGE};
+
+    fn data() -> Data {
+        DataBuilder::channels(["1", "2"])
+            .frame_invalid([Value::Valid(1.), Value::OutOfRange])
+            .frame_invalid([Value::Valid(2.), Value::OutOfRange])
+            .frame_invalid([Value::Valid(6.), Value::OutOfRange])
+            .frame([3., 4.])
+            .frame([8., 8.])
+            .build()
+    }
+
+    /// Naive moving average over the last `k` frames of one channel
+    fn reference_mean(
+        values: &[f64],
+        channels: usize,
+        channel: usize,
+        end: usize,
+        k: usize,
+    ) -> Value<f64> {
+        let start = (end + 1).saturating_sub(k);
+        let valid: Vec<f64> = (start..=end)
+            .map(|frame| values[frame * channels + channel])
+            .filter(|&v| v >= 0.)
+            .collect();
+        if valid.is_empty() {
+            Value::OutOfRange
+        } else {
+            Value::Valid(valid.iter().sum::<f64>() / valid.len() as f64)
+        }
+    }
+
+    #[test]
+    fn test_windows_partial_edges_test() {
+        let data = data();
+        let windows: Vec<_> = data.windows(3, WindowEdges::Partial).collect();
+        assert_eq!(windows.len(), 5);
+        assert_eq!(
+            windows
+                .iter()
+                .map(|w| (w.start_frame(), w.frame_count()))
+                .collect::<Vec<_>>(),
+            vec![(0, 1), (0, 2), (0, 3), (1, 3), (2, 3)]
+        );
+
+        for (end, window) in windows.iter().enumerate() {
+            for (channel, mean) in window.means().iter().enumerate() {
+                let expected = ChannelValue {
+                    value: reference_mean(&data.scaled_data, 2, channel, end, 3),
+                    ..mean.clone()
+                };
+                crate::assert_data_approx_eq!(mean, expected, 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_windows_drop_edges_test() {
+        let data = data();
+        let windows: Vec<_> = data.windows(3, WindowEdges::Drop).collect();
+        assert_eq!(windows.len(), 3);
+        assert_eq!(windows[0].start_frame(), 0);
+        assert_eq!(windows[2].start_frame(), 2);
+
+        assert_eq!(data.windows(6, WindowEdges::Drop).len(), 0);
+    }
+
+    #[test]
+    fn test_windows_min_max_test() {
+        let data = data();
+        let window = data.windows(3, WindowEdges::Drop).last().unwrap();
+        assert_eq!(window.min()[0].value, Value::Valid(3.));
+        assert_eq!(window.max()[0].value, Value::Valid(8.));
+        assert_eq!(window.min()[1].value, Value::Valid(4.));
+        assert_eq!(window.max()[1].value, Value::Valid(8.));
+    }
+
+    #[test]
+    fn test_windows_entirely_invalid_channel_test() {
+        let data = data();
+        let window = data.windows(3, WindowEdges::Drop).next().unwrap();
+        assert_eq!(window.means()[1].value, Value::OutOfRange);
+        assert_eq!(window.min()[1].value, Value::OutOfRange);
+        assert_eq!(window.max()[1].value, Value::OutOfRange);
+    }
+
+    #[test]
+    fn test_moving_average_test() {
+        let data = data();
+        let smoothed = data.moving_average(2, WindowEdges::Partial);
+        assert_eq!(smoothed.channels(), data.channels());
+        let expected = Data::new(
+            data.channels_arc(),
+            vec![],
+            vec![
+                1.,
+                SCALED_OUT_OF_RANGE,
+                1.5,
+                SCALED_OUT_OF_RANGE,
+                4.,
+                SCALED_OUT_OF_RANGE,
+                4.5,
+                4.,
+                5.5,
+                6.,
+            ],
+        );
+        crate::assert_data_approx_eq!(smoothed, expected, 1e-12);
+
+        let smoothed = data.moving_average(2, WindowEdges::Drop);
+        assert_eq!(smoothed.scaled_data.len(), 8);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_windows_zero_size_test() {
+        data().windows(0, WindowEdges::Partial);
+    }
+}
@@ -0,0 +1,22 @@
+//! The checked-in bindings match the bindings generated from `MEDAQLib.h`
+//!
+//! Run with `cargo test --features bindgen --test bindings`, which needs libclang.
+//! After updating the header copy the generated file over `src/bindings.rs`.
+
+#![cfg(feature = "bindgen")]
+
+/// Tokens of the source, formatting may differ between rustfmt versions
+fn tokens(source: &str) -> Vec<&str> {
+    source.split_whitespace().collect()
+}
+
+#[test]
+fn test_pregenerated_bindings_test() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+    let pregenerated = include_str!("../src/bindings.rs");
+    assert!(
+        tokens(generated) == tokens(pregenerated),
+        "src/bindings.rs differs from the bindings generated into {}",
+        env!("OUT_DIR")
+    );
+}
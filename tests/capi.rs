@@ -0,0 +1,74 @@
+//! The C API of the `capi` feature, run with `cargo test --features capi --test capi`
+
+#![cfg(feature = "capi")]
+
+/// Tokens of the header, formatting may differ between cbindgen versions
+fn tokens(source: &str) -> Vec<&str> {
+    source.split_whitespace().collect()
+}
+
+#[test]
+fn test_capi_header_test() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/medaqlib.h"));
+    let checked_in = include_str!("../include/medaqlib.h");
+    assert!(
+        tokens(generated) == tokens(checked_in),
+        "include/medaqlib.h differs from the header generated into {}",
+        env!("OUT_DIR")
+    );
+}
+
+/// Build the shared library, compile `tests/capi/main.c` against it and run it
+#[cfg(unix)]
+#[test]
+fn test_capi_c_program_test() {
+    use std::{path::Path, process::Command};
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi");
+    let status = Command::new(env!("CARGO"))
+        .args([
+            "rustc",
+            "--lib",
+            "--features",
+            "capi",
+            "--crate-type",
+            "cdylib",
+        ])
+        .arg("--manifest-path")
+        .arg(root.join("Cargo.toml"))
+        .env("CARGO_TARGET_DIR", &target_dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "building the shared library failed");
+
+    let library_dir = target_dir.join("debug");
+    let program = target_dir.join("capi-test");
+    let target = env!("MEDAQLIB_TARGET");
+    let compiler = cc::Build::new()
+        .target(target)
+        .host(target)
+        .opt_level(0)
+        .cargo_metadata(false)
+        .get_compiler();
+    let status = compiler
+        .to_command()
+        .arg(root.join("tests/capi/main.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-L")
+        .arg(&library_dir)
+        .arg(format!("-Wl,-rpath,{}", library_dir.display()))
+        .args(["-lmedaqlib", "-lm", "-o"])
+        .arg(&program)
+        .status()
+        .unwrap();
+    assert!(status.success(), "compiling tests/capi/main.c failed");
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
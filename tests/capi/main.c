@@ -0,0 +1,44 @@
+/* Connects to the simulator through the C API, run by tests/capi.rs */
+
+#include <math.h>
+#include <stdio.h>
+#include <string.h>
+#include <time.h>
+
+#include "medaqlib.h"
+
+#define CHECK(condition)                                                       \
+  if (!(condition)) {                                                          \
+    fprintf(stderr, "%s:%d: %s failed: %s\n", __FILE__, __LINE__, #condition, \
+            medaq_last_error_message());                                       \
+    return 1;                                                                  \
+  }
+
+int main(void) {
+  MedaqConfig config = {"IFD2421", "Simulated", NULL};
+  MedaqSensor *sensor = medaq_connect(&config);
+  CHECK(sensor != NULL);
+  CHECK(medaq_channel_count(sensor) == 1);
+
+  double frames[16];
+  size_t read = 0;
+  for (int attempt = 0; attempt < 100 && read == 0; attempt++) {
+    struct timespec pause = {0, 10 * 1000 * 1000};
+    nanosleep(&pause, NULL);
+    read = sizeof frames / sizeof frames[0];
+    CHECK(medaq_read(sensor, frames, &read) == MEDAQ_STATUS_OK);
+  }
+  CHECK(read > 0);
+  CHECK(!isnan(frames[0]));
+  CHECK(strcmp(medaq_last_error_message(), "") == 0);
+  medaq_close(sensor);
+
+  config.interface = "USB";
+  CHECK(medaq_connect(&config) == NULL);
+  CHECK(strcmp(medaq_last_error_message(), "unknown interface \"USB\"") == 0);
+  CHECK(medaq_read(NULL, frames, &read) == MEDAQ_STATUS_INVALID_ARGUMENT);
+  medaq_close(NULL);
+
+  printf("read %zu values\n", read);
+  return 0;
+}
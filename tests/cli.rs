@@ -0,0 +1,536 @@
+//! Tests of the bundled `medaq` binary against the simulator
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command, Output},
+};
+
+fn medaq(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_medaq"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_read_frames_test() {
+    let output = medaq(&["--interface", "simulated", "read", "--frames", "5"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("Distance"));
+    let frames: Vec<f64> = lines.take(5).map(|line| line.parse().unwrap()).collect();
+    assert_eq!(frames.len(), 5);
+}
+
+#[test]
+fn test_read_duration_test() {
+    // options of the connection are also accepted after the subcommand
+    let output = medaq(&["read", "--duration", "100ms", "--interface", "simulated"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("Distance\n"));
+}
+
+#[test]
+fn test_exit_codes_test() {
+    let output = medaq(&["--interface", "tcp", "read"]);
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+
+    let output = medaq(&["--interface", "usb", "read"]);
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+
+    // no sensor listens there, or MEDAQLib is not installed
+    let args = [
+        "--sensor",
+        "IFD2421",
+        "--interface",
+        "tcp",
+        "--ip",
+        "127.0.0.1",
+    ];
+    let output = medaq(&[&args[..], &["read", "--frames", "1"]].concat());
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("medaq: could not connect: "), "{stderr}");
+}
+
+fn temp_file(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("medaqlib-cli-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+/// Values of a CSV file written with `;` and decimal commas, after its header
+fn parse_csv(path: &Path) -> (String, Vec<f64>) {
+    let csv = fs::read_to_string(path).unwrap();
+    assert!(csv.ends_with('\n'), "last line is not finished");
+    let mut lines = csv.lines();
+    let header = lines.next().unwrap().to_string();
+    let values = lines
+        .map(|line| line.replace(',', ".").parse().unwrap())
+        .collect();
+    (header, values)
+}
+
+#[test]
+fn test_record_test() {
+    let path = temp_file("record.csv");
+    let out = path.to_str().unwrap();
+    let output = medaq(&[
+        "record",
+        "--out",
+        out,
+        "--duration",
+        "300ms",
+        "--interface",
+        "simulated",
+        "--delimiter",
+        ";",
+        "--decimal-comma",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+
+    let (header, values) = parse_csv(&path);
+    assert_eq!(header, "Distance [mm]");
+    assert!(!values.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let written = format!("{} frames written", values.len());
+    assert!(stderr.lines().any(|line| line == written), "{stderr}");
+    fs::remove_file(path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_record_interrupted_test() {
+    use std::{process::Stdio, thread, time::Duration};
+
+    let path = temp_file("interrupted.csv");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_medaq"))
+        .args(["--interface", "simulated", "record", "--out"])
+        .arg(&path)
+        .args(["--delimiter", ";", "--decimal-comma"])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(300));
+    let kill = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(kill.success());
+
+    // Ctrl-C finishes the file, the exit code tells it was interrupted
+    assert_eq!(child.wait().unwrap().code(), Some(130));
+    let (_, values) = parse_csv(&path);
+    assert!(!values.is_empty());
+    fs::remove_file(path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch_test() {
+    use std::{process::Stdio, thread, time::Duration};
+
+    let child = Command::new(env!("CARGO_BIN_EXE_medaq"))
+        .args(["--interface", "simulated", "watch", "--refresh", "50ms"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    thread::sleep(Duration::from_millis(400));
+    let kill = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(kill.success());
+
+    // not a terminal, so a plain line per refresh
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines = stdout.lines().filter(|line| line.starts_with("rate="));
+    let line = lines.next().unwrap();
+    assert!(line.contains(" | Distance [mm] latest="), "{line}");
+}
+
+#[test]
+fn test_params_dump_test() {
+    let output = medaq(&["--interface", "simulated", "params", "dump"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("IA_Scaled_Name1 = Distance\n"), "{stdout}");
+    assert!(stdout.contains("IA_Scaled_Unit1 = mm\n"), "{stdout}");
+
+    let args = ["--json", "--name", "SP_Missing"];
+    let output = medaq(&[&["--interface", "simulated", "params", "dump"][..], &args].concat());
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("{\n"), "{stdout}");
+    assert!(
+        stdout.contains("  \"IA_Scaled_Unit1\": \"mm\",\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("  \"SP_Missing\": null\n}\n"), "{stdout}");
+}
+
+#[test]
+fn test_params_set_test() {
+    let output = medaq(&[
+        "--interface",
+        "simulated",
+        "params",
+        "set",
+        "SP_Count=1",
+        "SP_Offset=0.5",
+        "SP_Name=value",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout
+            .starts_with("SP_Count = 1 (int)\nSP_Offset = 0.5 (float)\nSP_Name = value (string)\n"),
+        "{stdout}"
+    );
+
+    let output = medaq(&[
+        "--interface",
+        "simulated",
+        "params",
+        "set",
+        "--type",
+        "int",
+        "SP_Offset=0.5",
+    ]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+
+    let output = medaq(&["--interface", "simulated", "params", "set", "SP_Offset"]);
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+}
+
+#[test]
+fn test_bench_test() {
+    let args = ["--interface", "simulated", "bench", "--duration", "200ms"];
+    let output = medaq(&[&args[..], &["--json"]].concat());
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let report = stdout.lines().next().unwrap();
+    assert!(report.starts_with("{\"seconds\":"), "{report}");
+    assert!(!report.contains("\"frames\":0,"), "{report}");
+
+    let output = medaq(&args);
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("\nframes/s: "));
+}
+
+#[test]
+fn test_serve_test() {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpStream,
+        process::Stdio,
+    };
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_medaq"))
+        .args([
+            "--interface",
+            "simulated",
+            "serve",
+            "--listen",
+            "127.0.0.1:0",
+        ])
+        .args(["--decimation", "2", "--duration", "2s"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    let address = line.trim().strip_prefix("serving on ").unwrap();
+
+    let client = BufReader::new(TcpStream::connect(address).unwrap());
+    let lines: Vec<_> = client.lines().take(3).map(Result::unwrap).collect();
+    assert_eq!(lines.len(), 3);
+    for line in &lines {
+        assert!(line.starts_with("Distance="), "{line}");
+        assert!(line.split(';').all(|value| value.contains('=')), "{line}");
+    }
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(" frames read, "), "{stdout}");
+}
+
+#[cfg(feature = "mqtt")]
+#[test]
+fn test_publish_test() {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    // answers CONNECT with CONNACK and collects everything the client sends
+    let broker = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = broker.local_addr().unwrap().to_string();
+    let received = thread::spawn(move || {
+        let (mut stream, _) = broker.accept().unwrap();
+        let mut buffer = [0; 4096];
+        let read = stream.read(&mut buffer).unwrap();
+        stream.write_all(&[0x20, 0x02, 0x00, 0x00]).unwrap();
+        let mut received = buffer[..read].to_vec();
+        while let Ok(read @ 1..) = stream.read(&mut buffer) {
+            received.extend_from_slice(&buffer[..read]);
+        }
+        received
+    });
+
+    let args = ["--interface", "simulated", "publish", "--broker", &address];
+    let args = [&args[..], &["--serial", "test", "--interval", "100ms"]].concat();
+    let output = medaq(&[&args[..], &["--duration", "1s"]].concat());
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.ends_with(" dropped, 1 connections to the broker\n"),
+        "{stdout}"
+    );
+
+    let received = String::from_utf8_lossy(&received.join().unwrap()).into_owned();
+    assert!(
+        received.contains("plant/test/Distance{\"channel\":\"Distance\""),
+        "{received}"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_read_jsonl_test() {
+    let args = ["--interface", "simulated", "--format", "jsonl"];
+    let output = medaq(&[&args[..], &["read", "--frames", "5"]].concat());
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let frames: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(frames.len(), 5, "{stdout}");
+    assert!(frames.iter().all(|frame| frame["Distance"].is_number()));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_json_output_test() {
+    let args = ["--interface", "simulated", "--format", "json"];
+    let output = medaq(&[&args[..], &["params", "set", "SP_Offset=0.5"]].concat());
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "{\n  \"SP_Offset\": 0.5\n}\n"
+    );
+
+    let args = ["--interface", "simulated", "--format", "jsonl"];
+    let output = medaq(&[&args[..], &["bench", "--duration", "100ms"]].concat());
+    assert!(output.status.success(), "{output:?}");
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(report["frames"].as_u64().unwrap() > 0, "{report}");
+
+    // the error is the last line on stdout, the message is also written to stderr
+    let args = [
+        "--sensor",
+        "IFD2421",
+        "--ip",
+        "127.0.0.1",
+        "--format",
+        "json",
+    ];
+    let output = medaq(&[&args[..], &["read"]].concat());
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let error: serde_json::Value = serde_json::from_str(stdout.lines().last().unwrap()).unwrap();
+    assert_eq!(error["error"]["code"], 3);
+    assert!(error["error"]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("could not connect: "));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("medaq: could not connect: "));
+}
+
+#[test]
+fn test_profile_test() {
+    let path = temp_file("medaq.toml");
+    fs::write(
+        &path,
+        "[profiles.sim]\ninterface = \"simulated\"\n\n\
+         [profiles.cell3]\nsensor = \"IFD2421\"\ninterface = \"tcp\"\n",
+    )
+    .unwrap();
+    let config = path.to_str().unwrap();
+
+    let args = [
+        "--config",
+        config,
+        "--profile",
+        "sim",
+        "read",
+        "--frames",
+        "2",
+    ];
+    let output = medaq(&args);
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("Distance\n"));
+
+    // cell3 has no ip address
+    let output = medaq(&["--config", config, "config", "check"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "cell3: no ip address provided\nsim: ok\n"
+    );
+    let args = ["--config", config, "--ip", "10.0.3.5", "config", "check"];
+    assert!(medaq(&args).status.success());
+
+    let output = medaq(&["--config", config, "--profile", "cell4", "read"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.ends_with("has no profile cell4\n"), "{stderr}");
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_record_multi_test() {
+    let config = temp_file("multi.toml");
+    fs::write(
+        &config,
+        "[profiles.pair]\ninterface = \"simulated\"\n\n\
+         [[profiles.pair.sensors]]\nname = \"top\"\n\n\
+         [[profiles.pair.sensors]]\nname = \"bottom\"\n",
+    )
+    .unwrap();
+    let out = temp_file("multi");
+    let args = [
+        "--config",
+        config.to_str().unwrap(),
+        "--profile",
+        "pair",
+        "record-multi",
+        "--out",
+        out.to_str().unwrap(),
+        "--duration",
+        "500ms",
+    ];
+    let output = medaq(&args);
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let summary: Vec<_> = stdout.lines().collect();
+    assert_eq!(summary.len(), 2, "{stdout}");
+    assert!(summary[0].starts_with("top: "), "{stdout}");
+    assert!(summary[1].ends_with(" gaps, 0 reconnects"), "{stdout}");
+
+    for name in ["top", "bottom"] {
+        let (header, values) = parse_csv(&out.join(format!("{name}.csv")));
+        assert_eq!(header, "Distance [mm]");
+        assert!(!values.is_empty());
+    }
+    let combined = fs::read_to_string(out.join("combined.csv")).unwrap();
+    let mut lines = combined.lines();
+    assert_eq!(
+        lines.next(),
+        Some("time,top Distance [mm],bottom Distance [mm]")
+    );
+    let row: Vec<_> = lines.next().unwrap().split(',').collect();
+    assert_eq!(row.len(), 3);
+    assert!(row[0].parse::<f64>().unwrap() > 1.6e9);
+
+    let output = medaq(&["--interface", "simulated", "record-multi", "--out"]);
+    assert_eq!(output.status.code(), Some(2));
+    let args = ["--interface", "simulated", "record-multi", "--out", "x"];
+    assert_eq!(medaq(&args).status.code(), Some(1));
+    fs::remove_dir_all(out).unwrap();
+    fs::remove_file(config).unwrap();
+}
+
+#[test]
+fn test_convert_test() {
+    use medaqlib::{BinaryRecorder, Data, SCALED_OUT_OF_RANGE};
+
+    let input = temp_file("convert.bin");
+    let channels = vec!["Distance".to_string(), "Intensity".to_string()];
+    let captures: Vec<Data> = (0..3)
+        .map(|capture| {
+            let scaled = (0..100)
+                .flat_map(|frame| {
+                    let value = (capture * 100 + frame) as f64 / 7.;
+                    match frame {
+                        13 => [value, SCALED_OUT_OF_RANGE],
+                        _ => [value, value + 0.5],
+                    }
+                })
+                .collect();
+            Data::new(channels.clone(), vec![], scaled)
+        })
+        .collect();
+    let mut recorder = BinaryRecorder::new(fs::File::create(&input).unwrap());
+    for data in &captures {
+        recorder.record(data).unwrap();
+    }
+    recorder.into_inner().unwrap();
+
+    let out = temp_file("convert.csv");
+    let _ = fs::remove_file(&out);
+    let args = [
+        input.to_str().unwrap(),
+        "--to",
+        "csv",
+        "--out",
+        out.to_str().unwrap(),
+    ];
+    let output = medaq(&[&["convert"][..], &args].concat());
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.starts_with("300 frames of 3 captures written to "),
+        "{stdout}"
+    );
+    assert!(stdout.contains("\nIntensity mean="), "{stdout}");
+
+    // the values survive the round trip, out of range ones are empty
+    let csv = fs::read_to_string(&out).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("Distance,Intensity"));
+    let expected = captures.iter().flat_map(|data| data.scaled_data.iter());
+    let parsed: Vec<f64> = lines
+        .flat_map(|line| line.split(','))
+        .map(|value| match value {
+            "" => SCALED_OUT_OF_RANGE,
+            value => value.parse().unwrap(),
+        })
+        .collect();
+    assert!(parsed.iter().eq(expected));
+
+    // existing outputs are only replaced with --force
+    let output = medaq(&[&["convert"][..], &args].concat());
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("exists, replace it with --force"),
+        "{stderr}"
+    );
+    let output = medaq(&[&["convert", "--force", "--downsample", "7"][..], &args].concat());
+    assert!(output.status.success(), "{output:?}");
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .starts_with("43 frames of 3 captures"));
+
+    fs::remove_file(input).unwrap();
+    fs::remove_file(out).unwrap();
+}
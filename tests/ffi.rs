@@ -0,0 +1,17 @@
+//! The `ffi` feature builds on its own, `cargo test --no-default-features --features ffi --test ffi`
+
+#![cfg(feature = "ffi")]
+
+use medaqlib::ffi;
+
+#[test]
+fn test_ffi_reexports_test() {
+    // the safe layer and the bindings share their enums
+    let sensor_type: medaqlib::ME_SENSOR = ffi::ME_SENSOR::SENSOR_IFD2421;
+    let err_code: ffi::ERR_CODE = medaqlib::ERR_CODE::ERR_NOT_OPEN;
+    assert_eq!(sensor_type, medaqlib::ME_SENSOR::SENSOR_IFD2421);
+    assert_eq!(err_code as i32, -3);
+
+    // functions are reachable through the generated loader
+    let _load = ffi::MEDAQLib::new::<&str>;
+}
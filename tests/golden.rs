@@ -0,0 +1,118 @@
+//! Golden file tests of the text formats
+//!
+//! The expected output lives in `tests/golden/`. After a deliberate change of a
+//! format, rewrite the files with `UPDATE_GOLDEN=1 cargo test --features serde --test golden`
+//! and review the diff.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use medaqlib::{ChannelInfo, CsvOptions, Data, SCALED_OUT_OF_RANGE as OOR};
+
+/// Compare with the golden file, or rewrite it if `UPDATE_GOLDEN` is set
+fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("{}: {err}, create it with UPDATE_GOLDEN=1", path.display()));
+    assert!(
+        actual == expected,
+        "{name} differs from its golden file, rewrite it with UPDATE_GOLDEN=1 if intended\n\
+         --- expected\n{expected}\n--- actual\n{actual}"
+    );
+}
+
+/// Three frames of two channels with out of range samples in both
+fn data() -> Data {
+    Data::new(
+        vec!["Distance".to_string(), "Intensity".to_string()],
+        vec![1234, 80, -1, 75, 2250, -1],
+        vec![1.234, 80., OOR, 75.5, 2.25, OOR],
+    )
+    .with_channel_info(vec![
+        ChannelInfo::new("Distance").with_unit("mm"),
+        ChannelInfo::new("Intensity"),
+    ])
+    .with_timestamp(UNIX_EPOCH + Duration::from_millis(1_700_000_000_250))
+}
+
+fn csv(options: CsvOptions) -> String {
+    let mut csv = vec![];
+    data().write_csv(&mut csv, &options).unwrap();
+    String::from_utf8(csv).unwrap()
+}
+
+#[test]
+fn csv_comma() {
+    assert_golden("comma.csv", &csv(CsvOptions::default()));
+}
+
+#[test]
+fn csv_semicolon_decimal_comma() {
+    let options = CsvOptions {
+        delimiter: ';',
+        decimal_comma: true,
+        out_of_range: "OutOfRange".into(),
+        ..Default::default()
+    };
+    assert_golden("semicolon_decimal_comma.csv", &csv(options));
+}
+
+#[test]
+fn csv_out_of_range_variants() {
+    let variants = ["", "NaN", "-"].map(|out_of_range| {
+        csv(CsvOptions {
+            out_of_range: out_of_range.into(),
+            header: false,
+            ..Default::default()
+        })
+    });
+    assert_golden("out_of_range.csv", &variants.join("\n"));
+}
+
+#[cfg(feature = "serde")]
+mod jsonl {
+    use medaqlib::{InvalidEncoding, JsonlOptions};
+
+    use super::{assert_golden, data};
+
+    fn jsonl(options: JsonlOptions) -> String {
+        let mut jsonl = vec![];
+        data().write_jsonl(&mut jsonl, &options).unwrap();
+        String::from_utf8(jsonl).unwrap()
+    }
+
+    #[test]
+    fn jsonl_without_timestamps() {
+        assert_golden("plain.jsonl", &jsonl(JsonlOptions::default()));
+    }
+
+    #[test]
+    fn jsonl_with_timestamps() {
+        let options = JsonlOptions {
+            timestamp: true,
+            sample_rate_hz: Some(100.),
+            invalid: InvalidEncoding::Text("OutOfRange".into()),
+            ..Default::default()
+        };
+        assert_golden("timestamps.jsonl", &jsonl(options));
+    }
+}
+
+#[test]
+fn display() {
+    let data = data();
+    let mut lines = vec![data.to_string(), data.summary().to_string()];
+    lines.extend(data.get_first_scaled().iter().map(ToString::to_string));
+    lines.extend(data.channel_info().unwrap().iter().map(ToString::to_string));
+    lines.push(String::new());
+    assert_golden("display.txt", &lines.join("\n"));
+}
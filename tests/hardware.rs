@@ -0,0 +1,127 @@
+//! Tests against a real sensor, ignored unless run with
+//! `cargo test --test hardware -- --ignored --test-threads 1`
+//!
+//! The sensor is configured through the environment:
+//! - `MEDAQ_TEST_SENSOR`: sensor type, e.g. `IFD2421` or `SENSOR_ILD1320`
+//! - `MEDAQ_TEST_INTERFACE`: interface, e.g. `TCP/IP` (the default) or `IF2004_USB`
+//! - `MEDAQ_TEST_IP`: ip address for the ethernet interfaces
+//!
+//! Failures print the error text of MEDAQLib next to the error code.
+
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use medaqlib::{Data, Interface, MedaqError, Sensor, SensorBuilder, ME_SENSOR};
+
+/// Connect to the sensor of the test bench
+fn connect() -> Sensor {
+    let variable = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+    let sensor_type: ME_SENSOR = variable("MEDAQ_TEST_SENSOR")
+        .expect("set MEDAQ_TEST_SENSOR to the sensor type of the test bench")
+        .parse()
+        .unwrap_or_else(|err| panic!("MEDAQ_TEST_SENSOR: {err}"));
+    let interface: Interface = variable("MEDAQ_TEST_INTERFACE")
+        .map(|interface| interface.parse())
+        .unwrap_or(Ok(Interface::TcpIp))
+        .unwrap_or_else(|err| panic!("MEDAQ_TEST_INTERFACE: {err}"));
+
+    let mut builder = SensorBuilder::new(sensor_type).with_interface(interface);
+    if let Some(ip_address) = variable("MEDAQ_TEST_IP") {
+        builder = builder.with_ip_address(ip_address);
+    }
+    builder
+        .connect()
+        .unwrap_or_else(|err| panic!("connecting to {sensor_type:?} via {interface} failed: {err}"))
+}
+
+/// Unwrap a result, describing a failure with the error text of MEDAQLib
+trait Check<T> {
+    fn check(self, sensor: &Sensor, action: &str) -> T;
+}
+
+impl<T> Check<T> for Result<T, MedaqError> {
+    fn check(self, sensor: &Sensor, action: &str) -> T {
+        self.unwrap_or_else(|err| match sensor.last_error() {
+            Some(text) => panic!("{action} failed: {err} ({text})"),
+            None => panic!("{action} failed: {err}"),
+        })
+    }
+}
+
+/// Every capture read within `duration`
+fn read_for(sensor: &Sensor, duration: Duration) -> Vec<Data> {
+    let start = Instant::now();
+    let mut captures = vec![];
+    while start.elapsed() < duration {
+        let data = sensor
+            .read_data_blocking(Duration::from_millis(100))
+            .check(sensor, "reading data");
+        captures.extend(data);
+    }
+    captures
+}
+
+#[test]
+#[ignore = "needs a sensor, see the module documentation"]
+fn transmits_channels() {
+    let sensor = connect();
+    assert!(!sensor.parameters().is_empty(), "no transmitted channels");
+    assert_eq!(sensor.channel_info().len(), sensor.parameters().len());
+}
+
+#[test]
+#[ignore = "needs a sensor, see the module documentation"]
+fn reads_whole_frames_at_a_plausible_rate() {
+    let sensor = connect();
+    // skip the values buffered before
+    sensor.read_data().check(&sensor, "reading the backlog");
+
+    let start = Instant::now();
+    let captures = read_for(&sensor, Duration::from_secs(1));
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let channels = sensor.parameters().len();
+    for data in &captures {
+        assert_eq!(data.validate(), Ok(()));
+        assert_eq!(data.channels(), sensor.parameters());
+        assert_eq!(data.scaled_data.len(), data.frame_count() * channels);
+        assert_eq!(data.raw_data.len(), data.frame_count() * channels);
+    }
+    let frames: usize = captures.iter().map(Data::frame_count).sum();
+    let rate = frames as f64 / elapsed;
+    assert!(
+        (1. ..=1e6).contains(&rate),
+        "implausible sample rate of {rate:.1} Hz"
+    );
+}
+
+#[test]
+#[ignore = "needs a sensor, see the module documentation"]
+fn parameter_round_trip() {
+    let sensor = connect();
+    sensor
+        .set_parameter_string("SP_MedaqlibTest", "round trip")
+        .check(&sensor, "setting a parameter");
+    let value = sensor
+        .get_parameter_string("SP_MedaqlibTest")
+        .check(&sensor, "getting a parameter");
+    assert_eq!(value, "round trip");
+
+    let name = sensor
+        .get_parameter_string("IA_Scaled_Name1")
+        .check(&sensor, "getting the first channel name");
+    assert_eq!(name, sensor.parameters()[0]);
+}
+
+#[test]
+#[ignore = "needs a sensor, see the module documentation"]
+fn closes_cleanly() {
+    drop(connect());
+    // the sensor accepts a new connection once the previous one is closed
+    let sensor = connect();
+    sensor
+        .read_data()
+        .check(&sensor, "reading after reconnecting");
+}
@@ -0,0 +1,90 @@
+//! The Prometheus endpoint of the `metrics` feature, scraped while the simulator runs
+
+#![cfg(feature = "metrics")]
+
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use medaqlib::{AcquisitionConfig, Interface, PrometheusExporter, SensorBuilder, Watch, ME_SENSOR};
+
+fn watch() -> Watch {
+    SensorBuilder::new(ME_SENSOR::NO_SENSOR)
+        .with_interface(Interface::Simulated)
+        .connect()
+        .unwrap()
+        .watch_latest(AcquisitionConfig::default())
+}
+
+fn get(address: SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+/// Value of the sample starting with `series`
+fn sample(metrics: &str, series: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+#[test]
+fn test_metrics_endpoint_test() {
+    let exporter = PrometheusExporter::bind("127.0.0.1:0").unwrap();
+    assert!(exporter.register("left", watch()).is_none());
+    assert!(exporter.register("right", watch()).is_none());
+
+    let start = Instant::now();
+    let metrics = loop {
+        let response = get(exporter.local_addr(), "/metrics");
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{head}");
+        let received = |sensor| {
+            sample(
+                body,
+                &format!("medaq_frames_received_total{{sensor=\"{sensor}\"}}"),
+            )
+            .unwrap()
+        };
+        if received("left") > 0. && received("right") > 0. {
+            break body.to_string();
+        }
+        assert!(start.elapsed() < Duration::from_secs(5), "{body}");
+        thread::sleep(Duration::from_millis(20));
+    };
+    assert!(metrics.contains("# TYPE medaq_frames_received_total counter\n"));
+    let latest = "medaq_latest_value{sensor=\"left\",channel=\"Distance\",unit=\"mm\"}";
+    assert!(sample(&metrics, latest).is_some(), "{metrics}");
+    let age = sample(&metrics, "medaq_seconds_since_last_frame{sensor=\"right\"}").unwrap();
+    assert!(age < 5., "{metrics}");
+    let invalid = sample(&metrics, "medaq_invalid_sample_ratio{sensor=\"left\"}").unwrap();
+    assert!((0. ..=1.).contains(&invalid), "{metrics}");
+    assert_eq!(
+        sample(&metrics, "medaq_reconnects_total{sensor=\"left\"}"),
+        Some(0.)
+    );
+
+    // registering again counts as a reconnect and keeps the frame total
+    let replaced = exporter.register("left", watch()).unwrap();
+    let frames = replaced.stats().frames_read as f64;
+    replaced.join().close();
+    let metrics = exporter.render();
+    assert_eq!(
+        sample(&metrics, "medaq_reconnects_total{sensor=\"left\"}"),
+        Some(1.)
+    );
+    let total = sample(&metrics, "medaq_frames_received_total{sensor=\"left\"}").unwrap();
+    assert!(total >= frames, "{metrics}");
+
+    assert!(get(exporter.local_addr(), "/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    for name in ["left", "right"] {
+        exporter.unregister(name).unwrap().join().close();
+    }
+}
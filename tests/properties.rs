@@ -0,0 +1,162 @@
+//! Invariants of the transformations of [`Data`] for arbitrary captures
+
+use medaqlib::{ChannelValue, Data, Value, SCALED_OUT_OF_RANGE};
+use proptest::prelude::*;
+
+/// Out of range raw value as transmitted by the sensor
+const RAW_OUT_OF_RANGE: i32 = -1;
+
+fn channels(count: usize) -> Vec<String> {
+    (0..count).map(|channel| format!("ch{channel}")).collect()
+}
+
+/// Raw and scaled value of a sample, about every tenth is out of range
+fn sample() -> impl Strategy<Value = (i32, f64)> {
+    prop_oneof![
+        9 => (1..65535i32, 0.001..1000f64),
+        1 => Just((RAW_OUT_OF_RANGE, SCALED_OUT_OF_RANGE)),
+    ]
+}
+
+/// Capture of 1 to 7 channels and up to 199 frames with raw and scaled values
+fn capture() -> impl Strategy<Value = Data> {
+    (1..8usize, 0..200usize)
+        .prop_flat_map(|(channels, frames)| {
+            (
+                Just(channels),
+                proptest::collection::vec(sample(), channels * frames),
+            )
+        })
+        .prop_map(|(count, samples)| {
+            let (raw, scaled) = samples.into_iter().unzip();
+            Data::new(channels(count), raw, scaled)
+        })
+}
+
+/// Mean of the valid samples of every channel, summed up in one pass
+fn naive_means<T: Copy + Into<f64>>(values: &[T], channels: usize) -> Vec<Option<f64>> {
+    (0..channels)
+        .map(|channel| {
+            let valid: Vec<f64> = values
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .map(|&value| value.into())
+                .filter(|&value| value >= 0.)
+                .collect();
+            (!valid.is_empty()).then(|| valid.iter().sum::<f64>() / valid.len() as f64)
+        })
+        .collect()
+}
+
+fn assert_means(means: &[ChannelValue<'_, f64>], expected: &[Option<f64>]) {
+    assert_eq!(means.len(), expected.len());
+    for (mean, expected) in means.iter().zip(expected) {
+        match (&mean.value, expected) {
+            (Value::Valid(mean), Some(expected)) => {
+                assert!((mean - expected).abs() <= 1e-9 * expected.abs().max(1.))
+            }
+            (Value::OutOfRange, None) => (),
+            (mean, expected) => panic!("mean {mean:?} differs from {expected:?}"),
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn means_match_naive_reference(data in capture()) {
+        assert_means(&data.get_mean_scaled(), &naive_means(&data.scaled_data, data.channel_count()));
+        assert_means(&data.get_mean_raw(), &naive_means(&data.raw_data, data.channel_count()));
+    }
+
+    #[test]
+    fn first_matches_direct_indexing(data in capture()) {
+        let first = data.get_first_scaled();
+        prop_assert_eq!(first.len(), data.channel_count());
+        for (index, value) in first.iter().enumerate() {
+            prop_assert_eq!(&value.channel, &data.channels()[index]);
+            let expected = match data.scaled_data.get(index) {
+                Some(&sample) if sample >= 0. => Value::Valid(sample),
+                _ => Value::OutOfRange,
+            };
+            prop_assert_eq!(&value.value, &expected);
+        }
+        for (index, value) in data.get_first_raw().iter().enumerate() {
+            let expected = match data.raw_data.get(index) {
+                Some(&sample) if sample >= 0 => Value::Valid(sample),
+                _ => Value::OutOfRange,
+            };
+            prop_assert_eq!(&value.value, &expected);
+        }
+    }
+
+    #[test]
+    fn select_commutes_with_summary(
+        (data, names) in capture().prop_flat_map(|data| {
+            let names = data.channels().to_vec();
+            let count = 1..=names.len();
+            (Just(data), proptest::sample::subsequence(names, count).prop_shuffle())
+        })
+    ) {
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let selected = data.select(&names).unwrap().summary();
+        let summary = data.summary();
+
+        prop_assert_eq!(selected.frames, summary.frames);
+        for (channel, name) in selected.channels.iter().zip(&names) {
+            let expected = summary.channels.iter().find(|c| c.channel == *name).unwrap();
+            prop_assert_eq!(channel, expected);
+        }
+    }
+
+    #[test]
+    fn append_then_summary_equals_summary_of_concatenation(
+        (first, second) in (1..8usize, 0..100usize, 0..100usize).prop_flat_map(
+            |(channels, first, second)| {
+                let samples = |frames| proptest::collection::vec(sample(), channels * frames);
+                (samples(first), samples(second)).prop_map(move |(first, second)| {
+                    let capture = |samples: Vec<(i32, f64)>| {
+                        let (raw, scaled) = samples.into_iter().unzip();
+                        Data::new(self::channels(channels), raw, scaled)
+                    };
+                    (capture(first), capture(second))
+                })
+            }
+        )
+    ) {
+        let concatenation = Data::new(
+            first.channels().to_vec(),
+            [first.raw_data.clone(), second.raw_data.clone()].concat(),
+            [first.scaled_data.clone(), second.scaled_data.clone()].concat(),
+        );
+        let mut appended = first;
+        appended.append(&second).unwrap();
+
+        prop_assert_eq!(&appended.raw_data, &concatenation.raw_data);
+        prop_assert_eq!(&appended.scaled_data, &concatenation.scaled_data);
+        prop_assert_eq!(appended.summary(), concatenation.summary());
+    }
+}
+
+#[test]
+fn zero_frames() {
+    let data = Data::new(channels(3), vec![], vec![]);
+    assert!(data
+        .get_first_scaled()
+        .iter()
+        .all(|value| value.value == Value::OutOfRange));
+    assert!(data
+        .get_mean_raw()
+        .iter()
+        .all(|value| value.value == Value::OutOfRange));
+    assert_eq!(data.summary().frames, 0);
+}
+
+#[test]
+fn zero_channels() {
+    let data = Data::new(channels(0), vec![], vec![]);
+    assert!(data.get_first_raw().is_empty());
+    assert!(data.get_mean_scaled().is_empty());
+    assert!(data.summary().channels.is_empty());
+    assert_eq!(data.frame_count(), 0);
+}
@@ -0,0 +1,58 @@
+//! The WebSocket stream of the `ws` feature, read by a tungstenite client while the
+//! simulator runs
+
+#![cfg(feature = "ws")]
+
+use std::time::{Duration, Instant};
+
+use medaqlib::{
+    AcquisitionConfig, Interface, SensorBuilder, WebsocketConfig, WebsocketStreamer, ME_SENSOR,
+};
+use tokio_tungstenite::tungstenite::{connect, Message};
+
+#[test]
+fn test_websocket_stream_test() {
+    let acquisition = SensorBuilder::new(ME_SENSOR::NO_SENSOR)
+        .with_interface(Interface::Simulated)
+        .connect()
+        .unwrap()
+        .start_acquisition(AcquisitionConfig::default());
+    let streamer =
+        WebsocketStreamer::start("127.0.0.1:0", acquisition, WebsocketConfig::default()).unwrap();
+    let (mut socket, _) = connect(format!("ws://{}", streamer.local_addr())).unwrap();
+
+    let mut next = || -> serde_json::Value {
+        loop {
+            if let Message::Text(text) = socket.read().unwrap() {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    };
+    let metadata = next();
+    assert_eq!(metadata["metadata"]["channels"][0]["name"], "Distance");
+    assert!(next()["Distance"].is_number());
+
+    // at most one frame per 100 ms once negotiated
+    socket.send(Message::text("{\"max_rate_hz\": 10}")).unwrap();
+    let start = Instant::now();
+    let mut frames = 0;
+    while start.elapsed() < Duration::from_millis(600) {
+        if let Message::Text(_) = socket.read().unwrap() {
+            frames += 1;
+        }
+    }
+    // frames queued before the request arrived are delivered as well
+    assert!(frames <= 256 + 8, "{frames} frames");
+    let start = Instant::now();
+    let mut limited = 0;
+    while start.elapsed() < Duration::from_millis(500) {
+        if let Message::Text(_) = socket.read().unwrap() {
+            limited += 1;
+        }
+    }
+    assert!((1..=7).contains(&limited), "{limited} frames in 500 ms");
+    assert_eq!(streamer.clients(), 1);
+
+    socket.close(None).unwrap();
+    streamer.stop().join().close();
+}